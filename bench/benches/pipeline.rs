@@ -0,0 +1,140 @@
+//! Benchmarks for each stage of the pipeline, from schema parsing through to
+//! mesh export, over generated inputs.
+//!
+//! Run with `cargo bench -p foxtrot-bench`, or pass a filter (e.g.
+//! `cargo bench -p foxtrot-bench -- triangulate`) to run a subset.
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use nalgebra_glm::DVec2;
+use nurbs::AbstractSurface;
+use step::{lexer::Lexer, step_file::StepFile};
+use triangulate::{
+    export::{write_glb, write_obj, write_ply, write_stl},
+    mesh::Mesh,
+    triangulate::triangulate,
+};
+
+use foxtrot_bench::{bspline_surface, express_schema, step_assembly};
+
+fn express(c: &mut Criterion) {
+    let mut group = c.benchmark_group("express");
+    for n in [100, 1000] {
+        let text = express_schema(n);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(format!("parse/{}", n), |b| {
+            b.iter(|| express::parse::parse(black_box(&text)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    for n in [10, 100] {
+        let text = step_assembly(n);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(format!("strip_flatten/{}", n), |b| {
+            b.iter(|| StepFile::strip_flatten(black_box(text.as_bytes())))
+        });
+
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        group.throughput(Throughput::Bytes(flat.len() as u64));
+        group.bench_function(format!("parse/{}", n), |b| {
+            b.iter(|| StepFile::parse(black_box(&flat)).unwrap())
+        });
+
+        // Tokenizing alone, for comparison with the full parse
+        let flat = std::str::from_utf8(&flat).unwrap();
+        group.bench_function(format!("lex/{}", n), |b| {
+            b.iter(|| Lexer::new(black_box(flat)).filter(|t| t.is_ok()).count())
+        });
+    }
+    group.finish();
+}
+
+fn nurbs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nurbs");
+    let surf = bspline_surface(16);
+    let n = 100;
+    let uv = |i: usize| i as f64 / (n - 1) as f64;
+    group.throughput(Throughput::Elements((n * n) as u64));
+
+    group.bench_function("grid/point", |b| {
+        b.iter(|| {
+            let mut sum = 0.0;
+            for i in 0..n {
+                for j in 0..n {
+                    sum += surf.point(DVec2::new(uv(i), uv(j))).z;
+                }
+            }
+            sum
+        })
+    });
+
+    // Triangulation evaluates surfaces on grids, re-using the basis
+    // functions along each row and column
+    group.bench_function("grid/basis", |b| {
+        let basis = |k: &nurbs::KnotVector| {
+            (0..n)
+                .map(|i| {
+                    let span = k.find_span(uv(i));
+                    (span, k.basis_functions_for_span(span, uv(i)))
+                })
+                .collect::<Vec<_>>()
+        };
+        b.iter(|| {
+            let (us, vs) = (basis(&surf.u_knots), basis(&surf.v_knots));
+            let mut sum = 0.0;
+            for (us, nu) in &us {
+                for (vs, nv) in &vs {
+                    sum += surf.point_from_basis(*us, nu, *vs, nv).z;
+                }
+            }
+            sum
+        })
+    });
+    group.finish();
+}
+
+fn mesh(n: usize) -> Mesh {
+    let flat = StepFile::strip_flatten(step_assembly(n).as_bytes());
+    let step = StepFile::parse(&flat).unwrap();
+    triangulate(&step).0
+}
+
+fn triangulation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("triangulate");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(10));
+    let flat = StepFile::strip_flatten(step_assembly(50).as_bytes());
+    let step = StepFile::parse(&flat).unwrap();
+    group.bench_function("assembly/50", |b| b.iter(|| triangulate(black_box(&step))));
+    group.finish();
+}
+
+fn export(c: &mut Criterion) {
+    let mut group = c.benchmark_group("export");
+    let mesh = mesh(50);
+    group.throughput(Throughput::Elements(mesh.triangles.len() as u64));
+    type Writer = fn(&Mesh, &mut Vec<u8>) -> std::io::Result<()>;
+    let writers: [(&str, Writer); 4] = [
+        ("stl", write_stl),
+        ("obj", write_obj),
+        ("ply", write_ply),
+        ("glb", write_glb),
+    ];
+    for (name, write) in writers {
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                Vec::new,
+                |out| write(black_box(&mesh), out).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, express, step, nurbs, triangulation, export);
+criterion_main!(benches);
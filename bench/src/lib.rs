@@ -0,0 +1,106 @@
+//! Generated inputs for benchmarks, sized by a single parameter so that the
+//! repository doesn't need to carry large model files
+use nalgebra_glm::DVec3;
+use nurbs::{BsplineSurface, KnotVector};
+use step::fixture;
+
+/// Returns an EXPRESS schema with `n` entities, each a subtype of the one
+/// before it, along with a handful of defined, enumerated, and select types
+/// per entity.  The text is lowercase with comments stripped, ready for
+/// [`express::parse::parse`].
+pub fn express_schema(n: usize) -> String {
+    let mut out = String::from("schema bench;\n");
+    out += "type label = string;\nend_type;\n";
+    out += "type length_measure = real;\nend_type;\n";
+    for i in 0..n {
+        out += &format!("type kind_{i} = enumeration of (first, second, third);\nend_type;\n");
+        out += &format!(
+            "type ref_{i} = select (item_{i}, item_{});\nend_type;\n",
+            i.saturating_sub(1)
+        );
+        out += &format!("entity item_{i}");
+        if i > 0 {
+            out += &format!("\nsubtype of (item_{})", i - 1);
+        }
+        out += &format!(
+            ";\n  name_{i} : label;\n  length_{i} : length_measure;\n  \
+             kind_{i} : kind_{i};\n  refs_{i} : list [1:?] of ref_{i};\n  \
+             note_{i} : optional label;\nend_entity;\n"
+        );
+    }
+    out += "end_schema;\n";
+    out
+}
+
+/// Returns a STEP file for an assembly of `n` filleted blocks on a grid.
+/// Each block has 10 faces and is about 13 KB of text.
+pub fn step_assembly(n: usize) -> String {
+    let side = (n as f64).sqrt().ceil().max(1.0) as usize;
+    let parts = (0..n)
+        .map(|i| {
+            let (x, y) = ((i % side) as f64, (i / side) as f64);
+            fixture::filleted_block(10.0, 2.0).translate([x * 12.0, y * 12.0, 0.0])
+        })
+        .collect();
+    fixture::assembly(parts).to_step()
+}
+
+/// Returns a cubic b-spline surface with an `n` by `n` grid of control
+/// points, shaped like a gently rolling hill
+pub fn bspline_surface(n: usize) -> BsplineSurface {
+    assert!(n >= 4, "A cubic surface needs at least 4 control points");
+    let knots = || {
+        let inner = n - 3;
+        let k: Vec<f64> = (0..=inner).map(|i| i as f64 / inner as f64).collect();
+        let mut m = vec![1; k.len()];
+        m[0] = 4;
+        m[inner] = 4;
+        KnotVector::from_multiplicities(3, &k, &m).unwrap()
+    };
+    let pts = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    let (x, y) = (i as f64 / n as f64, j as f64 / n as f64);
+                    DVec3::new(x, y, (x * 7.0).sin() * (y * 5.0).cos() * 0.1)
+                })
+                .collect()
+        })
+        .collect();
+    BsplineSurface::new(true, true, knots(), knots(), pts).unwrap()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::DVec2;
+    use nurbs::AbstractSurface;
+    use step::step_file::StepFile;
+
+    #[test]
+    fn test_express_schema() {
+        let s = express_schema(20);
+        express::parse::parse(&s).unwrap();
+    }
+
+    #[test]
+    fn test_step_assembly() {
+        let s = step_assembly(5);
+        let flat = StepFile::strip_flatten(s.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let (mesh, stats) = triangulate::triangulate::triangulate(&step);
+        assert_eq!(mesh.parts.len(), 5);
+        assert_eq!(stats.num_faces, 50);
+    }
+
+    #[test]
+    fn test_bspline_surface() {
+        let s = bspline_surface(8);
+        let p = s.point(DVec2::new(0.0, 0.0));
+        assert_eq!(p, DVec3::zeros());
+        let p = s.point(DVec2::new(1.0, 1.0));
+        assert!((p.x - 7.0 / 8.0).abs() < 1e-12);
+    }
+}
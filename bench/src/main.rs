@@ -0,0 +1,178 @@
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use clap::{App, Arg, ArgMatches};
+
+use step::step_file::StepFile;
+use triangulate::{
+    export::{write_glb, write_obj, write_ply, write_stl},
+    mesh::Mesh,
+    triangulate::triangulate_with_tolerance,
+};
+
+/// A single row of the summary table
+struct Row {
+    stage: &'static str,
+    time: Duration,
+    /// Amount of work done, and its units (e.g. bytes or triangles)
+    work: Option<(usize, &'static str)>,
+}
+
+/// Runs a function several times, returning the fastest time and the last
+/// result
+fn best_of<T, F: FnMut() -> T>(runs: usize, mut f: F) -> (Duration, T) {
+    let start = Instant::now();
+    let mut out = f();
+    let mut best = start.elapsed();
+    for _ in 1..runs {
+        let start = Instant::now();
+        out = f();
+        best = best.min(start.elapsed());
+    }
+    (best, out)
+}
+
+fn print_table(rows: &[Row]) {
+    println!("{:<16}{:>12}{:>20}", "stage", "time (ms)", "throughput");
+    for r in rows {
+        let rate = match r.work {
+            Some((n, "bytes")) => format!("{:.1} MB/s", n as f64 / 1e6 / r.time.as_secs_f64()),
+            Some((n, units)) => format!("{:.0} {}/s", n as f64 / r.time.as_secs_f64(), units),
+            None => String::new(),
+        };
+        println!(
+            "{:<16}{:>12.2}{:>20}",
+            r.stage,
+            r.time.as_secs_f64() * 1e3,
+            rate
+        );
+    }
+    let total: Duration = rows.iter().map(|r| r.time).sum();
+    println!("{:<16}{:>12.2}", "total", total.as_secs_f64() * 1e3);
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let runs: usize = matches.value_of_t("runs")?;
+    let runs = runs.max(1);
+    let tolerance = matches
+        .value_of("tolerance")
+        .map(str::parse::<f64>)
+        .transpose()?;
+    let mut rows = vec![];
+
+    let data = match matches.value_of("input") {
+        Some(path) => {
+            let (time, data) = best_of(runs, || std::fs::read(path));
+            let data = data.map_err(|e| format!("Could not read {}: {}", path, e))?;
+            rows.push(Row {
+                stage: "read",
+                time,
+                work: Some((data.len(), "bytes")),
+            });
+            data
+        }
+        None => {
+            let parts: usize = matches.value_of_t("parts")?;
+            foxtrot_bench::step_assembly(parts).into_bytes()
+        }
+    };
+    println!(
+        "Input is {:.2} MB, best of {} runs",
+        data.len() as f64 / 1e6,
+        runs
+    );
+
+    let (time, flat) = best_of(runs, || StepFile::strip_flatten(&data));
+    rows.push(Row {
+        stage: "strip_flatten",
+        time,
+        work: Some((data.len(), "bytes")),
+    });
+
+    let (time, step) = best_of(runs, || StepFile::parse(&flat));
+    let step = step?;
+    rows.push(Row {
+        stage: "parse",
+        time,
+        work: Some((step.0.len(), "entities")),
+    });
+
+    let (time, (mesh, stats)) = best_of(runs, || {
+        triangulate_with_tolerance(&step, tolerance, &|_, _| ())
+    });
+    rows.push(Row {
+        stage: "triangulate",
+        time,
+        work: Some((stats.num_faces, "faces")),
+    });
+    if mesh.triangles.is_empty() {
+        return Err("The file contains no triangulated geometry".into());
+    }
+
+    type Writer = fn(&Mesh, &mut Vec<u8>) -> std::io::Result<()>;
+    let writers: [(&str, Writer); 4] = [
+        ("export_stl", write_stl),
+        ("export_obj", write_obj),
+        ("export_ply", write_ply),
+        ("export_glb", write_glb),
+    ];
+    for (stage, write) in writers {
+        let (time, r) = best_of(runs, || {
+            let mut out = vec![];
+            write(&mesh, &mut out).map(|_| out.len())
+        });
+        rows.push(Row {
+            stage,
+            time,
+            work: Some((r?, "bytes")),
+        });
+    }
+
+    println!(
+        "{} entities, {} faces, {} triangles\n",
+        step.0.len(),
+        stats.num_faces,
+        mesh.triangles.len()
+    );
+    print_table(&rows);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let matches = App::new("foxtrot-bench")
+        .author("Matt Keeter <matt@formlabs.com>")
+        .about("Times each stage of loading and exporting a STEP file")
+        .arg(
+            Arg::with_name("input")
+                .help("STEP file to load (by default, a generated assembly is used)"),
+        )
+        .arg(
+            Arg::with_name("parts")
+                .long("parts")
+                .takes_value(true)
+                .default_value("100")
+                .help("Number of parts in the generated assembly"),
+        )
+        .arg(
+            Arg::with_name("runs")
+                .long("runs")
+                .takes_value(true)
+                .default_value("3")
+                .help("Number of times to run each stage, keeping the fastest"),
+        )
+        .arg(
+            Arg::with_name("tolerance")
+                .long("tolerance")
+                .takes_value(true)
+                .help("Largest distance between curved edges and their triangulation"),
+        )
+        .get_matches();
+
+    match run(&matches) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Runs the summary harness on an external file
+use std::process::Command;
+
+#[test]
+fn test_external_file() {
+    let path = std::env::temp_dir().join(format!("foxtrot-bench-{}.step", std::process::id()));
+    std::fs::write(&path, foxtrot_bench::step_assembly(4)).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_foxtrot-bench"))
+        .arg(&path)
+        .args(["--runs", "1"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(out.status.success(), "{:?}", out);
+
+    let text = String::from_utf8(out.stdout).unwrap();
+    assert!(text.contains("40 faces"), "{}", text);
+    let stages: Vec<_> = text
+        .lines()
+        .skip_while(|l| !l.starts_with("stage"))
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().next())
+        .collect();
+    assert_eq!(
+        stages,
+        [
+            "read",
+            "strip_flatten",
+            "parse",
+            "triangulate",
+            "export_stl",
+            "export_obj",
+            "export_ply",
+            "export_glb",
+            "total"
+        ]
+    );
+}
+
+#[test]
+fn test_missing_file() {
+    let out = Command::new(env!("CARGO_BIN_EXE_foxtrot-bench"))
+        .arg("does-not-exist.step")
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    let err = String::from_utf8(out.stderr).unwrap();
+    assert!(
+        err.contains("Could not read does-not-exist.step"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::Point;
+
+/// Merges points which are within `rel_eps` (relative to the size of the
+/// points' bounding box) of each other, by snapping each one onto the
+/// earliest point in its cluster.  Merged points are exact duplicates
+/// afterwards, which the triangulation ignores, so the indexes of the
+/// points don't change.  Returns the number of points which were moved.
+pub fn merge_close_points(pts: &mut [Point], rel_eps: f64) -> usize {
+    let (mut lo, mut hi) = (
+        (f64::INFINITY, f64::INFINITY),
+        (-f64::INFINITY, -f64::INFINITY),
+    );
+    for p in pts.iter().filter(|p| p.0.is_finite() && p.1.is_finite()) {
+        lo = (lo.0.min(p.0), lo.1.min(p.1));
+        hi = (hi.0.max(p.0), hi.1.max(p.1));
+    }
+    let eps = rel_eps * (hi.0 - lo.0).max(hi.1 - lo.1);
+    if !eps.is_finite() || eps <= 0.0 {
+        return 0;
+    }
+
+    // Sweep across the points in X order, comparing each point against the
+    // cluster representatives which are within `eps` in X
+    let mut order: Vec<usize> = (0..pts.len())
+        .filter(|i| pts[*i].0.is_finite() && pts[*i].1.is_finite())
+        .collect();
+    order.sort_by(|a, b| pts[*a].0.partial_cmp(&pts[*b].0).unwrap().then(a.cmp(b)));
+    let mut rep: Vec<usize> = (0..pts.len()).collect();
+    let mut window: Vec<usize> = vec![];
+    for &i in &order {
+        let p = pts[i];
+        window.retain(|j| p.0 - pts[*j].0 <= eps);
+        let close = window.iter().copied().filter(|j| {
+            let q = pts[*j];
+            let (dx, dy) = (p.0 - q.0, p.1 - q.1);
+            dx * dx + dy * dy <= eps * eps
+        });
+        match close.min() {
+            Some(j) => rep[i] = j,
+            None => window.push(i),
+        }
+    }
+
+    // Clusters are represented by their first point in X order; move the
+    // representative to the earliest index, so that (for example) contour
+    // points win over Steiner points added after them
+    let mut earliest: HashMap<usize, usize> = HashMap::new();
+    for (i, r) in rep.iter().enumerate() {
+        let e = earliest.entry(*r).or_insert(i);
+        *e = (*e).min(i);
+    }
+    let mut moved = 0;
+    for i in 0..pts.len() {
+        let target = pts[earliest[&rep[i]]];
+        if pts[i] != target {
+            pts[i] = target;
+            moved += 1;
+        }
+    }
+    moved
+}
+
+/// Drops edges which have zero length, i.e. both ends are at the same
+/// position (perhaps after [`merge_close_points`]).  Returns the number of
+/// edges which were dropped.
+///
+/// Pairs of opposite edges are kept, since they're meaningful: a seam which
+/// is walked in both directions cuts a closed surface open.
+pub fn clean_edges(pts: &[Point], edges: &mut Vec<(usize, usize)>) -> usize {
+    let before = edges.len();
+    edges.retain(|&(a, b)| pts[a] != pts[b]);
+    before - edges.len()
+}
+
+/// Returns the area enclosed by a set of closed, consistently-oriented
+/// contours (e.g. counterclockwise outer boundaries and clockwise holes),
+/// given as edges into the points list.  Orientation can be flipped as a
+/// whole, since this returns the absolute value.
+pub fn contour_area(pts: &[Point], edges: &[(usize, usize)]) -> f64 {
+    edges
+        .iter()
+        .map(|&(a, b)| pts[a].0 * pts[b].1 - pts[b].0 * pts[a].1)
+        .sum::<f64>()
+        .abs()
+        / 2.0
+}
+
+/// Returns the total unsigned area of a set of triangles
+pub fn triangle_area(pts: &[Point], triangles: &[(usize, usize, usize)]) -> f64 {
+    triangles
+        .iter()
+        .map(|&(a, b, c)| {
+            let (a, b, c) = (pts[a], pts[b], pts[c]);
+            ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)).abs() / 2.0
+        })
+        .sum()
+}
+
+/// Checks that a triangulation covers the area within its contours exactly
+/// once, by comparing the triangles' summed area against the contours' area
+/// (see [`contour_area`]).  Returns the relative difference, which is zero
+/// (to within rounding) for a valid triangulation.
+pub fn coverage_error(
+    pts: &[Point],
+    edges: &[(usize, usize)],
+    triangles: &[(usize, usize, usize)],
+) -> f64 {
+    let expected = contour_area(pts, edges);
+    let actual = triangle_area(pts, triangles);
+    if expected == 0.0 {
+        actual
+    } else {
+        (actual - expected).abs() / expected
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Triangulation;
+    use rand::{Rng, SeedableRng};
+
+    /// Builds a closed contour from a list of points, appending to `pts`
+    /// and `edges`
+    fn contour(pts: &mut Vec<Point>, edges: &mut Vec<(usize, usize)>, loop_pts: &[Point]) {
+        let start = pts.len();
+        for (i, p) in loop_pts.iter().enumerate() {
+            pts.push(*p);
+            edges.push((start + i, start + (i + 1) % loop_pts.len()));
+        }
+    }
+
+    /// Triangulates after cleaning up the input, returning triangles
+    fn cleaned(pts: &mut [Point], edges: &mut Vec<(usize, usize)>) -> Vec<(usize, usize, usize)> {
+        merge_close_points(pts, 1e-9);
+        clean_edges(pts, edges);
+        Triangulation::build_with_edges(pts, &*edges)
+            .expect("Could not build triangulation")
+            .triangles()
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_close_points() {
+        let mut pts = vec![
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 1e-12),
+            (0.0, 1.0),
+            (1e-13, 0.0),
+        ];
+        assert_eq!(merge_close_points(&mut pts, 1e-9), 2);
+        assert_eq!(pts[2], (1.0, 0.0));
+        assert_eq!(pts[4], (0.0, 0.0));
+        assert_eq!(pts[3], (0.0, 1.0));
+
+        // Points are merged onto the earliest point in their cluster, even
+        // if a later point comes first in X
+        let mut pts = vec![(0.0, 0.0), (1.0, 1.0), (1.0 - 1e-12, 1.0)];
+        assert_eq!(merge_close_points(&mut pts, 1e-9), 1);
+        assert_eq!(pts[2], (1.0, 1.0));
+
+        // Nothing is merged in degenerate inputs
+        assert_eq!(merge_close_points(&mut [(1.0, 1.0), (1.0, 1.0)], 1e-9), 0);
+        assert_eq!(merge_close_points(&mut [], 1e-9), 0);
+    }
+
+    #[test]
+    fn test_clean_edges() {
+        let pts = [(0.0, 0.0), (1.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+        let mut edges = vec![(0, 1), (1, 2), (2, 3), (3, 0), (0, 3), (1, 0)];
+        assert_eq!(clean_edges(&pts, &mut edges), 1);
+        assert_eq!(edges, [(0, 1), (2, 3), (3, 0), (0, 3), (1, 0)]);
+    }
+
+    #[test]
+    fn test_tiny_edge() {
+        // A square with a tiny edge and a nearly-collinear point along its
+        // bottom, distilled from a trimmed planar face
+        let mut pts = vec![];
+        let mut edges = vec![];
+        contour(
+            &mut pts,
+            &mut edges,
+            &[
+                (0.0, 0.0),
+                (0.5, 1e-17),
+                (1.0, 0.0),
+                (1.0 + 1e-14, 1e-14),
+                (1.0, 1.0),
+                (0.0, 1.0),
+            ],
+        );
+        let tris = cleaned(&mut pts, &mut edges);
+        assert_eq!(edges.len(), 5);
+        assert!(coverage_error(&pts, &edges, &tris) < 1e-12);
+        assert!((triangle_area(&pts, &tris) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_doubled_hole_point() {
+        // A hole whose seam point appears twice, offset by rounding error,
+        // as happens when a closed curve is sampled from both ends
+        let mut pts = vec![];
+        let mut edges = vec![];
+        contour(
+            &mut pts,
+            &mut edges,
+            &[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)],
+        );
+        contour(
+            &mut pts,
+            &mut edges,
+            &[
+                (1.0, 1.0),
+                (1.0, 3.0),
+                (3.0, 3.0),
+                (3.0, 1.0),
+                (1.0 + 4e-16, 1.0 - 4e-16),
+            ],
+        );
+        let tris = cleaned(&mut pts, &mut edges);
+        assert!(coverage_error(&pts, &edges, &tris) < 1e-12);
+        assert!((triangle_area(&pts, &tris) - 12.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_coverage_random() {
+        // Random star-shaped polygons, each with a few non-overlapping
+        // star-shaped holes, must be covered exactly once
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(54321);
+        for _ in 0..200 {
+            let mut pts = vec![];
+            let mut edges = vec![];
+            let star = |rng: &mut rand_chacha::ChaCha8Rng, n, c: Point, r: f64, ccw: bool| {
+                let mut out: Vec<Point> = (0..n)
+                    .map(|i| {
+                        let a =
+                            (i as f64 + rng.gen_range(0.0..0.5)) / n as f64 * std::f64::consts::TAU;
+                        let r = r * rng.gen_range(0.5..1.0);
+                        (c.0 + r * a.cos(), c.1 + r * a.sin())
+                    })
+                    .collect();
+                if !ccw {
+                    out.reverse();
+                }
+                out
+            };
+            // With at least 16 points, the outer polygon's edges stay more
+            // than 4.7 from its center
+            let n = rng.gen_range(16..40);
+            let outer = star(&mut rng, n, (0.0, 0.0), 10.0, true);
+            contour(&mut pts, &mut edges, &outer);
+
+            // Holes sit on a ring well inside the outer polygon, spaced out
+            // so that they can't touch
+            let holes = rng.gen_range(0..5);
+            for h in 0..holes {
+                let a = h as f64 / 5.0 * std::f64::consts::TAU;
+                let n = rng.gen_range(3..20);
+                let hole = star(&mut rng, n, (2.5 * a.cos(), 2.5 * a.sin()), 1.0, false);
+                contour(&mut pts, &mut edges, &hole);
+            }
+
+            let tris = cleaned(&mut pts, &mut edges);
+            let err = coverage_error(&pts, &edges, &tris);
+            assert!(err < 1e-9, "coverage error {} with {} holes", err, holes);
+        }
+    }
+}
@@ -39,12 +39,14 @@ points in a single pass, you should enable the `long-indexes` feature.
 */
 
 #![warn(missing_docs)]
+pub(crate) mod clean;
 pub(crate) mod contour;
 pub(crate) mod half;
 pub(crate) mod hull;
 pub(crate) mod indexes;
 pub(crate) mod predicates;
 pub(crate) mod triangulate;
+pub use clean::{clean_edges, contour_area, coverage_error, merge_close_points, triangle_area};
 pub use triangulate::Triangulation;
 
 ////////////////////////////////////////////////////////////////////////////////
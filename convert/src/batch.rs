@@ -0,0 +1,244 @@
+//! Batch conversion, which checks every STEP file in a directory and writes
+//! a single report, rather than converting one file to a mesh
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde_json::json;
+use triangulate::{
+    coverage,
+    load::{load_mesh, TriangulateOptions},
+};
+
+/// Result of checking a single file
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// Whole-file time, including the coverage pass
+    pub seconds: f64,
+    /// Error which stopped the file from loading, if any
+    pub error: Option<String>,
+    pub entities: usize,
+    pub failed_entities: usize,
+    pub faces: usize,
+    pub face_errors: usize,
+    pub triangles: usize,
+    pub open_edges: usize,
+    pub watertight: bool,
+    /// Geometry entity types which the triangulator doesn't handle
+    pub unsupported: Vec<String>,
+    /// Time spent in each stage of loading, in seconds
+    pub timings: Vec<(&'static str, f64)>,
+}
+
+impl FileReport {
+    fn failed(path: &Path, seconds: f64, error: String) -> Self {
+        Self {
+            path: path.to_owned(),
+            seconds,
+            error: Some(error),
+            entities: 0,
+            failed_entities: 0,
+            faces: 0,
+            face_errors: 0,
+            triangles: 0,
+            open_edges: 0,
+            watertight: false,
+            unsupported: vec![],
+            timings: vec![],
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let timings: serde_json::Map<_, _> = self
+            .timings
+            .iter()
+            .map(|(stage, t)| (stage.to_string(), json!(t)))
+            .collect();
+        json!({
+            "path": self.path,
+            "ok": self.is_ok(),
+            "error": self.error,
+            "seconds": self.seconds,
+            "entities": self.entities,
+            "failed_entities": self.failed_entities,
+            "faces": self.faces,
+            "face_errors": self.face_errors,
+            "triangles": self.triangles,
+            "open_edges": self.open_edges,
+            "watertight": self.watertight,
+            "unsupported": self.unsupported,
+            "timings": timings,
+        })
+    }
+}
+
+/// Returns every `.step` or `.stp` file under `dir`, in sorted order
+pub fn find_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut out = vec![];
+    let mut todo = vec![dir.to_owned()];
+    while let Some(d) = todo.pop() {
+        for entry in std::fs::read_dir(&d)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                todo.push(path);
+            } else if path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case("step") || e.eq_ignore_ascii_case("stp"))
+            {
+                out.push(path);
+            }
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Loads, triangulates, and checks a single file.  Errors (and panics,
+/// which `load_mesh` catches) are recorded in the report.
+fn check(path: &Path, tolerance: Option<f64>) -> FileReport {
+    let start = Instant::now();
+    let opts = TriangulateOptions {
+        tolerance,
+        ..Default::default()
+    };
+    let (scene, report) = match load_mesh(path, &opts) {
+        Ok(r) => r,
+        Err(e) => {
+            return FileReport::failed(path, start.elapsed().as_secs_f64(), e.to_string());
+        }
+    };
+    let mesh = scene.mesh;
+    let open_edges = mesh.open_edges();
+
+    // The file has already parsed once, so the coverage pass shouldn't
+    // fail, but it mustn't take the batch down if it does
+    let unsupported = std::panic::catch_unwind(|| coverage::report_file(path))
+        .ok()
+        .and_then(Result::ok)
+        .map(|c| {
+            c.unsupported_geometry()
+                .iter()
+                .map(|e| e.keyword.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let stats = &report.stats;
+    FileReport {
+        path: path.to_owned(),
+        seconds: start.elapsed().as_secs_f64(),
+        error: None,
+        entities: report.parse.entities,
+        failed_entities: report.parse.failed,
+        faces: stats.num_faces,
+        face_errors: stats.num_errors + stats.num_panics,
+        triangles: mesh.triangles.len(),
+        open_edges,
+        watertight: open_edges == 0 && stats.num_open_shells == 0,
+        unsupported,
+        timings: report
+            .timings
+            .iter()
+            .map(|t| (t.stage, t.duration.as_secs_f64()))
+            .collect(),
+    }
+}
+
+/// Checks every file on a pool of `jobs` threads, calling `done` as each
+/// one finishes.  Reports are returned in the same order as `files`.
+pub fn run(
+    files: &[PathBuf],
+    tolerance: Option<f64>,
+    jobs: usize,
+    done: &(dyn Fn(&FileReport) + Sync),
+) -> Vec<FileReport> {
+    let next = AtomicUsize::new(0);
+    let out: Mutex<Vec<Option<FileReport>>> = Mutex::new(files.iter().map(|_| None).collect());
+    std::thread::scope(|s| {
+        for _ in 0..jobs.clamp(1, files.len().max(1)) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = files.get(i) else {
+                    break;
+                };
+                let r = check(path, tolerance);
+                done(&r);
+                out.lock().unwrap()[i] = Some(r);
+            });
+        }
+    });
+    out.into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("Every file was checked"))
+        .collect()
+}
+
+/// Builds the JSON report, with totals and one entry per file
+pub fn to_json(reports: &[FileReport]) -> serde_json::Value {
+    let failed = reports.iter().filter(|r| !r.is_ok()).count();
+    json!({
+        "files": reports.len(),
+        "failed": failed,
+        "watertight": reports.iter().filter(|r| r.watertight).count(),
+        "results": reports.iter().map(FileReport::to_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Quotes a CSV field if it needs it
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// Builds a CSV report, with one row per file.  Unsupported types are
+/// separated by semicolons within their column.
+pub fn to_csv(reports: &[FileReport]) -> String {
+    let mut out = String::from(
+        "path,ok,seconds,entities,failed_entities,faces,face_errors,\
+         triangles,open_edges,watertight,unsupported,error\n",
+    );
+    for r in reports {
+        let row = [
+            csv_field(&r.path.display().to_string()),
+            r.is_ok().to_string(),
+            format!("{:.3}", r.seconds),
+            r.entities.to_string(),
+            r.failed_entities.to_string(),
+            r.faces.to_string(),
+            r.face_errors.to_string(),
+            r.triangles.to_string(),
+            r.open_edges.to_string(),
+            r.watertight.to_string(),
+            csv_field(&r.unsupported.join(";")),
+            csv_field(r.error.as_deref().unwrap_or("")),
+        ];
+        out += &row.join(",");
+        out.push('\n');
+    }
+    out
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
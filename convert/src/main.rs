@@ -0,0 +1,643 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+mod batch;
+
+use clap::{App, Arg, ArgMatches};
+use serde_json::json;
+
+use step::{
+    pmi::{self, PmiItem},
+    step_file::StepFile,
+    units::Dimension,
+};
+use triangulate::{
+    convention::{Axis, CoordinateConvention},
+    coverage::{self, CoverageReport},
+    crop::Aabb,
+    export::{save_with, ExportError, ExportOptions, Format},
+    grid::MeshTopology,
+    load::{load_mesh, LoadError, LoadReport, TriangulateOptions},
+    mesh::Mesh,
+};
+
+/// Returns the path for a single part's file, which is the output path with
+/// the part's index appended to its stem (e.g. `out.stl` becomes
+/// `out-3.stl`)
+fn part_path(path: &Path, i: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("part");
+    let name = match path.extension().and_then(|s| s.to_str()) {
+        Some(ext) => format!("{}-{}.{}", stem, i, ext),
+        None => format!("{}-{}", stem, i),
+    };
+    path.with_file_name(name)
+}
+
+/// Writes the mesh, either as a single file or one file per part, returning
+/// the paths which were written
+fn write(
+    mesh: &Mesh,
+    path: &Path,
+    format: Format,
+    opts: &ExportOptions,
+    parts: bool,
+) -> Result<Vec<PathBuf>, ExportError> {
+    if !parts {
+        save_with(mesh, path, format, opts)?;
+        return Ok(vec![path.to_owned()]);
+    }
+    let mut out = vec![];
+    for i in 0..mesh.parts.len() {
+        let mut keep = vec![false; mesh.parts.len()];
+        keep[i] = true;
+        let p = part_path(path, i);
+        save_with(&mesh.select_parts(&keep), &p, format, opts)?;
+        out.push(p);
+    }
+    Ok(out)
+}
+
+/// Picks the coordinate convention for the output, starting from the named
+/// `--convention` (or the format's default) and overriding individual
+/// settings.  Returns `None` if no convention flags were given, so that the
+/// exporter uses its default.
+fn convention(
+    matches: &ArgMatches,
+    format: Format,
+) -> Result<Option<CoordinateConvention>, Box<dyn std::error::Error>> {
+    if !["convention", "up", "scale", "flip-handedness"]
+        .iter()
+        .any(|a| matches.is_present(a))
+    {
+        return Ok(None);
+    }
+    let mut out = match matches.value_of("convention") {
+        Some(c) => c.parse::<CoordinateConvention>()?,
+        None => format.default_convention(),
+    };
+    if let Some(up) = matches.value_of("up") {
+        out.up = up.parse::<Axis>()?;
+    }
+    if let Some(s) = matches.value_of("scale") {
+        out.scale = match s.parse::<f64>() {
+            Ok(s) if s > 0.0 && s.is_finite() => s,
+            _ => return Err(format!("Invalid scale {:?}", s).into()),
+        };
+    }
+    if matches.is_present("flip-handedness") {
+        out.flip_handedness = true;
+    }
+    Ok(Some(out))
+}
+
+/// Builds the machine-readable report for `--stats-json`
+fn stats_json(
+    mesh: &Mesh,
+    report: &LoadReport,
+    open_edges: usize,
+    format: Format,
+    outputs: &[PathBuf],
+) -> serde_json::Value {
+    let timings: serde_json::Map<_, _> = report
+        .timings
+        .iter()
+        .map(|t| (t.stage.to_owned(), json!(t.duration.as_secs_f64())))
+        .collect();
+    let diagnostics: Vec<_> = report
+        .stats
+        .diagnostics
+        .iter()
+        .map(|d| {
+            json!({
+                "level": d.level.as_str(),
+                "entity": d.entity,
+                "message": d.message,
+            })
+        })
+        .collect();
+    json!({
+        "format": format.extension(),
+        "outputs": outputs,
+        "entities": report.parse.entities,
+        "failed_entities": report.parse.failed,
+        "shells": report.stats.num_shells,
+        "open_shells": report.stats.num_open_shells,
+        "faces": report.stats.num_faces,
+        "face_errors": report.stats.num_errors,
+        "face_panics": report.stats.num_panics,
+        "face_fallbacks": report.stats.num_fallbacks,
+        "cropped_faces": report.stats.num_cropped,
+        "parts": mesh.parts.len(),
+        "vertices": mesh.verts.len(),
+        "triangles": mesh.triangles.len(),
+        "wires": mesh.wires.len(),
+        "open_edges": open_edges,
+        "watertight": open_edges == 0 && report.stats.num_open_shells == 0,
+        "timings": timings,
+        "diagnostics": diagnostics,
+    })
+}
+
+/// Prints a human-readable report
+fn print_report(
+    out: &mut dyn std::io::Write,
+    mesh: &Mesh,
+    report: &LoadReport,
+    open_edges: usize,
+    outputs: &[PathBuf],
+) -> std::io::Result<()> {
+    let stats = &report.stats;
+    let watertight = open_edges == 0 && stats.num_open_shells == 0;
+    let time: std::time::Duration = report.timings.iter().map(|t| t.duration).sum();
+    writeln!(
+        out,
+        "entities:   {} ({} skipped)",
+        report.parse.entities, report.parse.failed
+    )?;
+    writeln!(
+        out,
+        "faces:      {} ({} failed)",
+        stats.num_faces,
+        stats.num_errors + stats.num_panics
+    )?;
+    if stats.num_cropped > 0 {
+        writeln!(out, "cropped:    {} faces", stats.num_cropped)?;
+    }
+    writeln!(out, "parts:      {}", mesh.parts.len())?;
+    writeln!(out, "triangles:  {}", mesh.triangles.len())?;
+    if !mesh.wires.is_empty() {
+        writeln!(out, "wires:      {}", mesh.wires.len())?;
+    }
+    if open_edges > 0 {
+        writeln!(out, "watertight: no ({} open edges)", open_edges)?;
+    } else if stats.num_open_shells > 0 {
+        writeln!(
+            out,
+            "watertight: no ({} open shells)",
+            stats.num_open_shells
+        )?;
+    } else {
+        writeln!(out, "watertight: yes")?;
+    }
+    // Measurements need to know the file's units, and volumes are only
+    // meaningful for closed surfaces
+    let bounds = Aabb::from_points(mesh.verts.iter().map(|v| v.pos));
+    if let (Some(unit), Some(b)) = (report.parse.length_unit, bounds) {
+        let d = b.max - b.min;
+        let [x, y, z] = [d.x, d.y, d.z].map(|v| unit.quantity(v, Dimension::Length));
+        writeln!(out, "size:       {} x {} x {}", x, y, z)?;
+        let m = mesh.mass_properties(0..mesh.triangles.len());
+        writeln!(
+            out,
+            "area:       {}",
+            unit.quantity(m.area, Dimension::Area)
+        )?;
+        if watertight {
+            writeln!(
+                out,
+                "volume:     {}",
+                unit.quantity(m.volume, Dimension::Volume)
+            )?;
+        }
+    }
+    if !stats.diagnostics.is_empty() {
+        writeln!(
+            out,
+            "warnings:   {} (run with -v to see them)",
+            stats.diagnostics.len()
+        )?;
+    }
+    writeln!(out, "time:       {:.2?}", time)?;
+    for p in outputs {
+        writeln!(out, "wrote {}", p.display())?;
+    }
+    Ok(())
+}
+
+/// Prints a table of the input's entity types for `--coverage`, followed
+/// by the geometry which the triangulator doesn't handle
+fn print_coverage(out: &mut dyn std::io::Write, report: &CoverageReport) -> std::io::Result<()> {
+    let width = report
+        .entities
+        .iter()
+        .map(|e| e.keyword.len())
+        .max()
+        .unwrap_or(0);
+    for e in &report.entities {
+        write!(
+            out,
+            "{:<width$}  {:>7}  {}",
+            e.keyword,
+            e.count,
+            e.support.as_str(),
+            width = width
+        )?;
+        if e.failed > 0 {
+            write!(out, " ({} failed)", e.failed)?;
+        }
+        writeln!(out)?;
+    }
+    let unsupported: Vec<_> = report
+        .unsupported_geometry()
+        .iter()
+        .map(|e| e.keyword.as_str())
+        .collect();
+    if !unsupported.is_empty() {
+        writeln!(out, "unsupported geometry: {}", unsupported.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Prints one line per PMI item for `--pmi`, with its value, text, and the
+/// geometry which it's attached to
+fn print_pmi(out: &mut dyn std::io::Write, items: &[PmiItem]) -> std::io::Result<()> {
+    if items.is_empty() {
+        return writeln!(out, "no PMI");
+    }
+    for p in items {
+        write!(out, "#{} {} {}", p.id, p.kind.as_str(), p.keyword)?;
+        if !p.name.is_empty() {
+            write!(out, " {:?}", p.name)?;
+        }
+        if let Some(v) = p.value {
+            write!(out, " = {}", v)?;
+        }
+        if let Some(t) = &p.text {
+            write!(out, " {:?}", t)?;
+        }
+        if !p.geometry.is_empty() {
+            let ids: Vec<_> = p.geometry.iter().map(|i| format!("#{}", i)).collect();
+            write!(out, " on {}", ids.join(", "))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Reads and parses a STEP file, then summarizes its PMI
+fn read_pmi(path: &str) -> Result<Vec<PmiItem>, LoadError> {
+    let data = std::fs::read(path).map_err(|source| LoadError::Io {
+        path: path.into(),
+        source,
+    })?;
+    let flat = StepFile::strip_flatten(&data);
+    let step = StepFile::parse(&flat)?;
+    Ok(pmi::summary(&step))
+}
+
+/// Parses `--tolerance`, which must be positive
+fn tolerance(matches: &ArgMatches) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+    match matches.value_of("tolerance") {
+        Some(t) => match t.parse::<f64>() {
+            Ok(t) if t > 0.0 && t.is_finite() => Ok(Some(t)),
+            _ => Err(format!("Invalid tolerance {:?}", t).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Checks every STEP file in a directory for `--batch`, writing a report.
+/// One file failing doesn't stop the others, but is an error at the end.
+fn run_batch(matches: &ArgMatches, dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let tolerance = tolerance(matches)?;
+    let jobs = match matches.value_of("jobs") {
+        Some(j) => match j.parse::<usize>() {
+            Ok(j) if j > 0 => j,
+            _ => return Err(format!("Invalid job count {:?}", j).into()),
+        },
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    let files = batch::find_files(Path::new(dir))?;
+    if files.is_empty() {
+        return Err(format!("No STEP files found in {}", dir).into());
+    }
+
+    // As with --stats-json, progress moves to stderr if the report is
+    // written to stdout
+    let report_path = matches.value_of("report");
+    let quiet = matches.is_present("quiet");
+    let to_stderr = report_path == Some("-");
+    let progress = |r: &batch::FileReport| {
+        if quiet {
+            return;
+        }
+        let line = match &r.error {
+            None => format!(
+                "ok    {} ({} triangles, {}watertight, {:.2}s)",
+                r.path.display(),
+                r.triangles,
+                if r.watertight { "" } else { "not " },
+                r.seconds
+            ),
+            Some(e) => format!("FAIL  {}: {}", r.path.display(), e),
+        };
+        if to_stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    };
+    let reports = batch::run(&files, tolerance, jobs, &progress);
+
+    if let Some(p) = report_path {
+        let text = if p.to_ascii_lowercase().ends_with(".csv") {
+            batch::to_csv(&reports)
+        } else {
+            serde_json::to_string_pretty(&batch::to_json(&reports))? + "\n"
+        };
+        if p == "-" {
+            print!("{}", text);
+        } else {
+            std::fs::write(p, text)?;
+        }
+    }
+    let failed = reports.iter().filter(|r| !r.is_ok()).count();
+    if failed > 0 {
+        return Err(format!("{} of {} files failed", failed, reports.len()).into());
+    }
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = matches.value_of("batch") {
+        return run_batch(matches, dir);
+    }
+    let input = matches.value_of("input").expect("Could not get input file");
+    if matches.is_present("coverage") {
+        let report = coverage::report_file(input)?;
+        print_coverage(&mut std::io::stdout(), &report)?;
+        return Ok(());
+    }
+    if matches.is_present("pmi") {
+        print_pmi(&mut std::io::stdout(), &read_pmi(input)?)?;
+        return Ok(());
+    }
+    let output = Path::new(matches.value_of("output").expect("Could not get output"));
+
+    // Check everything we can before the (potentially slow) triangulation
+    let format = match matches.value_of("format") {
+        Some(f) => Format::from_extension(f).expect("Format was checked by clap"),
+        None => Format::from_path(output)
+            .ok_or_else(|| ExportError::UnknownFormat(output.to_owned()))?,
+    };
+    let tolerance = tolerance(matches)?;
+    let edge_angle = match matches.value_of("edges") {
+        Some(a) => match a.parse::<f64>() {
+            Ok(a) if (0.0..=180.0).contains(&a) => Some(a),
+            _ => return Err(format!("Invalid edge angle {:?}", a).into()),
+        },
+        None => None,
+    };
+    let convention = convention(matches, format)?;
+    let crop = matches
+        .value_of("crop")
+        .map(|c| c.parse::<Aabb>())
+        .transpose()?;
+
+    let opts = TriangulateOptions {
+        tolerance,
+        crop,
+        clip: matches.is_present("clip"),
+        topology: if matches.is_present("quads") {
+            MeshTopology::QuadDominant
+        } else {
+            MeshTopology::Triangles
+        },
+        ..Default::default()
+    };
+    let (scene, report) = load_mesh(input, &opts)?;
+    let mut mesh = scene.mesh;
+    if matches.is_present("align") {
+        if let Some(mat) = mesh.align_to_principal_axes() {
+            mesh.apply_rigid(&mat);
+        }
+    }
+    let open_edges = mesh.open_edges();
+    let export = ExportOptions {
+        edge_angle,
+        convention,
+    };
+    let outputs = write(&mesh, output, format, &export, matches.is_present("parts"))?;
+
+    // If the JSON report goes to stdout, then the human-readable report
+    // moves to stderr so that the two don't get mixed up.
+    let stats_path = matches.value_of("stats-json");
+    if !matches.is_present("quiet") {
+        if stats_path == Some("-") {
+            print_report(&mut std::io::stderr(), &mesh, &report, open_edges, &outputs)?;
+        } else {
+            print_report(&mut std::io::stdout(), &mesh, &report, open_edges, &outputs)?;
+        }
+    }
+    if let Some(p) = stats_path {
+        let json = stats_json(&mesh, &report, open_edges, format, &outputs);
+        let text = serde_json::to_string_pretty(&json)?;
+        if p == "-" {
+            println!("{}", text);
+        } else {
+            std::fs::write(p, text + "\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Crates whose logs are shown at the level picked by `-v`
+const CRATES: [&str; 5] = ["cdt", "nurbs", "step", "step_convert", "triangulate"];
+
+/// Starts logging, with more detail for each `-v`.  By default, warnings
+/// from our own crates and errors from everything else are shown.
+/// `RUST_LOG` overrides this.
+fn init_logging(verbosity: u64) {
+    use log::LevelFilter::*;
+    let (ours, others) = match verbosity {
+        0 => (Warn, Error),
+        1 => (Info, Warn),
+        2 => (Debug, Info),
+        _ => (Trace, Trace),
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(others);
+    for c in CRATES {
+        builder.filter_module(c, ours);
+    }
+    builder.parse_default_env().init();
+}
+
+fn main() -> ExitCode {
+    let formats: Vec<_> = Format::ALL.iter().map(|f| f.extension()).collect();
+    let conventions: Vec<_> = CoordinateConvention::NAMED
+        .iter()
+        .map(|(n, _)| *n)
+        .collect();
+    let matches = App::new("step-convert")
+        .author("Matt Keeter <matt@formlabs.com>")
+        .about("Converts a STEP file to a triangle mesh")
+        .arg(
+            Arg::with_name("input")
+                .help("STEP file to convert")
+                .takes_value(true)
+                .required_unless_present("batch"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short('o')
+                .long("out")
+                .help("Mesh file to write")
+                .takes_value(true)
+                .required_unless_present_any(["coverage", "pmi", "batch"]),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .help("Output format (by default, picked from the output's extension)")
+                .takes_value(true)
+                .possible_values(&formats)
+                .ignore_case(true),
+        )
+        .arg(
+            Arg::with_name("tolerance")
+                .long("tolerance")
+                .help("Largest distance between curved edges and the mesh, in model units")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("convention")
+                .long("convention")
+                .help("Coordinate convention to write in (by default, Y-up meters for glTF and the STEP file's own coordinates otherwise)")
+                .takes_value(true)
+                .possible_values(&conventions)
+                .ignore_case(true),
+        )
+        .arg(
+            Arg::with_name("up")
+                .long("up")
+                .help("Axis which the model's Z axis is rotated onto, overriding the convention")
+                .takes_value(true)
+                .possible_values(["x", "y", "z"])
+                .ignore_case(true),
+        )
+        .arg(
+            Arg::with_name("scale")
+                .long("scale")
+                .help("Scale from model units to output units, overriding the convention")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("flip-handedness")
+                .long("flip-handedness")
+                .help("Mirrors the output into a left-handed coordinate system"),
+        )
+        .arg(
+            Arg::with_name("align")
+                .long("align")
+                .help("Moves the model onto its principal axes, centered on the origin with its longest side along X"),
+        )
+        .arg(
+            Arg::with_name("crop")
+                .long("crop")
+                .help("Only triangulates faces which touch the box between two corners, given as x0,y0,z0,x1,y1,z1")
+                .takes_value(true)
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::with_name("clip")
+                .long("clip")
+                .requires("crop")
+                .help("Also removes triangles outside of the --crop box, rather than keeping faces whole"),
+        )
+        .arg(
+            Arg::with_name("quads")
+                .long("quads")
+                .help("Pairs triangles into quads along each face's (u,v) sample grid, for OBJ output (other formats only write triangles)"),
+        )
+        .arg(
+            Arg::with_name("edges")
+                .long("edges")
+                .help("Adds boundary edges and edges sharper than the given angle (in degrees) as lines, for OBJ and glTF output")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("stats-json")
+                .long("stats-json")
+                .help("Writes a JSON report to the given file, or to stdout for '-'")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("parts")
+                .long("parts")
+                .help("Writes each part to a separate file, numbered after the output's name"),
+        )
+        .arg(
+            Arg::with_name("coverage")
+                .long("coverage")
+                .help("Lists the input's entity types and whether they're supported, instead of converting it"),
+        )
+        .arg(
+            Arg::with_name("pmi")
+                .long("pmi")
+                .conflicts_with("coverage")
+                .help("Lists the input's PMI (dimensions, tolerances, datums, and annotations), instead of converting it"),
+        )
+        .arg(
+            Arg::with_name("batch")
+                .long("batch")
+                .help("Checks every .step and .stp file under a directory (parsing, coverage, triangulation, and watertightness), instead of converting one file")
+                .takes_value(true)
+                .conflicts_with_all(&["input", "output", "coverage", "pmi"]),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .requires("batch")
+                .help("Writes the --batch report to the given file (as CSV for a .csv extension, and JSON otherwise), or as JSON to stdout for '-'")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .short('j')
+                .long("jobs")
+                .requires("batch")
+                .help("Number of files to check at once with --batch (by default, one per CPU)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Don't print a report"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short('v')
+                .long("verbose")
+                .multiple_occurrences(true)
+                .help("Logs more detail (-v for info, -vv for debug, -vvv for everything)"),
+        )
+        .get_matches();
+    init_logging(matches.occurrences_of("verbose"));
+
+    match run(&matches) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_path() {
+        assert_eq!(
+            part_path(Path::new("dir/out.stl"), 3),
+            Path::new("dir/out-3.stl")
+        );
+        assert_eq!(part_path(Path::new("out"), 0), Path::new("out-0"));
+        assert_eq!(part_path(Path::new("a.b.glb"), 12), Path::new("a.b-12.glb"));
+    }
+}
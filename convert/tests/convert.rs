@@ -0,0 +1,489 @@
+use std::path::PathBuf;
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use step::fixture;
+use triangulate::export::{read_glb, read_obj, read_ply, read_stl};
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("foxtrot-convert-{}-{}", std::process::id(), name))
+}
+
+/// Writes a generated two-part assembly to a scratch path
+fn fixture(name: &str) -> PathBuf {
+    let path = scratch(name);
+    let asm = fixture::assembly(vec![
+        fixture::filleted_block(10.0, 2.0),
+        fixture::cylinder(2.0, 5.0).translate([20.0, 0.0, 0.0]),
+    ]);
+    std::fs::write(&path, asm.to_step()).unwrap();
+    path
+}
+
+fn convert() -> Command {
+    Command::cargo_bin("step-convert").unwrap()
+}
+
+#[test]
+fn test_each_format() {
+    let input = fixture("formats.step");
+    let mut counts = vec![];
+    for ext in ["stl", "obj", "ply", "glb"] {
+        let out = scratch(&format!("cube.{}", ext));
+        let assert = convert().arg(&input).arg("-o").arg(&out).assert().success();
+        let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+        assert!(stdout.contains("triangles:"), "{}", stdout);
+        assert!(stdout.contains("watertight: yes"), "{}", stdout);
+
+        let data = std::fs::read(&out).unwrap();
+        std::fs::remove_file(&out).unwrap();
+        let tris = match ext {
+            "stl" => read_stl(&data),
+            "obj" => read_obj(std::str::from_utf8(&data).unwrap()),
+            "ply" => read_ply(&data),
+            "glb" => read_glb(&data),
+            _ => unreachable!(),
+        }
+        .unwrap();
+        assert!(!tris.is_empty());
+        counts.push(tris.len());
+    }
+    std::fs::remove_file(&input).unwrap();
+
+    // Every format holds the same triangles
+    assert!(counts.windows(2).all(|w| w[0] == w[1]), "{:?}", counts);
+}
+
+#[test]
+fn test_quads() {
+    let input = fixture("quads.step");
+    let mut faces = vec![];
+    for ext in ["obj", "stl"] {
+        let out = scratch(&format!("quads.{}", ext));
+        convert()
+            .arg(&input)
+            .arg("-o")
+            .arg(&out)
+            .arg("--quads")
+            .assert()
+            .success();
+        let data = std::fs::read(&out).unwrap();
+        std::fs::remove_file(&out).unwrap();
+        faces.push(match ext {
+            "obj" => {
+                let text = String::from_utf8(data).unwrap();
+                // OBJ output has four-sided faces, which split back into
+                // the same triangles as the other formats
+                let quads = text
+                    .lines()
+                    .filter(|l| l.starts_with("f ") && l.split_whitespace().count() == 5)
+                    .count();
+                assert!(quads > 0);
+                read_obj(&text).unwrap().len()
+            }
+            _ => read_stl(&data).unwrap().len(),
+        });
+    }
+    std::fs::remove_file(&input).unwrap();
+    assert_eq!(faces[0], faces[1]);
+}
+
+#[test]
+fn test_stats_json() {
+    let input = fixture("stats.step");
+    let out = scratch("stats.glb");
+    let assert = convert()
+        .arg(&input)
+        .args(["-o"])
+        .arg(&out)
+        .args([
+            "--format",
+            "ply",
+            "--stats-json",
+            "-",
+            "--tolerance",
+            "0.0001",
+        ])
+        .assert()
+        .success();
+    let stats: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+
+    // The explicit format wins over the extension
+    let data = std::fs::read(&out).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    assert_eq!(stats["format"], "ply");
+    assert_eq!(
+        stats["triangles"].as_u64().unwrap() as usize,
+        read_ply(&data).unwrap().len()
+    );
+    assert_eq!(stats["failed_entities"], 0);
+    assert_eq!(stats["parts"], 2);
+    assert_eq!(stats["faces"], 14);
+    assert_eq!(stats["watertight"], true);
+
+    // A tighter tolerance produces a denser mesh
+    let coarse = convert()
+        .arg(&input)
+        .arg("-o")
+        .arg(&out)
+        .args(["--stats-json", "-", "--tolerance", "0.01", "-q"])
+        .assert()
+        .success();
+    let coarse: serde_json::Value = serde_json::from_slice(&coarse.get_output().stdout).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    std::fs::remove_file(&input).unwrap();
+    assert!(coarse["triangles"].as_u64() < stats["triangles"].as_u64());
+}
+
+#[test]
+fn test_slanted_cylinder() {
+    // A cylinder cut by an oblique plane is bounded by elliptical edges,
+    // which must line up between the side and top faces
+    let input = scratch("slanted.step");
+    let text = fixture::cylinder(2.0, 5.0).slant(0.5).to_step();
+    std::fs::write(&input, text).unwrap();
+    let out = scratch("slanted.stl");
+    let assert = convert()
+        .arg(&input)
+        .arg("-o")
+        .arg(&out)
+        .args(["--stats-json", "-", "-q"])
+        .assert()
+        .success();
+    let stats: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    std::fs::remove_file(&input).unwrap();
+    assert_eq!(stats["faces"], 4);
+    assert_eq!(stats["face_errors"], 0);
+    assert_eq!(stats["face_fallbacks"], 0);
+    assert_eq!(stats["diagnostics"], serde_json::json!([]));
+    assert_eq!(stats["watertight"], true, "{}", stats);
+}
+
+#[test]
+fn test_surface_models() {
+    // Open shells are triangulated, but never watertight
+    let input = scratch("open.step");
+    std::fs::write(&input, fixture::cube(1.0).open().to_step()).unwrap();
+    let out = scratch("open.stl");
+    let assert = convert()
+        .arg(&input)
+        .arg("-o")
+        .arg(&out)
+        .args(["--stats-json", "-", "-q"])
+        .assert()
+        .success();
+    let stats: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(stats["faces"], 5);
+    assert_eq!(stats["open_shells"], 1);
+    assert_eq!(stats["triangles"], 10);
+    assert_eq!(stats["watertight"], false);
+
+    // A drawing made only of curves converts to an empty mesh, rather than
+    // failing to load
+    std::fs::write(&input, fixture::cylinder(1.0, 2.0).wireframe().to_step()).unwrap();
+    let assert = convert().arg(&input).arg("-o").arg(&out).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(stdout.contains("triangles:  0"), "{}", stdout);
+    assert!(stdout.contains("wires:      6"), "{}", stdout);
+    std::fs::remove_file(&out).unwrap();
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[test]
+fn test_coverage() {
+    // Swap one face of a cube onto a surface of revolution, which is parsed
+    // but not triangulated
+    let text = fixture::cube(1.0).to_step();
+    let plane = text.lines().find(|l| l.contains("=PLANE(")).unwrap();
+    let (id, args) = plane.split_once("=PLANE('',").unwrap();
+    let axis = args.trim_end_matches(");");
+    let input = scratch("coverage.step");
+    let text = text.replace(
+        plane,
+        &format!("{}=SURFACE_OF_REVOLUTION('',{},{});", id, axis, axis),
+    );
+    std::fs::write(&input, text).unwrap();
+
+    // No output is needed, since nothing is converted
+    let assert = convert().arg(&input).arg("--coverage").assert().success();
+    std::fs::remove_file(&input).unwrap();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    let support = |keyword: &str| {
+        let line = stdout
+            .lines()
+            .find(|l| l.split_whitespace().next() == Some(keyword))
+            .unwrap_or_else(|| panic!("{} is missing:\n{}", keyword, stdout));
+        line.split_whitespace().nth(2).unwrap().to_owned()
+    };
+    assert_eq!(support("SURFACE_OF_REVOLUTION"), "ignored");
+    assert_eq!(support("PLANE"), "parsed");
+    assert_eq!(support("PRODUCT"), "ignored");
+    assert!(
+        stdout.ends_with("unsupported geometry: SURFACE_OF_REVOLUTION\n"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_pmi() {
+    let input = scratch("pmi.step");
+    std::fs::write(&input, fixture::cube_with_pmi()).unwrap();
+    let assert = convert().arg(&input).arg("--pmi").assert().success();
+    std::fs::remove_file(&input).unwrap();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    let line = |id: &str| {
+        stdout
+            .lines()
+            .find(|l| l.starts_with(id))
+            .unwrap_or_else(|| panic!("{} is missing:\n{}", id, stdout))
+    };
+    assert_eq!(
+        line("#1004 "),
+        "#1004 dimension DIMENSIONAL_LOCATION \"linear_distance\" = 10 \"width\" on #112, #136"
+    );
+    assert!(line("#1011 ").ends_with("on #160"), "{}", stdout);
+
+    // Files without PMI say so
+    let input = scratch("no-pmi.step");
+    std::fs::write(&input, fixture::cube(1.0).to_step()).unwrap();
+    let assert = convert().arg(&input).arg("--pmi").assert().success();
+    std::fs::remove_file(&input).unwrap();
+    assert_eq!(assert.get_output().stdout, b"no PMI\n");
+}
+
+#[test]
+fn test_measurements() {
+    // The fixture is in millimetres, and areas and volumes are scaled along
+    // with lengths when the file is in inches
+    let input = scratch("measure.step");
+    let text = fixture::cube(2.0).to_step();
+    std::fs::write(&input, &text).unwrap();
+    let out = scratch("measure.stl");
+    let assert = convert().arg(&input).arg("-o").arg(&out).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(
+        stdout.contains("size:       2 mm x 2 mm x 2 mm\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("area:       24 mm²\n"), "{}", stdout);
+    assert!(stdout.contains("volume:     8 mm³\n"), "{}", stdout);
+
+    let inches = text.replace(
+        "SI_UNIT(.MILLI.,.METRE.)",
+        "CONVERSION_BASED_UNIT('INCH',#9999)",
+    );
+    assert_ne!(inches, text);
+    std::fs::write(&input, inches).unwrap();
+    let assert = convert().arg(&input).arg("-o").arg(&out).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    assert!(
+        stdout.contains("size:       50.8 mm x 50.8 mm x 50.8 mm\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("area:       15484 mm²\n"), "{}", stdout);
+    assert!(stdout.contains("volume:     131097 mm³\n"), "{}", stdout);
+}
+
+#[test]
+fn test_parts() {
+    let input = fixture("parts.step");
+    let out = scratch("part.stl");
+    convert()
+        .arg(&input)
+        .arg("-o")
+        .arg(&out)
+        .args(["--parts", "--quiet"])
+        .assert()
+        .success()
+        .stdout("");
+    std::fs::remove_file(&input).unwrap();
+    assert!(!out.exists());
+    for i in 0..2 {
+        let part = scratch(&format!("part-{}.stl", i));
+        let tris = read_stl(&std::fs::read(&part).unwrap()).unwrap();
+        std::fs::remove_file(&part).unwrap();
+        assert!(!tris.is_empty());
+    }
+    assert!(!scratch("part-2.stl").exists());
+}
+
+#[test]
+fn test_edges() {
+    let input = fixture("edges.step");
+    let out = scratch("edges.obj");
+    convert()
+        .arg(&input)
+        .arg("-o")
+        .arg(&out)
+        .args(["--edges", "30", "-q"])
+        .assert()
+        .success();
+    let text = std::fs::read_to_string(&out).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    assert!(text.contains("g edges\n"));
+    assert!(text.lines().any(|l| l.starts_with("l ")));
+    assert!(!read_obj(&text).unwrap().is_empty());
+
+    convert()
+        .arg(&input)
+        .arg("-o")
+        .arg(&out)
+        .args(["--edges", "200"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid edge angle"));
+    assert!(!out.exists());
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[test]
+fn test_failures() {
+    let input = fixture("failures.step");
+
+    // Missing input
+    convert()
+        .args(["does/not/exist.step", "-o"])
+        .arg(scratch("missing.stl"))
+        .assert()
+        .failure()
+        .stderr(contains("Error:"));
+
+    // Unknown output format, which is caught before loading
+    convert()
+        .arg(&input)
+        .arg("-o")
+        .arg(scratch("cube.xyz"))
+        .assert()
+        .failure()
+        .stderr(contains("Could not pick an export format"));
+
+    // Invalid tolerance
+    convert()
+        .arg(&input)
+        .arg("-o")
+        .arg(scratch("cube.stl"))
+        .args(["--tolerance", "0"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid tolerance"));
+    assert!(!scratch("cube.stl").exists());
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[test]
+fn test_quiet_is_silent() {
+    // Library code logs rather than printing, so quiet conversions of
+    // every example write nothing to stdout (and, at the default verbosity,
+    // only warnings to stderr)
+    let dir = format!("{}/../examples", env!("CARGO_MANIFEST_DIR"));
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("step") {
+            continue;
+        }
+        let out = scratch("quiet.stl");
+        let assert = convert()
+            .arg(&path)
+            .arg("-o")
+            .arg(&out)
+            .arg("--quiet")
+            .env_remove("RUST_LOG")
+            .assert()
+            .success()
+            .stdout("");
+        std::fs::remove_file(&out).unwrap();
+        let stderr = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+        assert!(
+            stderr.lines().all(|line| !line.contains(" INFO ")
+                && !line.contains(" DEBUG ")
+                && !line.contains(" TRACE ")),
+            "{}: {}",
+            path.display(),
+            stderr
+        );
+    }
+}
+
+#[test]
+fn test_batch() {
+    // Two good files (one in a subdirectory, with the other extension) and
+    // one which isn't really a STEP file
+    let dir = scratch("batch");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let block = fixture::assembly(vec![fixture::filleted_block(10.0, 2.0)]);
+    std::fs::write(dir.join("block.step"), block.to_step()).unwrap();
+    let cylinder = fixture::assembly(vec![fixture::cylinder(2.0, 5.0)]);
+    std::fs::write(dir.join("sub/cylinder.STP"), cylinder.to_step()).unwrap();
+    std::fs::write(dir.join("broken.step"), "ISO-10303-21;\nnot a STEP file").unwrap();
+    std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+    // One bad file doesn't stop the others, but fails the run
+    let report = scratch("batch-report.json");
+    let assert = convert()
+        .arg("--batch")
+        .arg(&dir)
+        .arg("--report")
+        .arg(&report)
+        .args(["-j", "2"])
+        .assert()
+        .failure()
+        .stderr(contains("1 of 3 files failed"));
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(stdout.contains("FAIL"), "{}", stdout);
+    assert_eq!(stdout.matches("ok ").count(), 2, "{}", stdout);
+
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&report).unwrap()).unwrap();
+    std::fs::remove_file(&report).unwrap();
+    assert_eq!(json["files"], 3);
+    assert_eq!(json["failed"], 1);
+    assert_eq!(json["watertight"], 2);
+    let results = json["results"].as_array().unwrap();
+    let by_name = |name: &str| {
+        results
+            .iter()
+            .find(|r| r["path"].as_str().unwrap().ends_with(name))
+            .unwrap()
+    };
+    let broken = by_name("broken.step");
+    assert_eq!(broken["ok"], false);
+    assert!(!broken["error"].as_str().unwrap().is_empty());
+    for name in ["block.step", "cylinder.STP"] {
+        let r = by_name(name);
+        assert_eq!(r["ok"], true, "{}", r);
+        assert!(r["error"].is_null());
+        assert!(r["entities"].as_u64().unwrap() > 0);
+        assert!(r["triangles"].as_u64().unwrap() > 0);
+        assert_eq!(r["watertight"], true);
+        assert_eq!(r["unsupported"], serde_json::json!([]));
+        assert!(r["timings"]["triangulate"].as_f64().is_some());
+    }
+
+    // The same report as CSV, with a header and one row per file
+    let report = scratch("batch-report.csv");
+    convert()
+        .arg("--batch")
+        .arg(&dir)
+        .arg("--report")
+        .arg(&report)
+        .arg("-q")
+        .assert()
+        .failure()
+        .stdout("");
+    let csv = std::fs::read_to_string(&report).unwrap();
+    std::fs::remove_file(&report).unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("path,ok,"));
+    assert_eq!(lines.iter().filter(|l| l.contains(",false,")).count(), 1);
+
+    // Without the broken file, the run succeeds
+    std::fs::remove_file(dir.join("broken.step")).unwrap();
+    convert().arg("--batch").arg(&dir).assert().success();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,87 @@
+// Build the bindings first, from the `wasm` directory:
+//   wasm-pack build --target web --out-dir ../examples/wasm/pkg
+import init, { triangulate_step } from "./pkg/wasm.js";
+
+const status = document.getElementById("status");
+const canvas = document.getElementById("canvas");
+
+// Draws the mesh with a fixed isometric-ish view, sorting triangles back to
+// front and shading them by their facing direction
+function draw(mesh) {
+    const pos = mesh.positions;
+    const idx = mesh.indices;
+    const col = mesh.colors;
+
+    // Rotate 45° about Z, then tilt 30° about X
+    const [c1, s1] = [Math.cos(Math.PI / 4), Math.sin(Math.PI / 4)];
+    const [c2, s2] = [Math.cos(Math.PI / 6), Math.sin(Math.PI / 6)];
+    const pts = [];
+    for (let i = 0; i < pos.length; i += 3) {
+        const x = pos[i] * c1 - pos[i + 1] * s1;
+        const y = pos[i] * s1 + pos[i + 1] * c1;
+        pts.push([x, y * s2 + pos[i + 2] * c2, y * c2 - pos[i + 2] * s2]);
+    }
+    let [lo, hi] = [[Infinity, Infinity], [-Infinity, -Infinity]];
+    for (const p of pts) {
+        lo = [Math.min(lo[0], p[0]), Math.min(lo[1], p[1])];
+        hi = [Math.max(hi[0], p[0]), Math.max(hi[1], p[1])];
+    }
+    const scale = 0.9 * Math.min(canvas.width / (hi[0] - lo[0]), canvas.height / (hi[1] - lo[1]));
+    const screen = (p) => [
+        canvas.width / 2 + (p[0] - (lo[0] + hi[0]) / 2) * scale,
+        canvas.height / 2 - (p[1] - (lo[1] + hi[1]) / 2) * scale,
+    ];
+
+    const tris = [];
+    for (let i = 0; i < idx.length; i += 3) {
+        const [a, b, c] = [pts[idx[i]], pts[idx[i + 1]], pts[idx[i + 2]]];
+        // Z component of the screen-space normal, for backface culling
+        // and shading
+        const u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        const v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        const n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        const len = Math.hypot(...n);
+        if (len === 0 || n[2] > 0) {
+            continue;
+        }
+        tris.push({ i, depth: a[2] + b[2] + c[2], shade: 0.3 + 0.7 * -n[2] / len });
+    }
+    tris.sort((a, b) => b.depth - a.depth);
+
+    const ctx = canvas.getContext("2d");
+    ctx.clearRect(0, 0, canvas.width, canvas.height);
+    for (const t of tris) {
+        const k = idx[t.i] * 3;
+        const rgb = [col[k], col[k + 1], col[k + 2]].map((f) => Math.round(f * t.shade * 255));
+        ctx.fillStyle = ctx.strokeStyle = `rgb(${rgb.join(",")})`;
+        ctx.beginPath();
+        for (let j = 0; j < 3; ++j) {
+            ctx.lineTo(...screen(pts[idx[t.i + j]]));
+        }
+        ctx.closePath();
+        ctx.fill();
+        ctx.stroke();
+    }
+}
+
+await init();
+document.getElementById("file").addEventListener("change", async (e) => {
+    const file = e.target.files[0];
+    if (!file) {
+        return;
+    }
+    status.textContent = `Loading ${file.name}...`;
+    const bytes = new Uint8Array(await file.arrayBuffer());
+    const tolerance = parseFloat(document.getElementById("tolerance").value) || 0;
+    try {
+        const start = performance.now();
+        const mesh = triangulate_step(bytes, tolerance);
+        const ms = (performance.now() - start).toFixed(0);
+        status.textContent = `${file.name}: ${mesh.triangleCount} triangles, ` +
+            `${mesh.vertexCount} vertices (${ms} ms)`;
+        draw(mesh);
+        mesh.free();
+    } catch (err) {
+        status.textContent = `Failed to load ${file.name}: ${err}`;
+    }
+});
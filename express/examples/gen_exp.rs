@@ -3,9 +3,11 @@ use std::io::Read;
 use std::time::SystemTime;
 
 use clap::{App, Arg};
-use express::parse::{parse, strip_comments_and_lower};
+use express::parse::{parse, strip_comments_and_lower_with_remarks};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
     let matches = App::new("parse_exp")
         .author("Matt Keeter <matt@formlabs.com>")
         .about("Parses an EXPRESS file")
@@ -25,17 +27,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     f.read_to_end(&mut buffer).expect("read ok");
 
     let start = SystemTime::now();
-    let s = strip_comments_and_lower(&buffer);
-    let mut parsed = match parse(&s) {
-        Ok(o) => o,
-        Err(e) => panic!("Failed to parse:\n{:?}", e),
-    };
+    let (s, remarks) = strip_comments_and_lower_with_remarks(&buffer);
+    let mut parsed = parse(&s)?;
+    parsed.attach_remarks(remarks);
     let end = SystemTime::now();
     let since_the_epoch = end.duration_since(start).expect("Time went backwards");
     eprintln!("parsed in {:?}", since_the_epoch);
 
     let start = SystemTime::now();
-    let gen = express::gen::gen(&mut parsed.1)?;
+    let gen = express::gen::gen(&mut parsed)?;
     let end = SystemTime::now();
     let since_the_epoch = end.duration_since(start).expect("Time went backwards");
     eprintln!("generated in {:?}", since_the_epoch);
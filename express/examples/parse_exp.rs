@@ -33,8 +33,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     eprintln!("time {:?}", since_the_epoch);
 
     match parsed {
-        Err(e) => eprintln!("Got err {:?}", e),
-        Ok((_, ref mut p)) => match matches.value_of("output") {
+        Err(e) => eprintln!("Got err {}", e),
+        Ok(ref mut p) => match matches.value_of("output") {
             Some(o) => std::fs::write(o, format!("Parse tree:\n{:#?}", p))?,
             _ => {
                 if !matches.is_present("quiet") {
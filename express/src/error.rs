@@ -0,0 +1,42 @@
+/// Errors from parsing an EXPRESS schema, generating code from it, or
+/// evaluating its expressions
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// Byte offsets are into the text passed to
+    /// [`parse`](crate::parse::parse), i.e. after comments are stripped
+    #[error("Parse error at byte {offset}: {context}")]
+    Parse { offset: usize, context: String },
+
+    #[error("Unexpected trailing text at byte {offset}")]
+    TrailingInput { offset: usize },
+
+    #[error("Expected a single schema, but got {0}")]
+    MultipleSchemas(usize),
+
+    #[error(transparent)]
+    Fmt(#[from] std::fmt::Error),
+
+    #[error("Unknown name '{0}'")]
+    UnknownName(String),
+
+    #[error("No entity instance #{0}")]
+    UnknownEntity(usize),
+
+    #[error("Entity instance #{id} has no attribute '{attribute}'")]
+    UnknownAttribute { id: usize, attribute: String },
+
+    #[error("Cannot apply {op} to {args}")]
+    TypeMismatch { op: &'static str, args: String },
+
+    #[error("{function} expects {expected} argument(s), but got {got}")]
+    Arity {
+        function: String,
+        expected: usize,
+        got: usize,
+    },
+
+    /// Returned for constructs which can't be evaluated without more of the
+    /// schema, e.g. calls to user-defined functions or `typeof`
+    #[error("Cannot evaluate {0}")]
+    Unsupported(String),
+}
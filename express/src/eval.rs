@@ -0,0 +1,1262 @@
+//! Evaluation of EXPRESS expressions, e.g. to compute constant bounds or to
+//! check `WHERE` rules against an entity instance.
+//!
+//! Indeterminate values follow the EXPRESS semantics: arithmetic involving
+//! `?` (or a division by zero) produces `?`, while comparisons and logical
+//! operations involving `?` produce `UNKNOWN`.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::{parse::*, Error};
+
+/// A three-valued EXPRESS logical, ordered so that `FALSE < UNKNOWN < TRUE`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Logical {
+    False,
+    Unknown,
+    True,
+}
+
+impl Logical {
+    pub fn and(self, other: Self) -> Self {
+        self.min(other)
+    }
+    pub fn or(self, other: Self) -> Self {
+        self.max(other)
+    }
+    pub fn xor(self, other: Self) -> Self {
+        match (self, other) {
+            (Logical::Unknown, _) | (_, Logical::Unknown) => Logical::Unknown,
+            (a, b) => (a != b).into(),
+        }
+    }
+}
+
+impl std::ops::Not for Logical {
+    type Output = Self;
+    fn not(self) -> Self {
+        match self {
+            Logical::False => Logical::True,
+            Logical::Unknown => Logical::Unknown,
+            Logical::True => Logical::False,
+        }
+    }
+}
+
+impl From<bool> for Logical {
+    fn from(b: bool) -> Self {
+        if b {
+            Logical::True
+        } else {
+            Logical::False
+        }
+    }
+}
+
+/// The result of evaluating an expression
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Real(f64),
+    String(String),
+    Logical(Logical),
+    /// An enumeration item, by name
+    Enumeration(String),
+    /// Any kind of aggregate.  Aggregates don't record their declared bounds,
+    /// so they are always indexed from 1.
+    Aggregate(Vec<Value>),
+    /// A reference to an entity instance in the [`Context`], by id
+    Entity(usize),
+    /// The indeterminate value `?`, which is also used for optional
+    /// attributes that aren't set
+    Indeterminate,
+}
+
+impl Value {
+    fn as_real(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Real(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Builds a numeric value from a literal.  The parser doesn't distinguish
+    /// integer and real literals, so whole numbers become integers.
+    fn from_literal(f: f64) -> Self {
+        if f.fract() == 0.0 && f.abs() < (1u64 << 53) as f64 {
+            Value::Integer(f as i64)
+        } else {
+            Value::Real(f)
+        }
+    }
+
+    /// Builds a real value, which is indeterminate if the result of the
+    /// operation was out of range (e.g. `sqrt(-1)` or `log(0)`)
+    fn real(f: f64) -> Self {
+        if f.is_finite() {
+            Value::Real(f)
+        } else {
+            Value::Indeterminate
+        }
+    }
+}
+
+/// An entity instance, for evaluating attribute references
+#[derive(Clone, Debug, Default)]
+pub struct Instance {
+    /// Entity name, in lower case
+    pub entity: String,
+
+    /// Attribute values (including derived attributes), by lower-case name
+    pub attributes: HashMap<String, Value>,
+}
+
+/// Names and instances which are visible to an [`Evaluator`]
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    /// Constants, by lower-case name.  Enumeration items which appear as
+    /// bare names in expressions should be listed here as well.
+    pub constants: HashMap<String, Value>,
+
+    /// Entity instances which can be reached through [`Value::Entity`]
+    pub instances: HashMap<usize, Instance>,
+
+    /// The value of `SELF`, which is an entity instance when checking an
+    /// entity's rules or a plain value when checking a defined type's rules.
+    /// The attributes of an entity `SELF` can also be used as bare names.
+    pub this: Option<Value>,
+}
+
+/// Evaluates expressions within a [`Context`]
+pub struct Evaluator<'c> {
+    ctx: &'c Context,
+
+    /// Variables bound by enclosing `QUERY` expressions, innermost last
+    locals: Vec<(String, Value)>,
+}
+
+impl<'c> Evaluator<'c> {
+    pub fn new(ctx: &'c Context) -> Self {
+        Self {
+            ctx,
+            locals: vec![],
+        }
+    }
+
+    pub fn eval(&mut self, e: &Expression) -> Result<Value, Error> {
+        let a = self.simple_expression(&e.0)?;
+        let (op, b) = match &e.1 {
+            None => return Ok(a),
+            Some((op, b)) => (op, self.simple_expression(b)?),
+        };
+        Ok(Value::Logical(match op {
+            RelOpExtended::RelOp(op) => compare(op, &a, &b)?,
+            RelOpExtended::In => member(&a, &b),
+            RelOpExtended::Like => like(&a, &b)?,
+        }))
+    }
+
+    pub fn eval_simple(&mut self, e: &SimpleExpression) -> Result<Value, Error> {
+        self.simple_expression(e)
+    }
+
+    /// Evaluates an expression which must produce a logical value, e.g. a
+    /// `WHERE` rule.  An indeterminate result is `UNKNOWN`.
+    pub fn eval_logical(&mut self, e: &Expression) -> Result<Logical, Error> {
+        match self.eval(e)? {
+            Value::Logical(b) => Ok(b),
+            Value::Indeterminate => Ok(Logical::Unknown),
+            v => Err(mismatch("a logical expression", &[&v])),
+        }
+    }
+
+    /// Evaluates the bounds of an aggregate type, e.g. `[1 : 2*n]`.  The
+    /// upper bound is `None` if it's indeterminate (i.e. unbounded).
+    pub fn eval_bounds(&mut self, b: &BoundSpec) -> Result<(i64, Option<i64>), Error> {
+        let lo = self.simple_expression(&b.0 .0 .0)?;
+        let lo = integer("a lower bound", &lo)?
+            .ok_or_else(|| mismatch("a lower bound", &[&Value::Indeterminate]))?;
+        let hi = self.simple_expression(&b.1 .0 .0)?;
+        Ok((lo, integer("an upper bound", &hi)?))
+    }
+
+    fn simple_expression(&mut self, e: &SimpleExpression) -> Result<Value, Error> {
+        let mut a = self.term(&e.0)?;
+        for (op, t) in &e.1 {
+            let b = self.term(t)?;
+            a = add_like(op, a, b)?;
+        }
+        Ok(a)
+    }
+
+    fn term(&mut self, t: &Term) -> Result<Value, Error> {
+        let mut a = self.factor(&t.0)?;
+        for (op, f) in &t.1 {
+            let b = self.factor(f)?;
+            a = multiplication_like(op, a, b)?;
+        }
+        Ok(a)
+    }
+
+    /// Evaluates a chain of exponents, which groups from the right
+    fn factor(&mut self, f: &Factor) -> Result<Value, Error> {
+        let a = self.simple_factor(&f.0)?;
+        let mut exponents = f
+            .1
+            .iter()
+            .map(|b| self.simple_factor(b))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some(mut b) = exponents.pop() else {
+            return Ok(a);
+        };
+        while let Some(e) = exponents.pop() {
+            b = power(e, b)?;
+        }
+        power(a, b)
+    }
+
+    fn simple_factor(&mut self, f: &SimpleFactor) -> Result<Value, Error> {
+        match f {
+            SimpleFactor::_AmbiguousFunctionCall(name, args) => {
+                match to_built_in_function(name.0) {
+                    Some(f) => {
+                        let args = args
+                            .iter()
+                            .map(|e| self.eval(e))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        built_in(&f, args)
+                    }
+                    None => Err(Error::Unsupported(format!("call to '{}'", name.0))),
+                }
+            }
+            SimpleFactor::AggregateInitializer(a) => self.aggregate_initializer(a),
+            SimpleFactor::EntityConstructor(c) => Err(Error::Unsupported(format!(
+                "construction of '{}'",
+                c.entity_ref.0
+            ))),
+            SimpleFactor::EnumerationReference(r) => Ok(Value::Enumeration(r.1 .0.to_owned())),
+            SimpleFactor::Interval(i) => {
+                let low = self.simple_expression(&i.low.0)?;
+                let item = self.simple_expression(&i.item.0)?;
+                let high = self.simple_expression(&i.high.0)?;
+                Ok(Value::Logical(
+                    interval_compare(&i.op1, &low, &item)?
+                        .and(interval_compare(&i.op2, &item, &high)?),
+                ))
+            }
+            SimpleFactor::QueryExpression(q) => self.query(q),
+            SimpleFactor::Unary(op, e) => {
+                let v = match e {
+                    ExpressionOrPrimary::Expression(e) => self.eval(e)?,
+                    ExpressionOrPrimary::Primary(p) => self.primary(p)?,
+                };
+                match op {
+                    None => Ok(v),
+                    Some(op) => unary(op, v),
+                }
+            }
+        }
+    }
+
+    fn aggregate_initializer(&mut self, a: &AggregateInitializer) -> Result<Value, Error> {
+        let mut out = vec![];
+        for Element(e, repetition) in &a.0 {
+            let v = self.eval(e)?;
+            let n = match repetition {
+                None => 1,
+                Some(r) => {
+                    let n = self.simple_expression(&r.0 .0)?;
+                    match integer("a repetition", &n)? {
+                        Some(i) if i >= 0 => i as usize,
+                        _ => return Err(mismatch("a repetition", &[&n])),
+                    }
+                }
+            };
+            out.resize(out.len() + n, v);
+        }
+        Ok(Value::Aggregate(out))
+    }
+
+    fn query(&mut self, q: &QueryExpression) -> Result<Value, Error> {
+        let items = match self.simple_expression(&q.aggregate.0)? {
+            Value::Indeterminate => return Ok(Value::Indeterminate),
+            Value::Aggregate(items) => items,
+            v => return Err(mismatch("query", &[&v])),
+        };
+        let mut out = vec![];
+        for item in items {
+            self.locals.push((q.var.0.to_owned(), item));
+            let r = self.eval_logical(&q.logical_expression.0);
+            let (_, item) = self.locals.pop().unwrap();
+            if r? == Logical::True {
+                out.push(item);
+            }
+        }
+        Ok(Value::Aggregate(out))
+    }
+
+    fn primary(&mut self, p: &Primary) -> Result<Value, Error> {
+        match p {
+            Primary::Literal(lit) => match lit {
+                Literal::String(s) => Ok(Value::String(s.clone())),
+                Literal::Binary(_) => Err(Error::Unsupported("binary literals".to_owned())),
+                Literal::Logical(b) => Ok(Value::Logical(match b {
+                    LogicalLiteral::True => Logical::True,
+                    LogicalLiteral::False => Logical::False,
+                    LogicalLiteral::Unknown => Logical::Unknown,
+                })),
+                Literal::Real(f) => Ok(Value::from_literal(*f)),
+            },
+            Primary::Qualifiable(f, qualifiers) => {
+                let mut v = self.qualifiable_factor(f)?;
+                for q in qualifiers {
+                    v = self.qualifier(v, q)?;
+                }
+                Ok(v)
+            }
+        }
+    }
+
+    fn qualifiable_factor(&mut self, f: &QualifiableFactor) -> Result<Value, Error> {
+        match f {
+            QualifiableFactor::FunctionCall(c) => self.function_call(c),
+            QualifiableFactor::AttributeRef(r) => self.name(r.0),
+            QualifiableFactor::ConstantFactor(ConstantFactor::ConstantRef(r)) => self.name(r.0),
+            QualifiableFactor::ConstantFactor(ConstantFactor::BuiltIn(c)) => match c {
+                BuiltInConstant::ConstE => Ok(Value::Real(std::f64::consts::E)),
+                BuiltInConstant::Pi => Ok(Value::Real(std::f64::consts::PI)),
+                BuiltInConstant::Self_ => self.this(),
+                BuiltInConstant::Indeterminant => Ok(Value::Indeterminate),
+            },
+            QualifiableFactor::GeneralRef(r) => match r {
+                GeneralRef::Parameter(p) => self.name(p.0),
+                GeneralRef::Variable(v) => self.name(v.0),
+                GeneralRef::_SimpleId(s) => self.name(s.0),
+                GeneralRef::Self_ => self.this(),
+            },
+            QualifiableFactor::Population(_) => {
+                Err(Error::Unsupported("entity populations".to_owned()))
+            }
+            QualifiableFactor::_Ambiguous(name) => self.name(name),
+        }
+    }
+
+    fn this(&self) -> Result<Value, Error> {
+        self.ctx
+            .this
+            .clone()
+            .ok_or_else(|| Error::UnknownName("self".to_owned()))
+    }
+
+    /// Looks up a bare name, which may be a query variable, an attribute of
+    /// `SELF`, or a constant (in that order of priority)
+    fn name(&self, name: &str) -> Result<Value, Error> {
+        if let Some((_, v)) = self.locals.iter().rev().find(|(n, _)| n == name) {
+            return Ok(v.clone());
+        }
+        if let Some(Value::Entity(id)) = &self.ctx.this {
+            if let Some(v) = self
+                .ctx
+                .instances
+                .get(id)
+                .and_then(|i| i.attributes.get(name))
+            {
+                return Ok(v.clone());
+            }
+        }
+        self.ctx
+            .constants
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::UnknownName(name.to_owned()))
+    }
+
+    fn qualifier(&mut self, v: Value, q: &Qualifier) -> Result<Value, Error> {
+        match q {
+            Qualifier::Attribute(a) => self.attribute(v, a.0 .0),
+            // Group qualifiers pick out a supertype's attribute when names
+            // are ambiguous, but instances here store every attribute by name
+            Qualifier::Group(_) => Ok(v),
+            Qualifier::Index(i) => {
+                let lo = self.simple_expression(&i.0 .0 .0 .0)?;
+                let hi = match &i.1 {
+                    Some(hi) => Some(self.simple_expression(&hi.0 .0 .0)?),
+                    None => None,
+                };
+                index(v, &lo, hi.as_ref())
+            }
+        }
+    }
+
+    fn attribute(&self, v: Value, name: &str) -> Result<Value, Error> {
+        match v {
+            Value::Indeterminate => Ok(Value::Indeterminate),
+            Value::Entity(id) => self
+                .ctx
+                .instances
+                .get(&id)
+                .ok_or(Error::UnknownEntity(id))?
+                .attributes
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::UnknownAttribute {
+                    id,
+                    attribute: name.to_owned(),
+                }),
+            v => Err(mismatch("attribute access", &[&v])),
+        }
+    }
+
+    fn function_call(&mut self, c: &FunctionCall) -> Result<Value, Error> {
+        let f = match &c.0 {
+            BuiltInOrFunctionRef::BuiltIn(f) => f,
+            BuiltInOrFunctionRef::Ref(r) => {
+                return Err(Error::Unsupported(format!("call to '{}'", r.0)))
+            }
+        };
+        let args = (c.1 .0)
+            .iter()
+            .map(|p| self.eval(&p.0))
+            .collect::<Result<Vec<_>, _>>()?;
+        built_in(f, args)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+fn mismatch(op: &'static str, args: &[&Value]) -> Error {
+    Error::TypeMismatch {
+        op,
+        args: args
+            .iter()
+            .map(|v| format!("{:?}", v))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Unpacks an integer, returning `None` if it is indeterminate
+fn integer(op: &'static str, v: &Value) -> Result<Option<i64>, Error> {
+    match v {
+        Value::Integer(i) => Ok(Some(*i)),
+        Value::Indeterminate => Ok(None),
+        v => Err(mismatch(op, &[v])),
+    }
+}
+
+/// Unpacks a logical, treating `?` as `UNKNOWN`
+fn logical(op: &'static str, v: &Value) -> Result<Logical, Error> {
+    match v {
+        Value::Logical(b) => Ok(*b),
+        Value::Indeterminate => Ok(Logical::Unknown),
+        v => Err(mismatch(op, &[v])),
+    }
+}
+
+/// Applies a numeric operator, falling back to real arithmetic if an
+/// integer operation overflows
+fn arithmetic(
+    op: &'static str,
+    a: Value,
+    b: Value,
+    int: fn(i64, i64) -> Option<i64>,
+    real: fn(f64, f64) -> f64,
+) -> Result<Value, Error> {
+    if let (Value::Integer(x), Value::Integer(y)) = (&a, &b) {
+        if let Some(z) = int(*x, *y) {
+            return Ok(Value::Integer(z));
+        }
+    }
+    match (a.as_real(), b.as_real()) {
+        (Some(x), Some(y)) => Ok(Value::real(real(x, y))),
+        _ => Err(mismatch(op, &[&a, &b])),
+    }
+}
+
+/// Checks whether two values are equal, for membership and set operations,
+/// where values of different types are simply unequal
+fn same(a: &Value, b: &Value) -> bool {
+    matches!(equal(a, b), Ok(Logical::True))
+}
+
+fn add_like(op: &AddLikeOp, a: Value, b: Value) -> Result<Value, Error> {
+    let op = match op {
+        AddLikeOp::Or => return Ok(Value::Logical(logical("or", &a)?.or(logical("or", &b)?))),
+        AddLikeOp::Xor => return Ok(Value::Logical(logical("xor", &a)?.xor(logical("xor", &b)?))),
+        AddLikeOp::Add => "+",
+        AddLikeOp::Sub => "-",
+    };
+    match (a, b) {
+        (Value::Indeterminate, _) | (_, Value::Indeterminate) => Ok(Value::Indeterminate),
+        (Value::String(a), Value::String(b)) if op == "+" => Ok(Value::String(a + &b)),
+
+        // Aggregates don't know whether they're sets or bags, so union uses
+        // set semantics, which is what WHERE rules (e.g. on TYPEOF) rely on
+        (Value::Aggregate(mut a), Value::Aggregate(b)) if op == "+" => {
+            for v in b {
+                if !a.iter().any(|u| same(u, &v)) {
+                    a.push(v);
+                }
+            }
+            Ok(Value::Aggregate(a))
+        }
+        (Value::Aggregate(mut a), v) if op == "+" => {
+            if !a.iter().any(|u| same(u, &v)) {
+                a.push(v);
+            }
+            Ok(Value::Aggregate(a))
+        }
+        (v, Value::Aggregate(mut b)) if op == "+" => {
+            if !b.iter().any(|u| same(u, &v)) {
+                b.insert(0, v);
+            }
+            Ok(Value::Aggregate(b))
+        }
+        (Value::Aggregate(mut a), Value::Aggregate(b)) => {
+            a.retain(|u| !b.iter().any(|v| same(u, v)));
+            Ok(Value::Aggregate(a))
+        }
+        (Value::Aggregate(mut a), v) => {
+            if let Some(i) = a.iter().position(|u| same(u, &v)) {
+                a.remove(i);
+            }
+            Ok(Value::Aggregate(a))
+        }
+        (a, b) if op == "+" => arithmetic(op, a, b, i64::checked_add, |x, y| x + y),
+        (a, b) => arithmetic(op, a, b, i64::checked_sub, |x, y| x - y),
+    }
+}
+
+fn multiplication_like(op: &MultiplicationLikeOp, a: Value, b: Value) -> Result<Value, Error> {
+    use MultiplicationLikeOp::*;
+    match op {
+        And => return Ok(Value::Logical(logical("and", &a)?.and(logical("and", &b)?))),
+        ComplexEntity => return Err(Error::Unsupported("complex entity construction".to_owned())),
+        _ => (),
+    }
+    if a == Value::Indeterminate || b == Value::Indeterminate {
+        return Ok(Value::Indeterminate);
+    }
+    match op {
+        Mul => match (a, b) {
+            (Value::Aggregate(mut a), Value::Aggregate(b)) => {
+                a.retain(|u| b.iter().any(|v| same(u, v)));
+                Ok(Value::Aggregate(a))
+            }
+            (a, b) => arithmetic("*", a, b, i64::checked_mul, |x, y| x * y),
+        },
+        Div => match (a.as_real(), b.as_real()) {
+            (Some(x), Some(y)) => Ok(if y == 0.0 {
+                Value::Indeterminate
+            } else {
+                Value::real(x / y)
+            }),
+            _ => Err(mismatch("/", &[&a, &b])),
+        },
+        IntegerDiv | Mod => {
+            let name = if matches!(op, Mod) { "mod" } else { "div" };
+            match (&a, &b) {
+                (Value::Integer(_), Value::Integer(0)) => Ok(Value::Indeterminate),
+                (Value::Integer(x), Value::Integer(y)) => {
+                    // The remainder takes the sign of the divisor, so the
+                    // quotient rounds towards negative infinity
+                    let m = x.checked_rem(*y).map_or(0, |r| {
+                        if r != 0 && (r < 0) != (*y < 0) {
+                            r + y
+                        } else {
+                            r
+                        }
+                    });
+                    Ok(match op {
+                        Mod => Value::Integer(m),
+                        _ => (x - m)
+                            .checked_div(*y)
+                            .map_or(Value::Indeterminate, Value::Integer),
+                    })
+                }
+                _ => Err(mismatch(name, &[&a, &b])),
+            }
+        }
+        And | ComplexEntity => unreachable!(),
+    }
+}
+
+fn power(a: Value, b: Value) -> Result<Value, Error> {
+    match (&a, &b) {
+        (Value::Indeterminate, _) | (_, Value::Indeterminate) => Ok(Value::Indeterminate),
+        (Value::Integer(x), Value::Integer(y)) if *y >= 0 => Ok(u32::try_from(*y)
+            .ok()
+            .and_then(|y| x.checked_pow(y))
+            .map_or_else(|| Value::real((*x as f64).powf(*y as f64)), Value::Integer)),
+        _ => match (a.as_real(), b.as_real()) {
+            (Some(x), Some(y)) if x == 0.0 && y <= 0.0 => Ok(Value::Indeterminate),
+            (Some(x), Some(y)) => Ok(Value::real(x.powf(y))),
+            _ => Err(mismatch("**", &[&a, &b])),
+        },
+    }
+}
+
+fn unary(op: &UnaryOp, v: Value) -> Result<Value, Error> {
+    match (op, v) {
+        (UnaryOp::Not, v) => Ok(Value::Logical(!logical("not", &v)?)),
+        (_, Value::Indeterminate) => Ok(Value::Indeterminate),
+        (UnaryOp::Add, v @ Value::Integer(_)) | (UnaryOp::Add, v @ Value::Real(_)) => Ok(v),
+        (UnaryOp::Sub, Value::Integer(i)) => Ok(i
+            .checked_neg()
+            .map_or(Value::Real(-(i as f64)), Value::Integer)),
+        (UnaryOp::Sub, Value::Real(f)) => Ok(Value::Real(-f)),
+        (_, v) => Err(mismatch("unary operator", &[&v])),
+    }
+}
+
+fn equal(a: &Value, b: &Value) -> Result<Logical, Error> {
+    match (a, b) {
+        (Value::Indeterminate, _) | (_, Value::Indeterminate) => Ok(Logical::Unknown),
+        (Value::String(a), Value::String(b)) => Ok((a == b).into()),
+        (Value::Logical(a), Value::Logical(b)) => Ok((a == b).into()),
+        (Value::Enumeration(a), Value::Enumeration(b)) => Ok((a == b).into()),
+        (Value::Entity(a), Value::Entity(b)) => Ok((a == b).into()),
+        (Value::Aggregate(a), Value::Aggregate(b)) => {
+            if a.len() != b.len() {
+                return Ok(Logical::False);
+            }
+            a.iter()
+                .zip(b)
+                .try_fold(Logical::True, |acc, (a, b)| Ok(acc.and(equal(a, b)?)))
+        }
+        _ => match (a.as_real(), b.as_real()) {
+            (Some(x), Some(y)) => Ok((x == y).into()),
+            _ => Err(mismatch("=", &[a, b])),
+        },
+    }
+}
+
+fn order(op: &'static str, a: &Value, b: &Value) -> Result<Option<Ordering>, Error> {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Ok(Some(a.cmp(b))),
+        (Value::Logical(a), Value::Logical(b)) => Ok(Some(a.cmp(b))),
+        _ => match (a.as_real(), b.as_real()) {
+            (Some(x), Some(y)) => Ok(x.partial_cmp(&y)),
+            _ => Err(mismatch(op, &[a, b])),
+        },
+    }
+}
+
+fn compare(op: &RelOp, a: &Value, b: &Value) -> Result<Logical, Error> {
+    use RelOp::*;
+    if *a == Value::Indeterminate || *b == Value::Indeterminate {
+        return Ok(Logical::Unknown);
+    }
+    let (name, accept): (_, fn(Ordering) -> bool) = match op {
+        Equal | InstanceEqual => return equal(a, b),
+        NotEqual | InstanceNotEqual => return equal(a, b).map(|b| !b),
+        LessThan => ("<", Ordering::is_lt),
+        GreaterThan => (">", Ordering::is_gt),
+        LessThanOrEqual => ("<=", Ordering::is_le),
+        GreaterThanOrEqual => (">=", Ordering::is_ge),
+    };
+    Ok(order(name, a, b)?.map_or(Logical::Unknown, |o| accept(o).into()))
+}
+
+fn interval_compare(op: &IntervalOp, a: &Value, b: &Value) -> Result<Logical, Error> {
+    compare(
+        &match op {
+            IntervalOp::LessThan => RelOp::LessThan,
+            IntervalOp::LessThanOrEqual => RelOp::LessThanOrEqual,
+        },
+        a,
+        b,
+    )
+}
+
+/// Evaluates `a IN b`, which is `UNKNOWN` if no element matches but some
+/// comparison was `UNKNOWN`
+fn member(a: &Value, b: &Value) -> Logical {
+    match b {
+        Value::Aggregate(items) => items.iter().fold(Logical::False, |acc, v| {
+            acc.or(equal(a, v).unwrap_or(Logical::False))
+        }),
+        _ => Logical::Unknown,
+    }
+}
+
+fn like(a: &Value, b: &Value) -> Result<Logical, Error> {
+    match (a, b) {
+        (Value::Indeterminate, _) | (_, Value::Indeterminate) => Ok(Logical::Unknown),
+        (Value::String(s), Value::String(p)) => {
+            let s: Vec<char> = s.chars().collect();
+            let p: Vec<char> = p.chars().collect();
+            Ok(like_match(&s, &p).into())
+        }
+        _ => Err(mismatch("like", &[a, b])),
+    }
+}
+
+/// Matches a string against a `LIKE` pattern, supporting the wildcards `*`
+/// (any run of characters), `?` (any character), `#` (a digit), `@` (a
+/// letter), `^` (an upper-case letter), and `!` (a lower-case letter), with
+/// `\` escaping the following character.
+fn like_match(s: &[char], p: &[char]) -> bool {
+    match p {
+        [] => s.is_empty(),
+        ['*', rest @ ..] => (0..=s.len()).any(|i| like_match(&s[i..], rest)),
+        ['\\', c, rest @ ..] => s.first() == Some(c) && like_match(&s[1..], rest),
+        [c, rest @ ..] => match s.split_first() {
+            None => false,
+            Some((x, s)) => {
+                let ok = match c {
+                    '?' => true,
+                    '#' => x.is_ascii_digit(),
+                    '@' => x.is_alphabetic(),
+                    '^' => x.is_uppercase(),
+                    '!' => x.is_lowercase(),
+                    c => x == c,
+                };
+                ok && like_match(s, rest)
+            }
+        },
+    }
+}
+
+/// Indexes into an aggregate, or takes a substring of a string (both
+/// 1-indexed); indices which are out of range produce `?`.
+fn index(v: Value, lo: &Value, hi: Option<&Value>) -> Result<Value, Error> {
+    let lo = match integer("an index", lo)? {
+        Some(i) => i,
+        None => return Ok(Value::Indeterminate),
+    };
+    let hi = match hi.map(|h| integer("an index", h)).transpose()? {
+        Some(None) => return Ok(Value::Indeterminate),
+        Some(Some(i)) => Some(i),
+        None => None,
+    };
+    match (v, hi) {
+        (Value::Indeterminate, _) => Ok(Value::Indeterminate),
+        (Value::Aggregate(items), None) => Ok(usize::try_from(lo - 1)
+            .ok()
+            .and_then(|i| items.get(i))
+            .cloned()
+            .unwrap_or(Value::Indeterminate)),
+        (Value::String(s), hi) => {
+            let chars: Vec<char> = s.chars().collect();
+            let hi = hi.unwrap_or(lo);
+            if lo < 1 || hi < lo || hi as usize > chars.len() {
+                Ok(Value::Indeterminate)
+            } else {
+                Ok(Value::String(
+                    chars[(lo as usize - 1)..hi as usize].iter().collect(),
+                ))
+            }
+        }
+        (v, _) => Err(mismatch("index", &[&v])),
+    }
+}
+
+fn built_in(f: &BuiltInFunction, args: Vec<Value>) -> Result<Value, Error> {
+    use BuiltInFunction as F;
+    let name = || format!("{:?}", f).to_lowercase();
+    let arity = |n: usize| {
+        if args.len() == n {
+            Ok(())
+        } else {
+            Err(Error::Arity {
+                function: name(),
+                expected: n,
+                got: args.len(),
+            })
+        }
+    };
+    let aggregate = |op| match &args[0] {
+        Value::Aggregate(items) => Ok(Some(items)),
+        Value::Indeterminate => Ok(None),
+        v => Err(mismatch(op, &[v])),
+    };
+
+    match f {
+        F::Abs => {
+            arity(1)?;
+            match &args[0] {
+                Value::Integer(i) => Ok(i
+                    .checked_abs()
+                    .map_or(Value::Real((*i as f64).abs()), Value::Integer)),
+                Value::Real(x) => Ok(Value::Real(x.abs())),
+                Value::Indeterminate => Ok(Value::Indeterminate),
+                v => Err(mismatch("abs", &[v])),
+            }
+        }
+        F::Sqrt
+        | F::Sin
+        | F::Cos
+        | F::Tan
+        | F::Asin
+        | F::Acos
+        | F::Exp
+        | F::Log
+        | F::Log2
+        | F::Log10 => {
+            arity(1)?;
+            let x = match &args[0] {
+                Value::Indeterminate => return Ok(Value::Indeterminate),
+                v => v
+                    .as_real()
+                    .ok_or_else(|| mismatch("a math function", &[v]))?,
+            };
+            let y = match f {
+                F::Sqrt => x.sqrt(),
+                F::Sin => x.sin(),
+                F::Cos => x.cos(),
+                F::Tan => x.tan(),
+                F::Asin => x.asin(),
+                F::Acos => x.acos(),
+                F::Exp => x.exp(),
+                F::Log => x.ln(),
+                F::Log2 => x.log2(),
+                _ => x.log10(),
+            };
+            Ok(Value::real(y))
+        }
+        F::Atan => {
+            arity(2)?;
+            match (&args[0], &args[1]) {
+                (Value::Indeterminate, _) | (_, Value::Indeterminate) => Ok(Value::Indeterminate),
+                (a, b) => match (a.as_real(), b.as_real()) {
+                    (Some(y), Some(x)) if y == 0.0 && x == 0.0 => Ok(Value::Indeterminate),
+                    (Some(y), Some(x)) => Ok(Value::Real(y.atan2(x))),
+                    _ => Err(mismatch("atan", &[a, b])),
+                },
+            }
+        }
+        F::Exists => {
+            arity(1)?;
+            Ok(Value::Logical((args[0] != Value::Indeterminate).into()))
+        }
+        F::Nvl => {
+            arity(2)?;
+            let mut args = args;
+            if args[0] == Value::Indeterminate {
+                Ok(args.pop().unwrap())
+            } else {
+                Ok(args.swap_remove(0))
+            }
+        }
+        F::SizeOf | F::HiIndex => {
+            arity(1)?;
+            Ok(aggregate("sizeof")?
+                .map_or(Value::Indeterminate, |a| Value::Integer(a.len() as i64)))
+        }
+        F::LoIndex => {
+            arity(1)?;
+            Ok(aggregate("loindex")?.map_or(Value::Indeterminate, |_| Value::Integer(1)))
+        }
+        F::Length => {
+            arity(1)?;
+            match &args[0] {
+                Value::String(s) => Ok(Value::Integer(s.chars().count() as i64)),
+                Value::Indeterminate => Ok(Value::Indeterminate),
+                v => Err(mismatch("length", &[v])),
+            }
+        }
+        F::Odd => {
+            arity(1)?;
+            Ok(match integer("odd", &args[0])? {
+                Some(i) => Value::Logical((i % 2 != 0).into()),
+                None => Value::Logical(Logical::Unknown),
+            })
+        }
+        F::Value => {
+            arity(1)?;
+            match &args[0] {
+                Value::String(s) => Ok(s
+                    .trim()
+                    .parse::<f64>()
+                    .map_or(Value::Indeterminate, Value::from_literal)),
+                Value::Indeterminate => Ok(Value::Indeterminate),
+                v => Err(mismatch("value", &[v])),
+            }
+        }
+        F::ValueIn => {
+            arity(2)?;
+            Ok(Value::Logical(member(&args[1], &args[0])))
+        }
+        F::ValueUnique => {
+            arity(1)?;
+            Ok(Value::Logical(match aggregate("value_unique")? {
+                None => Logical::Unknown,
+                Some(items) if items.contains(&Value::Indeterminate) => Logical::Unknown,
+                Some(items) => items
+                    .iter()
+                    .enumerate()
+                    .all(|(i, a)| items[i + 1..].iter().all(|b| !same(a, b)))
+                    .into(),
+            }))
+        }
+        F::Blength | F::Format | F::Hibound | F::LoBound | F::RolesOf | F::Typeof | F::Usedin => {
+            Err(Error::Unsupported(format!("built-in function {}", name())))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(name: &str, attributes: &[(&str, Value)]) -> Instance {
+        Instance {
+            entity: name.to_owned(),
+            attributes: attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        }
+    }
+
+    /// Builds a context where `SELF` is entity #1, with the given instances
+    fn context(instances: Vec<(usize, Instance)>) -> Context {
+        Context {
+            instances: instances.into_iter().collect(),
+            this: Some(Value::Entity(1)),
+            ..Context::default()
+        }
+    }
+
+    fn eval(ctx: &Context, s: &str) -> Result<Value, Error> {
+        let e = parse_expression(s)?;
+        Evaluator::new(ctx).eval(&e)
+    }
+
+    fn check(ctx: &Context, s: &str) -> Logical {
+        let e = parse_expression(s).unwrap();
+        Evaluator::new(ctx).eval_logical(&e).unwrap()
+    }
+
+    #[test]
+    fn test_constants() {
+        let mut ctx = Context::default();
+        ctx.constants.insert("n".to_owned(), Value::Integer(3));
+        assert_eq!(eval(&ctx, "2 * pi"), Ok(Value::Real(std::f64::consts::TAU)));
+        assert_eq!(eval(&ctx, "n * 2"), Ok(Value::Integer(6)));
+        assert_eq!(eval(&ctx, "7 div 2"), Ok(Value::Integer(3)));
+        assert_eq!(eval(&ctx, "-7 mod 2"), Ok(Value::Integer(1)));
+        assert_eq!(eval(&ctx, "7 / 2"), Ok(Value::Real(3.5)));
+        assert_eq!(eval(&ctx, "2 ** 10"), Ok(Value::Integer(1024)));
+        assert_eq!(eval(&ctx, "2 ** 3 ** 2"), Ok(Value::Integer(512)));
+        assert_eq!(eval(&ctx, "10 - 4 - 3"), Ok(Value::Integer(3)));
+        assert_eq!(eval(&ctx, "64 div 4 div 2"), Ok(Value::Integer(8)));
+        assert_eq!(eval(&ctx, "1 + 2 * 3 - 4 / 5"), Ok(Value::Real(6.2)));
+        assert_eq!(eval(&ctx, "abs(-n)"), Ok(Value::Integer(3)));
+        assert_eq!(eval(&ctx, "sqrt(16)"), Ok(Value::Real(4.0)));
+        assert_eq!(
+            eval(&ctx, "'ab' + 'cd'"),
+            Ok(Value::String("abcd".to_owned()))
+        );
+        ctx.constants
+            .insert("s".to_owned(), Value::String("abcd".to_owned()));
+        assert_eq!(eval(&ctx, "s[2]"), Ok(Value::String("b".to_owned())));
+        assert_eq!(eval(&ctx, "s[5]"), Ok(Value::Indeterminate));
+        assert_eq!(eval(&ctx, "sizeof([1, 2, 0 : n])"), Ok(Value::Integer(5)));
+        assert_eq!(eval(&ctx, "m + 1"), Err(Error::UnknownName("m".to_owned())));
+        assert!(matches!(
+            eval(&ctx, "'a' + 1"),
+            Err(Error::TypeMismatch { op: "+", .. })
+        ));
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut ctx = Context::default();
+        ctx.constants.insert("n".to_owned(), Value::Integer(4));
+        let mut ev = Evaluator::new(&ctx);
+        let (_, b) = bound_spec("[1 : 2 * n]").unwrap();
+        assert_eq!(ev.eval_bounds(&b), Ok((1, Some(8))));
+        let (_, b) = bound_spec("[0 : ?]").unwrap();
+        assert_eq!(ev.eval_bounds(&b), Ok((0, None)));
+    }
+
+    #[test]
+    fn test_indeterminate() {
+        let ctx = Context::default();
+        assert_eq!(eval(&ctx, "1 / 0"), Ok(Value::Indeterminate));
+        assert_eq!(eval(&ctx, "1 div 0"), Ok(Value::Indeterminate));
+        assert_eq!(eval(&ctx, "? + 1"), Ok(Value::Indeterminate));
+        assert_eq!(eval(&ctx, "sqrt(-1)"), Ok(Value::Indeterminate));
+        assert_eq!(eval(&ctx, "nvl(1 / 0, 5)"), Ok(Value::Integer(5)));
+        assert_eq!(check(&ctx, "1 / 0 > 0"), Logical::Unknown);
+        assert_eq!(check(&ctx, "? = ?"), Logical::Unknown);
+        assert_eq!(check(&ctx, "not (? < 1)"), Logical::Unknown);
+        assert_eq!(check(&ctx, "exists(?)"), Logical::False);
+
+        // Three-valued logic can still reach a definite answer
+        assert_eq!(check(&ctx, "(1 / 0 > 0) or true"), Logical::True);
+        assert_eq!(check(&ctx, "(1 / 0 > 0) and false"), Logical::False);
+        assert_eq!(check(&ctx, "(1 / 0 > 0) xor true"), Logical::Unknown);
+    }
+
+    #[test]
+    fn test_type_rules() {
+        // positive_length_measure: WR1 : SELF > 0.0;
+        let mut ctx = Context::default();
+        for (v, expected) in [
+            (Value::Real(2.5), Logical::True),
+            (Value::Real(0.0), Logical::False),
+            (Value::Indeterminate, Logical::Unknown),
+        ] {
+            ctx.this = Some(v);
+            assert_eq!(check(&ctx, "self > 0.0"), expected);
+        }
+
+        // day_in_month_number: WR1 : {1 <= SELF <= 31};
+        for (v, expected) in [(15, Logical::True), (32, Logical::False)] {
+            ctx.this = Some(Value::Integer(v));
+            assert_eq!(check(&ctx, "{1 <= self <= 31}"), expected);
+        }
+    }
+
+    #[test]
+    fn test_entity_rules() {
+        // person: WR1 : EXISTS(last_name) OR EXISTS(first_name);
+        let rule = "exists(last_name) or exists(first_name)";
+        let ctx = context(vec![(
+            1,
+            entity(
+                "person",
+                &[
+                    ("last_name", Value::Indeterminate),
+                    ("first_name", Value::Indeterminate),
+                ],
+            ),
+        )]);
+        assert_eq!(check(&ctx, rule), Logical::False);
+        let ctx = context(vec![(
+            1,
+            entity(
+                "person",
+                &[
+                    ("last_name", Value::String("Keeter".to_owned())),
+                    ("first_name", Value::Indeterminate),
+                ],
+            ),
+        )]);
+        assert_eq!(check(&ctx, rule), Logical::True);
+
+        // vector: WR1 : magnitude >= 0.0;
+        let ctx = context(vec![(
+            1,
+            entity("vector", &[("magnitude", Value::Real(-1.0))]),
+        )]);
+        assert_eq!(check(&ctx, "magnitude >= 0.0"), Logical::False);
+
+        // b_spline_curve_with_knots: WR2 : SIZEOF(knot_multiplicities) =
+        // upper_index_on_knots; (which is derived as SIZEOF(knots))
+        let ints = |v: &[i64]| Value::Aggregate(v.iter().map(|i| Value::Integer(*i)).collect());
+        let ctx = context(vec![(
+            1,
+            entity(
+                "b_spline_curve_with_knots",
+                &[
+                    ("knot_multiplicities", ints(&[4, 4])),
+                    ("upper_index_on_knots", Value::Integer(2)),
+                ],
+            ),
+        )]);
+        assert_eq!(
+            check(&ctx, "sizeof(knot_multiplicities) = upper_index_on_knots"),
+            Logical::True
+        );
+
+        // rational_b_spline_curve: WR2 : SIZEOF(QUERY(temp <* weights_data |
+        // (temp <= 0.0))) = 0;
+        let rule = "sizeof(query(temp <* weights_data | (temp <= 0.0))) = 0";
+        let weights = |v: &[f64]| Value::Aggregate(v.iter().map(|f| Value::Real(*f)).collect());
+        let ctx = context(vec![(
+            1,
+            entity(
+                "rational_b_spline_curve",
+                &[("weights_data", weights(&[1.0, 0.5]))],
+            ),
+        )]);
+        assert_eq!(check(&ctx, rule), Logical::True);
+        let ctx = context(vec![(
+            1,
+            entity(
+                "rational_b_spline_curve",
+                &[("weights_data", weights(&[1.0, -0.5]))],
+            ),
+        )]);
+        assert_eq!(check(&ctx, rule), Logical::False);
+    }
+
+    #[test]
+    fn test_rule_populations() {
+        // A global rule sees each of its populations as an aggregate of every
+        // instance of that entity, e.g. (in the style of compatible_dimension)
+        //   RULE compatible_dimension FOR (cartesian_point,
+        //     geometric_representation_context);
+        //   WHERE
+        //     WR1 : ...;
+        let rule = "sizeof(query(x <* cartesian_point | sizeof(query(y <* \
+            geometric_representation_context | (x.context = y) and \
+            (hiindex(x.coordinates) <> y.coordinate_space_dimension))) > 0)) = 0";
+        let reals = |n| Value::Aggregate(vec![Value::Real(0.0); n]);
+        let population =
+            |ids: &[usize]| Value::Aggregate(ids.iter().map(|i| Value::Entity(*i)).collect());
+        let mut ctx = context(vec![
+            (
+                1,
+                entity(
+                    "geometric_representation_context",
+                    &[("coordinate_space_dimension", Value::Integer(3))],
+                ),
+            ),
+            (
+                2,
+                entity(
+                    "geometric_representation_context",
+                    &[("coordinate_space_dimension", Value::Integer(2))],
+                ),
+            ),
+            (
+                3,
+                entity(
+                    "cartesian_point",
+                    &[("context", Value::Entity(1)), ("coordinates", reals(3))],
+                ),
+            ),
+            (
+                4,
+                entity(
+                    "cartesian_point",
+                    &[("context", Value::Entity(2)), ("coordinates", reals(2))],
+                ),
+            ),
+        ]);
+        ctx.constants
+            .insert("cartesian_point".to_owned(), population(&[3, 4]));
+        ctx.constants.insert(
+            "geometric_representation_context".to_owned(),
+            population(&[1, 2]),
+        );
+        assert_eq!(check(&ctx, rule), Logical::True);
+
+        // A 2D point in a 3D context breaks the rule
+        ctx.instances
+            .get_mut(&4)
+            .unwrap()
+            .attributes
+            .insert("context".to_owned(), Value::Entity(1));
+        assert_eq!(check(&ctx, rule), Logical::False);
+
+        // Empty populations trivially pass
+        ctx.constants
+            .insert("cartesian_point".to_owned(), population(&[]));
+        assert_eq!(check(&ctx, rule), Logical::True);
+    }
+
+    #[test]
+    fn test_nested_attributes() {
+        // axis2_placement_3d, with its location and optional axis
+        let placement = |axis| {
+            vec![
+                (
+                    1,
+                    entity(
+                        "axis2_placement_3d",
+                        &[("location", Value::Entity(2)), ("axis", axis)],
+                    ),
+                ),
+                (2, entity("cartesian_point", &[("dim", Value::Integer(3))])),
+                (3, entity("direction", &[("dim", Value::Integer(3))])),
+                (4, entity("direction", &[("dim", Value::Integer(2))])),
+            ]
+        };
+
+        // WR1 : SELF\placement.location.dim = 3;
+        let ctx = context(placement(Value::Indeterminate));
+        assert_eq!(
+            check(&ctx, "self\\placement.location.dim = 3"),
+            Logical::True
+        );
+
+        // WR2 : (NOT EXISTS(axis)) OR (axis.dim = 3);
+        let rule = "(not exists(axis)) or (axis.dim = 3)";
+        assert_eq!(check(&ctx, rule), Logical::True);
+        let ctx = context(placement(Value::Entity(3)));
+        assert_eq!(check(&ctx, rule), Logical::True);
+        let ctx = context(placement(Value::Entity(4)));
+        assert_eq!(check(&ctx, rule), Logical::False);
+
+        assert_eq!(
+            eval(&ctx, "location.name"),
+            Err(Error::UnknownAttribute {
+                id: 2,
+                attribute: "name".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        // trimmed_curve: WR1 : (HIINDEX(trim_1) = 1) OR
+        // (TYPEOF(trim_1[1]) <> TYPEOF(trim_1[2]));
+        let ctx = context(vec![(
+            1,
+            entity(
+                "trimmed_curve",
+                &[(
+                    "trim_1",
+                    Value::Aggregate(vec![Value::Entity(2), Value::Real(0.0)]),
+                )],
+            ),
+        )]);
+        assert_eq!(
+            eval(
+                &ctx,
+                "(hiindex(trim_1) = 1) or (typeof(trim_1[1]) <> typeof(trim_1[2]))"
+            ),
+            Err(Error::Unsupported("built-in function typeof".to_owned()))
+        );
+        assert_eq!(
+            eval(&ctx, "valid_calendar_date(self)"),
+            Err(Error::Unsupported(
+                "call to 'valid_calendar_date'".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let ctx = context(vec![(1, entity("point", &[]))]);
+        assert_eq!(check(&ctx, "1 <= 2"), Logical::True);
+        assert_eq!(check(&ctx, "2 >= 3"), Logical::False);
+        assert_eq!(check(&ctx, "1 <> 2"), Logical::True);
+        assert_eq!(check(&ctx, "1 :=: 1"), Logical::True);
+        assert_eq!(check(&ctx, "1 :<>: 1"), Logical::False);
+        assert_eq!(check(&ctx, "self :=: self"), Logical::True);
+        assert_eq!(check(&ctx, "self :<>: self"), Logical::False);
+        assert_eq!(check(&ctx, "{0 <= 0 < 1}"), Logical::True);
+        assert_eq!(check(&ctx, "{0 < 0 <= 1}"), Logical::False);
+    }
+
+    #[test]
+    fn test_membership() {
+        let ctx = Context::default();
+        assert_eq!(check(&ctx, "2 in [1, 2, 3]"), Logical::True);
+        assert_eq!(check(&ctx, "4 in [1, 2, 3]"), Logical::False);
+        assert_eq!(check(&ctx, "4 in [1, ?]"), Logical::Unknown);
+        assert_eq!(eval(&ctx, "[1, 2] + [2, 3]"), eval(&ctx, "[1, 2, 3]"));
+        assert_eq!(eval(&ctx, "[1, 2, 3] * [2, 3, 4]"), eval(&ctx, "[2, 3]"));
+        assert_eq!(eval(&ctx, "[1, 2, 3] - [2]"), eval(&ctx, "[1, 3]"));
+        assert_eq!(
+            check(
+                &ctx,
+                "'automotive_design.length_unit' like 'automotive_design.*'"
+            ),
+            Logical::True
+        );
+        assert_eq!(check(&ctx, "'a1' like '@#'"), Logical::True);
+        assert_eq!(check(&ctx, "'ab' like '@#'"), Logical::False);
+    }
+}
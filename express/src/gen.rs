@@ -146,17 +146,29 @@ impl<'a> Type<'a> {
         }
         Ok(())
     }
-    fn write_type<W>(&self, name: &str, buf: &mut W, type_map: &TypeMap) -> std::fmt::Result
+    fn write_type<W>(
+        &self,
+        name: &str,
+        buf: &mut W,
+        type_map: &TypeMap,
+        remarks: &Remarks,
+    ) -> std::fmt::Result
     where
         W: std::fmt::Write,
     {
         let camel_name = to_camel(name);
+        // Redeclared types have always been preceded by a blank line
+        if let Type::Redeclared(_) = self {
+            writeln!(buf)?;
+        }
+        if !matches!(self, Type::Primitive(_)) {
+            write_doc(buf, remarks.get(name), "")?;
+        }
         match self {
             Type::Redeclared(c) => {
                 writeln!(
                     buf,
-                    r#"
-#[derive(Debug)]
+                    r#"#[derive(Debug)]
 pub struct {0}<'a>(pub {1}, std::marker::PhantomData<&'a ()>); // redeclared
 impl<'a> Parse<'a> for {0}<'a> {{
     fn parse(s: &'a str) -> IResult<'a, Self> {{
@@ -255,8 +267,8 @@ pub type {0}<'a> = Id<{0}_<'a>>;
                     )?;
                     return Ok(());
                 } else if num_entities > 1 {
-                    // Print a warning here (TODO: handle better)
-                    eprintln!(
+                    // TODO: handle better than a warning
+                    log::warn!(
                         "Ambiguous SELECT for {} (contains multiple entities)",
                         camel_name
                     );
@@ -386,6 +398,8 @@ pub struct {}_<'a> {{ // entity",
                     if a.derived {
                         continue;
                     }
+                    let path = format!("{}.{}", a.from.unwrap_or(name), a.name);
+                    write_doc(buf, remarks.get(&path), "    ")?;
                     if a.dupe {
                         write!(buf, "    pub {}__{}: ", a.from.unwrap(), a.name)?;
                     } else {
@@ -506,8 +520,16 @@ enum Ref<'a> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
-pub fn gen(s: &mut Syntax) -> Result<String, std::fmt::Error> {
-    assert!(s.0.len() == 1, "Multiple schemas are unsupported");
+/// Generates Rust code for a parsed schema.
+///
+/// # Panics
+/// If the schema uses EXPRESS features which aren't supported (e.g.
+/// redeclared attributes or generic types)
+pub fn gen(s: &mut Syntax) -> Result<String, crate::Error> {
+    if s.0.len() != 1 {
+        return Err(crate::Error::MultipleSchemas(s.0.len()));
+    }
+    let remarks = s.0[0].remarks.clone();
 
     // First pass: collect entity names, then convert ambiguous IDs in SELECT
     // data types into Entity or Type refs
@@ -557,7 +579,7 @@ use arrayvec::ArrayVec;"
     )?;
 
     for k in &keys {
-        type_map.0[k].write_type(k, &mut buf, &type_map)?;
+        type_map.0[k].write_type(k, &mut buf, &type_map, &remarks)?;
     }
     writeln!(
         &mut buf,
@@ -570,7 +592,7 @@ pub enum Entity<'a> {{"
     writeln!(
         &mut buf,
         r#"    ComplexEntity(Vec<Entity<'a>>),
-    _FailedToParse,
+    _FailedToParse(&'a str),
     _EmptySlot,
 }}
 impl<'a> ParseFromChunks<'a> for Entity<'a> {{
@@ -627,12 +649,63 @@ impl<'a> Entity<'a> {{
         }};
         out
     }}
-}}"
+    pub fn keyword(&self) -> Option<&'static str> {{
+        match self {{"
+    )?;
+    for k in keys.iter().filter(|k| type_map.0[*k].is_entity()) {
+        writeln!(
+            &mut buf,
+            r#"            Entity::{}(_) => Some("{}"),"#,
+            to_camel(k),
+            capitalize(k)
+        )?;
+    }
+    writeln!(
+        &mut buf,
+        "            _ => None,
+        }}
+    }}
+}}
+pub const KEYWORDS: &[&str] = &["
     )?;
+    for k in keys.iter().filter(|k| type_map.0[*k].is_entity()) {
+        writeln!(&mut buf, r#"    "{}","#, capitalize(k))?;
+    }
+    writeln!(&mut buf, "];")?;
 
     Ok(buf)
 }
 
+/// Writes documentation from a tagged remark as a doc comment, removing the
+/// indentation which is shared by its lines
+fn write_doc<W>(buf: &mut W, doc: Option<&String>, indent: &str) -> std::fmt::Result
+where
+    W: std::fmt::Write,
+{
+    let doc = match doc {
+        Some(d) => d,
+        None => return Ok(()),
+    };
+    // The first line starts right after the tag, so it's not indented
+    let dedent = doc
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    for (i, line) in doc.lines().enumerate() {
+        let line = if i == 0 { line } else { line.get(dedent..).unwrap_or("") };
+        let line = line.trim_end();
+        if line.is_empty() {
+            writeln!(buf, "{}///", indent)?;
+        } else {
+            writeln!(buf, "{}/// {}", indent, line)?;
+        }
+    }
+    Ok(())
+}
+
 fn capitalize(s: &str) -> String {
     s.chars()
         .map(|c| c.to_uppercase().next().unwrap())
@@ -778,7 +851,7 @@ impl<'a> SimpleExpression<'a> {
             return None;
         }
         let factor = &term.0;
-        if factor.1.is_some() {
+        if !factor.1.is_empty() {
             return None;
         }
         let simple_factor = &factor.0;
@@ -1091,3 +1164,54 @@ impl<'a> NamedTypes<'a> {
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiple_schemas() {
+        let mut s = crate::parse::parse("schema a;\nend_schema;\nschema b;\nend_schema;").unwrap();
+        assert_eq!(gen(&mut s), Err(crate::Error::MultipleSchemas(2)));
+    }
+
+    #[test]
+    fn test_remarks() {
+        let data = br#"SCHEMA demo;
+TYPE label = STRING;
+END_TYPE;
+(*"demo.label" A human-readable Name.
+   Second line
+     indented. *)
+ENTITY base;
+  id : label;
+END_ENTITY;
+ENTITY product SUBTYPE OF (base);
+  name : label;
+END_ENTITY;
+(*"product.name" The product's Name *)
+--"base.id" Unique ID
+END_SCHEMA;
+"#;
+        let (text, remarks) = crate::parse::strip_comments_and_lower_with_remarks(data);
+        let mut s = crate::parse::parse(&text).unwrap();
+        s.attach_remarks(remarks);
+        let out = gen(&mut s).unwrap();
+        assert!(out.contains(
+            "/// A human-readable Name.
+/// Second line
+///   indented.
+#[derive(Debug)]
+pub struct Label<'a>"
+        ), "{}", out);
+        assert!(out.contains(
+            "    /// The product's Name
+    pub name: Label<'a>,"
+        ), "{}", out);
+
+        // Inherited attributes keep their documentation
+        assert_eq!(out.matches("    /// Unique ID\n    pub id: Label<'a>,").count(), 2);
+    }
+}
@@ -1,2 +1,6 @@
+mod error;
+pub mod eval;
 pub mod gen;
 pub mod parse;
+
+pub use crate::error::Error;
@@ -1,10 +1,12 @@
+use std::collections::BTreeMap;
+
 use memchr::{memchr, memchr_iter};
 use nom::{
     branch::alt,
-    character::complete::{alpha1, multispace0},
-    combinator::{map, map_opt, not, opt, peek, recognize},
+    character::complete::multispace0,
+    combinator::{map, map_opt, not, opt, peek, recognize, rest},
     error::*,
-    multi::{fold_many0, fold_many1, many0, many0_count, many1, separated_list0, separated_list1},
+    multi::{fold_many0, many0, many0_count, many1, separated_list0, separated_list1},
     sequence::{delimited, pair, preceded, terminated, tuple},
 };
 
@@ -108,37 +110,152 @@ macro_rules! id_type {
     };
 }
 
+/// Documentation from tagged remarks, keyed by the lower-case dotted path of
+/// the item being described (e.g. `product.name`).  The text keeps its
+/// original case and line breaks.
+pub type Remarks = BTreeMap<String, String>;
+
 /// Remove comments from an EXPRESS file and converts to lower-case.  This
 /// should be run before any parsers.
 pub fn strip_comments_and_lower(data: &[u8]) -> String {
+    strip_comments_and_lower_with_remarks(data).0
+}
+
+/// Like [`strip_comments_and_lower`], but also collects documentation from
+/// tagged remarks, which can be attached to the parsed schema with
+/// [`Syntax::attach_remarks`].
+///
+/// A tagged remark starts with a quoted path, e.g.
+/// `(*"product.name" The name of the product *)` or
+/// `--"product.name" The name of the product`.  Remarks which quote the whole
+/// text instead (`(*" product.name The name of the product "*)`) are also
+/// accepted.  Untagged remarks are discarded.
+pub fn strip_comments_and_lower_with_remarks(data: &[u8]) -> (String, Remarks) {
     let mut out = String::with_capacity(data.len());
+    let mut remarks = Remarks::new();
+    let mut add_remark = |body: &[u8]| {
+        if let Some((tag, text)) = tagged_remark(body) {
+            remarks
+                .entry(tag)
+                .and_modify(|t: &mut String| {
+                    t.push_str("\n\n");
+                    t.push_str(&text)
+                })
+                .or_insert(text);
+        }
+    };
     let mut i = 0;
     while i < data.len() {
         match data[i] {
             // Block comments
             b'(' if i + 1 < data.len() && data[i + 1] == b'*' => {
-                for j in memchr_iter(b')', &data[i + 2..]) {
-                    if data[i + j + 1] == b'*' {
-                        i += j + 2;
-                        break;
-                    }
-                }
+                // Skip to the closing ')' (which can't share its '*' with
+                // the opening "(*"), or to the end of the file if the
+                // comment is never closed.
+                let end = memchr_iter(b')', &data[i + 2..])
+                    .map(|j| i + 2 + j)
+                    .find(|&k| k > i + 2 && data[k - 1] == b'*');
+                add_remark(&data[i + 2..end.map_or(data.len(), |k| k - 1)]);
+                i = end.unwrap_or(data.len());
+            }
+            // String literals are copied verbatim, so they keep their case
+            // and can contain "--" or "(*".  An escaped quote ('') looks
+            // like the end of one literal and the start of the next.
+            b'\'' => {
+                let end = memchr(b'\'', &data[i + 1..]).map_or(data.len() - 1, |j| i + 1 + j);
+                out.extend(data[i..=end].iter().map(|&c| c as char));
+                i = end;
             }
             // Single-line comments
             b'-' if i + 1 < data.len() && data[i + 1] == b'-' => {
                 let newline = memchr(b'\n', &data[i + 2..]);
-                i += newline.unwrap_or(0) + 2;
+                add_remark(&data[i + 2..newline.map_or(data.len(), |j| i + 2 + j)]);
+                i = newline.map_or(data.len(), |j| i + 2 + j);
             }
             c => out.push(c.to_ascii_lowercase() as char),
         }
         i += 1;
     }
-    out
+    (out, remarks)
+}
+
+/// Splits the body of a remark into its (lower-case) tag and text, returning
+/// `None` if it's not tagged
+fn tagged_remark(body: &[u8]) -> Option<(String, String)> {
+    let body = String::from_utf8_lossy(body);
+    let rest = body.strip_prefix('"')?.trim_start();
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+        .unwrap_or(rest.len());
+    let (tag, rest) = rest.split_at(end);
+    if tag.is_empty() {
+        return None;
+    }
+    let text = match rest.strip_prefix('"') {
+        Some(text) => text,
+        None => rest.trim_end().strip_suffix('"').unwrap_or(rest),
+    };
+    Some((tag.to_ascii_lowercase(), text.trim().to_owned()))
 }
 
-/// Main entry function for the parser
-pub fn parse(s: &str) -> IResult<Syntax> {
-    syntax(s)
+/// Runs the literal sub-parsers on arbitrary text, ignoring the results.
+/// This is only public so that the fuzzers can reach it.
+#[doc(hidden)]
+pub fn fuzz_literals(s: &str) {
+    let _ = binary_literal(s);
+    let _ = encoded_string_literal(s);
+    let _ = real_literal(s);
+    let _ = simple_id(s);
+    let _ = simple_string_literal(s);
+}
+
+/// Main entry function for the parser.  `s` should be preprocessed with
+/// [`strip_comments_and_lower`] first.
+pub fn parse(s: &str) -> Result<Syntax, crate::Error> {
+    finish(s, syntax(s))
+}
+
+/// Parses a single schema, returning it along with any text which follows
+/// `END_SCHEMA;` (e.g. the next schema in the file).  As with [`parse`], `s`
+/// should be lower-case and free of comments.
+pub fn parse_schema(s: &str) -> Result<(SchemaDecl<'_>, &str), crate::Error> {
+    finish(s, pair(preceded(multispace0, schema_decl), rest)(s))
+}
+
+/// Parses a single expression, e.g. the body of a `WHERE` rule.  As with
+/// [`parse`], `s` should be lower-case and free of comments.
+pub fn parse_expression(s: &str) -> Result<Expression, crate::Error> {
+    finish(s, preceded(multispace0, expression)(s))
+}
+
+/// Converts the result of a top-level parser into a [`crate::Error`], which
+/// requires that the entire input was consumed
+fn finish<'a, U>(s: &'a str, r: IResult<'a, U>) -> Result<U, crate::Error> {
+    let offset = |rest: &str| s.len() - rest.len();
+    match r {
+        Ok(("", out)) => Ok(out),
+        Ok((rest, _)) => Err(crate::Error::TrailingInput {
+            offset: offset(rest),
+        }),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+            // The first error is the innermost one, which is the most precise
+            // about where things went wrong.
+            let (rest, context) = match e.errors.first() {
+                Some((rest, VerboseErrorKind::Context(c))) => (*rest, c.to_string()),
+                Some((rest, VerboseErrorKind::Char(c))) => (*rest, format!("expected '{}'", c)),
+                Some((rest, VerboseErrorKind::Nom(k))) => (*rest, k.description().to_owned()),
+                None => (s, "unknown error".to_owned()),
+            };
+            Err(crate::Error::Parse {
+                offset: offset(rest),
+                context,
+            })
+        }
+        Err(nom::Err::Incomplete(_)) => Err(crate::Error::Parse {
+            offset: s.len(),
+            context: "unexpected end of input".to_owned(),
+        }),
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -153,8 +270,9 @@ fn digit(s: &str) -> IResult<char> {
 
 // 126
 fn encoded_character(s: &str) -> IResult<char> {
-    map(recognize(tuple((octet, octet, octet, octet))), |v| {
-        std::char::from_u32(u32::from_str_radix(v, 16).unwrap()).unwrap()
+    // Not every 32-bit value is a valid `char` (e.g. surrogates)
+    map_opt(recognize(tuple((octet, octet, octet, octet))), |v| {
+        std::char::from_u32(u32::from_str_radix(v, 16).ok()?)
     })(s)
 }
 
@@ -175,9 +293,12 @@ fn not_paren_star_quote_special(s: &str) -> IResult<char> {
 
 // 134
 fn not_quote(s: &str) -> IResult<char> {
+    // String literals are the only text which keeps its case after
+    // stripping comments, so they also allow upper-case letters
     alt((
         not_paren_star_quote_special,
         letter,
+        nom::character::complete::one_of("ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
         digit,
         nom::character::complete::one_of("()*"),
     ))(s)
@@ -190,8 +311,10 @@ fn octet(s: &str) -> IResult<&str> {
 
 // 139
 fn binary_literal(s: &str) -> IResult<usize> {
-    let bits = fold_many1(alt((char('0'), char('1'))), 0, |acc, item| {
-        acc * 2 + item.to_digit(10).unwrap() as usize
+    // Literals which are too long for a usize are rejected, rather than
+    // overflowing
+    let bits = map_opt(recognize(many1(alt((char('0'), char('1'))))), |b| {
+        usize::from_str_radix(b, 2).ok()
     });
     preceded(char('%'), bits)(s)
 }
@@ -417,7 +540,7 @@ fn abstract_supertype_declaration(s: &str) -> IResult<AbstractSupertypeDeclarati
 
 // 167 actual_parameter_list = ’(’ parameter { ’,’ parameter } ’)’ .
 #[derive(Debug)]
-pub struct ActualParameterList<'a>(Vec<Parameter<'a>>);
+pub struct ActualParameterList<'a>(pub Vec<Parameter<'a>>);
 fn actual_parameter_list(s: &str) -> IResult<ActualParameterList> {
     map(parens(list1(',', parameter)), ActualParameterList)(s)
 }
@@ -442,7 +565,7 @@ fn add_like_op(s: &str) -> IResult<AddLikeOp> {
 
 // 169
 #[derive(Debug)]
-pub struct AggregateInitializer<'a>(Vec<Element<'a>>);
+pub struct AggregateInitializer<'a>(pub Vec<Element<'a>>);
 fn aggregate_initializer(s: &str) -> IResult<AggregateInitializer> {
     map(
         delimited(char('['), list0(',', element), char(']')),
@@ -570,7 +693,7 @@ pub struct AssignmentStmt<'a> {
 fn assignment_stmt(s: &str) -> IResult<AssignmentStmt> {
     map(
         tuple((
-            general_ref,
+            alt((general_ref, map(kw("self"), |_| GeneralRef::Self_))),
             many0(qualifier),
             tag(":="),
             expression,
@@ -635,8 +758,8 @@ alias!(Bound2<'a>, NumericExpression, bound_2);
 
 // 185
 #[derive(Debug)]
-pub struct BoundSpec<'a>(Bound1<'a>, pub Bound2<'a>);
-fn bound_spec(s: &str) -> IResult<BoundSpec> {
+pub struct BoundSpec<'a>(pub Bound1<'a>, pub Bound2<'a>);
+pub(crate) fn bound_spec(s: &str) -> IResult<BoundSpec> {
     map(
         tuple((char('['), bound_1, char(':'), bound_2, char(']'))),
         |(_, b1, _, b2, _)| BoundSpec(b1, b2),
@@ -694,7 +817,7 @@ pub enum BuiltInFunction {
     ValueIn,
     ValueUnique,
 }
-fn to_built_in_function(s: &str) -> Option<BuiltInFunction> {
+pub(crate) fn to_built_in_function(s: &str) -> Option<BuiltInFunction> {
     use BuiltInFunction::*;
     Some(match s {
         "abs" => Abs,
@@ -732,8 +855,12 @@ fn to_built_in_function(s: &str) -> Option<BuiltInFunction> {
     })
 }
 fn built_in_function(s: &str) -> IResult<BuiltInFunction> {
-    // Tokenize then match the keyword, instead of doing a huge alt(...)
-    ws(map_opt(alpha1, to_built_in_function))(s)
+    // Tokenize then match the keyword, instead of doing a huge alt(...).  The
+    // token is the whole identifier, so that e.g. `value_in` isn't read as
+    // `value` followed by `_in`.
+    let underscore = nom::character::complete::char('_');
+    let token = recognize(pair(letter, many0_count(alt((letter, digit, underscore)))));
+    ws(map_opt(token, to_built_in_function))(s)
 }
 
 // 188 built_in_procedure = INSERT | REMOVE .
@@ -843,7 +970,7 @@ fn constant_body(s: &str) -> IResult<ConstantBody> {
 
 // 195
 #[derive(Debug)]
-pub struct ConstantDecl<'a>(Vec<ConstantBody<'a>>);
+pub struct ConstantDecl<'a>(pub Vec<ConstantBody<'a>>);
 fn constant_decl(s: &str) -> IResult<ConstantDecl> {
     map(
         tuple((
@@ -907,7 +1034,7 @@ fn declaration(s: &str) -> IResult<Declaration> {
 
 // 200 derived_attr = attribute_decl ’:’ parameter_type ’:=’ expression ’;’ .
 #[derive(Debug)]
-pub struct DerivedAttr<'a>(pub AttributeDecl<'a>, ParameterType<'a>, Expression<'a>);
+pub struct DerivedAttr<'a>(pub AttributeDecl<'a>, pub ParameterType<'a>, pub Expression<'a>);
 fn derived_attr(s: &str) -> IResult<DerivedAttr> {
     map(
         tuple((
@@ -949,7 +1076,7 @@ fn domain_rule(s: &str) -> IResult<DomainRule> {
 
 // 203
 #[derive(Debug)]
-pub struct Element<'a>(Expression<'a>, Option<Repetition<'a>>);
+pub struct Element<'a>(pub Expression<'a>, pub Option<Repetition<'a>>);
 fn element(s: &str) -> IResult<Element> {
     map(
         pair(expression, opt(preceded(char(':'), repetition))),
@@ -1049,7 +1176,7 @@ fn enumeration_items(s: &str) -> IResult<EnumerationItems> {
 
 // 212 enumeration_reference = [ type_ref ’.’ ] enumeration_ref .
 #[derive(Debug)]
-pub struct EnumerationReference<'a>(Option<TypeRef<'a>>, EnumerationRef<'a>);
+pub struct EnumerationReference<'a>(pub Option<TypeRef<'a>>, pub EnumerationRef<'a>);
 fn enumeration_reference(s: &str) -> IResult<EnumerationReference> {
     map(
         tuple((opt(terminated(type_ref, char('.'))), enumeration_ref)),
@@ -1124,8 +1251,8 @@ fn explicit_attr(s: &str) -> IResult<ExplicitAttr> {
 // 216 expression = simple_expression [ rel_op_extended simple_expression ] .
 #[derive(Debug)]
 pub struct Expression<'a>(
-    SimpleExpression<'a>,
-    Option<(RelOpExtended, SimpleExpression<'a>)>,
+    pub SimpleExpression<'a>,
+    pub Option<(RelOpExtended, SimpleExpression<'a>)>,
 );
 impl<'a> Expression<'a> {
     fn parse(s: &'a str) -> IResult<Self> {
@@ -1139,11 +1266,14 @@ fn expression(s: &str) -> IResult<Expression> {
 }
 
 // 217 factor = simple_factor [ ’**’ simple_factor ] .
+//
+// Chains of exponents (`a ** b ** c`) are accepted as well, and are
+// right-associative, i.e. `a ** (b ** c)`.
 #[derive(Debug)]
-pub struct Factor<'a>(pub SimpleFactor<'a>, pub Option<SimpleFactor<'a>>);
+pub struct Factor<'a>(pub SimpleFactor<'a>, pub Vec<SimpleFactor<'a>>);
 fn factor(s: &str) -> IResult<Factor> {
     map(
-        pair(simple_factor, opt(preceded(tag("**"), simple_factor))),
+        pair(simple_factor, many0(preceded(tag("**"), simple_factor))),
         |(a, b)| Factor(a, b),
     )(s)
 }
@@ -1165,7 +1295,7 @@ pub enum BuiltInOrFunctionRef<'a> {
     Ref(FunctionRef<'a>),
 }
 #[derive(Debug)]
-pub struct FunctionCall<'a>(BuiltInOrFunctionRef<'a>, ActualParameterList<'a>);
+pub struct FunctionCall<'a>(pub BuiltInOrFunctionRef<'a>, pub ActualParameterList<'a>);
 fn function_call(s: &str) -> IResult<FunctionCall> {
     map(
         pair(
@@ -1337,6 +1467,10 @@ pub enum GeneralRef<'a> {
     Parameter(ParameterRef<'a>),
     Variable(VariableRef<'a>),
     _SimpleId(SimpleId<'a>),
+
+    /// `SELF` isn't a general_ref in the grammar, but schemas use it as the
+    /// target of assignments, e.g. `SELF\super.attr[i] := ...`
+    Self_,
 }
 fn general_ref(s: &str) -> IResult<GeneralRef> {
     map(simple_id, GeneralRef::_SimpleId)(s)
@@ -1445,7 +1579,7 @@ alias!(Index2<'a>, Index, index_2);
 
 // 239 index_qualifier = ’[’ index_1 [ ’:’ index_2 ] ’]’ .
 #[derive(Debug)]
-pub struct IndexQualifier<'a>(Index1<'a>, Option<Index2<'a>>);
+pub struct IndexQualifier<'a>(pub Index1<'a>, pub Option<Index2<'a>>);
 fn index_qualifier(s: &str) -> IResult<IndexQualifier> {
     let (s, _) = char('[')(s)?;
     let (s, index1) = index_1(s)?;
@@ -2034,7 +2168,7 @@ fn reference_clause(s: &str) -> IResult<ReferenceClause> {
     map(
         tuple((
             kw("reference"),
-            kw("front"),
+            kw("from"),
             schema_ref,
             opt(parens(list1(',', resource_or_rename))),
             char(';'),
@@ -2062,8 +2196,8 @@ fn rel_op(s: &str) -> IResult<RelOp> {
     use RelOp::*;
     alt((
         // Sorted by length to avoid prefix issues
-        map(tag(":<>:"), |_| InstanceEqual),
-        map(tag(":=:"), |_| InstanceNotEqual),
+        map(tag(":<>:"), |_| InstanceNotEqual),
+        map(tag(":=:"), |_| InstanceEqual),
         map(tag("<="), |_| LessThanOrEqual),
         map(tag(">="), |_| GreaterThanOrEqual),
         map(tag("<>"), |_| NotEqual),
@@ -2268,6 +2402,9 @@ pub struct SchemaDecl<'a> {
     pub id: SchemaId<'a>,
     pub version: Option<SchemaVersionId>,
     pub body: SchemaBody<'a>,
+    /// Documentation from tagged remarks, keyed by paths within the schema.
+    /// This is empty unless filled in by [`Syntax::attach_remarks`].
+    pub remarks: Remarks,
 }
 fn schema_decl(s: &str) -> IResult<SchemaDecl> {
     map(
@@ -2280,7 +2417,12 @@ fn schema_decl(s: &str) -> IResult<SchemaDecl> {
             kw("end_schema"),
             char(';'),
         )),
-        |(_, id, version, _, body, _, _)| SchemaDecl { id, version, body },
+        |(_, id, version, _, body, _, _)| SchemaDecl {
+            id,
+            version,
+            body,
+            remarks: Remarks::new(),
+        },
     )(s)
 }
 
@@ -2687,6 +2829,30 @@ pub struct Syntax<'a>(pub Vec<SchemaDecl<'a>>);
 fn syntax(s: &str) -> IResult<Syntax> {
     preceded(multispace0, map(many1(schema_decl), Syntax))(s)
 }
+impl<'a> Syntax<'a> {
+    /// Attaches remarks from [`strip_comments_and_lower_with_remarks`] to
+    /// their schemas.  Paths which start with a schema's name are given to
+    /// that schema (without the prefix); other paths are given to every
+    /// schema.
+    pub fn attach_remarks(&mut self, remarks: Remarks) {
+        for (path, text) in remarks {
+            let owner = path.split_once('.').and_then(|(schema, rest)| {
+                let i = self.0.iter().position(|d| d.id.0 == schema)?;
+                Some((i, rest.to_owned()))
+            });
+            match owner {
+                Some((i, rest)) => {
+                    self.0[i].remarks.insert(rest, text);
+                }
+                None => {
+                    for d in &mut self.0 {
+                        d.remarks.insert(path.clone(), text.clone());
+                    }
+                }
+            }
+        }
+    }
+}
 
 // 325 term = factor { multiplication_like_op factor } .
 #[derive(Debug)]
@@ -2847,7 +3013,7 @@ id_type!(VariableId, variable_id);
 
 // 338 where_clause = WHERE domain_rule ’;’ { domain_rule ’;’ } .
 #[derive(Debug)]
-pub struct WhereClause<'a>(Vec<DomainRule<'a>>);
+pub struct WhereClause<'a>(pub Vec<DomainRule<'a>>);
 fn where_clause(s: &str) -> IResult<WhereClause> {
     let (s, _) = kw("where")(s)?;
     let (s, v) = many1(terminated(domain_rule, char(';')))(s)?;
@@ -2883,6 +3049,306 @@ fn width_spec(s: &str) -> IResult<WidthSpec> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse() {
+        let s = parse("schema s;\nentity a;\nend_entity;\nend_schema;").unwrap();
+        assert_eq!(s.0.len(), 1);
+
+        let s = "schema s;\nentity a;\nend_schema;";
+        assert!(
+            matches!(parse(s), Err(crate::Error::Parse { .. })),
+            "{:?}",
+            parse(s).err()
+        );
+
+        let s = "schema s;\nend_schema;\nentity";
+        assert_eq!(
+            parse(s).err(),
+            Some(crate::Error::TrailingInput { offset: 22 })
+        );
+    }
+
+    /// A cut-down version of the AP214 longform schema, with at least one of
+    /// each kind of declaration
+    const AP214_EXCERPT: &[u8] = br#"
+SCHEMA automotive_design
+  '{ iso standard 10303 part(214) version(2) object(1) automotive_design(1) }';
+
+CONSTANT
+  dummy_gri : geometric_representation_item := representation_item('') ||
+                geometric_representation_item();
+END_CONSTANT;
+
+TYPE ahead_or_behind = ENUMERATION OF (ahead, behind);
+END_TYPE;
+
+TYPE label = STRING;
+END_TYPE;
+
+TYPE length_measure = REAL;
+END_TYPE;
+
+TYPE positive_length_measure = length_measure;
+WHERE
+  WR1: SELF > 0.;
+END_TYPE;
+
+TYPE vector_or_direction = SELECT (vector, direction);
+END_TYPE;
+
+ENTITY representation_item;
+  name : label;
+WHERE
+  WR1: SIZEOF(using_representations(SELF)) > 0;
+END_ENTITY;
+
+ENTITY geometric_representation_item
+  SUPERTYPE OF (ONEOF (point, direction, vector))
+  SUBTYPE OF (representation_item);
+DERIVE
+  dim : dimension_count := dimension_of(SELF);
+END_ENTITY;
+
+RULE compatible_dimension FOR
+  (cartesian_point, direction, representation_context,
+   geometric_representation_context);
+WHERE
+  WR1: SIZEOF(QUERY(x <* cartesian_point | SIZEOF(QUERY(y <*
+    geometric_representation_context | (y IN x.context_of_items) AND
+    (HIINDEX(x.coordinates) <> y.coordinate_space_dimension))) > 0)) = 0;
+END_RULE;
+
+FUNCTION bag_to_set (the_bag : BAG OF GENERIC : intype) : SET OF GENERIC : intype;
+  LOCAL
+    the_set : SET OF GENERIC : intype := [];
+  END_LOCAL;
+  IF SIZEOF(the_bag) > 0 THEN
+    REPEAT i := 1 TO HIINDEX(the_bag);
+      the_set := the_set + the_bag[i];
+    END_REPEAT;
+  END_IF;
+  RETURN (the_set);
+END_FUNCTION;
+
+ENTITY cartesian_point
+  SUBTYPE OF (point);
+  coordinates : LIST [1:3] OF length_measure;
+END_ENTITY;
+
+END_SCHEMA;
+"#;
+
+    #[test]
+    fn test_parse_schema() {
+        let s = strip_comments_and_lower(AP214_EXCERPT);
+        let (d, rest) = parse_schema(&s).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(d.id.0, "automotive_design");
+        assert!(d.version.is_some());
+        assert_eq!(d.body.constants.unwrap().0.len(), 1);
+
+        // Declarations and rules are kept in their original order
+        let kinds: Vec<_> = d
+            .body
+            .declarations
+            .iter()
+            .map(|d| match d {
+                DeclarationOrRuleDecl::Declaration(Declaration::Entity(_)) => "entity",
+                DeclarationOrRuleDecl::Declaration(Declaration::Function(_)) => "function",
+                DeclarationOrRuleDecl::Declaration(Declaration::Type(_)) => "type",
+                DeclarationOrRuleDecl::Declaration(_) => "other",
+                DeclarationOrRuleDecl::RuleDecl(_) => "rule",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "type", "type", "type", "type", "type", "entity", "entity", "rule", "function",
+                "entity"
+            ]
+        );
+
+        // Anything after the schema is handed back unparsed
+        let (d, rest) = parse_schema("schema a;\nend_schema;\nschema b;\nend_schema;").unwrap();
+        assert_eq!(d.id.0, "a");
+        assert_eq!(rest, "schema b;\nend_schema;");
+        let (d, rest) = parse_schema(rest).unwrap();
+        assert_eq!(d.id.0, "b");
+        assert_eq!(rest, "");
+
+        assert!(matches!(
+            parse_schema("schema a;\nentity;"),
+            Err(crate::Error::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_interface_specification() {
+        // The header of a short-form AP242 module
+        let s = strip_comments_and_lower(
+            br#"SCHEMA Assembly_structure_arm;
+
+USE FROM Product_view_definition_arm;    -- ISO/TS 10303-1019
+USE FROM Assembly_component_arm
+  (assembly_component AS component,
+   next_assembly_usage);
+REFERENCE FROM Support_resource_arm
+  (bag_to_set,
+   identifier AS id_type);
+REFERENCE FROM Value_with_unit_arm;
+
+ENTITY Assembly_definition
+  SUBTYPE OF (Product_occurrence);
+  id : id_type;
+END_ENTITY;
+
+END_SCHEMA;
+"#,
+        );
+        let (d, rest) = parse_schema(&s).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(d.body.declarations.len(), 1);
+
+        // Summarizes each clause as (USE?, schema, [(item, rename)])
+        let clauses: Vec<_> = d
+            .body
+            .interfaces
+            .iter()
+            .map(|i| match i {
+                InterfaceSpecification::UseClause(u) => {
+                    let items: Vec<_> = u
+                        .named_type_or_rename
+                        .iter()
+                        .flatten()
+                        .map(|n| match (&n.named_types, &n.rename) {
+                            (NamedTypes::_Ambiguous(a), None) => (a.0, None),
+                            (NamedTypes::_Ambiguous(a), Some(EntityOrTypeId::_Ambiguous(b))) => {
+                                (a.0, Some(b.0))
+                            }
+                            n => panic!("unexpected item {:?}", n),
+                        })
+                        .collect();
+                    (true, u.schema_ref.0, items)
+                }
+                InterfaceSpecification::ReferenceClause(r) => {
+                    let items: Vec<_> = r
+                        .resource_or_rename
+                        .iter()
+                        .flatten()
+                        .map(|ResourceOrRename(a, b)| match (a, b) {
+                            (ResourceRef::_Ambiguous(a), None) => (a.0, None),
+                            (ResourceRef::_Ambiguous(a), Some(RenameId::_Ambiguous(b))) => {
+                                (a.0, Some(b.0))
+                            }
+                            n => panic!("unexpected item {:?}", n),
+                        })
+                        .collect();
+                    (false, r.schema_ref.0, items)
+                }
+            })
+            .collect();
+        assert_eq!(
+            clauses,
+            [
+                (true, "product_view_definition_arm", vec![]),
+                (
+                    true,
+                    "assembly_component_arm",
+                    vec![
+                        ("assembly_component", Some("component")),
+                        ("next_assembly_usage", None)
+                    ]
+                ),
+                (
+                    false,
+                    "support_resource_arm",
+                    vec![("bag_to_set", None), ("identifier", Some("id_type"))]
+                ),
+                (false, "value_with_unit_arm", vec![]),
+            ]
+        );
+
+        // Interfaces must come before any other declarations
+        assert!(
+            parse_schema("schema a;\ntype t = integer;\nend_type;\nuse from b;\nend_schema;")
+                .is_err()
+        );
+    }
+
+    /// The full longform schema can't be redistributed, so this only runs
+    /// when pointed at a local copy, e.g.
+    /// `AP214_EXP=path/to/10303-214e3-aim-long.exp cargo test -- --ignored`
+    #[test]
+    #[ignore = "needs the AP214 longform schema; set AP214_EXP to its path"]
+    fn test_parse_ap214() {
+        let path = std::env::var("AP214_EXP").expect("AP214_EXP is not set");
+        let data = std::fs::read(path).expect("Could not read schema");
+        let s = strip_comments_and_lower(&data);
+        let (d, rest) = parse_schema(&s).unwrap();
+        assert_eq!(rest.trim(), "");
+        assert_eq!(d.id.0, "automotive_design");
+        let types = d
+            .body
+            .declarations
+            .iter()
+            .filter(|d| matches!(d, DeclarationOrRuleDecl::Declaration(Declaration::Type(_))))
+            .count();
+        assert!(types > 0);
+    }
+
+    #[test]
+    fn test_fuzz_regressions() {
+        // Not a valid char
+        assert!(encoded_string_literal("\"730f7321\"").is_err());
+        assert_eq!(encoded_string_literal("\"00000041\"").unwrap().1, "A");
+
+        // Too long for a usize
+        assert!(binary_literal(&format!("%{}", "1".repeat(100))).is_err());
+        assert_eq!(binary_literal("%101").unwrap().1, 5);
+
+        // Comments which are never closed, or which look closed too early
+        assert_eq!(strip_comments_and_lower(b"a(*"), "a");
+        assert_eq!(strip_comments_and_lower(b"a(*)b*)C"), "ac");
+        assert_eq!(strip_comments_and_lower(b"a(**)C"), "ac");
+
+        // A single-line comment which isn't followed by a newline
+        assert_eq!(strip_comments_and_lower(b"a := 1; -- trailing"), "a := 1; ");
+        assert_eq!(strip_comments_and_lower(b"a := 1; --"), "a := 1; ");
+    }
+
+    #[test]
+    fn test_strip_string_literals() {
+        assert_eq!(
+            strip_comments_and_lower(b"A := 'Mixed Case';"),
+            "a := 'Mixed Case';"
+        );
+        assert_eq!(
+            strip_comments_and_lower(b"A := 'a -- b'; -- c\nB"),
+            "a := 'a -- b'; b"
+        );
+        assert_eq!(
+            strip_comments_and_lower(b"A := 'a (* b *)' (* c *);"),
+            "a := 'a (* b *)' ;"
+        );
+        assert_eq!(
+            strip_comments_and_lower(b"A := 'It''s -- X';"),
+            "a := 'It''s -- X';"
+        );
+        assert_eq!(strip_comments_and_lower(b"A := 'Open"), "a := 'Open");
+
+        let s = strip_comments_and_lower(
+            br#"ENTITY Named;
+  name : STRING;
+WHERE
+  WR1: name <> 'Mixed Case -- (* Isn''t a comment *)';
+END_ENTITY;"#,
+        );
+        let (rest, e) = entity_decl(&s).unwrap();
+        assert_eq!(rest, "");
+        assert!(format!("{:?}", e.1.where_)
+            .contains(r#"String("Mixed Case -- (* Isn't a comment *)")"#));
+    }
+
     #[test]
     fn test_real_literal() {
         assert!(real_literal("1.E6").unwrap().1 == 1.0e6);
@@ -3140,6 +3606,253 @@ end_entity;  "#,
         assert_eq!(e.0, "");
     }
 
+    #[test]
+    fn test_entity_decl_ap214() {
+        let entities = [
+            r#"entity address;
+  internal_location : optional label;
+  street_number : optional label;
+  street : optional label;
+  postal_box : optional label;
+  town : optional label;
+  region : optional label;
+  postal_code : optional label;
+  country : optional label;
+  facsimile_number : optional label;
+  telephone_number : optional label;
+  electronic_mail_address : optional label;
+  telex_number : optional label;
+where
+  wr1 : exists(internal_location) or exists(street_number) or
+    exists(street) or exists(postal_box) or exists(town) or exists(region)
+    or exists(postal_code) or exists(country) or exists(facsimile_number)
+    or exists(telephone_number) or exists(electronic_mail_address) or
+    exists(telex_number);
+end_entity;"#,
+            r#"entity product;
+  id : identifier;
+  name : label;
+  description : optional text;
+  frame_of_reference : set [1:?] of product_context;
+end_entity;"#,
+            r#"entity representation;
+  name : label;
+  items : set [1:?] of representation_item;
+  context_of_items : representation_context;
+derive
+  id : identifier := get_id_value(self);
+  description : text := get_description_value(self);
+where
+  wr1 : sizeof(usedin(self, 'automotive_design.' +
+    'id_attribute.identified_item')) <= 1;
+end_entity;"#,
+            r#"entity group_assignment
+  abstract supertype;
+  assigned_group : group;
+derive
+  role : object_role := get_role(self);
+where
+  wr1 : sizeof(usedin(self, 'automotive_design.' +
+    'role_association.item_with_role')) <= 1;
+end_entity;"#,
+            r#"entity topological_representation_item
+  supertype of (oneof (vertex, edge, face_bound, face, vertex_shell,
+    wire_shell, connected_edge_set, connected_face_set, (loop andor path)))
+  subtype of (representation_item);
+end_entity;"#,
+            r#"entity connected_face_set
+  supertype of (oneof (closed_shell, open_shell))
+  subtype of (topological_representation_item);
+  cfs_faces : set [1:?] of face;
+end_entity;"#,
+            r#"entity b_spline_curve
+  supertype of (oneof (uniform_curve, b_spline_curve_with_knots,
+    quasi_uniform_curve, bezier_curve) andor rational_b_spline_curve)
+  subtype of (bounded_curve);
+  degree : integer;
+  control_points_list : list [2:?] of cartesian_point;
+  curve_form : b_spline_curve_form;
+  closed_curve : logical;
+  self_intersect : logical;
+derive
+  upper_index_on_control_points : integer := sizeof(control_points_list) - 1;
+  control_points : array [0:upper_index_on_control_points] of
+    cartesian_point := list_to_array(control_points_list, 0,
+    upper_index_on_control_points);
+where
+  wr1 : ('automotive_design.uniform_curve' in typeof(self)) or
+    ('automotive_design.quasi_uniform_curve' in typeof(self)) or
+    ('automotive_design.bezier_curve' in typeof(self)) or
+    ('automotive_design.b_spline_curve_with_knots' in typeof(self));
+end_entity;"#,
+            r#"entity b_spline_surface
+  supertype of (oneof (b_spline_surface_with_knots, uniform_surface,
+    quasi_uniform_surface, bezier_surface) andor rational_b_spline_surface)
+  subtype of (bounded_surface);
+  u_degree : integer;
+  v_degree : integer;
+  control_points_list : list [2:?] of list [2:?] of cartesian_point;
+  surface_form : b_spline_surface_form;
+  u_closed : logical;
+  v_closed : logical;
+  self_intersect : logical;
+end_entity;"#,
+            r#"entity edge_curve
+  subtype of (edge, geometric_representation_item);
+  edge_geometry : curve;
+  same_sense : boolean;
+end_entity;"#,
+            r#"entity face_surface
+  subtype of (face, geometric_representation_item);
+  face_geometry : surface;
+  same_sense : boolean;
+where
+  wr1 : not ('automotive_design.oriented_surface' in typeof(face_geometry));
+end_entity;"#,
+            r#"entity poly_loop
+  subtype of (loop, geometric_representation_item);
+  polygon : list [3:?] of unique cartesian_point;
+end_entity;"#,
+            r#"entity pre_defined_colour
+  subtype of (pre_defined_item, colour);
+end_entity;"#,
+        ];
+        let parsed: Vec<_> = entities
+            .iter()
+            .map(|e| {
+                let (rest, d) = entity_decl(e).unwrap();
+                assert_eq!(rest, "", "trailing input after {}", e);
+                d
+            })
+            .collect();
+        let by_name = |name: &str| {
+            parsed
+                .iter()
+                .find(|d| d.0 .0 .0 == name)
+                .unwrap_or_else(|| panic!("missing entity {}", name))
+        };
+        let subtype_of = |name: &str| -> Vec<&str> {
+            by_name(name)
+                .0
+                 .1
+                 .1
+                .iter()
+                .flat_map(|s| &s.0)
+                .map(|r| r.0)
+                .collect()
+        };
+        // Digs through aggregates to the named element type
+        fn element_type<'a>(t: &'a ParameterType<'a>) -> (usize, &'a str) {
+            use GeneralAggregationTypes::*;
+            match t {
+                ParameterType::Generalized(GeneralizedTypes::GeneralAggregation(a)) => {
+                    let inner = match a {
+                        List(l) => &l.parameter_type,
+                        Set(s) => &s.parameter_type,
+                        Bag(b) => &b.1,
+                        Array(a) => &a.parameter_type,
+                    };
+                    let (depth, name) = element_type(inner);
+                    (depth + 1, name)
+                }
+                ParameterType::Named(NamedTypes::_Ambiguous(id)) => (0, id.0),
+                t => panic!("unexpected type {:?}", t),
+            }
+        }
+
+        // OPTIONAL attributes
+        let address = &by_name("address").1;
+        assert_eq!(address.explicit_attr.len(), 12);
+        assert!(address.explicit_attr.iter().all(|a| a.optional));
+        assert!(address.where_.is_some());
+        let product = &by_name("product").1.explicit_attr;
+        let optional: Vec<_> = product.iter().map(|a| a.optional).collect();
+        assert_eq!(optional, [false, false, true, false]);
+
+        // Aggregates of entity references, including nested aggregates
+        assert_eq!(
+            element_type(&product[3].parameter_type),
+            (1, "product_context")
+        );
+        let cfs = &by_name("connected_face_set").1.explicit_attr[0];
+        assert_eq!(element_type(&cfs.parameter_type), (1, "face"));
+        let bsc = &by_name("b_spline_curve").1.explicit_attr[1];
+        assert_eq!(element_type(&bsc.parameter_type), (1, "cartesian_point"));
+        let bss = &by_name("b_spline_surface").1.explicit_attr[2];
+        assert_eq!(element_type(&bss.parameter_type), (2, "cartesian_point"));
+        match &by_name("poly_loop").1.explicit_attr[0].parameter_type {
+            ParameterType::Generalized(GeneralizedTypes::GeneralAggregation(
+                GeneralAggregationTypes::List(l),
+            )) => assert!(l.unique),
+            t => panic!("unexpected type {:?}", t),
+        }
+
+        // Single and multiple inheritance
+        assert_eq!(subtype_of("product"), Vec::<&str>::new());
+        assert_eq!(
+            subtype_of("connected_face_set"),
+            ["topological_representation_item"]
+        );
+        assert_eq!(
+            subtype_of("edge_curve"),
+            ["edge", "geometric_representation_item"]
+        );
+        assert_eq!(
+            subtype_of("face_surface"),
+            ["face", "geometric_representation_item"]
+        );
+        assert_eq!(
+            subtype_of("poly_loop"),
+            ["loop", "geometric_representation_item"]
+        );
+        assert_eq!(
+            subtype_of("pre_defined_colour"),
+            ["pre_defined_item", "colour"]
+        );
+
+        // Supertype constraints
+        assert!(by_name("product").0 .1 .0.is_none());
+        assert!(matches!(
+            by_name("group_assignment").0 .1 .0,
+            Some(SupertypeConstraint::AbstractSupertype(
+                AbstractSupertypeDeclaration(None)
+            ))
+        ));
+        let one_of = |name: &str| match &by_name(name).0 .1 .0 {
+            Some(SupertypeConstraint::SupertypeRule(SupertypeRule(SubtypeConstraint(e)))) => e,
+            c => panic!("unexpected constraint {:?}", c),
+        };
+        // ONEOF(...) on its own
+        let e = one_of("connected_face_set");
+        assert!(e.1.is_empty());
+        match &e.0 .0[..] {
+            [SupertypeTerm::OneOf(OneOf(terms))] => assert_eq!(terms.len(), 2),
+            t => panic!("unexpected terms {:?}", t),
+        }
+        // ONEOF(...) ANDOR entity
+        let e = one_of("b_spline_curve");
+        assert_eq!(e.1.len(), 1);
+        match (&e.0 .0[..], &e.1[0].0[..]) {
+            ([SupertypeTerm::OneOf(OneOf(terms))], [SupertypeTerm::Entity(r)]) => {
+                assert_eq!(terms.len(), 4);
+                assert_eq!(r.0, "rational_b_spline_curve");
+            }
+            t => panic!("unexpected terms {:?}", t),
+        }
+        // A parenthesized ANDOR within the ONEOF
+        let e = one_of("topological_representation_item");
+        match &e.0 .0[..] {
+            [SupertypeTerm::OneOf(OneOf(terms))] => {
+                assert_eq!(terms.len(), 9);
+                match &terms[8].0 .0[..] {
+                    [SupertypeTerm::Expression(e)] => assert_eq!(e.1.len(), 1),
+                    t => panic!("unexpected terms {:?}", t),
+                }
+            }
+            t => panic!("unexpected terms {:?}", t),
+        }
+    }
+
     #[test]
     fn test_subsuper() {
         let e = subsuper("abstract supertype;").unwrap();
@@ -3317,6 +4030,43 @@ wr1 : self >= 0.0; "#,
         assert_eq!(e.0, "");
     }
 
+    #[test]
+    fn test_constant_decl() {
+        let (rest, c) = constant_decl(
+            r#"constant
+  two_pi : real := 2.0 * pi;
+  axes : list [3:3] of integer := [1, 2, 3];
+  nothing : set [0:?] of integer := [];
+  dummy_gri : geometric_representation_item := representation_item('') ||
+                  geometric_representation_item();
+end_constant;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        let ids: Vec<_> = c.0.iter().map(|b| b.constant_id.0).collect();
+        assert_eq!(ids, ["two_pi", "axes", "nothing", "dummy_gri"]);
+        // Entity and type references look the same to the parser
+        assert!(matches!(
+            c.0[3].instantiable_type,
+            InstantiableType::Concrete(ConcreteTypes::TypeRef(TypeRef(
+                "geometric_representation_item"
+            )))
+        ));
+
+        // Constants come before the rest of the schema
+        let s = parse(
+            "schema s;\nconstant\n  e2 : real := const_e ** 2;\nend_constant;\n\
+             entity a;\nend_entity;\nend_schema;",
+        )
+        .unwrap();
+        let body = &s.0[0].body;
+        assert_eq!(body.constants.as_ref().unwrap().0.len(), 1);
+        assert_eq!(body.declarations.len(), 1);
+
+        // A block must declare at least one constant
+        assert!(constant_decl("constant end_constant;").is_err());
+    }
+
     #[test]
     fn test_query_expression() {
         let e = query_expression(
@@ -3455,6 +4205,347 @@ end_function;  "#,
         assert_eq!(e.0, "");
     }
 
+    /// Summarizes a parameter type as text, e.g. `set of generic:t`, leaving
+    /// out bounds and qualifiers
+    fn type_str(t: &ParameterType) -> String {
+        fn label(l: &Option<TypeLabel>) -> String {
+            match l {
+                Some(TypeLabel::_Ambiguous(id)) => format!(":{}", id.0),
+                Some(l) => panic!("unexpected label {:?}", l),
+                None => String::new(),
+            }
+        }
+        match t {
+            ParameterType::Generalized(g) => match g {
+                GeneralizedTypes::Aggregate(AggregateType(l, t)) => {
+                    format!("aggregate{} of {}", label(l), type_str(t))
+                }
+                GeneralizedTypes::GeneralAggregation(a) => match a {
+                    GeneralAggregationTypes::Array(a) => {
+                        format!("array of {}", type_str(&a.parameter_type))
+                    }
+                    GeneralAggregationTypes::Bag(b) => format!("bag of {}", type_str(&b.1)),
+                    GeneralAggregationTypes::List(l) => {
+                        format!("list of {}", type_str(&l.parameter_type))
+                    }
+                    GeneralAggregationTypes::Set(s) => {
+                        format!("set of {}", type_str(&s.parameter_type))
+                    }
+                },
+                GeneralizedTypes::GenericEntity(GenericEntityType(l)) => {
+                    format!("generic_entity{}", label(l))
+                }
+                GeneralizedTypes::Generic(GenericType(l)) => format!("generic{}", label(l)),
+            },
+            ParameterType::Named(NamedTypes::_Ambiguous(id)) => id.0.to_owned(),
+            ParameterType::Simple(t) => match t {
+                SimpleTypes::Boolean => "boolean".to_owned(),
+                SimpleTypes::Integer => "integer".to_owned(),
+                SimpleTypes::Logical => "logical".to_owned(),
+                SimpleTypes::Number => "number".to_owned(),
+                SimpleTypes::Real(_) => "real".to_owned(),
+                SimpleTypes::String(_) => "string".to_owned(),
+                t => format!("{:?}", t),
+            },
+            t => panic!("unexpected type {:?}", t),
+        }
+    }
+
+    /// Summarizes each formal parameter as `ids : type`
+    fn params_str<'a>(params: impl IntoIterator<Item = &'a FormalParameter<'a>>) -> Vec<String> {
+        params
+            .into_iter()
+            .map(|FormalParameter(ids, t)| {
+                let ids: Vec<_> = ids.iter().map(|i| i.0).collect();
+                format!("{} : {}", ids.join(", "), type_str(t))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_function_signatures() {
+        let (rest, f) = function_decl(
+            r#"function bag_to_set(the_bag : bag of generic : intype) : set of generic : intype;
+local
+  the_set : set of generic : intype := [];
+end_local;
+  if sizeof(the_bag) > 0 then
+    repeat i := 1 to hiindex(the_bag);
+      the_set := the_set + the_bag[i];
+    end_repeat;
+  end_if;
+  return (the_set);
+end_function;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        let head = &f.function_head;
+        assert_eq!(head.id.0, "bag_to_set");
+        assert_eq!(
+            params_str(head.params.iter().flatten()),
+            ["the_bag : bag of generic:intype"]
+        );
+        assert_eq!(type_str(&head.out), "set of generic:intype");
+        let local = &f.algorithm_head.local.as_ref().unwrap().0;
+        assert_eq!(local.len(), 1);
+        assert_eq!(local[0].variable_id[0].0, "the_set");
+        assert_eq!(type_str(&local[0].parameter_type), "set of generic:intype");
+        assert!(local[0].expression.is_some());
+        assert!(matches!(f.stmts[..], [Stmt::If(_), Stmt::Return(_)]));
+
+        let (rest, f) = function_decl(
+            r#"function dimension_of(item : geometric_representation_item) :
+   dimension_count;
+local
+  x : set of representation;
+  y : representation_context;
+  dim : dimension_count;
+end_local;
+  if 'automotive_design.cartesian_point' in typeof(item) then
+    dim := sizeof(item\cartesian_point.coordinates);
+    return (dim);
+  end_if;
+  if 'automotive_design.direction' in typeof(item) then
+    dim := sizeof(item\direction.direction_ratios);
+    return (dim);
+  end_if;
+  if 'automotive_design.vector' in typeof(item) then
+    dim := sizeof(item\vector.orientation\direction.direction_ratios);
+    return (dim);
+  end_if;
+  x := using_representations(item);
+  y := x[1].context_of_items;
+  dim := y\geometric_representation_context.coordinate_space_dimension;
+  return (dim);
+end_function;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        let head = &f.function_head;
+        assert_eq!(head.id.0, "dimension_of");
+        assert_eq!(
+            params_str(head.params.iter().flatten()),
+            ["item : geometric_representation_item"]
+        );
+        assert_eq!(type_str(&head.out), "dimension_count");
+        let local: Vec<_> = f
+            .algorithm_head
+            .local
+            .as_ref()
+            .unwrap()
+            .0
+            .iter()
+            .map(|v| format!("{} : {}", v.variable_id[0].0, type_str(&v.parameter_type)))
+            .collect();
+        assert_eq!(
+            local,
+            [
+                "x : set of representation",
+                "y : representation_context",
+                "dim : dimension_count"
+            ]
+        );
+        assert_eq!(f.stmts.len(), 7);
+
+        // Grouped parameters, labelled GENERIC and AGGREGATE types, and a
+        // function without parameters
+        let (rest, f) = function_decl(
+            r#"function list_to_array(lis : list [0:?] of generic : t; low, u : integer) :
+   array [low:u] of generic : t;
+local
+  n : integer;
+  res : array [low:u] of generic : t;
+end_local;
+  n := sizeof(lis);
+  if n <> (u - low + 1) then
+    return (?);
+  else
+    res := [lis[1], n];
+    repeat i := 2 to n;
+      res[low + i - 1] := lis[i];
+    end_repeat;
+    return (res);
+  end_if;
+end_function;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            params_str(f.function_head.params.iter().flatten()),
+            ["lis : list of generic:t", "low, u : integer"]
+        );
+        assert_eq!(type_str(&f.function_head.out), "array of generic:t");
+
+        let (rest, f) = function_decl(
+            r#"function first_of(agg : aggregate : a of generic_entity : e; s : generic) :
+   generic_entity : e;
+  return (agg[loindex(agg)]);
+end_function;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            params_str(f.function_head.params.iter().flatten()),
+            ["agg : aggregate:a of generic_entity:e", "s : generic"]
+        );
+        assert_eq!(type_str(&f.function_head.out), "generic_entity:e");
+        assert!(f.algorithm_head.local.is_none());
+
+        let (rest, f) =
+            function_decl("function zero : integer;\n  return (0);\nend_function;").unwrap();
+        assert_eq!(rest, "");
+        assert!(f.function_head.params.is_none());
+        assert_eq!(type_str(&f.function_head.out), "integer");
+    }
+
+    #[test]
+    fn test_rule_decl() {
+        let (rest, r) = rule_decl(
+            r#"rule compatible_dimension for
+  (cartesian_point, direction, representation_context,
+   geometric_representation_context);
+where
+  wr1 : sizeof(query(x <* cartesian_point | sizeof(query(y <*
+    geometric_representation_context | item_in_context(x, y) and (hiindex(
+    x.coordinates) <> y.coordinate_space_dimension))) > 0)) = 0;
+  wr2 : sizeof(query(x <* direction | sizeof(query(y <*
+    geometric_representation_context | item_in_context(x, y) and (hiindex(
+    x.direction_ratios) <> y.coordinate_space_dimension))) > 0)) = 0;
+end_rule;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(r.rule_head.rule_id.0, "compatible_dimension");
+        let populations: Vec<_> = r.rule_head.entities.iter().map(|e| e.0).collect();
+        assert_eq!(
+            populations,
+            [
+                "cartesian_point",
+                "direction",
+                "representation_context",
+                "geometric_representation_context"
+            ]
+        );
+        assert!(r.algorithm_head.local.is_none());
+        assert!(r.stmt.is_empty());
+        let labels: Vec<_> = r
+            .where_clause
+            .0
+            .iter()
+            .map(|d| d.rule_label_id.unwrap().0)
+            .collect();
+        assert_eq!(labels, ["wr1", "wr2"]);
+
+        // Local variables and statements come before the WHERE clause
+        let (rest, r) = rule_decl(
+            r#"rule unique_version_change_order_rule for (change);
+local
+  ca : bag of product_definition_formation := [];
+end_local;
+  repeat i := 1 to sizeof(change);
+    ca := ca + change[i].items;
+  end_repeat;
+where
+  wr1 : sizeof(ca) = sizeof(bag_to_set(ca));
+end_rule;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        let populations: Vec<_> = r.rule_head.entities.iter().map(|e| e.0).collect();
+        assert_eq!(populations, ["change"]);
+        let local = &r.algorithm_head.local.as_ref().unwrap().0;
+        assert_eq!(local[0].variable_id[0].0, "ca");
+        assert_eq!(
+            type_str(&local[0].parameter_type),
+            "bag of product_definition_formation"
+        );
+        assert!(matches!(r.stmt[..], [Stmt::Repeat(_)]));
+        assert_eq!(r.where_clause.0.len(), 1);
+
+        // The WHERE clause is required, and unlabelled rules are allowed
+        assert!(rule_decl("rule r for (a);\nend_rule;").is_err());
+        let (rest, r) = rule_decl("rule r for (a);\nwhere\n  sizeof(a) > 0;\nend_rule;").unwrap();
+        assert_eq!(rest, "");
+        assert!(r.where_clause.0[0].rule_label_id.is_none());
+    }
+
+    #[test]
+    fn test_procedure_decl() {
+        let args = |p: &ProcedureDecl| -> Vec<(bool, String)> {
+            p.0.args
+                .iter()
+                .flatten()
+                .flat_map(|(var, f)| params_str([f]).into_iter().map(move |s| (*var, s)))
+                .collect()
+        };
+
+        // VAR parameters of aggregate and entity type
+        let (rest, p) = procedure_decl(
+            r#"procedure add_point(var pts : list [0:?] of cartesian_point;
+    var last : cartesian_point; p : cartesian_point);
+  pts := pts + p;
+  last := p;
+end_procedure;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(p.0.procedure_id.0, "add_point");
+        assert_eq!(
+            args(&p),
+            [
+                (true, "pts : list of cartesian_point".to_owned()),
+                (true, "last : cartesian_point".to_owned()),
+                (false, "p : cartesian_point".to_owned()),
+            ]
+        );
+        assert!(matches!(
+            p.2[..],
+            [Stmt::Assignment(_), Stmt::Assignment(_)]
+        ));
+
+        // Grouped VAR parameters, local variables, and built-in procedures
+        let (rest, p) = procedure_decl(
+            r#"procedure swap(var a, b : generic : t; var messages : set of string);
+local
+  tmp : generic : t;
+end_local;
+  tmp := a;
+  a := b;
+  b := tmp;
+  insert(messages, 'swapped', 0);
+end_procedure;"#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            args(&p),
+            [
+                (true, "a, b : generic:t".to_owned()),
+                (true, "messages : set of string".to_owned()),
+            ]
+        );
+        assert!(p.1.local.is_some());
+        assert_eq!(p.2.len(), 4);
+        assert!(matches!(
+            p.2[3],
+            Stmt::ProcedureCall(ProcedureCallStmt {
+                proc: BuiltInOrProcedureRef::BuiltIn(_),
+                ..
+            })
+        ));
+
+        // A parameter whose name starts with "var" isn't a VAR parameter, and
+        // procedures can have neither parameters nor statements
+        let (rest, p) =
+            procedure_decl("procedure p(variable : integer);\n  skip;\nend_procedure;").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(args(&p), [(false, "variable : integer".to_owned())]);
+        let (rest, p) = procedure_decl("procedure noop;\nend_procedure;").unwrap();
+        assert_eq!(rest, "");
+        assert!(p.0.args.is_none());
+        assert!(p.2.is_empty());
+    }
+
     #[test]
     fn test_return_stmt() {
         let e = return_stmt(
@@ -3493,6 +4584,138 @@ end_function;  "#,
         assert_eq!(e.0, "");
     }
 
+    #[test]
+    fn test_stmt() {
+        // Assignments with qualified left-hand sides, including to SELF
+        let qualifiers = |qs: &[Qualifier]| -> Vec<String> {
+            qs.iter()
+                .map(|q| match q {
+                    Qualifier::Attribute(a) => format!(".{}", a.0 .0),
+                    Qualifier::Group(g) => format!("\\{}", g.0 .0),
+                    Qualifier::Index(_) => "[]".to_owned(),
+                })
+                .collect()
+        };
+        match stmt("self\\b_spline_curve.control_points_list[i] := p;") {
+            Ok(("", Stmt::Assignment(a))) => {
+                assert!(matches!(a.general_ref, GeneralRef::Self_));
+                assert_eq!(
+                    qualifiers(&a.qualifiers),
+                    ["\\b_spline_curve", ".control_points_list", "[]"]
+                );
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        match stmt("res[low + i - 1] := lis[i];") {
+            Ok(("", Stmt::Assignment(a))) => {
+                assert!(matches!(
+                    a.general_ref,
+                    GeneralRef::_SimpleId(SimpleId("res"))
+                ));
+                assert_eq!(qualifiers(&a.qualifiers), ["[]"]);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(stmt("self := p;").is_ok());
+        assert!(stmt("selfish := p;").is_ok());
+
+        // Nested IF inside REPEAT, with every kind of repeat control
+        match stmt(
+            r#"repeat i := 1 to hiindex(pts) by 2 while i < n until done;
+  if pts[i] = p then
+    done := true;
+    escape;
+  else
+    if i = n then
+      skip;
+    end_if;
+  end_if;
+end_repeat;"#,
+        ) {
+            Ok(("", Stmt::Repeat(RepeatStmt(RepeatControl(inc, w, u), body)))) => {
+                let inc = inc.unwrap();
+                assert_eq!(inc.var.0, "i");
+                assert!(inc.increment.is_some());
+                assert!(w.is_some());
+                assert!(u.is_some());
+                match &body[..] {
+                    [Stmt::If(IfStmt(_, then, Some(else_)))] => {
+                        assert!(matches!(then[..], [Stmt::Assignment(_), Stmt::Escape]));
+                        match &else_[..] {
+                            [Stmt::If(IfStmt(_, then, None))] => {
+                                assert!(matches!(then[..], [Stmt::Skip]))
+                            }
+                            s => panic!("unexpected else branch {:?}", s),
+                        }
+                    }
+                    s => panic!("unexpected body {:?}", s),
+                }
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        // A bare REPEAT, which loops until it hits an ESCAPE
+        assert!(matches!(
+            stmt("repeat;\n  escape;\nend_repeat;"),
+            Ok((
+                "",
+                Stmt::Repeat(RepeatStmt(RepeatControl(None, None, None), _))
+            ))
+        ));
+
+        // CASE over an enumeration, with grouped labels and OTHERWISE
+        match stmt(
+            r#"case s.sense of
+  ahead : return (1);
+  behind, exact : begin
+      n := n + 1;
+      return (-1);
+    end;
+  otherwise : return (?);
+end_case;"#,
+        ) {
+            Ok(("", Stmt::Case(c))) => {
+                let labels: Vec<_> = c.actions.iter().map(|a| a.0.len()).collect();
+                assert_eq!(labels, [1, 2]);
+                assert!(matches!(c.actions[0].1, Stmt::Return(ReturnStmt(Some(_)))));
+                match &c.actions[1].1 {
+                    Stmt::Compound(CompoundStmt(s)) => assert_eq!(s.len(), 2),
+                    s => panic!("unexpected action {:?}", s),
+                }
+                assert!(matches!(
+                    c.otherwise.as_deref(),
+                    Some(Stmt::Return(ReturnStmt(Some(_))))
+                ));
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(matches!(
+            stmt("case x of\nend_case;"),
+            Ok((
+                "",
+                Stmt::Case(CaseStmt {
+                    otherwise: None,
+                    ..
+                })
+            ))
+        ));
+
+        // RETURN (with and without a value), ESCAPE, SKIP, and the null
+        // statement
+        assert!(matches!(
+            stmt("return;"),
+            Ok(("", Stmt::Return(ReturnStmt(None))))
+        ));
+        assert!(matches!(
+            stmt("return (a + b);"),
+            Ok(("", Stmt::Return(ReturnStmt(Some(_)))))
+        ));
+        assert!(matches!(stmt("escape;"), Ok(("", Stmt::Escape))));
+        assert!(matches!(stmt("skip;"), Ok(("", Stmt::Skip))));
+        assert!(matches!(stmt(";"), Ok(("", Stmt::Null))));
+        assert!(stmt("escape").is_err());
+    }
+
     #[test]
     fn test_function_call() {
         let e = function_call(
@@ -3547,6 +4770,103 @@ end_function;  "#,
         assert_eq!(e.0, "");
     }
 
+    #[test]
+    fn test_keyword_prefixes() {
+        // Returns the first factor of an expression, and the operators which
+        // join the rest of it at each level
+        fn split(s: &str) -> (&str, String) {
+            let Expression(SimpleExpression(term, adds), rel) = parse_expression(s).unwrap();
+            let ops = format!(
+                "{:?} {:?} {:?}",
+                rel.map(|r| r.0),
+                adds.into_iter().map(|a| a.0).collect::<Vec<_>>(),
+                term.1.into_iter().map(|m| m.0).collect::<Vec<_>>()
+            );
+            let name = match term.0 .0 {
+                SimpleFactor::Unary(
+                    None,
+                    ExpressionOrPrimary::Primary(Primary::Qualifiable(
+                        QualifiableFactor::_Ambiguous(n),
+                        _,
+                    )),
+                ) => n,
+                SimpleFactor::Unary(Some(UnaryOp::Not), _) => "not",
+                f => panic!("unexpected factor {:?}", f),
+            };
+            (name, ops)
+        }
+
+        // Identifiers which start with word operators or built-ins aren't
+        // split up, and the operators which follow them are still found
+        assert_eq!(
+            split("order or origin"),
+            ("order", "None [Or] []".to_owned())
+        );
+        assert_eq!(
+            split("xorientation xor orientation"),
+            ("xorientation", "None [Xor] []".to_owned())
+        );
+        assert_eq!(
+            split("model mod modulus"),
+            ("model", "None [] [Mod]".to_owned())
+        );
+        assert_eq!(
+            split("divisor div dividend"),
+            ("divisor", "None [] [IntegerDiv]".to_owned())
+        );
+        assert_eq!(
+            split("notch and andorra"),
+            ("notch", "None [] [And]".to_owned())
+        );
+        assert_eq!(split("not notch"), ("not", "None [] []".to_owned()));
+        assert_eq!(
+            split("inner in inside"),
+            ("inner", "Some(In) [] []".to_owned())
+        );
+        assert_eq!(
+            split("likeness like likely"),
+            ("likeness", "Some(Like) [] []".to_owned())
+        );
+        for s in [
+            "order",
+            "origin",
+            "xorientation",
+            "model",
+            "divisor",
+            "notch",
+            "inner",
+            "likeness",
+            "andorra",
+            "sin2",
+            "abs_value",
+            "self2",
+            "pi_2",
+            "truex",
+            "unknowns",
+        ] {
+            assert_eq!(split(s), (s, "None [] []".to_owned()));
+        }
+
+        // Built-in functions with underscores in their names
+        assert!(matches!(
+            function_call("value_in(a, b)"),
+            Ok((
+                "",
+                FunctionCall(BuiltInOrFunctionRef::BuiltIn(BuiltInFunction::ValueIn), _)
+            ))
+        ));
+        assert!(matches!(
+            function_call("value_unique(a)"),
+            Ok((
+                "",
+                FunctionCall(
+                    BuiltInOrFunctionRef::BuiltIn(BuiltInFunction::ValueUnique),
+                    _
+                )
+            ))
+        ));
+    }
+
     #[test]
     fn test_actual_parameter_list() {
         let e = actual_parameter_list("(self, [])").unwrap();
@@ -3593,6 +4913,53 @@ end_function;  "#,
         assert_eq!(e.0, "");
     }
 
+    /// Returns the integer literal in a simple factor, for checking the
+    /// shape of parsed expressions
+    fn int(f: &SimpleFactor) -> Option<usize> {
+        match f {
+            SimpleFactor::Unary(
+                None,
+                ExpressionOrPrimary::Primary(Primary::Literal(Literal::Real(f))),
+            ) => Some(*f as usize),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_chains() {
+        // Mixed operators are grouped by precedence, and each level keeps
+        // every operand in order, to be folded from the left
+        let (rest, e) = simple_expression("1 + 2 * 3 - 4 / 5").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(int(&e.0 .0 .0), Some(1));
+        assert!(e.0 .1.is_empty());
+        assert_eq!(e.1.len(), 2);
+        assert!(matches!(e.1[0].0, AddLikeOp::Add));
+        assert!(matches!(e.1[1].0, AddLikeOp::Sub));
+        let (t, u) = (&e.1[0].1, &e.1[1].1);
+        assert_eq!((int(&t.0 .0), int(&t.1[0].1 .0)), (Some(2), Some(3)));
+        assert!(matches!(t.1[0].0, MultiplicationLikeOp::Mul));
+        assert_eq!((int(&u.0 .0), int(&u.1[0].1 .0)), (Some(4), Some(5)));
+        assert!(matches!(u.1[0].0, MultiplicationLikeOp::Div));
+
+        let (rest, t) = term("x * y * z * w").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(t.1.len(), 3);
+
+        // Exponents chain too, without being mistaken for multiplication
+        let (rest, f) = factor("2 ** 3 ** 2").unwrap();
+        assert_eq!(rest, "");
+        let exps: Vec<_> = f.1.iter().map(int).collect();
+        assert_eq!((int(&f.0), exps), (Some(2), vec![Some(3), Some(2)]));
+        let (rest, t) = term("2 ** 3 * 4 ** 5").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!((t.0 .1.len(), t.1.len(), t.1[0].1 .1.len()), (1, 1, 1));
+
+        // Nothing is left over for the surrounding parser
+        let e = parse_expression("a + b + c = a - -b * c ** 2 ** d").unwrap();
+        assert!(e.1.is_some());
+    }
+
     #[test]
     fn test_simple_factor() {
         let e = simple_factor("1 ").unwrap();
@@ -3642,6 +5009,35 @@ end_function;  "#,
     fn test_interval_op() {
         let e = interval_op("<= ").unwrap();
         assert_eq!(e.0, "");
+        assert!(matches!(e.1, IntervalOp::LessThanOrEqual));
+        assert!(matches!(interval_op("< "), Ok(("", IntervalOp::LessThan))));
+
+        let e = interval("{0 <= x <= 10}").unwrap();
+        assert_eq!(e.0, "");
+        assert!(matches!(e.1.op1, IntervalOp::LessThanOrEqual));
+        assert!(matches!(e.1.op2, IntervalOp::LessThanOrEqual));
+        let e = interval("{0 < x <= 10}").unwrap();
+        assert!(matches!(e.1.op1, IntervalOp::LessThan));
+        assert!(matches!(e.1.op2, IntervalOp::LessThanOrEqual));
+        let e = interval("{0 <= x < 10}").unwrap();
+        assert!(matches!(e.1.op1, IntervalOp::LessThanOrEqual));
+        assert!(matches!(e.1.op2, IntervalOp::LessThan));
+    }
+
+    #[test]
+    fn test_rel_op() {
+        let op = |s| match parse_expression(s) {
+            Ok(Expression(_, Some((RelOpExtended::RelOp(op), _)))) => op,
+            e => panic!("unexpected parse of {}: {:?}", s, e),
+        };
+        assert!(matches!(op("1 <= 2"), RelOp::LessThanOrEqual));
+        assert!(matches!(op("1 >= 2"), RelOp::GreaterThanOrEqual));
+        assert!(matches!(op("1 < 2"), RelOp::LessThan));
+        assert!(matches!(op("1 > 2"), RelOp::GreaterThan));
+        assert!(matches!(op("x = y"), RelOp::Equal));
+        assert!(matches!(op("x <> y"), RelOp::NotEqual));
+        assert!(matches!(op("a :=: b"), RelOp::InstanceEqual));
+        assert!(matches!(op("a :<>: b"), RelOp::InstanceNotEqual));
     }
 
     #[test]
@@ -3681,4 +5077,52 @@ end_function;  "#,
             SimpleId("action_property")
         );
     }
+    #[test]
+    fn test_remarks() {
+        let data = br#"SCHEMA Demo;
+(* An untagged remark *)
+TYPE label = STRING;
+END_TYPE;
+(*"demo.label" A human-readable Name.
+   Second line *)
+ENTITY product;
+  name : label; --"product.name" The product's Name
+END_ENTITY;
+(*" product.id The ID (quoted) "*)
+END_SCHEMA;
+"#;
+        let (s, remarks) = strip_comments_and_lower_with_remarks(data);
+        assert_eq!(s, strip_comments_and_lower(data));
+        assert!(!s.contains("name."));
+        assert_eq!(remarks.len(), 3);
+        assert_eq!(
+            remarks["demo.label"],
+            "A human-readable Name.\n   Second line"
+        );
+        assert_eq!(remarks["product.name"], "The product's Name");
+        assert_eq!(remarks["product.id"], "The ID (quoted)");
+
+        // Remarks for this schema lose their prefix once attached
+        let mut syntax = parse(&s).unwrap();
+        syntax.attach_remarks(remarks);
+        let keys: Vec<_> = syntax.0[0].remarks.keys().collect();
+        assert_eq!(keys, ["label", "product.id", "product.name"]);
+    }
+
+    #[test]
+    fn test_tagged_remark() {
+        assert_eq!(tagged_remark(b" not tagged "), None);
+        assert_eq!(tagged_remark(b"\"\" empty tag"), None);
+        assert_eq!(
+            tagged_remark(b"\"A.B\"text"),
+            Some(("a.b".to_owned(), "text".to_owned()))
+        );
+        // Repeated tags are joined into paragraphs
+        let (_, r) = strip_comments_and_lower_with_remarks(b"(*\"a\" one *)(*\"a\" two *)");
+        assert_eq!(r["a"], "one\n\ntwo");
+        // A remark at the end of the file is still stripped
+        let (s, r) = strip_comments_and_lower_with_remarks(b"a := 1; --\"a\" Trailing");
+        assert_eq!(s, "a := 1; ");
+        assert_eq!(r["a"], "Trailing");
+    }
 }
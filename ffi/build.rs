@@ -0,0 +1,8 @@
+fn main() {
+    // The C test compiles a program at runtime, which needs to know which
+    // target we're building for
+    println!(
+        "cargo:rustc-env=FOXTROT_FFI_TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+}
@@ -0,0 +1,158 @@
+#ifndef FOXTROT_H
+#define FOXTROT_H
+
+/* Generated by cbindgen from ffi/src/lib.rs; do not edit by hand.  Regenerate with `cbindgen --config cbindgen.toml --output include/foxtrot.h` in ffi/ */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of loading a file
+ */
+typedef enum FoxtrotStatus {
+  FOXTROT_STATUS_OK = 0,
+  /**
+   * The file could not be read
+   */
+  FOXTROT_STATUS_ERROR_IO,
+  /**
+   * The file is malformed or contains no STEP entities
+   */
+  FOXTROT_STATUS_ERROR_PARSE,
+  /**
+   * The file contains no triangulated geometry
+   */
+  FOXTROT_STATUS_ERROR_EMPTY,
+  /**
+   * The parser or triangulator panicked
+   */
+  FOXTROT_STATUS_ERROR_PANIC,
+  /**
+   * An argument was invalid (e.g. a null or non-UTF-8 path)
+   */
+  FOXTROT_STATUS_ERROR_INVALID_ARGUMENT,
+} FoxtrotStatus;
+
+/**
+ * A loaded mesh, or the error which prevented it from loading.  This is
+ * opaque to C, which only sees it through a pointer.
+ */
+typedef struct FoxtrotMesh FoxtrotMesh;
+
+/**
+ * Options for [`foxtrot_load`]
+ */
+typedef struct FoxtrotOptions {
+  /**
+   * Largest allowed distance between curved edges and the mesh, in model
+   * units.  Zero (or any non-positive value) uses the default sampling.
+   */
+  double tolerance;
+} FoxtrotOptions;
+
+/**
+ * A single part of a loaded mesh
+ */
+typedef struct FoxtrotPart {
+  /**
+   * Index of the part's first triangle
+   */
+  size_t first_triangle;
+  /**
+   * Number of triangles in the part
+   */
+  size_t triangle_count;
+  /**
+   * STEP entity index of the part's shape; parts which share a shape are
+   * instances of each other
+   */
+  size_t shape;
+  /**
+   * Column-major transform which placed the shape
+   */
+  double transform[16];
+} FoxtrotPart;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Loads and triangulates a STEP file.
+ *
+ * `path` must be a NUL-terminated UTF-8 string.  `options` may be null to
+ * use the defaults.
+ *
+ * This always returns a handle, even on failure; check it with
+ * [`foxtrot_status`], then release it with [`foxtrot_free`].
+ */
+struct FoxtrotMesh *foxtrot_load(const char *path, const struct FoxtrotOptions *options);
+
+/**
+ * Releases a handle from [`foxtrot_load`].  Null handles are ignored.
+ */
+void foxtrot_free(struct FoxtrotMesh *mesh);
+
+/**
+ * Returns whether the mesh loaded successfully.  A null handle is reported
+ * as an invalid argument.
+ */
+enum FoxtrotStatus foxtrot_status(const struct FoxtrotMesh *mesh);
+
+/**
+ * Returns a description of the error which prevented the mesh from
+ * loading, or null if it loaded successfully.  The string is owned by the
+ * handle.
+ */
+const char *foxtrot_error_message(const struct FoxtrotMesh *mesh);
+
+/**
+ * Returns the number of vertices in the mesh
+ */
+size_t foxtrot_vertex_count(const struct FoxtrotMesh *mesh);
+
+/**
+ * Returns vertex positions, as `3 * foxtrot_vertex_count` floats
+ */
+const float *foxtrot_positions(const struct FoxtrotMesh *mesh);
+
+/**
+ * Returns vertex normals, as `3 * foxtrot_vertex_count` floats
+ */
+const float *foxtrot_normals(const struct FoxtrotMesh *mesh);
+
+/**
+ * Returns vertex colors, as `3 * foxtrot_vertex_count` floats in the
+ * range 0-1
+ */
+const float *foxtrot_colors(const struct FoxtrotMesh *mesh);
+
+/**
+ * Returns the number of triangles in the mesh
+ */
+size_t foxtrot_triangle_count(const struct FoxtrotMesh *mesh);
+
+/**
+ * Returns triangles, as `3 * foxtrot_triangle_count` vertex indices
+ */
+const uint32_t *foxtrot_indices(const struct FoxtrotMesh *mesh);
+
+/**
+ * Returns the number of parts in the mesh
+ */
+size_t foxtrot_part_count(const struct FoxtrotMesh *mesh);
+
+/**
+ * Copies a part's description into `out`, returning `false` (and leaving
+ * `out` untouched) if the index is out of range or `out` is null
+ */
+bool foxtrot_part(const struct FoxtrotMesh *mesh, size_t index, struct FoxtrotPart *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FOXTROT_H */
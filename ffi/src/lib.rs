@@ -0,0 +1,369 @@
+//! C bindings for loading and triangulating STEP files.
+//!
+//! The generated header is `include/foxtrot.h`.  Every function catches
+//! panics, so errors never unwind into the caller's code.
+//!
+//! # Safety
+//! Every function which takes a pointer shares the same contract:  strings
+//! must be NUL-terminated, mesh handles must come from [`foxtrot_load`] and
+//! not yet be freed, and other non-null pointers must be valid for the
+//! access described in each function's documentation.  Null pointers are
+//! always allowed, and are reported as errors (or ignored).
+#![allow(clippy::missing_safety_doc)]
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use triangulate::load::{load_mesh, LoadError, TriangulateOptions};
+
+/// Result of loading a file
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FoxtrotStatus {
+    Ok = 0,
+    /// The file could not be read
+    ErrorIo,
+    /// The file is malformed or contains no STEP entities
+    ErrorParse,
+    /// The file contains no triangulated geometry
+    ErrorEmpty,
+    /// The parser or triangulator panicked
+    ErrorPanic,
+    /// An argument was invalid (e.g. a null or non-UTF-8 path)
+    ErrorInvalidArgument,
+}
+
+impl From<&LoadError> for FoxtrotStatus {
+    fn from(e: &LoadError) -> Self {
+        match e {
+            LoadError::Io { .. } => FoxtrotStatus::ErrorIo,
+            LoadError::Step(..) | LoadError::Parse | LoadError::Triangulate(..) => {
+                FoxtrotStatus::ErrorParse
+            }
+            LoadError::Empty => FoxtrotStatus::ErrorEmpty,
+            LoadError::Panicked(..) => FoxtrotStatus::ErrorPanic,
+        }
+    }
+}
+
+/// Options for [`foxtrot_load`]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FoxtrotOptions {
+    /// Largest allowed distance between curved edges and the mesh, in model
+    /// units.  Zero (or any non-positive value) uses the default sampling.
+    pub tolerance: f64,
+}
+
+/// A single part of a loaded mesh
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct FoxtrotPart {
+    /// Index of the part's first triangle
+    pub first_triangle: usize,
+    /// Number of triangles in the part
+    pub triangle_count: usize,
+    /// STEP entity index of the part's shape; parts which share a shape are
+    /// instances of each other
+    pub shape: usize,
+    /// Column-major transform which placed the shape
+    pub transform: [f64; 16],
+}
+
+/// A loaded mesh, or the error which prevented it from loading.  This is
+/// opaque to C, which only sees it through a pointer.
+pub struct FoxtrotMesh {
+    status: FoxtrotStatus,
+    error: Option<CString>,
+    positions: Vec<f32>,
+    normals: Vec<f32>,
+    colors: Vec<f32>,
+    indices: Vec<u32>,
+    parts: Vec<FoxtrotPart>,
+}
+
+impl FoxtrotMesh {
+    fn error(status: FoxtrotStatus, msg: &str) -> Self {
+        Self {
+            status,
+            // Interior NULs would truncate the message, so drop them
+            error: CString::new(msg.replace('\0', "")).ok(),
+            positions: vec![],
+            normals: vec![],
+            colors: vec![],
+            indices: vec![],
+            parts: vec![],
+        }
+    }
+
+    unsafe fn load(path: *const c_char, options: *const FoxtrotOptions) -> Self {
+        if path.is_null() {
+            return Self::error(FoxtrotStatus::ErrorInvalidArgument, "path is null");
+        }
+        let Ok(path) = CStr::from_ptr(path).to_str() else {
+            return Self::error(FoxtrotStatus::ErrorInvalidArgument, "path is not UTF-8");
+        };
+        let options = options.as_ref().copied().unwrap_or_default();
+        let opts = TriangulateOptions {
+            tolerance: (options.tolerance > 0.0).then_some(options.tolerance),
+            ..Default::default()
+        };
+
+        let mesh = match load_mesh(path, &opts) {
+            Ok((scene, _report)) => scene.mesh,
+            Err(e) => return Self::error((&e).into(), &e.to_string()),
+        };
+        let flat = |f: fn(&triangulate::mesh::Vertex) -> [f64; 3]| -> Vec<f32> {
+            mesh.verts.iter().flat_map(f).map(|f| f as f32).collect()
+        };
+        Self {
+            status: FoxtrotStatus::Ok,
+            error: None,
+            positions: flat(|v| v.pos.into()),
+            normals: flat(|v| v.norm.into()),
+            colors: flat(|v| v.color.into()),
+            indices: mesh
+                .triangles
+                .iter()
+                .flat_map(|t| t.verts.iter().copied())
+                .collect(),
+            parts: mesh
+                .parts
+                .iter()
+                .map(|p| FoxtrotPart {
+                    first_triangle: p.triangles.start,
+                    triangle_count: p.triangles.len(),
+                    shape: p.shape,
+                    transform: p.transform.as_slice().try_into().unwrap(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Runs a function on a (possibly null) mesh handle, returning `default`
+/// if the handle is null or the function panics
+unsafe fn with_mesh<T, F: FnOnce(&FoxtrotMesh) -> T>(
+    mesh: *const FoxtrotMesh,
+    default: T,
+    f: F,
+) -> T {
+    match mesh.as_ref() {
+        Some(m) => catch_unwind(AssertUnwindSafe(|| f(m))).unwrap_or(default),
+        None => default,
+    }
+}
+
+/// Loads and triangulates a STEP file.
+///
+/// `path` must be a NUL-terminated UTF-8 string.  `options` may be null to
+/// use the defaults.
+///
+/// This always returns a handle, even on failure; check it with
+/// [`foxtrot_status`], then release it with [`foxtrot_free`].
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_load(
+    path: *const c_char,
+    options: *const FoxtrotOptions,
+) -> *mut FoxtrotMesh {
+    let mesh = catch_unwind(|| FoxtrotMesh::load(path, options)).unwrap_or_else(|e| {
+        let msg = e
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown error".to_owned());
+        FoxtrotMesh::error(FoxtrotStatus::ErrorPanic, &msg)
+    });
+    Box::into_raw(Box::new(mesh))
+}
+
+/// Releases a handle from [`foxtrot_load`].  Null handles are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_free(mesh: *mut FoxtrotMesh) {
+    if !mesh.is_null() {
+        let _ = catch_unwind(|| drop(Box::from_raw(mesh)));
+    }
+}
+
+/// Returns whether the mesh loaded successfully.  A null handle is reported
+/// as an invalid argument.
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_status(mesh: *const FoxtrotMesh) -> FoxtrotStatus {
+    with_mesh(mesh, FoxtrotStatus::ErrorInvalidArgument, |m| m.status)
+}
+
+/// Returns a description of the error which prevented the mesh from
+/// loading, or null if it loaded successfully.  The string is owned by the
+/// handle.
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_error_message(mesh: *const FoxtrotMesh) -> *const c_char {
+    with_mesh(mesh, std::ptr::null(), |m| {
+        m.error.as_ref().map_or(std::ptr::null(), |e| e.as_ptr())
+    })
+}
+
+/// Returns the number of vertices in the mesh
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_vertex_count(mesh: *const FoxtrotMesh) -> usize {
+    with_mesh(mesh, 0, |m| m.positions.len() / 3)
+}
+
+/// Returns vertex positions, as `3 * foxtrot_vertex_count` floats
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_positions(mesh: *const FoxtrotMesh) -> *const f32 {
+    with_mesh(mesh, std::ptr::null(), |m| m.positions.as_ptr())
+}
+
+/// Returns vertex normals, as `3 * foxtrot_vertex_count` floats
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_normals(mesh: *const FoxtrotMesh) -> *const f32 {
+    with_mesh(mesh, std::ptr::null(), |m| m.normals.as_ptr())
+}
+
+/// Returns vertex colors, as `3 * foxtrot_vertex_count` floats in the
+/// range 0-1
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_colors(mesh: *const FoxtrotMesh) -> *const f32 {
+    with_mesh(mesh, std::ptr::null(), |m| m.colors.as_ptr())
+}
+
+/// Returns the number of triangles in the mesh
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_triangle_count(mesh: *const FoxtrotMesh) -> usize {
+    with_mesh(mesh, 0, |m| m.indices.len() / 3)
+}
+
+/// Returns triangles, as `3 * foxtrot_triangle_count` vertex indices
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_indices(mesh: *const FoxtrotMesh) -> *const u32 {
+    with_mesh(mesh, std::ptr::null(), |m| m.indices.as_ptr())
+}
+
+/// Returns the number of parts in the mesh
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_part_count(mesh: *const FoxtrotMesh) -> usize {
+    with_mesh(mesh, 0, |m| m.parts.len())
+}
+
+/// Copies a part's description into `out`, returning `false` (and leaving
+/// `out` untouched) if the index is out of range or `out` is null
+#[no_mangle]
+pub unsafe extern "C" fn foxtrot_part(
+    mesh: *const FoxtrotMesh,
+    index: usize,
+    out: *mut FoxtrotPart,
+) -> bool {
+    with_mesh(mesh, false, |m| match (m.parts.get(index), out.is_null()) {
+        (Some(p), false) => {
+            out.write(*p);
+            true
+        }
+        _ => false,
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a generated two-part assembly to a scratch path
+    fn fixture(name: &str) -> std::path::PathBuf {
+        use step::fixture;
+        let path =
+            std::env::temp_dir().join(format!("foxtrot-ffi-{}-{}.step", std::process::id(), name));
+        let asm = fixture::assembly(vec![
+            fixture::cube(1.0),
+            fixture::cylinder(1.0, 2.0).translate([3.0, 0.0, 0.0]),
+        ]);
+        std::fs::write(&path, asm.to_step()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load() {
+        unsafe {
+            let path = fixture("load");
+            let c_path = CString::new(path.to_str().unwrap()).unwrap();
+            let mesh = foxtrot_load(c_path.as_ptr(), std::ptr::null());
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(foxtrot_status(mesh), FoxtrotStatus::Ok);
+            assert!(foxtrot_error_message(mesh).is_null());
+
+            let nv = foxtrot_vertex_count(mesh);
+            let nt = foxtrot_triangle_count(mesh);
+            assert!(nv > 0 && nt > 0);
+            let indices = std::slice::from_raw_parts(foxtrot_indices(mesh), nt * 3);
+            assert!(indices.iter().all(|i| (*i as usize) < nv));
+            let normals = std::slice::from_raw_parts(foxtrot_normals(mesh), nv * 3);
+            assert!(normals.iter().all(|f| f.is_finite()));
+
+            // The parts cover every triangle
+            let mut part = FoxtrotPart {
+                first_triangle: 0,
+                triangle_count: 0,
+                shape: 0,
+                transform: [0.0; 16],
+            };
+            let mut total = 0;
+            for i in 0..foxtrot_part_count(mesh) {
+                assert!(foxtrot_part(mesh, i, &mut part));
+                assert_eq!(part.first_triangle, total);
+                total += part.triangle_count;
+            }
+            assert_eq!(total, nt);
+            assert_eq!(foxtrot_part_count(mesh), 2);
+            assert!(!foxtrot_part(mesh, foxtrot_part_count(mesh), &mut part));
+            assert!(!foxtrot_part(mesh, 0, std::ptr::null_mut()));
+            foxtrot_free(mesh);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        unsafe {
+            let path = CString::new("does/not/exist.step").unwrap();
+            let mesh = foxtrot_load(path.as_ptr(), std::ptr::null());
+            assert_eq!(foxtrot_status(mesh), FoxtrotStatus::ErrorIo);
+            let msg = CStr::from_ptr(foxtrot_error_message(mesh));
+            assert!(!msg.to_bytes().is_empty());
+            assert_eq!(foxtrot_triangle_count(mesh), 0);
+            foxtrot_free(mesh);
+
+            let mesh = foxtrot_load(std::ptr::null(), std::ptr::null());
+            assert_eq!(foxtrot_status(mesh), FoxtrotStatus::ErrorInvalidArgument);
+            foxtrot_free(mesh);
+
+            // Null handles are treated as empty, rather than crashing
+            let null = std::ptr::null();
+            assert_eq!(foxtrot_status(null), FoxtrotStatus::ErrorInvalidArgument);
+            assert_eq!(foxtrot_vertex_count(null), 0);
+            assert!(foxtrot_positions(null).is_null());
+            foxtrot_free(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_panics_are_caught() {
+        unsafe {
+            let mesh = FoxtrotMesh::error(FoxtrotStatus::Ok, "");
+            let r = with_mesh(&mesh, 7, |_| -> i32 { panic!("oh no") });
+            assert_eq!(r, 7);
+        }
+    }
+
+    #[test]
+    fn test_header_is_current() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", dir)).unwrap();
+        let mut generated = vec![];
+        cbindgen::generate_with_config(dir, config)
+            .unwrap()
+            .write(&mut generated);
+        let header = std::fs::read(format!("{}/include/foxtrot.h", dir)).unwrap();
+        assert!(
+            generated == header,
+            "include/foxtrot.h is out of date; regenerate it with cbindgen"
+        );
+    }
+}
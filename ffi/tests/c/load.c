@@ -0,0 +1,60 @@
+/* Loads a STEP file through the C API and checks the resulting counts.
+ * Usage: load path/to/file.step */
+#include <stdio.h>
+
+#include "foxtrot.h"
+
+#define CHECK(cond)                                                  \
+    do {                                                             \
+        if (!(cond)) {                                               \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,   \
+                    __LINE__, #cond);                                \
+            return 1;                                                \
+        }                                                            \
+    } while (0)
+
+int main(int argc, char** argv) {
+    CHECK(argc == 2);
+
+    FoxtrotOptions options = { .tolerance = 0.0 };
+    FoxtrotMesh* mesh = foxtrot_load(argv[1], &options);
+    CHECK(mesh != NULL);
+    if (foxtrot_status(mesh) != FOXTROT_STATUS_OK) {
+        fprintf(stderr, "load failed: %s\n", foxtrot_error_message(mesh));
+        foxtrot_free(mesh);
+        return 1;
+    }
+
+    const size_t nv = foxtrot_vertex_count(mesh);
+    const size_t nt = foxtrot_triangle_count(mesh);
+    CHECK(nv > 0);
+    CHECK(nt > 0);
+    CHECK(foxtrot_positions(mesh) != NULL);
+    CHECK(foxtrot_normals(mesh) != NULL);
+
+    const uint32_t* indices = foxtrot_indices(mesh);
+    for (size_t i = 0; i < nt * 3; ++i) {
+        CHECK(indices[i] < nv);
+    }
+
+    size_t total = 0;
+    FoxtrotPart part;
+    for (size_t i = 0; i < foxtrot_part_count(mesh); ++i) {
+        CHECK(foxtrot_part(mesh, i, &part));
+        CHECK(part.first_triangle == total);
+        total += part.triangle_count;
+    }
+    CHECK(total == nt);
+    CHECK(!foxtrot_part(mesh, foxtrot_part_count(mesh), &part));
+    foxtrot_free(mesh);
+
+    /* Errors are reported through the handle */
+    mesh = foxtrot_load("does/not/exist.step", NULL);
+    CHECK(foxtrot_status(mesh) == FOXTROT_STATUS_ERROR_IO);
+    CHECK(foxtrot_error_message(mesh) != NULL);
+    CHECK(foxtrot_triangle_count(mesh) == 0);
+    foxtrot_free(mesh);
+
+    printf("%zu vertices, %zu triangles\n", nv, nt);
+    return 0;
+}
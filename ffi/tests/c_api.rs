@@ -0,0 +1,53 @@
+//! Compiles a C program against the generated header and shared library,
+//! then runs it on a fixture.
+#![cfg(unix)]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn test_c_program() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    // Test executables live in target/<profile>/deps, next to the shared
+    // library
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+
+    let target = env!("FOXTROT_FFI_TARGET");
+    let compiler = cc::Build::new()
+        .cargo_metadata(false)
+        .target(target)
+        .host(target)
+        .opt_level(0)
+        .get_compiler();
+    let out = std::env::temp_dir().join(format!("foxtrot-ffi-{}-load", std::process::id()));
+    let status = compiler
+        .to_command()
+        .arg(dir.join("tests/c/load.c"))
+        .arg("-std=c99")
+        .arg("-I")
+        .arg(dir.join("include"))
+        .arg("-L")
+        .arg(lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lfoxtrot_ffi")
+        .arg("-o")
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success(), "Failed to compile C test");
+
+    let fixture =
+        std::env::temp_dir().join(format!("foxtrot-ffi-{}-fixture.step", std::process::id()));
+    std::fs::write(&fixture, step::fixture::filleted_block(10.0, 2.0).to_step()).unwrap();
+    let result = Command::new(&out).arg(&fixture).output().unwrap();
+    std::fs::remove_file(&out).unwrap();
+    std::fs::remove_file(&fixture).unwrap();
+    assert!(
+        result.status.success(),
+        "C test failed:\n{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    assert!(String::from_utf8_lossy(&result.stdout).contains("triangles"));
+}
@@ -0,0 +1,9 @@
+#![no_main]
+
+use express::parse::{parse, strip_comments_and_lower};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let s = strip_comments_and_lower(data);
+    let _ = parse(&s);
+});
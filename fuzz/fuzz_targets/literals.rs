@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|s: &str| {
+    step::parse::fuzz_literals(s);
+    express::parse::fuzz_literals(s);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use step::step_file::StepFile;
+
+fuzz_target!(|data: &[u8]| {
+    let flat = StepFile::strip_flatten(data);
+    let _ = StepFile::parse(&flat);
+});
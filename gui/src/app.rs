@@ -1,32 +1,191 @@
-use glm::Vec2;
+use glm::{Vec2, Vec3};
 use nalgebra_glm as glm;
 use winit::{
     dpi::PhysicalSize,
     event::{
-        DeviceEvent, ElementState, ModifiersState, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+        DeviceEvent, ElementState, ModifiersState, MouseButton, MouseScrollDelta, WindowEvent,
     },
+    event_loop::EventLoopProxy,
+    window::Window,
 };
 
-use crate::{backdrop::Backdrop, camera::Camera, model::Model};
-use triangulate::mesh::Mesh;
+use crate::{
+    backdrop::Backdrop,
+    bookmarks::{Bookmarks, TRANSITION_TIME},
+    camera::{Camera, Click, DoubleClick},
+    chunks::ChunkBudget,
+    debug_colors::{face_colors, ColorMode},
+    diagnostics::{highlight_parts, Diagnostics, WARNING_COLOR},
+    edges::EdgeLines,
+    export::{export_visible, pick_export_path},
+    gizmo::{corner_viewport, Gizmo},
+    grid::{Grid, GridLayout},
+    inspect::{NormalLines, NORMAL_LENGTH},
+    keymap::{Action, Keymap},
+    lines::LineTarget,
+    loader::{spawn, spawn_retriangulate, LoadEvent},
+    material::Material,
+    measure::{measure_text, Target},
+    model::{Culling, Model},
+    outline::Outline,
+    passes::{plan, FrameContents, Pass},
+    pick::{pick_face, pick_part, pick_point},
+    present::{next_present_mode, select_present_mode, supported_present_modes, FrameRate},
+    profile::Profile,
+    refine::{part_faces, requests},
+    screenshot::Capture,
+    section::Section,
+    selection::Selection,
+    startup::Startup,
+    swapchain::{self, Acquired, FrameSource},
+    text::{TextOverlay, LINE_HEIGHT},
+    theme::Themes,
+    tile::{label_anchor, Slot, LABEL_GAP, LABEL_SIZE},
+    title::{file_name, status_text, window_title, with_frame_rate, Status},
+    visibility::{visible_bounds, Visibility},
+    wires::Wires,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use triangulate::{mesh::Mesh, stats::Stats, surface::SurfaceType};
 
 pub struct App {
-    start_time: std::time::SystemTime,
+    /// Startup timings, which are finished after the first frame
+    profile: Profile,
 
-    surface: wgpu::Surface,
+    /// Window surface, which is `None` when drawing headless
+    surface: Option<wgpu::Surface>,
+    /// Color target which frames are drawn into when there's no window
+    offscreen: Option<Capture>,
     device: wgpu::Device,
     swapchain_format: wgpu::TextureFormat,
-    swapchain: wgpu::SwapChain,
+    swapchain: Option<wgpu::SwapChain>,
+    present_mode: wgpu::PresentMode,
+    /// Present modes which can be selected at runtime
+    present_modes: &'static [wgpu::PresentMode],
+    frame_rate: FrameRate,
+    /// File name of the model, shown in the title bar
+    name: String,
+    /// Title describing the model (or loading progress), before the frame
+    /// rate is appended
+    status_title: String,
+    /// Loading status, which is shown in the viewport until the model is
+    /// drawn
+    status_text: String,
+    /// New window title, which the event loop picks up with
+    /// [`App::take_title`]
+    title: Option<String>,
 
-    loader: Option<std::thread::JoinHandle<Mesh>>,
+    /// Mesh from the loader thread, which is uploaded on the next redraw
+    pending: Option<(Mesh, Stats, Vec<Slot>)>,
+    /// Chunks of a model which is still loading, which are appended to the
+    /// preview on the next redraw
+    streamed: Vec<Mesh>,
     model: Option<Model>,
+    /// Whether `model` is a preview built from streamed chunks, which is
+    /// replaced once the whole mesh arrives
+    previewing: bool,
+
+    /// CPU-side copy of the mesh, used for picking
+    mesh: Option<Mesh>,
+    /// Input files which make up the mesh, which are labelled when there's
+    /// more than one
+    slots: Vec<Slot>,
+    /// Surface type of each face, from the triangulation statistics
+    surface_types: HashMap<usize, SurfaceType>,
+    color_mode: ColorMode,
+    visibility: Visibility,
+    selection: Selection,
+    /// Area (and volume) of the selected face or part, which is shown in
+    /// the viewport
+    measurement: Option<String>,
+    /// Face or part which is measured, along with the part it belongs to
+    measured: Option<(usize, Target)>,
+    /// Whether the next click selects a whole part (rather than a face) for
+    /// measurement, which is set by double-clicking
+    select_part: bool,
+    /// Problems from loading the model, which are shown in a banner
+    diagnostics: Diagnostics,
+    /// Rigid transform which moved the mesh onto its principal axes, if
+    /// it's been aligned, so that it can be moved back
+    alignment: Option<glm::DMat4>,
+    /// Transform which is applied to the mesh (and uploaded) on the next
+    /// redraw
+    realign: Option<glm::DMat4>,
+    /// Sends faces to be triangulated again (or new files to be loaded) in
+    /// the background, with the results coming back as load events
+    loader: Option<EventLoopProxy<LoadEvent>>,
+    /// Directory of cached meshes, used when loading a dropped file
+    cache: Option<PathBuf>,
+    /// Whether the loader thread is still working on the model
+    loading: bool,
+    /// Tolerance which each face (by STEP entity index) was last
+    /// triangulated with, when it's been changed at runtime
+    tolerances: HashMap<usize, f64>,
+    /// Faces which were triangulated again, which are spliced into the mesh
+    /// on the next redraw
+    spliced: Vec<(Mesh, Stats)>,
+    /// Outlines the selected parts
+    outline: Outline,
+    text: TextOverlay,
     backdrop: Backdrop,
+    /// Whether to draw the background gradient, rather than clearing to
+    /// transparent black
+    show_backdrop: bool,
+    /// Light and dark color themes, toggled at runtime
+    themes: Themes,
     camera: Camera,
 
-    depth: (wgpu::Texture, wgpu::TextureView),
+    bookmarks: Bookmarks,
+
+    /// Reference grid, which is sized once the model is loaded
+    grid: Option<Grid>,
+    gizmo: Gizmo,
+    /// Whether to draw the grid and axis gizmo
+    show_grid: bool,
+
+    /// Vertex normal overlay, which is built once the model is loaded
+    normals: Option<NormalLines>,
+    show_normals: bool,
+    /// Boundary and feature edge overlay, which is built once the model is
+    /// loaded
+    edges: Option<EdgeLines>,
+    show_edges: bool,
+    /// Curves which weren't triangulated, if the model has any
+    wires: Option<Wires>,
+    /// Whether to tint the model by its curvature
+    show_curvature: bool,
+    /// Back-face culling mode, which is picked when the model is loaded
+    culling: Culling,
+    /// Shading and lighting of the model
+    material: Material,
+    /// Whether parts without a color are given palette colors
+    show_palette: bool,
+    /// Section plane which cuts the model open, if any
+    section: Option<Section>,
+    /// Cursor height (in physical pixels) while the section plane is being
+    /// dragged with Alt held down
+    section_drag: Option<f32>,
+    /// Largest chunk of the model's geometry which is put into one vertex
+    /// and index buffer
+    chunk_budget: ChunkBudget,
+
+    depth: Option<(wgpu::Texture, wgpu::TextureView)>,
     size: PhysicalSize<u32>,
+    scale_factor: f64,
 
     modifiers: ModifiersState,
+    keymap: Keymap,
+    /// Most recent cursor position, in physical pixels
+    cursor: Option<Vec2>,
+    /// Double-clicking on the model sets the camera's orbit target
+    double_click: DoubleClick,
+    /// Clicking on a part selects it
+    click: Click,
+
+    /// Settings from the command line which are applied once the model is
+    /// loaded, then cleared after the first frame which shows all of it
+    startup: Option<Startup>,
 
     first_frame: bool,
 }
@@ -37,49 +196,251 @@ pub enum Reply {
     Quit,
 }
 
+/// Where the app draws its frames
+pub enum RenderTarget<'a> {
+    /// A window, through its surface's swapchain
+    Window(&'a Window, wgpu::Surface),
+    /// An offscreen texture of the given size, which needs no display
+    Offscreen(PhysicalSize<u32>),
+}
+
+/// Color format of the offscreen target, which matches the format that
+/// swapchains usually prefer, so that headless frames look the same
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
 impl App {
     pub fn new(
-        start_time: std::time::SystemTime,
-        size: PhysicalSize<u32>,
+        profile: Profile,
+        target: RenderTarget,
         adapter: wgpu::Adapter,
-        surface: wgpu::Surface,
         device: wgpu::Device,
-        loader: std::thread::JoinHandle<Mesh>,
+        name: String,
+        bookmarks: Bookmarks,
     ) -> Self {
-        let swapchain_format = adapter
-            .get_swap_chain_preferred_format(&surface)
-            .expect("Could not get swapchain format");
+        let (size, scale_factor, swapchain_format, surface) = match target {
+            RenderTarget::Window(window, surface) => {
+                let format = adapter
+                    .get_swap_chain_preferred_format(&surface)
+                    .expect("Could not get swapchain format");
+                (
+                    window.inner_size(),
+                    window.scale_factor(),
+                    format,
+                    Some(surface),
+                )
+            }
+            RenderTarget::Offscreen(size) => (size, 1.0, OFFSCREEN_FORMAT, None),
+        };
+        let present_modes = supported_present_modes(adapter.get_info().backend);
+        let present_mode = select_present_mode(wgpu::PresentMode::Mailbox, present_modes);
 
-        let swapchain = Self::rebuild_swapchain_(size, swapchain_format, &surface, &device);
-        let depth = Self::rebuild_depth_(size, &device);
-        let backdrop = Backdrop::new(&device, swapchain_format);
+        // The window may start out minimized, in which case we defer
+        // building the swapchain and depth buffer until it has a real size.
+        let (swapchain, depth) = if swapchain::is_drawable(size) {
+            (
+                surface.as_ref().map(|surface| {
+                    Self::rebuild_swapchain_(size, swapchain_format, present_mode, surface, &device)
+                }),
+                Some(Self::rebuild_depth_(size, &device)),
+            )
+        } else {
+            (None, None)
+        };
+        let offscreen = match surface {
+            Some(_) => None,
+            None => Some(Capture::new(&device, swapchain_format, size)),
+        };
+        let themes = Themes::default();
+        let backdrop = Backdrop::new(&device, swapchain_format, themes.theme());
+        let gizmo = Gizmo::new(&device, swapchain_format);
+        let mut outline = Outline::new(&device, swapchain_format);
+        let mut text = TextOverlay::new(&device, swapchain_format);
+        text.set_viewport(size, scale_factor);
+        if swapchain::is_drawable(size) {
+            outline.resize(&device, size);
+        }
 
         Self {
-            start_time,
+            profile,
 
             swapchain,
+            present_mode,
+            present_modes,
+            frame_rate: FrameRate::default(),
+            status_title: window_title(&name, &Status::Loading(None)),
+            status_text: status_text(&Status::Loading(None)),
+            name,
+            title: None,
             depth,
             backdrop,
+            show_backdrop: true,
+            themes,
+            grid: None,
+            gizmo,
+            show_grid: true,
+            normals: None,
+            show_normals: false,
+            edges: None,
+            show_edges: true,
+            wires: None,
+            show_curvature: false,
+            culling: Culling::Back,
+            material: Material::default(),
+            show_palette: true,
+            section: None,
+            section_drag: None,
+            chunk_budget: ChunkBudget::default(),
             swapchain_format,
-            loader: Some(loader),
+            pending: None,
+            streamed: vec![],
             model: None,
+            previewing: false,
+            mesh: None,
+            slots: vec![],
+            surface_types: HashMap::new(),
+            color_mode: ColorMode::Normal,
+            visibility: Visibility::new(0),
+            selection: Selection::new(0),
+            measurement: None,
+            measured: None,
+            select_part: false,
+            diagnostics: Diagnostics::default(),
+            alignment: None,
+            realign: None,
+            loader: None,
+            cache: None,
+            loading: true,
+            tolerances: HashMap::new(),
+            spliced: vec![],
+            outline,
+            text,
             camera: Camera::new(size.width as f32, size.height as f32),
+            bookmarks,
             surface,
+            offscreen,
             device,
             size,
+            scale_factor,
 
             modifiers: ModifiersState::empty(),
+            keymap: Keymap::default(),
+            cursor: None,
+            double_click: DoubleClick::default(),
+            click: Click::default(),
+
+            startup: None,
 
             first_frame: true,
         }
     }
 
+    /// Replaces the color themes (e.g. with ones loaded from a config file),
+    /// which take effect on the next redraw
+    pub fn set_themes(&mut self, themes: Themes) {
+        self.themes = themes;
+    }
+
+    /// Switches to a new present mode (falling back to FIFO if it isn't
+    /// supported), rebuilding the swapchain
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
+        self.present_mode = select_present_mode(mode, self.present_modes);
+        if self.swapchain.is_some() {
+            self.resize(self.size);
+        }
+    }
+
+    /// Sets the size of the chunks into which models are split when they're
+    /// uploaded to the GPU, which takes effect on the next load
+    pub fn set_chunk_budget(&mut self, budget: ChunkBudget) {
+        self.chunk_budget = budget;
+    }
+
+    /// Replaces the keyboard shortcuts (e.g. with ones from `keymap.toml`)
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// Sets up the initial view from the command line.  Overlays are toggled
+    /// right away, while the camera is moved once the model has loaded.
+    pub fn set_startup(&mut self, startup: Startup) {
+        self.show_backdrop = startup.backdrop;
+        self.show_grid = startup.grid;
+        self.show_edges = startup.edges;
+        self.startup = Some(startup);
+    }
+
+    /// Lets the app start background work (e.g. triangulating faces again,
+    /// or loading a dropped file), which reports back through the event loop
+    pub fn set_loader(&mut self, proxy: EventLoopProxy<LoadEvent>, cache: Option<PathBuf>) {
+        self.loader = Some(proxy);
+        self.cache = cache;
+    }
+
+    /// Returns a new window title, if one is ready
+    pub fn take_title(&mut self) -> Option<String> {
+        self.title.take()
+    }
+
+    fn set_status(&mut self, status: Status) {
+        self.status_text = status_text(&status);
+        self.status_title = window_title(&self.name, &status);
+        self.title = Some(self.status_title.clone());
+    }
+
+    /// Handles progress and results from the loader thread
+    pub fn load_event(&mut self, e: LoadEvent) -> Reply {
+        match e {
+            // The status is also shown in the viewport, so we redraw
+            LoadEvent::Progress(p) => {
+                self.set_status(Status::Loading(Some(p)));
+                Reply::Redraw
+            }
+            // Chunks which arrive after the final mesh are already part of
+            // it, so they're dropped
+            LoadEvent::Chunk(m) => {
+                if self.pending.is_none() {
+                    self.streamed.push(*m);
+                }
+                Reply::Redraw
+            }
+            LoadEvent::Loaded(m) => {
+                self.set_status(Status::loaded(&m.0));
+                self.loading = false;
+                self.pending = Some(*m);
+                self.streamed.clear();
+                Reply::Redraw
+            }
+            LoadEvent::Failed(e) => {
+                eprintln!("Error: could not load {}: {}", self.name, e);
+                self.loading = false;
+                // Hints (e.g. unsupported geometry in an empty model) are
+                // on the lines after the error, which only fit in the
+                // viewport
+                let hints: String = e.lines().skip(1).map(|l| format!("\n{}", l)).collect();
+                self.set_status(Status::Failed(e));
+                self.status_text += &hints;
+                if self.startup.as_ref().is_some_and(|s| s.exit) {
+                    Reply::Quit
+                } else {
+                    Reply::Redraw
+                }
+            }
+            LoadEvent::Retriangulated(m) => {
+                self.spliced.push(*m);
+                Reply::Redraw
+            }
+        }
+    }
+
     pub fn device_event(&mut self, e: DeviceEvent) {
         if let DeviceEvent::MouseWheel {
             delta: MouseScrollDelta::PixelDelta(p),
         } = e
         {
-            self.camera.mouse_scroll(p.y as f32);
+            // Pixel deltas are physical, so we convert them to logical units
+            // to keep the zoom speed consistent across displays
+            self.camera
+                .mouse_scroll(p.to_logical::<f32>(self.scale_factor).y);
         }
     }
 
@@ -89,33 +450,84 @@ impl App {
                 self.resize(size);
                 Reply::Redraw
             }
-            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                // `new_inner_size` is the window's new size in physical
+                // pixels, which is what the swapchain and camera expect.
+                self.scale_factor = scale_factor;
                 self.resize(*new_inner_size);
                 Reply::Redraw
             }
             WindowEvent::CloseRequested => Reply::Quit,
+            WindowEvent::DroppedFile(path) => self.open(path),
             WindowEvent::ModifiersChanged(m) => {
                 self.modifiers = m;
                 Reply::Continue
             }
             WindowEvent::KeyboardInput { input, .. } => {
-                if self.modifiers.logo() && input.virtual_keycode == Some(VirtualKeyCode::Q) {
-                    Reply::Quit
-                } else {
-                    Reply::Continue
+                if input.state != ElementState::Pressed {
+                    return Reply::Continue;
+                }
+                match input.virtual_keycode {
+                    Some(key) => match self.keymap.action(key, self.modifiers) {
+                        Some(action) => self.run_action(action),
+                        None => Reply::Continue,
+                    },
+                    None => Reply::Continue,
                 }
             }
             WindowEvent::MouseInput { button, state, .. } => {
                 use ElementState::*;
+                let pos = self.cursor.unwrap_or_else(Vec2::zeros);
+                // Alt+dragging moves the section plane, rather than the
+                // camera, and doesn't select anything
+                if button == MouseButton::Left {
+                    match state {
+                        Pressed if self.modifiers.alt() && self.section.is_some() => {
+                            self.section_drag = Some(pos.y);
+                            return Reply::Continue;
+                        }
+                        Released if self.section_drag.take().is_some() => return Reply::Redraw,
+                        _ => (),
+                    }
+                }
                 match state {
                     Pressed => self.camera.mouse_pressed(button),
                     Released => self.camera.mouse_released(button),
                 }
-                Reply::Continue
+                if state == Released && button == MouseButton::Left && self.click.release(pos) {
+                    self.select_hovered();
+                }
+                if state == Pressed && button == MouseButton::Left {
+                    self.click.press(pos);
+                    let now = std::time::Instant::now();
+                    if self.double_click.click(now, pos) {
+                        self.select_part = true;
+                        if let Some(p) = self.hovered_point() {
+                            return self.retarget(p);
+                        }
+                    }
+                }
+                // Releasing a drag may start inertia, which is animated
+                // by redrawing
+                if state == Released {
+                    Reply::Redraw
+                } else {
+                    Reply::Continue
+                }
             }
             WindowEvent::CursorMoved { position, .. } => {
-                self.camera
-                    .mouse_move(Vec2::new(position.x as f32, position.y as f32));
+                // Cursor positions are in physical pixels, matching the
+                // camera's width and height.
+                let pos = Vec2::new(position.x as f32, position.y as f32);
+                self.cursor = Some(pos);
+                self.camera.mouse_move(pos);
+                if let Some(y) = self.section_drag {
+                    self.section_drag = Some(pos.y);
+                    self.drag_section(pos.y - y);
+                }
                 Reply::Redraw
             }
             WindowEvent::MouseWheel { delta, .. } => {
@@ -128,11 +540,535 @@ impl App {
         }
     }
 
+    /// Replaces the model with a file which was dropped onto the window.
+    /// Drops are ignored until the current model has finished loading (or
+    /// failed to), since the loader's events can't be told apart.
+    fn open(&mut self, path: PathBuf) -> Reply {
+        let proxy = match &self.loader {
+            Some(p) if !self.loading => p.clone(),
+            _ => return Reply::Continue,
+        };
+        let input = path.to_string_lossy().into_owned();
+        self.name = file_name(&input);
+        self.bookmarks = Bookmarks::load(&path);
+        self.loading = true;
+        self.pending = None;
+        self.streamed.clear();
+        self.model = None;
+        self.previewing = false;
+        self.mesh = None;
+        self.slots.clear();
+        self.visibility = Visibility::new(0);
+        self.selection = Selection::new(0);
+        self.measurement = None;
+        self.measured = None;
+        self.diagnostics = Diagnostics::default();
+        self.alignment = None;
+        self.realign = None;
+        self.tolerances.clear();
+        self.spliced.clear();
+        self.normals = None;
+        self.edges = None;
+        self.wires = None;
+        self.grid = None;
+        self.section = None;
+        self.section_drag = None;
+        self.set_status(Status::Loading(None));
+        spawn(
+            vec![input],
+            self.profile.clone(),
+            proxy,
+            None,
+            self.cache.clone(),
+        );
+        Reply::Redraw
+    }
+
+    /// Handles a keyboard shortcut
+    fn run_action(&mut self, action: Action) -> Reply {
+        match action {
+            Action::Quit => Reply::Quit,
+            Action::ResetTarget => {
+                let target = self.camera.default_target();
+                self.retarget(target)
+            }
+            Action::HidePart => {
+                if let Some(i) = self.hovered_part() {
+                    self.visibility.hide(i);
+                }
+                Reply::Redraw
+            }
+            Action::IsolatePart => {
+                if let Some(i) = self.hovered_part() {
+                    self.visibility.isolate(i);
+                    self.measure(Some((i, Target::Part(i))));
+                    self.fit_visible(true);
+                }
+                Reply::Redraw
+            }
+            Action::UnhideAll => {
+                self.visibility.unhide_all();
+                Reply::Redraw
+            }
+            Action::CycleColorMode => {
+                self.color_mode = self.color_mode.next();
+                Reply::Redraw
+            }
+            Action::Export => {
+                self.export();
+                Reply::Continue
+            }
+            Action::FitView => {
+                self.fit_visible(true);
+                Reply::Redraw
+            }
+            Action::ToggleNormals => {
+                self.show_normals = !self.show_normals;
+                Reply::Redraw
+            }
+            Action::ToggleEdges => {
+                self.show_edges = !self.show_edges;
+                Reply::Redraw
+            }
+            Action::ToggleCurvature => {
+                self.show_curvature = !self.show_curvature;
+                Reply::Redraw
+            }
+            Action::CycleMaterial => {
+                self.material = self.material.next();
+                Reply::Redraw
+            }
+            Action::ToggleHeadlight => {
+                self.material = self.material.toggle_light();
+                Reply::Redraw
+            }
+            Action::TogglePalette => {
+                self.show_palette = !self.show_palette;
+                Reply::Redraw
+            }
+            Action::ToggleTheme => {
+                self.themes.toggle();
+                Reply::Redraw
+            }
+            Action::CyclePresentMode => {
+                self.set_present_mode(next_present_mode(self.present_mode, self.present_modes));
+                Reply::Redraw
+            }
+            Action::ToggleSection => {
+                self.section_drag = None;
+                self.section = match (&self.section, &self.mesh, &self.model) {
+                    (None, Some(mesh), Some(model)) => {
+                        // The plane starts through the middle of the model,
+                        // cutting away the half which faces the viewer
+                        let to_f64 = |v: Vec3| glm::DVec3::new(v.x as f64, v.y as f64, v.z as f64);
+                        let center = model.bounding_sphere().0;
+                        let toward = self.camera.rotation_matrix().transpose() * glm::Vec4::z();
+                        Some(Section::new(mesh, to_f64(center), to_f64(toward.xyz())))
+                    }
+                    _ => None,
+                };
+                Reply::Redraw
+            }
+            Action::ToggleCulling => {
+                self.culling = self.culling.toggle();
+                Reply::Redraw
+            }
+            Action::ToggleGrid => {
+                self.show_grid = !self.show_grid;
+                Reply::Redraw
+            }
+            Action::NextDiagnostic => {
+                let parts = match (self.diagnostics.next(), &self.mesh) {
+                    (Some(d), Some(mesh)) => d
+                        .entity
+                        .map(|e| highlight_parts(mesh, e))
+                        .unwrap_or_default(),
+                    _ => vec![],
+                };
+                self.selection.select_only(None);
+                for p in parts {
+                    self.selection.toggle(p);
+                }
+                self.measure(None);
+                Reply::Redraw
+            }
+            Action::DismissDiagnostics => {
+                if self.diagnostics.dismiss() {
+                    Reply::Redraw
+                } else {
+                    Reply::Continue
+                }
+            }
+            Action::AlignPrincipalAxes => {
+                self.realign = match (&self.alignment, &self.mesh) {
+                    (Some(mat), _) => mat.try_inverse(),
+                    (None, Some(mesh)) => mesh.align_to_principal_axes(),
+                    (None, None) => None,
+                };
+                if self.realign.is_some() {
+                    Reply::Redraw
+                } else {
+                    Reply::Continue
+                }
+            }
+            Action::RefineFaces => self.retriangulate(0.5),
+            Action::CoarsenFaces => self.retriangulate(2.0),
+            Action::SaveBookmark(slot) => {
+                self.bookmarks.set(slot, self.camera.state());
+                Reply::Continue
+            }
+            Action::Bookmark(slot) => self.bookmark(slot),
+            Action::ToggleSlot(slot) => match self.slots.get(slot as usize - 1) {
+                Some(s) if self.slots.len() > 1 => {
+                    self.visibility.toggle_range(s.parts.clone());
+                    Reply::Redraw
+                }
+                _ => Reply::Continue,
+            },
+            Action::ToggleLayer(layer) => {
+                let parts = match &self.mesh {
+                    Some(mesh) => mesh.layer_parts(layer as usize - 1),
+                    None => vec![],
+                };
+                if parts.is_empty() {
+                    Reply::Continue
+                } else {
+                    self.visibility.toggle_parts(&parts);
+                    Reply::Redraw
+                }
+            }
+        }
+    }
+
+    /// Labels each input file with its name, when several are tiled side by
+    /// side.  Files whose parts are all hidden aren't labelled.
+    fn queue_slot_labels(&mut self) {
+        if self.slots.len() < 2 || self.model.is_none() {
+            return;
+        }
+        let mat = self.camera.mat();
+        let sf = self.scale_factor as f32;
+        let color = self.themes.theme().text_color();
+        for slot in &self.slots {
+            let (lo, hi) = match slot.bounds {
+                Some(b) if slot.parts.clone().any(|i| self.visibility.is_visible(i)) => b,
+                _ => continue,
+            };
+            // Anchors are in physical pixels, but text is placed in logical
+            // pixels
+            let anchor = label_anchor(&mat, self.camera.size(), lo, hi) / sf;
+            let width = slot.name.chars().count() as f32 * LABEL_SIZE;
+            self.text.queue(
+                &slot.name,
+                [anchor.x - width / 2.0, anchor.y - LABEL_SIZE - LABEL_GAP],
+                LABEL_SIZE,
+                color,
+            );
+        }
+    }
+
+    /// Triangulates the measured face (or, without one, every face of the
+    /// selected parts) again in the background, scaling its tolerance by
+    /// `factor`
+    fn retriangulate(&mut self, factor: f64) -> Reply {
+        let (mesh, proxy) = match (&self.mesh, &self.loader) {
+            (Some(m), Some(p)) => (m, p),
+            _ => return Reply::Continue,
+        };
+        let faces = match self.measured {
+            Some((_, Target::Face(f))) => vec![f],
+            _ => match self.selection.mask(&self.visibility) {
+                Some(mask) => part_faces(mesh, &mask),
+                None => return Reply::Continue,
+            },
+        };
+        for r in requests(mesh, &self.slots, &faces, &mut self.tolerances, factor) {
+            let slot = &self.slots[r.slot];
+            log::info!(
+                "Triangulating {} faces of {} with tolerance {}",
+                r.faces.len(),
+                slot.name,
+                r.tolerance
+            );
+            spawn_retriangulate(
+                slot.path.clone(),
+                r.faces,
+                slot.first_id,
+                r.tolerance,
+                proxy.clone(),
+            );
+        }
+        Reply::Continue
+    }
+
+    /// Starts animating towards a previously-stored bookmark
+    fn bookmark(&mut self, slot: u8) -> Reply {
+        if let Some(state) = self.bookmarks.get(slot) {
+            self.camera.animate_to(state, TRANSITION_TIME);
+            Reply::Redraw
+        } else {
+            Reply::Continue
+        }
+    }
+
+    /// Moves the section plane as the cursor is dragged down by `dy`
+    /// physical pixels, with a drag across the whole window moving it
+    /// across the model.  Dragging down pushes it away from the viewer.
+    fn drag_section(&mut self, dy: f32) {
+        if let (Some(section), Some(model)) = (&mut self.section, &self.model) {
+            let radius = model.bounding_sphere().1;
+            let height = self.size.height.max(1) as f32;
+            let toward = self.camera.rotation_matrix().transpose() * glm::Vec4::z();
+            let toward = glm::DVec3::new(toward.x as f64, toward.y as f64, toward.z as f64);
+            // The camera may have turned since the plane was placed, so it
+            // could now face away from the viewer
+            let away = if section.normal().dot(&toward) < 0.0 {
+                1.0
+            } else {
+                -1.0
+            };
+            section.shift(away * (dy * 2.0 * radius / height) as f64);
+        }
+    }
+
+    /// Asks for a filename, then exports the visible parts of the mesh
+    fn export(&self) {
+        let mesh = match &self.mesh {
+            Some(m) => m,
+            None => return,
+        };
+        if let Some(path) = pick_export_path() {
+            match export_visible(mesh, &self.visibility, &path) {
+                Ok(()) => println!("Exported to {:?}", path),
+                Err(e) => eprintln!("Error: could not export to {:?}: {}", path, e),
+            }
+        }
+    }
+
+    /// Selects the part under the cursor, measuring the face which was
+    /// clicked (or the whole part, after a double-click).  With Shift held,
+    /// the part is added to (or removed from) the selection instead, which
+    /// clears the measurement.
+    fn select_hovered(&mut self) {
+        let hit = self.hovered_face();
+        let whole = std::mem::take(&mut self.select_part);
+        if self.modifiers.shift() {
+            if let Some((i, _)) = hit {
+                self.selection.toggle(i);
+            }
+            self.measure(None);
+        } else {
+            self.selection.select_only(hit.map(|h| h.0));
+            self.measure(hit.map(|(part, face)| match face {
+                Some(f) if !whole => (part, Target::Face(f)),
+                _ => (part, Target::Part(part)),
+            }));
+        }
+    }
+
+    /// Measures a face or part (which belongs to the given part), in the
+    /// units of the file which it came from
+    fn measure(&mut self, target: Option<(usize, Target)>) {
+        self.measured = target;
+        self.measurement = match (&self.mesh, target) {
+            (Some(mesh), Some((part, target))) => {
+                let unit = self
+                    .slots
+                    .iter()
+                    .find(|s| s.parts.contains(&part))
+                    .and_then(|s| s.unit);
+                Some(measure_text(mesh, target, unit))
+            }
+            _ => None,
+        };
+    }
+
+    /// Animates the camera to orbit around a new target, keeping the view
+    /// in place
+    fn retarget(&mut self, target: Vec3) -> Reply {
+        let state = self.camera.retarget(target);
+        self.camera.animate_to(state, TRANSITION_TIME);
+        Reply::Redraw
+    }
+
+    /// Returns the point on a visible part under the cursor
+    fn hovered_point(&self) -> Option<Vec3> {
+        let mesh = self.mesh.as_ref()?;
+        let (start, dir) = self.camera.ray(self.cursor?);
+        let to_f64 = |v: Vec3| glm::DVec3::new(v.x as f64, v.y as f64, v.z as f64);
+        pick_point(mesh, &self.visibility, to_f64(start), to_f64(dir)).map(|p| p.map(|v| v as f32))
+    }
+
+    /// Returns the index of the visible part under the cursor
+    fn hovered_part(&self) -> Option<usize> {
+        let mesh = self.mesh.as_ref()?;
+        let (start, dir) = self.camera.ray(self.cursor?);
+        let to_f64 = |v: Vec3| glm::DVec3::new(v.x as f64, v.y as f64, v.z as f64);
+        pick_part(mesh, &self.visibility, to_f64(start), to_f64(dir))
+    }
+
+    /// Returns the index of the visible part under the cursor, along with
+    /// the index of the face under the cursor (if the mesh has faces)
+    fn hovered_face(&self) -> Option<(usize, Option<usize>)> {
+        let mesh = self.mesh.as_ref()?;
+        let (start, dir) = self.camera.ray(self.cursor?);
+        let to_f64 = |v: Vec3| glm::DVec3::new(v.x as f64, v.y as f64, v.z as f64);
+        pick_face(mesh, &self.visibility, to_f64(start), to_f64(dir))
+    }
+
+    /// Uploads chunks of a model which is still loading, building a preview
+    /// from the first chunk (and fitting the camera to it), then appending
+    /// the rest.  The camera fit is refined once the whole mesh arrives.
+    fn append_streamed(&mut self, queue: &wgpu::Queue) {
+        for chunk in std::mem::take(&mut self.streamed) {
+            match &mut self.model {
+                Some(model) => model.append(&self.device, queue, &chunk),
+                None => {
+                    let model = Model::preview(
+                        &self.device,
+                        queue,
+                        self.swapchain_format,
+                        &chunk,
+                        self.chunk_budget,
+                    );
+                    let all = Visibility::new(chunk.parts.len());
+                    if let Some((lo, hi)) = visible_bounds(&chunk, &all) {
+                        let lo = Vec3::new(lo.x as f32, lo.y as f32, lo.z as f32);
+                        let hi = Vec3::new(hi.x as f32, hi.y as f32, hi.z as f32);
+                        self.camera.fit_bounds(lo, hi);
+                    }
+                    self.model = Some(model);
+                    self.previewing = true;
+                }
+            }
+        }
+        if let Some(model) = &self.model {
+            let (center, radius) = model.bounding_sphere();
+            self.camera.set_bounding_sphere(center, radius);
+        }
+    }
+
+    /// Builds the GPU-side model and overlays for a mesh, sizing the grid
+    /// to its visible parts
+    fn upload(&mut self, queue: &wgpu::Queue, mesh: &Mesh) {
+        let model = Model::new(
+            &self.device,
+            queue,
+            self.swapchain_format,
+            mesh,
+            self.chunk_budget,
+        );
+        let (center, radius) = model.bounding_sphere();
+        self.camera.set_bounding_sphere(center, radius);
+        self.normals = Some(NormalLines::new(
+            &self.device,
+            self.swapchain_format,
+            mesh,
+            radius * NORMAL_LENGTH,
+        ));
+        self.edges = Some(EdgeLines::new(&self.device, self.swapchain_format, mesh));
+        self.wires = Wires::new(&self.device, self.swapchain_format, mesh);
+        self.model = Some(model);
+        if let Some(s) = &mut self.section {
+            s.set_mesh(mesh);
+        }
+        self.grid = visible_bounds(mesh, &self.visibility).map(|(lo, hi)| {
+            let to_f32 = |v: glm::DVec3| Vec3::new(v.x as f32, v.y as f32, v.z as f32);
+            let layout = GridLayout::new(to_f32(lo), to_f32(hi));
+            Grid::new(&self.device, self.swapchain_format, layout)
+        });
+    }
+
+    /// Replaces faces which were triangulated again, uploading only their
+    /// new triangles, then rebuilds the overlays which depend on them
+    fn apply_spliced(&mut self, queue: &wgpu::Queue) {
+        let spliced = std::mem::take(&mut self.spliced);
+        let (mesh, model) = match (&mut self.mesh, &mut self.model) {
+            (Some(mesh), Some(model)) => (mesh, model),
+            _ => return,
+        };
+        for (fragments, stats) in spliced {
+            for d in &stats.diagnostics {
+                log::warn!("{}", d.message);
+            }
+            let replaced = mesh.splice_faces(&fragments);
+            model.replace_faces(&self.device, queue, mesh, &replaced);
+            self.surface_types.extend(stats.surface_types);
+        }
+        let radius = model.bounding_sphere().1;
+        self.normals = Some(NormalLines::new(
+            &self.device,
+            self.swapchain_format,
+            mesh,
+            radius * NORMAL_LENGTH,
+        ));
+        self.edges = Some(EdgeLines::new(&self.device, self.swapchain_format, mesh));
+        if let Some(s) = &mut self.section {
+            s.set_mesh(mesh);
+        }
+        let status = Status::loaded(mesh);
+        self.set_status(status);
+        self.measure(self.measured);
+    }
+
+    /// Moves the mesh by a rigid transform and re-uploads it, keeping track
+    /// of whether it's now aligned to its principal axes
+    fn apply_realign(&mut self, queue: &wgpu::Queue, mat: &glm::DMat4) {
+        let mut mesh = match self.mesh.take() {
+            Some(m) => m,
+            None => return,
+        };
+        mesh.apply_rigid(mat);
+        for slot in &mut self.slots {
+            slot.transform(mat);
+        }
+        self.alignment = match self.alignment {
+            Some(_) => None,
+            None => Some(*mat),
+        };
+        self.upload(queue, &mesh);
+        self.mesh = Some(mesh);
+        self.fit_visible(true);
+    }
+
+    /// Fits the camera to the bounds of all visible parts, either
+    /// immediately or with an animated transition
+    fn fit_visible(&mut self, animate: bool) {
+        if let Some((lo, hi)) = self
+            .mesh
+            .as_ref()
+            .and_then(|mesh| visible_bounds(mesh, &self.visibility))
+        {
+            let lo = Vec3::new(lo.x as f32, lo.y as f32, lo.z as f32);
+            let hi = Vec3::new(hi.x as f32, hi.y as f32, hi.z as f32);
+            if animate {
+                let state = self.camera.fit_state(lo, hi);
+                self.camera.animate_to(state, TRANSITION_TIME);
+            } else {
+                self.camera.fit_bounds(lo, hi);
+            }
+        }
+    }
+
     fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size = size;
-        self.swapchain =
-            Self::rebuild_swapchain_(size, self.swapchain_format, &self.surface, &self.device);
-        self.depth = Self::rebuild_depth_(size, &self.device);
+        if !swapchain::is_drawable(size) {
+            // Keep the old swapchain and depth buffer around; they'll be
+            // rebuilt when the window is restored to a non-zero size.
+            return;
+        }
+        if let Some(surface) = &self.surface {
+            self.swapchain = Some(Self::rebuild_swapchain_(
+                size,
+                self.swapchain_format,
+                self.present_mode,
+                surface,
+                &self.device,
+            ));
+        }
+        self.depth = Some(Self::rebuild_depth_(size, &self.device));
+        self.outline.resize(&self.device, size);
+        self.text.set_viewport(size, self.scale_factor);
         self.camera.set_size(size.width as f32, size.height as f32);
     }
 
@@ -162,6 +1098,7 @@ impl App {
     fn rebuild_swapchain_(
         size: PhysicalSize<u32>,
         format: wgpu::TextureFormat,
+        present_mode: wgpu::PresentMode,
         surface: &wgpu::Surface,
         device: &wgpu::Device,
     ) -> wgpu::SwapChain {
@@ -170,61 +1107,346 @@ impl App {
             format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Mailbox,
+            present_mode,
         };
         device.create_swap_chain(surface, &sc_desc)
     }
 
-    // Redraw the GUI, returning true if the model was not drawn (which means
-    // that the parent loop should keep calling redraw to force model load)
-    pub fn redraw(&mut self, queue: &wgpu::Queue) -> bool {
-        let frame = self
-            .swapchain
-            .get_current_frame()
-            .expect("Failed to acquire next swap chain texture")
-            .output;
+    // Redraw the GUI, returning `Reply::Redraw` if the model was not drawn
+    // (which means that the parent loop should keep calling redraw to force
+    // model load, or to retry after rebuilding the swapchain)
+    pub fn redraw(&mut self, queue: &wgpu::Queue) -> Reply {
+        let redraw_start = std::time::Instant::now();
+        // Without a window, frames are drawn into the offscreen target
+        let frame = match &self.surface {
+            Some(surface) => {
+                let mut src = SwapchainFrames {
+                    surface,
+                    device: &self.device,
+                    format: self.swapchain_format,
+                    present_mode: self.present_mode,
+                    swapchain: &mut self.swapchain,
+                    depth: &mut self.depth,
+                };
+                match swapchain::acquire(&mut src, self.size) {
+                    Acquired::Frame(f) => Some(f.output),
+                    Acquired::Retry => return Reply::Redraw,
+                    Acquired::Skip => return Reply::Continue,
+                    Acquired::Fatal => {
+                        log::error!("Ran out of GPU memory while acquiring a frame");
+                        return Reply::Quit;
+                    }
+                }
+            }
+            None => None,
+        };
+        // Animations and inertia keep requesting redraws until they finish
+        let animating = self.camera.update(std::time::Instant::now());
+
+        // The startup script finishes on the first frame which shows the
+        // whole model, which is drawn offscreen if we're taking a screenshot
+        let startup =
+            if self.model.is_some() && !self.previewing && self.pending.is_none() && !animating {
+                self.startup.take()
+            } else {
+                None
+            };
+        let capture = startup
+            .as_ref()
+            .and_then(|s| s.screenshot.as_ref())
+            .map(|_| Capture::new(&self.device, self.swapchain_format, self.size));
+
+        let realigned = match self.realign.take() {
+            Some(mat) => {
+                self.apply_realign(queue, &mat);
+                true
+            }
+            None => false,
+        };
+        let spliced = !self.spliced.is_empty();
+        if spliced {
+            self.apply_spliced(queue);
+        }
+
+        // Upload a new table of face colors if the color mode has changed
+        if let Some(model) = &mut self.model {
+            if model.color_mode() != self.color_mode {
+                let colors = face_colors(model.faces(), &self.surface_types, self.color_mode);
+                model.set_color_mode(queue, self.color_mode, &colors);
+            }
+            model.set_show_curvature(self.show_curvature);
+            model.set_culling(self.culling);
+            model.set_base_color(self.themes.theme().base);
+            model.set_material(self.material);
+            model.set_show_palette(self.show_palette);
+        }
+
+        // Caps are rebuilt when the section plane moves (at a limited rate
+        // while it's dragged), or when parts are shown or hidden
+        if let (Some(model), Some(mesh)) = (&mut self.model, &self.mesh) {
+            let visible = self.visibility.as_slice();
+            match &mut self.section {
+                Some(s) => {
+                    model.set_clip(Some(s.clip()));
+                    let now = std::time::Instant::now();
+                    if s.due(visible, self.section_drag.is_some(), now) {
+                        let caps = s.build(mesh, visible, now);
+                        model.set_caps(&self.device, &caps, s.normal());
+                    }
+                }
+                None => model.set_clip(None),
+            }
+        }
+
+        self.queue_slot_labels();
+
+        let depth_view = &self.depth.as_ref().expect("Missing depth buffer").1;
+        let view = match (&capture, &frame) {
+            (Some(c), _) => c.view(),
+            (None, Some(f)) => &f.view,
+            (None, None) => self
+                .offscreen
+                .as_ref()
+                .expect("Missing offscreen target")
+                .view(),
+        };
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        self.backdrop.draw(&frame, &self.depth.1, &mut encoder);
-        if let Some(model) = &self.model {
-            model.draw(&self.camera, queue, &frame, &self.depth.1, &mut encoder);
+        self.backdrop.set_theme(queue, self.themes.theme());
+        self.outline.set_scale_factor(queue, self.scale_factor);
+        let selected = self.selection.mask(&self.visibility);
+        if self.model.is_none() {
+            self.text.queue(
+                &self.status_text,
+                [16.0, 16.0],
+                16.0,
+                self.themes.theme().text_color(),
+            );
+        } else if let Some(m) = &self.measurement {
+            self.text
+                .queue(m, [16.0, 16.0], 16.0, self.themes.theme().text_color());
+        }
+        if let (Some(banner), true) = (self.diagnostics.banner(), self.model.is_some()) {
+            // The banner sits in the bottom-left corner, growing upwards
+            let lines = banner.lines().count() as f32;
+            let height = self.size.height as f32 / self.scale_factor as f32;
+            let y = height - 16.0 - lines * 16.0 * LINE_HEIGHT;
+            self.text.queue(&banner, [16.0, y], 16.0, WARNING_COLOR);
+        }
+        let contents = FrameContents {
+            model: self.model.is_some(),
+            edges: self.show_edges && self.edges.is_some(),
+            wires: self.wires.is_some(),
+            normals: self.show_normals && self.normals.is_some(),
+            grid: self.show_grid && self.grid.is_some(),
+            gizmo: self.show_grid,
+            selection: selected.is_some(),
+        };
+        let lines = LineTarget {
+            view,
+            depth: Some(depth_view),
+            scale_factor: self.scale_factor as f32,
+            clip: self.section.as_ref().map(Section::clip),
+        };
+        for pass in plan(contents) {
+            match pass {
+                Pass::Backdrop if self.show_backdrop => {
+                    self.backdrop.draw(view, depth_view, &mut encoder)
+                }
+                Pass::Backdrop => self.backdrop.clear(view, depth_view, &mut encoder),
+                Pass::Model => {
+                    if let Some(model) = &self.model {
+                        model.draw(
+                            &self.camera,
+                            queue,
+                            view,
+                            depth_view,
+                            self.visibility.as_slice(),
+                            &mut encoder,
+                        );
+                    }
+                }
+                Pass::Edges => {
+                    if let Some(edges) = &self.edges {
+                        edges.draw(
+                            &self.camera,
+                            queue,
+                            &lines,
+                            self.visibility.as_slice(),
+                            &mut encoder,
+                        );
+                    }
+                }
+                Pass::Wires => {
+                    if let Some(wires) = &self.wires {
+                        wires.draw(&self.camera, queue, &lines, &mut encoder);
+                    }
+                }
+                Pass::Normals => {
+                    if let Some(normals) = &self.normals {
+                        normals.draw(
+                            &self.camera,
+                            queue,
+                            &lines,
+                            self.visibility.as_slice(),
+                            &mut encoder,
+                        );
+                    }
+                }
+                Pass::Grid => {
+                    if let Some(grid) = &self.grid {
+                        grid.draw(&self.camera, queue, &lines, &mut encoder);
+                    }
+                }
+                Pass::Gizmo => {
+                    let viewport = corner_viewport(
+                        self.size.width as f32,
+                        self.size.height as f32,
+                        self.scale_factor as f32,
+                    );
+                    self.gizmo
+                        .draw(&self.camera, viewport, queue, &lines, &mut encoder);
+                }
+                Pass::SelectionMask => {
+                    if let (Some(model), Some(mask), Some(selected)) =
+                        (&self.model, self.outline.mask_view(), &selected)
+                    {
+                        model.draw_mask(mask, depth_view, selected, &mut encoder);
+                    }
+                }
+                Pass::Outline => self.outline.draw(view, &mut encoder),
+                Pass::Text => self.text.draw(queue, view, &mut encoder),
+            }
+        }
+        if let Some(c) = &capture {
+            c.copy(&mut encoder);
         }
         let drew_model = self.model.is_some();
         queue.submit(Some(encoder.finish()));
 
+        if let (Some(c), Some(path)) = (
+            &capture,
+            startup.as_ref().and_then(|s| s.screenshot.as_ref()),
+        ) {
+            match c.save(&self.device, path) {
+                Ok(()) => log::info!("Saved screenshot to {}", path.display()),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+        }
+        let quit = startup.is_some_and(|s| s.exit);
+
+        if drew_model {
+            if let Some(fps) = self.frame_rate.tick(std::time::Instant::now()) {
+                let memory = self.model.as_ref().map_or(0, Model::memory);
+                self.title = Some(with_frame_rate(
+                    &self.status_title,
+                    fps,
+                    self.present_mode,
+                    memory,
+                ));
+            }
+        }
+
         if drew_model && self.first_frame {
-            let end = std::time::SystemTime::now();
-            let dt = end.duration_since(self.start_time).expect("dt < 0??");
-            println!("First redraw at {:?}", dt);
+            self.profile.record("first frame", redraw_start);
+            log::info!("First redraw at {:?}", self.profile.elapsed());
+            self.profile.finish();
             self.first_frame = false;
         }
 
         // This is very awkward, but WebGPU doesn't actually do the GPU work
         // until after a queue is submitted, so we don't wait to wait for
         // the model until the _second_ frame.
-        if !self.first_frame && self.model.is_none() {
-            println!("Waiting for mesh");
-            let mesh = self
-                .loader
-                .take()
-                .unwrap()
-                .join()
-                .expect("Failed to load mesh");
-            let model = Model::new(
-                &self.device,
-                self.swapchain_format,
-                &mesh.verts,
-                &mesh.triangles,
-            );
-            self.model = Some(model);
-            self.camera.fit_verts(&mesh.verts);
+        let pending = if !self.first_frame && (self.model.is_none() || self.previewing) {
+            self.pending.take()
+        } else {
+            None
+        };
+        let mut uploaded = false;
+        if let Some((mesh, stats, slots)) = pending {
+            let upload_start = std::time::Instant::now();
+            self.visibility = Visibility::new(mesh.parts.len());
+            self.selection = Selection::new(mesh.parts.len());
+            self.measurement = None;
+            self.measured = None;
+            self.alignment = None;
+            self.realign = None;
+            self.tolerances.clear();
+            self.spliced.clear();
+            self.upload(queue, &mesh);
+            self.previewing = false;
+            self.profile.record("upload", upload_start);
+            self.mesh = Some(mesh);
+            self.diagnostics = Diagnostics::new(&stats, &slots);
+            self.slots = slots;
+            self.culling = Culling::for_stats(&stats);
+            self.surface_types = stats.surface_types;
+            self.fit_visible(false);
+            if let Some(s) = &self.startup {
+                s.apply_camera(&mut self.camera);
+            }
             self.first_frame = true;
         } else {
+            if !self.first_frame && !self.streamed.is_empty() {
+                self.append_streamed(queue);
+                uploaded = true;
+            }
             self.first_frame = false;
         }
 
-        !drew_model
+        // Without a model, we only need to redraw once the mesh arrives, and
+        // newly-streamed chunks are drawn on the next frame.  A screenshot
+        // replaces the window's frame, so it's followed by a normal redraw.
+        let scripted = capture.is_some() || (self.startup.is_some() && self.model.is_some());
+        if quit {
+            Reply::Quit
+        } else if (drew_model || self.pending.is_none())
+            && !uploaded
+            && !realigned
+            && !spliced
+            && !animating
+            && !scripted
+            && !self.section.as_ref().is_some_and(Section::is_stale)
+        {
+            Reply::Continue
+        } else {
+            Reply::Redraw
+        }
+    }
+}
+
+/// Borrowed view of the `App`'s swapchain state, used to acquire frames
+struct SwapchainFrames<'a> {
+    surface: &'a wgpu::Surface,
+    device: &'a wgpu::Device,
+    format: wgpu::TextureFormat,
+    present_mode: wgpu::PresentMode,
+    swapchain: &'a mut Option<wgpu::SwapChain>,
+    depth: &'a mut Option<(wgpu::Texture, wgpu::TextureView)>,
+}
+
+impl FrameSource for SwapchainFrames<'_> {
+    type Frame = wgpu::SwapChainFrame;
+
+    fn next_frame(&mut self) -> Result<Self::Frame, wgpu::SwapChainError> {
+        match self.swapchain {
+            Some(s) => s.get_current_frame(),
+            // We only get here if the window was created with zero size and
+            // never resized, so we treat it like a stale swapchain.
+            None => Err(wgpu::SwapChainError::Outdated),
+        }
+    }
+
+    fn reconfigure(&mut self, size: PhysicalSize<u32>) {
+        *self.swapchain = Some(App::rebuild_swapchain_(
+            size,
+            self.format,
+            self.present_mode,
+            self.surface,
+            self.device,
+        ));
+        *self.depth = Some(App::rebuild_depth_(size, self.device));
     }
 }
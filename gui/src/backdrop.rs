@@ -1,11 +1,55 @@
 use std::borrow::Cow;
 
+use wgpu::util::DeviceExt;
+
+use crate::theme::Theme;
+
 pub struct Backdrop {
+    /// Top and bottom colors, stored as uniforms so that they can be changed
+    /// without rebuilding the pipeline
+    uniform_buf: wgpu::Buffer,
+    /// Most recent colors written to `uniform_buf`
+    colors: [[f32; 4]; 2],
+    bind_group: wgpu::BindGroup,
     render_pipeline: wgpu::RenderPipeline,
 }
 
 impl Backdrop {
-    pub fn new(device: &wgpu::Device, swapchain_format: wgpu::TextureFormat) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        swapchain_format: wgpu::TextureFormat,
+        theme: &Theme,
+    ) -> Self {
+        let colors = theme.backdrop_uniforms();
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Backdrop uniform buffer"),
+            contents: bytemuck::cast_slice(&colors),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of_val(&colors) as u64),
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+            label: None,
+        });
+
         // Load the shaders from disk, either at runtime or compile-time
         #[cfg(feature = "bundle-shaders")]
         let backdrop_src = Cow::Borrowed(include_str!("backdrop.wgsl"));
@@ -26,7 +70,7 @@ impl Backdrop {
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[],
+            bind_group_layouts: &[&bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -54,22 +98,59 @@ impl Backdrop {
             multisample: wgpu::MultisampleState::default(),
         });
 
-        Backdrop { render_pipeline }
+        Backdrop {
+            uniform_buf,
+            colors,
+            bind_group,
+            render_pipeline,
+        }
+    }
+
+    /// Updates the backdrop colors, writing to the uniform buffer only if
+    /// they've changed
+    pub fn set_theme(&mut self, queue: &wgpu::Queue, theme: &Theme) {
+        if let Some(data) = update_colors(&mut self.colors, theme) {
+            queue.write_buffer(&self.uniform_buf, 0, bytemuck::cast_slice(&data));
+        }
     }
 
+    /// Clears the frame and depth buffer, then draws the gradient
     pub fn draw(
         &self,
-        frame: &wgpu::SwapChainTexture,
+        view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut rpass = Self::begin(view, depth_view, wgpu::Color::GREEN, encoder);
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..6, 0..1);
+    }
+
+    /// Clears the frame to transparent black without drawing the gradient,
+    /// so that screenshots have a transparent background
+    pub fn clear(
+        &self,
+        view: &wgpu::TextureView,
         depth_view: &wgpu::TextureView,
         encoder: &mut wgpu::CommandEncoder,
     ) {
-        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        Self::begin(view, depth_view, wgpu::Color::TRANSPARENT, encoder);
+    }
+
+    fn begin<'a>(
+        view: &'a wgpu::TextureView,
+        depth_view: &'a wgpu::TextureView,
+        color: wgpu::Color,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: &frame.view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
+                    load: wgpu::LoadOp::Clear(color),
                     store: true,
                 },
             }],
@@ -81,8 +162,50 @@ impl Backdrop {
                 }),
                 stencil_ops: None,
             }),
-        });
-        rpass.set_pipeline(&self.render_pipeline);
-        rpass.draw(0..6, 0..1);
+        })
+    }
+}
+
+/// Stores the theme's backdrop colors in `current`, returning the new
+/// uniform data if it has changed (and therefore needs to be uploaded).
+fn update_colors(current: &mut [[f32; 4]; 2], theme: &Theme) -> Option<[[f32; 4]; 2]> {
+    let next = theme.backdrop_uniforms();
+    if *current == next {
+        None
+    } else {
+        *current = next;
+        Some(next)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_colors() {
+        let dark = Theme::dark();
+        let light = Theme::light();
+        let mut current = dark.backdrop_uniforms();
+
+        // No upload is needed if the theme hasn't changed
+        assert_eq!(update_colors(&mut current, &dark), None);
+
+        // Switching themes uploads the new colors once
+        assert_eq!(
+            update_colors(&mut current, &light),
+            Some(light.backdrop_uniforms())
+        );
+        assert_eq!(current, light.backdrop_uniforms());
+        assert_eq!(update_colors(&mut current, &light), None);
+
+        // Changing the model's base color doesn't touch the backdrop
+        let tinted = Theme {
+            base: [0.5; 3],
+            ..light
+        };
+        assert_eq!(update_colors(&mut current, &tinted), None);
     }
 }
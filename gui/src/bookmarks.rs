@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use winit::event::VirtualKeyCode;
+
+use crate::camera::CameraState;
+
+/// Name of the bookmark file, which lives in the same directory as the
+/// model and stores bookmarks for every model in that directory.
+const BOOKMARK_FILE: &str = "foxtrot-bookmarks.json";
+
+/// How long it takes to animate to a restored bookmark
+pub const TRANSITION_TIME: Duration = Duration::from_millis(300);
+
+/// Bookmarks for every model in a directory, keyed by filename and then
+/// by slot number.
+type BookmarkMap = BTreeMap<String, BTreeMap<u8, CameraState>>;
+
+/// Maps the number keys 1-9 to bookmark slots
+pub fn key_slot(key: VirtualKeyCode) -> Option<u8> {
+    use VirtualKeyCode::*;
+    Some(match key {
+        Key1 => 1,
+        Key2 => 2,
+        Key3 => 3,
+        Key4 => 4,
+        Key5 => 5,
+        Key6 => 6,
+        Key7 => 7,
+        Key8 => 8,
+        Key9 => 9,
+        _ => return None,
+    })
+}
+
+/// Camera bookmarks for a single model, backed by a JSON file
+pub struct Bookmarks {
+    /// Path to the bookmark file
+    path: PathBuf,
+    /// Filename of the model, used as a key into the bookmark file
+    key: String,
+    slots: BTreeMap<u8, CameraState>,
+}
+
+impl Bookmarks {
+    /// Loads bookmarks for the given model.  A missing or unreadable
+    /// bookmark file is treated as an empty set of bookmarks.
+    pub fn load(model: &Path) -> Self {
+        let dir = model.parent().unwrap_or_else(|| Path::new(""));
+        let path = dir.join(BOOKMARK_FILE);
+        let key = model
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let slots = Self::read_all(&path).remove(&key).unwrap_or_default();
+        Bookmarks { path, key, slots }
+    }
+
+    fn read_all(path: &Path) -> BookmarkMap {
+        match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid bookmark file {:?}: {}", path, e);
+                BookmarkMap::new()
+            }),
+            Err(_) => BookmarkMap::new(),
+        }
+    }
+
+    pub fn get(&self, slot: u8) -> Option<CameraState> {
+        self.slots.get(&slot).copied()
+    }
+
+    /// Stores a bookmark and writes it to disk.  Failing to write the file
+    /// isn't fatal: the bookmark still works for the rest of the session.
+    pub fn set(&mut self, slot: u8, state: CameraState) {
+        self.slots.insert(slot, state);
+        if let Err(e) = self.save() {
+            log::warn!("Could not save bookmarks to {:?}: {}", self.path, e);
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        // Re-read the file, so that we don't clobber bookmarks for other
+        // models which were saved since we started.
+        let mut all = Self::read_all(&self.path);
+        all.insert(self.key.clone(), self.slots.clone());
+        let json = serde_json::to_string_pretty(&all)?;
+        std::fs::write(&self.path, json)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(x: f32) -> CameraState {
+        CameraState {
+            center: [x, 2.0 * x, 0.0],
+            offset: [0.0, -x],
+            scale: 1.0 + x,
+            pitch: 0.1 * x,
+            yaw: -0.2 * x,
+        }
+    }
+
+    /// Returns an empty scratch directory for a test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("foxtrot-bookmarks-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(key_slot(VirtualKeyCode::Key1), Some(1));
+        assert_eq!(key_slot(VirtualKeyCode::Key9), Some(9));
+        assert_eq!(key_slot(VirtualKeyCode::Key0), None);
+        assert_eq!(key_slot(VirtualKeyCode::A), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = scratch_dir("save");
+        let a = dir.join("a.step");
+        let b = dir.join("b.step");
+
+        let mut marks = Bookmarks::load(&a);
+        assert_eq!(marks.get(1), None);
+        marks.set(1, state(1.0));
+        marks.set(3, state(3.0));
+
+        // Bookmarks for a second model share the file without clobbering
+        // the first model's bookmarks
+        let mut other = Bookmarks::load(&b);
+        assert_eq!(other.get(1), None);
+        other.set(1, state(10.0));
+
+        let marks = Bookmarks::load(&a);
+        assert_eq!(marks.get(1), Some(state(1.0)));
+        assert_eq!(marks.get(2), None);
+        assert_eq!(marks.get(3), Some(state(3.0)));
+        assert_eq!(Bookmarks::load(&b).get(1), Some(state(10.0)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let dir = scratch_dir("invalid");
+        std::fs::write(dir.join(BOOKMARK_FILE), "not json").unwrap();
+        let mut marks = Bookmarks::load(&dir.join("model.step"));
+        assert_eq!(marks.get(1), None);
+
+        // Saving replaces the broken file
+        marks.set(2, state(2.0));
+        assert_eq!(
+            Bookmarks::load(&dir.join("model.step")).get(2),
+            Some(state(2.0))
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,51 @@
+use std::time::{Duration, Instant};
+
 use glm::{Mat4, Vec2, Vec3, Vec4};
-use itertools::Itertools;
 use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
 use winit::event::MouseButton;
 
-use triangulate::mesh::Vertex;
+/// Detects double-clicks, which `winit` doesn't report directly
+#[derive(Default)]
+pub struct DoubleClick {
+    last: Option<(Instant, Vec2)>,
+}
+
+impl DoubleClick {
+    /// Maximum time between the two clicks
+    const INTERVAL: Duration = Duration::from_millis(400);
+    /// Maximum cursor movement between the two clicks, in physical pixels
+    const SLOP: f32 = 4.0;
+
+    /// Records a click at the given time and cursor position, returning
+    /// `true` if it completes a double-click
+    pub fn click(&mut self, now: Instant, pos: Vec2) -> bool {
+        let double = matches!(self.last, Some((t, p))
+            if now.duration_since(t) <= Self::INTERVAL && (pos - p).norm() <= Self::SLOP);
+        // A third click starts a new double-click, rather than completing one
+        self.last = if double { None } else { Some((now, pos)) };
+        double
+    }
+}
+
+/// Distinguishes clicks from drags, by checking how far the cursor moved
+/// while the button was held
+#[derive(Default)]
+pub struct Click {
+    press: Option<Vec2>,
+}
+
+impl Click {
+    pub fn press(&mut self, pos: Vec2) {
+        self.press = Some(pos);
+    }
+
+    /// Returns `true` if the button was released close to where it was
+    /// pressed
+    pub fn release(&mut self, pos: Vec2) -> bool {
+        matches!(self.press.take(), Some(p) if (pos - p).norm() <= DoubleClick::SLOP)
+    }
+}
 
 #[derive(Copy, Clone, Debug)]
 enum MouseState {
@@ -27,12 +69,221 @@ pub struct Camera {
     /// Model scale
     scale: f32,
 
-    /// Center of view volume
+    /// Center of rotation (the orbit target), in model units
     center: Vec3,
 
+    /// Screen-space position of the orbit target, in scaled view units.
+    /// This is zero unless the target was moved to a point which isn't in
+    /// the middle of the screen (see [`Camera::retarget`]).
+    offset: Vec2,
+
+    /// Bounding sphere of the model (center and radius), in model units
+    bounds: (Vec3, f32),
+
     mouse: MouseState,
+
+    /// Recent drag velocity, used to start inertia on release
+    tracker: Tracker,
+    inertia: Option<Inertia>,
+    /// Animation towards a target state (e.g. a bookmark)
+    transition: Option<Transition>,
+}
+
+/// Snapshot of the camera's position and orientation, which can be saved
+/// and restored independently of the window size or mouse state.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraState {
+    pub center: [f32; 3],
+    /// Screen-space offset of the center (missing in older bookmarks)
+    #[serde(default)]
+    pub offset: [f32; 2],
+    pub scale: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+impl CameraState {
+    /// Interpolates between two states, with `t` running from 0 to 1.
+    ///
+    /// Scale is interpolated geometrically, so that zooming feels uniform,
+    /// and angles take the shortest way around the circle.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let angle = |a: f32, b: f32| {
+            let tau = std::f32::consts::TAU;
+            let d = (b - a).rem_euclid(tau);
+            let d = if d > tau / 2.0 { d - tau } else { d };
+            a + d * t
+        };
+        let mut center = [0.0; 3];
+        for (i, c) in center.iter_mut().enumerate() {
+            *c = self.center[i] + (other.center[i] - self.center[i]) * t;
+        }
+        let offset = [0, 1].map(|i| self.offset[i] + (other.offset[i] - self.offset[i]) * t);
+        CameraState {
+            center,
+            offset,
+            scale: (self.scale.ln() + (other.scale.ln() - self.scale.ln()) * t).exp(),
+            pitch: angle(self.pitch, other.pitch),
+            yaw: angle(self.yaw, other.yaw),
+        }
+    }
+}
+
+/// Animation from one camera state to another
+pub struct Transition {
+    from: CameraState,
+    to: CameraState,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Transition {
+    pub fn new(from: CameraState, to: CameraState, start: Instant, duration: Duration) -> Self {
+        Transition {
+            from,
+            to,
+            start,
+            duration,
+        }
+    }
+
+    /// Returns the camera state at the given time, and whether the
+    /// animation is finished.
+    ///
+    /// The camera has no roll, so orientation is interpolated as pitch and
+    /// yaw (each taking the shortest way around), rather than by slerping
+    /// a quaternion which could pass through rolled orientations.
+    pub fn state(&self, now: Instant) -> (CameraState, bool) {
+        let elapsed = now.saturating_duration_since(self.start);
+        if elapsed >= self.duration {
+            (self.to, true)
+        } else {
+            // Smoothstep, so that the motion eases in and out
+            let t = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+            let t = t * t * (3.0 - 2.0 * t);
+            (self.from.lerp(&self.to, t), false)
+        }
+    }
+}
+
+/// Which kind of drag is being tracked for inertia
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Motion {
+    /// Velocity is in radians per second of (pitch, yaw, unused)
+    Spin,
+    /// Velocity is in model units per second
+    Pan,
+}
+
+/// Estimates the velocity of a drag from recent mouse motion
+#[derive(Copy, Clone, Debug, Default)]
+struct Tracker {
+    last: Option<Instant>,
+    velocity: Vec3,
+}
+
+impl Tracker {
+    /// Time over which velocity samples are smoothed
+    const SMOOTHING: f32 = 0.05;
+    /// If the mouse was still for this long before release, the camera
+    /// doesn't keep moving
+    const STOPPED: Duration = Duration::from_millis(60);
+
+    /// Records a movement which happened at the given time
+    fn record(&mut self, now: Instant, delta: Vec3) {
+        match self.last {
+            Some(t) if now.saturating_duration_since(t) < Self::STOPPED => {
+                let dt = now.saturating_duration_since(t).as_secs_f32();
+                if dt > 0.0 {
+                    let a = (dt / Self::SMOOTHING).min(1.0);
+                    self.velocity = self.velocity.lerp(&(delta / dt), a);
+                }
+            }
+            _ => self.velocity = Vec3::zeros(),
+        }
+        self.last = Some(now);
+    }
+
+    /// Returns the velocity at release, which is zero if the mouse had
+    /// already stopped
+    fn release(&self, now: Instant) -> Vec3 {
+        match self.last {
+            Some(t) if now.saturating_duration_since(t) < Self::STOPPED => self.velocity,
+            _ => Vec3::zeros(),
+        }
+    }
+}
+
+/// Motion which continues after the mouse is released, decaying
+/// exponentially
+#[derive(Copy, Clone, Debug)]
+struct Inertia {
+    motion: Motion,
+    velocity: Vec3,
+    start: Instant,
+    /// Time (since `start`) of the previous step
+    prev: f32,
+}
+
+impl Inertia {
+    /// Time constant of the exponential decay, in seconds
+    const DECAY: f32 = 0.1;
+    /// The motion stops after this long, by which point it's imperceptible
+    const DURATION: f32 = 0.5;
+
+    fn new(motion: Motion, velocity: Vec3, start: Instant) -> Self {
+        Inertia {
+            motion,
+            velocity,
+            start,
+            prev: 0.0,
+        }
+    }
+
+    /// Returns how far to move since the previous step, and whether the
+    /// motion is finished.  This integrates the decaying velocity exactly,
+    /// so the total distance doesn't depend on the frame rate.
+    fn step(&mut self, now: Instant) -> (Vec3, bool) {
+        let t = now
+            .saturating_duration_since(self.start)
+            .as_secs_f32()
+            .min(Self::DURATION);
+        let travel = |t: f32| Self::DECAY * (1.0 - (-t / Self::DECAY).exp());
+        let delta = self.velocity * (travel(t) - travel(self.prev));
+        self.prev = t;
+        (delta, t >= Self::DURATION)
+    }
+}
+
+/// How far the (virtual) eye sits from the center of the bounding sphere,
+/// as a multiple of the sphere's radius.  The projection is orthographic, so
+/// this only affects the placement of the clipping planes.
+const EYE_DISTANCE: f32 = 2.0;
+
+/// Computes near and far clipping planes (as distances from the eye) for a
+/// bounding sphere of the given radius, with its center `distance` away.
+///
+/// The near plane never reaches zero (even with the eye at the center of the
+/// sphere), which would destroy depth precision.
+pub fn clip_planes(distance: f32, radius: f32) -> (f32, f32) {
+    let near = (distance - radius)
+        .max(distance / 1000.0)
+        .max(radius * 1e-4)
+        .max(f32::MIN_POSITIVE);
+    let far = distance + radius * 2.0;
+    (near, far)
 }
 
+/// Limits on the model's on-screen radius, in normalized device units.
+/// Zooming out stops before the model shrinks to a speck, and zooming in
+/// stops before floating-point precision runs out.
+const MIN_ZOOM: f32 = 0.01;
+const MAX_ZOOM: f32 = 1e4;
+
+/// The camera tilts up and down by at most this much, so it can look
+/// straight down at the model but never flip over the poles
+const MAX_TILT: f32 = std::f32::consts::FRAC_PI_2;
+
 impl Camera {
     pub fn new(width: f32, height: f32) -> Self {
         Camera {
@@ -42,11 +293,107 @@ impl Camera {
             yaw: 0.0,
             scale: 1.0,
             center: Vec3::zeros(),
+            offset: Vec2::zeros(),
+            bounds: (Vec3::zeros(), 1.0),
             mouse: MouseState::Unknown,
+            tracker: Tracker::default(),
+            inertia: None,
+            transition: None,
+        }
+    }
+
+    pub fn state(&self) -> CameraState {
+        CameraState {
+            center: [self.center.x, self.center.y, self.center.z],
+            offset: [self.offset.x, self.offset.y],
+            scale: self.scale,
+            pitch: self.pitch,
+            yaw: self.yaw,
         }
     }
 
+    pub fn set_state(&mut self, state: &CameraState) {
+        let finite = state
+            .center
+            .iter()
+            .chain(&state.offset)
+            .all(|v| v.is_finite())
+            && [state.scale, state.pitch, state.yaw]
+                .iter()
+                .all(|v| v.is_finite())
+            && state.scale > 0.0;
+        if !finite {
+            log::warn!("Ignoring invalid camera state {:?}", state);
+            return;
+        }
+        self.center = Vec3::from(state.center);
+        self.offset = Vec2::from(state.offset);
+        self.scale = state.scale;
+        self.pitch = state.pitch;
+        self.yaw = state.yaw.clamp(-MAX_TILT, MAX_TILT);
+        self.check("set_state");
+    }
+
+    /// Returns the range of allowed scales, based on the model's size
+    pub fn scale_limits(&self) -> (f32, f32) {
+        let r = self.bounds.1.max(1e-6);
+        (MIN_ZOOM / r, MAX_ZOOM / r)
+    }
+
+    /// Checks that the camera's matrix is finite (in debug builds), so that
+    /// a NaN is caught where it's introduced rather than as a blank window
+    fn check(&self, op: &str) {
+        debug_assert!(
+            self.mat().iter().all(|v| v.is_finite()),
+            "Camera matrix is not finite after {}",
+            op
+        );
+    }
+
+    /// Smoothly moves the camera to the given state.  The animation runs
+    /// as [`Camera::update`] is called, and is interrupted by mouse input.
+    pub fn animate_to(&mut self, state: CameraState, duration: Duration) {
+        self.inertia = None;
+        self.transition = Some(Transition::new(
+            self.state(),
+            state,
+            Instant::now(),
+            duration,
+        ));
+    }
+
+    /// Stops any animation or inertia, leaving the camera where it is
+    pub fn stop(&mut self) {
+        self.inertia = None;
+        self.transition = None;
+    }
+
+    /// Advances animations and inertia to the given time, returning `true`
+    /// if the camera is still moving (so another redraw is needed)
+    pub fn update(&mut self, now: Instant) -> bool {
+        if let Some(t) = &self.transition {
+            let (state, done) = t.state(now);
+            self.set_state(&state);
+            if done {
+                self.transition = None;
+            }
+        }
+        if let Some(i) = &mut self.inertia {
+            let (delta, done) = i.step(now);
+            match i.motion {
+                Motion::Spin => self.spin(delta.x, delta.y),
+                Motion::Pan => self.center += delta,
+            }
+            if done {
+                self.inertia = None;
+            }
+        }
+        self.transition.is_some() || self.inertia.is_some()
+    }
+
     pub fn mouse_pressed(&mut self, button: MouseButton) {
+        self.stop();
+        self.tracker = Tracker::default();
         // If we were previously free, then switch to panning or rotating
         if let MouseState::Free(pos) = &self.mouse {
             if let Some(m) = match button {
@@ -60,6 +407,21 @@ impl Camera {
     }
 
     pub fn mouse_released(&mut self, button: MouseButton) {
+        self.release_at(button, Instant::now())
+    }
+
+    fn release_at(&mut self, button: MouseButton, now: Instant) {
+        let motion = match (&self.mouse, button) {
+            (MouseState::Rotate(..), MouseButton::Left) => Some(Motion::Spin),
+            (MouseState::Pan(..), MouseButton::Right) => Some(Motion::Pan),
+            _ => None,
+        };
+        if let Some(motion) = motion {
+            let v = self.tracker.release(now);
+            if v != Vec3::zeros() {
+                self.inertia = Some(Inertia::new(motion, v, now));
+            }
+        }
         if let Some(m) = match &self.mouse {
             MouseState::Rotate(pos) if button == MouseButton::Left => Some(MouseState::Free(*pos)),
             MouseState::Pan(pos, ..) if button == MouseButton::Right => {
@@ -86,21 +448,50 @@ impl Camera {
         (self.mat_i() * Vec4::new(pos_norm.x, pos_norm.y, 0.0, 1.0)).xyz()
     }
 
+    /// Converts a cursor position in physical pixels into normalized
+    /// device coordinates (-1 to 1 on each axis, with Y pointing up)
+    pub fn pixel_to_norm(&self, pixel: Vec2) -> Vec2 {
+        let x_norm = 2.0 * (pixel.x / self.width - 0.5);
+        let y_norm = -2.0 * (pixel.y / self.height - 0.5);
+        Vec2::new(x_norm, y_norm)
+    }
+
+    /// Returns a ray (in model coordinates) pointing from the viewer into the
+    /// scene, through the given cursor position in physical pixels.
+    pub fn ray(&self, pixel: Vec2) -> (Vec3, Vec3) {
+        let norm = self.pixel_to_norm(pixel);
+        let mat_i = self.mat_i();
+        // Depth 1 is the near plane and depth 0 is the far plane
+        let start = (mat_i * Vec4::new(norm.x, norm.y, 1.0, 1.0)).xyz();
+        let end = (mat_i * Vec4::new(norm.x, norm.y, 0.0, 1.0)).xyz();
+        (start, end - start)
+    }
+
     pub fn mouse_move(&mut self, new_pos: Vec2) {
-        let x_norm = 2.0 * (new_pos.x / self.width - 0.5);
-        let y_norm = -2.0 * (new_pos.y / self.height - 0.5);
-        let new_pos = Vec2::new(x_norm, y_norm);
+        self.move_at(new_pos, Instant::now())
+    }
 
-        // Pan or rotate depending on current mouse state
+    fn move_at(&mut self, new_pos: Vec2, now: Instant) {
+        let new_pos = self.pixel_to_norm(new_pos);
+
+        // Pan or rotate depending on current mouse state, recording the
+        // motion so that it can continue after release
         match &self.mouse {
             MouseState::Pan(_pos, orig) => {
                 let current_pos = self.mouse_pos(new_pos);
                 let delta_pos = orig - current_pos;
-                self.center += delta_pos;
+                if delta_pos.iter().all(|v| v.is_finite()) {
+                    self.center += delta_pos;
+                    self.tracker.record(now, delta_pos);
+                    self.check("pan");
+                }
             }
             MouseState::Rotate(pos) => {
                 let delta = new_pos - *pos;
-                self.spin(delta.x * 3.0, -delta.y * 3.0 * self.height / self.width);
+                let dx = delta.x * 3.0;
+                let dy = -delta.y * 3.0 * self.height / self.width;
+                self.spin(dx, dy);
+                self.tracker.record(now, Vec3::new(dx, dy, 0.0));
             }
             _ => (),
         }
@@ -116,43 +507,99 @@ impl Camera {
 
     pub fn mouse_scroll(&mut self, delta: f32) {
         if let MouseState::Free(pos) = self.mouse {
-            self.scale(1.0 + delta / 200.0, pos);
+            self.stop();
+            // Very fast scrolling would otherwise produce a negative scale
+            self.scale((1.0 + delta / 200.0).max(0.1), pos);
         }
     }
 
-    pub fn fit_verts(&mut self, verts: &[Vertex]) {
-        let xb = verts
-            .iter()
-            .map(|v| v.pos.x)
-            .minmax()
-            .into_option()
-            .unwrap();
-        let yb = verts
-            .iter()
-            .map(|v| v.pos.y)
-            .minmax()
-            .into_option()
-            .unwrap();
-        let zb = verts
-            .iter()
-            .map(|v| v.pos.z)
-            .minmax()
-            .into_option()
-            .unwrap();
-        let dx = xb.1 - xb.0;
-        let dy = yb.1 - yb.0;
-        let dz = zb.1 - zb.0;
-        self.scale = (1.0 / dx.max(dy).max(dz)) as f32;
-        self.center = Vec3::new(
-            (xb.0 + xb.1) as f32 / 2.0,
-            (yb.0 + yb.1) as f32 / 2.0,
-            (zb.0 + zb.1) as f32 / 2.0,
-        );
+    /// Fits the view to an axis-aligned bounding box
+    pub fn fit_bounds(&mut self, lo: Vec3, hi: Vec3) {
+        let state = self.fit_state(lo, hi);
+        self.set_state(&state);
+    }
+
+    /// Returns a state which fits an axis-aligned bounding box in view,
+    /// keeping the current orientation.
+    ///
+    /// A box with zero size (e.g. a single point) is shown at the scale of
+    /// the model's bounding sphere, and a non-finite box is ignored.
+    pub fn fit_state(&self, lo: Vec3, hi: Vec3) -> CameraState {
+        if !lo.iter().chain(hi.iter()).all(|v| v.is_finite()) {
+            return self.state();
+        }
+        let d = hi - lo;
+        let c = (lo + hi) / 2.0;
+        let size = match d.x.max(d.y).max(d.z) {
+            s if s > 0.0 => s,
+            _ if self.bounds.1 > 0.0 => self.bounds.1 * 2.0,
+            _ => 1.0,
+        };
+        CameraState {
+            center: [c.x, c.y, c.z],
+            offset: [0.0, 0.0],
+            scale: 1.0 / size,
+            ..self.state()
+        }
+    }
+
+    /// Returns a state which orbits around `target` instead of the current
+    /// center, without changing what's on screen.  Animating towards this
+    /// state (see [`CameraState::lerp`]) also leaves the view unchanged, so
+    /// a clicked point stays under the cursor throughout.
+    pub fn retarget(&self, target: Vec3) -> CameraState {
+        if !target.iter().all(|v| v.is_finite()) {
+            return self.state();
+        }
+        // Position of the target relative to the current center, after
+        // scaling and rotation (i.e. in the same units as `offset`)
+        let d = self.rotation_matrix() * (target - self.center).to_homogeneous() * self.scale;
+        let mut state = self.state();
+        state.center = [target.x, target.y, target.z];
+        state.offset = [self.offset.x + d.x, self.offset.y + d.y];
+        state
+    }
+
+    /// Returns the center of the model's bounding sphere, which is the
+    /// default orbit target
+    pub fn default_target(&self) -> Vec3 {
+        self.bounds.0
+    }
+
+    /// Sets the model's bounding sphere, which is used to fit the clipping
+    /// planes tightly around the model.
+    pub fn set_bounding_sphere(&mut self, center: Vec3, radius: f32) {
+        if center.iter().all(|v| v.is_finite()) && radius.is_finite() {
+            self.bounds = (center, radius.max(0.0));
+        }
+    }
+
+    /// Returns the eye's Z position in view space (before the depth mapping
+    /// in [`Camera::view_matrix`]), plus near and far clipping distances
+    /// measured from the eye.
+    fn depth_range(&self) -> (f32, f32, f32) {
+        let (center, radius) = self.bounds;
+        let c = self.model_matrix() * Vec4::new(center.x, center.y, center.z, 1.0);
+
+        // Radius in view space, clamped so that a degenerate (single-point)
+        // model still produces a valid depth range
+        let r = (radius * self.scale).max(1e-6);
+        let distance = r * EYE_DISTANCE;
+        let (near, far) = clip_planes(distance, r);
+        (c.z + distance, near, far)
+    }
+
+    /// Returns the window size, in physical pixels
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
     }
 
     pub fn set_size(&mut self, width: f32, height: f32) {
-        self.width = width;
-        self.height = height;
+        // A minimized window has zero size, which would break the aspect
+        // ratio calculation
+        self.width = width.max(1.0);
+        self.height = height.max(1.0);
+        self.check("set_size");
     }
 
     pub fn model_matrix(&self) -> Mat4 {
@@ -160,36 +607,68 @@ impl Camera {
         // The transforms below are applied bottom-to-top when thinking about
         // the model, i.e. it's translated, then scaled, then rotated, etc.
 
+        // Move the orbit target to its place on the screen
+        glm::translate(&i, &Vec3::new(self.offset.x, self.offset.y, 0.0)) *
+
         // Scale to compensate for model size
         glm::scale(&i, &Vec3::new(self.scale, self.scale, self.scale)) *
 
         // Rotation!
-        glm::rotate_x(&i, self.yaw) *
-        glm::rotate_y(&i, self.pitch) *
+        self.rotation_matrix() *
 
         // Recenter model
         glm::translate(&i, &-self.center)
     }
 
+    /// Returns the rotation-only part of the model matrix, which shows the
+    /// current orientation without any scaling or panning.
+    pub fn rotation_matrix(&self) -> Mat4 {
+        let i = Mat4::identity();
+        glm::rotate_x(&i, self.yaw) * glm::rotate_y(&i, self.pitch)
+    }
+
     /// Returns a matrix which compensates for window aspect ratio and clipping
     pub fn view_matrix(&self) -> Mat4 {
         let i = Mat4::identity();
-        // The Z clipping range is 0-1, so push forward
-        glm::translate(&i, &Vec3::new(0.0, 0.0, 0.5)) *
+        let (z_eye, near, far) = self.depth_range();
+
+        // The Z clipping range is 0-1, and we use a `Greater` depth test, so
+        // the near plane maps to 1 and the far plane maps to 0.
+        glm::translate(&i, &Vec3::new(0.0, 0.0, (far - z_eye) / (far - near))) *
 
-        // Scale to compensate for aspect ratio and reduce Z scale to improve
-        // clipping
-        glm::scale(&i, &Vec3::new(1.0, self.width / self.height, 0.1))
+        // Scale to compensate for aspect ratio and to fit the clipping planes
+        glm::scale(&i, &Vec3::new(1.0, self.width / self.height, 1.0 / (far - near)))
+    }
+
+    /// Turns the camera to the given azimuth and elevation, in radians.
+    ///
+    /// Azimuth turns around the screen's vertical axis, with positive
+    /// values moving the viewer towards the model's +X side, and elevation
+    /// moves the viewer towards +Y.  Zero for both looks down the model's
+    /// Z axis, which is the initial view.
+    pub fn set_orientation(&mut self, azimuth: f32, elevation: f32) {
+        if azimuth.is_finite() && elevation.is_finite() {
+            self.pitch = -azimuth;
+            self.yaw = elevation.clamp(-MAX_TILT, MAX_TILT);
+        }
     }
 
     pub fn spin(&mut self, dx: f32, dy: f32) {
-        self.pitch += dx;
-        self.yaw += dy;
+        if dx.is_finite() && dy.is_finite() {
+            self.pitch += dx;
+            self.yaw = (self.yaw + dy).clamp(-MAX_TILT, MAX_TILT);
+        }
     }
 
+    /// Zooms by the given factor, keeping the point under the cursor fixed.
+    /// The scale is clamped to [`Camera::scale_limits`].
     pub fn scale(&mut self, value: f32, pos: Vec2) {
+        if !(value.is_finite() && value > 0.0 && pos.iter().all(|v| v.is_finite())) {
+            return;
+        }
+        let (lo, hi) = self.scale_limits();
         let start_pos = self.mouse_pos(pos);
-        self.scale *= value;
+        self.scale = (self.scale * value).clamp(lo.min(self.scale), hi.max(self.scale));
         let end_pos = self.mouse_pos(pos);
 
         let delta = start_pos - end_pos;
@@ -197,5 +676,531 @@ impl Camera {
         delta_mouse.z = 0.0;
 
         self.center += (self.mat_i() * delta_mouse.to_homogeneous()).xyz();
+        self.check("scale");
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a camera for a window with the given logical size and scale
+    /// factor, as we'd see after a `ScaleFactorChanged` event.
+    fn camera_at_scale(scale_factor: f32) -> Camera {
+        let mut c = Camera::new(1.0, 1.0);
+        c.set_size(800.0 * scale_factor, 600.0 * scale_factor);
+        c.scale = 0.25;
+        c.center = Vec3::new(1.0, 2.0, 3.0);
+        c.spin(0.3, -0.2);
+        c
+    }
+
+    /// Unprojects a pixel position onto the view plane
+    fn pixel_to_world(c: &Camera, pixel: Vec2) -> Vec3 {
+        c.mouse_pos(c.pixel_to_norm(pixel))
+    }
+
+    /// Projects a 3D point back into physical pixels
+    fn world_to_pixel(c: &Camera, pos: Vec3) -> Vec2 {
+        let p = c.mat() * Vec4::new(pos.x, pos.y, pos.z, 1.0);
+        Vec2::new((p.x / 2.0 + 0.5) * c.width, (-p.y / 2.0 + 0.5) * c.height)
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let a = camera_at_scale(1.0);
+        let json = serde_json::to_string(&a.state()).unwrap();
+        let state: CameraState = serde_json::from_str(&json).unwrap();
+        assert_eq!(state, a.state());
+
+        let mut b = Camera::new(100.0, 100.0);
+        b.set_state(&state);
+        assert_eq!(b.state(), a.state());
+        // The window size isn't part of the state
+        assert_eq!(b.width, 100.0);
+    }
+
+    #[test]
+    fn test_state_lerp() {
+        let a = CameraState {
+            center: [0.0, 0.0, 0.0],
+            offset: [0.0, 0.0],
+            scale: 1.0,
+            pitch: 0.0,
+            yaw: 0.5,
+        };
+        let b = CameraState {
+            center: [2.0, -4.0, 6.0],
+            offset: [1.0, -1.0],
+            scale: 100.0,
+            pitch: 1.0,
+            yaw: -0.5,
+        };
+        assert_eq!(a.lerp(&b, 0.0), a);
+        let end = a.lerp(&b, 1.0);
+        assert!((end.scale - b.scale).abs() < 1e-3);
+        assert!((end.pitch - b.pitch).abs() < 1e-6);
+        assert!((end.yaw - b.yaw).abs() < 1e-6);
+        assert_eq!(end.center, b.center);
+
+        let mid = a.lerp(&b, 0.5);
+        assert_eq!(mid.center, [1.0, -2.0, 3.0]);
+        assert_eq!(mid.offset, [0.5, -0.5]);
+        assert!((mid.scale - 10.0).abs() < 1e-4, "{}", mid.scale);
+        assert!((mid.pitch - 0.5).abs() < 1e-6);
+        assert!(mid.yaw.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_state_lerp_shortest_angle() {
+        use std::f32::consts::PI;
+        let mut a = Camera::new(1.0, 1.0).state();
+        let mut b = a;
+        a.pitch = PI - 0.1;
+        b.pitch = -PI + 0.1;
+        // Going the short way crosses PI, rather than passing through zero
+        let mid = a.lerp(&b, 0.5);
+        assert!((mid.pitch - PI).abs() < 1e-5, "{}", mid.pitch);
+        let end = a.lerp(&b, 1.0);
+        let d = (end.pitch - b.pitch).rem_euclid(2.0 * PI);
+        assert!(d.min(2.0 * PI - d) < 1e-5);
+    }
+
+    #[test]
+    fn test_clip_planes_bracket_sphere() {
+        for radius in [1e-3, 0.5, 1.0, 250.0, 1e5] {
+            for ratio in [0.0, 0.01, 0.5, 1.0, 1.5, 2.0, 10.0, 1000.0] {
+                let distance = radius * ratio;
+                let (near, far) = clip_planes(distance, radius);
+                assert!(near > 0.0, "{} {}", distance, radius);
+                assert!(near <= far);
+                // The far plane is always behind the sphere
+                assert!(far >= distance + radius);
+                // If the sphere is in front of the eye, the near plane is
+                // in front of the sphere
+                if distance > radius {
+                    assert!(near <= distance - radius * 0.999);
+                }
+            }
+        }
+        assert!(clip_planes(0.0, 0.0).0 > 0.0);
+    }
+
+    #[test]
+    fn test_model_inside_depth_range() {
+        let mut c = Camera::new(800.0, 600.0);
+        let center = Vec3::new(100.0, -50.0, 20.0);
+        let radius = 300.0;
+        c.center = center;
+        c.set_bounding_sphere(center, radius);
+        c.spin(0.7, 1.1);
+
+        // Zoom from far away to extremely close
+        for scale in [1e-4, 1e-3, 1.0 / radius, 0.1, 10.0, 1000.0] {
+            c.scale = scale;
+            let (_z_eye, near, far) = c.depth_range();
+            assert!(near > 0.0);
+            assert!(far.is_finite());
+
+            // Points on the sphere along each axis should land within the
+            // 0-1 depth range (with a little slack for float error)
+            for d in [
+                Vec3::x(),
+                Vec3::y(),
+                Vec3::z(),
+                -Vec3::x(),
+                -Vec3::y(),
+                -Vec3::z(),
+            ] {
+                let p = center + d * radius;
+                let q = c.mat() * Vec4::new(p.x, p.y, p.z, 1.0);
+                assert!(
+                    q.z >= -1e-3 && q.z <= 1.0 + 1e-3,
+                    "depth {} out of range at scale {}",
+                    q.z,
+                    scale
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ray_hits_center() {
+        let mut c = camera_at_scale(1.0);
+        c.fit_bounds(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(3.0, 5.0, 1.0));
+        let (start, dir) = c.ray(Vec2::new(400.0, 300.0));
+        // The ray through the middle of the screen passes through the center
+        let t = (c.center - start).dot(&dir) / dir.norm_squared();
+        assert!((start + dir * t - c.center).norm() < 1e-3);
+    }
+
+    #[test]
+    fn test_pixel_round_trip() {
+        for scale_factor in [1.0, 2.0] {
+            let c = camera_at_scale(scale_factor);
+            for (x, y) in [(0.0, 0.0), (400.0, 300.0), (123.0, 456.0), (799.0, 1.0)] {
+                let pixel = Vec2::new(x, y) * scale_factor;
+                let world = pixel_to_world(&c, pixel);
+                let back = world_to_pixel(&c, world);
+                assert!(
+                    (back - pixel).norm() < 1e-2,
+                    "{:?} became {:?} at scale {}",
+                    pixel,
+                    back,
+                    scale_factor
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_scale_factor_independence() {
+        // The same logical cursor position should land on the same 3D point,
+        // regardless of how many physical pixels the window uses
+        let lo = camera_at_scale(1.0);
+        let hi = camera_at_scale(2.0);
+        for (x, y) in [(10.0, 20.0), (400.0, 300.0), (640.0, 480.0)] {
+            let logical = Vec2::new(x, y);
+            let a = pixel_to_world(&lo, logical);
+            let b = pixel_to_world(&hi, logical * 2.0);
+            assert!((a - b).norm() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_retarget_keeps_view() {
+        let mut c = camera_at_scale(1.0);
+        c.offset = Vec2::new(0.1, -0.2);
+        let target = Vec3::new(4.0, -1.0, 2.5);
+        let pixel = world_to_pixel(&c, target);
+        let other = Vec3::new(-3.0, 0.5, 1.0);
+        let other_pixel = world_to_pixel(&c, other);
+
+        let start = c.state();
+        let end = c.retarget(target);
+        assert_eq!(end.center, [target.x, target.y, target.z]);
+
+        // The clicked point stays under the cursor during the transition,
+        // and so does everything else
+        for t in [0.25, 0.5, 1.0] {
+            c.set_state(&start.lerp(&end, t));
+            assert!((world_to_pixel(&c, target) - pixel).norm() < 1e-2);
+            assert!((world_to_pixel(&c, other) - other_pixel).norm() < 1e-2);
+        }
+
+        // Rotating now pivots around the clicked point
+        c.spin(0.5, -0.3);
+        assert!((world_to_pixel(&c, target) - pixel).norm() < 1e-2);
+        assert!((world_to_pixel(&c, other) - other_pixel).norm() > 1.0);
+
+        // Zooming and fitting still work, and fitting recenters the view
+        c.scale(2.0, Vec2::zeros());
+        c.fit_bounds(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(c.offset, Vec2::zeros());
+        assert!((world_to_pixel(&c, Vec3::zeros()) - Vec2::new(400.0, 300.0)).norm() < 1e-2);
+    }
+
+    #[test]
+    fn test_old_state_without_offset() {
+        let json = r#"{"center":[1.0,2.0,3.0],"scale":2.0,"pitch":0.5,"yaw":0.25}"#;
+        let state: CameraState = serde_json::from_str(json).unwrap();
+        assert_eq!(state.offset, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_double_click() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let p = Vec2::new(100.0, 100.0);
+        let mut d = DoubleClick::default();
+        assert!(!d.click(ms(0), p));
+        assert!(d.click(ms(200), p + Vec2::new(1.0, 1.0)));
+        // A third click doesn't count as another double-click
+        assert!(!d.click(ms(300), p));
+
+        // Too slow, or too far apart
+        let mut d = DoubleClick::default();
+        assert!(!d.click(ms(0), p));
+        assert!(!d.click(ms(1000), p));
+        assert!(!d.click(ms(1100), p + Vec2::new(20.0, 0.0)));
+        assert!(d.click(ms(1200), p + Vec2::new(20.0, 0.0)));
+    }
+
+    #[test]
+    fn test_click() {
+        let p = Vec2::new(100.0, 100.0);
+        let mut c = Click::default();
+        c.press(p);
+        assert!(c.release(p + Vec2::new(2.0, 1.0)));
+
+        // Dragging isn't a click
+        c.press(p);
+        assert!(!c.release(p + Vec2::new(30.0, 0.0)));
+
+        // Nor is a release without a matching press
+        assert!(!c.release(p));
+    }
+
+    fn state(x: f32) -> CameraState {
+        CameraState {
+            center: [x, 2.0 * x, 0.0],
+            offset: [0.0, -x],
+            scale: 1.0 + x,
+            pitch: 0.1 * x,
+            yaw: -0.2 * x,
+        }
+    }
+
+    #[test]
+    fn test_transition() {
+        let start = Instant::now();
+        let time = Duration::from_millis(300);
+        let tr = Transition::new(state(0.0), state(1.0), start, time);
+
+        let (s, done) = tr.state(start);
+        assert_eq!(s, state(0.0));
+        assert!(!done);
+
+        // Halfway through, smoothstep is also at the halfway point
+        let (s, done) = tr.state(start + time / 2);
+        assert!(!done);
+        assert!((s.center[0] - 0.5).abs() < 1e-4);
+        assert!((s.pitch - 0.05).abs() < 1e-4);
+
+        // Motion is monotonic, and eases in and out
+        let mut prev = -1.0;
+        for i in 0..=10 {
+            let (s, _) = tr.state(start + time * i / 10);
+            assert!(s.center[0] >= prev);
+            prev = s.center[0];
+        }
+        let (s, _) = tr.state(start + time / 10);
+        assert!(s.center[0] < 0.05);
+
+        let (s, done) = tr.state(start + time * 2);
+        assert_eq!(s, state(1.0));
+        assert!(done);
+
+        // A zero-length transition finishes immediately
+        let tr = Transition::new(state(0.0), state(1.0), start, Duration::ZERO);
+        assert_eq!(tr.state(start), (state(1.0), true));
+    }
+
+    #[test]
+    fn test_inertia_decay() {
+        let t0 = Instant::now();
+        let v = Vec3::new(2.0, -1.0, 0.0);
+
+        // The total distance is the same regardless of frame rate, and
+        // approaches velocity * time constant
+        for step in [1, 7, 16, 50] {
+            let mut i = Inertia::new(Motion::Spin, v, t0);
+            let mut total = Vec3::zeros();
+            let mut prev = f32::INFINITY;
+            for n in 1.. {
+                let (d, done) = i.step(t0 + Duration::from_millis(step * n));
+                // Each step moves less than the one before (per unit time)
+                let speed = d.norm() / step as f32;
+                assert!(speed <= prev + 1e-6);
+                prev = speed;
+                total += d;
+                if done {
+                    break;
+                }
+            }
+            let expected = v * Inertia::DECAY * (1.0 - (-Inertia::DURATION / Inertia::DECAY).exp());
+            assert!((total - expected).norm() < 1e-5, "{} {}", total, expected);
+            assert!((total - v * Inertia::DECAY).norm() < 0.01 * v.norm());
+        }
+
+        // Long after the motion finishes, there's no further movement
+        let mut i = Inertia::new(Motion::Pan, v, t0);
+        let (_, done) = i.step(t0 + Duration::from_secs(10));
+        assert!(done);
+        assert_eq!(i.step(t0 + Duration::from_secs(11)).0, Vec3::zeros());
+    }
+
+    #[test]
+    fn test_tracker() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut t = Tracker::default();
+        for n in 0..30 {
+            t.record(ms(n * 10), Vec3::new(0.1, 0.0, 0.0));
+        }
+        // Steady motion of 0.1 per 10 ms
+        assert!((t.release(ms(295)) - Vec3::new(10.0, 0.0, 0.0)).norm() < 0.5);
+
+        // Pausing before release means no inertia
+        assert_eq!(t.release(ms(500)), Vec3::zeros());
+
+        // After a pause, the velocity starts over
+        t.record(ms(1000), Vec3::new(0.1, 0.0, 0.0));
+        assert_eq!(t.release(ms(1000)), Vec3::zeros());
+    }
+
+    #[test]
+    fn test_drag_inertia() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut c = Camera::new(800.0, 600.0);
+        c.move_at(Vec2::new(400.0, 300.0), ms(0));
+        c.mouse_pressed(MouseButton::Left);
+        for n in 1..=5 {
+            c.move_at(Vec2::new(400.0 + 10.0 * n as f32, 300.0), ms(n * 10));
+        }
+        let pitch = c.pitch;
+        c.release_at(MouseButton::Left, ms(55));
+
+        // The camera keeps spinning in the same direction, then stops
+        assert!(c.update(ms(100)));
+        assert!(c.pitch > pitch);
+        assert!(!c.update(ms(2000)));
+        let end = c.pitch;
+        assert!(!c.update(ms(3000)));
+        assert_eq!(c.pitch, end);
+
+        // Releasing a drag after the mouse stops doesn't start inertia
+        c.mouse_pressed(MouseButton::Left);
+        c.move_at(Vec2::new(500.0, 300.0), ms(3000));
+        c.release_at(MouseButton::Left, ms(3500));
+        assert!(!c.update(ms(3600)));
+    }
+
+    #[test]
+    fn test_animation_interrupted() {
+        let mut c = Camera::new(800.0, 600.0);
+        c.move_at(Vec2::new(400.0, 300.0), Instant::now());
+        c.animate_to(state(1.0), Duration::from_secs(1));
+        assert!(c.update(Instant::now()));
+
+        // Finishing the animation lands exactly on the target
+        assert!(!c.update(Instant::now() + Duration::from_secs(2)));
+        assert_eq!(c.state(), state(1.0));
+
+        // Clicking stops the animation where it is
+        c.animate_to(state(0.0), Duration::from_secs(1));
+        c.mouse_pressed(MouseButton::Left);
+        assert!(!c.update(Instant::now() + Duration::from_secs(2)));
+        assert_eq!(c.state(), state(1.0));
+
+        // So does scrolling
+        c.mouse_released(MouseButton::Left);
+        c.animate_to(state(0.0), Duration::from_secs(1));
+        c.mouse_scroll(1.0);
+        assert!(!c.update(Instant::now() + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_fit_state() {
+        let mut c = camera_at_scale(1.0);
+        c.offset = Vec2::new(0.5, 0.5);
+        let s = c.fit_state(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 4.0, 1.0));
+        assert_eq!(s.center, [0.0, 2.0, 0.5]);
+        assert_eq!(s.offset, [0.0, 0.0]);
+        assert_eq!(s.scale, 0.25);
+        assert_eq!((s.pitch, s.yaw), (c.pitch, c.yaw));
+    }
+
+    /// Asserts that the camera's matrices are finite and that its tilt is
+    /// within limits
+    fn assert_sane(c: &Camera) {
+        assert!(c.mat().iter().all(|v| v.is_finite()));
+        assert!(c.mat_i().iter().all(|v| v.is_finite()));
+        assert!(c.yaw.abs() <= MAX_TILT);
+        assert!(c.scale > 0.0);
+    }
+
+    #[test]
+    fn test_zoom_limits() {
+        let mut c = Camera::new(800.0, 600.0);
+        c.set_bounding_sphere(Vec3::new(1.0, 2.0, 3.0), 5.0);
+        c.fit_bounds(Vec3::new(-4.0, -3.0, -2.0), Vec3::new(6.0, 7.0, 8.0));
+        c.move_at(Vec2::new(400.0, 300.0), Instant::now());
+        for _ in 0..1000 {
+            c.mouse_scroll(100.0);
+        }
+        assert_sane(&c);
+        assert_eq!(c.scale, c.scale_limits().1);
+        for _ in 0..2000 {
+            c.mouse_scroll(-1000.0);
+        }
+        assert_sane(&c);
+        assert_eq!(c.scale, c.scale_limits().0);
+
+        // Invalid zoom factors are ignored
+        let scale = c.scale;
+        for v in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            c.scale(v, Vec2::zeros());
+        }
+        c.scale(2.0, Vec2::new(f32::NAN, 0.0));
+        assert_eq!(c.scale, scale);
+    }
+
+    #[test]
+    fn test_tilt_limits() {
+        let mut c = Camera::new(800.0, 600.0);
+        c.spin(0.0, 10.0);
+        assert_eq!(c.yaw, MAX_TILT);
+        c.spin(0.0, -25.0);
+        assert_eq!(c.yaw, -MAX_TILT);
+        c.spin(f32::NAN, 0.1);
+        assert_sane(&c);
+
+        // Dragging far past the pole doesn't flip the camera
+        c.move_at(Vec2::new(400.0, 300.0), Instant::now());
+        c.mouse_pressed(MouseButton::Left);
+        for i in 0..100 {
+            c.move_at(Vec2::new(400.0, 300.0 + 50.0 * i as f32), Instant::now());
+        }
+        assert_eq!(c.yaw, MAX_TILT);
+        assert_sane(&c);
+    }
+
+    #[test]
+    fn test_degenerate_inputs() {
+        let mut c = Camera::new(800.0, 600.0);
+
+        // A single point (zero-size bounding box)
+        let p = Vec3::new(1.0, 1.0, 1.0);
+        c.set_bounding_sphere(p, 0.0);
+        c.fit_bounds(p, p);
+        assert!(c.scale.is_finite());
+        assert_sane(&c);
+
+        // A flat model, with the sphere giving the scale
+        c.set_bounding_sphere(p, 2.0);
+        c.fit_bounds(p, p);
+        assert_eq!(c.scale, 0.25);
+        c.fit_bounds(Vec3::zeros(), Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(c.scale, 0.5);
+
+        // Non-finite boxes, targets, and spheres are ignored
+        let before = c.state();
+        c.fit_bounds(Vec3::repeat(f32::NAN), p);
+        c.fit_bounds(Vec3::zeros(), Vec3::repeat(f32::INFINITY));
+        let s = c.retarget(Vec3::new(f32::NAN, 0.0, 0.0));
+        c.set_state(&s);
+        c.set_bounding_sphere(Vec3::repeat(f32::NAN), 1.0);
+        c.set_bounding_sphere(p, f32::INFINITY);
+        assert_eq!(c.state(), before);
+        assert_sane(&c);
+
+        // So are invalid states
+        let mut bad = c.state();
+        bad.scale = 0.0;
+        c.set_state(&bad);
+        bad.scale = 1.0;
+        bad.center[1] = f32::NAN;
+        c.set_state(&bad);
+        assert_eq!(c.state(), before);
+
+        // A minimized window
+        c.set_size(0.0, 0.0);
+        assert_sane(&c);
+        let (start, dir) = c.ray(Vec2::zeros());
+        assert!(start.iter().chain(dir.iter()).all(|v| v.is_finite()));
     }
 }
@@ -0,0 +1,254 @@
+use std::ops::Range;
+
+/// Limits on each chunk of the model's geometry, which is split across
+/// several vertex and index buffers (each with its own draw calls) when it's
+/// too large for one
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ChunkBudget {
+    pub vertices: u32,
+    pub indices: u32,
+}
+
+impl Default for ChunkBudget {
+    /// About 270 MB of vertices and 48 MB of indices per chunk
+    fn default() -> Self {
+        Self {
+            vertices: 1 << 22,
+            indices: 3 << 22,
+        }
+    }
+}
+
+impl ChunkBudget {
+    /// Returns the budget with room for at least one triangle
+    fn clamped(&self) -> Self {
+        Self {
+            vertices: self.vertices.max(3),
+            indices: self.indices.max(3),
+        }
+    }
+
+    /// Checks whether a chunk of this size is within the budget
+    pub fn fits(&self, vertices: usize, indices: usize) -> bool {
+        let b = self.clamped();
+        vertices <= b.vertices as usize && indices <= b.indices as usize
+    }
+}
+
+/// Vertices and indices of a chunk which only holds some of the mesh
+#[derive(Clone, Debug, PartialEq)]
+pub struct Remap {
+    /// The chunk's vertices, as indices into the mesh's vertices
+    pub verts: Vec<u32>,
+    /// The chunk's indices, which refer to `verts`
+    pub indices: Vec<u32>,
+}
+
+/// A run of triangles which is uploaded as one chunk
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkLayout {
+    /// Range of the mesh's indices which are drawn from this chunk
+    pub range: Range<usize>,
+    /// How the chunk's vertices are picked from the mesh, or `None` if this
+    /// is the only chunk and it holds the mesh as is
+    pub remap: Option<Remap>,
+}
+
+/// Splits a triangle mesh (as a flat list of indices into `num_verts`
+/// vertices) into runs of triangles which fit into the budget.  Vertices
+/// which are shared between chunks are copied into each of them.
+pub fn split(indices: &[u32], num_verts: usize, budget: &ChunkBudget) -> Vec<ChunkLayout> {
+    if budget.fits(num_verts, indices.len()) {
+        return vec![ChunkLayout {
+            range: 0..indices.len(),
+            remap: None,
+        }];
+    }
+    let budget = budget.clamped();
+    let (max_verts, max_indices) = (budget.vertices as usize, budget.indices as usize / 3 * 3);
+
+    // Position of each of the mesh's vertices in the current chunk
+    let mut local = vec![u32::MAX; num_verts];
+    let mut out = vec![];
+    let mut start = 0;
+    let mut remap = Remap {
+        verts: vec![],
+        indices: vec![],
+    };
+    for (i, tri) in indices.chunks_exact(3).enumerate() {
+        let new = tri
+            .iter()
+            .enumerate()
+            .filter(|(j, v)| local[**v as usize] == u32::MAX && !tri[..*j].contains(v))
+            .count();
+        if remap.indices.len() + 3 > max_indices || remap.verts.len() + new > max_verts {
+            for v in &remap.verts {
+                local[*v as usize] = u32::MAX;
+            }
+            out.push(ChunkLayout {
+                range: start..i * 3,
+                remap: Some(std::mem::replace(
+                    &mut remap,
+                    Remap {
+                        verts: vec![],
+                        indices: vec![],
+                    },
+                )),
+            });
+            start = i * 3;
+        }
+        for v in tri {
+            let j = &mut local[*v as usize];
+            if *j == u32::MAX {
+                *j = remap.verts.len() as u32;
+                remap.verts.push(*v);
+            }
+            remap.indices.push(*j);
+        }
+    }
+    if !remap.indices.is_empty() || out.is_empty() {
+        out.push(ChunkLayout {
+            range: start..indices.len(),
+            remap: Some(remap),
+        });
+    }
+    out
+}
+
+/// Returns the part of `range` (in the model's index space, which runs
+/// through each chunk in turn) which is in a chunk covering `chunk`,
+/// relative to the start of that chunk's index buffer
+pub fn local_range(chunk: Range<u32>, range: &Range<u32>) -> Option<Range<u32>> {
+    let (lo, hi) = (range.start.max(chunk.start), range.end.min(chunk.end));
+    (lo < hi).then(|| (lo - chunk.start)..(hi - chunk.start))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A strip of triangles, each sharing an edge with the next
+    fn strip(n: u32) -> Vec<u32> {
+        (0..n).flat_map(|i| [i, i + 1, i + 2]).collect()
+    }
+
+    /// Checks that chunks cover every triangle in order, stay within the
+    /// budget, and map back onto the original indices
+    fn check(indices: &[u32], num_verts: usize, budget: &ChunkBudget) -> Vec<ChunkLayout> {
+        let chunks = split(indices, num_verts, budget);
+        assert_eq!(chunks[0].range.start, 0);
+        assert_eq!(chunks.last().unwrap().range.end, indices.len());
+        for w in chunks.windows(2) {
+            assert_eq!(w[0].range.end, w[1].range.start);
+        }
+        for c in &chunks {
+            assert_eq!(c.range.len() % 3, 0);
+            match &c.remap {
+                None => assert_eq!(chunks.len(), 1),
+                Some(r) => {
+                    assert!(budget.fits(r.verts.len(), r.indices.len()), "{:?}", c);
+                    let back: Vec<u32> = r.indices.iter().map(|i| r.verts[*i as usize]).collect();
+                    assert_eq!(back, indices[c.range.clone()]);
+                    // Every vertex is used, and only listed once
+                    let mut verts = r.verts.clone();
+                    verts.sort_unstable();
+                    verts.dedup();
+                    assert_eq!(verts.len(), r.verts.len());
+                }
+            }
+        }
+        chunks
+    }
+
+    #[test]
+    fn test_single_chunk() {
+        let indices = strip(4);
+        let budget = ChunkBudget {
+            vertices: 6,
+            indices: 12,
+        };
+        let chunks = check(&indices, 6, &budget);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].remap, None);
+    }
+
+    #[test]
+    fn test_vertex_budget() {
+        // One more vertex than fits, so the last triangle moves to a second
+        // chunk along with the two vertices it shares
+        let indices = strip(4);
+        let budget = ChunkBudget {
+            vertices: 5,
+            indices: 100,
+        };
+        let chunks = check(&indices, 6, &budget);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].range, 0..9);
+        let r = chunks[1].remap.as_ref().unwrap();
+        assert_eq!(r.verts, vec![3, 4, 5]);
+        assert_eq!(r.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_index_budget() {
+        // Index budgets are rounded down to whole triangles
+        let indices = strip(10);
+        let budget = ChunkBudget {
+            vertices: 100,
+            indices: 7,
+        };
+        let chunks = check(&indices, 12, &budget);
+        assert_eq!(chunks.len(), 5);
+        assert!(chunks.iter().all(|c| c.range.len() == 6));
+    }
+
+    #[test]
+    fn test_straddling() {
+        // Pseudo-random triangles over a pool of vertices, with budgets on
+        // either side of the mesh's size
+        let mut seed = 1u64;
+        let mut rand = |n: u32| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((seed >> 33) % n as u64) as u32
+        };
+        let indices: Vec<u32> = (0..3000).map(|_| rand(500)).collect();
+        for (vertices, indices_budget) in [(499, 3000), (500, 2999), (64, 300), (3, 3), (0, 0)] {
+            let budget = ChunkBudget {
+                vertices,
+                indices: indices_budget,
+            };
+            let chunks = check(&indices, 500, &budget);
+            assert!(chunks.iter().all(|c| c.remap.is_some()));
+        }
+        let chunks = check(&indices, 500, &ChunkBudget::default());
+        assert_eq!(chunks.len(), 1);
+
+        // Degenerate triangles only count their vertices once
+        let budget = ChunkBudget {
+            vertices: 3,
+            indices: 3,
+        };
+        let chunks = check(&[0, 0, 0, 1, 1, 2], 3, &budget);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].remap.as_ref().unwrap().verts, vec![0]);
+    }
+
+    #[test]
+    fn test_empty() {
+        let chunks = check(&[], 0, &ChunkBudget::default());
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].range, 0..0);
+    }
+
+    #[test]
+    fn test_local_range() {
+        assert_eq!(local_range(10..20, &(0..30)), Some(0..10));
+        assert_eq!(local_range(10..20, &(12..15)), Some(2..5));
+        assert_eq!(local_range(10..20, &(15..25)), Some(5..10));
+        assert_eq!(local_range(10..20, &(20..25)), None);
+        assert_eq!(local_range(10..20, &(0..10)), None);
+        assert_eq!(local_range(10..20, &(12..12)), None);
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use triangulate::{mesh::Face, surface::SurfaceType};
+
+/// How the model is colored
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorMode {
+    /// Colors from the STEP file
+    Normal,
+    /// Each surface type gets a fixed hue
+    SurfaceType,
+    /// Each face gets a color based on its STEP entity index
+    Face,
+}
+
+impl ColorMode {
+    /// Returns the next mode, wrapping around at the end
+    pub fn next(self) -> Self {
+        match self {
+            ColorMode::Normal => ColorMode::SurfaceType,
+            ColorMode::SurfaceType => ColorMode::Face,
+            ColorMode::Face => ColorMode::Normal,
+        }
+    }
+
+    /// Value passed to the shader to select this mode
+    pub fn shader_index(self) -> u32 {
+        match self {
+            ColorMode::Normal => 0,
+            ColorMode::SurfaceType | ColorMode::Face => 1,
+        }
+    }
+}
+
+/// Converts a hue (0-1) into a fully-saturated RGBA color
+pub(crate) fn hue_color(hue: f32) -> [f32; 4] {
+    let h = hue.rem_euclid(1.0) * 6.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    let (r, g, b) = match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    // Lighten the colors a little, so that shading is still visible
+    let f = |c: f32| 0.25 + 0.75 * c;
+    [f(r), f(g), f(b), 1.0]
+}
+
+/// Returns the color for a surface type, with `None` used for faces whose
+/// surface type is unknown (which use the fallback color)
+pub fn surface_type_color(t: Option<SurfaceType>) -> [f32; 4] {
+    match t {
+        Some(SurfaceType::Plane) => hue_color(0.6),
+        Some(SurfaceType::Cylinder) => hue_color(0.0),
+        Some(SurfaceType::Cone) => hue_color(0.15),
+        Some(SurfaceType::Sphere) => hue_color(0.3),
+        Some(SurfaceType::Torus) => hue_color(0.8),
+        Some(SurfaceType::Spline) => hue_color(0.45),
+        Some(SurfaceType::FallbackPlanar) => hue_color(0.95),
+        None => [0.5, 0.5, 0.5, 1.0],
+    }
+}
+
+/// Mixes the bits of an integer (the finalizer from SplitMix64).  We don't
+/// use `std::hash` because its output isn't guaranteed to be stable.
+pub(crate) fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Picks a color for a face by hashing its STEP entity index, so the same
+/// face is the same color every time a file is loaded.
+pub fn face_color(id: usize) -> [f32; 4] {
+    hue_color((mix(id as u64) >> 40) as f32 / (1u64 << 24) as f32)
+}
+
+/// Builds a table with one color for each face, which is indexed by the
+/// shader in the debug color modes.
+pub fn face_colors(
+    faces: &[Face],
+    surface_types: &HashMap<usize, SurfaceType>,
+    mode: ColorMode,
+) -> Vec<[f32; 4]> {
+    faces
+        .iter()
+        .map(|f| match mode {
+            ColorMode::Normal => [1.0; 4],
+            ColorMode::SurfaceType => surface_type_color(surface_types.get(&f.id).copied()),
+            ColorMode::Face => face_color(f.id),
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_cycle() {
+        let mut m = ColorMode::Normal;
+        for _ in 0..3 {
+            m = m.next();
+        }
+        assert_eq!(m, ColorMode::Normal);
+        assert_ne!(m.next(), m);
+    }
+
+    #[test]
+    fn test_hue_color() {
+        assert_eq!(hue_color(0.0), [1.0, 0.25, 0.25, 1.0]);
+        assert_eq!(hue_color(1.0), hue_color(0.0));
+        for i in 0..100 {
+            let c = hue_color(i as f32 / 100.0);
+            assert!(c.iter().all(|c| (0.25..=1.0).contains(c)), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_surface_colors_are_distinct() {
+        use SurfaceType::*;
+        let types = [
+            Some(Plane),
+            Some(Cylinder),
+            Some(Cone),
+            Some(Sphere),
+            Some(Torus),
+            Some(Spline),
+            Some(FallbackPlanar),
+            None,
+        ];
+        for (i, a) in types.iter().enumerate() {
+            for b in &types[i + 1..] {
+                assert_ne!(surface_type_color(*a), surface_type_color(*b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_face_color_stable() {
+        // These values must not change between runs or platforms, so that
+        // screenshots of a given model are comparable.
+        assert_eq!(mix(0), 0);
+        assert_eq!(mix(1), 0x5692161d100b05e5);
+        assert_eq!(face_color(1234), face_color(1234));
+
+        // Neighboring IDs should get visibly different colors
+        let a = face_color(100);
+        let b = face_color(101);
+        let d: f32 = a.iter().zip(b.iter()).map(|(a, b)| (a - b).abs()).sum();
+        assert!(d > 0.1);
+    }
+
+    #[test]
+    fn test_face_color_table() {
+        let mut faces = vec![];
+        for (i, id) in [7, 8, 9].iter().enumerate() {
+            faces.push(Face {
+                id: *id,
+                triangles: i..i,
+            });
+        }
+        let mut types = HashMap::new();
+        types.insert(7, SurfaceType::Plane);
+        types.insert(8, SurfaceType::Cylinder);
+
+        let t = face_colors(&faces, &types, ColorMode::SurfaceType);
+        assert_eq!(
+            t,
+            vec![
+                surface_type_color(Some(SurfaceType::Plane)),
+                surface_type_color(Some(SurfaceType::Cylinder)),
+                surface_type_color(None),
+            ]
+        );
+
+        let t = face_colors(&faces, &types, ColorMode::Face);
+        assert_eq!(t, vec![face_color(7), face_color(8), face_color(9)]);
+    }
+}
@@ -0,0 +1,251 @@
+use triangulate::{
+    mesh::Mesh,
+    stats::{Diagnostic, Stats},
+};
+
+use crate::{tile::Slot, title::plural};
+
+/// Color of the warning banner, which is readable on both themes
+pub const WARNING_COLOR: [f32; 4] = [1.0, 0.7, 0.2, 1.0];
+
+/// Longest diagnostic message shown in the banner, in characters
+const MAX_MESSAGE_LEN: usize = 100;
+
+/// Summarizes the problems found while loading a model, e.g. "12 faces
+/// failed, 3 faces used fallback surfaces", or returns `None` if there
+/// weren't any
+pub fn summarize(stats: &Stats, slots: &[Slot]) -> Option<String> {
+    let mut out = vec![];
+    let unparsed: usize = slots.iter().map(|s| s.failed).sum();
+    if unparsed == 1 {
+        out.push("1 entity failed to parse".to_owned());
+    } else if unparsed > 1 {
+        out.push(format!("{} entities failed to parse", unparsed));
+    }
+    let failed = stats.num_errors + stats.num_panics;
+    if failed > 0 {
+        out.push(format!("{} failed", plural(failed, "face")));
+    }
+    if stats.num_fallbacks > 0 {
+        out.push(format!(
+            "{} used fallback surfaces",
+            plural(stats.num_fallbacks, "face")
+        ));
+    }
+    if out.is_empty() && !stats.diagnostics.is_empty() {
+        out.push(plural(stats.diagnostics.len(), "warning"));
+    }
+    (!out.is_empty()).then(|| out.join(", "))
+}
+
+/// Returns the parts which show the STEP entity that a diagnostic refers
+/// to, which is either one of their faces or the shape they were built
+/// from.  Faces which failed to triangulate aren't in the mesh, so they
+/// can't be found.
+pub fn highlight_parts(mesh: &Mesh, entity: usize) -> Vec<usize> {
+    let mut out: Vec<usize> = mesh
+        .faces
+        .iter()
+        .filter(|f| f.id == entity)
+        .filter_map(|f| {
+            mesh.parts
+                .iter()
+                .position(|p| p.triangles.contains(&f.triangles.start))
+        })
+        .chain(
+            mesh.parts
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.shape == entity)
+                .map(|(i, _)| i),
+        )
+        .collect();
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+/// Problems from loading the model, which are summarized in a banner until
+/// it's dismissed and can be stepped through one at a time
+#[derive(Default)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+    summary: Option<String>,
+    /// Index of the diagnostic being shown, if any
+    current: Option<usize>,
+    dismissed: bool,
+}
+
+impl Diagnostics {
+    pub fn new(stats: &Stats, slots: &[Slot]) -> Self {
+        Self {
+            items: stats.diagnostics.clone(),
+            summary: summarize(stats, slots),
+            current: None,
+            dismissed: false,
+        }
+    }
+
+    /// Moves to the next diagnostic (wrapping around), bringing the banner
+    /// back if it was dismissed
+    pub fn next(&mut self) -> Option<&Diagnostic> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let i = self.current.map_or(0, |i| (i + 1) % self.items.len());
+        self.current = Some(i);
+        self.dismissed = false;
+        self.items.get(i)
+    }
+
+    /// Hides the banner, returning `false` if it wasn't shown
+    pub fn dismiss(&mut self) -> bool {
+        let shown = self.banner().is_some();
+        self.dismissed = true;
+        self.current = None;
+        shown
+    }
+
+    /// Returns the text of the banner: the summary, followed by the current
+    /// diagnostic (if one has been picked)
+    pub fn banner(&self) -> Option<String> {
+        if self.dismissed {
+            return None;
+        }
+        let mut out = self.summary.clone()?;
+        if let Some(d) = self.current.and_then(|i| self.items.get(i)) {
+            let mut msg: String = d.message.chars().take(MAX_MESSAGE_LEN).collect();
+            if msg.len() < d.message.len() {
+                msg.push_str("...");
+            }
+            out += &format!(
+                "\n{}/{}: {}",
+                self.current.unwrap() + 1,
+                self.items.len(),
+                msg
+            );
+        }
+        Some(out)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(entity: Option<usize>, message: &str) -> Diagnostic {
+        Diagnostic {
+            level: log::Level::Warn,
+            entity,
+            message: message.to_owned(),
+        }
+    }
+
+    fn slot(failed: usize) -> Slot {
+        Slot {
+            name: "a.step".to_owned(),
+            path: "a.step".to_owned(),
+            parts: 0..0,
+            bounds: None,
+            unit: None,
+            failed,
+            first_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let mut stats = Stats::default();
+        assert_eq!(summarize(&stats, &[slot(0)]), None);
+
+        stats.diagnostics.push(diagnostic(None, "odd"));
+        assert_eq!(summarize(&stats, &[slot(0)]).as_deref(), Some("1 warning"));
+
+        stats.num_errors = 10;
+        stats.num_panics = 2;
+        stats.num_fallbacks = 1;
+        assert_eq!(
+            summarize(&stats, &[slot(0)]).as_deref(),
+            Some("12 faces failed, 1 face used fallback surfaces")
+        );
+        assert_eq!(
+            summarize(&stats, &[slot(2), slot(1)]).as_deref(),
+            Some("3 entities failed to parse, 12 faces failed, 1 face used fallback surfaces")
+        );
+        assert_eq!(
+            summarize(&Stats::default(), &[slot(1)]).as_deref(),
+            Some("1 entity failed to parse")
+        );
+    }
+
+    #[test]
+    fn test_banner() {
+        let mut stats = Stats::default();
+        assert_eq!(Diagnostics::new(&stats, &[]).banner(), None);
+
+        stats.num_errors = 2;
+        stats.diagnostics = vec![
+            diagnostic(Some(4), "face #4 failed"),
+            diagnostic(None, &"x".repeat(200)),
+        ];
+        let mut d = Diagnostics::new(&stats, &[]);
+        assert_eq!(d.banner().as_deref(), Some("2 faces failed"));
+
+        assert_eq!(d.next().unwrap().entity, Some(4));
+        assert_eq!(
+            d.banner().as_deref(),
+            Some("2 faces failed\n1/2: face #4 failed")
+        );
+        d.next();
+        let banner = d.banner().unwrap();
+        assert!(banner.ends_with(&format!("2/2: {}...", "x".repeat(MAX_MESSAGE_LEN))));
+
+        // Cycling wraps around, and brings back a dismissed banner
+        assert!(d.dismiss());
+        assert_eq!(d.banner(), None);
+        assert!(!d.dismiss());
+        assert_eq!(d.next().unwrap().entity, Some(4));
+        assert!(d.banner().is_some());
+
+        // There's nothing to cycle through without diagnostics
+        assert!(Diagnostics::default().next().is_none());
+    }
+
+    #[test]
+    fn test_highlight_parts() {
+        use step::{fixture, step_file::StepFile};
+        use triangulate::triangulate::triangulate;
+
+        let asm = fixture::assembly(vec![
+            fixture::cube(1.0),
+            fixture::cube(1.0).translate([5.0, 0.0, 0.0]),
+        ]);
+        let text = asm.to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let mesh = triangulate(&step).0;
+        assert_eq!(mesh.parts.len(), 2);
+
+        // Faces are found in the part which contains their triangles
+        for (i, part) in mesh.parts.iter().enumerate() {
+            let face = mesh
+                .faces
+                .iter()
+                .find(|f| part.triangles.contains(&f.triangles.start))
+                .unwrap();
+            assert_eq!(highlight_parts(&mesh, face.id), vec![i]);
+        }
+
+        // Diagnostics about a shape highlight every part built from it
+        let shape = mesh.parts[1].shape;
+        let expected: Vec<usize> = (0..mesh.parts.len())
+            .filter(|i| mesh.parts[*i].shape == shape)
+            .collect();
+        assert_eq!(highlight_parts(&mesh, shape), expected);
+
+        // Other entities aren't shown anywhere
+        assert!(highlight_parts(&mesh, usize::MAX).is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+use std::ops::Range;
+
+use nalgebra_glm as glm;
+
+use triangulate::{export::edge_lines as mesh_edge_lines, mesh::Mesh};
+
+use crate::{
+    camera::Camera,
+    lines::{LineStyle, LineTarget, Lines, Segment},
+};
+
+/// Edges between faces which meet at a sharper angle than this (in degrees)
+/// are drawn
+pub const FEATURE_ANGLE: f64 = 30.0;
+
+/// Color of boundary and feature edges
+const EDGE_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+
+/// Width of edges, in logical pixels
+const EDGE_WIDTH: f32 = 1.0;
+
+/// Pulls edges towards the viewer (in normalized depth units), so that they
+/// aren't hidden by the faces which they lie on
+const DEPTH_BIAS: f32 = -1e-4;
+
+/// Builds segments along the mesh's boundary edges and its feature edges
+/// which are sharper than `angle_deg`.  As with
+/// [`normal_lines`](crate::inspect::normal_lines), segments are grouped by
+/// part, and the returned ranges index into the segment list; meshes
+/// without parts produce a single range.
+pub fn edge_lines(mesh: &Mesh, angle_deg: f64) -> (Vec<Segment>, Vec<Range<u32>>) {
+    // Parts never share vertices, so each polyline belongs to the part of
+    // its first vertex
+    let mut part_of = vec![0; mesh.verts.len()];
+    for (i, p) in mesh.parts.iter().enumerate() {
+        for t in &mesh.triangles[p.triangles.clone()] {
+            for v in t.verts.iter() {
+                part_of[*v as usize] = i;
+            }
+        }
+    }
+
+    let mut by_part = vec![vec![]; mesh.parts.len().max(1)];
+    for line in mesh_edge_lines(mesh, angle_deg) {
+        let pos = |v: u32| glm::convert(mesh.verts[v as usize].pos);
+        by_part[part_of[line[0] as usize]].extend(
+            line.windows(2)
+                .map(|w| Segment::new(pos(w[0]), pos(w[1]), EDGE_COLOR)),
+        );
+    }
+
+    let mut out = vec![];
+    let mut ranges = vec![];
+    for segments in by_part {
+        let start = out.len() as u32;
+        out.extend(segments);
+        ranges.push(start..out.len() as u32);
+    }
+    (out, ranges)
+}
+
+/// Overlay which draws the mesh's boundary and feature edges
+pub struct EdgeLines {
+    lines: Lines,
+    parts: Vec<Range<u32>>,
+}
+
+impl EdgeLines {
+    pub fn new(device: &wgpu::Device, swapchain_format: wgpu::TextureFormat, mesh: &Mesh) -> Self {
+        let (segments, parts) = edge_lines(mesh, FEATURE_ANGLE);
+        let lines = Lines::new(
+            device,
+            swapchain_format,
+            &segments,
+            Some(wgpu::CompareFunction::GreaterEqual),
+        );
+        EdgeLines { lines, parts }
+    }
+
+    pub fn draw(
+        &self,
+        camera: &Camera,
+        queue: &wgpu::Queue,
+        target: &LineTarget,
+        visible: &[bool],
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let style = LineStyle {
+            depth_bias: DEPTH_BIAS,
+            clip: target.clip,
+            ..LineStyle::new(EDGE_WIDTH)
+        };
+        self.lines.set_uniforms(
+            queue,
+            &camera.mat(),
+            camera.size(),
+            &style,
+            target.scale_factor,
+        );
+
+        // Meshes without parts have a single range, which is always drawn
+        let always = self.parts.len() == 1 && visible.is_empty();
+        let ranges = self
+            .parts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| always || visible.get(*i).copied().unwrap_or(false))
+            .map(|(_, r)| r.clone());
+        self.lines.draw(target, None, ranges, encoder);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{DMat4, DVec3, U32Vec3};
+    use triangulate::mesh::{Part, Triangle, Vertex};
+
+    #[test]
+    fn test_edge_lines() {
+        // Two open triangles, each of which is its own part
+        let mut mesh = Mesh::default();
+        for (i, x) in [0.0, 5.0].iter().enumerate() {
+            for d in [DVec3::zeros(), DVec3::x(), DVec3::y()] {
+                mesh.verts.push(Vertex {
+                    pos: d + DVec3::new(*x, 0.0, 0.0),
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+            let j = 3 * i as u32;
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(j, j + 1, j + 2),
+            });
+        }
+
+        // Without parts, every edge is in one range
+        let (segments, ranges) = edge_lines(&mesh, FEATURE_ANGLE);
+        assert_eq!(segments.len(), 6);
+        assert_eq!(ranges, vec![0..6]);
+        assert!(segments.iter().all(|s| s.color == EDGE_COLOR));
+
+        for i in 0..2 {
+            mesh.parts.push(Part {
+                shape: i,
+                triangles: i..(i + 1),
+                transform: DMat4::identity(),
+            });
+        }
+        let (segments, ranges) = edge_lines(&mesh, FEATURE_ANGLE);
+        assert_eq!(ranges, vec![0..3, 3..6]);
+        assert!(segments[..3].iter().all(|s| s.a[0] < 2.0));
+        assert!(segments[3..].iter().all(|s| s.a[0] >= 5.0));
+
+        assert_eq!(edge_lines(&Mesh::default(), FEATURE_ANGLE).1, vec![0..0]);
+    }
+}
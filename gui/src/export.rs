@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+use triangulate::{
+    export::{save, ExportError, Format},
+    mesh::Mesh,
+};
+
+use crate::visibility::Visibility;
+
+/// Writes the visible parts of the mesh to a file, picking the format
+/// based on the file's extension.
+pub fn export_visible(mesh: &Mesh, vis: &Visibility, path: &Path) -> Result<(), ExportError> {
+    save(&mesh.select_parts(vis.as_slice()), path)
+}
+
+/// Asks the user for a target file, returning `None` if they cancel
+pub fn pick_export_path() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Export mesh")
+        .add_filter("STL", &["stl"])
+        .add_filter("OBJ", &["obj"])
+        .add_filter("PLY", &["ply"])
+        .add_filter("glTF binary", &["glb"])
+        .set_file_name("export.stl")
+        .save_file()
+}
+
+/// Loads and triangulates a STEP file, then exports the whole mesh without
+/// opening a window.
+pub fn export_headless(input: &str, output: &Path) -> Result<(), ExportError> {
+    // Check the format up front, rather than after a (potentially slow)
+    // triangulation step.
+    if Format::from_path(output).is_none() {
+        return Err(ExportError::UnknownFormat(output.to_owned()));
+    }
+    let (mesh, _stats) = crate::load_mesh(input)?;
+    let vis = Visibility::new(mesh.parts.len());
+    export_visible(&mesh, &vis, output)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use triangulate::{
+        export::{read_obj, read_stl},
+        load::LoadError,
+    };
+
+    fn scratch(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("foxtrot-gui-{}-{}", std::process::id(), name))
+    }
+
+    /// Writes a generated STEP file to a scratch path
+    fn fixture(name: &str) -> String {
+        let path = scratch(name);
+        std::fs::write(&path, step::fixture::cylinder(5.0, 10.0).to_step()).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn test_headless_export() {
+        let input = fixture("export.step");
+        let (mesh, _stats) = crate::load_mesh(&input).unwrap();
+        assert!(!mesh.triangles.is_empty());
+
+        let stl = scratch("cube.stl");
+        export_headless(&input, &stl).unwrap();
+        let tris = read_stl(&std::fs::read(&stl).unwrap()).unwrap();
+        assert_eq!(tris.len(), mesh.triangles.len());
+        std::fs::remove_file(&stl).unwrap();
+
+        let obj = scratch("cube.obj");
+        export_headless(&input, &obj).unwrap();
+        let tris = read_obj(&std::fs::read_to_string(&obj).unwrap()).unwrap();
+        assert_eq!(tris.len(), mesh.triangles.len());
+        std::fs::remove_file(&obj).unwrap();
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn test_headless_export_errors() {
+        let input = fixture("errors.step");
+        let bad = scratch("cube.step");
+        assert!(matches!(
+            export_headless(&input, &bad),
+            Err(ExportError::UnknownFormat(..))
+        ));
+        std::fs::remove_file(&input).unwrap();
+        assert!(matches!(
+            export_headless("does/not/exist.step", &scratch("missing.stl")),
+            Err(ExportError::Load(LoadError::Io { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_export_hidden_parts() {
+        let input = fixture("hidden.step");
+        let (mesh, _stats) = crate::load_mesh(&input).unwrap();
+        std::fs::remove_file(&input).unwrap();
+        let mut vis = Visibility::new(mesh.parts.len());
+        let path = scratch("hidden.stl");
+        for i in 0..mesh.parts.len() {
+            vis.hide(i);
+        }
+        export_visible(&mesh, &vis, &path).unwrap();
+        let tris = read_stl(&std::fs::read(&path).unwrap()).unwrap();
+        assert!(tris.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+use glm::{Mat4, Vec2, Vec3};
+use nalgebra_glm as glm;
+
+use crate::{
+    camera::Camera,
+    lines::{LineStyle, LineTarget, Lines, Segment},
+};
+
+/// Side length of the gizmo's square viewport, in logical pixels
+const GIZMO_SIZE: f32 = 96.0;
+
+/// Gap between the gizmo and the corner of the window, in logical pixels
+const GIZMO_MARGIN: f32 = 8.0;
+
+/// Width of the arrows, in logical pixels
+const ARROW_WIDTH: f32 = 2.0;
+
+/// A rectangle within the window, in physical pixels with the origin at
+/// the top-left corner (matching `wgpu::RenderPass::set_viewport`)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Returns the square viewport in the bottom-left corner of a window of the
+/// given physical size, shrinking it to fit if the window is tiny.
+pub fn corner_viewport(width: f32, height: f32, scale_factor: f32) -> Viewport {
+    let size = (GIZMO_SIZE * scale_factor).min(width).min(height).max(0.0);
+    let margin = (GIZMO_MARGIN * scale_factor)
+        .min(width - size)
+        .min(height - size)
+        .max(0.0);
+    Viewport {
+        x: margin,
+        y: height - size - margin,
+        width: size,
+        height: size,
+    }
+}
+
+/// Builds the matrix used to draw the gizmo within its (square) viewport.
+///
+/// This only uses the rotation of the camera, so the gizmo stays the same
+/// size and in the same place as the model is panned and zoomed.  Unit
+/// vectors land within the viewport and within the 0-1 depth range.
+pub fn gizmo_matrix(rotation: &Mat4) -> Mat4 {
+    let i = Mat4::identity();
+    glm::translate(&i, &Vec3::new(0.0, 0.0, 0.5))
+        * glm::scale(&i, &Vec3::new(0.8, 0.8, 0.4))
+        * rotation
+}
+
+/// Builds line segments for the X, Y, and Z arrows, colored red, green,
+/// and blue respectively.
+fn arrows() -> Vec<Segment> {
+    let axes = [
+        (Vec3::x(), Vec3::y(), [1.0, 0.2, 0.2, 1.0]),
+        (Vec3::y(), Vec3::z(), [0.2, 1.0, 0.2, 1.0]),
+        (Vec3::z(), Vec3::x(), [0.3, 0.5, 1.0, 1.0]),
+    ];
+    let mut out = vec![];
+    for (axis, perp, color) in axes {
+        // Shaft, then the two sides of the arrowhead
+        out.push(Segment::new(Vec3::zeros(), axis, color));
+        for side in [1.0, -1.0] {
+            out.push(Segment::new(axis, axis * 0.8 + perp * 0.08 * side, color));
+        }
+    }
+    out
+}
+
+pub struct Gizmo {
+    lines: Lines,
+}
+
+impl Gizmo {
+    pub fn new(device: &wgpu::Device, swapchain_format: wgpu::TextureFormat) -> Self {
+        // The gizmo is drawn in its own pass without a depth buffer, so that
+        // it's always on top of the model.
+        let lines = Lines::new(device, swapchain_format, &arrows(), None);
+        Gizmo { lines }
+    }
+
+    pub fn draw(
+        &self,
+        camera: &Camera,
+        viewport: Viewport,
+        queue: &wgpu::Queue,
+        target: &LineTarget,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        if viewport.width < 1.0 || viewport.height < 1.0 {
+            return;
+        }
+        let mat = gizmo_matrix(&camera.rotation_matrix());
+        self.lines.set_uniforms(
+            queue,
+            &mat,
+            Vec2::new(viewport.width, viewport.height),
+            &LineStyle::new(ARROW_WIDTH),
+            target.scale_factor,
+        );
+        self.lines.draw(
+            target,
+            Some(viewport),
+            std::iter::once(0..self.lines.count()),
+            encoder,
+        );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::Vec4;
+
+    /// Projects a point through the gizmo matrix and viewport into window
+    /// pixels, returning the pixel position and depth.
+    fn project(v: &Viewport, mat: &Mat4, p: Vec3) -> (Vec2, f32) {
+        let q = mat * Vec4::new(p.x, p.y, p.z, 1.0);
+        let x = v.x + (q.x / 2.0 + 0.5) * v.width;
+        let y = v.y + (-q.y / 2.0 + 0.5) * v.height;
+        (Vec2::new(x, y), q.z)
+    }
+
+    #[test]
+    fn test_corner_viewport() {
+        let v = corner_viewport(800.0, 600.0, 1.0);
+        assert_eq!(
+            v,
+            Viewport {
+                x: GIZMO_MARGIN,
+                y: 600.0 - GIZMO_SIZE - GIZMO_MARGIN,
+                width: GIZMO_SIZE,
+                height: GIZMO_SIZE,
+            }
+        );
+
+        // On a high-DPI display, everything doubles in physical pixels
+        let w = corner_viewport(1600.0, 1200.0, 2.0);
+        assert_eq!(w.x, v.x * 2.0);
+        assert_eq!(w.width, v.width * 2.0);
+        assert_eq!(1200.0 - w.y, (600.0 - v.y) * 2.0);
+    }
+
+    #[test]
+    fn test_corner_viewport_tiny_window() {
+        for (w, h) in [(50.0, 400.0), (400.0, 50.0), (1.0, 1.0), (0.0, 0.0)] {
+            let v = corner_viewport(w, h, 1.0);
+            assert!(v.width <= w && v.height <= h);
+            assert_eq!(v.width, v.height);
+            assert!(v.x >= 0.0 && v.y >= 0.0);
+            assert!(v.x + v.width <= w && v.y + v.height <= h);
+        }
+    }
+
+    #[test]
+    fn test_identity_orientation() {
+        let v = corner_viewport(800.0, 600.0, 1.0);
+        let mat = gizmo_matrix(&Mat4::identity());
+        let (center, _) = project(&v, &mat, Vec3::zeros());
+        assert_eq!(center, Vec2::new(v.x + v.width / 2.0, v.y + v.height / 2.0));
+
+        // X points right, Y points up (towards the top of the window), and
+        // Z points towards the viewer (larger depth is closer)
+        let (x, x_depth) = project(&v, &mat, Vec3::x());
+        assert!(x.x > center.x && (x.y - center.y).abs() < 1e-4);
+        let (y, _) = project(&v, &mat, Vec3::y());
+        assert!(y.y < center.y && (y.x - center.x).abs() < 1e-4);
+        let (z, z_depth) = project(&v, &mat, Vec3::z());
+        assert!((z - center).norm() < 1e-4);
+        assert!(z_depth > x_depth);
+    }
+
+    #[test]
+    fn test_arrows_stay_in_viewport() {
+        let v = corner_viewport(800.0, 600.0, 1.0);
+        let mut camera = Camera::new(800.0, 600.0);
+        for _ in 0..20 {
+            camera.spin(0.37, -0.61);
+            let mat = gizmo_matrix(&camera.rotation_matrix());
+            for s in arrows() {
+                for q in [s.a, s.b] {
+                    let (p, depth) = project(&v, &mat, Vec3::new(q[0], q[1], q[2]));
+                    assert!(p.x >= v.x && p.x <= v.x + v.width);
+                    assert!(p.y >= v.y && p.y <= v.y + v.height);
+                    assert!((0.0..=1.0).contains(&depth));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_camera_orientation() {
+        // The gizmo's axes should point in the same screen-space directions
+        // as the model's axes, regardless of pan and zoom.
+        let mut camera = Camera::new(600.0, 600.0);
+        camera.spin(0.8, 0.3);
+        camera.fit_bounds(Vec3::new(5.0, 5.0, 5.0), Vec3::new(50.0, 20.0, 10.0));
+        let model = camera.mat();
+        let gizmo = gizmo_matrix(&camera.rotation_matrix());
+        for axis in [Vec3::x(), Vec3::y(), Vec3::z()] {
+            let a = (model * axis.to_homogeneous()).xy().normalize();
+            let b = (gizmo * axis.to_homogeneous()).xy().normalize();
+            assert!((a - b).norm() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+}
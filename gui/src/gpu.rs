@@ -0,0 +1,248 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Which kind of adapter to ask for
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AdapterKind {
+    /// Whatever the platform picks, which is usually a hardware GPU
+    Default,
+    /// A software (CPU) adapter, e.g. lavapipe or WARP
+    Fallback,
+}
+
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum GpuError {
+    #[error(
+        "No suitable graphics adapter found (available backends: {}); \
+         check that Vulkan, Metal, or DirectX 12 drivers are installed",
+        backend_list(.0)
+    )]
+    NoAdapter(Vec<wgpu::Backend>),
+}
+
+fn backend_list(backends: &[wgpu::Backend]) -> String {
+    if backends.is_empty() {
+        "none".to_owned()
+    } else {
+        let names: Vec<_> = backends.iter().map(|b| format!("{:?}", b)).collect();
+        names.join(", ")
+    }
+}
+
+/// Abstracts over adapter creation, so that the retry logic can be tested
+/// without a GPU
+pub trait AdapterSource {
+    type Adapter;
+
+    /// Requests an adapter of the given kind, returning `None` on failure
+    fn request(&self, kind: AdapterKind) -> Option<Self::Adapter>;
+
+    /// Returns the backends with at least one adapter, for error messages
+    fn backends(&self) -> Vec<wgpu::Backend>;
+}
+
+/// Returns the order in which to try adapters.  With `software`, the
+/// fallback adapter is tried first, but a hardware adapter is still
+/// accepted if no software adapter is available.
+pub fn adapter_order(software: bool) -> [AdapterKind; 2] {
+    if software {
+        [AdapterKind::Fallback, AdapterKind::Default]
+    } else {
+        [AdapterKind::Default, AdapterKind::Fallback]
+    }
+}
+
+/// Tries each kind of adapter in turn, returning the first one found
+pub fn select_adapter<S: AdapterSource>(src: &S, software: bool) -> Result<S::Adapter, GpuError> {
+    for kind in adapter_order(software) {
+        match src.request(kind) {
+            Some(a) => return Ok(a),
+            None => log::warn!("Could not get {:?} adapter", kind),
+        }
+    }
+    Err(GpuError::NoAdapter(src.backends()))
+}
+
+/// Requests adapters from a `wgpu` instance, which must be able to draw
+/// to the given surface (if there is one; headless rendering has none)
+pub struct WgpuAdapters<'a> {
+    pub instance: &'a wgpu::Instance,
+    pub surface: Option<&'a wgpu::Surface>,
+}
+
+impl AdapterSource for WgpuAdapters<'_> {
+    type Adapter = wgpu::Adapter;
+
+    fn request(&self, kind: AdapterKind) -> Option<wgpu::Adapter> {
+        match kind {
+            AdapterKind::Default => {
+                pollster::block_on(self.instance.request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: self.surface,
+                }))
+            }
+            // `wgpu` 0.9 can't be asked for a fallback adapter directly, so
+            // look for a CPU adapter which can present to our surface.
+            AdapterKind::Fallback => self
+                .instance
+                .enumerate_adapters(wgpu::BackendBit::all())
+                .find(|a| {
+                    a.get_info().device_type == wgpu::DeviceType::Cpu
+                        && self
+                            .surface
+                            .is_none_or(|s| a.get_swap_chain_preferred_format(s).is_some())
+                }),
+        }
+    }
+
+    fn backends(&self) -> Vec<wgpu::Backend> {
+        let mut out: Vec<_> = self
+            .instance
+            .enumerate_adapters(wgpu::BackendBit::all())
+            .map(|a| a.get_info().backend)
+            .collect();
+        out.sort_by_key(|b| *b as usize);
+        out.dedup();
+        out
+    }
+}
+
+/// Describes an uncaptured `wgpu` error for the log
+pub fn describe_error(e: &wgpu::Error) -> String {
+    match e {
+        wgpu::Error::OutOfMemoryError { source } => {
+            format!("GPU out of memory: {}", source)
+        }
+        wgpu::Error::ValidationError { description, .. } => {
+            format!("GPU validation error: {}", description.trim_end())
+        }
+    }
+}
+
+/// Number of out-of-memory errors reported so far.  `wgpu` 0.9 has no error
+/// scopes, so this is how a failed allocation is noticed.
+static OUT_OF_MEMORY_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of out-of-memory errors reported so far, which can be
+/// compared before and after creating buffers to see if any of them failed
+pub fn out_of_memory_errors() -> usize {
+    OUT_OF_MEMORY_ERRORS.load(Ordering::Relaxed)
+}
+
+/// Logs an uncaptured error, counting it if the GPU ran out of memory
+fn record_error(e: &wgpu::Error) {
+    if let wgpu::Error::OutOfMemoryError { .. } = e {
+        OUT_OF_MEMORY_ERRORS.fetch_add(1, Ordering::Relaxed);
+    }
+    log::error!("{}", describe_error(e));
+}
+
+/// Logs GPU errors instead of panicking, which is `wgpu`'s default
+pub fn install_error_handler(device: &wgpu::Device) {
+    device.on_uncaptured_error(|e| record_error(&e));
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Fake adapter source, which records every request
+    struct Stub {
+        default: bool,
+        fallback: bool,
+        requests: RefCell<Vec<AdapterKind>>,
+    }
+
+    impl Stub {
+        fn new(default: bool, fallback: bool) -> Self {
+            Stub {
+                default,
+                fallback,
+                requests: RefCell::new(vec![]),
+            }
+        }
+    }
+
+    impl AdapterSource for Stub {
+        type Adapter = AdapterKind;
+        fn request(&self, kind: AdapterKind) -> Option<AdapterKind> {
+            self.requests.borrow_mut().push(kind);
+            let ok = match kind {
+                AdapterKind::Default => self.default,
+                AdapterKind::Fallback => self.fallback,
+            };
+            ok.then_some(kind)
+        }
+        fn backends(&self) -> Vec<wgpu::Backend> {
+            vec![wgpu::Backend::Gl]
+        }
+    }
+
+    #[test]
+    fn test_prefers_default() {
+        let s = Stub::new(true, true);
+        assert_eq!(select_adapter(&s, false), Ok(AdapterKind::Default));
+        assert_eq!(*s.requests.borrow(), vec![AdapterKind::Default]);
+    }
+
+    #[test]
+    fn test_retries_with_fallback() {
+        let s = Stub::new(false, true);
+        assert_eq!(select_adapter(&s, false), Ok(AdapterKind::Fallback));
+        assert_eq!(
+            *s.requests.borrow(),
+            vec![AdapterKind::Default, AdapterKind::Fallback]
+        );
+    }
+
+    #[test]
+    fn test_software() {
+        let s = Stub::new(true, true);
+        assert_eq!(select_adapter(&s, true), Ok(AdapterKind::Fallback));
+        assert_eq!(*s.requests.borrow(), vec![AdapterKind::Fallback]);
+
+        // Hardware is still used if there's no software adapter
+        let s = Stub::new(true, false);
+        assert_eq!(select_adapter(&s, true), Ok(AdapterKind::Default));
+    }
+
+    #[test]
+    fn test_no_adapter() {
+        let s = Stub::new(false, false);
+        let err = select_adapter(&s, false).unwrap_err();
+        assert_eq!(err, GpuError::NoAdapter(vec![wgpu::Backend::Gl]));
+        assert!(err.to_string().contains("available backends: Gl"));
+        assert_eq!(s.requests.borrow().len(), 2);
+
+        let err = GpuError::NoAdapter(vec![]);
+        assert!(err.to_string().contains("available backends: none"));
+        let err = GpuError::NoAdapter(vec![wgpu::Backend::Vulkan, wgpu::Backend::Gl]);
+        assert!(err.to_string().contains("Vulkan, Gl"));
+    }
+
+    #[test]
+    fn test_describe_error() {
+        let source: Box<dyn std::error::Error + Send + Sync> = "bad buffer".into();
+        let e = wgpu::Error::ValidationError {
+            source: source as Box<dyn std::error::Error + Send>,
+            description: "Buffer is too small\n".to_owned(),
+        };
+        assert_eq!(
+            describe_error(&e),
+            "GPU validation error: Buffer is too small"
+        );
+
+        let source: Box<dyn std::error::Error + Send + Sync> = "no memory".into();
+        let e = wgpu::Error::OutOfMemoryError {
+            source: source as Box<dyn std::error::Error + Send>,
+        };
+        assert_eq!(describe_error(&e), "GPU out of memory: no memory");
+
+        // Running out of memory is counted, so callers can notice it
+        let before = out_of_memory_errors();
+        record_error(&e);
+        assert!(out_of_memory_errors() > before);
+    }
+}
@@ -0,0 +1,195 @@
+use glm::{Vec2, Vec3};
+use nalgebra_glm as glm;
+
+use crate::{
+    camera::Camera,
+    lines::{LineStyle, LineTarget, Lines, Segment},
+};
+
+/// How far to push the grid away from the viewer, in normalized depth
+/// units.  The depth range is fit tightly around the model, so this is a
+/// small fraction of the model's size.
+const DEPTH_BIAS: f32 = 1e-4;
+
+/// Width of grid lines, in logical pixels
+const GRID_WIDTH: f32 = 1.0;
+
+/// Grid lines are translucent, and fade out towards the grid's edge
+const GRID_COLOR: [f32; 4] = [0.7, 0.75, 0.8, 0.5];
+
+/// Placement of the reference grid, in model units
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridLayout {
+    /// Center of the grid, which is always on a grid line
+    pub center: Vec2,
+    /// Spacing between lines, which is always a power of ten
+    pub step: f32,
+    /// Number of lines on each side of the center
+    pub half_count: u32,
+    /// Height of the grid's plane
+    pub z: f32,
+}
+
+impl GridLayout {
+    /// Sizes a grid to sit under a model with the given bounding box,
+    /// covering roughly twice its footprint.
+    ///
+    /// The grid lies on the XY plane, unless the model dips below it, in
+    /// which case it's moved down to the bottom of the model.
+    pub fn new(lo: Vec3, hi: Vec3) -> Self {
+        let size = hi - lo;
+        let radius = size.x.max(size.y).max(1e-6);
+        let step = 10f32.powf((radius / 10.0).log10().floor());
+        let half_count = (radius / step).ceil() as u32;
+        let center = ((lo.xy() + hi.xy()) / (2.0 * step)).map(f32::round) * step;
+        GridLayout {
+            center,
+            step,
+            half_count,
+            z: lo.z.min(0.0),
+        }
+    }
+
+    /// Distance from the center to the edge of the grid
+    pub fn radius(&self) -> f32 {
+        self.half_count as f32 * self.step
+    }
+
+    /// Returns a segment for each line in the grid
+    fn lines(&self) -> Vec<Segment> {
+        let r = self.radius();
+        let n = self.half_count as i32;
+        let mut out = vec![];
+        for i in -n..=n {
+            let d = i as f32 * self.step;
+            let c = self.center;
+            out.push(Segment::new(
+                Vec3::new(c.x + d, c.y - r, self.z),
+                Vec3::new(c.x + d, c.y + r, self.z),
+                GRID_COLOR,
+            ));
+            out.push(Segment::new(
+                Vec3::new(c.x - r, c.y + d, self.z),
+                Vec3::new(c.x + r, c.y + d, self.z),
+                GRID_COLOR,
+            ));
+        }
+        out
+    }
+}
+
+pub struct Grid {
+    layout: GridLayout,
+    lines: Lines,
+}
+
+impl Grid {
+    pub fn new(
+        device: &wgpu::Device,
+        swapchain_format: wgpu::TextureFormat,
+        layout: GridLayout,
+    ) -> Self {
+        // The grid is translucent, so it's tested against the model's
+        // depth but doesn't write depth itself
+        let lines = Lines::new(
+            device,
+            swapchain_format,
+            &layout.lines(),
+            Some(wgpu::CompareFunction::Greater),
+        );
+        Grid { layout, lines }
+    }
+
+    pub fn draw(
+        &self,
+        camera: &Camera,
+        queue: &wgpu::Queue,
+        target: &LineTarget,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let style = LineStyle {
+            width: GRID_WIDTH,
+            depth_bias: DEPTH_BIAS,
+            fade: Some((self.layout.center, self.layout.radius())),
+            clip: None,
+        };
+        self.lines.set_uniforms(
+            queue,
+            &camera.mat(),
+            camera.size(),
+            &style,
+            target.scale_factor,
+        );
+        self.lines.draw(
+            target,
+            None,
+            std::iter::once(0..self.lines.count()),
+            encoder,
+        );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::Vec4;
+
+    #[test]
+    fn test_layout_covers_model() {
+        for (lo, hi) in [
+            (Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0)),
+            (Vec3::new(-3.0, 10.0, 2.0), Vec3::new(250.0, 40.0, 9.0)),
+            (Vec3::new(0.01, 0.02, 0.0), Vec3::new(0.03, 0.05, 0.0)),
+        ] {
+            let g = GridLayout::new(lo, hi);
+            // The step is a power of ten, giving 10-100 lines per side
+            let e = g.step.log10();
+            assert!(
+                (e - e.round()).abs() < 1e-4,
+                "{} is not a power of ten",
+                g.step
+            );
+            assert!((10..=100).contains(&g.half_count), "{}", g.half_count);
+
+            // The center is on a grid line
+            let c = g.center / g.step;
+            assert!((c - c.map(f32::round)).norm() < 1e-3);
+
+            // The model's footprint is inside the grid
+            let r = g.radius();
+            assert!(lo.x >= g.center.x - r && hi.x <= g.center.x + r);
+            assert!(lo.y >= g.center.y - r && hi.y <= g.center.y + r);
+        }
+    }
+
+    #[test]
+    fn test_layout_height() {
+        // Models sitting on (or above) the XY plane get a grid at z = 0,
+        // which is where the depth bias matters.
+        let g = GridLayout::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(g.z, 0.0);
+        let g = GridLayout::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(1.0, 1.0, 6.0));
+        assert_eq!(g.z, 0.0);
+        let g = GridLayout::new(Vec3::new(0.0, 0.0, -2.0), Vec3::new(1.0, 1.0, 6.0));
+        assert_eq!(g.z, -2.0);
+    }
+
+    #[test]
+    fn test_bias_keeps_grid_behind_faces() {
+        // Seen from above, a point on the grid should end up farther away
+        // (smaller depth) than a model face at the same position, but still
+        // in front of anything slightly below the grid's plane.
+        let mut camera = Camera::new(800.0, 600.0);
+        let (lo, hi) = (Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        camera.fit_bounds(lo, hi);
+        camera.set_bounding_sphere((lo + hi) / 2.0, (hi - lo).norm() / 2.0);
+        let depth = |z: f32| (camera.mat() * Vec4::new(0.5, 0.5, z, 1.0)).z;
+
+        let face = depth(0.0);
+        let grid = face - DEPTH_BIAS;
+        assert!(face - grid > face * f32::EPSILON * 16.0);
+        assert!(grid > depth(-0.01));
+    }
+}
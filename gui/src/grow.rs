@@ -0,0 +1,212 @@
+/// Buffers are sized in multiples of this many bytes, which is
+/// `wgpu::COPY_BUFFER_ALIGNMENT`
+const ALIGNMENT: u64 = 4;
+
+/// The GPU operations needed to grow a buffer, which are behind a trait so
+/// that [`GrowableBuffer`] can be tested without a GPU
+pub trait BufferOps {
+    type Buffer;
+
+    /// Allocates a new (uninitialized) buffer of `size` bytes
+    fn create(&mut self, size: u64) -> Self::Buffer;
+    /// Writes `data` into `buf`, starting at `offset` bytes
+    fn write(&mut self, buf: &Self::Buffer, offset: u64, data: &[u8]);
+    /// Copies the first `size` bytes of `src` into `dst`
+    fn copy(&mut self, src: &Self::Buffer, dst: &Self::Buffer, size: u64);
+}
+
+/// A buffer which can have data appended to it.  When it runs out of room,
+/// it's replaced by a buffer of twice the capacity and the old contents are
+/// copied over, so appending `n` bytes in total only does `O(log n)`
+/// allocations.
+pub struct GrowableBuffer<B> {
+    buf: B,
+    /// Number of bytes written so far
+    len: u64,
+    /// Size of `buf`, in bytes
+    capacity: u64,
+}
+
+impl<B> GrowableBuffer<B> {
+    /// Builds a buffer which holds exactly `data` (rounded up to the copy
+    /// alignment), so models which never grow don't waste any memory
+    pub fn new<O: BufferOps<Buffer = B>>(ops: &mut O, data: &[u8]) -> Self {
+        let capacity = align(data.len() as u64).max(ALIGNMENT);
+        let buf = ops.create(capacity);
+        if !data.is_empty() {
+            ops.write(&buf, 0, data);
+        }
+        Self {
+            buf,
+            len: data.len() as u64,
+            capacity,
+        }
+    }
+
+    /// Appends `data` to the end of the buffer, reallocating if needed.
+    /// The existing length and `data.len()` must be multiples of the copy
+    /// alignment.
+    pub fn append<O: BufferOps<Buffer = B>>(&mut self, ops: &mut O, data: &[u8]) {
+        let needed = self.len + data.len() as u64;
+        if needed > self.capacity {
+            self.capacity = grown_capacity(self.capacity, needed);
+            let buf = ops.create(self.capacity);
+            ops.copy(&self.buf, &buf, self.len);
+            self.buf = buf;
+        }
+        if !data.is_empty() {
+            ops.write(&self.buf, self.len, data);
+        }
+        self.len = needed;
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.buf
+    }
+
+    /// Returns the number of bytes which have been written
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the size of the underlying buffer, in bytes
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+}
+
+fn align(size: u64) -> u64 {
+    size.next_multiple_of(ALIGNMENT)
+}
+
+/// Doubles `capacity` until it holds at least `needed` bytes
+fn grown_capacity(capacity: u64, needed: u64) -> u64 {
+    let mut out = capacity.max(ALIGNMENT);
+    while out < needed {
+        out *= 2;
+    }
+    align(out)
+}
+
+/// [`BufferOps`] for real GPU buffers.  Copies are submitted immediately, so
+/// they're ordered after any earlier writes and before any later ones.
+pub struct GpuBufferOps<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub label: &'static str,
+    /// Usage of each new buffer, which must include `COPY_SRC` and
+    /// `COPY_DST` so that it can be grown
+    pub usage: wgpu::BufferUsage,
+}
+
+impl BufferOps for GpuBufferOps<'_> {
+    type Buffer = wgpu::Buffer;
+
+    fn create(&mut self, size: u64) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(self.label),
+            size,
+            usage: self.usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn write(&mut self, buf: &wgpu::Buffer, offset: u64, data: &[u8]) {
+        self.queue.write_buffer(buf, offset, data);
+    }
+
+    fn copy(&mut self, src: &wgpu::Buffer, dst: &wgpu::Buffer, size: u64) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some(self.label),
+            });
+        encoder.copy_buffer_to_buffer(src, 0, dst, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Buffers are stored on the CPU and referred to by index
+    #[derive(Default)]
+    struct MockOps {
+        buffers: Vec<Vec<u8>>,
+    }
+
+    impl BufferOps for MockOps {
+        type Buffer = usize;
+
+        fn create(&mut self, size: u64) -> usize {
+            self.buffers.push(vec![0xAA; size as usize]);
+            self.buffers.len() - 1
+        }
+
+        fn write(&mut self, buf: &usize, offset: u64, data: &[u8]) {
+            let offset = offset as usize;
+            self.buffers[*buf][offset..offset + data.len()].copy_from_slice(data);
+        }
+
+        fn copy(&mut self, src: &usize, dst: &usize, size: u64) {
+            let data = self.buffers[*src][..size as usize].to_vec();
+            self.buffers[*dst][..data.len()].copy_from_slice(&data);
+        }
+    }
+
+    #[test]
+    fn test_grown_capacity() {
+        assert_eq!(grown_capacity(16, 10), 16);
+        assert_eq!(grown_capacity(16, 17), 32);
+        assert_eq!(grown_capacity(16, 100), 128);
+        assert_eq!(grown_capacity(0, 6), 8);
+        assert_eq!(grown_capacity(12, 13), 24);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut ops = MockOps::default();
+        let mut buf = GrowableBuffer::new(&mut ops, &[1, 2, 3, 4]);
+        assert_eq!(buf.len(), 4);
+        assert_eq!(buf.capacity, 4);
+        assert_eq!(ops.buffers.len(), 1);
+
+        // Each chunk is appended after the last, and the contents survive
+        // every reallocation
+        let mut expected = vec![1, 2, 3, 4];
+        for i in 0..100u8 {
+            let chunk = [i; 12];
+            buf.append(&mut ops, &chunk);
+            expected.extend(chunk);
+            assert_eq!(buf.len(), expected.len() as u64);
+            assert!(buf.capacity >= buf.len());
+            let contents = &ops.buffers[*buf.buffer()];
+            assert_eq!(contents.len() as u64, buf.capacity);
+            assert_eq!(&contents[..expected.len()], &expected[..]);
+        }
+
+        // Growing from 4 to 1204 bytes by doubling takes 8 reallocations,
+        // since the first append skips straight to 16 bytes
+        assert_eq!(buf.capacity, 2048);
+        assert_eq!(ops.buffers.len(), 9);
+
+        // Appending nothing doesn't allocate or write
+        buf.append(&mut ops, &[]);
+        assert_eq!(ops.buffers.len(), 9);
+        assert_eq!(buf.len(), expected.len() as u64);
+    }
+
+    #[test]
+    fn test_empty() {
+        // Buffers are never zero-sized, even if they start out empty
+        let mut ops = MockOps::default();
+        let mut buf = GrowableBuffer::new(&mut ops, &[]);
+        assert_eq!(buf.len(), 0);
+        assert_eq!(buf.capacity, 4);
+        buf.append(&mut ops, &[5; 8]);
+        assert_eq!(&ops.buffers[*buf.buffer()][..8], &[5; 8]);
+    }
+}
@@ -0,0 +1,250 @@
+use std::ops::Range;
+
+use nalgebra_glm as glm;
+
+use triangulate::mesh::Mesh;
+
+use crate::{
+    camera::Camera,
+    debug_colors::hue_color,
+    lines::{LineStyle, LineTarget, Lines, Segment},
+};
+
+/// Normal lines are this fraction of the model's bounding radius
+pub const NORMAL_LENGTH: f32 = 0.02;
+
+/// Color for vertices which are flat or have unknown curvature
+const NEUTRAL: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+/// Number of distinct curvature bands
+const CURVATURE_BANDS: u32 = 8;
+
+/// Color of normal lines
+const NORMAL_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+
+/// Width of normal lines, in logical pixels
+const NORMAL_WIDTH: f32 = 1.0;
+
+/// Builds one segment per vertex, starting at the vertex and pointing
+/// along its normal.  Vertices are grouped by part (so that hidden parts
+/// can be skipped), and the returned ranges index into the segment list;
+/// meshes without parts produce a single range.  Vertices with a zero
+/// normal are skipped.
+pub fn normal_lines(mesh: &Mesh, length: f32) -> (Vec<Segment>, Vec<Range<u32>>) {
+    let mut out = vec![];
+    let push = |out: &mut Vec<Segment>, v: usize| {
+        let v = &mesh.verts[v];
+        let n = v.norm.norm();
+        if n > 0.0 {
+            let p = v.pos;
+            let q = p + v.norm * (length as f64 / n);
+            out.push(Segment::new(glm::convert(p), glm::convert(q), NORMAL_COLOR));
+        }
+    };
+
+    let mut ranges = vec![];
+    if mesh.parts.is_empty() {
+        (0..mesh.verts.len()).for_each(|v| push(&mut out, v));
+        ranges.push(0..out.len() as u32);
+    } else {
+        // Parts may share vertices, so we track which have been used
+        // within the current part.
+        let mut seen = vec![usize::MAX; mesh.verts.len()];
+        for (i, p) in mesh.parts.iter().enumerate() {
+            let start = out.len() as u32;
+            for t in &mesh.triangles[p.triangles.clone()] {
+                for v in t.verts.iter().map(|v| *v as usize) {
+                    if seen[v] != i {
+                        seen[v] = i;
+                        push(&mut out, v);
+                    }
+                }
+            }
+            ranges.push(start..out.len() as u32);
+        }
+    }
+    (out, ranges)
+}
+
+/// Maps a curvature (in inverse model units) to a color.  Curvature is made
+/// dimensionless by multiplying by the model's radius, then split into
+/// power-of-two bands running from blue (gently curved) to red (tight
+/// curves).  Flat regions and unknown (NaN) curvature are neutral gray.
+pub fn curvature_color(k: f32, radius: f32) -> [f32; 4] {
+    let k = k.abs() * radius;
+    if !k.is_finite() || k < 1.0 {
+        return NEUTRAL;
+    }
+    let band = (k.log2().floor() as u32).min(CURVATURE_BANDS - 1);
+    hue_color(0.66 * (1.0 - band as f32 / (CURVATURE_BANDS - 1) as f32))
+}
+
+/// Returns one curvature color per vertex, or neutral colors if the mesh
+/// doesn't have curvature data.
+pub fn curvature_colors(mesh: &Mesh, radius: f32) -> Vec<[f32; 4]> {
+    match &mesh.curvature {
+        Some(c) => c.iter().map(|k| curvature_color(*k, radius)).collect(),
+        None => vec![NEUTRAL; mesh.verts.len()],
+    }
+}
+
+/// Overlay which draws vertex normals as short line segments
+pub struct NormalLines {
+    lines: Lines,
+    parts: Vec<Range<u32>>,
+}
+
+impl NormalLines {
+    pub fn new(
+        device: &wgpu::Device,
+        swapchain_format: wgpu::TextureFormat,
+        mesh: &Mesh,
+        length: f32,
+    ) -> Self {
+        let (segments, parts) = normal_lines(mesh, length);
+
+        // Lines are depth-tested against the model, but don't write depth
+        // themselves, so they never hide each other.
+        let lines = Lines::new(
+            device,
+            swapchain_format,
+            &segments,
+            Some(wgpu::CompareFunction::GreaterEqual),
+        );
+        NormalLines { lines, parts }
+    }
+
+    pub fn draw(
+        &self,
+        camera: &Camera,
+        queue: &wgpu::Queue,
+        target: &LineTarget,
+        visible: &[bool],
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        self.lines.set_uniforms(
+            queue,
+            &camera.mat(),
+            camera.size(),
+            &LineStyle {
+                clip: target.clip,
+                ..LineStyle::new(NORMAL_WIDTH)
+            },
+            target.scale_factor,
+        );
+
+        // Meshes without parts have a single range, which is always drawn
+        let always = self.parts.len() == 1 && visible.is_empty();
+        let ranges = self
+            .parts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| always || visible.get(*i).copied().unwrap_or(false))
+            .map(|(_, r)| r.clone());
+        self.lines.draw(target, None, ranges, encoder);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{DMat4, DVec3, U32Vec3};
+    use triangulate::mesh::{Part, Triangle, Vertex};
+
+    fn mesh() -> Mesh {
+        let mut mesh = Mesh::default();
+        for (i, n) in [
+            DVec3::new(0.0, 0.0, 2.0),
+            DVec3::new(0.0, 3.0, 0.0),
+            DVec3::zeros(),
+            DVec3::new(1.0, 0.0, 0.0),
+        ]
+        .iter()
+        .enumerate()
+        {
+            mesh.verts.push(Vertex {
+                pos: DVec3::new(i as f64, 0.0, 0.0),
+                norm: *n,
+                color: DVec3::zeros(),
+            });
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_normal_lines() {
+        let mesh = mesh();
+        let (lines, ranges) = normal_lines(&mesh, 0.5);
+        // The vertex with a zero normal is skipped
+        assert_eq!(ranges, vec![0..3]);
+        let ends: Vec<_> = lines.iter().map(|s| (s.a, s.b)).collect();
+        assert_eq!(
+            ends,
+            vec![
+                ([0.0, 0.0, 0.0, 1.0], [0.0, 0.0, 0.5, 1.0]),
+                ([1.0, 0.0, 0.0, 1.0], [1.0, 0.5, 0.0, 1.0]),
+                ([3.0, 0.0, 0.0, 1.0], [3.5, 0.0, 0.0, 1.0]),
+            ]
+        );
+        assert!(lines.iter().all(|s| s.color == NORMAL_COLOR));
+    }
+
+    #[test]
+    fn test_normal_lines_by_part() {
+        let mut mesh = mesh();
+        for (i, v) in [[0, 1, 2], [1, 2, 3]].iter().enumerate() {
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(v[0], v[1], v[2]),
+            });
+            mesh.parts.push(Part {
+                shape: i,
+                triangles: i..(i + 1),
+                transform: DMat4::identity(),
+            });
+        }
+        // The shared vertex (1) appears in both parts
+        let (lines, ranges) = normal_lines(&mesh, 1.0);
+        assert_eq!(ranges, vec![0..2, 2..4]);
+        assert_eq!(lines[2].a, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(lines[3].b, [4.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_curvature_color() {
+        // Flat and unknown curvature are neutral
+        assert_eq!(curvature_color(0.0, 10.0), NEUTRAL);
+        assert_eq!(curvature_color(0.05, 10.0), NEUTRAL);
+        assert_eq!(curvature_color(f32::NAN, 10.0), NEUTRAL);
+
+        // Gentle curves are blue, and tight curves are red
+        assert_eq!(curvature_color(0.1, 10.0), hue_color(0.66));
+        assert_eq!(curvature_color(1e6, 10.0), hue_color(0.0));
+
+        // Curvature within a power-of-two band gets the same color,
+        // and each band is distinct from its neighbors
+        assert_eq!(curvature_color(0.2, 10.0), curvature_color(0.3, 10.0));
+        let bands: Vec<_> = (0..CURVATURE_BANDS)
+            .map(|i| curvature_color((1 << i) as f32 / 10.0, 10.0))
+            .collect();
+        for w in bands.windows(2) {
+            assert_ne!(w[0], w[1]);
+        }
+
+        // The sign of the curvature is ignored
+        assert_eq!(curvature_color(-0.4, 10.0), curvature_color(0.4, 10.0));
+    }
+
+    #[test]
+    fn test_curvature_colors() {
+        let mut mesh = mesh();
+        assert_eq!(curvature_colors(&mesh, 1.0), vec![NEUTRAL; 4]);
+        mesh.curvature = Some(vec![0.0, 1.0, f32::NAN, 200.0]);
+        let c = curvature_colors(&mesh, 1.0);
+        assert_eq!(c[0], NEUTRAL);
+        assert_eq!(c[1], hue_color(0.66));
+        assert_eq!(c[2], NEUTRAL);
+        assert_eq!(c[3], hue_color(0.0));
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm::DMat4;
+
+use triangulate::mesh::Mesh;
+
+/// A set of parts which share geometry, differing only by their transforms
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InstanceGroup {
+    /// Indices into [`Mesh::parts`], in increasing order.  The first part's
+    /// vertices are uploaded, and every part is drawn as an instance of it.
+    pub parts: Vec<usize>,
+}
+
+/// Per-instance data, matching the instance buffer layout in `model.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct GPUInstance {
+    /// Column-major transform, relative to the group's first part
+    pub transform: [[f32; 4]; 4],
+    /// Multiplied into the instance's color (e.g. for highlighting)
+    pub tint: [f32; 4],
+    /// Replaces the instance's vertex colors when the palette is shown, or
+    /// zero (with a zero alpha) to keep them
+    pub palette: [f32; 4],
+}
+
+/// Groups parts which were built from the same STEP shape.  A part only
+/// joins a group if its triangle count matches and the group's first part
+/// has an invertible transform; otherwise, it starts a new group.  Groups
+/// are ordered by their first part.
+pub fn group_instances(mesh: &Mesh) -> Vec<InstanceGroup> {
+    let mut groups: Vec<InstanceGroup> = vec![];
+    let mut by_shape: HashMap<usize, usize> = HashMap::new();
+    for (i, p) in mesh.parts.iter().enumerate() {
+        let group = by_shape.get(&p.shape).copied().filter(|g| {
+            let base = &mesh.parts[groups[*g].parts[0]];
+            base.triangles.len() == p.triangles.len() && base.transform.try_inverse().is_some()
+        });
+        match group {
+            Some(g) => groups[g].parts.push(i),
+            None => {
+                by_shape.insert(p.shape, groups.len());
+                groups.push(InstanceGroup { parts: vec![i] });
+            }
+        }
+    }
+    groups
+}
+
+fn to_columns(m: &DMat4) -> [[f32; 4]; 4] {
+    let mut out = [[0.0; 4]; 4];
+    for (c, col) in out.iter_mut().enumerate() {
+        for (r, v) in col.iter_mut().enumerate() {
+            *v = m[(r, c)] as f32;
+        }
+    }
+    out
+}
+
+/// Builds the instance buffer, with each group's instances stored
+/// contiguously (in group order).  Transforms are relative to each group's
+/// first part, whose vertices are already in their final position.
+pub fn instance_data(mesh: &Mesh, groups: &[InstanceGroup]) -> Vec<GPUInstance> {
+    let mut out = vec![];
+    for g in groups {
+        let base = mesh.parts[g.parts[0]]
+            .transform
+            .try_inverse()
+            .unwrap_or_else(DMat4::identity);
+        for p in &g.parts {
+            out.push(GPUInstance {
+                transform: to_columns(&(mesh.parts[*p].transform * base)),
+                tint: [1.0; 4],
+                palette: [0.0; 4],
+            });
+        }
+    }
+    out
+}
+
+/// Returns a mesh with only the first part of each group, which is the
+/// geometry that's uploaded to the GPU.  Its parts are in group order.
+pub fn unique_geometry(mesh: &Mesh, groups: &[InstanceGroup]) -> Mesh {
+    let mut keep = vec![false; mesh.parts.len()];
+    for g in groups {
+        keep[g.parts[0]] = true;
+    }
+    mesh.select_parts(&keep)
+}
+
+/// Splits a group's instances into runs of visible parts, returning
+/// ranges in the instance buffer (which starts at `first` for this group)
+pub fn visible_runs(parts: &[usize], visible: &[bool], first: u32) -> Vec<Range<u32>> {
+    let mut out: Vec<Range<u32>> = vec![];
+    for (i, p) in parts.iter().enumerate() {
+        if visible.get(*p).copied().unwrap_or(true) {
+            let i = first + i as u32;
+            match out.last_mut() {
+                Some(r) if r.end == i => r.end += 1,
+                _ => out.push(i..(i + 1)),
+            }
+        }
+    }
+    out
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{self as glm, DVec3, DVec4, U32Vec3};
+    use triangulate::mesh::{Part, Triangle, Vertex};
+
+    /// Builds a mesh with `n` copies of a triangle (shape 1), translated
+    /// along X, followed by a single quad (shape 2)
+    fn assembly(n: usize) -> Mesh {
+        let mut mesh = Mesh::default();
+        let tri = [DVec3::zeros(), DVec3::x(), DVec3::y()];
+        let mut add = |pts: &[DVec3], tris: &[[u32; 3]], shape, transform: DMat4| {
+            let v0 = mesh.verts.len() as u32;
+            let t0 = mesh.triangles.len();
+            for p in pts {
+                let p = transform * DVec4::new(p.x, p.y, p.z, 1.0);
+                mesh.verts.push(Vertex {
+                    pos: p.xyz(),
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+            for t in tris {
+                mesh.triangles.push(Triangle {
+                    verts: U32Vec3::new(t[0], t[1], t[2]).add_scalar(v0),
+                });
+            }
+            mesh.parts.push(Part {
+                shape,
+                triangles: t0..mesh.triangles.len(),
+                transform,
+            });
+        };
+        for i in 0..n {
+            let m = glm::translation(&DVec3::new(2.0 * i as f64 + 1.0, 0.0, 0.0));
+            add(&tri, &[[0, 1, 2]], 1, m);
+        }
+        let quad = [
+            DVec3::zeros(),
+            DVec3::x(),
+            DVec3::new(1.0, 1.0, 0.0),
+            DVec3::y(),
+        ];
+        add(&quad, &[[0, 1, 2], [0, 2, 3]], 2, DMat4::identity());
+        mesh
+    }
+
+    #[test]
+    fn test_group_instances() {
+        let mesh = assembly(3);
+        let groups = group_instances(&mesh);
+        assert_eq!(
+            groups,
+            vec![
+                InstanceGroup {
+                    parts: vec![0, 1, 2]
+                },
+                InstanceGroup { parts: vec![3] },
+            ]
+        );
+
+        // Parts with a mismatched triangle count aren't grouped
+        let mut mesh = assembly(2);
+        mesh.parts[2].shape = 1;
+        assert_eq!(group_instances(&mesh).len(), 2);
+
+        // A degenerate base transform prevents instancing
+        let mut mesh = assembly(2);
+        mesh.parts[0].transform = DMat4::zeros();
+        assert_eq!(group_instances(&mesh).len(), 3);
+
+        assert!(group_instances(&Mesh::default()).is_empty());
+    }
+
+    #[test]
+    fn test_instance_transforms() {
+        let mesh = assembly(3);
+        let groups = group_instances(&mesh);
+        let data = instance_data(&mesh, &groups);
+        assert_eq!(data.len(), 4);
+
+        // Applying each instance's transform to the base part's vertices
+        // should reproduce that part's vertices.
+        for (i, inst) in data.iter().enumerate().take(3) {
+            let m = glm::make_mat4(&inst.transform.concat());
+            for k in 0..3 {
+                let p = mesh.verts[k].pos.map(|v| v as f32);
+                let q = m * glm::vec4(p.x, p.y, p.z, 1.0);
+                let expected = mesh.verts[i * 3 + k].pos.map(|v| v as f32);
+                assert!((q.xyz() - expected).norm() < 1e-6);
+            }
+            assert_eq!(inst.tint, [1.0; 4]);
+            assert_eq!(inst.palette, [0.0; 4]);
+        }
+        // The first instance in each group is the identity
+        assert_eq!(data[0].transform, to_columns(&DMat4::identity()));
+        assert_eq!(data[3].transform, to_columns(&DMat4::identity()));
+
+        // Translation is stored in the last column
+        assert_eq!(data[1].transform[3], [2.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_instance_layout() {
+        // Four columns plus a tint and a palette color, tightly packed
+        assert_eq!(std::mem::size_of::<GPUInstance>(), 96);
+        let inst = GPUInstance {
+            transform: to_columns(&DMat4::identity()),
+            tint: [0.5, 0.25, 0.125, 1.0],
+            palette: [0.75, 0.5, 0.25, 1.0],
+        };
+        let floats: &[f32] = bytemuck::cast_slice(std::slice::from_ref(&inst));
+        assert_eq!(floats[0], 1.0);
+        assert_eq!(floats[5], 1.0);
+        assert_eq!(floats[16..20], [0.5, 0.25, 0.125, 1.0]);
+        assert_eq!(floats[20..], [0.75, 0.5, 0.25, 1.0]);
+    }
+
+    #[test]
+    fn test_unique_geometry() {
+        // Many copies of the same shape only upload one copy of its
+        // vertices
+        let mesh = assembly(100);
+        let groups = group_instances(&mesh);
+        let unique = unique_geometry(&mesh, &groups);
+        assert_eq!(mesh.verts.len(), 304);
+        assert_eq!(unique.verts.len(), 7);
+        assert_eq!(unique.triangles.len(), 3);
+        assert_eq!(unique.parts.len(), groups.len());
+        assert_eq!(unique.parts[1].triangles, 1..3);
+    }
+
+    #[test]
+    fn test_visible_runs() {
+        let parts = [0, 2, 3, 5, 6];
+        let visible = [true, true, true, false, true, false, true];
+        assert_eq!(visible_runs(&parts, &visible, 10), vec![10..12, 14..15]);
+        assert_eq!(visible_runs(&parts, &[true; 7], 0), vec![0..5]);
+        assert!(visible_runs(&parts, &[false; 7], 0).is_empty());
+    }
+}
@@ -0,0 +1,630 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+use crate::bookmarks::key_slot;
+
+/// Name of the keymap file, which lives in `foxtrot` in the platform's
+/// config directory
+pub const KEYMAP_FILE: &str = "keymap.toml";
+
+/// Something that can be triggered from the keyboard
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Action {
+    Quit,
+    FitView,
+    ResetTarget,
+    HidePart,
+    IsolatePart,
+    UnhideAll,
+    CycleColorMode,
+    ToggleNormals,
+    ToggleEdges,
+    ToggleCurvature,
+    ToggleTheme,
+    CyclePresentMode,
+    ToggleCulling,
+    ToggleGrid,
+    Export,
+    /// Steps through the problems found while loading the model,
+    /// highlighting the parts they affect
+    NextDiagnostic,
+    /// Hides the banner which summarizes problems from loading
+    DismissDiagnostics,
+    /// Moves the model onto its principal axes, or back again
+    AlignPrincipalAxes,
+    /// Triangulates the selected face (or parts) again with half the
+    /// tolerance, for a finer mesh
+    RefineFaces,
+    /// Triangulates the selected face (or parts) again with twice the
+    /// tolerance, for a coarser mesh
+    CoarsenFaces,
+    /// Steps through the material presets (matte, satin, glossy)
+    CycleMaterial,
+    /// Switches between lighting from the camera and from a fixed direction
+    ToggleHeadlight,
+    /// Shows or hides palette colors on parts without a color
+    TogglePalette,
+    /// Cuts the model open with a section plane facing the viewer, or
+    /// removes the plane
+    ToggleSection,
+    /// Animates to the bookmark in the given slot (1-9)
+    Bookmark(u8),
+    /// Stores the camera in the given bookmark slot (1-9)
+    SaveBookmark(u8),
+    /// Shows or hides the model from the given input file (1-9), when
+    /// several files are tiled side by side
+    ToggleSlot(u8),
+    /// Shows or hides every part on the given presentation layer (1-9),
+    /// in the order they're listed in the STEP file
+    ToggleLayer(u8),
+}
+
+impl Action {
+    /// Returns every action, in the order they're listed by `--print-keymap`
+    pub fn all() -> Vec<Action> {
+        use Action::*;
+        let mut out = vec![
+            Quit,
+            FitView,
+            ResetTarget,
+            HidePart,
+            IsolatePart,
+            UnhideAll,
+            CycleColorMode,
+            ToggleNormals,
+            ToggleEdges,
+            ToggleCurvature,
+            ToggleTheme,
+            CyclePresentMode,
+            ToggleCulling,
+            ToggleGrid,
+            Export,
+            NextDiagnostic,
+            DismissDiagnostics,
+            AlignPrincipalAxes,
+            RefineFaces,
+            CoarsenFaces,
+            CycleMaterial,
+            ToggleHeadlight,
+            TogglePalette,
+            ToggleSection,
+        ];
+        out.extend((1..=9).map(Bookmark));
+        out.extend((1..=9).map(SaveBookmark));
+        out.extend((1..=9).map(ToggleSlot));
+        out.extend((1..=9).map(ToggleLayer));
+        out
+    }
+
+    /// Returns the name used in the keymap file
+    pub fn name(&self) -> String {
+        use Action::*;
+        match self {
+            Quit => "quit".to_owned(),
+            FitView => "fit_view".to_owned(),
+            ResetTarget => "reset_target".to_owned(),
+            HidePart => "hide_part".to_owned(),
+            IsolatePart => "isolate_part".to_owned(),
+            UnhideAll => "unhide_all".to_owned(),
+            CycleColorMode => "cycle_color_mode".to_owned(),
+            ToggleNormals => "toggle_normals".to_owned(),
+            ToggleEdges => "toggle_edges".to_owned(),
+            ToggleCurvature => "toggle_curvature".to_owned(),
+            ToggleTheme => "toggle_theme".to_owned(),
+            CyclePresentMode => "cycle_present_mode".to_owned(),
+            ToggleCulling => "toggle_culling".to_owned(),
+            ToggleGrid => "toggle_grid".to_owned(),
+            Export => "export".to_owned(),
+            NextDiagnostic => "next_diagnostic".to_owned(),
+            DismissDiagnostics => "dismiss_diagnostics".to_owned(),
+            AlignPrincipalAxes => "align_principal_axes".to_owned(),
+            RefineFaces => "refine_faces".to_owned(),
+            CoarsenFaces => "coarsen_faces".to_owned(),
+            CycleMaterial => "cycle_material".to_owned(),
+            ToggleHeadlight => "toggle_headlight".to_owned(),
+            TogglePalette => "toggle_palette".to_owned(),
+            ToggleSection => "toggle_section".to_owned(),
+            Bookmark(i) => format!("bookmark_{}", i),
+            SaveBookmark(i) => format!("save_bookmark_{}", i),
+            ToggleSlot(i) => format!("toggle_slot_{}", i),
+            ToggleLayer(i) => format!("toggle_layer_{}", i),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Self::all().into_iter().find(|a| a.name() == name)
+    }
+}
+
+/// Key names, as written in the keymap file
+const KEY_NAMES: &[(&str, VirtualKeyCode)] = {
+    use VirtualKeyCode::*;
+    &[
+        ("A", A),
+        ("B", B),
+        ("C", C),
+        ("D", D),
+        ("E", E),
+        ("F", F),
+        ("G", G),
+        ("H", H),
+        ("I", I),
+        ("J", J),
+        ("K", K),
+        ("L", L),
+        ("M", M),
+        ("N", N),
+        ("O", O),
+        ("P", P),
+        ("Q", Q),
+        ("R", R),
+        ("S", S),
+        ("T", T),
+        ("U", U),
+        ("V", V),
+        ("W", W),
+        ("X", X),
+        ("Y", Y),
+        ("Z", Z),
+        ("0", Key0),
+        ("1", Key1),
+        ("2", Key2),
+        ("3", Key3),
+        ("4", Key4),
+        ("5", Key5),
+        ("6", Key6),
+        ("7", Key7),
+        ("8", Key8),
+        ("9", Key9),
+        ("F1", F1),
+        ("F2", F2),
+        ("F3", F3),
+        ("F4", F4),
+        ("F5", F5),
+        ("F6", F6),
+        ("F7", F7),
+        ("F8", F8),
+        ("F9", F9),
+        ("F10", F10),
+        ("F11", F11),
+        ("F12", F12),
+        ("Home", Home),
+        ("End", End),
+        ("PageUp", PageUp),
+        ("PageDown", PageDown),
+        ("Insert", Insert),
+        ("Delete", Delete),
+        ("Backspace", Back),
+        ("Escape", Escape),
+        ("Space", Space),
+        ("Tab", Tab),
+        ("Enter", Return),
+        ("Left", Left),
+        ("Right", Right),
+        ("Up", Up),
+        ("Down", Down),
+        ("Minus", Minus),
+        ("Equals", Equals),
+        ("Comma", Comma),
+        ("Period", Period),
+        ("Slash", Slash),
+    ]
+};
+
+/// A key plus the modifiers which must be held with it
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Binding {
+    pub key: VirtualKeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl Binding {
+    pub fn new(key: VirtualKeyCode, mods: ModifiersState) -> Self {
+        Binding {
+            key,
+            ctrl: mods.ctrl(),
+            shift: mods.shift(),
+            alt: mods.alt(),
+            logo: mods.logo(),
+        }
+    }
+
+    fn key(key: VirtualKeyCode) -> Self {
+        Self::new(key, ModifiersState::empty())
+    }
+
+    fn with(mut self, mods: ModifiersState) -> Self {
+        self.ctrl |= mods.ctrl();
+        self.shift |= mods.shift();
+        self.alt |= mods.alt();
+        self.logo |= mods.logo();
+        self
+    }
+
+    /// Parses a binding like `Ctrl+Shift+E`.  Modifier and key names are
+    /// case-insensitive.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop()?;
+        let key = KEY_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))?
+            .1;
+        let mut out = Binding::key(key);
+        for m in parts {
+            let m = match m.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => ModifiersState::CTRL,
+                "shift" => ModifiersState::SHIFT,
+                "alt" | "option" => ModifiersState::ALT,
+                "logo" | "cmd" | "super" | "win" => ModifiersState::LOGO,
+                _ => return None,
+            };
+            out = out.with(m);
+        }
+        Some(out)
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+            (self.logo, "Logo+"),
+        ] {
+            if held {
+                write!(f, "{}", name)?;
+            }
+        }
+        let name = KEY_NAMES
+            .iter()
+            .find(|(_, k)| *k == self.key)
+            .map(|(name, _)| *name)
+            .unwrap_or("?");
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns the built-in bindings
+fn defaults() -> Vec<(Action, Binding)> {
+    use Action::*;
+    use VirtualKeyCode as K;
+    let mut out = vec![
+        (Quit, Binding::key(K::Q).with(ModifiersState::LOGO)),
+        (FitView, Binding::key(K::F)),
+        (ResetTarget, Binding::key(K::Home)),
+        (HidePart, Binding::key(K::H)),
+        (IsolatePart, Binding::key(K::I)),
+        (UnhideAll, Binding::key(K::U)),
+        (CycleColorMode, Binding::key(K::D)),
+        (
+            ToggleNormals,
+            Binding::key(K::N).with(ModifiersState::SHIFT),
+        ),
+        (ToggleEdges, Binding::key(K::E)),
+        (
+            ToggleCurvature,
+            Binding::key(K::C).with(ModifiersState::SHIFT),
+        ),
+        (ToggleTheme, Binding::key(K::T)),
+        (CyclePresentMode, Binding::key(K::V)),
+        (ToggleCulling, Binding::key(K::B)),
+        (ToggleGrid, Binding::key(K::G)),
+        (Export, Binding::key(K::E).with(ModifiersState::CTRL)),
+        (
+            NextDiagnostic,
+            Binding::key(K::E).with(ModifiersState::SHIFT),
+        ),
+        (DismissDiagnostics, Binding::key(K::Escape)),
+        (AlignPrincipalAxes, Binding::key(K::A)),
+        (RefineFaces, Binding::key(K::Equals)),
+        (CoarsenFaces, Binding::key(K::Minus)),
+        (CycleMaterial, Binding::key(K::M)),
+        (ToggleHeadlight, Binding::key(K::L)),
+        (TogglePalette, Binding::key(K::P)),
+        (ToggleSection, Binding::key(K::S)),
+    ];
+    for (_, key) in KEY_NAMES {
+        if let Some(slot) = key_slot(*key) {
+            out.push((Bookmark(slot), Binding::key(*key)));
+            let save = Binding::key(*key).with(ModifiersState::CTRL);
+            out.push((SaveBookmark(slot), save));
+            let toggle = Binding::key(*key).with(ModifiersState::ALT);
+            out.push((ToggleSlot(slot), toggle));
+            let layer = toggle.with(ModifiersState::SHIFT);
+            out.push((ToggleLayer(slot), layer));
+        }
+    }
+    out
+}
+
+/// Maps key presses to actions
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: BTreeMap<Action, Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: defaults().into_iter().collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Builds a keymap from a TOML file of `action = "binding"` lines, which
+    /// replace the defaults for those actions.  An empty binding (`""`)
+    /// unbinds an action.
+    ///
+    /// Returns the keymap along with warnings for anything that was
+    /// skipped: unknown actions, unparseable bindings, and keys bound to
+    /// more than one action (in which case the action from the file wins).
+    pub fn from_toml(text: &str) -> Result<(Self, Vec<String>), String> {
+        let table: BTreeMap<String, String> = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut out = Keymap::default();
+        let mut warnings = vec![];
+        let mut custom = vec![];
+        for (name, binding) in &table {
+            let action = match Action::from_name(name) {
+                Some(a) => a,
+                None => {
+                    warnings.push(format!("Unknown action {:?}", name));
+                    continue;
+                }
+            };
+            if binding.trim().is_empty() {
+                out.bindings.remove(&action);
+                continue;
+            }
+            match Binding::parse(binding) {
+                Some(b) => {
+                    out.bindings.insert(action, b);
+                    custom.push(action);
+                }
+                None => warnings.push(format!("Invalid binding {:?} for {}", binding, name)),
+            }
+        }
+
+        // Resolve conflicts, preferring actions which were set in the file
+        for (binding, actions) in out.conflicts() {
+            let keep = actions
+                .iter()
+                .find(|a| custom.contains(a))
+                .unwrap_or(&actions[0]);
+            let names: Vec<_> = actions.iter().map(|a| a.name()).collect();
+            warnings.push(format!(
+                "{} is bound to {}; using {}",
+                binding,
+                names.join(" and "),
+                keep.name()
+            ));
+            for a in &actions {
+                if a != keep {
+                    out.bindings.remove(a);
+                }
+            }
+        }
+        Ok((out, warnings))
+    }
+
+    /// Returns bindings which are shared by multiple actions
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<Action>)> {
+        let mut by_binding: HashMap<Binding, Vec<Action>> = HashMap::new();
+        for (a, b) in &self.bindings {
+            by_binding.entry(*b).or_default().push(*a);
+        }
+        let mut out: Vec<_> = by_binding
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .collect();
+        out.sort_by_key(|(_, actions)| actions[0]);
+        out
+    }
+
+    /// Returns the path of the keymap file, if there's a config directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("foxtrot").join(KEYMAP_FILE))
+    }
+
+    /// Loads the keymap file (if it exists), printing warnings for any
+    /// problems and falling back to the defaults if it can't be parsed
+    pub fn load(path: Option<&Path>) -> Self {
+        let (path, text) = match path.and_then(|p| Some((p, std::fs::read_to_string(p).ok()?))) {
+            Some(t) => t,
+            None => return Keymap::default(),
+        };
+        match Self::from_toml(&text) {
+            Ok((keymap, warnings)) => {
+                for w in warnings {
+                    eprintln!("Warning: {:?}: {}", path, w);
+                }
+                keymap
+            }
+            Err(e) => {
+                eprintln!("Warning: ignoring {:?}: {}", path, e);
+                Keymap::default()
+            }
+        }
+    }
+
+    /// Looks up the action for a key press
+    pub fn action(&self, key: VirtualKeyCode, mods: ModifiersState) -> Option<Action> {
+        let b = Binding::new(key, mods);
+        self.bindings
+            .iter()
+            .find(|(_, v)| **v == b)
+            .map(|(a, _)| *a)
+    }
+
+    /// Returns the effective bindings as a TOML file, which can be used as
+    /// a starting point for `keymap.toml`
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        for a in Action::all() {
+            let b = self.bindings.get(&a).map(|b| b.to_string());
+            out += &format!("{} = {:?}\n", a.name(), b.unwrap_or_default());
+        }
+        out
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_binding() {
+        let b = Binding::parse("Ctrl+Shift+E").unwrap();
+        assert_eq!(b.key, VirtualKeyCode::E);
+        assert!(b.ctrl && b.shift && !b.alt && !b.logo);
+        assert_eq!(b.to_string(), "Ctrl+Shift+E");
+
+        assert_eq!(Binding::parse("cmd + q"), Binding::parse("Logo+Q"));
+        assert_eq!(
+            Binding::parse("home"),
+            Some(Binding::key(VirtualKeyCode::Home))
+        );
+        assert_eq!(
+            Binding::parse("3"),
+            Some(Binding::key(VirtualKeyCode::Key3))
+        );
+        for bad in ["", "Ctrl+", "Hyper+A", "Ctrl+Banana", "AB"] {
+            assert_eq!(Binding::parse(bad), None, "{}", bad);
+        }
+
+        // Every key name round-trips
+        for (name, key) in KEY_NAMES {
+            let b = Binding::key(*key);
+            assert_eq!(b.to_string(), *name);
+            assert_eq!(Binding::parse(name), Some(b));
+        }
+    }
+
+    #[test]
+    fn test_action_names() {
+        for a in Action::all() {
+            assert_eq!(Action::from_name(&a.name()), Some(a));
+        }
+        assert_eq!(Action::from_name("bookmark_3"), Some(Action::Bookmark(3)));
+        assert_eq!(Action::from_name("bookmark_0"), None);
+        assert_eq!(Action::from_name("fly"), None);
+    }
+
+    #[test]
+    fn test_defaults() {
+        let k = Keymap::default();
+        assert!(k.conflicts().is_empty());
+        let none = ModifiersState::empty();
+        assert_eq!(k.action(VirtualKeyCode::F, none), Some(Action::FitView));
+        assert_eq!(k.action(VirtualKeyCode::N, none), None);
+        assert_eq!(
+            k.action(VirtualKeyCode::N, ModifiersState::SHIFT),
+            Some(Action::ToggleNormals)
+        );
+        assert_eq!(
+            k.action(VirtualKeyCode::Key4, none),
+            Some(Action::Bookmark(4))
+        );
+        assert_eq!(
+            k.action(VirtualKeyCode::Key4, ModifiersState::CTRL),
+            Some(Action::SaveBookmark(4))
+        );
+        assert_eq!(
+            k.action(VirtualKeyCode::Key4, ModifiersState::ALT),
+            Some(Action::ToggleSlot(4))
+        );
+        assert_eq!(
+            k.action(
+                VirtualKeyCode::Key2,
+                ModifiersState::ALT | ModifiersState::SHIFT
+            ),
+            Some(Action::ToggleLayer(2))
+        );
+        // Modifiers must match exactly
+        assert_eq!(k.action(VirtualKeyCode::F, ModifiersState::ALT), None);
+
+        // Every action has a default binding
+        for a in Action::all() {
+            assert!(k.bindings.contains_key(&a), "{:?}", a);
+        }
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let text = "fit_view = \"Space\"\ntoggle_grid = \"\"\n";
+        let (k, warnings) = Keymap::from_toml(text).unwrap();
+        assert!(warnings.is_empty());
+        let none = ModifiersState::empty();
+        assert_eq!(k.action(VirtualKeyCode::Space, none), Some(Action::FitView));
+        assert_eq!(k.action(VirtualKeyCode::F, none), None);
+        assert_eq!(k.action(VirtualKeyCode::G, none), None);
+        // Other bindings are unchanged
+        assert_eq!(k.action(VirtualKeyCode::T, none), Some(Action::ToggleTheme));
+
+        let text = "fly = \"X\"\nexport = \"Ctrl+Banana\"\n";
+        let (k, warnings) = Keymap::from_toml(text).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("Ctrl+Banana"));
+        assert!(warnings[1].contains("fly"));
+        assert_eq!(k, Keymap::default());
+
+        assert!(Keymap::from_toml("fit_view = 3").is_err());
+        assert!(Keymap::from_toml("fit_view = ").is_err());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        // Moving an action onto another's key keeps the one from the file
+        let (k, warnings) = Keymap::from_toml("fit_view = \"G\"\n").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("G is bound to"), "{}", warnings[0]);
+        assert!(k.conflicts().is_empty());
+        let none = ModifiersState::empty();
+        assert_eq!(k.action(VirtualKeyCode::G, none), Some(Action::FitView));
+
+        // Two actions from the file with the same key
+        let text = "fit_view = \"X\"\ntoggle_grid = \"X\"\n";
+        let (k, warnings) = Keymap::from_toml(text).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(k.conflicts().is_empty());
+        assert_eq!(k.action(VirtualKeyCode::X, none), Some(Action::FitView));
+
+        let mut k = Keymap::default();
+        k.bindings
+            .insert(Action::Quit, Binding::key(VirtualKeyCode::F));
+        assert_eq!(
+            k.conflicts(),
+            vec![(
+                Binding::key(VirtualKeyCode::F),
+                vec![Action::Quit, Action::FitView]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (k, _) = Keymap::from_toml("fit_view = \"Ctrl+Alt+F5\"\nquit = \"\"").unwrap();
+        let text = k.to_toml();
+        assert!(text.contains("fit_view = \"Ctrl+Alt+F5\"\n"));
+        assert!(text.contains("quit = \"\"\n"));
+        let (again, warnings) = Keymap::from_toml(&text).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(again, k);
+    }
+
+    #[test]
+    fn test_load() {
+        assert_eq!(Keymap::load(None), Keymap::default());
+        let missing = std::env::temp_dir().join("foxtrot-no-such-keymap.toml");
+        assert_eq!(Keymap::load(Some(&missing)), Keymap::default());
+    }
+}
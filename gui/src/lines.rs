@@ -0,0 +1,455 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use glm::{Mat4, Vec2, Vec3};
+use nalgebra_glm as glm;
+use wgpu::util::DeviceExt;
+
+use crate::gizmo::Viewport;
+
+/// Width of the antialiased fringe around each line, in physical pixels
+const FEATHER: f32 = 1.0;
+
+/// A line segment, which is drawn as one instance of a screen-space quad
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Segment {
+    pub a: [f32; 4],
+    pub b: [f32; 4],
+    pub color: [f32; 4],
+}
+
+impl Segment {
+    pub fn new(a: Vec3, b: Vec3, color: [f32; 4]) -> Self {
+        Self {
+            a: [a.x, a.y, a.z, 1.0],
+            b: [b.x, b.y, b.z, 1.0],
+            color,
+        }
+    }
+}
+
+/// How a set of lines is drawn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LineStyle {
+    /// Line width, in logical pixels
+    pub width: f32,
+    /// Pushes lines away from the viewer, in normalized depth units
+    pub depth_bias: f32,
+    /// Center and radius of a circle (in model space) outside which lines
+    /// fade out
+    pub fade: Option<(Vec2, f32)>,
+    /// Section plane (as `(normal, -normal · origin)` in model space),
+    /// on whose positive side lines are cut away
+    pub clip: Option<[f32; 4]>,
+}
+
+impl LineStyle {
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            depth_bias: 0.0,
+            fade: None,
+            clip: None,
+        }
+    }
+}
+
+/// Uniforms for `lines.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct LineUniforms {
+    mat: [[f32; 4]; 4],
+    /// Viewport size, line width, and feather width, in physical pixels
+    viewport: [f32; 4],
+    /// Depth bias, then the fade circle's center and radius
+    params: [f32; 4],
+    /// Section plane, or zero to keep every line
+    clip: [f32; 4],
+}
+
+impl LineUniforms {
+    fn new(mat: &Mat4, size: Vec2, style: &LineStyle, scale_factor: f32) -> Self {
+        let (center, radius) = style.fade.unwrap_or((Vec2::zeros(), 0.0));
+        Self {
+            mat: (*mat).into(),
+            viewport: [size.x, size.y, style.width * scale_factor, FEATHER],
+            params: [style.depth_bias, center.x, center.y, radius],
+            clip: style.clip.unwrap_or_default(),
+        }
+    }
+}
+
+/// One corner of the quad which covers a segment, as computed by
+/// `vs_main` in `lines.wgsl`
+#[cfg(test)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct QuadCorner {
+    /// Position in pixels, relative to the viewport's center
+    pos: Vec2,
+    /// Signed distance from the segment's center line, in pixels
+    across: f32,
+    /// Distance along the segment from its start, in pixels
+    along: f32,
+}
+
+/// Expands a segment between two points (in pixels) into one corner of a
+/// quad, for `corner` in `0..6` (two triangles).  The quad is wide enough
+/// for the line and its antialiased fringe, and extends past both ends by
+/// the same margin.  Zero-length segments become a square dot.
+///
+/// Returns the corner and the segment's length.
+#[cfg(test)]
+fn expand(a: Vec2, b: Vec2, corner: u32, width: f32, feather: f32) -> (QuadCorner, f32) {
+    let d = b - a;
+    let len = d.norm();
+    let dir = if len > 1e-6 { d / len } else { Vec2::x() };
+    let normal = Vec2::new(-dir.y, dir.x);
+    let half_width = width / 2.0 + feather;
+
+    let (t, side) = match corner {
+        1 | 4 => (1.0, -1.0),
+        2 | 3 => (0.0, 1.0),
+        5 => (1.0, 1.0),
+        _ => (0.0, -1.0),
+    };
+    let along = -half_width + t * (len + 2.0 * half_width);
+    let pos = a + dir * along + normal * side * half_width;
+    let c = QuadCorner {
+        pos,
+        across: side * half_width,
+        along,
+    };
+    (c, len)
+}
+
+/// Returns the fraction of a pixel covered by a line, given the pixel's
+/// distance across and along it (as interpolated from [`QuadCorner`]).
+/// The line has square caps, and its edges fade out over `feather` pixels.
+#[cfg(test)]
+fn coverage(across: f32, along: f32, len: f32, width: f32, feather: f32) -> f32 {
+    let beyond = (-along).max(along - len).max(0.0);
+    let dist = across.abs().max(beyond);
+    (0.5 + (width / 2.0 - dist) / feather).clamp(0.0, 1.0)
+}
+
+/// Where lines are drawn in the current frame
+pub struct LineTarget<'a> {
+    pub view: &'a wgpu::TextureView,
+    /// Depth buffer to test against, for lines which use depth testing
+    pub depth: Option<&'a wgpu::TextureView>,
+    pub scale_factor: f32,
+    /// Section plane which overlays on the model are cut by, if any
+    pub clip: Option<[f32; 4]>,
+}
+
+/// A set of antialiased line segments, drawn as instanced quads which are
+/// expanded in screen space
+pub struct Lines {
+    instance_buf: wgpu::Buffer,
+    count: u32,
+    uniform_buf: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    /// Depth comparison, or `None` to draw lines on top of everything
+    depth_compare: Option<wgpu::CompareFunction>,
+}
+
+impl Lines {
+    pub fn new(
+        device: &wgpu::Device,
+        swapchain_format: wgpu::TextureFormat,
+        segments: &[Segment],
+        depth_compare: Option<wgpu::CompareFunction>,
+    ) -> Self {
+        // Empty vertex buffers aren't allowed, so pad with a single segment
+        // (which is never drawn)
+        let padding = [Segment::new(Vec3::zeros(), Vec3::zeros(), [0.0; 4])];
+        let instance_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Line segment buffer"),
+            contents: bytemuck::cast_slice(if segments.is_empty() {
+                &padding
+            } else {
+                segments
+            }),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let uniform_size = std::mem::size_of::<LineUniforms>();
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Line uniform buffer"),
+            size: uniform_size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(uniform_size as u64),
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+            label: None,
+        });
+
+        // Load the shaders from disk, either at runtime or compile-time
+        #[cfg(feature = "bundle-shaders")]
+        let lines_src = Cow::Borrowed(include_str!("lines.wgsl"));
+
+        #[cfg(not(feature = "bundle-shaders"))]
+        let lines_src = Cow::Owned(
+            String::from_utf8(std::fs::read("gui/src/lines.wgsl").expect("Could not read shader"))
+                .expect("Shader is invalid UTF-8"),
+        );
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(lines_src),
+            flags: wgpu::ShaderFlags::all(),
+        });
+
+        // Lines are translucent at their edges, so they never write depth
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Segment>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x4,
+                        1 => Float32x4,
+                        2 => Float32x4,
+                    ],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: swapchain_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: depth_compare.map(|depth_compare| wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        Lines {
+            instance_buf,
+            count: segments.len() as u32,
+            uniform_buf,
+            bind_group,
+            render_pipeline,
+            depth_compare,
+        }
+    }
+
+    /// Number of segments
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Uploads the transform (from model space to clip space) and style,
+    /// for a viewport of the given size in physical pixels
+    pub fn set_uniforms(
+        &self,
+        queue: &wgpu::Queue,
+        mat: &Mat4,
+        size: Vec2,
+        style: &LineStyle,
+        scale_factor: f32,
+    ) {
+        let u = LineUniforms::new(mat, size, style, scale_factor);
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&u));
+    }
+
+    /// Draws the given ranges of segments, optionally within a viewport
+    pub fn draw(
+        &self,
+        target: &LineTarget,
+        viewport: Option<Viewport>,
+        ranges: impl IntoIterator<Item = Range<u32>>,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let depth = match (self.depth_compare, target.depth) {
+            (Some(_), Some(view)) => Some(view),
+            (Some(_), None) => return,
+            (None, _) => None,
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: target.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: depth.map(|view| wgpu::RenderPassDepthStencilAttachment {
+                view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        if let Some(v) = viewport {
+            rpass.set_viewport(v.x, v.y, v.width, v.height, 0.0, 1.0);
+        }
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_vertex_buffer(0, self.instance_buf.slice(..));
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        for r in ranges {
+            if !r.is_empty() {
+                rpass.draw(0..6, r);
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corners(a: Vec2, b: Vec2, width: f32) -> Vec<(QuadCorner, f32)> {
+        (0..6).map(|i| expand(a, b, i, width, FEATHER)).collect()
+    }
+
+    #[test]
+    fn test_expand_horizontal() {
+        let a = Vec2::new(-10.0, 5.0);
+        let b = Vec2::new(20.0, 5.0);
+        let c = corners(a, b, 2.0);
+        let half = 1.0 + FEATHER;
+        assert!(c.iter().all(|(_, len)| *len == 30.0));
+
+        // Both triangles share the diagonal corners
+        assert_eq!(c[1].0, c[4].0);
+        assert_eq!(c[2].0, c[3].0);
+
+        // The quad extends past both ends and both sides by the half-width
+        assert_eq!(c[0].0.pos, Vec2::new(-10.0 - half, 5.0 - half));
+        assert_eq!(c[1].0.pos, Vec2::new(20.0 + half, 5.0 - half));
+        assert_eq!(c[2].0.pos, Vec2::new(-10.0 - half, 5.0 + half));
+        assert_eq!(c[5].0.pos, Vec2::new(20.0 + half, 5.0 + half));
+
+        assert_eq!(c[0].0.across, -half);
+        assert_eq!(c[5].0.across, half);
+        assert_eq!(c[0].0.along, -half);
+        assert_eq!(c[5].0.along, 30.0 + half);
+    }
+
+    #[test]
+    fn test_expand_width_is_in_pixels() {
+        // Diagonal segments are just as wide as horizontal ones
+        let a = Vec2::new(0.0, 0.0);
+        let b = Vec2::new(30.0, 40.0);
+        let c = corners(a, b, 4.0);
+        let dir = (b - a).normalize();
+        let normal = Vec2::new(-dir.y, dir.x);
+        let width = (c[2].0.pos - c[0].0.pos).dot(&normal);
+        assert!((width - (4.0 + 2.0 * FEATHER)).abs() < 1e-4);
+        let length = (c[1].0.pos - c[0].0.pos).dot(&dir);
+        assert!((length - (50.0 + 4.0 + 2.0 * FEATHER)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_expand_degenerate() {
+        // A zero-length segment becomes a square dot around the point
+        let p = Vec2::new(3.0, -7.0);
+        let c = corners(p, p, 2.0);
+        let half = 1.0 + FEATHER;
+        for (q, len) in &c {
+            assert_eq!(*len, 0.0);
+            assert!(q.pos.x.is_finite() && q.pos.y.is_finite());
+            assert_eq!((q.pos - p).abs(), Vec2::repeat(half));
+        }
+        // The dot has full coverage at its center
+        assert_eq!(coverage(0.0, 0.0, 0.0, 2.0, FEATHER), 1.0);
+
+        // Nearly-degenerate segments don't blow up either
+        let c = corners(p, p + Vec2::new(1e-9, 0.0), 2.0);
+        assert!(c.iter().all(|(q, _)| q.pos.x.is_finite()));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let cov = |across, along| coverage(across, along, 10.0, 2.0, FEATHER);
+        // Fully covered within the line
+        assert_eq!(cov(0.0, 5.0), 1.0);
+        assert_eq!(cov(0.5, 0.0), 1.0);
+        // Half-covered at the line's edge, fading out over the feather
+        assert_eq!(cov(1.0, 5.0), 0.5);
+        assert_eq!(cov(-1.0, 5.0), 0.5);
+        assert_eq!(cov(1.5, 5.0), 0.0);
+        // The same is true past the ends (square caps)
+        assert_eq!(cov(0.0, -1.0), 0.5);
+        assert_eq!(cov(0.0, 11.0), 0.5);
+        assert_eq!(cov(0.0, 12.0), 0.0);
+
+        // The quad's outer edge is always fully transparent
+        let (c, len) = expand(Vec2::zeros(), Vec2::new(10.0, 0.0), 0, 2.0, FEATHER);
+        assert_eq!(coverage(c.across, c.along, len, 2.0, FEATHER), 0.0);
+    }
+
+    #[test]
+    fn test_uniforms() {
+        assert_eq!(std::mem::size_of::<LineUniforms>(), 112);
+        let style = LineStyle {
+            width: 1.5,
+            depth_bias: 1e-4,
+            fade: Some((Vec2::new(1.0, 2.0), 3.0)),
+            clip: Some([0.0, 1.0, 0.0, -0.5]),
+        };
+        let u = LineUniforms::new(&Mat4::identity(), Vec2::new(800.0, 600.0), &style, 2.0);
+        assert_eq!(u.viewport, [800.0, 600.0, 3.0, FEATHER]);
+        assert_eq!(u.params, [1e-4, 1.0, 2.0, 3.0]);
+        assert_eq!(u.clip, [0.0, 1.0, 0.0, -0.5]);
+
+        // Without fading, the radius is zero (which the shader checks)
+        let u = LineUniforms::new(
+            &Mat4::identity(),
+            Vec2::new(1.0, 1.0),
+            &LineStyle::new(1.0),
+            1.0,
+        );
+        assert_eq!(u.params[3], 0.0);
+        assert_eq!(u.clip, [0.0; 4]);
+    }
+}
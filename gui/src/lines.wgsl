@@ -0,0 +1,113 @@
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+    // Distance from the line's center (x) and along the line from its
+    // start (y), in pixels
+    [[location(1)]] edge: vec2<f32>;
+    // Length of the line in pixels (x), then the model-space position
+    // (zw), which is used to fade out lines
+    [[location(2)]] extra: vec4<f32>;
+    // Model-space position, which is tested against the section plane
+    [[location(3)]] model: vec3<f32>;
+};
+
+[[block]]
+struct Locals {
+    mat: mat4x4<f32>;
+    // Viewport size (xy), line width (z), and antialiasing width (w), all
+    // in physical pixels
+    viewport: vec4<f32>;
+    // Depth bias (x), then the center (yz) and radius (w) of a circle in
+    // model space outside which lines fade out.  A zero radius disables
+    // fading.
+    params: vec4<f32>;
+    // Section plane, as (normal, -normal · origin) in model space; lines on
+    // the side which it faces are cut away.  A zero plane keeps every line.
+    clip: vec4<f32>;
+};
+[[group(0), binding(0)]]
+var r_locals: Locals;
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] in_vertex_index: u32,
+    // Per-segment endpoints and color
+    [[location(0)]] a: vec4<f32>,
+    [[location(1)]] b: vec4<f32>,
+    [[location(2)]] color: vec4<f32>,
+) -> VertexOutput {
+    var pa: vec4<f32> = r_locals.mat * vec4<f32>(a.xyz, 1.0);
+    var pb: vec4<f32> = r_locals.mat * vec4<f32>(b.xyz, 1.0);
+
+    // Work in pixels, relative to the center of the viewport.  This must
+    // match `expand` in lines.rs.
+    var half_size: vec2<f32> = r_locals.viewport.xy * 0.5;
+    var sa: vec2<f32> = pa.xy / pa.w * half_size;
+    var sb: vec2<f32> = pb.xy / pb.w * half_size;
+    var d: vec2<f32> = sb - sa;
+    var len: f32 = length(d);
+    // Zero-length segments are drawn as a dot, facing an arbitrary way
+    var dir: vec2<f32> = vec2<f32>(1.0, 0.0);
+    if (len > 0.000001) {
+        dir = d / len;
+    }
+    var normal: vec2<f32> = vec2<f32>(-dir.y, dir.x);
+    var half_width: f32 = r_locals.viewport.z * 0.5 + r_locals.viewport.w;
+
+    // Two triangles, covering the segment plus a margin on every side
+    var t: f32 = 0.0;
+    var side: f32 = -1.0;
+    if (in_vertex_index == 1u || in_vertex_index == 4u) {
+        t = 1.0;
+    } elseif (in_vertex_index == 2u || in_vertex_index == 3u) {
+        side = 1.0;
+    } elseif (in_vertex_index == 5u) {
+        t = 1.0;
+        side = 1.0;
+    }
+    var along: f32 = -half_width + t * (len + 2.0 * half_width);
+    var s: vec2<f32> = sa + dir * along + normal * side * half_width;
+    var p: vec4<f32> = pa;
+    if (t > 0.5) {
+        p = pb;
+    }
+
+    var out: VertexOutput;
+    // Smaller depth is farther away, so the bias pushes lines back
+    out.position = vec4<f32>(s / half_size * p.w, p.z - r_locals.params.x * p.w, p.w);
+    out.color = color;
+    out.edge = vec2<f32>(side * half_width, along);
+    // Extend the model-space position past the ends of the segment along
+    // with the quad, so that it interpolates correctly
+    var u: f32 = t;
+    if (len > 0.000001) {
+        u = along / len;
+    }
+    var q: vec3<f32> = mix(a.xyz, b.xyz, vec3<f32>(u, u, u));
+    out.extra = vec4<f32>(len, q.x, q.y, 0.0);
+    out.model = q;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    if (dot(r_locals.clip.xyz, in.model) + r_locals.clip.w > 0.0) {
+        discard;
+    }
+    // Distance from the segment, treating its ends as square caps.  This
+    // must match `coverage` in lines.rs.
+    var across: f32 = abs(in.edge.x);
+    var beyond: f32 = max(max(-in.edge.y, in.edge.y - in.extra.x), 0.0);
+    var dist: f32 = max(across, beyond);
+    var feather: f32 = r_locals.viewport.w;
+    var coverage: f32 = clamp(0.5 + (r_locals.viewport.z * 0.5 - dist) / feather, 0.0, 1.0);
+    var alpha: f32 = in.color.a * coverage;
+    if (r_locals.params.w > 0.0) {
+        var fade: f32 = length(in.extra.zw - r_locals.params.yz) / r_locals.params.w;
+        alpha = alpha * (1.0 - smoothStep(0.3, 1.0, fade));
+    }
+    if (alpha <= 0.0) {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, alpha);
+}
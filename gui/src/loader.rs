@@ -0,0 +1,556 @@
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc::Sender,
+    Mutex,
+};
+
+use winit::event_loop::EventLoopProxy;
+
+use triangulate::{
+    coverage::{self, CoverageReport},
+    crop::Aabb,
+    load::{load_faces, load_mesh, LoadError, ParseReport, TriangulateOptions},
+    mesh::Mesh,
+    stats::{Diagnostic, Stats},
+};
+
+use crate::{
+    profile::Profile,
+    tile::{shift_ids, tile, Slot},
+    title::file_name,
+};
+
+/// Messages from the loader thread, delivered through an [`EventSink`]
+pub enum LoadEvent {
+    /// Fraction of solids which have been triangulated, from 0 to 1
+    Progress(f32),
+    /// Solids which have been triangulated so far, which can be drawn while
+    /// the rest of the model loads
+    Chunk(Box<Mesh>),
+    /// The combined mesh, with one slot per input file
+    Loaded(Box<(Mesh, Stats, Vec<Slot>)>),
+    Failed(String),
+    /// Faces which were triangulated again with a new tolerance, with
+    /// entity indices matching the combined mesh
+    Retriangulated(Box<(Mesh, Stats)>),
+}
+
+/// Converts per-solid progress into whole percentages, so that the event
+/// loop isn't flooded with updates for models with many solids
+#[derive(Default)]
+pub struct Throttle {
+    last: AtomicUsize,
+}
+
+impl Throttle {
+    /// Returns the new percentage if it has increased since the last call.
+    /// This may be called from several threads at once.
+    pub fn update(&self, done: usize, total: usize) -> Option<usize> {
+        let percent = (done * 100).checked_div(total)?;
+        let prev = self.last.fetch_max(percent, Ordering::Relaxed);
+        (percent > prev).then_some(percent)
+    }
+}
+
+/// Number of triangles which are gathered up before a chunk is sent to the
+/// event loop, so that big models are drawn in a handful of uploads
+const CHUNK_TRIANGLES: usize = 50_000;
+
+/// Gathers finished solids into chunks of at least `size` triangles
+pub struct Chunker {
+    size: usize,
+    pending: Mutex<Mesh>,
+}
+
+impl Chunker {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            pending: Mutex::new(Mesh::default()),
+        }
+    }
+
+    /// Adds a finished solid, returning a chunk once enough triangles have
+    /// built up.  This may be called from several threads at once.
+    pub fn push(&self, solid: &Mesh) -> Option<Mesh> {
+        // Wires are only drawn once the whole model has loaded
+        if solid.triangles.is_empty() {
+            return None;
+        }
+        let mut pending = self.pending.lock().unwrap();
+        let prev = std::mem::take(&mut *pending);
+        *pending = Mesh::combine(prev, solid.clone());
+        (pending.triangles.len() >= self.size).then(|| std::mem::take(&mut *pending))
+    }
+
+    /// Returns the triangles which haven't been sent yet, if there are any
+    pub fn finish(self) -> Option<Mesh> {
+        let pending = self.pending.into_inner().unwrap();
+        (!pending.triangles.is_empty()).then_some(pending)
+    }
+}
+
+/// Reads, parses, and triangulates a STEP file, recording the time spent in
+/// each stage.  `progress` is called with the number of solids finished and
+/// the total number of solids, and `on_solid` with each solid's triangles.
+/// If `cache` is a directory, triangulated meshes are stored there and
+/// reused when the same file is loaded again.  A summary of the parsed
+/// entities (including the file's length unit) is returned along with the
+/// mesh.
+pub fn read_mesh(
+    input: &str,
+    profile: &Profile,
+    progress: &(dyn Fn(usize, usize) + Sync),
+    on_solid: &(dyn Fn(&Mesh) + Sync),
+    crop: Option<Aabb>,
+    cache: Option<&Path>,
+) -> Result<(Mesh, Stats, ParseReport), LoadError> {
+    let (scene, report) = load_mesh(
+        input,
+        &TriangulateOptions {
+            progress,
+            on_solid,
+            crop,
+            cache,
+            ..Default::default()
+        },
+    )?;
+    for t in &report.timings {
+        profile.record_span(t.stage, t.start, t.duration);
+    }
+    profile.record_stats(&report.stats);
+    Ok((scene.mesh, report.stats, report.parse))
+}
+
+/// Number of unsupported geometry types which are logged when a file
+/// produces an empty mesh
+const TOP_UNSUPPORTED: usize = 5;
+
+/// Lists the most common geometry types which the triangulator doesn't
+/// handle, with their counts, or returns `None` if there are none
+fn unsupported_summary(report: &CoverageReport) -> Option<String> {
+    let top: Vec<_> = report
+        .unsupported_geometry()
+        .iter()
+        .take(TOP_UNSUPPORTED)
+        .map(|e| format!("{} ({})", e.keyword, e.count))
+        .collect();
+    (!top.is_empty()).then(|| top.join(", "))
+}
+
+/// Describes the geometry types which may explain why a file produced an
+/// empty mesh, which is shown under the error.  This parses the file again,
+/// which is fine because it's only done once the load has already failed.
+fn unsupported_hint(input: &str) -> String {
+    let hint = match coverage::report_file(input) {
+        Ok(r) => match unsupported_summary(&r) {
+            Some(s) => format!("Unsupported geometry: {}", s),
+            None => "No unsupported geometry found".to_owned(),
+        },
+        Err(e) => format!("Could not check coverage: {}", e),
+    };
+    log::warn!("{}: {}", file_name(input), hint);
+    hint
+}
+
+/// Somewhere to deliver [`LoadEvent`]s: the window's event loop, or a
+/// channel when drawing without a window
+pub trait EventSink: Send + Sync + 'static {
+    /// Delivers an event, which only fails (silently) if the receiver has
+    /// shut down
+    fn deliver(&self, e: LoadEvent);
+}
+
+impl EventSink for EventLoopProxy<LoadEvent> {
+    fn deliver(&self, e: LoadEvent) {
+        let _ = self.send_event(e);
+    }
+}
+
+impl EventSink for Sender<LoadEvent> {
+    fn deliver(&self, e: LoadEvent) {
+        let _ = self.send(e);
+    }
+}
+
+/// Loads every input file in parallel, then tiles them side by side,
+/// reporting combined progress to the event loop.  A single file is also
+/// streamed to the event loop in chunks as it's triangulated; multiple files
+/// aren't, since they're only placed once every file has loaded.
+fn load_all<S: EventSink>(
+    inputs: &[String],
+    profile: &Profile,
+    sink: &S,
+    crop: Option<Aabb>,
+    cache: Option<&Path>,
+) -> Result<(Mesh, Stats, Vec<Slot>), String> {
+    let throttle = Throttle::default();
+    let counts: Vec<_> = inputs
+        .iter()
+        .map(|_| (AtomicUsize::new(0), AtomicUsize::new(0)))
+        .collect();
+    let progress = |i: usize, done: usize, total: usize| {
+        counts[i].0.store(done, Ordering::Relaxed);
+        counts[i].1.store(total, Ordering::Relaxed);
+        let sum = |f: fn(&(AtomicUsize, AtomicUsize)) -> &AtomicUsize| -> usize {
+            counts.iter().map(|c| f(c).load(Ordering::Relaxed)).sum()
+        };
+        if let Some(p) = throttle.update(sum(|c| &c.0), sum(|c| &c.1)) {
+            sink.deliver(LoadEvent::Progress(p as f32 / 100.0));
+        }
+    };
+    let chunker = Chunker::new(CHUNK_TRIANGLES);
+    let send_chunk = |chunk: Option<Mesh>| {
+        if let Some(c) = chunk {
+            sink.deliver(LoadEvent::Chunk(Box::new(c)));
+        }
+    };
+    let on_solid = |solid: &Mesh| {
+        if inputs.len() == 1 {
+            send_chunk(chunker.push(solid));
+        }
+    };
+    let results: Vec<_> = std::thread::scope(|s| {
+        let handles: Vec<_> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let (progress, on_solid) = (&progress, &on_solid);
+                s.spawn(move || {
+                    read_mesh(
+                        input,
+                        profile,
+                        &|done, total| progress(i, done, total),
+                        on_solid,
+                        crop,
+                        cache,
+                    )
+                })
+            })
+            .collect();
+        // Panics are caught by `load_mesh`, so joining can't fail
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    send_chunk(chunker.finish());
+    combine(inputs, results, &unsupported_hint)
+}
+
+/// Tiles the results of loading each input file.  Files which have nothing
+/// to draw are left out, with a warning (and `hint`, which explains why) in
+/// the combined stats, so that the rest of the model can still be shown;
+/// the load only fails if every file is empty, or if any file couldn't be
+/// loaded at all.
+fn combine(
+    inputs: &[String],
+    results: Vec<Result<(Mesh, Stats, ParseReport), LoadError>>,
+    hint: &dyn Fn(&str) -> String,
+) -> Result<(Mesh, Stats, Vec<Slot>), String> {
+    let mut loaded = vec![];
+    let mut empty = vec![];
+    for (input, r) in inputs.iter().zip(results) {
+        match r {
+            Ok((mesh, stats, parse)) => loaded.push((input.clone(), mesh, stats, parse)),
+            Err(LoadError::Empty) => empty.push(input),
+            Err(e) if inputs.len() > 1 => return Err(format!("{}: {}", file_name(input), e)),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    // With nothing to show, the hints go on the lines after the error
+    if loaded.is_empty() {
+        let hints: Vec<_> = empty
+            .iter()
+            .map(|i| match inputs.len() {
+                1 => hint(i),
+                _ => format!("{}: {}", file_name(i), hint(i)),
+            })
+            .collect();
+        return Err(format!("{}\n{}", LoadError::Empty, hints.join("\n")));
+    }
+    let (mesh, mut stats, slots) = tile(loaded);
+    stats
+        .diagnostics
+        .extend(empty.iter().map(|input| Diagnostic {
+            level: log::Level::Warn,
+            entity: None,
+            message: format!(
+                "{}: {} ({})",
+                file_name(input),
+                LoadError::Empty,
+                hint(input)
+            ),
+        }));
+    Ok((mesh, stats, slots))
+}
+
+/// Starts loading one or more STEP files in the background, using (and
+/// filling) the mesh cache in `cache` if it's given
+pub fn spawn<S: EventSink>(
+    inputs: Vec<String>,
+    profile: Profile,
+    sink: S,
+    crop: Option<Aabb>,
+    cache: Option<PathBuf>,
+) {
+    std::thread::spawn(move || {
+        log::info!("Loading {}", inputs.join(", "));
+        let event = match load_all(&inputs, &profile, &sink, crop, cache.as_deref()) {
+            Ok(m) => LoadEvent::Loaded(Box::new(m)),
+            Err(e) => LoadEvent::Failed(e),
+        };
+        sink.deliver(event);
+    });
+}
+
+/// Triangulates a few faces of an already-loaded file again, with a new
+/// tolerance.  `faces` are entity indices within the file, and the results
+/// are shifted by `first_id` to match the combined mesh (see [`Slot`]).
+pub fn spawn_retriangulate(
+    path: String,
+    faces: Vec<usize>,
+    first_id: usize,
+    tolerance: f64,
+    proxy: EventLoopProxy<LoadEvent>,
+) {
+    std::thread::spawn(move || {
+        let opts = TriangulateOptions {
+            tolerance: Some(tolerance),
+            ..Default::default()
+        };
+        match load_faces(&path, &faces, &opts) {
+            Ok((mut mesh, mut stats)) => {
+                shift_ids(&mut mesh, &mut stats, first_id);
+                let event = LoadEvent::Retriangulated(Box::new((mesh, stats)));
+                let _ = proxy.send_event(event);
+            }
+            Err(e) => eprintln!(
+                "Error: could not re-triangulate {}: {}",
+                file_name(&path),
+                e
+            ),
+        }
+    });
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle() {
+        let t = Throttle::default();
+        assert_eq!(t.update(0, 1000), None);
+        assert_eq!(t.update(5, 1000), None);
+        assert_eq!(t.update(10, 1000), Some(1));
+        assert_eq!(t.update(15, 1000), None);
+        assert_eq!(t.update(500, 1000), Some(50));
+        // Updates from other threads may arrive out of order
+        assert_eq!(t.update(400, 1000), None);
+        assert_eq!(t.update(1000, 1000), Some(100));
+
+        // An empty model has nothing to report
+        assert_eq!(Throttle::default().update(0, 0), None);
+    }
+
+    #[test]
+    fn test_unsupported_summary() {
+        use step::{fixture, step_file::StepFile};
+        let text = fixture::cube(1.0).to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        assert_eq!(unsupported_summary(&coverage::report(&step)), None);
+
+        // Every plane is replaced with a (malformed) surface of revolution
+        let text = text.replace("=PLANE(", "=SURFACE_OF_REVOLUTION(");
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        assert_eq!(
+            unsupported_summary(&coverage::report(&step)).as_deref(),
+            Some("SURFACE_OF_REVOLUTION (6)")
+        );
+    }
+
+    #[test]
+    fn test_unsupported_hint() {
+        use step::fixture;
+        let path =
+            std::env::temp_dir().join(format!("foxtrot-gui-{}-hint.step", std::process::id()));
+        let text = fixture::cube(1.0)
+            .to_step()
+            .replace("=PLANE(", "=SURFACE_OF_REVOLUTION(");
+        std::fs::write(&path, text).unwrap();
+        let hint = unsupported_hint(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(hint, "Unsupported geometry: SURFACE_OF_REVOLUTION (6)");
+    }
+
+    #[test]
+    fn test_combine() {
+        use step::fixture;
+        use triangulate::load::load_mesh_from_bytes;
+
+        let load = |text: &[u8]| {
+            load_mesh_from_bytes(text, &TriangulateOptions::default())
+                .map(|(scene, r)| (scene.mesh, r.stats, r.parse))
+        };
+        let solid = || load(fixture::cube(1.0).to_step().as_bytes());
+        let curves = || load(fixture::cylinder(1.0, 2.0).wireframe().to_step().as_bytes());
+        let empty = || {
+            load(b"ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n#1=CARTESIAN_POINT('',(0.,0.,0.));\nENDSEC;\nEND-ISO-10303-21;\n")
+        };
+        let hint = |input: &str| format!("nothing in {}", input);
+        let inputs = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        // Triangles are loaded as usual
+        let (mesh, stats, slots) = combine(&inputs(&["a.step"]), vec![solid()], &hint).unwrap();
+        assert_eq!(mesh.triangles.len(), 12);
+        assert!(stats.diagnostics.is_empty());
+        assert_eq!(slots.len(), 1);
+
+        // So are curves without any triangles, which are drawn as wires
+        let (mesh, _, slots) = combine(&inputs(&["a.step"]), vec![curves()], &hint).unwrap();
+        assert!(mesh.triangles.is_empty());
+        assert_eq!(mesh.wires.len(), 6);
+        assert_eq!(slots.len(), 1);
+
+        // A file with nothing to draw fails, with the hint under the error
+        let err = combine(&inputs(&["a.step"]), vec![empty()], &hint)
+            .err()
+            .unwrap();
+        assert_eq!(err, format!("{}\nnothing in a.step", LoadError::Empty));
+
+        // ...unless another file has something to show, in which case it's
+        // left out with a warning
+        let (mesh, stats, slots) = combine(
+            &inputs(&["a.step", "dir/b.step"]),
+            vec![curves(), empty()],
+            &hint,
+        )
+        .unwrap();
+        assert_eq!(mesh.wires.len(), 6);
+        assert_eq!(slots.len(), 1);
+        assert_eq!(stats.diagnostics.len(), 1);
+        assert_eq!(stats.diagnostics[0].entity, None);
+        assert!(stats.diagnostics[0]
+            .message
+            .starts_with("b.step: The file contains no"));
+        assert!(stats.diagnostics[0]
+            .message
+            .ends_with("(nothing in dir/b.step)"));
+
+        // Every file being empty fails, naming each of them
+        let err = combine(
+            &inputs(&["a.step", "b.step"]),
+            vec![empty(), empty()],
+            &hint,
+        )
+        .err()
+        .unwrap();
+        assert!(err.ends_with("\na.step: nothing in a.step\nb.step: nothing in b.step"));
+
+        // Other errors still fail the whole load
+        let broken = load(b"solid cube;\nendsolid cube;\n");
+        let err = combine(&inputs(&["a.step", "b.step"]), vec![solid(), broken], &hint)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("b.step: "), "{}", err);
+    }
+
+    #[test]
+    fn test_profile_stages() {
+        use step::fixture;
+        use triangulate::units::LengthUnit;
+        let path =
+            std::env::temp_dir().join(format!("foxtrot-gui-{}-profile.step", std::process::id()));
+        let asm = fixture::assembly(vec![fixture::cube(1.0), fixture::cylinder(1.0, 1.0)]);
+        std::fs::write(&path, asm.to_step()).unwrap();
+        let input = path.to_str().unwrap();
+
+        let profile = Profile::enabled(std::time::Instant::now(), None);
+        let (mesh, _stats, parse) =
+            read_mesh(input, &profile, &|_, _| (), &|_| (), None, None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!mesh.triangles.is_empty());
+        assert_eq!(parse.length_unit, Some(LengthUnit::Millimetre));
+        assert_eq!(parse.failed, 0);
+
+        let report = profile.report().unwrap();
+        let names: Vec<_> = report.stages.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["read", "strip_flatten", "parse", "triangulate"]);
+        for s in &report.stages {
+            assert!(s.duration > 0.0, "{} has zero duration", s.name);
+        }
+        assert!(report.surface_types.get("Plane").copied().unwrap_or(0) > 0);
+        assert!(report.surface_types.contains_key("Cylinder"));
+    }
+    #[test]
+    fn test_chunker() {
+        use step::{fixture, step_file::StepFile};
+        use triangulate::triangulate::{triangulate_streaming, triangulate_with_tolerance};
+
+        // Sorted triangles, as vertex positions, so that meshes can be
+        // compared regardless of the order in which solids were added
+        let triangles = |mesh: &Mesh| {
+            let mut out: Vec<_> = mesh
+                .triangles
+                .iter()
+                .map(|t| {
+                    [0, 1, 2].map(|i| {
+                        let p = mesh.verts[t.verts[i] as usize].pos;
+                        [p.x, p.y, p.z].map(f64::to_bits)
+                    })
+                })
+                .collect();
+            out.sort();
+            out
+        };
+
+        let asm = fixture::assembly(vec![
+            fixture::filleted_block(10.0, 2.0),
+            fixture::cylinder(2.0, 5.0).translate([20.0, 0.0, 0.0]),
+            fixture::cube(3.0).translate([0.0, 20.0, 0.0]),
+            fixture::cube(3.0).translate([0.0, -20.0, 0.0]),
+        ]);
+        let text = asm.to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let (batch, _) = triangulate_with_tolerance(&step, None, &|_, _| ());
+
+        // Each cube has 12 triangles, and every other solid has more, so
+        // every chunk but the last has at least 100 triangles and there are
+        // at most four of them
+        let chunker = Chunker::new(100);
+        let chunks = Mutex::new(vec![]);
+        let (streamed, _) = triangulate_streaming(
+            &step,
+            None,
+            None,
+            Default::default(),
+            &|_, _| (),
+            &|solid| {
+                if let Some(c) = chunker.push(solid) {
+                    chunks.lock().unwrap().push(c);
+                }
+            },
+        );
+        let mut chunks = chunks.into_inner().unwrap();
+        assert!(chunks.iter().all(|c| c.triangles.len() >= 100));
+        chunks.extend(chunker.finish());
+        assert!(!chunks.is_empty() && chunks.len() <= 4);
+
+        // The streamed chunks add up to the final mesh, which is the same as
+        // a mesh triangulated without streaming
+        let combined = chunks.into_iter().fold(Mesh::default(), Mesh::combine);
+        assert_eq!(triangles(&combined), triangles(&batch));
+        assert_eq!(triangles(&streamed), triangles(&batch));
+        assert_eq!(streamed.triangles.len(), batch.triangles.len());
+
+        // Solids without triangles (e.g. wires) aren't streamed, and an
+        // empty chunker has nothing left over
+        let chunker = Chunker::new(100);
+        assert!(chunker.push(&Mesh::default()).is_none());
+        assert!(chunker.finish().is_none());
+    }
+}
@@ -1,5 +1,6 @@
-use std::time::SystemTime;
+use std::{sync::mpsc::Receiver, time::Instant};
 use winit::{
+    dpi::PhysicalSize,
     event::Event,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
@@ -7,34 +8,95 @@ use winit::{
 
 pub(crate) mod app;
 pub(crate) mod backdrop;
+pub(crate) mod bookmarks;
 pub(crate) mod camera;
+pub(crate) mod chunks;
+pub(crate) mod debug_colors;
+pub(crate) mod diagnostics;
+pub(crate) mod edges;
+pub(crate) mod export;
+pub(crate) mod gizmo;
+pub(crate) mod gpu;
+pub(crate) mod grid;
+pub(crate) mod grow;
+pub(crate) mod inspect;
+pub(crate) mod instances;
+pub(crate) mod keymap;
+pub(crate) mod lines;
+pub(crate) mod loader;
+pub(crate) mod material;
+pub(crate) mod measure;
 pub(crate) mod model;
+pub(crate) mod outline;
+pub(crate) mod passes;
+pub(crate) mod pick;
+pub(crate) mod present;
+pub(crate) mod profile;
+pub(crate) mod refine;
+pub(crate) mod screenshot;
+pub(crate) mod section;
+pub(crate) mod selection;
+pub(crate) mod startup;
+pub(crate) mod swapchain;
+pub(crate) mod text;
+pub(crate) mod theme;
+pub(crate) mod tile;
+pub(crate) mod title;
+pub(crate) mod visibility;
+pub(crate) mod wires;
 
-use crate::app::App;
-use triangulate::mesh::Mesh;
+use crate::{
+    app::{App, RenderTarget},
+    bookmarks::Bookmarks,
+    chunks::ChunkBudget,
+    keymap::Keymap,
+    loader::LoadEvent,
+    profile::Profile,
+    startup::{Preset, Startup},
+    theme::Themes,
+};
+use triangulate::{crop::Aabb, load::LoadError, mesh::Mesh, stats::Stats};
 
-async fn run(
-    start: SystemTime,
-    event_loop: EventLoop<()>,
-    window: Window,
-    loader: std::thread::JoinHandle<Mesh>,
-) {
-    let size = window.inner_size();
-    let (surface, adapter) = {
-        let instance = wgpu::Instance::new(wgpu::BackendBit::all());
-        let surface = unsafe { instance.create_surface(&window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                // Request an adapter which can render to our surface
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .expect("Failed to find an appropriate adapter");
-        (surface, adapter)
+/// Settings from the command line and config file
+struct Options {
+    themes: Themes,
+    keymap: Keymap,
+    present_mode: Option<wgpu::PresentMode>,
+    /// Prefer a software adapter over the GPU
+    software: bool,
+    /// Initial view, screenshot, and whether to exit afterwards
+    startup: Startup,
+    /// Directory of cached meshes, which is also used for files dropped
+    /// onto the window
+    cache: Option<std::path::PathBuf>,
+    /// Largest chunk of geometry which is put into one GPU buffer
+    chunk_budget: ChunkBudget,
+}
+
+/// Picks an adapter (which can render to `surface`, if there is one) and
+/// creates its logical device and command queue, exiting on failure
+async fn open_device(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    software: bool,
+) -> (wgpu::Adapter, wgpu::Device, wgpu::Queue) {
+    // Falls back to a software adapter if there's no usable GPU
+    let source = gpu::WgpuAdapters { instance, surface };
+    let adapter = match gpu::select_adapter(&source, software) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     };
+    let info = adapter.get_info();
+    log::info!(
+        "Using {} ({:?}, {:?})",
+        info.name,
+        info.backend,
+        info.device_type
+    );
 
-    // Create the logical device and command queue
     let (device, queue) = adapter
         .request_device(
             &wgpu::DeviceDescriptor {
@@ -45,68 +107,424 @@ async fn run(
             None,
         )
         .await
-        .expect("Failed to create device");
+        .unwrap_or_else(|e| {
+            eprintln!("Error: could not create GPU device: {}", e);
+            std::process::exit(1);
+        });
+    gpu::install_error_handler(&device);
+    (adapter, device, queue)
+}
+
+async fn run(
+    profile: Profile,
+    event_loop: EventLoop<LoadEvent>,
+    window: Window,
+    name: String,
+    bookmarks: Bookmarks,
+    options: Options,
+) {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::all());
+    let surface = unsafe { instance.create_surface(&window) };
+    let (adapter, device, queue) = open_device(&instance, Some(&surface), options.software).await;
 
-    let mut app = App::new(start, size, adapter, surface, device, loader);
+    let target = RenderTarget::Window(&window, surface);
+    let mut app = App::new(profile, target, adapter, device, name, bookmarks);
+    app.set_themes(options.themes);
+    app.set_keymap(options.keymap);
+    if let Some(mode) = options.present_mode {
+        app.set_present_mode(mode);
+    }
+    app.set_startup(options.startup);
+    app.set_chunk_budget(options.chunk_budget);
+    app.set_loader(event_loop.create_proxy(), options.cache);
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Wait;
         use app::Reply;
-        match event {
+        let reply = match event {
             Event::WindowEvent { event, .. } => match app.window_event(event) {
-                Reply::Continue => (),
-                Reply::Quit => *control_flow = ControlFlow::Exit,
-                Reply::Redraw => {
-                    if app.redraw(&queue) {
-                        window.request_redraw();
-                    }
-                }
+                Reply::Redraw => app.redraw(&queue),
+                r => r,
             },
-            Event::RedrawRequested(_) => {
-                if app.redraw(&queue) {
-                    window.request_redraw();
-                }
+            Event::RedrawRequested(_) => app.redraw(&queue),
+            Event::UserEvent(e) => app.load_event(e),
+            Event::DeviceEvent { event, .. } => {
+                app.device_event(event);
+                Reply::Continue
             }
-            Event::DeviceEvent { event, .. } => app.device_event(event),
-            _ => (),
+            _ => Reply::Continue,
+        };
+        match reply {
+            Reply::Continue => (),
+            Reply::Redraw => window.request_redraw(),
+            Reply::Quit => *control_flow = ControlFlow::Exit,
+        }
+        if let Some(title) = app.take_title() {
+            window.set_title(&title);
         }
     });
 }
 
+/// Draws into an offscreen texture rather than a window, so that no display
+/// is needed.  Events from the loader arrive through a channel, and frames
+/// are drawn for as long as the app asks for them; the startup script
+/// always exits, so this returns once the screenshot is taken.
+async fn run_headless(
+    profile: Profile,
+    events: Receiver<LoadEvent>,
+    size: PhysicalSize<u32>,
+    name: String,
+    bookmarks: Bookmarks,
+    options: Options,
+) {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::all());
+    let (adapter, device, queue) = open_device(&instance, None, options.software).await;
+
+    let target = RenderTarget::Offscreen(size);
+    let mut app = App::new(profile, target, adapter, device, name, bookmarks);
+    app.set_themes(options.themes);
+    app.set_startup(options.startup);
+    app.set_chunk_budget(options.chunk_budget);
+
+    use app::Reply;
+    let mut reply = Reply::Redraw;
+    loop {
+        reply = match reply {
+            Reply::Redraw => app.redraw(&queue),
+            // Nothing changes until the loader sends something, and it only
+            // hangs up once it's done
+            Reply::Continue => match events.recv() {
+                Ok(e) => app.load_event(e),
+                Err(_) => Reply::Quit,
+            },
+            Reply::Quit => break,
+        };
+    }
+}
+
+/// Loads a STEP file and triangulates it
+pub(crate) fn load_mesh(input: &str) -> Result<(Mesh, Stats), LoadError> {
+    let profile = Profile::disabled(Instant::now());
+    loader::read_mesh(input, &profile, &|_, _| (), &|_| (), None, None)
+        .map(|(mesh, stats, _)| (mesh, stats))
+}
+
+/// Crates whose logs are shown at the level picked by `-v`
+const CRATES: [&str; 5] = ["cdt", "gui", "nurbs", "step", "triangulate"];
+
+/// Starts logging, with more detail for each `-v`.  By default, warnings
+/// from our own crates (and errors from everything else, which includes
+/// chatty graphics libraries) are shown.  `RUST_LOG` overrides this.
+fn init_logging(verbosity: u64) {
+    use log::LevelFilter::*;
+    let (ours, others) = match verbosity {
+        0 => (Warn, Error),
+        1 => (Info, Warn),
+        2 => (Debug, Info),
+        _ => (Trace, Trace),
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(others);
+    for c in CRATES {
+        builder.filter_module(c, ours);
+    }
+    builder.parse_default_env().init();
+}
+
 fn main() {
-    let start = SystemTime::now();
-    env_logger::init();
+    let start = Instant::now();
 
     let matches = clap::App::new("gui")
         .author("Matt Keeter <matt@formlabs.com>")
-        .about("Renders a STEP file")
+        .about("Renders one or more STEP files")
         .arg(
             clap::Arg::with_name("input")
                 .takes_value(true)
-                .required(true),
+                .multiple_values(true)
+                .required_unless_present("print-keymap")
+                .help("STEP files to load, which are tiled side by side"),
+        )
+        .arg(
+            clap::Arg::with_name("export")
+                .long("export")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Exports the mesh to an STL, OBJ, PLY, or GLB file, then exits"),
+        )
+        .arg(
+            clap::Arg::with_name("bg")
+                .long("bg")
+                .takes_value(true)
+                .value_name("TOP,BOTTOM")
+                .validator(|s| theme::parse_background(s).map(|_| ()))
+                .help("Sets the background gradient, as two hex colors (e.g. #2b3849,#0d0f1a)"),
+        )
+        .arg(
+            clap::Arg::with_name("present-mode")
+                .long("present-mode")
+                .takes_value(true)
+                .possible_values(["fifo", "mailbox", "immediate"])
+                .help("Sets the swapchain's present mode (press V to cycle at runtime)"),
+        )
+        .arg(
+            clap::Arg::with_name("software")
+                .long("software")
+                .help("Prefers a software (CPU) adapter, for machines without GPU drivers"),
+        )
+        .arg(
+            clap::Arg::with_name("chunk-vertices")
+                .long("chunk-vertices")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|s| s.parse::<u32>().map(|_| ()))
+                .help("Splits models into GPU buffers of at most this many vertices each"),
+        )
+        .arg(
+            clap::Arg::with_name("chunk-indices")
+                .long("chunk-indices")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|s| s.parse::<u32>().map(|_| ()))
+                .help("Splits models into GPU buffers of at most this many indices each"),
+        )
+        .arg(
+            clap::Arg::with_name("camera")
+                .long("camera")
+                .takes_value(true)
+                .possible_values(Preset::NAMES)
+                .help("Sets the initial view direction"),
+        )
+        .arg(
+            clap::Arg::with_name("azimuth")
+                .long("azimuth")
+                .takes_value(true)
+                .value_name("DEGREES")
+                .allow_hyphen_values(true)
+                .validator(|s| startup::parse_angle(s).map(|_| ()))
+                .help("Turns the initial view around the vertical axis, overriding --camera"),
+        )
+        .arg(
+            clap::Arg::with_name("elevation")
+                .long("elevation")
+                .takes_value(true)
+                .value_name("DEGREES")
+                .allow_hyphen_values(true)
+                .validator(|s| startup::parse_angle(s).map(|_| ()))
+                .help("Tilts the initial view up or down, overriding --camera"),
+        )
+        .arg(
+            clap::Arg::with_name("zoom")
+                .long("zoom")
+                .takes_value(true)
+                .value_name("FACTOR")
+                .validator(|s| startup::parse_zoom(s).map(|_| ()))
+                .help("Magnifies the initial view, relative to fitting the model"),
+        )
+        .arg(
+            clap::Arg::with_name("hide-backdrop")
+                .long("hide-backdrop")
+                .help("Draws a transparent background instead of the gradient"),
+        )
+        .arg(
+            clap::Arg::with_name("hide-grid")
+                .long("hide-grid")
+                .help("Starts with the grid and axis gizmo hidden"),
+        )
+        .arg(
+            clap::Arg::with_name("hide-edges")
+                .long("hide-edges")
+                .help("Starts with the edge overlay hidden"),
+        )
+        .arg(
+            clap::Arg::with_name("screenshot")
+                .long("screenshot")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Saves the first frame which shows the whole model to a PNG file"),
+        )
+        .arg(
+            clap::Arg::with_name("headless")
+                .long("headless")
+                .takes_value(true)
+                .value_name("WIDTHxHEIGHT")
+                .requires("screenshot")
+                .validator(|s| startup::parse_size(s).map(|_| ()))
+                .help("Draws the screenshot offscreen at this size, without opening a window, then exits"),
+        )
+        .arg(
+            clap::Arg::with_name("exit")
+                .long("exit")
+                .help("Exits after the first frame which shows the whole model"),
+        )
+        .arg(
+            clap::Arg::with_name("crop")
+                .long("crop")
+                .takes_value(true)
+                .value_name("X0,Y0,Z0,X1,Y1,Z1")
+                .allow_hyphen_values(true)
+                .validator(|s| s.parse::<Aabb>().map(|_| ()))
+                .help("Only loads faces which touch the box between two corners, in model units"),
+        )
+        .arg(
+            clap::Arg::with_name("no-cache")
+                .long("no-cache")
+                .help("Re-triangulates the input instead of reusing a cached mesh"),
+        )
+        .arg(
+            clap::Arg::with_name("profile")
+                .long("profile")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Records startup timings, writing them to a JSON file"),
+        )
+        .arg(
+            clap::Arg::with_name("print-keymap")
+                .long("print-keymap")
+                .help("Prints the effective keyboard shortcuts, then exits"),
+        )
+        .arg(
+            clap::Arg::with_name("verbose")
+                .short('v')
+                .long("verbose")
+                .multiple_occurrences(true)
+                .help("Logs more detail (-v for info, -vv for debug, -vvv for everything)"),
         )
         .get_matches();
-    let input = matches
-        .value_of("input")
+    init_logging(matches.occurrences_of("verbose"));
+
+    // Keyboard shortcuts are read from the platform's config directory,
+    // e.g. ~/.config/foxtrot/keymap.toml on Linux
+    let keymap_path = Keymap::path();
+    let keymap = Keymap::load(keymap_path.as_deref());
+    if matches.is_present("print-keymap") {
+        if let Some(p) = &keymap_path {
+            println!("# {}", p.display());
+        }
+        print!("{}", keymap.to_toml());
+        return;
+    }
+
+    let inputs: Vec<String> = matches
+        .values_of("input")
         .expect("Could not get input file")
-        .to_owned();
-
-    // Kick off the loader thread immediately, so that the STEP file is parsed
-    // and triangulated in the background while we wait for a GPU context
-    let loader = std::thread::spawn(|| {
-        println!("Loading mesh!");
-        use step::step_file::StepFile;
-        use triangulate::triangulate::triangulate;
-
-        let data = std::fs::read(input).expect("Could not open file");
-        let flat = StepFile::strip_flatten(&data);
-        let step = StepFile::parse(&flat);
-        let (mesh, _stats) = triangulate(&step);
-        mesh
+        .map(str::to_owned)
+        .collect();
+    let input = &inputs[0];
+
+    if let Some(out) = matches.value_of("export") {
+        if inputs.len() > 1 {
+            eprintln!("Error: --export takes a single input file");
+            std::process::exit(1);
+        }
+        if let Err(e) = export::export_headless(input, std::path::Path::new(out)) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Bookmarks are stored alongside the first input file
+    let bookmarks = Bookmarks::load(std::path::Path::new(input));
+
+    // Read the theme from foxtrot.toml in the working directory, ignoring
+    // the file if it's invalid (`--bg` was already checked by clap)
+    let bg = matches.value_of("bg");
+    let themes = Themes::load(std::path::Path::new("."), bg).unwrap_or_else(|e| {
+        eprintln!("Warning: ignoring {}: {}", theme::CONFIG_FILE, e);
+        Themes::from_config(None, bg).unwrap_or_default()
     });
 
-    let event_loop = EventLoop::new();
+    let present_mode = matches
+        .value_of("present-mode")
+        .map(|m| present::parse_present_mode(m).expect("Invalid present mode"));
+
+    // Values were checked by clap's validators
+    let default_budget = ChunkBudget::default();
+    let chunk_budget = ChunkBudget {
+        vertices: matches
+            .value_of("chunk-vertices")
+            .map_or(default_budget.vertices, |n| {
+                n.parse().expect("Invalid vertex count")
+            }),
+        indices: matches
+            .value_of("chunk-indices")
+            .map_or(default_budget.indices, |n| {
+                n.parse().expect("Invalid index count")
+            }),
+    };
+    let startup = Startup {
+        preset: matches
+            .value_of("camera")
+            .map(|c| startup::parse_preset(c).expect("Invalid camera preset")),
+        azimuth: matches
+            .value_of("azimuth")
+            .map(|a| startup::parse_angle(a).expect("Invalid azimuth")),
+        elevation: matches
+            .value_of("elevation")
+            .map(|e| startup::parse_angle(e).expect("Invalid elevation")),
+        zoom: matches
+            .value_of("zoom")
+            .map_or(1.0, |z| startup::parse_zoom(z).expect("Invalid zoom")),
+        backdrop: !matches.is_present("hide-backdrop"),
+        grid: !matches.is_present("hide-grid"),
+        edges: !matches.is_present("hide-edges"),
+        screenshot: matches.value_of("screenshot").map(Into::into),
+        // There's no window to close when running headless
+        exit: matches.is_present("exit") || matches.is_present("headless"),
+    };
+
+    let profile = match matches.value_of("profile") {
+        Some(p) => Profile::enabled(start, Some(p.into())),
+        None => Profile::disabled(start),
+    };
+
+    let name = inputs
+        .iter()
+        .map(|i| title::file_name(i))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // Kick off the loader thread immediately, so that the STEP files are
+    // parsed and triangulated in the background while we wait for a GPU
+    // context.  It reports progress and the finished mesh through the event
+    // loop.  Meshes are cached in the platform's cache directory (e.g.
+    // ~/.cache/foxtrot/meshes on Linux), so that reopening a file is fast.
+    let cache = if matches.is_present("no-cache") {
+        None
+    } else {
+        triangulate::cache::default_dir()
+    };
+    let crop = matches
+        .value_of("crop")
+        .map(|c| c.parse().expect("Crop box was checked by clap"));
+    let options = Options {
+        themes,
+        keymap,
+        present_mode,
+        software: matches.is_present("software"),
+        startup,
+        cache: cache.clone(),
+        chunk_budget,
+    };
+
+    if let Some(size) = matches.value_of("headless") {
+        let size = startup::parse_size(size).expect("Size was checked by clap");
+        let (tx, rx) = std::sync::mpsc::channel();
+        loader::spawn(inputs, profile.clone(), tx, crop, cache);
+        pollster::block_on(run_headless(profile, rx, size, name, bookmarks, options));
+        return;
+    }
+
+    let event_loop = EventLoop::with_user_event();
+    loader::spawn(
+        inputs,
+        profile.clone(),
+        event_loop.create_proxy(),
+        crop,
+        cache,
+    );
+
     let window = winit::window::Window::new(&event_loop).unwrap();
-    window.set_title("Foxtrot");
-    pollster::block_on(run(start, event_loop, window, loader));
+    window.set_title(&title::window_title(&name, &title::Status::Loading(None)));
+    pollster::block_on(run(profile, event_loop, window, name, bookmarks, options));
 }
@@ -0,0 +1,244 @@
+use triangulate::mesh::Mesh;
+
+use crate::debug_colors::{hue_color, mix};
+
+/// Color which the triangulator gives to solids without a color in the
+/// STEP file
+const UNCOLORED: [u8; 4] = [128, 128, 128, 255];
+
+/// Direction towards the fixed light, in model coordinates (above and in
+/// front of a Z-up model, off to one side)
+const FIXED_LIGHT: [f32; 3] = [-0.4, -0.6, 0.7];
+
+/// Where the model is lit from
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Light {
+    /// From the camera, so that whatever faces the viewer is brightest
+    Headlight,
+    /// From a fixed direction relative to the model, which moves with it
+    Fixed,
+}
+
+/// How light reflects off the model
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Material {
+    /// Multiplied into every color (along with the theme's base color)
+    pub base: [f32; 3],
+    /// Brightness of surfaces which face away from the light
+    pub ambient: f32,
+    pub diffuse: f32,
+    /// Strength of the white highlight
+    pub specular: f32,
+    /// Sharpness of the highlight
+    pub shininess: f32,
+    pub light: Light,
+}
+
+impl Material {
+    /// Diffuse shading only, which is the viewer's original look
+    pub const MATTE: Material = Material {
+        base: [1.0; 3],
+        ambient: 0.0,
+        diffuse: 1.0,
+        specular: 0.0,
+        shininess: 1.0,
+        light: Light::Headlight,
+    };
+    pub const SATIN: Material = Material {
+        ambient: 0.15,
+        diffuse: 0.75,
+        specular: 0.15,
+        shininess: 8.0,
+        ..Material::MATTE
+    };
+    pub const GLOSSY: Material = Material {
+        ambient: 0.1,
+        diffuse: 0.7,
+        specular: 0.5,
+        shininess: 64.0,
+        ..Material::MATTE
+    };
+
+    /// Steps to the next preset, keeping the base color and light
+    pub fn next(self) -> Self {
+        let preset = if self.same_weights(&Material::MATTE) {
+            Material::SATIN
+        } else if self.same_weights(&Material::SATIN) {
+            Material::GLOSSY
+        } else {
+            Material::MATTE
+        };
+        Material {
+            base: self.base,
+            light: self.light,
+            ..preset
+        }
+    }
+
+    fn same_weights(&self, other: &Material) -> bool {
+        (self.ambient, self.diffuse, self.specular, self.shininess)
+            == (
+                other.ambient,
+                other.diffuse,
+                other.specular,
+                other.shininess,
+            )
+    }
+
+    /// Switches between the headlight and the fixed light
+    pub fn toggle_light(self) -> Self {
+        let light = match self.light {
+            Light::Headlight => Light::Fixed,
+            Light::Fixed => Light::Headlight,
+        };
+        Material { light, ..self }
+    }
+
+    /// Returns the ambient, diffuse, and specular weights and the
+    /// shininess, as passed to the shader
+    pub fn weights(&self) -> [f32; 4] {
+        [self.ambient, self.diffuse, self.specular, self.shininess]
+    }
+
+    /// Returns the light's direction, with a final element which is 1 for
+    /// the headlight (whose direction is ignored) and 0 for the fixed light
+    pub fn light(&self) -> [f32; 4] {
+        let [x, y, z] = FIXED_LIGHT;
+        let headlight = (self.light == Light::Headlight) as u32 as f32;
+        [x, y, z, headlight]
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::MATTE
+    }
+}
+
+/// Picks a color for a part by hashing the STEP entity which it was built
+/// from, so that it's the same color every time a file is loaded
+pub fn palette_color(shape: usize) -> [f32; 4] {
+    // Salted so that parts don't match the colors of their faces in the
+    // per-face debug mode
+    let [r, g, b, _] = hue_color((mix(shape as u64 ^ 0x5EED) >> 40) as f32 / (1u64 << 24) as f32);
+    // Paler than the debug colors, since this is the everyday look
+    let f = |c: f32| 0.4 + 0.6 * c;
+    [f(r), f(g), f(b), 1.0]
+}
+
+/// Returns a palette color for each part which has no color in the STEP
+/// file, or zero (transparent) for parts which do.  Models with only one
+/// shape aren't given palette colors, since there are no neighbors to tell
+/// apart.
+pub fn part_palette(mesh: &Mesh) -> Vec<[f32; 4]> {
+    let single = mesh.parts.windows(2).all(|w| w[0].shape == w[1].shape);
+    let colors = mesh.vertex_colors();
+    mesh.parts
+        .iter()
+        .map(|p| {
+            let uncolored = mesh.triangles[p.triangles.clone()]
+                .iter()
+                .flat_map(|t| t.verts.iter())
+                .all(|v| colors[*v as usize] == UNCOLORED);
+            if uncolored && !single {
+                palette_color(p.shape)
+            } else {
+                [0.0; 4]
+            }
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use step::{fixture, step_file::StepFile};
+    use triangulate::triangulate::triangulate;
+
+    fn load(asm: fixture::Assembly) -> Mesh {
+        let text = asm.to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        triangulate(&step).0
+    }
+
+    #[test]
+    fn test_palette_color() {
+        // Colors are stable and light enough to shade
+        assert_eq!(palette_color(12), palette_color(12));
+        for i in 0..100 {
+            let c = palette_color(i);
+            assert!(c[..3].iter().all(|c| (0.4..=1.0).contains(c)), "{:?}", c);
+            assert_eq!(c[3], 1.0);
+        }
+        // Nearby entity indices (e.g. neighboring solids) get distinct
+        // colors
+        for i in 0..20 {
+            assert_ne!(palette_color(i), palette_color(i + 1));
+        }
+    }
+
+    #[test]
+    fn test_part_palette() {
+        let mesh = load(fixture::assembly(vec![
+            fixture::cube(1.0),
+            fixture::cube(1.0).color([1.0, 0.0, 0.0]),
+            fixture::cylinder(1.0, 1.0).translate([5.0, 0.0, 0.0]),
+        ]));
+        assert_eq!(mesh.parts.len(), 3);
+        let palette = part_palette(&mesh);
+        let colors = mesh.vertex_colors();
+        for (p, c) in mesh.parts.iter().zip(&palette) {
+            let v = mesh.triangles[p.triangles.start].verts[0] as usize;
+            if colors[v] == UNCOLORED {
+                assert_eq!(*c, palette_color(p.shape));
+            } else {
+                assert_eq!(*c, [0.0; 4]);
+            }
+        }
+        assert_eq!(palette.iter().filter(|c| c[3] == 0.0).count(), 1);
+
+        // Reloading gives each shape the same color, even though parts may
+        // come out in a different order
+        let by_shape = |mesh: &Mesh| {
+            let mut out: Vec<(usize, [u32; 4])> = mesh
+                .parts
+                .iter()
+                .zip(part_palette(mesh))
+                .map(|(p, c)| (p.shape, c.map(f32::to_bits)))
+                .collect();
+            out.sort_unstable();
+            out
+        };
+        let again = load(fixture::assembly(vec![
+            fixture::cube(1.0),
+            fixture::cube(1.0).color([1.0, 0.0, 0.0]),
+            fixture::cylinder(1.0, 1.0).translate([5.0, 0.0, 0.0]),
+        ]));
+        assert_eq!(by_shape(&again), by_shape(&mesh));
+
+        // A lone part keeps its own color
+        let single = load(fixture::assembly(vec![fixture::cube(1.0)]));
+        assert_eq!(part_palette(&single), vec![[0.0; 4]]);
+    }
+
+    #[test]
+    fn test_material_controls() {
+        let m = Material::default();
+        assert_eq!(m, Material::MATTE);
+        assert_eq!(m.next(), Material::SATIN);
+        assert_eq!(m.next().next(), Material::GLOSSY);
+        assert_eq!(m.next().next().next(), Material::MATTE);
+
+        // Cycling presets keeps the light, and vice versa
+        let fixed = m.toggle_light();
+        assert_eq!(fixed.light, Light::Fixed);
+        assert_eq!(fixed.next().light, Light::Fixed);
+        assert_eq!(fixed.toggle_light(), m);
+        assert_eq!(m.light()[3], 1.0);
+        assert_eq!(fixed.light()[3], 0.0);
+        assert_eq!(Material::GLOSSY.weights(), [0.1, 0.7, 0.5, 64.0]);
+    }
+}
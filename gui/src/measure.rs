@@ -0,0 +1,132 @@
+use step::units::Dimension;
+use triangulate::{mesh::Mesh, units::LengthUnit};
+
+/// Number of significant digits shown in measurements
+const SIGNIFICANT: i32 = 4;
+
+/// Something which is measured when it's selected
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Target {
+    /// A single face, as an index into [`Mesh::faces`], whose tessellated
+    /// area is measured
+    Face(usize),
+    /// A whole part, as an index into [`Mesh::parts`], whose surface area
+    /// and volume are measured
+    Part(usize),
+}
+
+/// Formats a number to a few significant digits, without trailing zeros
+fn number(x: f64) -> String {
+    if x == 0.0 || !x.is_finite() {
+        return format!("{}", x);
+    }
+    let decimals = (SIGNIFICANT - 1 - x.abs().log10().floor() as i32).max(0);
+    let s = format!("{:.*}", decimals as usize, x);
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_owned()
+    } else {
+        s
+    }
+}
+
+/// Formats a length, area, or volume in the model's units, e.g. "12.5 mm^2",
+/// picking a metric unit which suits its size.  Models which don't declare a
+/// unit are measured in generic "units".  Exponents are spelled out, since
+/// the text overlay's font only has ASCII characters.
+pub fn quantity(x: f64, unit: Option<LengthUnit>, dimension: Dimension) -> String {
+    match unit {
+        Some(u) => format!("{:#}", u.quantity(x, dimension)),
+        None => {
+            let exponent = match dimension {
+                Dimension::Length => "",
+                Dimension::Area => "^2",
+                Dimension::Volume => "^3",
+            };
+            format!("{} units{}", number(x), exponent)
+        }
+    }
+}
+
+/// Describes a measurement of the given target, e.g. "Face #12: area 25 mm^2"
+pub fn measure_text(mesh: &Mesh, target: Target, unit: Option<LengthUnit>) -> String {
+    match target {
+        Target::Face(i) => {
+            let m = mesh.face_properties(&[i]);
+            format!(
+                "Face #{}: area {}",
+                mesh.faces[i].id,
+                quantity(m.area, unit, Dimension::Area)
+            )
+        }
+        Target::Part(i) => {
+            let m = mesh.part_properties(i);
+            format!(
+                "Part #{}: area {}, volume {}",
+                mesh.parts[i].shape,
+                quantity(m.area, unit, Dimension::Area),
+                quantity(m.volume, unit, Dimension::Volume)
+            )
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number(0.0), "0");
+        assert_eq!(number(4.0), "4");
+        assert_eq!(number(24.0), "24");
+        assert_eq!(number(12.5), "12.5");
+        assert_eq!(number(1234.5678), "1235");
+        assert_eq!(number(123456.7), "123457");
+        assert_eq!(number(0.00123456), "0.001235");
+        assert_eq!(number(-8.0), "-8");
+    }
+
+    #[test]
+    fn test_quantity() {
+        use Dimension::*;
+        use LengthUnit::*;
+        assert_eq!(quantity(12.5, Some(Millimetre), Area), "12.5 mm^2");
+        assert_eq!(quantity(0.25, Some(Metre), Length), "250 mm");
+        assert_eq!(quantity(2e6, Some(Millimetre), Area), "2 m^2");
+        assert_eq!(quantity(0.02, Some(Millimetre), Length), "20 um");
+        assert_eq!(quantity(3.0, None, Area), "3 units^2");
+        assert_eq!(quantity(3.0, None, Length), "3 units");
+
+        // Inches are converted, with areas and volumes scaled by the square
+        // and cube of an inch
+        assert_eq!(quantity(1.0, Some(Inch), Length), "25.4 mm");
+        assert_eq!(quantity(1.0, Some(Inch), Area), "645.2 mm^2");
+        assert_eq!(quantity(1000.0, Some(Inch), Volume), "16387064 mm^3");
+    }
+
+    #[test]
+    fn test_measure_text() {
+        use step::{fixture, step_file::StepFile};
+        use triangulate::triangulate::triangulate;
+
+        let text = fixture::cube(2.0).to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let mesh = triangulate(&step).0;
+
+        let face = &mesh.faces[0];
+        assert_eq!(
+            measure_text(&mesh, Target::Face(0), Some(LengthUnit::Millimetre)),
+            format!("Face #{}: area 4 mm^2", face.id)
+        );
+        assert_eq!(
+            measure_text(&mesh, Target::Part(0), Some(LengthUnit::Inch)),
+            format!(
+                "Part #{}: area 15484 mm^2, volume 131097 mm^3",
+                mesh.parts[0].shape
+            )
+        );
+    }
+}
@@ -1,13 +1,42 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
 
 use bytemuck::{Pod, Zeroable};
-use glm::{Mat4, Vec4};
+use glm::{Mat4, Vec3, Vec4};
 use nalgebra_glm as glm;
 use wgpu::util::DeviceExt;
 
-use triangulate::mesh::{Triangle, Vertex};
+use triangulate::{
+    mesh::{Face, Mesh, Vertex},
+    stats::Stats,
+};
 
-use crate::camera::Camera;
+use crate::{
+    camera::Camera,
+    chunks::{local_range, split, ChunkBudget},
+    debug_colors::ColorMode,
+    gpu::out_of_memory_errors,
+    grow::{GpuBufferOps, GrowableBuffer},
+    inspect::{curvature_color, curvature_colors},
+    instances::{group_instances, instance_data, unique_geometry, visible_runs, GPUInstance},
+    material::{part_palette, Material},
+    outline::MASK_FORMAT,
+};
+
+/// Face index for vertices which aren't part of a face
+const NO_FACE: u32 = u32::MAX;
+
+/// Color of the caps which fill in a section, which stands out from the
+/// model's own colors
+const CAP_COLOR: [f32; 4] = [0.85, 0.3, 0.25, 1.0];
+
+/// Number of times that a model is decimated (each time more coarsely) if
+/// its buffers can't be allocated, before giving up
+const MAX_DECIMATIONS: i32 = 6;
+
+/// Cell size for the first decimation, as a fraction of the model's radius
+const DECIMATION_CELL: f64 = 1.0 / 1024.0;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
@@ -15,68 +44,403 @@ struct GPUVertex {
     pos: [f32; 4],
     norm: [f32; 4],
     color: [f32; 4],
+    /// Curvature color, used by the curvature overlay
+    tint: [f32; 4],
+    face: u32,
 }
 
 impl GPUVertex {
-    fn from_vertex(v: &Vertex) -> Self {
+    /// Builds a GPU vertex, with its color from [`Mesh::vertex_colors`]
+    fn from_vertex(v: &Vertex, color: [u8; 4], tint: [f32; 4], face: Option<usize>) -> Self {
         Self {
             pos: [v.pos.x as f32, v.pos.y as f32, v.pos.z as f32, 1.0],
             norm: [v.norm.x as f32, v.norm.y as f32, v.norm.z as f32, 1.0],
-            color: [v.color.x as f32, v.color.y as f32, v.color.z as f32, 1.0],
+            color: color.map(|c| c as f32 / 255.0),
+            tint,
+            face: face.map(|f| f as u32).unwrap_or(NO_FACE),
         }
     }
 }
 
+/// Uniforms for `model.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct ModelUniforms {
+    view_mat: [[f32; 4]; 4],
+    model_mat: [[f32; 4]; 4],
+    /// Color mode, curvature tint, and palette flags, then padding
+    mode: [u32; 4],
+    /// Base color, set by the theme and the material
+    base: [f32; 4],
+    /// Ambient, diffuse, and specular weights, then shininess
+    material: [f32; 4],
+    /// Direction of the fixed light, then 1 to use the headlight instead
+    light: [f32; 4],
+    /// Section plane, or zero to draw the whole model
+    clip: [f32; 4],
+}
+
+/// Which faces are drawn
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Culling {
+    /// Back faces are culled, which is faster and works for solid models
+    Back,
+    /// Both sides of each face are drawn and shaded, which is needed for
+    /// open shells and sheet bodies
+    TwoSided,
+}
+
+impl Culling {
+    /// Picks a default based on the triangulation statistics:  meshes with
+    /// open shells (or holes from failed faces) are drawn two-sided.
+    pub fn for_stats(stats: &Stats) -> Self {
+        if stats.is_open() {
+            Culling::TwoSided
+        } else {
+            Culling::Back
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Culling::Back => Culling::TwoSided,
+            Culling::TwoSided => Culling::Back,
+        }
+    }
+}
+
+/// Part of the model's geometry, with its own vertex and index buffers so
+/// that no single buffer (or draw call) grows too large
+struct Chunk {
+    vertex_buf: GrowableBuffer<wgpu::Buffer>,
+    index_buf: GrowableBuffer<wgpu::Buffer>,
+    /// Position of the chunk's first index in the model's index space, which
+    /// runs through each chunk in turn
+    start: u32,
+}
+
+impl Chunk {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        verts: &[GPUVertex],
+        indices: &[u32],
+        start: u32,
+    ) -> Self {
+        Self {
+            vertex_buf: GrowableBuffer::new(
+                &mut vertex_buf_ops(device, queue),
+                bytemuck::cast_slice(verts),
+            ),
+            index_buf: GrowableBuffer::new(
+                &mut index_buf_ops(device, queue),
+                bytemuck::cast_slice(indices),
+            ),
+            start,
+        }
+    }
+
+    fn num_verts(&self) -> u32 {
+        (self.vertex_buf.len() / std::mem::size_of::<GPUVertex>() as u64) as u32
+    }
+
+    fn num_indices(&self) -> u32 {
+        (self.index_buf.len() / std::mem::size_of::<u32>() as u64) as u32
+    }
+
+    /// Returns the chunk's range in the model's index space
+    fn range(&self) -> Range<u32> {
+        self.start..(self.start + self.num_indices())
+    }
+}
+
+/// Builds chunks for vertices and indices (which refer to `verts`), with
+/// the first one starting at `start` in the model's index space
+fn build_chunks(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    verts: &[GPUVertex],
+    indices: &[u32],
+    budget: &ChunkBudget,
+    start: u32,
+) -> Vec<Chunk> {
+    split(indices, verts.len(), budget)
+        .into_iter()
+        .map(|c| {
+            let start = start + c.range.start as u32;
+            match c.remap {
+                None => Chunk::new(device, queue, verts, indices, start),
+                Some(r) => {
+                    let verts: Vec<GPUVertex> =
+                        r.verts.iter().map(|v| verts[*v as usize]).collect();
+                    Chunk::new(device, queue, &verts, &r.indices, start)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Builds vertices for a mesh and uploads them into chunks, starting at the
+/// beginning of the model's index space
+fn upload_geometry(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    geometry: &Mesh,
+    radius: f32,
+    budget: &ChunkBudget,
+) -> Vec<Chunk> {
+    // Parts with and without explicit colors can be mixed freely, since
+    // every vertex carries its own color either way
+    let tints = curvature_colors(geometry, radius);
+    let mut vertex_data: Vec<GPUVertex> = geometry
+        .verts
+        .iter()
+        .zip(geometry.vertex_colors())
+        .zip(tints)
+        .zip(geometry.vertex_faces())
+        .map(|(((v, c), t), f)| GPUVertex::from_vertex(v, c, t, f))
+        .collect();
+    let mut index_data: Vec<u32> = geometry
+        .triangles
+        .iter()
+        .flat_map(|t| t.verts.iter())
+        .copied()
+        .collect();
+
+    // Models made only of wires have no triangles, but empty buffers
+    // aren't allowed, so pad with a degenerate triangle (which is never
+    // drawn)
+    if index_data.is_empty() {
+        vertex_data.push(GPUVertex::zeroed());
+        index_data = vec![0; 3];
+    }
+    build_chunks(device, queue, &vertex_data, &index_data, budget, 0)
+}
+
 pub struct Model {
-    vertex_buf: wgpu::Buffer,
-    index_buf: wgpu::Buffer,
+    /// Vertex and index buffers, split to stay under `budget`.  The last
+    /// chunk grows as geometry is appended to a preview (see
+    /// [`Model::append`]) or faces are replaced.
+    chunks: Vec<Chunk>,
+    budget: ChunkBudget,
+    /// Per-instance transforms and tints, one per part
+    instance_buf: wgpu::Buffer,
     uniform_buf: wgpu::Buffer,
+    /// Size of the buffers other than the chunks, in bytes
+    fixed_memory: u64,
+    /// Per-face colors, used by the debug color modes
+    face_color_buf: wgpu::Buffer,
+    color_mode: ColorMode,
+    show_curvature: bool,
+    /// Multiplied into vertex colors, set by the theme
+    base_color: [f32; 3],
+    material: Material,
+    /// Whether parts without a STEP color are drawn with palette colors
+    show_palette: bool,
     bind_group: wgpu::BindGroup,
-    index_count: u32,
-    render_pipeline: wgpu::RenderPipeline,
+    /// Faces of the uploaded geometry, which index the face color table
+    faces: Vec<Face>,
+
+    /// Pipelines with and without back-face culling, selected by `culling`
+    cull_pipeline: wgpu::RenderPipeline,
+    two_sided_pipeline: wgpu::RenderPipeline,
+    culling: Culling,
+    /// Draws selected parts into the outline's mask texture
+    mask_pipeline: wgpu::RenderPipeline,
+
+    /// Section plane (see [`crate::section::Section::clip`]), if the model
+    /// is cut open
+    clip: Option<[f32; 4]>,
+    /// Triangles which fill in the section, and how many vertices they have
+    caps: Option<(wgpu::Buffer, u32)>,
+    /// Draws the caps, which are already in model space, as one instance
+    cap_pipeline: wgpu::RenderPipeline,
+    cap_instance_buf: wgpu::Buffer,
+
+    /// Bounding sphere (center and radius) of the model's vertices
+    bounding_sphere: (Vec3, f32),
+
+    /// Parts which share geometry are drawn with instancing
+    groups: Vec<Group>,
 }
 
+/// Parts which are drawn as instances of the same geometry: its ranges in
+/// the model's index space (the first from the initial upload, then one per
+/// face replaced by [`Model::replace_faces`]), the parts, and the index of
+/// the first instance in `instance_buf`
+type Group = (Vec<Range<u32>>, Vec<usize>, u32);
+
 impl Model {
+    /// Builds a preview from the first chunk of a mesh which is still being
+    /// triangulated.  More chunks can be added with [`Model::append`].
+    ///
+    /// Previews are drawn as a single instance without face information, so
+    /// parts can't be hidden or selected and the debug color modes fall
+    /// back to vertex colors.
+    pub fn preview(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        swapchain_format: wgpu::TextureFormat,
+        chunk: &Mesh,
+        budget: ChunkBudget,
+    ) -> Self {
+        let mesh = Mesh {
+            parts: vec![],
+            faces: vec![],
+            ..chunk.clone()
+        };
+        Self::new(device, queue, swapchain_format, &mesh, budget)
+    }
+
+    /// Appends another chunk to a model built with [`Model::preview`],
+    /// growing its buffers as needed
+    pub fn append(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, chunk: &Mesh) {
+        assert!(
+            self.groups.len() == 1 && self.groups[0].1.is_empty(),
+            "Only previews can be appended to"
+        );
+        let vertex_data: Vec<GPUVertex> = chunk
+            .verts
+            .iter()
+            .zip(chunk.vertex_colors())
+            .map(|(v, c)| GPUVertex::from_vertex(v, c, [0.0; 4], None))
+            .collect();
+        let index_data: Vec<u32> = chunk
+            .triangles
+            .iter()
+            .flat_map(|t| t.verts.iter())
+            .copied()
+            .collect();
+        let range = self.push_geometry(device, queue, &vertex_data, &index_data);
+        self.groups[0].0[0].end = range.end;
+        self.bounding_sphere = merge_spheres(self.bounding_sphere, bounding_sphere(chunk));
+    }
+
     pub fn new(
         device: &wgpu::Device,
+        queue: &wgpu::Queue,
         swapchain_format: wgpu::TextureFormat,
-        verts: &[Vertex],
-        tris: &[Triangle],
+        mesh: &Mesh,
+        budget: ChunkBudget,
     ) -> Self {
-        let vertex_data: Vec<GPUVertex> = verts.iter().map(GPUVertex::from_vertex).collect();
-        let index_data: Vec<u32> = tris.iter().flat_map(|t| t.verts.iter()).copied().collect();
+        let bounding_sphere = bounding_sphere(mesh);
 
-        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex buffer"),
-            contents: bytemuck::cast_slice(&vertex_data),
-            usage: wgpu::BufferUsage::VERTEX,
-        });
+        // Only upload one copy of each shape, then draw every part which
+        // uses that shape as an instance.  Meshes without part information
+        // are drawn as a single instance.
+        let instance_groups = group_instances(mesh);
+        let (mut geometry, instances, group_parts) = if instance_groups.is_empty() {
+            let identity = GPUInstance {
+                transform: Mat4::identity().into(),
+                tint: [1.0; 4],
+                palette: [0.0; 4],
+            };
+            (Cow::Borrowed(mesh), vec![identity], vec![])
+        } else {
+            let geometry = unique_geometry(mesh, &instance_groups);
+            let mut instances = instance_data(mesh, &instance_groups);
+            // Instances are stored in group order
+            let palette = part_palette(mesh);
+            for (inst, p) in instances
+                .iter_mut()
+                .zip(instance_groups.iter().flat_map(|g| g.parts.iter()))
+            {
+                inst.palette = palette[*p];
+            }
+            let mut first = 0;
+            let group_parts: Vec<(Vec<usize>, u32)> = instance_groups
+                .into_iter()
+                .map(|g| {
+                    let out = (g.parts, first);
+                    first += out.0.len() as u32;
+                    out
+                })
+                .collect();
+            (Cow::Owned(geometry), instances, group_parts)
+        };
 
-        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index buffer"),
-            contents: bytemuck::cast_slice(&index_data),
-            usage: wgpu::BufferUsage::INDEX,
+        // If the GPU runs out of memory, try again with coarser and coarser
+        // versions of the geometry rather than leaving holes in the model
+        let mut attempt = 0;
+        let chunks = loop {
+            let errors = out_of_memory_errors();
+            let chunks = upload_geometry(device, queue, &geometry, bounding_sphere.1, &budget);
+            if out_of_memory_errors() == errors || attempt == MAX_DECIMATIONS {
+                break chunks;
+            }
+            drop(chunks);
+            let cell = bounding_sphere.1 as f64 * DECIMATION_CELL * 2f64.powi(attempt);
+            log::warn!(
+                "Out of GPU memory with {} triangles; decimating with a cell size of {}",
+                geometry.triangles.len(),
+                cell
+            );
+            geometry = Cow::Owned(geometry.decimate(cell));
+            attempt += 1;
+        };
+
+        let groups = if group_parts.is_empty() {
+            let all = 0..(geometry.triangles.len() as u32 * 3);
+            vec![(vec![all], vec![], 0)]
+        } else {
+            group_parts
+                .into_iter()
+                .zip(&geometry.parts)
+                .map(|((parts, first), p)| {
+                    let range = (p.triangles.start as u32 * 3)..(p.triangles.end as u32 * 3);
+                    (vec![range], parts, first)
+                })
+                .collect()
+        };
+
+        let instance_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
         });
 
+        let uniform_size = std::mem::size_of::<ModelUniforms>();
         let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Uniform Buffer"),
-            size: std::mem::size_of::<Mat4>() as wgpu::BufferAddress * 2,
+            size: uniform_size as wgpu::BufferAddress,
             usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
             mapped_at_creation: false,
         });
 
+        // Storage buffers can't be empty, so we always allocate at least
+        // one color here.
+        let face_color_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Face color buffer"),
+            size: (std::mem::size_of::<Vec4>() * geometry.faces.len().max(1))
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStage::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Mat4>() as u64 * 2),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(uniform_size as u64),
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
         });
 
         // Create pipeline layout
@@ -108,16 +472,49 @@ impl Model {
                     offset: 2 * std::mem::size_of::<Vec4>() as wgpu::BufferAddress,
                     shader_location: 2,
                 },
+                // Curvature tints
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 3 * std::mem::size_of::<Vec4>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                },
+                // Face indices
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Uint32,
+                    offset: 4 * std::mem::size_of::<Vec4>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                },
+            ],
+        };
+
+        // Each instance has a transform (as four columns), a tint, and a
+        // palette color
+        let instance_buf_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GPUInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![
+                5 => Float32x4,
+                6 => Float32x4,
+                7 => Float32x4,
+                8 => Float32x4,
+                9 => Float32x4,
+                10 => Float32x4,
             ],
         };
 
         // Create bind group
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buf.as_entire_binding(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: face_color_buf.as_entire_binding(),
+                },
+            ],
             label: None,
         });
 
@@ -137,17 +534,79 @@ impl Model {
             flags: wgpu::ShaderFlags::all(),
         });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
+        // Both pipelines share a shader, which flips normals of back faces
+        // (these are only rasterized by the two-sided pipeline).
+        let make_pipeline = |cull_mode| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[vertex_buf_layout.clone(), instance_buf_layout.clone()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[swapchain_format.into()],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode,
+                    ..wgpu::PrimitiveState::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Greater,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+            })
+        };
+        let cull_pipeline = make_pipeline(Some(wgpu::Face::Back));
+        let two_sided_pipeline = make_pipeline(None);
+
+        // The mask pipeline uses the same vertex shader, so its depth
+        // values match the model's exactly and the depth test only keeps
+        // the selected parts' visible pixels.
+        let mask_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Selection mask"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_buf_layout.clone(), instance_buf_layout.clone()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_mask",
+                targets: &[MASK_FORMAT.into()],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::GreaterEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        // Caps are seen from either side as the camera turns, and are
+        // depth-tested like the model so that nearer parts hide them
+        let cap_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Section caps"),
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[vertex_buf_layout],
+                buffers: &[vertex_buf_layout.clone(), instance_buf_layout.clone()],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
-                entry_point: "fs_main",
+                entry_point: "fs_cap",
                 targets: &[swapchain_format.into()],
             }),
             primitive: wgpu::PrimitiveState::default(),
@@ -160,43 +619,275 @@ impl Model {
             }),
             multisample: wgpu::MultisampleState::default(),
         });
+        let cap_instance_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cap instance buffer"),
+            contents: bytemuck::bytes_of(&GPUInstance {
+                transform: Mat4::identity().into(),
+                tint: [1.0; 4],
+                palette: [0.0; 4],
+            }),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
 
+        let fixed_memory = (std::mem::size_of_val(instances.as_slice())
+            + uniform_size
+            + std::mem::size_of::<Vec4>() * geometry.faces.len().max(1))
+            as u64;
         Model {
-            render_pipeline,
-            index_buf,
-            vertex_buf,
+            bounding_sphere,
+            groups,
+            faces: geometry.faces.clone(),
+            cull_pipeline,
+            two_sided_pipeline,
+            culling: Culling::Back,
+            mask_pipeline,
+            clip: None,
+            caps: None,
+            cap_pipeline,
+            cap_instance_buf,
+            chunks,
+            budget,
+            instance_buf,
             uniform_buf,
+            fixed_memory,
+            face_color_buf,
+            color_mode: ColorMode::Normal,
+            show_curvature: false,
+            base_color: [1.0; 3],
+            material: Material::default(),
+            show_palette: true,
             bind_group,
-            index_count: tris.len() as u32 * 3,
         }
     }
 
+    /// Uploads new triangles for faces which were triangulated again (see
+    /// [`Mesh::splice_faces`]), given as indices into the mesh's faces.
+    ///
+    /// Rather than rebuilding the buffers, each face's new vertices and
+    /// triangles are appended, and its old triangles are overwritten with
+    /// degenerate ones (which draw nothing).  Faces in parts which are drawn
+    /// as instances of another part are skipped, since their geometry is
+    /// the other part's.
+    pub fn replace_faces(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mesh: &Mesh,
+        faces: &[usize],
+    ) {
+        let radius = self.bounding_sphere.1;
+        for i in faces {
+            let (g, k) = match geometry_face(mesh, *i, &self.groups, &self.faces) {
+                Some(f) => f,
+                None => continue,
+            };
+            let old = self.faces[k].triangles.clone();
+            let old = (old.start as u32 * 3)..(old.end as u32 * 3);
+            for c in &self.chunks {
+                if let Some(r) = local_range(c.range(), &old) {
+                    let zeros = vec![0u32; r.len()];
+                    queue.write_buffer(
+                        c.index_buf.buffer(),
+                        (r.start as usize * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+                        bytemuck::cast_slice(&zeros),
+                    );
+                }
+            }
+
+            let mut vertex_data: Vec<GPUVertex> = vec![];
+            let mut remap = HashMap::new();
+            let face = &mesh.faces[*i];
+            let index_data: Vec<u32> = mesh.triangles[face.triangles.clone()]
+                .iter()
+                .flat_map(|t| t.verts.iter())
+                .map(|v| {
+                    *remap.entry(*v).or_insert_with(|| {
+                        let v = *v as usize;
+                        let color = mesh.colors.as_ref().map_or(mesh.verts[v].rgba(), |c| c[v]);
+                        let k_v = mesh.curvature.as_ref().map_or(f32::NAN, |c| c[v]);
+                        let tint = curvature_color(k_v, radius);
+                        vertex_data.push(GPUVertex::from_vertex(
+                            &mesh.verts[v],
+                            color,
+                            tint,
+                            Some(k),
+                        ));
+                        vertex_data.len() as u32 - 1
+                    })
+                })
+                .collect();
+
+            let range = self.push_geometry(device, queue, &vertex_data, &index_data);
+            self.faces[k].triangles = (range.start as usize / 3)..(range.end as usize / 3);
+            self.groups[g].0.push(range);
+        }
+    }
+
+    /// Adds vertices and indices (which refer to `verts`) to the end of the
+    /// model's index space, returning their range.  They're appended to the
+    /// last chunk if it has room, and otherwise put into new chunks.
+    fn push_geometry(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        verts: &[GPUVertex],
+        indices: &[u32],
+    ) -> Range<u32> {
+        let last = self.chunks.last_mut().unwrap();
+        let start = last.range().end;
+        let end = start + indices.len() as u32;
+        let (num_verts, num_indices) = (last.num_verts(), last.num_indices());
+        if self.budget.fits(
+            num_verts as usize + verts.len(),
+            num_indices as usize + indices.len(),
+        ) {
+            let indices: Vec<u32> = indices.iter().map(|i| i + num_verts).collect();
+            last.vertex_buf.append(
+                &mut vertex_buf_ops(device, queue),
+                bytemuck::cast_slice(verts),
+            );
+            last.index_buf.append(
+                &mut index_buf_ops(device, queue),
+                bytemuck::cast_slice(&indices),
+            );
+        } else if !indices.is_empty() {
+            let chunks = build_chunks(device, queue, verts, indices, &self.budget, start);
+            self.chunks.extend(chunks);
+        }
+        start..end
+    }
+
+    /// Returns the GPU memory used by the model's buffers, in bytes
+    pub fn memory(&self) -> u64 {
+        self.fixed_memory
+            + self
+                .chunks
+                .iter()
+                .map(|c| c.vertex_buf.capacity() + c.index_buf.capacity())
+                .sum::<u64>()
+            + self.caps.as_ref().map_or(0, |(_, n)| {
+                (*n as usize * std::mem::size_of::<GPUVertex>()) as u64
+            })
+    }
+
+    pub fn bounding_sphere(&self) -> (Vec3, f32) {
+        self.bounding_sphere
+    }
+
+    /// Returns the faces of the uploaded geometry, in the order expected by
+    /// [`Model::set_color_mode`]
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    /// Switches to a new color mode, uploading a table with one color per
+    /// face in the mesh (see [`crate::debug_colors::face_colors`])
+    pub fn set_color_mode(&mut self, queue: &wgpu::Queue, mode: ColorMode, colors: &[[f32; 4]]) {
+        if !colors.is_empty() {
+            queue.write_buffer(&self.face_color_buf, 0, bytemuck::cast_slice(colors));
+        }
+        self.color_mode = mode;
+    }
+
+    /// Enables or disables tinting the model by its curvature, which
+    /// overrides the color mode
+    pub fn set_show_curvature(&mut self, show: bool) {
+        self.show_curvature = show;
+    }
+
+    pub fn set_base_color(&mut self, base: [f32; 3]) {
+        self.base_color = base;
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    /// Enables or disables palette colors for parts which don't have a
+    /// color in the STEP file
+    pub fn set_show_palette(&mut self, show: bool) {
+        self.show_palette = show;
+    }
+
+    pub fn set_culling(&mut self, culling: Culling) {
+        self.culling = culling;
+    }
+
+    /// Cuts the model open with a section plane, or draws all of it if
+    /// `clip` is `None`.  Caps are only drawn while there's a plane.
+    pub fn set_clip(&mut self, clip: Option<[f32; 4]>) {
+        self.clip = clip;
+    }
+
+    /// Replaces the caps which fill in the section (as built by
+    /// [`crate::section::Section::build`]), which are flat triangles in
+    /// model space with the given normal
+    pub fn set_caps(
+        &mut self,
+        device: &wgpu::Device,
+        tris: &[[glm::DVec3; 3]],
+        normal: glm::DVec3,
+    ) {
+        let norm = [normal.x as f32, normal.y as f32, normal.z as f32, 1.0];
+        let verts: Vec<GPUVertex> = tris
+            .iter()
+            .flatten()
+            .map(|p| GPUVertex {
+                pos: [p.x as f32, p.y as f32, p.z as f32, 1.0],
+                norm,
+                color: CAP_COLOR,
+                tint: [0.0; 4],
+                face: NO_FACE,
+            })
+            .collect();
+        self.caps = if verts.is_empty() {
+            None
+        } else {
+            let buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Cap vertex buffer"),
+                contents: bytemuck::cast_slice(&verts),
+                usage: wgpu::BufferUsage::VERTEX,
+            });
+            Some((buf, verts.len() as u32))
+        };
+    }
+
     pub fn draw(
         &self,
         camera: &Camera,
         queue: &wgpu::Queue,
-        frame: &wgpu::SwapChainTexture,
+        view: &wgpu::TextureView,
         depth_view: &wgpu::TextureView,
+        visible: &[bool],
         encoder: &mut wgpu::CommandEncoder,
     ) {
         // Update the uniform buffer with our new matrix
-        let view_mat = camera.view_matrix();
-        let model_mat = camera.model_matrix();
-        queue.write_buffer(
-            &self.uniform_buf,
-            0,
-            bytemuck::cast_slice(view_mat.as_slice()),
-        );
-        queue.write_buffer(
-            &self.uniform_buf,
-            std::mem::size_of::<Mat4>() as wgpu::BufferAddress,
-            bytemuck::cast_slice(model_mat.as_slice()),
-        );
+        let [r, g, b] = self.base_color;
+        let [mr, mg, mb] = self.material.base;
+        let uniforms = ModelUniforms {
+            view_mat: camera.view_matrix().into(),
+            model_mat: camera.model_matrix().into(),
+            mode: [
+                self.color_mode.shader_index(),
+                self.show_curvature as u32,
+                self.show_palette as u32,
+                0,
+            ],
+            base: [r * mr, g * mg, b * mb, 1.0],
+            material: self.material.weights(),
+            light: self.material.light(),
+            clip: self.clip.unwrap_or_default(),
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniforms));
 
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: &frame.view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Load,
@@ -212,10 +903,318 @@ impl Model {
                 stencil_ops: None,
             }),
         });
-        rpass.set_pipeline(&self.render_pipeline);
-        rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint32);
-        rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        rpass.set_pipeline(match self.culling {
+            Culling::Back => &self.cull_pipeline,
+            Culling::TwoSided => &self.two_sided_pipeline,
+        });
+        rpass.set_vertex_buffer(1, self.instance_buf.slice(..));
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        for c in &self.chunks {
+            rpass.set_index_buffer(c.index_buf.buffer().slice(..), wgpu::IndexFormat::Uint32);
+            rpass.set_vertex_buffer(0, c.vertex_buf.buffer().slice(..));
+            for (ranges, parts, first) in &self.groups {
+                for range in ranges.iter().filter_map(|r| local_range(c.range(), r)) {
+                    if parts.is_empty() {
+                        rpass.draw_indexed(range, 0, 0..1);
+                    } else {
+                        for instances in visible_runs(parts, visible, *first) {
+                            rpass.draw_indexed(range.clone(), 0, instances);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Caps are drawn last, filling in the cut wherever the model's
+        // remaining (back) faces are behind them
+        if let (Some(_), Some((buf, n))) = (self.clip, &self.caps) {
+            rpass.set_pipeline(&self.cap_pipeline);
+            rpass.set_vertex_buffer(0, buf.slice(..));
+            rpass.set_vertex_buffer(1, self.cap_instance_buf.slice(..));
+            rpass.draw(0..*n, 0..1);
+        }
+    }
+
+    /// Draws the selected parts into the outline's mask texture, testing
+    /// against the depth buffer from [`Model::draw`] (which must be called
+    /// first in the same frame, since it uploads the camera matrices).
+    ///
+    /// `selected` has one flag per part.  Meshes without part information
+    /// can't be selected, so they're never drawn into the mask.
+    pub fn draw_mask(
+        &self,
+        mask_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        selected: &[bool],
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: mask_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        rpass.set_pipeline(&self.mask_pipeline);
+        rpass.set_vertex_buffer(1, self.instance_buf.slice(..));
         rpass.set_bind_group(0, &self.bind_group, &[]);
-        rpass.draw_indexed(0..self.index_count, 0, 0..1);
+        for c in &self.chunks {
+            rpass.set_index_buffer(c.index_buf.buffer().slice(..), wgpu::IndexFormat::Uint32);
+            rpass.set_vertex_buffer(0, c.vertex_buf.buffer().slice(..));
+            for (ranges, parts, first) in &self.groups {
+                for range in ranges.iter().filter_map(|r| local_range(c.range(), r)) {
+                    for instances in visible_runs(parts, selected, *first) {
+                        rpass.draw_indexed(range.clone(), 0, instances);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn vertex_buf_ops<'a>(device: &'a wgpu::Device, queue: &'a wgpu::Queue) -> GpuBufferOps<'a> {
+    GpuBufferOps {
+        device,
+        queue,
+        label: "Vertex buffer",
+        usage: wgpu::BufferUsage::VERTEX
+            | wgpu::BufferUsage::COPY_SRC
+            | wgpu::BufferUsage::COPY_DST,
+    }
+}
+
+fn index_buf_ops<'a>(device: &'a wgpu::Device, queue: &'a wgpu::Queue) -> GpuBufferOps<'a> {
+    GpuBufferOps {
+        device,
+        queue,
+        label: "Index buffer",
+        usage: wgpu::BufferUsage::INDEX | wgpu::BufferUsage::COPY_SRC | wgpu::BufferUsage::COPY_DST,
+    }
+}
+
+/// Finds where a face of the mesh (as an index into its faces) was
+/// uploaded, returning its group and its index among the uploaded faces.
+/// Faces of parts which aren't the first in their group aren't uploaded.
+fn geometry_face(
+    mesh: &Mesh,
+    face: usize,
+    groups: &[Group],
+    faces: &[Face],
+) -> Option<(usize, usize)> {
+    let face = &mesh.faces[face];
+    let g = match mesh
+        .parts
+        .iter()
+        .position(|p| p.triangles.contains(&face.triangles.start))
+    {
+        Some(p) => groups.iter().position(|g| g.1.first() == Some(&p))?,
+        None if groups.len() == 1 && groups[0].1.is_empty() => 0,
+        None => return None,
+    };
+    let in_group = |t: usize| groups[g].0.iter().any(|r| r.contains(&(t as u32 * 3)));
+    let k = faces
+        .iter()
+        .position(|f| f.id == face.id && in_group(f.triangles.start))?;
+    Some((g, k))
+}
+
+/// Returns the smallest sphere which contains both spheres
+fn merge_spheres(a: (Vec3, f32), b: (Vec3, f32)) -> (Vec3, f32) {
+    let ((ca, ra), (cb, rb)) = (a, b);
+    let d = (cb - ca).norm();
+    if d + rb <= ra {
+        a
+    } else if d + ra <= rb {
+        b
+    } else {
+        let r = (d + ra + rb) / 2.0;
+        (ca + (cb - ca) * ((r - ra) / d), r)
+    }
+}
+
+/// Computes a bounding sphere for a mesh's vertices and wires, centered on
+/// their axis-aligned bounding box.  Returns a unit sphere at the origin if
+/// the mesh is empty.
+fn bounding_sphere(mesh: &Mesh) -> (Vec3, f32) {
+    let points: Vec<Vec3> = mesh
+        .verts
+        .iter()
+        .map(|v| v.pos)
+        .chain(mesh.wires.iter().flat_map(|w| w.points.iter().copied()))
+        .map(|p| Vec3::new(p.x as f32, p.y as f32, p.z as f32))
+        .collect();
+    if points.is_empty() {
+        return (Vec3::zeros(), 1.0);
+    }
+    let (lo, hi) = points.iter().fold(
+        (Vec3::repeat(f32::INFINITY), Vec3::repeat(-f32::INFINITY)),
+        |(lo, hi), p| (lo.inf(p), hi.sup(p)),
+    );
+    let center = (lo + hi) / 2.0;
+    let radius = points
+        .iter()
+        .map(|p| (p - center).norm())
+        .fold(0.0, f32::max);
+    (center, radius)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_culling() {
+        // Closed solids are culled
+        let mut stats = Stats {
+            num_shells: 2,
+            ..Stats::default()
+        };
+        assert_eq!(Culling::for_stats(&stats), Culling::Back);
+
+        // Any open shell makes the whole model two-sided
+        stats.num_open_shells = 1;
+        assert_eq!(Culling::for_stats(&stats), Culling::TwoSided);
+
+        // Faces which failed to triangulate leave holes, so we can see
+        // into otherwise-closed shells
+        let stats = Stats {
+            num_shells: 1,
+            num_errors: 1,
+            ..Stats::default()
+        };
+        assert_eq!(Culling::for_stats(&stats), Culling::TwoSided);
+        let stats = Stats {
+            num_shells: 1,
+            num_panics: 1,
+            ..Stats::default()
+        };
+        assert_eq!(Culling::for_stats(&stats), Culling::TwoSided);
+
+        // Stats combine, so one open shell in an assembly is enough
+        let a = Stats {
+            num_shells: 1,
+            ..Stats::default()
+        };
+        let b = Stats {
+            num_shells: 1,
+            num_open_shells: 1,
+            ..Stats::default()
+        };
+        assert_eq!(Culling::for_stats(&Stats::combine(a, b)), Culling::TwoSided);
+    }
+
+    #[test]
+    fn test_merge_spheres() {
+        let a = (Vec3::zeros(), 1.0);
+        let b = (Vec3::new(4.0, 0.0, 0.0), 1.0);
+        assert_eq!(merge_spheres(a, b), (Vec3::new(2.0, 0.0, 0.0), 3.0));
+
+        // A sphere which is already inside the other changes nothing
+        let c = (Vec3::new(0.5, 0.0, 0.0), 0.25);
+        assert_eq!(merge_spheres(a, c), a);
+        assert_eq!(merge_spheres(c, a), a);
+    }
+
+    #[test]
+    fn test_geometry_face() {
+        use triangulate::mesh::{Part, Triangle};
+
+        // Two copies of a shape with two faces, which are grouped so that
+        // only the first copy is uploaded
+        let mut mesh = Mesh::default();
+        mesh.verts.push(Vertex {
+            pos: glm::DVec3::zeros(),
+            norm: glm::DVec3::z(),
+            color: glm::DVec3::zeros(),
+        });
+        for t in 0..4 {
+            mesh.triangles.push(Triangle {
+                verts: glm::U32Vec3::new(0, 0, 0),
+            });
+            mesh.faces.push(Face {
+                id: 10 + t % 2,
+                triangles: t..t + 1,
+            });
+        }
+        for p in [0..2, 2..4] {
+            mesh.parts.push(Part {
+                shape: 1,
+                triangles: p,
+                transform: glm::DMat4::identity(),
+            });
+        }
+        let groups = group_instances(&mesh);
+        let geometry = unique_geometry(&mesh, &groups);
+        let first = 0..6;
+        let mut groups = vec![(vec![first], groups[0].parts.clone(), 0)];
+        let mut faces = geometry.faces.clone();
+        assert_eq!(geometry_face(&mesh, 0, &groups, &faces), Some((0, 0)));
+        assert_eq!(geometry_face(&mesh, 1, &groups, &faces), Some((0, 1)));
+        assert_eq!(geometry_face(&mesh, 3, &groups, &faces), None);
+
+        // Faces which were already replaced are found where they moved to
+        groups[0].0.push(6..12);
+        faces[1].triangles = 2..4;
+        assert_eq!(geometry_face(&mesh, 1, &groups, &faces), Some((0, 1)));
+
+        // Without parts, the whole mesh is uploaded as one group of
+        // distinct faces
+        mesh.parts.clear();
+        for (i, f) in mesh.faces.iter_mut().enumerate() {
+            f.id = 10 + i;
+        }
+        let all = 0..12;
+        let groups = vec![(vec![all], vec![], 0)];
+        assert_eq!(geometry_face(&mesh, 3, &groups, &mesh.faces), Some((0, 3)));
+    }
+
+    #[test]
+    fn test_culling_toggle() {
+        assert_eq!(Culling::Back.toggle(), Culling::TwoSided);
+        assert_eq!(Culling::TwoSided.toggle(), Culling::Back);
+    }
+
+    #[test]
+    fn test_uniform_layout() {
+        // Matches the `Locals` struct in model.wgsl, where every member
+        // starts on a 16-byte boundary
+        assert_eq!(std::mem::size_of::<ModelUniforms>(), 208);
+        assert_eq!(std::mem::offset_of!(ModelUniforms, mode), 128);
+        assert_eq!(std::mem::offset_of!(ModelUniforms, base), 144);
+        assert_eq!(std::mem::offset_of!(ModelUniforms, material), 160);
+        assert_eq!(std::mem::offset_of!(ModelUniforms, light), 176);
+        assert_eq!(std::mem::offset_of!(ModelUniforms, clip), 192);
+
+        let m = Material::default();
+        let u = ModelUniforms {
+            view_mat: Mat4::identity().into(),
+            model_mat: Mat4::identity().into(),
+            mode: [1, 0, 1, 0],
+            base: [1.0; 4],
+            material: m.weights(),
+            light: m.light(),
+            clip: [0.0, 0.0, 1.0, -2.0],
+        };
+        let words: &[u32] = bytemuck::cast_slice(bytemuck::bytes_of(&u));
+        assert_eq!(&words[32..36], &[1, 0, 1, 0]);
+        let floats: &[f32] = bytemuck::cast_slice(bytemuck::bytes_of(&u));
+        assert_eq!(floats[0], 1.0);
+        assert_eq!(floats[40..44], m.weights());
+        assert_eq!(floats[47], 1.0);
+        assert_eq!(floats[48..52], [0.0, 0.0, 1.0, -2.0]);
     }
 }
@@ -2,30 +2,135 @@ struct VertexOutput {
     [[builtin(position)]] position: vec4<f32>;
     [[location(0)]] normal: vec4<f32>;
     [[location(1)]] color: vec4<f32>;
+    [[location(2), interpolate(flat)]] face: u32;
+    [[location(3)]] tint: vec4<f32>;
+    [[location(4)]] instance_tint: vec4<f32>;
+    [[location(5)]] palette: vec4<f32>;
+    // Position in model space, which is tested against the section plane
+    [[location(6)]] world: vec4<f32>;
 };
 
 [[block]]
 struct Locals {
     view_mat: mat4x4<f32>;
     model_mat: mat4x4<f32>;
+    // The first element selects the color mode (0 for vertex colors, 1 for
+    // per-face colors), the second enables the curvature tint, and the third
+    // enables palette colors; the rest is padding.
+    mode: vec4<u32>;
+    // Multiplied into the final color, so the model stands out from the
+    // backdrop in both light and dark themes
+    base: vec4<f32>;
+    // Ambient, diffuse, and specular weights, then the shininess
+    material: vec4<f32>;
+    // Direction towards the fixed light (in model coordinates), then 1 to
+    // light the model from the camera instead
+    light: vec4<f32>;
+    // Section plane, as (normal, -normal · origin); fragments on the side
+    // which it faces are cut away.  A zero plane cuts away nothing.
+    clip: vec4<f32>;
 };
 [[group(0), binding(0)]]
 var r_locals: Locals;
 
+[[block]]
+struct FaceColors {
+    colors: [[stride(16)]] array<vec4<f32>>;
+};
+[[group(0), binding(1)]]
+var<storage> r_faces: [[access(read)]] FaceColors;
+
 [[stage(vertex)]]
 fn vs_main(
     [[location(0)]] position: vec4<f32>,
     [[location(1)]] normal: vec4<f32>,
     [[location(2)]] color: vec4<f32>,
+    [[location(3)]] face: u32,
+    [[location(4)]] tint: vec4<f32>,
+    // Per-instance transform (as columns), tint, and palette color
+    [[location(5)]] instance_0: vec4<f32>,
+    [[location(6)]] instance_1: vec4<f32>,
+    [[location(7)]] instance_2: vec4<f32>,
+    [[location(8)]] instance_3: vec4<f32>,
+    [[location(9)]] instance_tint: vec4<f32>,
+    [[location(10)]] palette: vec4<f32>,
 ) -> VertexOutput {
+    var instance_mat: mat4x4<f32> = mat4x4<f32>(instance_0, instance_1, instance_2, instance_3);
     var out: VertexOutput;
-    out.position = r_locals.view_mat * r_locals.model_mat * vec4<f32>(position.xyz, 1.0);
-    out.normal = normalize(r_locals.model_mat * vec4<f32>(normal.xyz, 0.0));
+    out.world = instance_mat * vec4<f32>(position.xyz, 1.0);
+    out.position = r_locals.view_mat * r_locals.model_mat * out.world;
+    out.normal = normalize(r_locals.model_mat * instance_mat * vec4<f32>(normal.xyz, 0.0));
+    out.instance_tint = instance_tint;
+    out.palette = palette;
     out.color = color;
+    out.face = face;
+    out.tint = tint;
     return out;
 }
 
+// Checks whether a point (in model space) is cut away by the section plane
+fn is_cut(world: vec4<f32>) -> bool {
+    return dot(r_locals.clip.xyz, world.xyz) + r_locals.clip.w > 0.0;
+}
+
+// Lights a surface with the given normal (turned with the model, as in
+// `vs_main`) and color
+fn shade(normal: vec3<f32>, color: vec3<f32>) -> vec4<f32> {
+    // The headlight points straight into the screen, while the fixed light
+    // turns with the model
+    var light: vec3<f32> = vec3<f32>(0.0, 0.0, 1.0);
+    if (r_locals.light.w == 0.0) {
+        light = normalize((r_locals.model_mat * vec4<f32>(r_locals.light.xyz, 0.0)).xyz);
+    }
+    let n: vec3<f32> = normalize(normal);
+    let diffuse: f32 = r_locals.material.x + r_locals.material.y * abs(dot(n, light));
+    let halfway: vec3<f32> = normalize(light + vec3<f32>(0.0, 0.0, 1.0));
+    let specular: f32 = r_locals.material.z * pow(abs(dot(n, halfway)), r_locals.material.w);
+    let shaded: vec3<f32> = diffuse * color * r_locals.base.xyz;
+    return vec4<f32>(shaded + vec3<f32>(specular, specular, specular), 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_main(
+    in: VertexOutput,
+    [[builtin(front_facing)]] front: bool,
+) -> [[location(0)]] vec4<f32> {
+    if (is_cut(in.world)) {
+        discard;
+    }
+    // Back faces are only drawn by the two-sided pipeline, and are shaded
+    // as if their normal pointed towards the viewer.
+    var normal: vec4<f32> = in.normal;
+    if (!front) {
+        normal = -normal;
+    }
+    var color: vec3<f32> = in.color.xyz;
+    // Parts without a STEP color have a palette color with a nonzero alpha
+    if (r_locals.mode.x == 0u && r_locals.mode.z == 1u && in.palette.w > 0.0) {
+        color = in.palette.xyz;
+    }
+    // Vertices which don't belong to a face are marked with 0xFFFFFFFF
+    if (r_locals.mode.x == 1u && in.face != 4294967295u) {
+        color = r_faces.colors[in.face].xyz;
+    }
+    if (r_locals.mode.y == 1u) {
+        color = in.tint.xyz;
+    }
+    return shade(normal.xyz, color * in.instance_tint.xyz);
+}
+
+// Used when drawing selected parts into the outline's mask texture
+[[stage(fragment)]]
+fn fs_mask(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    if (is_cut(in.world)) {
+        discard;
+    }
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
+
+// Used for the caps which fill in the section, which lie on the plane (so
+// they're never cut away) and are always drawn in their vertex color
 [[stage(fragment)]]
-fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    return vec4<f32>(abs(in.normal.z) * in.color.xyz, 1.0);
+fn fs_cap(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return shade(in.normal.xyz, in.color.xyz);
 }
@@ -0,0 +1,285 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
+
+/// Format of the selection mask, which only needs one channel
+pub const MASK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// Width of the outline, in logical pixels
+const OUTLINE_WIDTH: f64 = 2.0;
+
+/// Largest search radius in the outline shader, which keeps the cost of the
+/// post-process pass bounded on very high-DPI displays
+const MAX_RADIUS: i32 = 4;
+
+/// Outline color (orange), chosen to stand out against both themes
+const OUTLINE_COLOR: [f32; 4] = [1.0, 0.55, 0.1, 1.0];
+
+/// Uniforms for `outline.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct OutlineUniforms {
+    color: [f32; 4],
+    /// The first element is the search radius in pixels; the rest is padding
+    radius: [i32; 4],
+}
+
+impl OutlineUniforms {
+    fn new(scale_factor: f64) -> Self {
+        Self {
+            color: OUTLINE_COLOR,
+            radius: [outline_radius(scale_factor), 0, 0, 0],
+        }
+    }
+}
+
+/// Returns the outline shader's search radius for a given HiDPI scale.
+///
+/// Pixels within this radius of the mask's edge are drawn on both sides of
+/// it, so the outline is twice the radius wide.
+fn outline_radius(scale_factor: f64) -> i32 {
+    let r = (OUTLINE_WIDTH * scale_factor / 2.0).round();
+    if r.is_finite() {
+        (r as i32).clamp(1, MAX_RADIUS)
+    } else {
+        1
+    }
+}
+
+/// Draws an outline around selected parts, in two passes:  the parts are
+/// drawn into an offscreen mask (see [`crate::model::Model::draw_mask`]),
+/// then a full-screen pass draws the outline wherever the mask changes.
+pub struct Outline {
+    uniform_buf: wgpu::Buffer,
+    /// Most recent uniforms written to `uniform_buf`
+    uniforms: OutlineUniforms,
+    bind_group_layout: wgpu::BindGroupLayout,
+    render_pipeline: wgpu::RenderPipeline,
+
+    /// Mask texture and a bind group which reads it, which are rebuilt
+    /// when the window is resized
+    mask: Option<(wgpu::Texture, wgpu::TextureView, wgpu::BindGroup)>,
+}
+
+impl Outline {
+    pub fn new(device: &wgpu::Device, swapchain_format: wgpu::TextureFormat) -> Self {
+        let uniforms = OutlineUniforms::new(1.0);
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Outline uniform buffer"),
+            contents: bytemuck::bytes_of(&uniforms),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<OutlineUniforms>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        // Load the shaders from disk, either at runtime or compile-time
+        #[cfg(feature = "bundle-shaders")]
+        let outline_src = Cow::Borrowed(include_str!("outline.wgsl"));
+
+        #[cfg(not(feature = "bundle-shaders"))]
+        let outline_src = Cow::Owned(
+            String::from_utf8(
+                std::fs::read("gui/src/outline.wgsl").expect("Could not read shader"),
+            )
+            .expect("Shader is invalid UTF-8"),
+        );
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(outline_src),
+            flags: wgpu::ShaderFlags::all(),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: swapchain_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        Outline {
+            uniform_buf,
+            uniforms,
+            bind_group_layout,
+            render_pipeline,
+            mask: None,
+        }
+    }
+
+    /// Rebuilds the mask texture to match the window size
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        let tex = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Selection mask"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: MASK_FORMAT,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        });
+        let view = tex.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ],
+            label: None,
+        });
+        self.mask = Some((tex, view, bind_group));
+    }
+
+    /// Returns the mask texture, which is cleared and drawn into by the
+    /// selection mask pass
+    pub fn mask_view(&self) -> Option<&wgpu::TextureView> {
+        self.mask.as_ref().map(|(_, view, _)| view)
+    }
+
+    /// Updates the outline width for a new HiDPI scale, writing to the
+    /// uniform buffer only if it has changed
+    pub fn set_scale_factor(&mut self, queue: &wgpu::Queue, scale_factor: f64) {
+        if let Some(data) = update_uniforms(&mut self.uniforms, scale_factor) {
+            queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&data));
+        }
+    }
+
+    /// Composites the outline over the frame
+    pub fn draw(&self, view: &wgpu::TextureView, encoder: &mut wgpu::CommandEncoder) {
+        let bind_group = match &self.mask {
+            Some((_, _, b)) => b,
+            None => return,
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+/// Stores new uniforms in `current`, returning them if they've changed
+/// (and therefore need to be uploaded)
+fn update_uniforms(current: &mut OutlineUniforms, scale_factor: f64) -> Option<OutlineUniforms> {
+    let next = OutlineUniforms::new(scale_factor);
+    if *current == next {
+        None
+    } else {
+        *current = next;
+        Some(next)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline_radius() {
+        // Two logical pixels wide, i.e. one pixel on each side of the edge
+        assert_eq!(outline_radius(1.0), 1);
+        assert_eq!(outline_radius(2.0), 2);
+        assert_eq!(outline_radius(1.5), 2);
+        assert_eq!(outline_radius(3.0), 3);
+
+        // Always at least one pixel, and bounded on very dense displays
+        assert_eq!(outline_radius(0.25), 1);
+        assert_eq!(outline_radius(16.0), MAX_RADIUS);
+        assert_eq!(outline_radius(f64::NAN), 1);
+    }
+
+    #[test]
+    fn test_uniform_layout() {
+        // Matches the `Outline` struct in outline.wgsl
+        assert_eq!(std::mem::size_of::<OutlineUniforms>(), 32);
+        let u = OutlineUniforms::new(2.0);
+        let words: &[i32] = bytemuck::cast_slice(bytemuck::bytes_of(&u));
+        assert_eq!(words[4], 2);
+        assert_eq!(&words[5..], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn test_update_uniforms() {
+        let mut current = OutlineUniforms::new(1.0);
+        assert_eq!(update_uniforms(&mut current, 1.0), None);
+
+        // Moving to a HiDPI display widens the outline once
+        let next = update_uniforms(&mut current, 2.0).unwrap();
+        assert_eq!(next.radius[0], 2);
+        assert_eq!(current, next);
+        assert_eq!(update_uniforms(&mut current, 2.0), None);
+
+        // Scales which round to the same radius don't need an upload
+        assert_eq!(update_uniforms(&mut current, 2.2), None);
+    }
+}
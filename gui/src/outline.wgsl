@@ -0,0 +1,65 @@
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Outline {
+    color: vec4<f32>;
+    // The first element is the outline's radius in pixels; the rest is
+    // padding.
+    radius: vec4<i32>;
+};
+[[group(0), binding(0)]]
+var r_outline: Outline;
+
+// 1 for selected parts, 0 elsewhere
+[[group(0), binding(1)]]
+var t_mask: texture_2d<f32>;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] in_vertex_index: u32) -> VertexOutput {
+    // A single triangle which covers the whole window
+    var out: VertexOutput;
+    var x: f32 = f32((in_vertex_index & 1u) * 4u) - 1.0;
+    var y: f32 = f32((in_vertex_index >> 1u) * 4u) - 1.0;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    var size: vec2<i32> = textureDimensions(t_mask);
+    var p: vec2<i32> = vec2<i32>(in.position.xy);
+    var r: i32 = r_outline.radius.x;
+
+    // Find the range of mask values in the neighborhood of this pixel,
+    // which spans both 0 and 1 near the edge of the selection
+    var lo: f32 = 1.0;
+    var hi: f32 = 0.0;
+    var dy: i32 = -r;
+    loop {
+        if (dy > r) {
+            break;
+        }
+        var dx: i32 = -r;
+        loop {
+            if (dx > r) {
+                break;
+            }
+            var q: vec2<i32> = clamp(p + vec2<i32>(dx, dy), vec2<i32>(0, 0), size - vec2<i32>(1, 1));
+            var m: f32 = textureLoad(t_mask, q, 0).x;
+            lo = min(lo, m);
+            hi = max(hi, m);
+            continuing {
+                dx = dx + 1;
+            }
+        }
+        continuing {
+            dy = dy + 1;
+        }
+    }
+    if (hi - lo < 0.5) {
+        discard;
+    }
+    return r_outline.color;
+}
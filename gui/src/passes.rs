@@ -0,0 +1,153 @@
+/// A single step of rendering a frame
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Pass {
+    /// Clears the frame and depth buffer, drawing the background gradient
+    Backdrop,
+    Model,
+    /// Boundary and feature edges of the model
+    Edges,
+    /// Curves from wireframe and surface models
+    Wires,
+    /// Vertex normal overlay
+    Normals,
+    Grid,
+    /// Axis gizmo, drawn in the corner of the window
+    Gizmo,
+    /// Renders the selected parts into an offscreen mask, which is tested
+    /// against the model's depth buffer
+    SelectionMask,
+    /// Post-process pass which outlines the edges of the selection mask
+    Outline,
+    /// On-screen text, which is always drawn last
+    Text,
+}
+
+/// What's available to draw in a frame
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameContents {
+    pub model: bool,
+    pub edges: bool,
+    pub wires: bool,
+    pub normals: bool,
+    pub grid: bool,
+    pub gizmo: bool,
+    /// Whether any visible part is selected
+    pub selection: bool,
+}
+
+/// Returns the passes needed to draw a frame, in order.
+///
+/// The selection mask reuses the depth buffer, so it must come after every
+/// pass which writes depth.  The outline is composited over the rendered
+/// scene, and text is drawn over everything.
+pub fn plan(f: FrameContents) -> Vec<Pass> {
+    let mut out = vec![Pass::Backdrop];
+    if f.model {
+        out.push(Pass::Model);
+    }
+    if f.edges {
+        out.push(Pass::Edges);
+    }
+    if f.wires {
+        out.push(Pass::Wires);
+    }
+    if f.normals {
+        out.push(Pass::Normals);
+    }
+    if f.grid {
+        out.push(Pass::Grid);
+    }
+    if f.gizmo {
+        out.push(Pass::Gizmo);
+    }
+    if f.model && f.selection {
+        out.push(Pass::SelectionMask);
+        out.push(Pass::Outline);
+    }
+    out.push(Pass::Text);
+    out
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(
+            plan(FrameContents::default()),
+            vec![Pass::Backdrop, Pass::Text]
+        );
+
+        // There's nothing to outline without a model
+        let f = FrameContents {
+            selection: true,
+            gizmo: true,
+            ..FrameContents::default()
+        };
+        assert_eq!(plan(f), vec![Pass::Backdrop, Pass::Gizmo, Pass::Text]);
+    }
+
+    #[test]
+    fn test_order() {
+        let all = FrameContents {
+            model: true,
+            edges: true,
+            wires: true,
+            normals: true,
+            grid: true,
+            gizmo: true,
+            selection: true,
+        };
+        assert_eq!(
+            plan(all),
+            vec![
+                Pass::Backdrop,
+                Pass::Model,
+                Pass::Edges,
+                Pass::Wires,
+                Pass::Normals,
+                Pass::Grid,
+                Pass::Gizmo,
+                Pass::SelectionMask,
+                Pass::Outline,
+                Pass::Text,
+            ]
+        );
+
+        let no_selection = FrameContents {
+            selection: false,
+            ..all
+        };
+        assert_eq!(
+            plan(no_selection),
+            vec![
+                Pass::Backdrop,
+                Pass::Model,
+                Pass::Edges,
+                Pass::Wires,
+                Pass::Normals,
+                Pass::Grid,
+                Pass::Gizmo,
+                Pass::Text,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mask_before_outline() {
+        let f = FrameContents {
+            model: true,
+            selection: true,
+            ..FrameContents::default()
+        };
+        let p = plan(f);
+        let pos = |pass| p.iter().position(|q| *q == pass).unwrap();
+        assert!(pos(Pass::Model) < pos(Pass::SelectionMask));
+        assert!(pos(Pass::SelectionMask) < pos(Pass::Outline));
+        assert!(pos(Pass::Outline) < pos(Pass::Text));
+        assert_eq!(p.last(), Some(&Pass::Text));
+    }
+}
@@ -0,0 +1,154 @@
+use nalgebra_glm::DVec3;
+use triangulate::mesh::Mesh;
+
+use crate::visibility::Visibility;
+
+/// Intersects a ray with a triangle (Möller–Trumbore), returning the
+/// distance along the ray if there's a hit.
+pub fn ray_triangle(origin: DVec3, dir: DVec3, tri: [DVec3; 3]) -> Option<f64> {
+    let e1 = tri[1] - tri[0];
+    let e2 = tri[2] - tri[0];
+    let p = dir.cross(&e2);
+    let det = e1.dot(&p);
+    if det.abs() < f64::EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - tri[0];
+    let u = s.dot(&p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&e1);
+    let v = dir.dot(&q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(&q) * inv_det;
+    if t >= 0.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// Finds the visible part which is hit first by the given ray, by testing
+/// every triangle.
+pub fn pick_part(mesh: &Mesh, vis: &Visibility, origin: DVec3, dir: DVec3) -> Option<usize> {
+    pick(mesh, vis, origin, dir).map(|b| b.1)
+}
+
+/// Finds the visible part which is hit first by the given ray, along with
+/// the face that was hit (as an index into [`Mesh::faces`]).  Meshes
+/// without face information only report the part.
+pub fn pick_face(
+    mesh: &Mesh,
+    vis: &Visibility,
+    origin: DVec3,
+    dir: DVec3,
+) -> Option<(usize, Option<usize>)> {
+    pick(mesh, vis, origin, dir).map(|b| (b.1, mesh.triangle_face(b.2)))
+}
+
+/// Finds the first point on a visible part which is hit by the given ray
+pub fn pick_point(mesh: &Mesh, vis: &Visibility, origin: DVec3, dir: DVec3) -> Option<DVec3> {
+    pick(mesh, vis, origin, dir).map(|b| origin + dir * b.0)
+}
+
+/// Returns the distance along the ray, part index, and triangle index of
+/// the nearest hit
+fn pick(mesh: &Mesh, vis: &Visibility, origin: DVec3, dir: DVec3) -> Option<(f64, usize, usize)> {
+    let mut best: Option<(f64, usize, usize)> = None;
+    for (i, part) in mesh.parts.iter().enumerate() {
+        if !vis.is_visible(i) {
+            continue;
+        }
+        for t in part.triangles.clone() {
+            let verts = mesh.triangles[t].verts;
+            let tri = [0, 1, 2].map(|j| mesh.verts[verts[j] as usize].pos);
+            if let Some(d) = ray_triangle(origin, dir, tri) {
+                if best.map(|b| d < b.0).unwrap_or(true) {
+                    best = Some((d, i, t));
+                }
+            }
+        }
+    }
+    best
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{DMat4, U32Vec3};
+    use triangulate::mesh::{Face, Part, Triangle, Vertex};
+
+    #[test]
+    fn test_ray_triangle() {
+        let tri = [DVec3::zeros(), DVec3::x(), DVec3::y()];
+        let o = DVec3::new(0.25, 0.25, 5.0);
+        assert_eq!(ray_triangle(o, -DVec3::z(), tri), Some(5.0));
+        // Pointing away from the triangle
+        assert_eq!(ray_triangle(o, DVec3::z(), tri), None);
+        // Outside of the triangle
+        let o = DVec3::new(0.75, 0.75, 5.0);
+        assert_eq!(ray_triangle(o, -DVec3::z(), tri), None);
+        // Parallel to the triangle
+        assert_eq!(ray_triangle(o, DVec3::x(), tri), None);
+    }
+
+    #[test]
+    fn test_pick_nearest_visible() {
+        // Two stacked triangles at z = 0 and z = 1
+        let mut mesh = Mesh::default();
+        for z in [0.0, 1.0] {
+            for d in [DVec3::zeros(), DVec3::x(), DVec3::y()] {
+                mesh.verts.push(Vertex {
+                    pos: d + DVec3::new(0.0, 0.0, z),
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+        }
+        for i in 0..2 {
+            let j = 3 * i as u32;
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(j, j + 1, j + 2),
+            });
+            mesh.parts.push(Part {
+                shape: i,
+                triangles: i..(i + 1),
+                transform: DMat4::identity(),
+            });
+        }
+        let mut vis = Visibility::new(2);
+        let o = DVec3::new(0.1, 0.1, 10.0);
+        let d = -DVec3::z();
+        assert_eq!(pick_part(&mesh, &vis, o, d), Some(1));
+        assert_eq!(
+            pick_point(&mesh, &vis, o, d),
+            Some(DVec3::new(0.1, 0.1, 1.0))
+        );
+        // Faces are only reported if the mesh has them
+        assert_eq!(pick_face(&mesh, &vis, o, d), Some((1, None)));
+        mesh.faces = (0..2)
+            .map(|i| Face {
+                id: 10 + i,
+                triangles: i..(i + 1),
+            })
+            .collect();
+        assert_eq!(pick_face(&mesh, &vis, o, d), Some((1, Some(1))));
+
+        vis.hide(1);
+        assert_eq!(pick_part(&mesh, &vis, o, d), Some(0));
+        assert_eq!(pick_face(&mesh, &vis, o, d), Some((0, Some(0))));
+        assert_eq!(
+            pick_point(&mesh, &vis, o, d),
+            Some(DVec3::new(0.1, 0.1, 0.0))
+        );
+        vis.hide(0);
+        assert_eq!(pick_part(&mesh, &vis, o, d), None);
+        assert_eq!(pick_point(&mesh, &vis, o, d), None);
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant};
+
+use wgpu::PresentMode;
+
+/// Parses a present mode from the command line
+pub fn parse_present_mode(s: &str) -> Result<PresentMode, String> {
+    match s {
+        "fifo" => Ok(PresentMode::Fifo),
+        "mailbox" => Ok(PresentMode::Mailbox),
+        "immediate" => Ok(PresentMode::Immediate),
+        _ => Err(format!(
+            "invalid present mode {:?} (expected fifo, mailbox, or immediate)",
+            s
+        )),
+    }
+}
+
+/// Returns the present modes which the given backend can use.
+///
+/// `wgpu` 0.9 doesn't expose the surface's capabilities, and silently
+/// falls back to FIFO when asked for an unsupported mode, so this is a
+/// conservative table based on what each backend's driver typically
+/// offers.  FIFO is always supported.
+pub fn supported_present_modes(backend: wgpu::Backend) -> &'static [PresentMode] {
+    use PresentMode::*;
+    match backend {
+        wgpu::Backend::Vulkan | wgpu::Backend::Dx12 => &[Fifo, Mailbox, Immediate],
+        wgpu::Backend::Metal => &[Fifo, Immediate],
+        _ => &[Fifo],
+    }
+}
+
+/// Picks the requested mode if it's supported, otherwise falling back to
+/// FIFO (with a warning)
+pub fn select_present_mode(requested: PresentMode, supported: &[PresentMode]) -> PresentMode {
+    if supported.contains(&requested) {
+        requested
+    } else {
+        log::warn!(
+            "Present mode {:?} is not supported (available: {:?}); using Fifo",
+            requested,
+            supported
+        );
+        PresentMode::Fifo
+    }
+}
+
+/// Returns the next supported mode after `current`, wrapping around
+pub fn next_present_mode(current: PresentMode, supported: &[PresentMode]) -> PresentMode {
+    match supported.iter().position(|m| *m == current) {
+        Some(i) => supported[(i + 1) % supported.len()],
+        None => supported.first().copied().unwrap_or(PresentMode::Fifo),
+    }
+}
+
+/// Counts frames to measure the frame rate, which is reported about once
+/// per second
+#[derive(Default)]
+pub struct FrameRate {
+    start: Option<Instant>,
+    frames: u32,
+}
+
+impl FrameRate {
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Records a frame, returning the average frame rate if at least one
+    /// interval has passed since the last report.  Gaps longer than an
+    /// interval (i.e. when the viewer is idle) restart the measurement.
+    pub fn tick(&mut self, now: Instant) -> Option<f64> {
+        let start = match self.start {
+            Some(s) if now.duration_since(s) < 2 * Self::INTERVAL => s,
+            _ => {
+                self.start = Some(now);
+                self.frames = 0;
+                return None;
+            }
+        };
+        self.frames += 1;
+        let dt = now.duration_since(start);
+        if dt >= Self::INTERVAL {
+            let fps = self.frames as f64 / dt.as_secs_f64();
+            self.start = Some(now);
+            self.frames = 0;
+            Some(fps)
+        } else {
+            None
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PresentMode::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_present_mode("fifo"), Ok(Fifo));
+        assert_eq!(parse_present_mode("mailbox"), Ok(Mailbox));
+        assert_eq!(parse_present_mode("immediate"), Ok(Immediate));
+        assert!(parse_present_mode("vsync").is_err());
+        assert!(parse_present_mode("FIFO").is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(select_present_mode(Mailbox, &[Fifo, Mailbox]), Mailbox);
+        assert_eq!(select_present_mode(Immediate, &[Fifo, Mailbox]), Fifo);
+        assert_eq!(select_present_mode(Fifo, &[Fifo]), Fifo);
+
+        // Every backend supports FIFO, so it's always a valid fallback
+        for b in [
+            wgpu::Backend::Empty,
+            wgpu::Backend::Vulkan,
+            wgpu::Backend::Metal,
+            wgpu::Backend::Dx12,
+            wgpu::Backend::Dx11,
+            wgpu::Backend::Gl,
+            wgpu::Backend::BrowserWebGpu,
+        ] {
+            let s = supported_present_modes(b);
+            assert!(s.contains(&Fifo));
+            assert_eq!(select_present_mode(Immediate, s), *s.last().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_next() {
+        let all = [Fifo, Mailbox, Immediate];
+        assert_eq!(next_present_mode(Fifo, &all), Mailbox);
+        assert_eq!(next_present_mode(Immediate, &all), Fifo);
+        assert_eq!(next_present_mode(Fifo, &[Fifo]), Fifo);
+        assert_eq!(next_present_mode(Mailbox, &[Fifo, Immediate]), Fifo);
+    }
+
+    #[test]
+    fn test_frame_rate() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut f = FrameRate::default();
+        assert_eq!(f.tick(ms(0)), None);
+        for i in 1..100 {
+            assert_eq!(f.tick(ms(i * 10)), None);
+        }
+        let fps = f.tick(ms(1000)).unwrap();
+        assert!((fps - 100.0).abs() < 1e-6, "{}", fps);
+
+        // After a long idle period, the measurement starts over
+        assert_eq!(f.tick(ms(5000)), None);
+        assert_eq!(f.tick(ms(5500)), None);
+        assert_eq!(f.tick(ms(6000)), Some(2.0));
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use triangulate::stats::Stats;
+
+/// A single timed stage of startup
+#[derive(Clone, Debug, Serialize)]
+pub struct Stage {
+    pub name: String,
+    /// Time at which the stage started, in seconds since launch
+    pub start: f64,
+    /// Time spent in the stage, in seconds
+    pub duration: f64,
+}
+
+/// Startup timing report, written by `--profile`
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Report {
+    pub stages: Vec<Stage>,
+    /// Number of faces of each surface type, from the triangulation
+    pub surface_types: BTreeMap<String, usize>,
+}
+
+impl Report {
+    /// Formats the report as a human-readable table
+    pub fn summary(&self) -> String {
+        let mut out = "Startup profile:\n".to_owned();
+        for s in &self.stages {
+            out += &format!(
+                "  {:<16} {:>10.3} ms  (at {:.3} ms)\n",
+                s.name,
+                s.duration * 1000.0,
+                s.start * 1000.0
+            );
+        }
+        if !self.surface_types.is_empty() {
+            let types: Vec<_> = self
+                .surface_types
+                .iter()
+                .map(|(t, n)| format!("{} {}", t, n))
+                .collect();
+            out += &format!("  surface types: {}\n", types.join(", "));
+        }
+        out
+    }
+}
+
+/// Report under construction, and the file to which it's written
+struct Shared {
+    report: Report,
+    path: Option<PathBuf>,
+}
+
+/// Records stage timings, shared between the loader thread and the `App`.
+///
+/// When profiling is disabled, recording is a no-op (but the time since
+/// launch is still available for logging).
+#[derive(Clone)]
+pub struct Profile {
+    origin: Instant,
+    /// Only present if profiling is enabled
+    inner: Option<Arc<Mutex<Shared>>>,
+}
+
+impl Profile {
+    /// Builds a profile which records nothing
+    pub fn disabled(origin: Instant) -> Self {
+        Self {
+            origin,
+            inner: None,
+        }
+    }
+
+    /// Builds a profile which records stages, writing them to `path` (if
+    /// present) when [`Profile::finish`] is called
+    pub fn enabled(origin: Instant, path: Option<PathBuf>) -> Self {
+        Self {
+            origin,
+            inner: Some(Arc::new(Mutex::new(Shared {
+                report: Report::default(),
+                path,
+            }))),
+        }
+    }
+
+    /// Returns the time since launch
+    pub fn elapsed(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    /// Records a stage which started at `start` and ends now
+    pub fn record(&self, name: &str, start: Instant) {
+        self.record_span(name, start, start.elapsed());
+    }
+
+    /// Records a stage which started at `start` and ran for `duration`
+    pub fn record_span(&self, name: &str, start: Instant, duration: Duration) {
+        if let Some(inner) = &self.inner {
+            let stage = Stage {
+                name: name.to_owned(),
+                start: start.saturating_duration_since(self.origin).as_secs_f64(),
+                duration: duration.as_secs_f64(),
+            };
+            inner.lock().unwrap().report.stages.push(stage);
+        }
+    }
+
+    /// Runs a function, recording it as a stage
+    #[cfg(test)]
+    pub fn time<T, F: FnOnce() -> T>(&self, name: &str, f: F) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.record(name, start);
+        out
+    }
+
+    /// Records the per-surface-type breakdown from triangulation
+    pub fn record_stats(&self, stats: &Stats) {
+        if let Some(inner) = &self.inner {
+            let mut counts = BTreeMap::new();
+            for t in stats.surface_types.values() {
+                *counts.entry(format!("{:?}", t)).or_default() += 1;
+            }
+            inner.lock().unwrap().report.surface_types = counts;
+        }
+    }
+
+    /// Returns a copy of the report so far
+    #[cfg(test)]
+    pub fn report(&self) -> Option<Report> {
+        self.inner
+            .as_ref()
+            .map(|i| i.lock().unwrap().report.clone())
+    }
+
+    /// Prints a summary to stderr and writes the report to its file.  This
+    /// only happens once; later calls do nothing.
+    pub fn finish(&self) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let shared = &mut *inner.lock().unwrap();
+        let Some(path) = shared.path.take() else {
+            return;
+        };
+        eprint!("{}", shared.report.summary());
+        let json =
+            serde_json::to_string_pretty(&shared.report).expect("Could not serialize profile");
+        if let Err(e) = std::fs::write(&path, json) {
+            eprintln!("Error: could not write {}: {}", path.display(), e);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled() {
+        let p = Profile::disabled(Instant::now());
+        assert_eq!(p.time("work", || 3), 3);
+        assert!(p.report().is_none());
+        p.finish();
+    }
+
+    #[test]
+    fn test_record() {
+        let p = Profile::enabled(Instant::now(), None);
+        p.time("sleep", || std::thread::sleep(Duration::from_millis(2)));
+        let q = p.clone();
+        q.time("other", || ());
+
+        let r = p.report().unwrap();
+        assert_eq!(r.stages.len(), 2);
+        let (s, t) = (&r.stages[0], &r.stages[1]);
+        assert_eq!((s.name.as_str(), t.name.as_str()), ("sleep", "other"));
+        assert!(s.duration >= 0.002);
+        assert!(t.start > s.start);
+        assert!(r.summary().contains("sleep"));
+    }
+
+    #[test]
+    fn test_write() {
+        let path =
+            std::env::temp_dir().join(format!("foxtrot-gui-{}-profile.json", std::process::id()));
+        let p = Profile::enabled(Instant::now(), Some(path.clone()));
+        p.time("stage", || ());
+        p.finish();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["stages"][0]["name"], "stage");
+        std::fs::remove_file(&path).unwrap();
+
+        // The report is only written once
+        p.finish();
+        assert!(!path.exists());
+    }
+}
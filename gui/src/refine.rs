@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, HashMap};
+
+use triangulate::mesh::Mesh;
+
+use crate::tile::Slot;
+
+/// Tolerance used for a face which hasn't been triangulated again yet, as a
+/// fraction of its bounding radius.  This is close to the sampling used when
+/// loading without a tolerance (64 points on a full circle).
+const DEFAULT_TOLERANCE: f64 = 1e-3;
+
+/// A batch of faces from one input file which are triangulated again with
+/// the same tolerance
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    /// Index into the app's slots
+    pub slot: usize,
+    /// STEP entity indices of the faces, within the slot's file
+    pub faces: Vec<usize>,
+    pub tolerance: f64,
+}
+
+/// Returns the faces (as indices into [`Mesh::faces`]) which have their
+/// triangles in one of the given parts
+pub fn part_faces(mesh: &Mesh, parts: &[bool]) -> Vec<usize> {
+    (0..mesh.faces.len())
+        .filter(|i| {
+            let start = mesh.faces[*i].triangles.start;
+            mesh.parts
+                .iter()
+                .enumerate()
+                .any(|(j, p)| parts.get(j) == Some(&true) && p.triangles.contains(&start))
+        })
+        .collect()
+}
+
+/// Returns half the diagonal of a face's bounding box
+fn face_radius(mesh: &Mesh, face: usize) -> f64 {
+    let mut pts = mesh.triangles[mesh.faces[face].triangles.clone()]
+        .iter()
+        .flat_map(|t| t.verts.iter())
+        .map(|v| mesh.verts[*v as usize].pos);
+    let first = match pts.next() {
+        Some(p) => p,
+        None => return 0.0,
+    };
+    let (lo, hi) = pts.fold((first, first), |(lo, hi), p| (lo.inf(&p), hi.sup(&p)));
+    (hi - lo).norm() / 2.0
+}
+
+/// Scales the tolerance of each face by `factor` (starting from a default
+/// based on the face's size), then groups the faces into requests by input
+/// file and tolerance.  Copies of a face (in instanced parts) share an
+/// entity index, so they're only listed once.
+pub fn requests(
+    mesh: &Mesh,
+    slots: &[Slot],
+    faces: &[usize],
+    tolerances: &mut HashMap<usize, f64>,
+    factor: f64,
+) -> Vec<Request> {
+    let mut groups: BTreeMap<(usize, u64), Vec<usize>> = BTreeMap::new();
+    let mut seen = vec![];
+    for f in faces {
+        let id = mesh.faces[*f].id;
+        if seen.contains(&id) {
+            continue;
+        }
+        seen.push(id);
+        let start = mesh.faces[*f].triangles.start;
+        let slot = mesh
+            .parts
+            .iter()
+            .position(|p| p.triangles.contains(&start))
+            .and_then(|p| slots.iter().position(|s| s.parts.contains(&p)));
+        let slot = match slot {
+            Some(s) => s,
+            None => continue,
+        };
+        let t = tolerances
+            .get(&id)
+            .copied()
+            .unwrap_or_else(|| face_radius(mesh, *f) * DEFAULT_TOLERANCE)
+            * factor;
+        if t <= 0.0 {
+            continue;
+        }
+        tolerances.insert(id, t);
+        groups
+            .entry((slot, t.to_bits()))
+            .or_default()
+            .push(id - slots[slot].first_id);
+    }
+    groups
+        .into_iter()
+        .map(|((slot, t), faces)| Request {
+            slot,
+            faces,
+            tolerance: f64::from_bits(t),
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use step::{fixture, step_file::StepFile};
+    use triangulate::{load::ParseReport, stats::Stats, triangulate::triangulate};
+
+    fn load(text: &str) -> (Mesh, Stats) {
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        triangulate(&step)
+    }
+
+    #[test]
+    fn test_requests() {
+        let text = fixture::cube(2.0).to_step();
+        let (a, sa) = load(&text);
+        let (b, sb) = load(&text);
+        let (mesh, _, slots) = crate::tile::tile(vec![
+            ("a.step".to_owned(), a, sa, ParseReport::default()),
+            ("b.step".to_owned(), b, sb, ParseReport::default()),
+        ]);
+        assert_eq!(mesh.parts.len(), 2);
+
+        // Faces are found through their parts
+        let all = part_faces(&mesh, &[true, true]);
+        assert_eq!(all.len(), 12);
+        let second = part_faces(&mesh, &[false, true]);
+        assert_eq!(second.len(), 6);
+        assert!(part_faces(&mesh, &[false, false]).is_empty());
+
+        // Each file gets its own request, with ids local to that file
+        let mut tolerances = HashMap::new();
+        let reqs = requests(&mesh, &slots, &all, &mut tolerances, 0.5);
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[0].slot, 0);
+        assert_eq!(reqs[1].slot, 1);
+        assert_eq!(reqs[0].faces, reqs[1].faces);
+        // Every face of a cube with side 2 has a bounding radius of sqrt(2)
+        let expected = 2f64.sqrt() * DEFAULT_TOLERANCE * 0.5;
+        assert!((reqs[0].tolerance - expected).abs() < 1e-12);
+        assert_eq!(tolerances.len(), 12);
+
+        // Tolerances are remembered, so changes build on each other
+        let reqs = requests(&mesh, &slots, &second, &mut tolerances, 4.0);
+        assert_eq!(reqs.len(), 1);
+        assert_eq!(reqs[0].slot, 1);
+        assert_eq!(reqs[0].faces.len(), 6);
+        assert!((reqs[0].tolerance - expected * 4.0).abs() < 1e-12);
+
+        // Listing a face twice only requests it once
+        let reqs = requests(&mesh, &slots, &[0, 0], &mut tolerances, 1.0);
+        assert_eq!(reqs[0].faces.len(), 1);
+    }
+}
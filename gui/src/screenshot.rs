@@ -0,0 +1,291 @@
+use std::{
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
+
+use winit::dpi::PhysicalSize;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScreenshotError {
+    #[error("Could not read the frame back from the GPU")]
+    Map(#[from] wgpu::BufferAsyncError),
+
+    #[error("Cannot save frames in {0:?} format")]
+    Format(wgpu::TextureFormat),
+
+    #[error("Could not write {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Offscreen color target which a frame is drawn into (in place of the
+/// swapchain, which can't be read), then copied into a buffer on the CPU
+pub struct Capture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    size: PhysicalSize<u32>,
+    format: wgpu::TextureFormat,
+    /// Bytes per row in `buffer`, which are padded to wgpu's alignment
+    padded_row: u32,
+}
+
+impl Capture {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("screenshot tex"),
+            size: Self::extent(size),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let padded_row = padded_row(size.width);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("screenshot buffer"),
+            size: padded_row as u64 * size.height as u64,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Capture {
+            texture,
+            view,
+            buffer,
+            size,
+            format,
+            padded_row,
+        }
+    }
+
+    fn extent(size: PhysicalSize<u32>) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    /// Returns the view to draw the frame into
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Copies the frame into the readback buffer, after it has been drawn
+    pub fn copy(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.padded_row),
+                    rows_per_image: None,
+                },
+            },
+            Self::extent(self.size),
+        );
+    }
+
+    /// Waits for the copy to finish, then writes the frame to a PNG file
+    pub fn save(&self, device: &wgpu::Device, path: &Path) -> Result<(), ScreenshotError> {
+        let bgra = match self.format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            f => return Err(ScreenshotError::Format(f)),
+        };
+        let slice = self.buffer.slice(..);
+        let mapped = slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapped)?;
+
+        let rgba = unpad(&slice.get_mapped_range(), self.size, self.padded_row, bgra);
+        self.buffer.unmap();
+        let png = encode_png(self.size, &rgba);
+        std::fs::write(path, png).map_err(|source| ScreenshotError::Io {
+            path: path.to_owned(),
+            source,
+        })
+    }
+}
+
+/// Returns the number of bytes in each row of a readback buffer, which
+/// must be a multiple of [`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`]
+fn padded_row(width: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (width * 4).div_ceil(align) * align
+}
+
+/// Strips the padding from each row of a readback buffer, converting BGRA
+/// pixels to RGBA if needed
+fn unpad(data: &[u8], size: PhysicalSize<u32>, padded_row: u32, bgra: bool) -> Vec<u8> {
+    let row = size.width as usize * 4;
+    let mut out = Vec::with_capacity(row * size.height as usize);
+    for r in data.chunks(padded_row as usize).take(size.height as usize) {
+        out.extend_from_slice(&r[..row]);
+    }
+    if bgra {
+        for p in out.chunks_exact_mut(4) {
+            p.swap(0, 2);
+        }
+    }
+    out
+}
+
+/// Encodes an 8-bit RGBA image as a PNG.
+///
+/// The image data is stored without compression, which keeps the encoder
+/// tiny and its output byte-for-byte reproducible.
+fn encode_png(size: PhysicalSize<u32>, rgba: &[u8]) -> Vec<u8> {
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let mut header = vec![];
+    header.extend_from_slice(&size.width.to_be_bytes());
+    header.extend_from_slice(&size.height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, standard filters, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &header);
+
+    // Each row starts with a filter type byte, which is zero (no filter)
+    let mut raw = vec![];
+    for row in rgba.chunks(size.width as usize * 4) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Appends a PNG chunk, with its length and checksum
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps data in a zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // CMF and FLG bytes for a 32K window and no preset dictionary
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(b) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = b.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(b);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & 0u32.wrapping_sub(crc & 1));
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for d in data {
+        a = (a + *d as u32) % MOD;
+        b = (b + a) % MOD;
+    }
+    (b << 16) | a
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+        assert_eq!(adler32(b""), 1);
+    }
+
+    #[test]
+    fn test_padded_row() {
+        assert_eq!(padded_row(1), 256);
+        assert_eq!(padded_row(64), 256);
+        assert_eq!(padded_row(65), 512);
+    }
+
+    #[test]
+    fn test_unpad() {
+        let size = PhysicalSize::new(2, 2);
+        let mut data = vec![0; 256 * 2];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        data[256..264].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+        assert_eq!(
+            unpad(&data, size, 256, false),
+            (1..=16).collect::<Vec<u8>>()
+        );
+        assert_eq!(
+            unpad(&data, size, 256, true),
+            vec![3, 2, 1, 4, 7, 6, 5, 8, 11, 10, 9, 12, 15, 14, 13, 16]
+        );
+    }
+
+    #[test]
+    fn test_zlib_stored() {
+        let z = zlib_stored(b"abc");
+        assert_eq!(z[..2], [0x78, 0x01]);
+        // A single final block, with its length and the length's complement
+        assert_eq!(z[2..7], [1, 3, 0, 0xFC, 0xFF]);
+        assert_eq!(&z[7..10], b"abc");
+        assert_eq!(z[10..], adler32(b"abc").to_be_bytes());
+
+        // Large inputs are split into several blocks, and only the last one
+        // is marked as final
+        let data = vec![7; 70000];
+        let z = zlib_stored(&data);
+        assert_eq!(z.len(), 2 + 5 * 2 + data.len() + 4);
+        assert_eq!(z[2], 0);
+        assert_eq!(z[2 + 5 + 65535], 1);
+
+        assert_eq!(zlib_stored(b"")[2..7], [1, 0, 0, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let png = encode_png(PhysicalSize::new(1, 1), &[255, 0, 0, 255]);
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[16..24], [0, 0, 0, 1, 0, 0, 0, 1]);
+        // The IEND chunk is always the same twelve bytes
+        assert_eq!(
+            png[png.len() - 12..],
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]
+        );
+        assert_eq!(png, encode_png(PhysicalSize::new(1, 1), &[255, 0, 0, 255]));
+    }
+}
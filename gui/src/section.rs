@@ -0,0 +1,258 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use nalgebra_glm::DVec3;
+use triangulate::{
+    mesh::Mesh,
+    section::{cap_triangles, section_loops, Plane},
+};
+
+/// Shortest time between rebuilding the caps while the plane is dragged,
+/// so that sectioning a large model doesn't hold up every frame
+const CAP_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A section plane, which cuts away the side of the model facing along its
+/// normal, along with the state of the caps which fill in the cut
+pub struct Section {
+    plane: Plane,
+    /// Triangles of each part (or of the whole mesh, if it has no parts),
+    /// with their axis-aligned bounds, so that parts which the plane misses
+    /// are skipped without looking at their triangles
+    parts: Vec<(Range<usize>, DVec3, DVec3)>,
+    /// Whether the plane (or mesh) changed since the caps were built
+    stale: bool,
+    /// Part visibility which the caps were built for
+    visible: Vec<bool>,
+    /// When the caps were last built
+    built_at: Option<Instant>,
+}
+
+impl Section {
+    pub fn new(mesh: &Mesh, origin: DVec3, normal: DVec3) -> Self {
+        Self {
+            plane: Plane::new(origin, normal),
+            parts: part_bounds(mesh),
+            stale: true,
+            visible: vec![],
+            built_at: None,
+        }
+    }
+
+    /// Picks up a new (or edited) mesh, whose caps are built on the next
+    /// call to [`Section::build`]
+    pub fn set_mesh(&mut self, mesh: &Mesh) {
+        self.parts = part_bounds(mesh);
+        self.stale = true;
+    }
+
+    pub fn normal(&self) -> DVec3 {
+        self.plane.normal
+    }
+
+    /// Returns the plane as `(normal, -normal · origin)`, so that points on
+    /// the side which is cut away have a positive dot product with it
+    pub fn clip(&self) -> [f32; 4] {
+        let n = self.plane.normal;
+        let w = -n.dot(&self.plane.origin);
+        [n.x as f32, n.y as f32, n.z as f32, w as f32]
+    }
+
+    /// Moves the plane along its normal
+    pub fn shift(&mut self, distance: f64) {
+        if distance != 0.0 {
+            self.plane.origin += self.plane.normal * distance;
+            self.stale = true;
+        }
+    }
+
+    /// Returns true if the caps no longer match the plane or the mesh
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Checks whether the caps should be rebuilt for the given visibility.
+    /// While the plane is being dragged, they're rebuilt at most once every
+    /// [`CAP_INTERVAL`]; the last rebuild happens once the drag ends.
+    pub fn due(&self, visible: &[bool], dragging: bool, now: Instant) -> bool {
+        if !self.stale && visible == self.visible.as_slice() {
+            false
+        } else if dragging {
+            self.built_at
+                .is_none_or(|t| now.duration_since(t) >= CAP_INTERVAL)
+        } else {
+            true
+        }
+    }
+
+    /// Builds caps for every visible part which the plane cuts through,
+    /// returning triangles which face along the plane's normal.
+    ///
+    /// Parts whose section can't be triangulated are left without a cap,
+    /// with a warning.
+    pub fn build(&mut self, mesh: &Mesh, visible: &[bool], now: Instant) -> Vec<[DVec3; 3]> {
+        let mut out = vec![];
+        for (i, (triangles, lo, hi)) in self.parts.iter().enumerate() {
+            let shown = mesh.parts.is_empty() || visible.get(i).copied().unwrap_or(false);
+            if !shown || !straddles(&self.plane, *lo, *hi) {
+                continue;
+            }
+            let loops = section_loops(mesh, triangles.clone(), &self.plane);
+            match cap_triangles(&loops, &self.plane) {
+                Ok(tris) => out.extend(tris),
+                Err(e) => log::warn!("Could not cap the section of part {}: {:?}", i, e),
+            }
+        }
+        self.stale = false;
+        self.visible = visible.to_vec();
+        self.built_at = Some(now);
+        out
+    }
+}
+
+/// Returns each part's triangles and bounds, or a single entry for the
+/// whole mesh if it has no parts.  Parts without triangles have empty
+/// bounds, which never straddle a plane.
+fn part_bounds(mesh: &Mesh) -> Vec<(Range<usize>, DVec3, DVec3)> {
+    let ranges: Vec<Range<usize>> = if mesh.parts.is_empty() {
+        let all = 0..mesh.triangles.len();
+        vec![all]
+    } else {
+        mesh.parts.iter().map(|p| p.triangles.clone()).collect()
+    };
+    ranges
+        .into_iter()
+        .map(|r| {
+            let (lo, hi) = mesh.triangles[r.clone()]
+                .iter()
+                .flat_map(|t| t.verts.iter())
+                .map(|i| mesh.verts[*i as usize].pos)
+                .fold(
+                    (DVec3::repeat(f64::INFINITY), DVec3::repeat(-f64::INFINITY)),
+                    |(lo, hi), p| (lo.inf(&p), hi.sup(&p)),
+                );
+            (r, lo, hi)
+        })
+        .collect()
+}
+
+/// Checks whether a plane passes through a bounding box
+fn straddles(plane: &Plane, lo: DVec3, hi: DVec3) -> bool {
+    if lo.x > hi.x {
+        return false;
+    }
+    let center = (lo + hi) / 2.0;
+    let extent = (hi - lo) / 2.0;
+    plane.distance(center).abs() <= extent.dot(&plane.normal.abs())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use step::fixture;
+    use triangulate::load::{load_mesh_from_bytes, TriangulateOptions};
+
+    /// Loads two unit cubes, side by side along X
+    fn two_cubes() -> Mesh {
+        let asm = fixture::assembly(vec![
+            fixture::cube(1.0),
+            fixture::cube(1.0).translate([3.0, 0.0, 0.0]),
+        ]);
+        let (scene, _) =
+            load_mesh_from_bytes(asm.to_step().as_bytes(), &TriangulateOptions::default()).unwrap();
+        scene.mesh
+    }
+
+    /// Total area of the caps, as seen along the normal
+    fn area(tris: &[[DVec3; 3]], normal: DVec3) -> f64 {
+        tris.iter()
+            .map(|t| (t[1] - t[0]).cross(&(t[2] - t[0])).dot(&normal) / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_cube_at_mid_height() {
+        let mesh = two_cubes();
+        assert_eq!(mesh.parts.len(), 2);
+        // Parts may be loaded in either order, so each cap is checked
+        // against the bounds of the part it should belong to
+        let bounds = part_bounds(&mesh);
+        let within =
+            |p: &DVec3, i: usize| p.x >= bounds[i].1.x - 1e-9 && p.x <= bounds[i].2.x + 1e-9;
+        let (lo, hi) = (bounds[0].1, bounds[0].2);
+        let size = hi.x - lo.x;
+        let mid = (lo + hi) / 2.0;
+
+        // A horizontal cut through both cubes caps each of them
+        let mut s = Section::new(&mesh, mid, DVec3::z());
+        let now = Instant::now();
+        let caps = s.build(&mesh, &[true, true], now);
+        assert!((area(&caps, DVec3::z()) - 2.0 * size * size).abs() < 1e-9);
+        for t in &caps {
+            assert!((t[1] - t[0]).cross(&(t[2] - t[0])).z > 0.0);
+            assert!(t.iter().all(|p| (p.z - mid.z).abs() < 1e-9));
+        }
+        assert!(!s.is_stale());
+
+        // Hidden parts aren't capped
+        let caps = s.build(&mesh, &[false, true], now);
+        assert!((area(&caps, DVec3::z()) - size * size).abs() < 1e-9);
+        assert!(caps.iter().all(|t| t.iter().all(|p| within(p, 1))));
+
+        // Neither are parts which the plane misses
+        let mut s = Section::new(&mesh, mid, DVec3::x());
+        let caps = s.build(&mesh, &[true, true], now);
+        assert!((area(&caps, DVec3::x()) - size * size).abs() < 1e-9);
+        assert!(caps.iter().all(|t| t.iter().all(|p| within(p, 0))));
+
+        // Moving the plane past the model leaves nothing to cap
+        s.shift(-10.0 * size);
+        assert!(s.is_stale());
+        assert!(s.build(&mesh, &[true, true], now).is_empty());
+    }
+
+    #[test]
+    fn test_clip() {
+        let mesh = Mesh::default();
+        let s = Section::new(&mesh, DVec3::new(0.0, 0.0, 2.0), DVec3::new(0.0, 0.0, -3.0));
+        // Points above the plane are kept, since the normal points down
+        assert_eq!(s.clip(), [0.0, 0.0, -1.0, 2.0]);
+        assert_eq!(s.normal(), -DVec3::z());
+    }
+
+    #[test]
+    fn test_throttle() {
+        let mesh = two_cubes();
+        let all = [true, true];
+        let mut s = Section::new(&mesh, DVec3::zeros(), DVec3::z());
+        let t0 = Instant::now();
+        assert!(s.due(&all, true, t0));
+        s.build(&mesh, &all, t0);
+        assert!(!s.due(&all, true, t0));
+        assert!(!s.due(&all, false, t0));
+
+        // While dragging, a moved plane waits for the interval to pass
+        s.shift(0.1);
+        assert!(!s.due(&all, true, t0 + CAP_INTERVAL / 2));
+        assert!(s.due(&all, true, t0 + CAP_INTERVAL));
+        // ...but not once the drag has ended
+        assert!(s.due(&all, false, t0 + CAP_INTERVAL / 2));
+
+        // Hiding a part or changing the mesh rebuilds the caps too
+        s.build(&mesh, &all, t0);
+        assert!(s.due(&[true, false], false, t0));
+        s.set_mesh(&mesh);
+        assert!(s.due(&all, false, t0));
+    }
+
+    #[test]
+    fn test_straddles() {
+        let plane = Plane::new(DVec3::repeat(0.5), DVec3::new(1.0, 1.0, 0.0));
+        assert!(straddles(&plane, DVec3::zeros(), DVec3::repeat(1.0)));
+        assert!(!straddles(&plane, DVec3::repeat(2.0), DVec3::repeat(3.0)));
+        // Empty bounds (from a part without triangles) are never cut
+        let empty = (DVec3::repeat(f64::INFINITY), DVec3::repeat(-f64::INFINITY));
+        assert!(!straddles(&plane, empty.0, empty.1));
+    }
+}
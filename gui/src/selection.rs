@@ -0,0 +1,93 @@
+use crate::visibility::Visibility;
+
+/// Tracks which parts of the model are selected
+#[derive(Clone, Debug)]
+pub struct Selection {
+    selected: Vec<bool>,
+}
+
+impl Selection {
+    /// Builds an empty selection for a model with `n` parts
+    pub fn new(n: usize) -> Self {
+        Self {
+            selected: vec![false; n],
+        }
+    }
+
+    /// Selects a single part, or clears the selection with `None`
+    pub fn select_only(&mut self, part: Option<usize>) {
+        for (i, s) in self.selected.iter_mut().enumerate() {
+            *s = Some(i) == part;
+        }
+    }
+
+    /// Adds a part to the selection, or removes it if it was selected
+    pub fn toggle(&mut self, part: usize) {
+        if let Some(s) = self.selected.get_mut(part) {
+            *s = !*s;
+        }
+    }
+
+    /// Returns one flag per part which is both selected and visible, for
+    /// drawing the selection mask, or `None` if no such part exists
+    pub fn mask(&self, vis: &Visibility) -> Option<Vec<bool>> {
+        let mask: Vec<bool> = self
+            .selected
+            .iter()
+            .enumerate()
+            .map(|(i, s)| *s && vis.is_visible(i))
+            .collect();
+        mask.iter().any(|m| *m).then_some(mask)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let mut s = Selection::new(3);
+        assert_eq!(s.selected, [false, false, false]);
+
+        s.select_only(Some(1));
+        assert_eq!(s.selected, [false, true, false]);
+        s.select_only(Some(2));
+        assert_eq!(s.selected, [false, false, true]);
+
+        s.toggle(0);
+        assert_eq!(s.selected, [true, false, true]);
+        s.toggle(0);
+        assert_eq!(s.selected, [false, false, true]);
+
+        // Invalid parts are ignored, though selecting one still clears the
+        // rest of the selection
+        s.toggle(5);
+        assert_eq!(s.selected, [false, false, true]);
+        s.select_only(Some(5));
+        assert_eq!(s.selected, [false, false, false]);
+
+        s.select_only(Some(0));
+        s.select_only(None);
+        assert_eq!(s.selected, [false, false, false]);
+    }
+
+    #[test]
+    fn test_mask() {
+        let mut s = Selection::new(3);
+        let mut vis = Visibility::new(3);
+        assert_eq!(s.mask(&vis), None);
+
+        s.toggle(0);
+        s.toggle(2);
+        assert_eq!(s.mask(&vis), Some(vec![true, false, true]));
+
+        // Hidden parts aren't outlined
+        vis.hide(2);
+        assert_eq!(s.mask(&vis), Some(vec![true, false, false]));
+        vis.isolate(1);
+        assert_eq!(s.mask(&vis), None);
+    }
+}
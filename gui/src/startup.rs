@@ -0,0 +1,230 @@
+use std::path::PathBuf;
+
+use glm::Vec2;
+use nalgebra_glm as glm;
+use winit::dpi::PhysicalSize;
+
+use crate::camera::Camera;
+
+/// A named camera orientation, picked with `--camera`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Preset {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Iso,
+}
+
+impl Preset {
+    pub const NAMES: [&'static str; 7] = ["front", "back", "left", "right", "top", "bottom", "iso"];
+
+    /// Returns the preset's azimuth and elevation, in degrees (see
+    /// [`Camera::set_orientation`])
+    pub fn angles(&self) -> (f32, f32) {
+        use Preset::*;
+        match self {
+            Front => (0.0, 0.0),
+            Back => (180.0, 0.0),
+            Left => (-90.0, 0.0),
+            Right => (90.0, 0.0),
+            Top => (0.0, 90.0),
+            Bottom => (0.0, -90.0),
+            // Looks down the diagonal of a cube, so that all three axes are
+            // equally foreshortened
+            Iso => (45.0, (0.5f32).sqrt().atan().to_degrees()),
+        }
+    }
+}
+
+/// Parses a camera preset from the command line
+pub fn parse_preset(s: &str) -> Result<Preset, String> {
+    use Preset::*;
+    match s {
+        "front" => Ok(Front),
+        "back" => Ok(Back),
+        "left" => Ok(Left),
+        "right" => Ok(Right),
+        "top" => Ok(Top),
+        "bottom" => Ok(Bottom),
+        "iso" => Ok(Iso),
+        _ => Err(format!(
+            "invalid camera preset {:?} (expected one of {})",
+            s,
+            Preset::NAMES.join(", ")
+        )),
+    }
+}
+
+/// Parses an angle in degrees from the command line
+pub fn parse_angle(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(a) if a.is_finite() => Ok(a),
+        _ => Err(format!("invalid angle {:?} (expected degrees)", s)),
+    }
+}
+
+/// Parses a zoom factor from the command line
+pub fn parse_zoom(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(z) if z.is_finite() && z > 0.0 => Ok(z),
+        _ => Err(format!("invalid zoom {:?} (expected a positive number)", s)),
+    }
+}
+
+/// Parses an image size in pixels, e.g. `640x480`, from the command line
+pub fn parse_size(s: &str) -> Result<PhysicalSize<u32>, String> {
+    match s
+        .split_once('x')
+        .map(|(w, h)| (w.parse::<u32>(), h.parse::<u32>()))
+    {
+        Some((Ok(w), Ok(h))) if w > 0 && h > 0 => Ok(PhysicalSize::new(w, h)),
+        _ => Err(format!("invalid size {:?} (expected WIDTHxHEIGHT)", s)),
+    }
+}
+
+/// Settings from the command line which are applied once the model has
+/// loaded, so that a given model and set of flags always draws the same
+/// image (e.g. for screenshots in bug reports).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Startup {
+    /// Initial orientation, as a preset
+    pub preset: Option<Preset>,
+    /// Azimuth in degrees, overriding the preset's
+    pub azimuth: Option<f32>,
+    /// Elevation in degrees, overriding the preset's
+    pub elevation: Option<f32>,
+    /// Magnification relative to fitting the model in view
+    pub zoom: f32,
+    pub backdrop: bool,
+    pub grid: bool,
+    pub edges: bool,
+    /// PNG file which captures the first frame with the whole model
+    pub screenshot: Option<PathBuf>,
+    /// Whether to quit after that frame (and screenshot, if any)
+    pub exit: bool,
+}
+
+impl Default for Startup {
+    fn default() -> Self {
+        Startup {
+            preset: None,
+            azimuth: None,
+            elevation: None,
+            zoom: 1.0,
+            backdrop: true,
+            grid: true,
+            edges: true,
+            screenshot: None,
+            exit: false,
+        }
+    }
+}
+
+impl Startup {
+    /// Returns the requested azimuth and elevation in degrees, if either
+    /// was set.  An angle which isn't given comes from the preset, or is
+    /// zero without one.
+    pub fn angles(&self) -> Option<(f32, f32)> {
+        if self.preset.is_none() && self.azimuth.is_none() && self.elevation.is_none() {
+            return None;
+        }
+        let (az, el) = self.preset.map_or((0.0, 0.0), |p| p.angles());
+        Some((self.azimuth.unwrap_or(az), self.elevation.unwrap_or(el)))
+    }
+
+    /// Orients and zooms a camera which has just been fit to the model
+    pub fn apply_camera(&self, camera: &mut Camera) {
+        if let Some((az, el)) = self.angles() {
+            camera.set_orientation(az.to_radians(), el.to_radians());
+        }
+        if self.zoom != 1.0 {
+            camera.scale(self.zoom, Vec2::zeros());
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{Vec3, Vec4};
+
+    #[test]
+    fn test_parse_preset() {
+        for name in Preset::NAMES {
+            assert!(parse_preset(name).is_ok(), "{}", name);
+        }
+        assert_eq!(parse_preset("iso"), Ok(Preset::Iso));
+        assert!(parse_preset("ISO").is_err());
+        assert!(parse_preset("").is_err());
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse_angle("-30"), Ok(-30.0));
+        assert!(parse_angle("NaN").is_err());
+        assert!(parse_angle("north").is_err());
+        assert_eq!(parse_zoom("1.2"), Ok(1.2));
+        assert!(parse_zoom("0").is_err());
+        assert!(parse_zoom("-1").is_err());
+        assert!(parse_zoom("inf").is_err());
+        assert_eq!(parse_size("640x480"), Ok(PhysicalSize::new(640, 480)));
+        assert!(parse_size("640x0").is_err());
+        assert!(parse_size("640").is_err());
+        assert!(parse_size("-1x2").is_err());
+    }
+
+    #[test]
+    fn test_angles() {
+        let mut s = Startup::default();
+        assert_eq!(s.angles(), None);
+        s.elevation = Some(20.0);
+        assert_eq!(s.angles(), Some((0.0, 20.0)));
+        s.preset = Some(Preset::Back);
+        assert_eq!(s.angles(), Some((180.0, 20.0)));
+        s.azimuth = Some(10.0);
+        assert_eq!(s.angles(), Some((10.0, 20.0)));
+    }
+
+    #[test]
+    fn test_apply_camera() {
+        // Returns the direction from the model towards the viewer
+        let towards_viewer = |preset: Preset| {
+            let mut camera = Camera::new(100.0, 100.0);
+            let s = Startup {
+                preset: Some(preset),
+                ..Startup::default()
+            };
+            s.apply_camera(&mut camera);
+            let v = camera.rotation_matrix().transpose() * Vec4::new(0.0, 0.0, 1.0, 0.0);
+            v.xyz()
+        };
+        let close = |a: Vec3, b: Vec3| (a - b).norm() < 1e-5;
+        assert!(close(towards_viewer(Preset::Front), Vec3::z()));
+        assert!(close(towards_viewer(Preset::Back), -Vec3::z()));
+        assert!(close(towards_viewer(Preset::Right), Vec3::x()));
+        assert!(close(towards_viewer(Preset::Left), -Vec3::x()));
+        assert!(close(towards_viewer(Preset::Top), Vec3::y()));
+        assert!(close(towards_viewer(Preset::Bottom), -Vec3::y()));
+        assert!(close(
+            towards_viewer(Preset::Iso),
+            Vec3::new(1.0, 1.0, 1.0).normalize()
+        ));
+
+        // Zooming is relative to the fitted scale
+        let mut camera = Camera::new(100.0, 100.0);
+        camera.set_bounding_sphere(Vec3::zeros(), 1.0);
+        camera.fit_bounds(-Vec3::repeat(1.0), Vec3::repeat(1.0));
+        let fit = camera.state().scale;
+        let s = Startup {
+            zoom: 1.5,
+            ..Startup::default()
+        };
+        s.apply_camera(&mut camera);
+        assert!((camera.state().scale / fit - 1.5).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,153 @@
+use winit::dpi::PhysicalSize;
+
+/// Thin wrapper around the parts of the swapchain that can fail at runtime,
+/// so that the recovery logic in [`acquire`] can be tested without a GPU.
+pub trait FrameSource {
+    type Frame;
+
+    /// Attempts to get the next frame from the swapchain
+    fn next_frame(&mut self) -> Result<Self::Frame, wgpu::SwapChainError>;
+
+    /// Rebuilds the swapchain at the given (non-zero) size
+    fn reconfigure(&mut self, size: PhysicalSize<u32>);
+}
+
+/// Result of trying to acquire a frame for rendering
+#[derive(Debug)]
+pub enum Acquired<F> {
+    /// We've got a frame and should render into it
+    Frame(F),
+    /// No frame this time around; try again on the next redraw
+    Retry,
+    /// There's nothing to draw into (e.g. the window is minimized), so don't
+    /// bother redrawing until something changes.
+    Skip,
+    /// The swapchain is out of memory and the application should exit
+    Fatal,
+}
+
+/// Returns true if a surface of this size can actually be rendered into.
+/// Minimized windows report a size of zero on some platforms, and building
+/// a swapchain or depth texture with a zero dimension panics in `wgpu`.
+pub fn is_drawable(size: PhysicalSize<u32>) -> bool {
+    size.width > 0 && size.height > 0
+}
+
+/// Acquires the next frame from `src`, recovering from lost or outdated
+/// swapchains by rebuilding them at the current size.
+pub fn acquire<S: FrameSource>(src: &mut S, size: PhysicalSize<u32>) -> Acquired<S::Frame> {
+    if !is_drawable(size) {
+        return Acquired::Skip;
+    }
+    match src.next_frame() {
+        Ok(f) => Acquired::Frame(f),
+        Err(wgpu::SwapChainError::Lost) | Err(wgpu::SwapChainError::Outdated) => {
+            log::warn!("Swapchain is lost or outdated; reconfiguring");
+            src.reconfigure(size);
+            Acquired::Retry
+        }
+        Err(wgpu::SwapChainError::Timeout) => Acquired::Retry,
+        Err(wgpu::SwapChainError::OutOfMemory) => Acquired::Fatal,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fake swapchain, which returns a scripted series of results and
+    /// records reconfiguration calls.
+    struct MockSource {
+        results: Vec<Result<u32, wgpu::SwapChainError>>,
+        reconfigured: Vec<PhysicalSize<u32>>,
+        acquired: usize,
+    }
+
+    impl MockSource {
+        fn new(mut results: Vec<Result<u32, wgpu::SwapChainError>>) -> Self {
+            results.reverse();
+            Self {
+                results,
+                reconfigured: vec![],
+                acquired: 0,
+            }
+        }
+    }
+
+    impl FrameSource for MockSource {
+        type Frame = u32;
+        fn next_frame(&mut self) -> Result<u32, wgpu::SwapChainError> {
+            self.acquired += 1;
+            self.results.pop().expect("Ran out of scripted frames")
+        }
+        fn reconfigure(&mut self, size: PhysicalSize<u32>) {
+            self.reconfigured.push(size);
+        }
+    }
+
+    #[test]
+    fn test_zero_size_is_skipped() {
+        let mut src = MockSource::new(vec![]);
+        for size in [
+            PhysicalSize::new(0, 0),
+            PhysicalSize::new(0, 100),
+            PhysicalSize::new(100, 0),
+        ] {
+            assert!(!is_drawable(size));
+            assert!(matches!(acquire(&mut src, size), Acquired::Skip));
+        }
+        // We should never have touched the swapchain
+        assert_eq!(src.acquired, 0);
+        assert!(src.reconfigured.is_empty());
+    }
+
+    #[test]
+    fn test_reconfigure_then_recover() {
+        use wgpu::SwapChainError::*;
+        let size = PhysicalSize::new(640, 480);
+        let mut src = MockSource::new(vec![Ok(1), Err(Lost), Err(Outdated), Ok(2)]);
+
+        assert!(matches!(acquire(&mut src, size), Acquired::Frame(1)));
+        assert!(src.reconfigured.is_empty());
+
+        assert!(matches!(acquire(&mut src, size), Acquired::Retry));
+        assert_eq!(src.reconfigured, vec![size]);
+
+        assert!(matches!(acquire(&mut src, size), Acquired::Retry));
+        assert_eq!(src.reconfigured, vec![size, size]);
+
+        assert!(matches!(acquire(&mut src, size), Acquired::Frame(2)));
+        assert_eq!(src.reconfigured.len(), 2);
+    }
+
+    #[test]
+    fn test_minimize_and_restore() {
+        use wgpu::SwapChainError::*;
+        let mut src = MockSource::new(vec![Err(Outdated), Ok(7)]);
+
+        // While minimized, nothing happens at all
+        assert!(matches!(
+            acquire(&mut src, PhysicalSize::new(0, 0)),
+            Acquired::Skip
+        ));
+        assert_eq!(src.acquired, 0);
+
+        // On restore, the stale swapchain is rebuilt at the new size
+        let size = PhysicalSize::new(800, 600);
+        assert!(matches!(acquire(&mut src, size), Acquired::Retry));
+        assert_eq!(src.reconfigured, vec![size]);
+        assert!(matches!(acquire(&mut src, size), Acquired::Frame(7)));
+    }
+
+    #[test]
+    fn test_timeout_and_oom() {
+        use wgpu::SwapChainError::*;
+        let size = PhysicalSize::new(10, 10);
+        let mut src = MockSource::new(vec![Err(Timeout), Err(OutOfMemory)]);
+        assert!(matches!(acquire(&mut src, size), Acquired::Retry));
+        assert!(matches!(acquire(&mut src, size), Acquired::Fatal));
+        assert!(src.reconfigured.is_empty());
+    }
+}
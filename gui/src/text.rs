@@ -0,0 +1,629 @@
+use std::borrow::Cow;
+use std::num::NonZeroU32;
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use winit::dpi::PhysicalSize;
+
+/// Width and height of each glyph in the built-in font, in pixels
+const GLYPH_SIZE: u32 = 8;
+
+/// Empty pixels between neighboring glyphs in the atlas, so that sampling
+/// at a glyph's edge never picks up its neighbor
+const ATLAS_PADDING: u32 = 1;
+
+/// Widest atlas that we'll build, in pixels
+const ATLAS_MAX_WIDTH: u32 = 128;
+
+/// The atlas stores printable ASCII, from space to tilde
+const FIRST_CHAR: u8 = b' ';
+const GLYPH_COUNT: u32 = (b'~' - b' ') as u32 + 1;
+
+/// Distance between lines, as a multiple of the text size
+pub const LINE_HEIGHT: f32 = 1.25;
+
+/// Largest number of glyphs drawn in a frame; any extra are dropped
+const MAX_GLYPHS: usize = 4096;
+
+/// Placement of glyphs in the font atlas, which packs them into rows of
+/// equally-sized cells
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AtlasLayout {
+    /// Size of each glyph, in pixels
+    pub glyph: u32,
+    /// Size of each cell (a glyph and its padding), in pixels
+    pub cell: u32,
+    pub cols: u32,
+    pub rows: u32,
+    /// Size of the whole atlas, in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasLayout {
+    /// Packs `count` glyphs into an atlas which is at most `max_width`
+    /// pixels wide (but always at least one cell wide)
+    pub fn new(count: u32, glyph: u32, padding: u32, max_width: u32) -> Self {
+        let cell = glyph + padding;
+        let cols = (max_width / cell).clamp(1, count.max(1));
+        let rows = count.div_ceil(cols);
+        Self {
+            glyph,
+            cell,
+            cols,
+            rows,
+            width: cols * cell,
+            height: rows * cell,
+        }
+    }
+
+    /// Returns the top-left pixel of a glyph
+    pub fn origin(&self, i: u32) -> (u32, u32) {
+        ((i % self.cols) * self.cell, (i / self.cols) * self.cell)
+    }
+
+    /// Returns texture coordinates of a glyph's top-left and bottom-right
+    /// corners
+    pub fn uv(&self, i: u32) -> [f32; 4] {
+        let (x, y) = self.origin(i);
+        let (w, h) = (self.width as f32, self.height as f32);
+        [
+            x as f32 / w,
+            y as f32 / h,
+            (x + self.glyph) as f32 / w,
+            (y + self.glyph) as f32 / h,
+        ]
+    }
+}
+
+/// Returns a character's index in the atlas, substituting `?` for
+/// characters which aren't in the font
+fn glyph_index(c: char) -> u32 {
+    if c.is_ascii_graphic() || c == ' ' {
+        c as u32 - FIRST_CHAR as u32
+    } else {
+        b'?' as u32 - FIRST_CHAR as u32
+    }
+}
+
+/// Draws the built-in font into a single-channel atlas image
+fn rasterize(layout: &AtlasLayout) -> Vec<u8> {
+    let mut out = vec![0; (layout.width * layout.height) as usize];
+    for i in 0..GLYPH_COUNT {
+        let bits = font8x8::legacy::BASIC_LEGACY[(i + FIRST_CHAR as u32) as usize];
+        let (x0, y0) = layout.origin(i);
+        for (y, row) in bits.iter().enumerate() {
+            for x in 0..GLYPH_SIZE {
+                // The lowest bit is the leftmost pixel
+                if row & (1 << x) != 0 {
+                    let p = (y0 + y as u32) * layout.width + x0 + x;
+                    out[p as usize] = 255;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Per-glyph instance data, matching `text.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct GPUGlyph {
+    /// Position and size, in logical pixels from the window's top-left
+    rect: [f32; 4],
+    uv: [f32; 4],
+    color: [f32; 4],
+}
+
+/// A queued string, which refers to a range of [`Batch::text`]
+#[derive(Clone, Debug, PartialEq)]
+struct Label {
+    text: Range<usize>,
+    position: [f32; 2],
+    size: f32,
+    color: [f32; 4],
+}
+
+/// Strings queued for a single frame.  The text of every label is stored
+/// in one buffer, so a batch can be cleared and refilled without
+/// allocating.
+#[derive(Default, PartialEq)]
+struct Batch {
+    text: String,
+    labels: Vec<Label>,
+}
+
+impl Batch {
+    fn clear(&mut self) {
+        self.text.clear();
+        self.labels.clear();
+    }
+
+    /// Lays out every label, appending glyphs to `out`
+    fn layout(&self, atlas: &AtlasLayout, out: &mut Vec<GPUGlyph>) {
+        for label in &self.labels {
+            layout_label(&self.text[label.text.clone()], label, atlas, out);
+        }
+    }
+}
+
+/// Lays out a single string, with the top-left corner of its first glyph
+/// at the label's position.  Spaces are skipped, and newlines start a new
+/// line below the first.
+fn layout_label(text: &str, label: &Label, atlas: &AtlasLayout, out: &mut Vec<GPUGlyph>) {
+    let [x0, mut y] = label.position;
+    let mut x = x0;
+    for c in text.chars() {
+        if c == '\n' {
+            x = x0;
+            y += label.size * LINE_HEIGHT;
+            continue;
+        }
+        if c != ' ' && out.len() < MAX_GLYPHS {
+            out.push(GPUGlyph {
+                rect: [x, y, label.size, label.size],
+                uv: atlas.uv(glyph_index(c)),
+                color: label.color,
+            });
+        }
+        x += label.size;
+    }
+}
+
+/// Draws text over the viewport, using a built-in 8×8 bitmap font.
+///
+/// Strings are queued each frame with [`TextOverlay::queue`], then drawn
+/// by [`TextOverlay::draw`].  If the same strings are queued as in the
+/// previous frame, the glyph buffer isn't touched.
+pub struct TextOverlay {
+    atlas: AtlasLayout,
+    /// Atlas image, which is uploaded on the first draw
+    atlas_data: Option<Vec<u8>>,
+    atlas_tex: wgpu::Texture,
+
+    /// Window size in physical pixels and HiDPI scale factor, as passed to
+    /// the shader
+    viewport: [f32; 4],
+    viewport_dirty: bool,
+    uniform_buf: wgpu::Buffer,
+
+    /// Strings for the next frame, and those which are in `glyph_buf`
+    queued: Batch,
+    drawn: Batch,
+    /// Scratch space for laying out glyphs
+    glyphs: Vec<GPUGlyph>,
+    glyph_buf: wgpu::Buffer,
+    glyph_count: u32,
+
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl TextOverlay {
+    pub fn new(device: &wgpu::Device, swapchain_format: wgpu::TextureFormat) -> Self {
+        let atlas = AtlasLayout::new(GLYPH_COUNT, GLYPH_SIZE, ATLAS_PADDING, ATLAS_MAX_WIDTH);
+        let atlas_tex = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Font atlas"),
+            size: wgpu::Extent3d {
+                width: atlas.width,
+                height: atlas.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let atlas_view = atlas_tex.create_view(&wgpu::TextureViewDescriptor::default());
+        // Nearest-neighbor sampling keeps the bitmap font crisp
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Font atlas sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..wgpu::SamplerDescriptor::default()
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text uniform buffer"),
+            size: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let glyph_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Glyph buffer"),
+            size: (std::mem::size_of::<GPUGlyph>() * MAX_GLYPHS) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<[f32; 4]>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: None,
+        });
+
+        // Load the shaders from disk, either at runtime or compile-time
+        #[cfg(feature = "bundle-shaders")]
+        let text_src = Cow::Borrowed(include_str!("text.wgsl"));
+
+        #[cfg(not(feature = "bundle-shaders"))]
+        let text_src = Cow::Owned(
+            String::from_utf8(std::fs::read("gui/src/text.wgsl").expect("Could not read shader"))
+                .expect("Shader is invalid UTF-8"),
+        );
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(text_src),
+            flags: wgpu::ShaderFlags::all(),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<GPUGlyph>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x4,
+                        1 => Float32x4,
+                        2 => Float32x4,
+                    ],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: swapchain_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        TextOverlay {
+            atlas_data: Some(rasterize(&atlas)),
+            atlas,
+            atlas_tex,
+            viewport: [1.0, 1.0, 1.0, 0.0],
+            viewport_dirty: true,
+            uniform_buf,
+            queued: Batch::default(),
+            drawn: Batch::default(),
+            glyphs: vec![],
+            glyph_buf,
+            glyph_count: 0,
+            bind_group,
+            render_pipeline,
+        }
+    }
+
+    /// Updates the window size (in physical pixels) and HiDPI scale, which
+    /// is uploaded on the next draw.  Text positions and sizes are in
+    /// logical pixels, so they don't need to change.
+    pub fn set_viewport(&mut self, size: PhysicalSize<u32>, scale_factor: f64) {
+        let next = [
+            size.width.max(1) as f32,
+            size.height.max(1) as f32,
+            scale_factor as f32,
+            0.0,
+        ];
+        if next != self.viewport {
+            self.viewport = next;
+            self.viewport_dirty = true;
+        }
+    }
+
+    /// Queues a string to be drawn in the next frame.  `position` is the
+    /// top-left corner of the first glyph and `size` is the height of each
+    /// glyph, both in logical pixels.
+    pub fn queue(&mut self, text: &str, position: [f32; 2], size: f32, color: [f32; 4]) {
+        let start = self.queued.text.len();
+        self.queued.text.push_str(text);
+        self.queued.labels.push(Label {
+            text: start..self.queued.text.len(),
+            position,
+            size,
+            color,
+        });
+    }
+
+    /// Draws every queued string over the frame, then clears the queue
+    pub fn draw(
+        &mut self,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        if let Some(data) = self.atlas_data.take() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.atlas_tex,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                &data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.atlas.width),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: self.atlas.width,
+                    height: self.atlas.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        if self.viewport_dirty {
+            queue.write_buffer(&self.uniform_buf, 0, bytemuck::cast_slice(&self.viewport));
+            self.viewport_dirty = false;
+        }
+
+        // Only lay out and upload glyphs if the text has changed
+        if self.queued != self.drawn {
+            self.glyphs.clear();
+            self.queued.layout(&self.atlas, &mut self.glyphs);
+            if !self.glyphs.is_empty() {
+                queue.write_buffer(&self.glyph_buf, 0, bytemuck::cast_slice(&self.glyphs));
+            }
+            self.glyph_count = self.glyphs.len() as u32;
+            std::mem::swap(&mut self.queued, &mut self.drawn);
+        }
+        self.queued.clear();
+
+        if self.glyph_count == 0 {
+            return;
+        }
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.glyph_buf.slice(..));
+        rpass.draw(0..6, 0..self.glyph_count);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atlas() -> AtlasLayout {
+        AtlasLayout::new(GLYPH_COUNT, GLYPH_SIZE, ATLAS_PADDING, ATLAS_MAX_WIDTH)
+    }
+
+    #[test]
+    fn test_atlas_layout() {
+        let a = atlas();
+        assert_eq!(a.cell, 9);
+        assert_eq!(a.cols, 14);
+        assert_eq!(a.rows, 7);
+        assert_eq!((a.width, a.height), (126, 63));
+        assert!(a.width <= ATLAS_MAX_WIDTH);
+        assert!(a.cols * a.rows >= GLYPH_COUNT);
+
+        assert_eq!(a.origin(0), (0, 0));
+        assert_eq!(a.origin(13), (117, 0));
+        assert_eq!(a.origin(14), (0, 9));
+        assert_eq!(a.origin(GLYPH_COUNT - 1), (90, 54));
+    }
+
+    #[test]
+    fn test_atlas_edge_cases() {
+        // A single glyph needs a single cell
+        let a = AtlasLayout::new(1, 8, 1, 128);
+        assert_eq!((a.cols, a.rows, a.width, a.height), (1, 1, 9, 9));
+
+        // Glyphs wider than the limit still get one column
+        let a = AtlasLayout::new(3, 16, 0, 8);
+        assert_eq!((a.cols, a.rows, a.width, a.height), (1, 3, 16, 48));
+
+        // An exact fit doesn't add an empty row
+        let a = AtlasLayout::new(4, 8, 0, 16);
+        assert_eq!((a.cols, a.rows), (2, 2));
+    }
+
+    #[test]
+    fn test_glyphs_disjoint() {
+        // Every glyph (plus padding) lies inside the atlas, without
+        // overlapping any other glyph
+        let a = atlas();
+        let mut used = vec![false; (a.width * a.height) as usize];
+        for i in 0..GLYPH_COUNT {
+            let (x0, y0) = a.origin(i);
+            assert!(x0 + a.cell <= a.width && y0 + a.cell <= a.height);
+            for y in y0..(y0 + a.cell) {
+                for x in x0..(x0 + a.cell) {
+                    let p = (y * a.width + x) as usize;
+                    assert!(!used[p], "glyph {} overlaps at ({}, {})", i, x, y);
+                    used[p] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_uv() {
+        let a = atlas();
+        assert_eq!(a.uv(0), [0.0, 0.0, 8.0 / 126.0, 8.0 / 63.0]);
+        let [u0, v0, u1, v1] = a.uv(15);
+        assert_eq!((u0, v0), (9.0 / 126.0, 9.0 / 63.0));
+        assert_eq!((u1, v1), (17.0 / 126.0, 17.0 / 63.0));
+    }
+
+    #[test]
+    fn test_rasterize() {
+        let a = atlas();
+        let data = rasterize(&a);
+        assert_eq!(data.len(), (a.width * a.height) as usize);
+
+        // Space is blank, and '#' isn't
+        let count = |c: char| {
+            let (x0, y0) = a.origin(glyph_index(c));
+            (y0..y0 + GLYPH_SIZE)
+                .flat_map(|y| (x0..x0 + GLYPH_SIZE).map(move |x| (x, y)))
+                .filter(|(x, y)| data[(y * a.width + x) as usize] != 0)
+                .count()
+        };
+        assert_eq!(count(' '), 0);
+        assert!(count('#') > 0);
+
+        // Padding is left empty
+        for y in 0..a.height {
+            assert_eq!(data[(y * a.width + GLYPH_SIZE) as usize], 0);
+        }
+    }
+
+    #[test]
+    fn test_glyph_index() {
+        assert_eq!(glyph_index(' '), 0);
+        assert_eq!(glyph_index('!'), 1);
+        assert_eq!(glyph_index('~'), GLYPH_COUNT - 1);
+        assert_eq!(glyph_index('é'), glyph_index('?'));
+        assert_eq!(glyph_index('\t'), glyph_index('?'));
+    }
+
+    #[test]
+    fn test_layout() {
+        let a = atlas();
+        let label = Label {
+            text: 0..0,
+            position: [10.0, 20.0],
+            size: 16.0,
+            color: [1.0; 4],
+        };
+        let mut out = vec![];
+        layout_label("a b\nc", &label, &a, &mut out);
+        let rects: Vec<_> = out.iter().map(|g| g.rect).collect();
+        assert_eq!(
+            rects,
+            vec![
+                [10.0, 20.0, 16.0, 16.0],
+                // The space advances without drawing anything
+                [42.0, 20.0, 16.0, 16.0],
+                [10.0, 40.0, 16.0, 16.0],
+            ]
+        );
+        assert_eq!(out[0].uv, a.uv(glyph_index('a')));
+    }
+
+    #[test]
+    fn test_batch() {
+        let a = atlas();
+        let mut batch = Batch::default();
+        let push = |b: &mut Batch, text: &str, x: f32| {
+            let start = b.text.len();
+            b.text.push_str(text);
+            b.labels.push(Label {
+                text: start..b.text.len(),
+                position: [x, 0.0],
+                size: 8.0,
+                color: [1.0; 4],
+            });
+        };
+        push(&mut batch, "ab", 0.0);
+        push(&mut batch, "c", 100.0);
+        let mut out = vec![];
+        batch.layout(&a, &mut out);
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[2].rect[0], 100.0);
+        assert_eq!(out[2].uv, a.uv(glyph_index('c')));
+
+        // Refilling a batch with the same strings compares equal, which is
+        // how redundant uploads are skipped
+        let mut other = Batch::default();
+        push(&mut other, "ab", 0.0);
+        push(&mut other, "c", 100.0);
+        assert!(batch == other);
+        other.clear();
+        push(&mut other, "ab", 0.0);
+        push(&mut other, "d", 100.0);
+        assert!(batch != other);
+
+        // Glyphs beyond the limit are dropped
+        let long = Label {
+            text: 0..0,
+            position: [0.0; 2],
+            size: 1.0,
+            color: [1.0; 4],
+        };
+        let mut out = vec![];
+        layout_label(&"x".repeat(MAX_GLYPHS + 10), &long, &a, &mut out);
+        assert_eq!(out.len(), MAX_GLYPHS);
+    }
+}
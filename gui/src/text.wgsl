@@ -0,0 +1,61 @@
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+    [[location(1)]] color: vec4<f32>;
+};
+
+[[block]]
+struct Viewport {
+    // Window width and height in physical pixels, then the HiDPI scale
+    // factor; the last element is padding.
+    size: vec4<f32>;
+};
+[[group(0), binding(0)]]
+var r_viewport: Viewport;
+
+[[group(0), binding(1)]]
+var t_atlas: texture_2d<f32>;
+[[group(0), binding(2)]]
+var s_atlas: sampler;
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] in_vertex_index: u32,
+    // Per-glyph rectangle (x, y, width, height) in logical pixels, from the
+    // top-left corner of the window
+    [[location(0)]] rect: vec4<f32>,
+    // Atlas coordinates of the glyph's top-left and bottom-right corners
+    [[location(1)]] uv: vec4<f32>,
+    [[location(2)]] color: vec4<f32>,
+) -> VertexOutput {
+    // Two triangles which cover the glyph's rectangle
+    var corner: vec2<f32> = vec2<f32>(0.0, 0.0);
+    if (in_vertex_index == 1u || in_vertex_index == 4u) {
+        corner = vec2<f32>(1.0, 0.0);
+    } elseif (in_vertex_index == 2u || in_vertex_index == 3u) {
+        corner = vec2<f32>(0.0, 1.0);
+    } elseif (in_vertex_index == 5u) {
+        corner = vec2<f32>(1.0, 1.0);
+    }
+
+    // Snap to physical pixels, so that glyphs stay crisp
+    var p: vec2<f32> = floor((rect.xy + corner * rect.zw) * r_viewport.size.z + 0.5);
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        p.x / r_viewport.size.x * 2.0 - 1.0,
+        1.0 - p.y / r_viewport.size.y * 2.0,
+        0.0,
+        1.0);
+    out.uv = mix(uv.xy, uv.zw, corner);
+    out.color = color;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    var a: f32 = textureSample(t_atlas, s_atlas, in.uv).x;
+    if (a < 0.5) {
+        discard;
+    }
+    return in.color;
+}
@@ -0,0 +1,298 @@
+use std::path::Path;
+
+/// Name of the config file which is read (if present) at startup
+pub const CONFIG_FILE: &str = "foxtrot.toml";
+
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum ConfigError {
+    #[error("Invalid color {0:?} (expected #rrggbb)")]
+    BadColor(String),
+
+    #[error("Invalid background {0:?} (expected top,bottom)")]
+    BadBackground(String),
+
+    #[error("Invalid theme {0:?} (expected light or dark)")]
+    BadTheme(String),
+
+    #[error("Could not parse config: {0}")]
+    Parse(String),
+}
+
+/// Parses a hex color of the form `#rrggbb` or `rrggbb` into RGB values
+/// in the 0-1 range
+pub fn parse_hex_color(s: &str) -> Result<[f32; 3], ConfigError> {
+    let err = || ConfigError::BadColor(s.to_owned());
+    let hex = s.trim();
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(err());
+    }
+    let mut out = [0.0; 3];
+    for (i, c) in out.iter_mut().enumerate() {
+        let v = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| err())?;
+        *c = v as f32 / 255.0;
+    }
+    Ok(out)
+}
+
+/// Parses a pair of `top,bottom` hex colors for the backdrop
+pub fn parse_background(s: &str) -> Result<([f32; 3], [f32; 3]), ConfigError> {
+    match s.split(',').collect::<Vec<_>>().as_slice() {
+        [top, bottom] => Ok((parse_hex_color(top)?, parse_hex_color(bottom)?)),
+        _ => Err(ConfigError::BadBackground(s.to_owned())),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mode {
+    Dark,
+    Light,
+}
+
+impl std::str::FromStr for Mode {
+    type Err = ConfigError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dark" => Ok(Mode::Dark),
+            "light" => Ok(Mode::Light),
+            _ => Err(ConfigError::BadTheme(s.to_owned())),
+        }
+    }
+}
+
+/// Colors used when drawing the scene
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Backdrop color at the top of the window
+    pub top: [f32; 3],
+    /// Backdrop color at the bottom of the window
+    pub bottom: [f32; 3],
+    /// Multiplied into the model's colors, to keep it legible against the
+    /// backdrop
+    pub base: [f32; 3],
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            top: [0.17, 0.22, 0.29],
+            bottom: [0.05, 0.06, 0.10],
+            base: [1.0; 3],
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            top: [0.95, 0.96, 0.98],
+            bottom: [0.72, 0.76, 0.81],
+            base: [0.8; 3],
+        }
+    }
+
+    /// Returns the backdrop colors in the layout of the backdrop's uniform
+    /// buffer (top, then bottom, each padded out to a `vec4`)
+    pub fn backdrop_uniforms(&self) -> [[f32; 4]; 2] {
+        let pad = |c: [f32; 3]| [c[0], c[1], c[2], 1.0];
+        [pad(self.top), pad(self.bottom)]
+    }
+
+    /// Returns black or white, whichever is more legible over the backdrop
+    pub fn text_color(&self) -> [f32; 4] {
+        let luma = |c: [f32; 3]| 0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2];
+        if (luma(self.top) + luma(self.bottom)) / 2.0 > 0.5 {
+            [0.0, 0.0, 0.0, 1.0]
+        } else {
+            [1.0; 4]
+        }
+    }
+}
+
+/// Settings from `foxtrot.toml`, all of which are optional
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    /// Either `"light"` or `"dark"`
+    theme: Option<String>,
+    /// Backdrop colors, as `"top,bottom"` (matching the `--bg` flag)
+    bg: Option<String>,
+}
+
+/// Light and dark themes, and which one is active
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Themes {
+    dark: Theme,
+    light: Theme,
+    mode: Mode,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Themes {
+            dark: Theme::dark(),
+            light: Theme::light(),
+            mode: Mode::Dark,
+        }
+    }
+}
+
+impl Themes {
+    /// Builds themes from the contents of a config file and an optional
+    /// `--bg` flag, which takes priority over the config file.  Custom
+    /// backdrop colors replace those of the starting theme.
+    pub fn from_config(toml: Option<&str>, bg: Option<&str>) -> Result<Self, ConfigError> {
+        let config: ConfigFile = match toml {
+            Some(t) => toml::from_str(t).map_err(|e| ConfigError::Parse(e.to_string()))?,
+            None => ConfigFile::default(),
+        };
+        let mut out = Themes::default();
+        if let Some(mode) = &config.theme {
+            out.mode = mode.parse()?;
+        }
+        if let Some(bg) = bg.or(config.bg.as_deref()) {
+            let (top, bottom) = parse_background(bg)?;
+            let t = out.theme_mut();
+            t.top = top;
+            t.bottom = bottom;
+        }
+        Ok(out)
+    }
+
+    /// Reads `foxtrot.toml` from the given directory (if it exists), then
+    /// applies the `--bg` flag
+    pub fn load(dir: &Path, bg: Option<&str>) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(dir.join(CONFIG_FILE)).ok();
+        Self::from_config(text.as_deref(), bg)
+    }
+
+    pub fn theme(&self) -> &Theme {
+        match self.mode {
+            Mode::Dark => &self.dark,
+            Mode::Light => &self.light,
+        }
+    }
+
+    fn theme_mut(&mut self) -> &mut Theme {
+        match self.mode {
+            Mode::Dark => &mut self.dark,
+            Mode::Light => &mut self.light,
+        }
+    }
+
+    /// Switches between the light and dark themes
+    pub fn toggle(&mut self) {
+        self.mode = match self.mode {
+            Mode::Dark => Mode::Light,
+            Mode::Light => Mode::Dark,
+        };
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_color() {
+        assert_eq!(Theme::dark().text_color(), [1.0; 4]);
+        assert_eq!(Theme::light().text_color(), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff0000"), Ok([1.0, 0.0, 0.0]));
+        assert_eq!(parse_hex_color("00FF00"), Ok([0.0, 1.0, 0.0]));
+        assert_eq!(parse_hex_color(" #000080 "), Ok([0.0, 0.0, 128.0 / 255.0]));
+        for bad in ["", "#", "#fff", "#ff00000", "#gg0000", "#ff 000", "#ffé00"] {
+            assert_eq!(
+                parse_hex_color(bad),
+                Err(ConfigError::BadColor(bad.to_owned()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_background() {
+        assert_eq!(
+            parse_background("#ffffff,#000000"),
+            Ok(([1.0; 3], [0.0; 3]))
+        );
+        assert_eq!(
+            parse_background("#ffffff, 000000"),
+            Ok(([1.0; 3], [0.0; 3]))
+        );
+        assert!(matches!(
+            parse_background("#ffffff"),
+            Err(ConfigError::BadBackground(..))
+        ));
+        assert!(matches!(
+            parse_background("#ffffff,#000000,#ff0000"),
+            Err(ConfigError::BadBackground(..))
+        ));
+        assert!(matches!(
+            parse_background("#ffffff,nope"),
+            Err(ConfigError::BadColor(..))
+        ));
+    }
+
+    #[test]
+    fn test_config() {
+        assert_eq!(Themes::from_config(None, None), Ok(Themes::default()));
+
+        let t = Themes::from_config(Some("theme = \"light\"\n"), None).unwrap();
+        assert_eq!(*t.theme(), Theme::light());
+
+        // The flag takes priority over the config file, and only replaces
+        // the starting theme's backdrop
+        let config = "theme = \"light\"\nbg = \"#ff0000,#00ff00\"\n";
+        let mut t = Themes::from_config(Some(config), Some("#0000ff,#000000")).unwrap();
+        assert_eq!(t.theme().top, [0.0, 0.0, 1.0]);
+        assert_eq!(t.theme().bottom, [0.0; 3]);
+        assert_eq!(t.theme().base, Theme::light().base);
+        t.toggle();
+        assert_eq!(*t.theme(), Theme::dark());
+
+        let t = Themes::from_config(Some(config), None).unwrap();
+        assert_eq!(t.theme().top, [1.0, 0.0, 0.0]);
+
+        assert!(matches!(
+            Themes::from_config(Some("theme = \"blue\""), None),
+            Err(ConfigError::BadTheme(..))
+        ));
+        assert!(matches!(
+            Themes::from_config(Some("colour = 1"), None),
+            Err(ConfigError::Parse(..))
+        ));
+        assert!(matches!(
+            Themes::from_config(None, Some("red,blue")),
+            Err(ConfigError::BadColor(..))
+        ));
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut t = Themes::default();
+        assert_eq!(*t.theme(), Theme::dark());
+        t.toggle();
+        assert_eq!(*t.theme(), Theme::light());
+        t.toggle();
+        assert_eq!(*t.theme(), Theme::dark());
+
+        // The light theme darkens the model for contrast
+        assert!(Theme::light().base[0] < Theme::dark().base[0]);
+    }
+
+    #[test]
+    fn test_backdrop_uniforms() {
+        let t = Theme {
+            top: [0.1, 0.2, 0.3],
+            bottom: [0.4, 0.5, 0.6],
+            base: [1.0; 3],
+        };
+        let u = t.backdrop_uniforms();
+        assert_eq!(u, [[0.1, 0.2, 0.3, 1.0], [0.4, 0.5, 0.6, 1.0]]);
+        // Two vec4<f32>, matching the `Colors` struct in backdrop.wgsl
+        assert_eq!(bytemuck::cast_slice::<_, u8>(&u).len(), 32);
+    }
+}
@@ -0,0 +1,357 @@
+use std::ops::Range;
+
+use glm::{DMat4, DVec3, Mat4, Vec2, Vec3, Vec4};
+use nalgebra_glm as glm;
+use triangulate::{load::ParseReport, mesh::Mesh, stats::Stats, units::LengthUnit};
+
+use crate::title::file_name;
+
+/// Gap between tiled models, as a fraction of the widest model
+const MARGIN: f64 = 0.2;
+
+/// Height of file name labels, in logical pixels
+pub const LABEL_SIZE: f32 = 12.0;
+
+/// Gap between a label and the top of its model, in logical pixels
+pub const LABEL_GAP: f32 = 6.0;
+
+/// One input file, after it has been tiled into the combined mesh
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slot {
+    /// File name, which is drawn as a label
+    pub name: String,
+    /// Path which the file was loaded from
+    pub path: String,
+    /// Range of parts in the combined mesh which came from this file
+    pub parts: Range<usize>,
+    /// Bounding box of the file's geometry after it was moved into place,
+    /// or `None` if it's empty
+    pub bounds: Option<(DVec3, DVec3)>,
+    /// Unit of length declared by the file, if any
+    pub unit: Option<LengthUnit>,
+    /// Number of entities in the file which could not be parsed
+    pub failed: usize,
+    /// Amount by which the file's STEP entity indices were shifted in the
+    /// combined mesh (see [`shift_ids`])
+    pub first_id: usize,
+}
+
+impl Slot {
+    /// Moves the slot's bounds along with its model, growing them to fit
+    /// the transformed box if it's rotated
+    pub fn transform(&mut self, mat: &DMat4) {
+        self.bounds = self.bounds.map(|(lo, hi)| {
+            let corners = (0..8).map(|i| {
+                let c = DVec3::new(
+                    if i & 1 == 0 { lo.x } else { hi.x },
+                    if i & 2 == 0 { lo.y } else { hi.y },
+                    if i & 4 == 0 { lo.z } else { hi.z },
+                );
+                (mat * c.push(1.0)).xyz()
+            });
+            let inf = DVec3::repeat(f64::INFINITY);
+            corners.fold((inf, -inf), |(lo, hi), p| (lo.inf(&p), hi.sup(&p)))
+        });
+    }
+}
+
+/// Returns the bounding box of every vertex in a mesh
+fn mesh_bounds(mesh: &Mesh) -> Option<(DVec3, DVec3)> {
+    let mut verts = mesh.verts.iter().map(|v| v.pos);
+    let first = verts.next()?;
+    Some(verts.fold((first, first), |(lo, hi), p| (lo.inf(&p), hi.sup(&p))))
+}
+
+/// Computes the offset which moves each model into place, so that they're
+/// laid out left-to-right along the X axis in order, separated by a margin
+/// which scales with the widest model.  Only X is changed, so models which
+/// share a coordinate system stay aligned in Y and Z.
+///
+/// Empty models take up no space.
+pub fn tile_offsets(bounds: &[Option<(DVec3, DVec3)>]) -> Vec<DVec3> {
+    let widest = bounds
+        .iter()
+        .flatten()
+        .map(|(lo, hi)| hi.x - lo.x)
+        .fold(0.0, f64::max);
+    let margin = widest * MARGIN;
+
+    let mut x = match bounds.iter().flatten().next() {
+        Some((lo, _)) => lo.x,
+        None => 0.0,
+    };
+    let mut out = vec![];
+    for b in bounds {
+        match b {
+            Some((lo, hi)) => {
+                out.push(DVec3::new(x - lo.x, 0.0, 0.0));
+                x += hi.x - lo.x + margin;
+            }
+            None => out.push(DVec3::zeros()),
+        }
+    }
+    out
+}
+
+/// Moves a mesh (and the transforms of its parts) by the given offset
+fn translate(mesh: &mut Mesh, offset: DVec3) {
+    for v in &mut mesh.verts {
+        v.pos += offset;
+    }
+    let t = glm::translation(&offset);
+    for p in &mut mesh.parts {
+        p.transform = t * p.transform;
+    }
+}
+
+/// Shifts the STEP entity indices in a mesh and its stats by `base`, so
+/// that they don't collide with those from other files.  Instancing groups
+/// parts by shape, and face colors are keyed by face, so collisions would
+/// mix up geometry from different files.
+///
+/// Returns one past the largest shifted index.
+pub fn shift_ids(mesh: &mut Mesh, stats: &mut Stats, base: usize) -> usize {
+    let mut end = base;
+    for p in &mut mesh.parts {
+        p.shape += base;
+        end = end.max(p.shape + 1);
+    }
+    for f in &mut mesh.faces {
+        f.id += base;
+        end = end.max(f.id + 1);
+    }
+    for g in &mut mesh.grids {
+        g.id += base;
+    }
+    stats.surface_types = std::mem::take(&mut stats.surface_types)
+        .into_iter()
+        .map(|(k, v)| (k + base, v))
+        .collect();
+    for d in &mut stats.diagnostics {
+        if let Some(e) = &mut d.entity {
+            *e += base;
+            end = end.max(*e + 1);
+        }
+    }
+    end.max(stats.surface_types.keys().max().map_or(0, |k| k + 1))
+}
+
+/// Lays out several meshes side by side, then combines them into a single
+/// mesh.  Each input is given as a path, mesh, stats, and parse report
+/// (which has the file's length unit).  Models aren't rescaled, so files
+/// with different units keep their own.
+pub fn tile(inputs: Vec<(String, Mesh, Stats, ParseReport)>) -> (Mesh, Stats, Vec<Slot>) {
+    let bounds: Vec<_> = inputs.iter().map(|(_, m, _, _)| mesh_bounds(m)).collect();
+    let offsets = tile_offsets(&bounds);
+
+    let mut mesh = Mesh::default();
+    let mut stats = Stats::default();
+    let mut slots = vec![];
+    let mut next_id = 0;
+    for (((path, mut m, mut s, parse), offset), b) in inputs.into_iter().zip(offsets).zip(bounds) {
+        translate(&mut m, offset);
+        let first_id = next_id;
+        next_id = shift_ids(&mut m, &mut s, next_id);
+        let start = mesh.parts.len();
+        mesh = Mesh::combine(mesh, m);
+        stats = Stats::combine(stats, s);
+        slots.push(Slot {
+            name: file_name(&path),
+            path,
+            parts: start..mesh.parts.len(),
+            bounds: b.map(|(lo, hi)| (lo + offset, hi + offset)),
+            unit: parse.length_unit,
+            failed: parse.failed,
+            first_id,
+        });
+    }
+    (mesh, stats, slots)
+}
+
+/// Returns where a slot's label should be drawn, in physical pixels from
+/// the top-left corner of the window:  centered horizontally on the
+/// projected bounding box, just above its top edge.
+///
+/// `mat` maps from model to clip space, and `size` is the window size in
+/// physical pixels.
+pub fn label_anchor(mat: &Mat4, size: Vec2, lo: DVec3, hi: DVec3) -> Vec2 {
+    let lo: Vec3 = glm::convert(lo);
+    let hi: Vec3 = glm::convert(hi);
+    let mut min = Vec2::repeat(f32::INFINITY);
+    let mut max = Vec2::repeat(-f32::INFINITY);
+    for i in 0..8 {
+        let pick = |bit: usize, lo: f32, hi: f32| if i & bit == 0 { lo } else { hi };
+        let p = mat
+            * Vec4::new(
+                pick(1, lo.x, hi.x),
+                pick(2, lo.y, hi.y),
+                pick(4, lo.z, hi.z),
+                1.0,
+            );
+        // Flip Y, since pixels count down from the top of the window
+        let q = Vec2::new(p.x / p.w + 1.0, 1.0 - p.y / p.w).component_mul(&size) / 2.0;
+        min = min.inf(&q);
+        max = max.sup(&q);
+    }
+    Vec2::new((min.x + max.x) / 2.0, min.y)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::U32Vec3;
+    use triangulate::{
+        mesh::{Part, Triangle, Vertex},
+        stats::Diagnostic,
+    };
+
+    fn cube(lo: DVec3, hi: DVec3) -> Mesh {
+        let mut mesh = Mesh::default();
+        for p in [lo, hi] {
+            mesh.verts.push(Vertex {
+                pos: p,
+                norm: DVec3::z(),
+                color: DVec3::zeros(),
+            });
+        }
+        mesh.triangles.push(Triangle {
+            verts: U32Vec3::new(0, 1, 1),
+        });
+        mesh.parts.push(Part {
+            shape: 0,
+            triangles: 0..1,
+            transform: DMat4::identity(),
+        });
+        mesh
+    }
+
+    #[test]
+    fn test_tile_offsets() {
+        // A narrow model, a wide one that starts far from the origin, and
+        // an empty one at the end
+        let bounds = [
+            Some((DVec3::new(0.0, 0.0, 0.0), DVec3::new(2.0, 1.0, 1.0))),
+            Some((DVec3::new(100.0, -5.0, 3.0), DVec3::new(110.0, 5.0, 4.0))),
+            Some((DVec3::new(-1.0, 0.0, 0.0), DVec3::new(1.0, 30.0, 1.0))),
+            None,
+        ];
+        let offsets = tile_offsets(&bounds);
+        assert_eq!(offsets.len(), 4);
+
+        // The margin is 20% of the widest model, which is 10 units
+        let placed: Vec<_> = bounds
+            .iter()
+            .zip(&offsets)
+            .filter_map(|(b, o)| b.map(|(lo, hi)| (lo.x + o.x, hi.x + o.x)))
+            .collect();
+        assert_eq!(placed, vec![(0.0, 2.0), (4.0, 14.0), (16.0, 18.0)]);
+
+        // Only X is changed, and empty models stay put
+        assert!(offsets.iter().all(|o| o.y == 0.0 && o.z == 0.0));
+        assert_eq!(offsets[3], DVec3::zeros());
+    }
+
+    #[test]
+    fn test_tile_offsets_single() {
+        // A single model is left where it is
+        let b = [Some((DVec3::new(5.0, 1.0, 2.0), DVec3::new(8.0, 3.0, 4.0)))];
+        assert_eq!(tile_offsets(&b), vec![DVec3::zeros()]);
+        assert_eq!(tile_offsets(&[None, None]), vec![DVec3::zeros(); 2]);
+        assert!(tile_offsets(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_tile() {
+        let a = cube(DVec3::zeros(), DVec3::new(10.0, 1.0, 1.0));
+        let b = cube(DVec3::new(-3.0, 0.0, 0.0), DVec3::new(-1.0, 1.0, 1.0));
+        let mut b_stats = Stats::default();
+        b_stats.diagnostics.push(Diagnostic {
+            level: log::Level::Warn,
+            entity: Some(0),
+            message: "Face #0 failed".to_owned(),
+        });
+        let (mesh, stats, slots) = tile(vec![
+            (
+                "a.step".to_owned(),
+                a,
+                Stats::default(),
+                ParseReport::default(),
+            ),
+            (
+                "models/b.step".to_owned(),
+                b,
+                b_stats,
+                ParseReport {
+                    entities: 10,
+                    failed: 2,
+                    length_unit: Some(LengthUnit::Inch),
+                },
+            ),
+        ]);
+        assert_eq!(mesh.parts.len(), 2);
+        assert_eq!(slots[0].parts, 0..1);
+        assert_eq!(slots[1].parts, 1..2);
+        assert_eq!(slots[1].name, "b.step");
+        assert_eq!(slots[1].path, "models/b.step");
+        assert_eq!(slots[1].first_id, 1);
+        assert_eq!(slots[1].unit, Some(LengthUnit::Inch));
+        assert_eq!(slots[1].failed, 2);
+
+        // The second model is moved to just past the first, and its part's
+        // transform is updated to match
+        let (lo, hi) = slots[1].bounds.unwrap();
+        assert_eq!(lo, DVec3::new(12.0, 0.0, 0.0));
+        assert_eq!(hi, DVec3::new(14.0, 1.0, 1.0));
+        assert_eq!(mesh.verts[2].pos, lo);
+        assert_eq!(
+            mesh.parts[1].transform.column(3),
+            glm::DVec4::new(15.0, 0.0, 0.0, 1.0)
+        );
+        assert_eq!(mesh_bounds(&mesh).map(|b| b.1.x), Some(14.0));
+
+        // Both models were built from shape 0, but aren't instances of
+        // each other
+        assert_eq!(mesh.parts[0].shape, 0);
+        assert_eq!(mesh.parts[1].shape, 1);
+
+        // Diagnostics are shifted along with the shapes that they're about
+        assert_eq!(stats.diagnostics[0].entity, Some(1));
+    }
+
+    #[test]
+    fn test_slot_transform() {
+        let mut slot = Slot {
+            name: "a.step".to_owned(),
+            path: "a.step".to_owned(),
+            parts: 0..1,
+            bounds: Some((DVec3::zeros(), DVec3::new(2.0, 1.0, 1.0))),
+            unit: None,
+            failed: 0,
+            first_id: 0,
+        };
+        let quarter = glm::rotation(std::f64::consts::FRAC_PI_2, &DVec3::z());
+        slot.transform(&(glm::translation(&DVec3::new(0.0, 0.0, 3.0)) * quarter));
+        let (lo, hi) = slot.bounds.unwrap();
+        assert!((lo - DVec3::new(-1.0, 0.0, 3.0)).norm() < 1e-12, "{:?}", lo);
+        assert!((hi - DVec3::new(0.0, 2.0, 4.0)).norm() < 1e-12, "{:?}", hi);
+
+        slot.bounds = None;
+        slot.transform(&quarter);
+        assert_eq!(slot.bounds, None);
+    }
+
+    #[test]
+    fn test_label_anchor() {
+        // With an identity matrix, clip space maps straight to the window
+        let size = Vec2::new(800.0, 600.0);
+        let p = label_anchor(
+            &Mat4::identity(),
+            size,
+            DVec3::new(-0.5, 0.0, 0.0),
+            DVec3::new(0.0, 0.5, 0.0),
+        );
+        assert_eq!(p, Vec2::new(300.0, 150.0));
+    }
+}
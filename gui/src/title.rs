@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use triangulate::mesh::Mesh;
+
+/// Longest error message shown in the title bar, in characters
+const MAX_ERROR_LEN: usize = 80;
+
+/// What the window title is describing
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    /// Still loading, with the fraction finished (if known)
+    Loading(Option<f32>),
+    Loaded {
+        parts: usize,
+        triangles: usize,
+        /// Curves which are drawn as wires
+        curves: usize,
+    },
+    Failed(String),
+}
+
+impl Status {
+    /// Describes a model which has finished loading
+    pub fn loaded(mesh: &Mesh) -> Self {
+        Status::Loaded {
+            parts: mesh.parts.len(),
+            triangles: mesh.triangles.len(),
+            curves: mesh.wires.len(),
+        }
+    }
+}
+
+/// Returns the file name of a path, for display
+pub fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// Formats a count with thousands separators
+fn count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Formats a count of something, e.g. "1 part" or "1,024 tris"
+pub fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        format!("1 {}", word)
+    } else {
+        format!("{} {}s", count(n), word)
+    }
+}
+
+/// Describes the loading status, e.g. "loading 43%"
+pub fn status_text(status: &Status) -> String {
+    match status {
+        Status::Loading(None) => "loading…".to_owned(),
+        Status::Loading(Some(p)) => format!("loading {:.0}%", p * 100.0),
+        // Drawings and sketches only have curves, so they're counted instead
+        Status::Loaded {
+            triangles: 0,
+            curves,
+            ..
+        } if *curves > 0 => plural(*curves, "curve"),
+        Status::Loaded {
+            parts, triangles, ..
+        } => {
+            format!("{}, {}", plural(*parts, "part"), plural(*triangles, "tri"))
+        }
+        Status::Failed(e) => {
+            // Only the first line fits, and even that may need trimming
+            let line = e.lines().next().unwrap_or("");
+            let mut msg: String = line.chars().take(MAX_ERROR_LEN).collect();
+            if msg.len() < line.len() {
+                msg.push('…');
+            }
+            format!("failed to load: {}", msg)
+        }
+    }
+}
+
+/// Builds the window title for a model with the given file name
+pub fn window_title(name: &str, status: &Status) -> String {
+    format!("{} — {} — Foxtrot", name, status_text(status))
+}
+
+/// Formats a size in bytes, e.g. "12.5 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut x = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while x >= 999.95 && unit + 1 < UNITS.len() {
+        x /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", x, UNITS[unit])
+}
+
+/// Appends the frame rate, present mode, and GPU memory used by the model
+/// to a title
+pub fn with_frame_rate(title: &str, fps: f64, mode: wgpu::PresentMode, memory: u64) -> String {
+    format!(
+        "{} ({:.0} fps, {:?}, {} on GPU)",
+        title,
+        fps,
+        mode,
+        format_bytes(memory)
+    )
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("models/part.step"), "part.step");
+        assert_eq!(file_name("part.step"), "part.step");
+        assert_eq!(file_name(".."), "..");
+    }
+
+    #[test]
+    fn test_count() {
+        assert_eq!(count(0), "0");
+        assert_eq!(count(999), "999");
+        assert_eq!(count(1000), "1,000");
+        assert_eq!(count(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn test_loading() {
+        assert_eq!(
+            window_title("a.step", &Status::Loading(None)),
+            "a.step — loading… — Foxtrot"
+        );
+        assert_eq!(
+            window_title("a.step", &Status::Loading(Some(0.426))),
+            "a.step — loading 43% — Foxtrot"
+        );
+        assert_eq!(
+            window_title("a.step", &Status::Loading(Some(1.0))),
+            "a.step — loading 100% — Foxtrot"
+        );
+    }
+
+    #[test]
+    fn test_loaded() {
+        let status = Status::Loaded {
+            parts: 12,
+            triangles: 48200,
+            curves: 3,
+        };
+        assert_eq!(
+            window_title("a.step", &status),
+            "a.step — 12 parts, 48,200 tris — Foxtrot"
+        );
+        let status = Status::Loaded {
+            parts: 1,
+            triangles: 1,
+            curves: 0,
+        };
+        assert_eq!(
+            window_title("a.step", &status),
+            "a.step — 1 part, 1 tri — Foxtrot"
+        );
+
+        // Models with only curves count those instead
+        let status = Status::Loaded {
+            parts: 0,
+            triangles: 0,
+            curves: 6,
+        };
+        assert_eq!(
+            window_title("a.step", &status),
+            "a.step — 6 curves — Foxtrot"
+        );
+    }
+
+    #[test]
+    fn test_failed() {
+        let status = Status::Failed("No such file\nmore details".to_owned());
+        assert_eq!(
+            window_title("a.step", &status),
+            "a.step — failed to load: No such file — Foxtrot"
+        );
+
+        // Long messages are trimmed
+        let status = Status::Failed("x".repeat(200));
+        let title = window_title("a.step", &status);
+        assert!(title.contains(&format!("{}…", "x".repeat(MAX_ERROR_LEN))));
+        assert!(!title.contains(&"x".repeat(MAX_ERROR_LEN + 1)));
+    }
+
+    #[test]
+    fn test_frame_rate() {
+        assert_eq!(
+            with_frame_rate(
+                "a.step — 1 part, 1 tri — Foxtrot",
+                59.7,
+                wgpu::PresentMode::Fifo,
+                12_345_678
+            ),
+            "a.step — 1 part, 1 tri — Foxtrot (60 fps, Fifo, 12.3 MB on GPU)"
+        );
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1500), "1.5 KB");
+        assert_eq!(format_bytes(999_999), "1.0 MB");
+        assert_eq!(format_bytes(3_200_000_000), "3.2 GB");
+    }
+}
@@ -0,0 +1,251 @@
+use std::ops::Range;
+
+use nalgebra_glm::DVec3;
+use triangulate::mesh::Mesh;
+
+/// Tracks which parts of the model are currently shown
+#[derive(Clone, Debug)]
+pub struct Visibility {
+    visible: Vec<bool>,
+}
+
+impl Visibility {
+    /// Builds a new visibility set with `n` parts, all of which are visible
+    pub fn new(n: usize) -> Self {
+        Self {
+            visible: vec![true; n],
+        }
+    }
+
+    pub fn is_visible(&self, part: usize) -> bool {
+        self.visible.get(part).copied().unwrap_or(false)
+    }
+
+    /// Returns a slice with one flag per part, for use when drawing
+    pub fn as_slice(&self) -> &[bool] {
+        &self.visible
+    }
+
+    pub fn hide(&mut self, part: usize) {
+        if let Some(v) = self.visible.get_mut(part) {
+            *v = false;
+        }
+    }
+
+    /// Hides every part except the given one
+    pub fn isolate(&mut self, part: usize) {
+        if part < self.visible.len() {
+            for (i, v) in self.visible.iter_mut().enumerate() {
+                *v = i == part;
+            }
+        }
+    }
+
+    /// Hides a range of parts if any of them are visible, or shows them
+    /// all otherwise
+    pub fn toggle_range(&mut self, parts: Range<usize>) {
+        if let Some(vs) = self.visible.get_mut(parts) {
+            let show = !vs.iter().any(|v| *v);
+            vs.iter_mut().for_each(|v| *v = show);
+        }
+    }
+
+    /// Hides a set of parts if any of them are visible, or shows them all
+    /// otherwise, as in [`Visibility::toggle_range`]
+    pub fn toggle_parts(&mut self, parts: &[usize]) {
+        let parts: Vec<usize> = parts
+            .iter()
+            .copied()
+            .filter(|i| *i < self.visible.len())
+            .collect();
+        let show = !parts.iter().any(|i| self.visible[*i]);
+        for i in parts {
+            self.visible[i] = show;
+        }
+    }
+
+    pub fn unhide_all(&mut self) {
+        self.visible.iter_mut().for_each(|v| *v = true);
+    }
+}
+
+/// Returns the axis-aligned bounding box of every vertex used by a visible
+/// part (and every wire, since wires don't belong to parts), or `None` if
+/// nothing is visible.
+///
+/// Meshes without part information are treated as a single visible part.
+pub fn visible_bounds(mesh: &Mesh, vis: &Visibility) -> Option<(DVec3, DVec3)> {
+    let mut lo = DVec3::repeat(f64::INFINITY);
+    let mut hi = DVec3::repeat(-f64::INFINITY);
+    let mut found = false;
+    let mut add_triangles = |tris: &[triangulate::mesh::Triangle]| {
+        for t in tris {
+            for i in t.verts.iter() {
+                let p = mesh.verts[*i as usize].pos;
+                lo = lo.inf(&p);
+                hi = hi.sup(&p);
+                found = true;
+            }
+        }
+    };
+    if mesh.parts.is_empty() {
+        add_triangles(&mesh.triangles);
+    } else {
+        for (i, part) in mesh.parts.iter().enumerate() {
+            if vis.is_visible(i) {
+                add_triangles(&mesh.triangles[part.triangles.clone()]);
+            }
+        }
+    }
+    for p in mesh.wires.iter().flat_map(|w| w.points.iter()) {
+        lo = lo.inf(p);
+        hi = hi.sup(p);
+        found = true;
+    }
+    if found {
+        Some((lo, hi))
+    } else {
+        None
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{DMat4, U32Vec3};
+    use triangulate::mesh::{Part, Triangle, Vertex};
+
+    fn visible_count(v: &Visibility) -> usize {
+        v.as_slice().iter().filter(|v| **v).count()
+    }
+
+    #[test]
+    fn test_transitions() {
+        let mut v = Visibility::new(4);
+        assert_eq!(visible_count(&v), 4);
+
+        v.hide(1);
+        assert!(!v.is_visible(1));
+        assert_eq!(visible_count(&v), 3);
+
+        // Hiding twice is harmless, as is hiding a part that doesn't exist
+        v.hide(1);
+        v.hide(10);
+        assert_eq!(visible_count(&v), 3);
+
+        v.isolate(2);
+        assert_eq!(v.as_slice(), &[false, false, true, false]);
+
+        // Isolating a hidden part shows it
+        v.isolate(1);
+        assert_eq!(v.as_slice(), &[false, true, false, false]);
+
+        // Isolating an invalid part does nothing
+        v.isolate(7);
+        assert_eq!(v.as_slice(), &[false, true, false, false]);
+
+        v.unhide_all();
+        assert_eq!(visible_count(&v), 4);
+        assert!(!v.is_visible(4));
+    }
+
+    #[test]
+    fn test_toggle_range() {
+        let mut v = Visibility::new(5);
+        v.toggle_range(1..3);
+        assert_eq!(v.as_slice(), &[true, false, false, true, true]);
+        v.toggle_range(1..3);
+        assert_eq!(visible_count(&v), 5);
+
+        // A partly-visible range is hidden first
+        v.hide(3);
+        v.toggle_range(2..5);
+        assert_eq!(v.as_slice(), &[true, true, false, false, false]);
+
+        // Out-of-range parts are ignored
+        v.toggle_range(4..9);
+        assert_eq!(v.as_slice(), &[true, true, false, false, false]);
+    }
+
+    #[test]
+    fn test_toggle_parts() {
+        let mut v = Visibility::new(5);
+        v.toggle_parts(&[0, 3]);
+        assert_eq!(v.as_slice(), &[false, true, true, false, true]);
+        v.toggle_parts(&[3, 0]);
+        assert_eq!(visible_count(&v), 5);
+
+        // Parts are hidden if any are visible, and missing parts are ignored
+        v.hide(1);
+        v.toggle_parts(&[1, 2, 8]);
+        assert_eq!(v.as_slice(), &[true, false, false, true, true]);
+        v.toggle_parts(&[]);
+        assert_eq!(visible_count(&v), 3);
+    }
+
+    /// Builds a mesh with one single-triangle part per origin
+    fn mesh_with_parts(origins: &[DVec3]) -> Mesh {
+        let mut mesh = Mesh::default();
+        for (i, o) in origins.iter().enumerate() {
+            for d in [DVec3::zeros(), DVec3::x(), DVec3::y()] {
+                mesh.verts.push(Vertex {
+                    pos: o + d,
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+            let j = 3 * i as u32;
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(j, j + 1, j + 2),
+            });
+            mesh.parts.push(Part {
+                shape: i,
+                triangles: i..(i + 1),
+                transform: DMat4::identity(),
+            });
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_visible_bounds() {
+        let mesh = mesh_with_parts(&[
+            DVec3::new(0.0, 0.0, 0.0),
+            DVec3::new(10.0, 0.0, 0.0),
+            DVec3::new(0.0, -5.0, 3.0),
+        ]);
+        let mut vis = Visibility::new(3);
+        assert_eq!(
+            visible_bounds(&mesh, &vis),
+            Some((DVec3::new(0.0, -5.0, 0.0), DVec3::new(11.0, 1.0, 3.0)))
+        );
+
+        vis.hide(1);
+        assert_eq!(
+            visible_bounds(&mesh, &vis),
+            Some((DVec3::new(0.0, -5.0, 0.0), DVec3::new(1.0, 1.0, 3.0)))
+        );
+
+        vis.isolate(1);
+        assert_eq!(
+            visible_bounds(&mesh, &vis),
+            Some((DVec3::new(10.0, 0.0, 0.0), DVec3::new(11.0, 1.0, 0.0)))
+        );
+
+        vis.hide(1);
+        assert_eq!(visible_bounds(&mesh, &vis), None);
+
+        // Wires are always visible
+        let mut mesh = mesh;
+        mesh.wires.push(triangulate::mesh::Wire {
+            id: 0,
+            points: vec![DVec3::new(-1.0, 0.0, 0.0), DVec3::new(-2.0, 0.0, 0.0)],
+        });
+        assert_eq!(
+            visible_bounds(&mesh, &vis),
+            Some((DVec3::new(-2.0, 0.0, 0.0), DVec3::new(-1.0, 0.0, 0.0)))
+        );
+    }
+}
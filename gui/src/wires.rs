@@ -0,0 +1,108 @@
+use nalgebra_glm as glm;
+
+use triangulate::mesh::Mesh;
+
+use crate::{
+    camera::Camera,
+    lines::{LineStyle, LineTarget, Lines, Segment},
+};
+
+/// Color of curves from wireframe and surface models
+const WIRE_COLOR: [f32; 4] = [0.25, 0.6, 1.0, 1.0];
+
+/// Width of wires, in logical pixels
+const WIRE_WIDTH: f32 = 1.5;
+
+/// Splits every wire in the mesh into segments between its points
+pub fn wire_segments(mesh: &Mesh) -> Vec<Segment> {
+    mesh.wires
+        .iter()
+        .flat_map(|w| w.points.windows(2))
+        .map(|w| Segment::new(glm::convert(w[0]), glm::convert(w[1]), WIRE_COLOR))
+        .collect()
+}
+
+/// Draws the mesh's wires, which are curves that weren't triangulated (e.g.
+/// from a wireframe drawing).  Wires don't belong to any part, so they're
+/// always drawn.
+pub struct Wires {
+    lines: Lines,
+}
+
+impl Wires {
+    /// Builds the wires for a mesh, returning `None` if it has none
+    pub fn new(
+        device: &wgpu::Device,
+        swapchain_format: wgpu::TextureFormat,
+        mesh: &Mesh,
+    ) -> Option<Self> {
+        let segments = wire_segments(mesh);
+        if segments.is_empty() {
+            return None;
+        }
+        // Wires are hidden behind the model, as with normal lines
+        let lines = Lines::new(
+            device,
+            swapchain_format,
+            &segments,
+            Some(wgpu::CompareFunction::GreaterEqual),
+        );
+        Some(Wires { lines })
+    }
+
+    pub fn draw(
+        &self,
+        camera: &Camera,
+        queue: &wgpu::Queue,
+        target: &LineTarget,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        self.lines.set_uniforms(
+            queue,
+            &camera.mat(),
+            camera.size(),
+            &LineStyle {
+                clip: target.clip,
+                ..LineStyle::new(WIRE_WIDTH)
+            },
+            target.scale_factor,
+        );
+        self.lines.draw(
+            target,
+            None,
+            std::iter::once(0..self.lines.count()),
+            encoder,
+        );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::DVec3;
+    use triangulate::mesh::Wire;
+
+    #[test]
+    fn test_wire_segments() {
+        let mut mesh = Mesh::default();
+        assert!(wire_segments(&mesh).is_empty());
+
+        let p = |x: f64| DVec3::new(x, 0.0, 0.0);
+        mesh.wires.push(Wire {
+            id: 1,
+            points: vec![p(0.0), p(1.0), p(2.0)],
+        });
+        mesh.wires.push(Wire {
+            id: 2,
+            points: vec![p(5.0), p(6.0)],
+        });
+        let ends: Vec<_> = wire_segments(&mesh)
+            .iter()
+            .map(|s| (s.a[0], s.b[0]))
+            .collect();
+        // Segments never bridge the gap between two wires
+        assert_eq!(ends, vec![(0.0, 1.0), (1.0, 2.0), (5.0, 6.0)]);
+    }
+}
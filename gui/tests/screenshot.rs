@@ -0,0 +1,37 @@
+use std::{path::Path, process::Command};
+
+fn screenshot(input: &Path, output: &Path) -> Vec<u8> {
+    let status = Command::new(env!("CARGO_BIN_EXE_gui"))
+        .arg(input)
+        .args([
+            "--software",
+            "--no-cache",
+            "--camera",
+            "iso",
+            "--zoom",
+            "1.2",
+        ])
+        .args(["--hide-backdrop", "--headless", "320x240", "--screenshot"])
+        .arg(output)
+        .status()
+        .expect("Could not run the viewer");
+    assert!(status.success(), "Viewer exited with {}", status);
+    std::fs::read(output).expect("Could not read screenshot")
+}
+
+/// Draws the same model twice, offscreen with a software adapter (so that
+/// no display or GPU is needed), and checks that the PNGs are identical
+#[test]
+fn test_screenshot_is_reproducible() {
+    let dir = std::env::temp_dir().join(format!("foxtrot-screenshot-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("cube.step");
+    std::fs::write(&input, step::fixture::cube(1.0).to_step()).unwrap();
+
+    let a = screenshot(&input, &dir.join("a.png"));
+    let b = screenshot(&input, &dir.join("b.png"));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(a[..8], *b"\x89PNG\r\n\x1a\n");
+    assert!(a == b, "Screenshots differ");
+}
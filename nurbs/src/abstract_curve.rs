@@ -1,4 +1,27 @@
-use nalgebra_glm::DVec3;
+use nalgebra_glm::{length, DVec3};
+
+/// Five-point Gauss-Legendre nodes and weights on [-1, 1]
+const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+    (0.0, 0.568_888_888_888_888_9),
+    (-0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+    (0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+    (-0.906_179_845_938_664, 0.236_926_885_056_189_1),
+    (0.906_179_845_938_664, 0.236_926_885_056_189_1),
+];
+
+/// Relative error at which adaptive quadrature stops subdividing
+const QUADRATURE_TOLERANCE: f64 = 1e-10;
+
+/// Maximum depth of subdivision in adaptive quadrature
+const QUADRATURE_DEPTH: usize = 24;
+
+/// Number of segments which a range starts out with when tessellating, so
+/// that a curve whose midpoint happens to land on its chord is still split
+const TESSELLATION_SEGMENTS: usize = 4;
+
+/// Maximum depth of subdivision when tessellating, which bounds each range
+/// to 4 * 2^12 segments on degenerate curves or tiny tolerances
+const TESSELLATION_DEPTH: u32 = 12;
 
 /// Trait for a curve which maps from 1D to 3D.
 ///
@@ -8,4 +31,325 @@ use nalgebra_glm::DVec3;
 pub trait AbstractCurve {
     fn point(&self, u: f64) -> DVec3;
     fn derivatives<const E: usize>(&self, u: f64) -> Vec<DVec3>;
+
+    /// Returns the range of the curve's parameter
+    fn domain(&self) -> (f64, f64);
+
+    /// Returns the length of the curve between parameters `a` and `b`,
+    /// which is negative if `b < a`.
+    ///
+    /// The speed of the curve is integrated with adaptive Gauss-Legendre
+    /// quadrature, which splits the range until halves agree with the
+    /// whole, so kinks at knots are narrowed down rather than smoothed over.
+    fn arc_length(&self, a: f64, b: f64) -> f64 {
+        if b < a {
+            return -self.arc_length(b, a);
+        }
+        let speed = |u: f64| length(&self.derivatives::<1>(u)[1]);
+        let whole = gauss_legendre(&speed, a, b);
+        adaptive_length(&speed, a, b, whole, QUADRATURE_DEPTH)
+    }
+
+    /// Finds the parameter at which the curve is `s` long, measured from
+    /// the start of its domain, to within `tol` of arc length.
+    ///
+    /// Lengths outside of the curve are clamped to its ends.  The parameter
+    /// is found by bisection, which is slower than Newton's method but
+    /// can't be thrown off by places where the curve stops moving.
+    fn arc_length_param(&self, s: f64, tol: f64) -> f64 {
+        let (mut lo, mut hi) = self.domain();
+        if s <= 0.0 {
+            return lo;
+        }
+        // Length from the start of the domain to `lo`
+        let mut at_lo = 0.0;
+        let total = self.arc_length(lo, hi);
+        if s >= total {
+            return hi;
+        }
+        loop {
+            let mid = (lo + hi) / 2.0;
+            if mid <= lo || mid >= hi {
+                return mid;
+            }
+            let at_mid = at_lo + self.arc_length(lo, mid);
+            if (at_mid - s).abs() <= tol {
+                return mid;
+            } else if at_mid < s {
+                lo = mid;
+                at_lo = at_mid;
+            } else {
+                hi = mid;
+            }
+        }
+    }
+
+    /// Returns the curvature at `u`, which is `|C' × C''| / |C'|³`, or zero
+    /// where the curve stops moving (so the curvature isn't defined)
+    fn curvature(&self, u: f64) -> f64 {
+        let d = self.derivatives::<2>(u);
+        let speed = d[1].norm();
+        if speed.is_nan() || speed <= 0.0 {
+            return 0.0;
+        }
+        let k = d[1].cross(&d[2]).norm() / speed.powi(3);
+        if k.is_finite() {
+            k
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the torsion at `u`, which is `(C' × C'') · C''' / |C' × C''|²`,
+    /// or zero where the curve is straight or stops moving (so the
+    /// osculating plane isn't defined)
+    fn torsion(&self, u: f64) -> f64 {
+        let d = self.derivatives::<3>(u);
+        let c = d[1].cross(&d[2]);
+        let norm = c.norm_squared();
+        // Relative to the derivatives, as in the surface normal, so that the
+        // test doesn't depend on the curve's scale or parameterization
+        let scale = d[1].norm_squared() * d[2].norm_squared();
+        if norm.is_nan() || norm <= f64::EPSILON * scale {
+            return 0.0;
+        }
+        let t = c.dot(&d[3]) / norm;
+        if t.is_finite() {
+            t
+        } else {
+            0.0
+        }
+    }
+
+    /// Samples the whole curve as a polyline, as in
+    /// [`tessellate_range`](Self::tessellate_range)
+    fn tessellate(&self, chord_tol: f64) -> Vec<DVec3> {
+        let (a, b) = self.domain();
+        self.tessellate_range(a, b, chord_tol)
+    }
+
+    /// Samples the curve from `t0` to `t1` as a polyline, including both
+    /// ends.
+    ///
+    /// Segments are split in half until the curve's midpoint is within
+    /// `chord_tol` of the segment's midpoint, or until they've been split
+    /// 12 times.
+    fn tessellate_range(&self, t0: f64, t1: f64, chord_tol: f64) -> Vec<DVec3> {
+        let mut prev = (t0, self.point(t0));
+        let mut out = vec![prev.1];
+        for i in 1..=TESSELLATION_SEGMENTS {
+            let t = t0 + (t1 - t0) * i as f64 / TESSELLATION_SEGMENTS as f64;
+            let next = (t, self.point(t));
+            subdivide(self, prev, next, chord_tol, 0, &mut out);
+            prev = next;
+        }
+        out
+    }
+}
+
+/// Recursively splits the segment from `a` to `b` (each of which is a
+/// parameter and its point), pushing every point after `a`
+fn subdivide<C: AbstractCurve + ?Sized>(
+    c: &C,
+    a: (f64, DVec3),
+    b: (f64, DVec3),
+    chord_tol: f64,
+    depth: u32,
+    out: &mut Vec<DVec3>,
+) {
+    let t = (a.0 + b.0) / 2.0;
+    let p = c.point(t);
+    if depth < TESSELLATION_DEPTH && length(&(p - (a.1 + b.1) / 2.0)) > chord_tol {
+        subdivide(c, a, (t, p), chord_tol, depth + 1, out);
+        subdivide(c, (t, p), b, chord_tol, depth + 1, out);
+    } else {
+        out.push(b.1);
+    }
+}
+
+/// Integrates a function over `[a, b]` with five-point Gauss-Legendre
+/// quadrature
+fn gauss_legendre<F: Fn(f64) -> f64>(f: &F, a: f64, b: f64) -> f64 {
+    let (mid, half) = ((a + b) / 2.0, (b - a) / 2.0);
+    half * GAUSS_LEGENDRE
+        .iter()
+        .map(|(x, w)| w * f(mid + half * x))
+        .sum::<f64>()
+}
+
+/// Refines `whole` (the integral over `[a, b]`) by splitting the range in
+/// half until both halves add up to it
+fn adaptive_length<F: Fn(f64) -> f64>(f: &F, a: f64, b: f64, whole: f64, depth: usize) -> f64 {
+    let mid = (a + b) / 2.0;
+    let (left, right) = (gauss_legendre(f, a, mid), gauss_legendre(f, mid, b));
+    let sum = left + right;
+    if depth == 0 || (sum - whole).abs() <= QUADRATURE_TOLERANCE * sum.abs().max(f64::MIN_POSITIVE)
+    {
+        sum
+    } else {
+        adaptive_length(f, a, mid, left, depth - 1) + adaptive_length(f, mid, b, right, depth - 1)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BsplineCurve, KnotVector, NurbsCurve};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_arc_length() {
+        // A rational arc, whose speed isn't constant in its parameter
+        let arc = NurbsCurve::arc(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0, 0.0, 1.5 * PI);
+        assert!((arc.arc_length(0.0, 1.0) - 3.0 * PI).abs() < 1e-9);
+        assert!((arc.arc_length(1.0, 0.0) + 3.0 * PI).abs() < 1e-9);
+        assert_eq!(arc.arc_length(0.5, 0.5), 0.0);
+
+        // A polyline with a kink at its middle knot
+        let knots = KnotVector::from_multiplicities(1, &[0.0, 0.25, 1.0], &[2, 1, 2]).unwrap();
+        let line = BsplineCurve::new(
+            false,
+            knots,
+            vec![
+                DVec3::zeros(),
+                DVec3::new(3.0, 0.0, 0.0),
+                DVec3::new(3.0, 4.0, 0.0),
+            ],
+        )
+        .unwrap();
+        assert!((line.arc_length(0.0, 1.0) - 7.0).abs() < 1e-9);
+        // A kink which isn't on a subdivision is only resolved down to the
+        // smallest one
+        assert!((line.arc_length(0.1, 0.625) - 3.8).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_arc_length_param() {
+        let arc = NurbsCurve::arc(DVec3::zeros(), DVec3::x(), DVec3::y(), 1.0, 0.0, PI);
+        for i in 0..=10 {
+            let s = PI * i as f64 / 10.0;
+            let u = arc.arc_length_param(s, 1e-9);
+            // On a unit circle, arc length is the angle
+            let p = arc.point(u);
+            assert!((p.y.atan2(p.x) - s).abs() < 1e-6, "{} {:?}", s, p);
+        }
+        assert_eq!(arc.arc_length_param(-1.0, 1e-9), 0.0);
+        assert_eq!(arc.arc_length_param(10.0, 1e-9), 1.0);
+
+        // Evenly spaced lengths give evenly spaced points, even where the
+        // parameter speeds up
+        let knots = KnotVector::from_multiplicities(1, &[0.0, 0.25, 1.0], &[2, 1, 2]).unwrap();
+        let line = BsplineCurve::new(
+            false,
+            knots,
+            vec![
+                DVec3::zeros(),
+                DVec3::new(3.0, 0.0, 0.0),
+                DVec3::new(3.0, 4.0, 0.0),
+            ],
+        )
+        .unwrap();
+        let u = line.arc_length_param(5.0, 1e-9);
+        assert!((line.point(u) - DVec3::new(3.0, 2.0, 0.0)).norm() < 1e-6);
+    }
+
+    /// A helix of radius `r` around the Z axis, which rises by `2π c` each
+    /// turn, with exact derivatives
+    struct Helix {
+        r: f64,
+        c: f64,
+    }
+    impl AbstractCurve for Helix {
+        fn point(&self, u: f64) -> DVec3 {
+            DVec3::new(self.r * u.cos(), self.r * u.sin(), self.c * u)
+        }
+        fn derivatives<const E: usize>(&self, u: f64) -> Vec<DVec3> {
+            (0..=E)
+                .map(|k| {
+                    let a = u + k as f64 * PI / 2.0;
+                    let z = match k {
+                        0 => self.c * u,
+                        1 => self.c,
+                        _ => 0.0,
+                    };
+                    DVec3::new(self.r * a.cos(), self.r * a.sin(), z)
+                })
+                .collect()
+        }
+        fn domain(&self) -> (f64, f64) {
+            (0.0, 4.0 * PI)
+        }
+    }
+
+    #[test]
+    fn test_curvature() {
+        // A rational circle, whose speed isn't constant in its parameter
+        let circle = NurbsCurve::circle(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0);
+        for i in 0..=10 {
+            let u = i as f64 / 10.0;
+            assert!(
+                (circle.curvature(u) - 0.5).abs() < 1e-9,
+                "{}",
+                circle.curvature(u)
+            );
+            assert!(circle.torsion(u).abs() < 1e-9, "{}", circle.torsion(u));
+        }
+
+        // A helix has constant curvature r / (r² + c²) and torsion
+        // c / (r² + c²), which is negative if it's left-handed
+        for (r, c) in [(1.0, 0.5), (3.0, 2.0), (2.0, -1.0)] {
+            let helix = Helix { r, c };
+            let denom = r * r + c * c;
+            for u in [0.0, 1.0, 5.0] {
+                assert!((helix.curvature(u) - r / denom).abs() < 1e-12);
+                assert!((helix.torsion(u) - c / denom).abs() < 1e-12);
+            }
+        }
+
+        // Straight lines have neither, even where they stop moving
+        let knots = KnotVector::from_multiplicities(2, &[0.0, 1.0], &[3, 3]).unwrap();
+        let line = BsplineCurve::new(
+            false,
+            knots,
+            vec![DVec3::zeros(), DVec3::zeros(), DVec3::x()],
+        )
+        .unwrap();
+        for u in [0.0, 0.5, 1.0] {
+            assert_eq!(line.curvature(u), 0.0);
+            assert_eq!(line.torsion(u), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_tessellate() {
+        let circle = NurbsCurve::circle(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0);
+        for tol in [0.1, 1e-3, 1e-5] {
+            let pts = circle.tessellate(tol);
+            assert_eq!(pts[0], circle.point(0.0));
+            assert_eq!(pts[pts.len() - 1], circle.point(1.0));
+            // Every point is on the circle, and the sagitta of each segment
+            // (which is the furthest that the circle gets from it) is small
+            for w in pts.windows(2) {
+                assert!((w[0].norm() - 2.0).abs() < 1e-12);
+                let mid = (w[0] + w[1]) / 2.0;
+                assert!(2.0 - mid.norm() <= tol, "{}", 2.0 - mid.norm());
+            }
+        }
+        assert!(circle.tessellate(1e-3).len() > circle.tessellate(0.1).len());
+
+        // Straight lines aren't split past their first few segments, and
+        // ranges can run backwards
+        let knots = KnotVector::from_multiplicities(1, &[0.0, 1.0], &[2, 2]).unwrap();
+        let line = BsplineCurve::new(false, knots, vec![DVec3::zeros(), DVec3::x()]).unwrap();
+        assert_eq!(line.tessellate(1e-9).len(), TESSELLATION_SEGMENTS + 1);
+        let pts = line.tessellate_range(0.75, 0.25, 1e-9);
+        assert_eq!(pts.first(), Some(&DVec3::new(0.75, 0.0, 0.0)));
+        assert_eq!(pts.last(), Some(&DVec3::new(0.25, 0.0, 0.0)));
+
+        // A negative tolerance splits as deeply as it can, but no further
+        let n = circle.tessellate(-1.0).len();
+        assert_eq!(n, TESSELLATION_SEGMENTS * (1 << TESSELLATION_DEPTH) + 1);
+    }
 }
@@ -1,6 +1,17 @@
+use std::collections::HashMap;
+
 use crate::VecF;
 use nalgebra_glm::{DVec2, DVec3};
 
+/// Number of cells along each side of the grid which tessellation starts
+/// from, so that a surface whose midpoints happen to land on their chords is
+/// still split
+const TESSELLATION_SEGMENTS: usize = 4;
+
+/// Maximum depth of subdivision when tessellating, which bounds the grid to
+/// 4 * 2^8 cells along each side on degenerate surfaces or tiny tolerances
+const TESSELLATION_DEPTH: u32 = 8;
+
 /// Trait for a curve which maps from 2D (uv) to 3D.
 ///
 /// This trait is implement for both Bezier and NURBS surfaces, and abstracts
@@ -14,4 +25,315 @@ pub trait AbstractSurface {
     fn point_from_basis(&self, uspan: usize, Nu: &VecF, vspan: usize, Nv: &VecF) -> DVec3;
 
     fn derivatives<const E: usize>(&self, uv: DVec2) -> Vec<Vec<DVec3>>;
+
+    /// Returns the axis-aligned bounding box (as min and max corners) of the
+    /// control points.  By the convex hull property, this contains the
+    /// entire surface.
+    fn control_bounds(&self) -> (DVec3, DVec3);
+
+    /// Returns the range of the surface's parameters, as min and max corners
+    fn domain(&self) -> (DVec2, DVec2);
+
+    /// Returns the unit normal at a point on the surface, as the cross
+    /// product of its partial derivatives.
+    ///
+    /// Where the normal isn't defined (at a pole, or on a patch which
+    /// collapses to a curve or a point), this returns zero instead of NaN.
+    fn normal(&self, uv: DVec2) -> DVec3 {
+        let d = self.derivatives::<1>(uv);
+        unit_normal(d[1][0], d[0][1]).unwrap_or_else(DVec3::zeros)
+    }
+
+    /// Returns the Gaussian and mean curvature at a point on the surface,
+    /// from its first and second fundamental forms.
+    ///
+    /// The mean curvature is positive where the surface bends towards its
+    /// [`normal`](Self::normal), so its sign depends on the direction of
+    /// the parameters.  Where the normal isn't defined, this returns zeros.
+    fn curvature(&self, uv: DVec2) -> (f64, f64) {
+        let d = self.derivatives::<2>(uv);
+        let (su, sv) = (d[1][0], d[0][1]);
+        let Some(n) = unit_normal(su, sv) else {
+            return (0.0, 0.0);
+        };
+        let (e, f, g) = (su.dot(&su), su.dot(&sv), sv.dot(&sv));
+        let (l, m, n) = (d[2][0].dot(&n), d[1][1].dot(&n), d[0][2].dot(&n));
+        let det = e * g - f * f;
+        let gaussian = (l * n - m * m) / det;
+        let mean = (e * n + g * l - 2.0 * f * m) / (2.0 * det);
+        (gaussian, mean)
+    }
+
+    /// Tessellates the whole surface into a triangle soup, returning its
+    /// vertices and triangles (as indices into the vertices).
+    ///
+    /// The domain starts out as a grid of quads, each of which is split into
+    /// four until the surface at the midpoints of its edges and diagonals is
+    /// within `chord_tol` of the chords between their ends, or until it's
+    /// been split 8 times.  Quads which border smaller quads are fanned
+    /// around their centers, so that the mesh doesn't crack where it changes
+    /// depth.  Triangles wind counter-clockwise around
+    /// [`normal`](Self::normal), and are degenerate where an edge of the
+    /// domain collapses to a point (like a pole).
+    fn tessellate(&self, chord_tol: f64) -> (Vec<DVec3>, Vec<[usize; 3]>) {
+        let (lo, hi) = self.domain();
+        let n = TESSELLATION_SEGMENTS;
+        let split = |a: f64, b: f64, i: usize| a + (b - a) * i as f64 / n as f64;
+        let mut cells = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                let u = (split(lo.x, hi.x, i), split(lo.x, hi.x, i + 1));
+                let v = (split(lo.y, hi.y, j), split(lo.y, hi.y, j + 1));
+                subdivide_quad(self, u, v, chord_tol, 0, &mut cells);
+            }
+        }
+        triangulate_quads(self, &cells)
+    }
+}
+
+/// Parameter ranges (in `u` then `v`) of a quad in a surface's domain
+type Quad = ((f64, f64), (f64, f64));
+
+/// Recursively splits a quad into four, pushing the ones which aren't split
+/// any further
+fn subdivide_quad<S: AbstractSurface + ?Sized>(
+    s: &S,
+    u: (f64, f64),
+    v: (f64, f64),
+    chord_tol: f64,
+    depth: u32,
+    out: &mut Vec<Quad>,
+) {
+    let (um, vm) = ((u.0 + u.1) / 2.0, (v.0 + v.1) / 2.0);
+    let p = |u, v| s.point(DVec2::new(u, v));
+    let (p00, p10, p01, p11) = (p(u.0, v.0), p(u.1, v.0), p(u.0, v.1), p(u.1, v.1));
+    let center = p(um, vm);
+    // Each midpoint on the surface, with the ends of its chord
+    let far = [
+        (p(um, v.0), p00, p10),
+        (p(um, v.1), p01, p11),
+        (p(u.0, vm), p00, p01),
+        (p(u.1, vm), p10, p11),
+        (center, p00, p11),
+        (center, p10, p01),
+    ]
+    .iter()
+    .any(|(m, a, b)| (m - (a + b) / 2.0).norm() > chord_tol);
+    if depth < TESSELLATION_DEPTH && far {
+        for u in [(u.0, um), (um, u.1)] {
+            for v in [(v.0, vm), (vm, v.1)] {
+                subdivide_quad(s, u, v, chord_tol, depth + 1, out);
+            }
+        }
+    } else {
+        out.push((u, v));
+    }
+}
+
+/// Triangulates a set of quads which tile a surface's domain, sharing
+/// vertices between neighbors.  A quad whose edges pass through corners of
+/// its neighbors is fanned around its center to include them.
+fn triangulate_quads<S: AbstractSurface + ?Sized>(
+    s: &S,
+    quads: &[Quad],
+) -> (Vec<DVec3>, Vec<[usize; 3]>) {
+    // Quads are split exactly in half, so corners which are shared between
+    // neighbors have bitwise-equal parameters.  These maps find the corners
+    // along each line of constant u or v, sorted by the other parameter.
+    let mut along_u: HashMap<u64, Vec<f64>> = HashMap::new();
+    let mut along_v: HashMap<u64, Vec<f64>> = HashMap::new();
+    for &((u0, u1), (v0, v1)) in quads {
+        for u in [u0, u1] {
+            for v in [v0, v1] {
+                along_u.entry(u.to_bits()).or_default().push(v);
+                along_v.entry(v.to_bits()).or_default().push(u);
+            }
+        }
+    }
+    for line in along_u.values_mut().chain(along_v.values_mut()) {
+        line.sort_by(f64::total_cmp);
+        line.dedup();
+    }
+    // Corners strictly between `a` and `b` on a line, in order from `a`
+    let between = |line: &Vec<f64>, a: f64, b: f64| {
+        let (lo, hi) = (a.min(b), a.max(b));
+        let start = line.partition_point(|&t| t <= lo);
+        let end = line.partition_point(|&t| t < hi);
+        let mut out = line[start..end].to_vec();
+        if a > b {
+            out.reverse();
+        }
+        out
+    };
+
+    let mut verts = vec![];
+    let mut index: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut vertex = |u: f64, v: f64| {
+        *index.entry((u.to_bits(), v.to_bits())).or_insert_with(|| {
+            verts.push(s.point(DVec2::new(u, v)));
+            verts.len() - 1
+        })
+    };
+    let mut tris = vec![];
+    for &((u0, u1), (v0, v1)) in quads {
+        // Walk counter-clockwise around the quad in parameter space
+        let mut ring = vec![];
+        for (a, b, along, fixed_u) in [
+            ((u0, v0), (u1, v0), &along_v, false),
+            ((u1, v0), (u1, v1), &along_u, true),
+            ((u1, v1), (u0, v1), &along_v, false),
+            ((u0, v1), (u0, v0), &along_u, true),
+        ] {
+            ring.push(vertex(a.0, a.1));
+            if fixed_u {
+                for v in between(&along[&a.0.to_bits()], a.1, b.1) {
+                    ring.push(vertex(a.0, v));
+                }
+            } else {
+                for u in between(&along[&a.1.to_bits()], a.0, b.0) {
+                    ring.push(vertex(u, a.1));
+                }
+            }
+        }
+        if let [a, b, c, d] = ring[..] {
+            tris.push([a, b, c]);
+            tris.push([a, c, d]);
+        } else {
+            let center = vertex((u0 + u1) / 2.0, (v0 + v1) / 2.0);
+            for i in 0..ring.len() {
+                tris.push([center, ring[i], ring[(i + 1) % ring.len()]]);
+            }
+        }
+    }
+    (verts, tris)
+}
+
+/// Returns the unit normal from a surface's partial derivatives, or `None`
+/// if they're parallel (or too short to tell)
+fn unit_normal(su: DVec3, sv: DVec3) -> Option<DVec3> {
+    let n = su.cross(&sv);
+    let norm = n.norm();
+    // Relative to the derivatives, so that the test doesn't depend on the
+    // surface's scale or parameterization
+    (norm.is_finite() && norm > f64::EPSILON * su.norm() * sv.norm()).then(|| n / norm)
+}
+
+/// Returns the bounding box of a set of points, which is inverted (with
+/// infinite corners) if there are no points
+pub(crate) fn point_bounds(pts: impl Iterator<Item = DVec3>) -> (DVec3, DVec3) {
+    pts.fold(
+        (DVec3::repeat(f64::INFINITY), DVec3::repeat(-f64::INFINITY)),
+        |(lo, hi), p| (lo.inf(&p), hi.sup(&p)),
+    )
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BsplineSurface, KnotVector, NurbsSurface};
+
+    fn bilinear(pts: [[DVec3; 2]; 2]) -> BsplineSurface {
+        let knots = KnotVector::from_multiplicities(1, &[0.0, 1.0], &[2, 2]).unwrap();
+        let pts = pts.iter().map(|r| r.to_vec()).collect();
+        BsplineSurface::new(false, false, knots.clone(), knots, pts).unwrap()
+    }
+
+    #[test]
+    fn test_normal() {
+        let plane = bilinear([
+            [DVec3::new(0.0, 0.0, 0.0), DVec3::new(0.0, 2.0, 0.0)],
+            [DVec3::new(3.0, 0.0, 0.0), DVec3::new(3.0, 2.0, 0.0)],
+        ]);
+        for uv in [
+            DVec2::new(0.0, 0.0),
+            DVec2::new(0.3, 0.7),
+            DVec2::new(1.0, 1.0),
+        ] {
+            assert!((plane.normal(uv) - DVec3::z()).norm() < 1e-12);
+        }
+
+        // Weights don't change the direction of a planar patch's normal
+        let knots = KnotVector::from_multiplicities(1, &[0.0, 1.0], &[2, 2]).unwrap();
+        let rational = NurbsSurface::new(
+            false,
+            false,
+            knots.clone(),
+            knots,
+            vec![
+                vec![
+                    DVec3::new(0.0, 0.0, 0.0).push(1.0),
+                    DVec3::new(0.0, 2.0, 0.0).push(2.0),
+                ],
+                vec![
+                    DVec3::new(3.0, 0.0, 0.0).push(0.5),
+                    DVec3::new(3.0, 2.0, 0.0).push(1.0),
+                ],
+            ],
+        )
+        .unwrap();
+        let n = rational.normal(DVec2::new(0.4, 0.6));
+        assert!((n - DVec3::z()).norm() < 1e-12, "{:?}", n);
+    }
+
+    #[test]
+    fn test_curvature() {
+        // Planes don't curve, however they're parameterized
+        let plane = bilinear([
+            [DVec3::new(0.0, 0.0, 0.0), DVec3::new(1.0, 2.0, 0.0)],
+            [DVec3::new(3.0, 0.0, 1.0), DVec3::new(4.0, 2.0, 1.0)],
+        ]);
+        let (k, h) = plane.curvature(DVec2::new(0.3, 0.6));
+        assert!(k.abs() < 1e-12 && h.abs() < 1e-12, "{} {}", k, h);
+
+        // A hyperbolic paraboloid z = xy, which is a saddle at the origin
+        let saddle = bilinear([
+            [DVec3::new(-1.0, -1.0, 1.0), DVec3::new(-1.0, 1.0, -1.0)],
+            [DVec3::new(1.0, -1.0, -1.0), DVec3::new(1.0, 1.0, 1.0)],
+        ]);
+        let (k, h) = saddle.curvature(DVec2::new(0.5, 0.5));
+        assert!((k + 1.0).abs() < 1e-12, "{}", k);
+        assert!(h.abs() < 1e-12, "{}", h);
+
+        // Degenerate points have no curvature
+        let point = bilinear([[DVec3::zeros(); 2]; 2]);
+        assert_eq!(point.curvature(DVec2::new(0.5, 0.5)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_degenerate_normal() {
+        // A triangle, with one edge collapsed to a point (like a pole)
+        let pole = bilinear([
+            [DVec3::new(0.0, 0.0, 0.0), DVec3::new(0.0, 0.0, 0.0)],
+            [DVec3::new(1.0, 0.0, 0.0), DVec3::new(1.0, 1.0, 0.0)],
+        ]);
+        assert_eq!(pole.normal(DVec2::new(0.0, 0.5)), DVec3::zeros());
+        assert!((pole.normal(DVec2::new(0.5, 0.5)) - DVec3::z()).norm() < 1e-12);
+
+        // A patch which collapses to a line, and one which is a point
+        let line = bilinear([
+            [DVec3::new(0.0, 0.0, 0.0), DVec3::new(1.0, 0.0, 0.0)],
+            [DVec3::new(0.0, 0.0, 0.0), DVec3::new(1.0, 0.0, 0.0)],
+        ]);
+        assert_eq!(line.normal(DVec2::new(0.5, 0.5)), DVec3::zeros());
+        let point = bilinear([[DVec3::zeros(); 2]; 2]);
+        assert_eq!(point.normal(DVec2::new(0.5, 0.5)), DVec3::zeros());
+    }
+
+    #[test]
+    fn test_tessellate_plane() {
+        let plane = bilinear([
+            [DVec3::new(0.0, 0.0, 0.0), DVec3::new(0.0, 2.0, 0.0)],
+            [DVec3::new(1.0, 0.0, 0.0), DVec3::new(1.0, 2.0, 0.0)],
+        ]);
+        // A plane is never split, so this is the starting grid
+        let (verts, tris) = plane.tessellate(1e-6);
+        assert_eq!(verts.len(), 25);
+        assert_eq!(tris.len(), 32);
+        for [a, b, c] in tris {
+            let n = (verts[b] - verts[a]).cross(&(verts[c] - verts[a]));
+            assert!((n.normalize() - DVec3::z()).norm() < 1e-12);
+        }
+    }
 }
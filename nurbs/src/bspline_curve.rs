@@ -10,4 +10,8 @@ impl AbstractCurve for BsplineCurve {
     fn derivatives<const E: usize>(&self, u: f64) -> Vec<DVec3> {
         self.curve_derivatives::<E>(u)
     }
+
+    fn domain(&self) -> (f64, f64) {
+        (self.min_u(), self.max_u())
+    }
 }
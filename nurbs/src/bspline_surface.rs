@@ -1,4 +1,8 @@
-use crate::{abstract_surface::AbstractSurface, nd_surface::NdBsplineSurface, VecF};
+use crate::{
+    abstract_surface::{point_bounds, AbstractSurface},
+    nd_surface::NdBsplineSurface,
+    VecF,
+};
 use nalgebra_glm::{DVec2, DVec3};
 
 pub type BsplineSurface = NdBsplineSurface<3>;
@@ -15,4 +19,15 @@ impl AbstractSurface for BsplineSurface {
     fn derivatives<const E: usize>(&self, uv: DVec2) -> Vec<Vec<DVec3>> {
         self.surface_derivatives::<E>(uv)
     }
+
+    fn control_bounds(&self) -> (DVec3, DVec3) {
+        point_bounds(self.control_points().iter().flatten().copied())
+    }
+
+    fn domain(&self) -> (DVec2, DVec2) {
+        (
+            DVec2::new(self.min_u(), self.min_v()),
+            DVec2::new(self.max_u(), self.max_v()),
+        )
+    }
 }
@@ -0,0 +1,35 @@
+/// Errors from building knot vectors, curves, and surfaces out of
+/// inconsistent data
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum NurbsError {
+    #[error("Got {knots} knots but {multiplicities} multiplicities")]
+    MultiplicityMismatch { knots: usize, multiplicities: usize },
+
+    #[error("Knot {index} is smaller than the knot before it")]
+    DecreasingKnots { index: usize },
+
+    #[error("A degree {degree} knot vector needs at least {} knots, but got {count}", 2 * (.degree + 1))]
+    TooFewKnots { degree: usize, count: usize },
+
+    #[error("A degree {degree} curve with {control_points} control points needs {} knots, but got {knots}", .control_points + .degree + 1)]
+    KnotCount {
+        degree: usize,
+        control_points: usize,
+        knots: usize,
+    },
+
+    #[error("Expected {expected} control points, but got {actual}")]
+    ControlPointCount { expected: usize, actual: usize },
+
+    #[error("Knot {index} is outside of the parameter range")]
+    KnotOutOfDomain { index: usize },
+
+    #[error("Cannot combine knot vectors of degree {left} and {right}")]
+    DegreeMismatch { left: usize, right: usize },
+
+    #[error("Cannot combine knot vectors with different parameter ranges")]
+    DomainMismatch,
+
+    #[error("Curves can only be split inside of their parameter range")]
+    SplitOutOfDomain,
+}
@@ -1,11 +1,14 @@
 use std::convert::TryInto;
 
 use smallvec::smallvec;
+use std::cmp::Ordering::{Equal, Less};
 use std::mem::swap;
 
-use crate::VecF;
+use crate::{NurbsError, VecF};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawKnotVector"))]
 pub struct KnotVector {
     /// Knot positions.
     U: VecF,
@@ -14,9 +17,73 @@ pub struct KnotVector {
     p: usize,
 }
 
+/// A knot vector as it's serialized, which is checked by
+/// [`KnotVector::from_multiplicities`] when deserializing
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawKnotVector {
+    U: VecF,
+    p: usize,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawKnotVector> for KnotVector {
+    type Error = NurbsError;
+    fn try_from(raw: RawKnotVector) -> Result<Self, Self::Error> {
+        let mut knots = vec![];
+        let mut multiplicities = vec![];
+        for &u in &raw.U {
+            if knots.last() == Some(&u) {
+                *multiplicities.last_mut().unwrap() += 1;
+            } else {
+                knots.push(u);
+                multiplicities.push(1);
+            }
+        }
+        Self::from_multiplicities(raw.p, &knots, &multiplicities)
+    }
+}
+
 impl KnotVector {
-    /// Constructs a new knot vector.
-    pub fn from_multiplicities(p: usize, knots: &[f64], multiplicities: &[usize]) -> Self {
+    /// Constructs a new knot vector, checking that the knots are
+    /// non-decreasing and that there are enough of them for the degree.
+    pub fn from_multiplicities(
+        p: usize,
+        knots: &[f64],
+        multiplicities: &[usize],
+    ) -> Result<Self, NurbsError> {
+        if knots.len() != multiplicities.len() {
+            return Err(NurbsError::MultiplicityMismatch {
+                knots: knots.len(),
+                multiplicities: multiplicities.len(),
+            });
+        }
+        if let Some(i) = knots
+            .windows(2)
+            .position(|w| !matches!(w[0].partial_cmp(&w[1]), Some(Less | Equal)))
+        {
+            return Err(NurbsError::DecreasingKnots { index: i + 1 });
+        }
+        let out = Self::from_multiplicities_unchecked(p, knots, multiplicities);
+        if out.len() < 2 * (p + 1) {
+            return Err(NurbsError::TooFewKnots {
+                degree: p,
+                count: out.len(),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Constructs a new knot vector without validating it, beyond checking
+    /// that there's one multiplicity per knot.
+    ///
+    /// # Panics
+    /// If `knots` and `multiplicities` have different lengths
+    pub fn from_multiplicities_unchecked(
+        p: usize,
+        knots: &[f64],
+        multiplicities: &[usize],
+    ) -> Self {
         assert!(knots.len() == multiplicities.len());
         let U = knots
             .iter()
@@ -26,8 +93,102 @@ impl KnotVector {
         Self { U, p }
     }
 
+    /// Builds a knot vector from a complete (non-decreasing) list of knots
+    pub(crate) fn from_knots(p: usize, U: VecF) -> Self {
+        Self { U, p }
+    }
+
+    /// Returns the number of knots within `tol` of `u`
+    pub fn multiplicity(&self, u: f64, tol: f64) -> usize {
+        self.U.iter().filter(|k| (**k - u).abs() <= tol).count()
+    }
+
+    /// Returns each distinct knot with its multiplicity, in order (i.e. the
+    /// inputs to [`KnotVector::from_multiplicities`])
+    pub fn unique_knots(&self) -> Vec<(f64, usize)> {
+        let mut out: Vec<(f64, usize)> = vec![];
+        for &u in self.iter() {
+            match out.last_mut() {
+                Some((k, m)) if *k == u => *m += 1,
+                _ => out.push((u, 1)),
+            }
+        }
+        out
+    }
+
+    /// Returns the knots which must be inserted into this knot vector so
+    /// that it contains every knot in `new_knots`, counting repeats.  The
+    /// result is sorted, and has each knot once per missing multiplicity.
+    ///
+    /// Every new knot must be within the parameter range.
+    pub fn refine(&self, new_knots: &[f64]) -> Result<Vec<f64>, NurbsError> {
+        let (lo, hi) = (self.min_t(), self.max_t());
+        if let Some(index) = new_knots.iter().position(|u| !(*u >= lo && *u <= hi)) {
+            return Err(NurbsError::KnotOutOfDomain { index });
+        }
+        Ok(self.missing(new_knots))
+    }
+
+    /// Returns the knots in `knots` which aren't in this knot vector, as in
+    /// [`KnotVector::refine`] but without checking the parameter range
+    fn missing(&self, knots: &[f64]) -> Vec<f64> {
+        let mut sorted: VecF = knots.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+
+        let mut out = vec![];
+        for (u, m) in Self::from_knots(self.p, sorted).unique_knots() {
+            let have = self.multiplicity(u, 0.0);
+            out.extend(std::iter::repeat_n(u, m.saturating_sub(have)));
+        }
+        out
+    }
+
+    /// Returns the union of two knot vectors, where each knot has the
+    /// larger of its two multiplicities.  This is the knot vector which
+    /// curves built on either one can be refined to, e.g. to make them
+    /// compatible for lofting.
+    ///
+    /// Both knot vectors must have the same degree and parameter range.
+    pub fn merge(&self, other: &Self) -> Result<Self, NurbsError> {
+        if self.p != other.p {
+            return Err(NurbsError::DegreeMismatch {
+                left: self.p,
+                right: other.p,
+            });
+        }
+        if self.min_t() != other.min_t() || self.max_t() != other.max_t() {
+            return Err(NurbsError::DomainMismatch);
+        }
+        let mut U = self.U.clone();
+        U.extend(self.missing(&other.U));
+        U.sort_by(f64::total_cmp);
+        Ok(Self::from_knots(self.p, U))
+    }
+
+    /// Checks whether the first and last knots are repeated exactly `p + 1`
+    /// times, so the curve starts and ends on its first and last control
+    /// points
+    pub fn is_clamped(&self) -> bool {
+        !self.is_empty()
+            && self.multiplicity(self.U[0], 0.0) == self.p + 1
+            && self.multiplicity(self.U[self.len() - 1], 0.0) == self.p + 1
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, f64> {
+        self.U.iter()
+    }
+
+    /// Returns the number of control points which this knot vector expects
+    pub fn num_control_points(&self) -> usize {
+        self.len().saturating_sub(self.p + 1)
+    }
+
     /// For basis functions of order `p + 1`, finds the span in the knot vector
-    /// that is relevant for position `u`.
+    /// that is relevant for position `u`, i.e. the index `i` of the last
+    /// non-empty span `[U[i], U[i + 1])` which starts at or before `u`.
+    ///
+    /// Values outside of the parameter range are clamped to it, so `u` at
+    /// (or past) the end of the range is in the last non-empty span.
     ///
     /// Algorithm A2.1
     pub fn find_span(&self, u: f64) -> usize {
@@ -70,6 +231,18 @@ impl KnotVector {
         self[self.len() - 1 - self.p]
     }
 
+    /// Returns the length of the knot vector's parameter range, which is
+    /// the period of a closed curve built on it
+    pub fn period(&self) -> f64 {
+        self.max_t() - self.min_t()
+    }
+
+    /// Mirrors the knot vector, keeping the same parameter range
+    pub(crate) fn reversed(&self) -> Self {
+        let c = self.min_t() + self.max_t();
+        Self::from_knots(self.p, self.U.iter().rev().map(|u| c - u).collect())
+    }
+
     /// Computes non-vanishing basis functions of order `p + 1` at point `u`.
     ///
     /// Algorithm A2.2
@@ -111,7 +284,12 @@ impl KnotVector {
         self.basis_functions_derivatives_for_span(i, u, n)
     }
 
-    pub fn basis_functions_derivatives_for_span(&self, i: usize, u: f64, n: usize) -> Vec<Vec<f64>> {
+    pub fn basis_functions_derivatives_for_span(
+        &self,
+        i: usize,
+        u: f64,
+        n: usize,
+    ) -> Vec<Vec<f64>> {
         let mut ndu = vec![vec![0.0; self.p + 1]; self.p + 1];
         let mut a = vec![vec![0.0; self.p + 1]; 2];
         let mut left = vec![0.0; self.p + 1];
@@ -191,17 +369,109 @@ impl std::ops::Index<usize> for KnotVector {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_multiplicities() {
+        let k = KnotVector::from_multiplicities(2, &[0.0, 1.0], &[3, 3]).unwrap();
+        assert_eq!(k.len(), 6);
+        assert_eq!(k.num_control_points(), 3);
+        assert_eq!(k.find_span(0.0), 2);
+        assert_eq!(k.find_span(0.99), 2);
+
+        assert_eq!(
+            KnotVector::from_multiplicities(2, &[0.0, 1.0], &[3]).unwrap_err(),
+            NurbsError::MultiplicityMismatch {
+                knots: 2,
+                multiplicities: 1
+            }
+        );
+        assert_eq!(
+            KnotVector::from_multiplicities(1, &[0.0, 2.0, 1.0], &[2, 1, 2]).unwrap_err(),
+            NurbsError::DecreasingKnots { index: 2 }
+        );
+        assert_eq!(
+            KnotVector::from_multiplicities(1, &[0.0, f64::NAN], &[2, 2]).unwrap_err(),
+            NurbsError::DecreasingKnots { index: 1 }
+        );
+        assert_eq!(
+            KnotVector::from_multiplicities(3, &[0.0, 1.0], &[3, 3]).unwrap_err(),
+            NurbsError::TooFewKnots {
+                degree: 3,
+                count: 6
+            }
+        );
+    }
 
-    /*
     #[test]
     fn test_find_span() {
-        let k = KnotVector {
-            U: vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
-        };
-        assert!(k.find_span(0, 0.0) == 2);
-        assert!(k.find_span(0, 0.99) == 2);
-        assert!(k.find_span(1, 0.99) == 2);
-        assert!(k.find_span(2, 0.99) == 2);
-    }
-    */
+        let k = KnotVector::from_multiplicities(2, &[0.0, 1.0, 2.0, 3.0], &[3, 1, 2, 3]).unwrap();
+        // Knot values start a new span, skipping empty ones
+        assert_eq!(k.find_span(0.0), 2);
+        assert_eq!(k.find_span(0.5), 2);
+        assert_eq!(k.find_span(1.0), 3);
+        assert_eq!(k.find_span(2.0), 5);
+        assert_eq!(k.find_span(2.5), 5);
+        // The end of the range (and beyond) is in the last non-empty span
+        assert_eq!(k.find_span(3.0), 5);
+        assert_eq!(k.find_span(4.0), 5);
+        assert_eq!(k.find_span(-1.0), 2);
+    }
+
+    #[test]
+    fn test_multiplicity() {
+        let k = KnotVector::from_multiplicities(2, &[0.0, 1.0, 1.0 + 1e-9, 2.0], &[3, 1, 1, 3])
+            .unwrap();
+        assert_eq!(k.multiplicity(0.0, 0.0), 3);
+        assert_eq!(k.multiplicity(1.0, 0.0), 1);
+        assert_eq!(k.multiplicity(1.0, 1e-6), 2);
+        assert_eq!(k.multiplicity(0.5, 1e-6), 0);
+        assert_eq!(
+            k.unique_knots(),
+            vec![(0.0, 3), (1.0, 1), (1.0 + 1e-9, 1), (2.0, 3)]
+        );
+    }
+
+    #[test]
+    fn test_refine() {
+        let k = KnotVector::from_multiplicities(2, &[0.0, 1.0, 2.0], &[3, 1, 3]).unwrap();
+        assert_eq!(k.refine(&[]).unwrap(), vec![]);
+        assert_eq!(k.refine(&[1.0]).unwrap(), vec![]);
+        assert_eq!(
+            k.refine(&[1.5, 1.0, 0.5, 1.0]).unwrap(),
+            vec![0.5, 1.0, 1.5]
+        );
+        assert_eq!(k.refine(&[0.0, 2.0]).unwrap(), vec![]);
+        assert_eq!(
+            k.refine(&[1.0, 3.0]).unwrap_err(),
+            NurbsError::KnotOutOfDomain { index: 1 }
+        );
+        assert_eq!(
+            k.refine(&[f64::NAN]).unwrap_err(),
+            NurbsError::KnotOutOfDomain { index: 0 }
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let a = KnotVector::from_multiplicities(2, &[0.0, 0.5, 1.0], &[3, 2, 3]).unwrap();
+        let b = KnotVector::from_multiplicities(2, &[0.0, 0.25, 0.5, 1.0], &[3, 1, 1, 3]).unwrap();
+        let ab = a.merge(&b).unwrap();
+        let ba = b.merge(&a).unwrap();
+        let knots = |k: &KnotVector| k.iter().copied().collect::<Vec<_>>();
+        assert_eq!(knots(&ab), knots(&ba));
+        assert_eq!(
+            ab.unique_knots(),
+            vec![(0.0, 3), (0.25, 1), (0.5, 2), (1.0, 3)]
+        );
+        assert_eq!(knots(&a.merge(&a).unwrap()), knots(&a));
+
+        let c = KnotVector::from_multiplicities(3, &[0.0, 1.0], &[4, 4]).unwrap();
+        assert_eq!(
+            a.merge(&c).unwrap_err(),
+            NurbsError::DegreeMismatch { left: 2, right: 3 }
+        );
+        let d = KnotVector::from_multiplicities(2, &[0.0, 2.0], &[3, 3]).unwrap();
+        assert_eq!(a.merge(&d).unwrap_err(), NurbsError::DomainMismatch);
+    }
 }
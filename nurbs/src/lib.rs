@@ -7,11 +7,13 @@ mod abstract_curve;
 mod abstract_surface;
 mod bspline_curve;
 mod bspline_surface;
+mod error;
 mod knot_vector;
 mod nd_curve;
 mod nd_surface;
 mod nurbs_curve;
 mod nurbs_surface;
+mod projection;
 mod sampled_curve;
 mod sampled_surface;
 
@@ -22,6 +24,7 @@ pub use crate::abstract_curve::AbstractCurve;
 pub use crate::abstract_surface::AbstractSurface;
 pub use crate::bspline_curve::BsplineCurve;
 pub use crate::bspline_surface::BsplineSurface;
+pub use crate::error::NurbsError;
 pub use crate::knot_vector::KnotVector;
 pub use crate::nd_curve::NdBsplineCurve;
 pub use crate::nd_surface::NdBsplineSurface;
@@ -1,17 +1,56 @@
-use crate::KnotVector;
+use crate::{KnotVector, NurbsError, VecF};
 use nalgebra_glm::TVec;
 use std::cmp::min;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawNdBsplineCurve<D>"))]
 pub struct NdBsplineCurve<const D: usize> {
     pub open: bool,
     pub knots: KnotVector,
     control_points: Vec<TVec<f64, D>>,
 }
 
+/// A curve as it's serialized, which is checked by [`NdBsplineCurve::new`]
+/// when deserializing
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawNdBsplineCurve<const D: usize> {
+    open: bool,
+    knots: KnotVector,
+    control_points: Vec<TVec<f64, D>>,
+}
+
+#[cfg(feature = "serde")]
+impl<const D: usize> TryFrom<RawNdBsplineCurve<D>> for NdBsplineCurve<D> {
+    type Error = NurbsError;
+    fn try_from(raw: RawNdBsplineCurve<D>) -> Result<Self, Self::Error> {
+        Self::new(raw.open, raw.knots, raw.control_points)
+    }
+}
+
 /// Abstract b-spline curve with N-dimensional control points.
 impl<const D: usize> NdBsplineCurve<D> {
-    pub fn new(open: bool, knots: KnotVector, control_points: Vec<TVec<f64, D>>) -> Self {
+    /// Builds a new curve, checking that the number of control points
+    /// matches the knot vector
+    pub fn new(
+        open: bool,
+        knots: KnotVector,
+        control_points: Vec<TVec<f64, D>>,
+    ) -> Result<Self, NurbsError> {
+        let expected = knots.num_control_points();
+        if control_points.len() != expected {
+            return Err(NurbsError::ControlPointCount {
+                expected,
+                actual: control_points.len(),
+            });
+        }
+        Ok(Self::new_unchecked(open, knots, control_points))
+    }
+
+    /// Builds a new curve without checking the number of control points.
+    /// Evaluating the curve will panic if there are too few.
+    pub fn new_unchecked(open: bool, knots: KnotVector, control_points: Vec<TVec<f64, D>>) -> Self {
         Self {
             open,
             knots,
@@ -19,6 +58,10 @@ impl<const D: usize> NdBsplineCurve<D> {
         }
     }
 
+    pub fn control_points(&self) -> &[TVec<f64, D>] {
+        &self.control_points
+    }
+
     pub fn min_u(&self) -> f64 {
         self.knots.min_t()
     }
@@ -103,4 +146,373 @@ impl<const D: usize> NdBsplineCurve<D> {
 
         result
     }
+
+    /// Inserts the knot `u` up to `r` times, without changing the shape of
+    /// the curve.  The knot's multiplicity is capped at the curve's degree,
+    /// and knots outside of the curve's interior are ignored.
+    ///
+    /// Algorithm A5.1
+    pub fn insert_knot(&self, u: f64, r: usize) -> Self {
+        let p = self.knots.degree();
+        let s = self.knots.multiplicity(u, 0.0);
+        let r = min(r, p.saturating_sub(s));
+        if r == 0 || !(u > self.min_u() && u < self.max_u()) {
+            return self.clone();
+        }
+        self.insert_knot_in_span(u, self.knots.find_span(u), s, r)
+    }
+
+    /// Inserts the knot `u` exactly `r` times, where `k` is the index of the
+    /// last knot which is less than or equal to `u`, `s` is the multiplicity
+    /// of `u`, and `s + r <= p`
+    fn insert_knot_in_span(&self, u: f64, k: usize, s: usize, r: usize) -> Self {
+        let p = self.knots.degree();
+        let np = self.control_points.len() - 1;
+        let UP = &self.knots;
+        let Pw = &self.control_points;
+
+        let mut UQ: VecF = UP.iter().take(k + 1).copied().collect();
+        UQ.extend(std::iter::repeat_n(u, r));
+        UQ.extend(UP.iter().skip(k + 1).copied());
+
+        let mut Qw = vec![TVec::zeros(); np + r + 1];
+        Qw[..=(k - p)].copy_from_slice(&Pw[..=(k - p)]);
+        Qw[(k - s + r)..].copy_from_slice(&Pw[(k - s)..]);
+        let mut Rw: Vec<TVec<f64, D>> = Pw[(k - p)..=(k - s)].to_vec();
+        let mut L = 0;
+        for j in 1..=r {
+            L = k - p + j;
+            for i in 0..=(p - j - s) {
+                let alpha = (u - UP[L + i]) / (UP[i + k + 1] - UP[L + i]);
+                Rw[i] = alpha * Rw[i + 1] + (1.0 - alpha) * Rw[i];
+            }
+            Qw[L] = Rw[0];
+            Qw[k + r - j - s] = Rw[p - j - s];
+        }
+        if L + 1 < k - s {
+            Qw[(L + 1)..(k - s)].copy_from_slice(&Rw[1..(k - s - L)]);
+        }
+        Self::new_unchecked(self.open, KnotVector::from_knots(p, UQ), Qw)
+    }
+
+    /// Removes one occurrence of the interior knot `u`, if that moves no
+    /// control point by more than `tol`.  This is the inverse of
+    /// [`insert_knot`](Self::insert_knot), so a knot which was inserted can
+    /// be removed again (to within rounding error).
+    ///
+    /// Returns `None` if `u` isn't an interior knot, or if removing it would
+    /// change the curve too much.  Distances are measured between control
+    /// points as they're stored, i.e. in homogeneous coordinates for
+    /// rational curves.
+    ///
+    /// Algorithm A5.8
+    pub fn try_remove_knot(&self, u: f64, tol: f64) -> Option<Self> {
+        let s = self.knots.multiplicity(u, 0.0);
+        if s == 0 || !(u > self.min_u() && u < self.max_u()) {
+            return None;
+        }
+        let p = self.knots.degree();
+        let U = &self.knots;
+        let Pw = &self.control_points;
+        let ord = p + 1;
+        let r = U.iter().rposition(|&k| k == u).unwrap();
+        let (first, last) = (r - p, r - s);
+        let off = first - 1;
+
+        // Solve for new control points from both ends of the affected range
+        let mut temp = vec![TVec::zeros(); last - off + 2];
+        temp[0] = Pw[off];
+        temp[last + 1 - off] = Pw[last + 1];
+        let (mut i, mut j) = (first, last);
+        let (mut ii, mut jj) = (1, last - off);
+        while j > i {
+            let alfi = (u - U[i]) / (U[i + ord] - U[i]);
+            let alfj = (u - U[j]) / (U[j + ord] - U[j]);
+            temp[ii] = (Pw[i] - (1.0 - alfi) * temp[ii - 1]) / alfi;
+            temp[jj] = (Pw[j] - alfj * temp[jj + 1]) / (1.0 - alfj);
+            i += 1;
+            ii += 1;
+            j -= 1;
+            jj -= 1;
+        }
+        // The two solutions must meet, or agree with the point in between
+        let err = if j < i {
+            (temp[ii - 1] - temp[jj + 1]).norm()
+        } else {
+            let alfi = (u - U[i]) / (U[i + ord] - U[i]);
+            (Pw[i] - (alfi * temp[ii + 1] + (1.0 - alfi) * temp[ii - 1])).norm()
+        };
+        if err.is_nan() || err > tol {
+            return None;
+        }
+
+        let mut Qw = Pw.clone();
+        let (mut i, mut j) = (first, last);
+        while j > i {
+            Qw[i] = temp[i - off];
+            Qw[j] = temp[j - off];
+            i += 1;
+            j -= 1;
+        }
+        Qw.remove((2 * r - s - p) / 2);
+        let mut UQ: VecF = U.iter().copied().collect();
+        UQ.remove(r);
+        Some(Self::new_unchecked(
+            self.open,
+            KnotVector::from_knots(p, UQ),
+            Qw,
+        ))
+    }
+
+    /// Splits the curve at `t`, returning the parts before and after it,
+    /// which keep their parameter ranges (so the first ends at `t` and the
+    /// second starts there).
+    ///
+    /// The knot `t` is inserted up to multiplicity `p`, at which point the
+    /// curve passes through a control point, which both parts share.  `t`
+    /// must be strictly inside of the curve's parameter range.
+    pub fn split(&self, t: f64) -> Result<(Self, Self), NurbsError> {
+        if !(t > self.min_u() && t < self.max_u()) {
+            return Err(NurbsError::SplitOutOfDomain);
+        }
+        let p = self.knots.degree();
+        let c = self.insert_knot(t, p);
+        let m = c.knots.multiplicity(t, 0.0);
+        let k = c.knots.iter().rposition(|&u| u <= t).unwrap();
+        let extra = (p + 1).saturating_sub(m);
+
+        let mut U: VecF = c.knots.iter().take(k + 1).copied().collect();
+        U.extend(std::iter::repeat_n(t, extra));
+        let n = U.len() - p - 1;
+        let left = Self::new_unchecked(
+            self.open,
+            KnotVector::from_knots(p, U),
+            c.control_points[..n].to_vec(),
+        );
+
+        let mut U: VecF = std::iter::repeat_n(t, extra).collect();
+        U.extend(c.knots.iter().skip(k + 1 - m).copied());
+        let n = U.len() - p - 1;
+        let right = Self::new_unchecked(
+            self.open,
+            KnotVector::from_knots(p, U),
+            c.control_points[(c.control_points.len() - n)..].to_vec(),
+        );
+        Ok((left, right))
+    }
+
+    /// Returns the same curve with a clamped knot vector, so that it starts
+    /// and ends on its first and last control points.  The curve's shape and
+    /// parameter range are unchanged.
+    pub fn clamped(&self) -> Self {
+        if self.knots.is_clamped() {
+            self.clone()
+        } else {
+            self.clamp_start().reversed().clamp_start().reversed()
+        }
+    }
+
+    /// Clamps the start of the curve, by bringing its first knot up to
+    /// multiplicity `p`, then dropping the knots and control points which no
+    /// longer affect the curve.
+    fn clamp_start(&self) -> Self {
+        let p = self.knots.degree();
+        let a = self.min_u();
+        let s = self.knots.multiplicity(a, 0.0);
+        let k = self.knots.iter().rposition(|&u| u <= a).unwrap();
+        let (c, k) = if s < p {
+            (self.insert_knot_in_span(a, k, s, p - s), k + p - s)
+        } else {
+            (self.clone(), k)
+        };
+        let mut U: VecF = c.knots.iter().skip(k - p).copied().collect();
+        U[..=p].fill(a);
+        let Qw = c.control_points[(k - p)..].to_vec();
+        Self::new_unchecked(self.open, KnotVector::from_knots(p, U), Qw)
+    }
+
+    /// Reverses the direction of the curve, keeping the same parameter range
+    fn reversed(&self) -> Self {
+        let mut Qw = self.control_points.clone();
+        Qw.reverse();
+        Self::new_unchecked(self.open, self.knots.reversed(), Qw)
+    }
+
+    /// Raises the curve's degree by `t`, without changing its shape.
+    ///
+    /// Unclamped curves are clamped first.  Each interior knot's
+    /// multiplicity grows by `t`, so the curve keeps its continuity.
+    ///
+    /// Algorithm A5.9
+    pub fn elevate_degree(&self, t: usize) -> Self {
+        if t == 0 {
+            return self.clone();
+        }
+        let c = self.clamped();
+        let p = c.knots.degree();
+        if p == 0 {
+            return c.elevate_constant(t);
+        }
+        let U = &c.knots;
+        let Pw = &c.control_points;
+        let n = Pw.len() - 1;
+        let m = n + p + 1;
+        let ph = p + t;
+        let ph2 = ph / 2;
+        let bin = |a: usize, b: usize| num_integer::binomial(a, b) as f64;
+
+        // Coefficients for degree elevating a Bézier segment
+        let mut bezalfs = vec![vec![0.0; p + 1]; ph + 1];
+        bezalfs[0][0] = 1.0;
+        bezalfs[ph][p] = 1.0;
+        for i in 1..=ph2 {
+            let inv = 1.0 / bin(ph, i);
+            for j in i.saturating_sub(t)..=min(p, i) {
+                bezalfs[i][j] = inv * bin(p, j) * bin(t, i - j);
+            }
+        }
+        for i in (ph2 + 1)..ph {
+            for j in i.saturating_sub(t)..=min(p, i) {
+                bezalfs[i][j] = bezalfs[ph - i][p - j];
+            }
+        }
+
+        // Each of the (at most m - 2p) segments gains t control points and
+        // t knots
+        let segments = m - 2 * p;
+        let mut Uh: VecF = std::iter::repeat_n(0.0, m + 1 + (segments + 1) * t).collect();
+        let mut Qw = vec![TVec::zeros(); n + 1 + segments * t];
+        let mut bpts = Pw[..=p].to_vec();
+        let mut ebpts = vec![TVec::zeros(); ph + 1];
+        let mut next_bpts = vec![TVec::zeros(); p.saturating_sub(1)];
+        let mut alfs = vec![0.0; p.saturating_sub(1)];
+
+        let mut mh = ph;
+        let mut kind = ph + 1;
+        let mut r: isize = -1;
+        let mut a = p;
+        let mut b = p + 1;
+        let mut cind = 1;
+        let mut ua = U[0];
+        Qw[0] = Pw[0];
+        Uh[..=ph].fill(ua);
+
+        while b < m {
+            let i = b;
+            while b < m && U[b] == U[b + 1] {
+                b += 1;
+            }
+            let mul = b - i + 1;
+            mh += mul + t;
+            let ub = U[b];
+            let oldr = r;
+            r = p as isize - mul as isize;
+
+            // Insert the knot ub r times, to get a Bézier segment
+            let lbz = if oldr > 0 { (oldr as usize + 2) / 2 } else { 1 };
+            let rbz = if r > 0 {
+                ph - (r as usize).div_ceil(2)
+            } else {
+                ph
+            };
+            if r > 0 {
+                let r = r as usize;
+                let numer = ub - ua;
+                for k in ((mul + 1)..=p).rev() {
+                    alfs[k - mul - 1] = numer / (U[a + k] - ua);
+                }
+                for j in 1..=r {
+                    let save = r - j;
+                    let s = mul + j;
+                    for k in (s..=p).rev() {
+                        bpts[k] = alfs[k - s] * bpts[k] + (1.0 - alfs[k - s]) * bpts[k - 1];
+                    }
+                    next_bpts[save] = bpts[p];
+                }
+            }
+
+            // Degree elevate the Bézier segment
+            for i in lbz..=ph {
+                ebpts[i] = TVec::zeros();
+                for j in i.saturating_sub(t)..=min(p, i) {
+                    ebpts[i] += bezalfs[i][j] * bpts[j];
+                }
+            }
+
+            // Remove the knot ua oldr times
+            if oldr > 1 {
+                let oldr = oldr as usize;
+                let den = ub - ua;
+                let bet = (ub - Uh[kind - 1]) / den;
+                for tr in 1..oldr {
+                    let (mut i, mut j) = (kind - 1 - tr, kind - 1 + tr);
+                    let mut kj = j - kind + 1;
+                    while j - i > tr {
+                        if i < cind {
+                            let alf = (ub - Uh[i]) / (ua - Uh[i]);
+                            Qw[i] = alf * Qw[i] + (1.0 - alf) * Qw[i - 1];
+                        }
+                        if j >= lbz {
+                            if j - tr <= kind - ph + oldr {
+                                let gam = (ub - Uh[j - tr]) / den;
+                                ebpts[kj] = gam * ebpts[kj] + (1.0 - gam) * ebpts[kj + 1];
+                            } else {
+                                ebpts[kj] = bet * ebpts[kj] + (1.0 - bet) * ebpts[kj + 1];
+                            }
+                        }
+                        i += 1;
+                        j -= 1;
+                        kj -= 1;
+                    }
+                }
+            }
+
+            // Load the knot ua and the new control points
+            if a != p {
+                let count = ph - oldr.max(0) as usize;
+                Uh[kind..(kind + count)].fill(ua);
+                kind += count;
+            }
+            for j in lbz..=rbz {
+                Qw[cind] = ebpts[j];
+                cind += 1;
+            }
+
+            // Set up for the next segment
+            if b < m {
+                let r = r.max(0) as usize;
+                bpts[..r].copy_from_slice(&next_bpts[..r]);
+                bpts[r..].copy_from_slice(&Pw[(b - p + r)..=b]);
+                a = b;
+                b += 1;
+                ua = ub;
+            } else {
+                Uh[kind..=(kind + ph)].fill(ub);
+            }
+        }
+
+        let nh = mh - ph - 1;
+        Uh.truncate(mh + 1);
+        Qw.truncate(nh + 1);
+        Self::new_unchecked(self.open, KnotVector::from_knots(ph, Uh), Qw)
+    }
+
+    /// Raises a piecewise constant curve to degree `t`, by giving each of
+    /// its (non-empty) segments `t + 1` copies of its control point
+    fn elevate_constant(&self, t: usize) -> Self {
+        let U: VecF = self
+            .knots
+            .unique_knots()
+            .into_iter()
+            .flat_map(|(u, _)| std::iter::repeat_n(u, t + 1))
+            .collect();
+        let Qw = self
+            .control_points
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.knots[*i] < self.knots[i + 1])
+            .flat_map(|(_, p)| std::iter::repeat_n(*p, t + 1))
+            .collect();
+        Self::new_unchecked(self.open, KnotVector::from_knots(t, U), Qw)
+    }
 }
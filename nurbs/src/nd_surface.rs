@@ -1,8 +1,10 @@
-use crate::{KnotVector, VecF};
+use crate::{KnotVector, NurbsError, VecF};
 use nalgebra_glm::{DVec2, DVec3, TVec};
 use std::cmp::min;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawNdBsplineSurface<D>"))]
 pub struct NdBsplineSurface<const D: usize> {
     pub u_open: bool,
     pub v_open: bool,
@@ -11,14 +13,74 @@ pub struct NdBsplineSurface<const D: usize> {
     control_points: Vec<Vec<TVec<f64, D>>>,
 }
 
+/// A surface as it's serialized, which is checked by
+/// [`NdBsplineSurface::new`] when deserializing
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawNdBsplineSurface<const D: usize> {
+    u_open: bool,
+    v_open: bool,
+    u_knots: KnotVector,
+    v_knots: KnotVector,
+    control_points: Vec<Vec<TVec<f64, D>>>,
+}
+
+#[cfg(feature = "serde")]
+impl<const D: usize> TryFrom<RawNdBsplineSurface<D>> for NdBsplineSurface<D> {
+    type Error = NurbsError;
+    fn try_from(raw: RawNdBsplineSurface<D>) -> Result<Self, Self::Error> {
+        Self::new(
+            raw.u_open,
+            raw.v_open,
+            raw.u_knots,
+            raw.v_knots,
+            raw.control_points,
+        )
+    }
+}
+
 /// Non-rational b-spline surface with 3D control points.
 impl<const D: usize> NdBsplineSurface<D> {
+    /// Builds a new surface, checking that the grid of control points
+    /// matches the two knot vectors
     pub fn new(
         u_open: bool,
         v_open: bool,
         u_knots: KnotVector,
         v_knots: KnotVector,
         control_points: Vec<Vec<TVec<f64, D>>>,
+    ) -> Result<Self, NurbsError> {
+        let expected = u_knots.num_control_points();
+        if control_points.len() != expected {
+            return Err(NurbsError::ControlPointCount {
+                expected,
+                actual: control_points.len(),
+            });
+        }
+        let expected = v_knots.num_control_points();
+        if let Some(row) = control_points.iter().find(|r| r.len() != expected) {
+            return Err(NurbsError::ControlPointCount {
+                expected,
+                actual: row.len(),
+            });
+        }
+        Ok(Self::new_unchecked(
+            u_open,
+            v_open,
+            u_knots,
+            v_knots,
+            control_points,
+        ))
+    }
+
+    /// Builds a new surface without checking the grid of control points.
+    /// Evaluating the surface will panic if it's too small.
+    pub fn new_unchecked(
+        u_open: bool,
+        v_open: bool,
+        u_knots: KnotVector,
+        v_knots: KnotVector,
+        control_points: Vec<Vec<TVec<f64, D>>>,
     ) -> Self {
         Self {
             u_open,
@@ -29,6 +91,10 @@ impl<const D: usize> NdBsplineSurface<D> {
         }
     }
 
+    pub fn control_points(&self) -> &[Vec<TVec<f64, D>>] {
+        &self.control_points
+    }
+
     pub fn min_u(&self) -> f64 {
         self.u_knots.min_t()
     }
@@ -99,10 +165,14 @@ impl<const D: usize> NdBsplineSurface<D> {
         let mut SKL = vec![vec![TVec::zeros(); E + 1]; E + 1];
 
         let uspan = self.u_knots.find_span(uv.x);
-        let Nu_deriv = self.u_knots.basis_functions_derivatives_for_span(uspan, uv.x, du);
+        let Nu_deriv = self
+            .u_knots
+            .basis_functions_derivatives_for_span(uspan, uv.x, du);
 
         let vspan = self.v_knots.find_span(uv.y);
-        let Nv_deriv = self.v_knots.basis_functions_derivatives_for_span(vspan, uv.y, dv);
+        let Nv_deriv = self
+            .v_knots
+            .basis_functions_derivatives_for_span(vspan, uv.y, dv);
 
         let mut temp = vec![TVec::zeros(); q + 1];
         for k in 0..=du {
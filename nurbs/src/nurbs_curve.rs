@@ -1,8 +1,14 @@
-use crate::{abstract_curve::AbstractCurve, nd_curve::NdBsplineCurve};
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::{abstract_curve::AbstractCurve, nd_curve::NdBsplineCurve, projection, KnotVector};
 use nalgebra_glm::DVec3;
 
 pub type NurbsCurve = NdBsplineCurve<4>;
 
+/// Number of samples per knot span when looking for a starting point for
+/// [`NurbsCurve::project_point`]
+const PROJECTION_SAMPLES: usize = 8;
+
 impl AbstractCurve for NurbsCurve {
     /// Converts a point at position t onto the 3D line, using basis functions
     /// of order `p + 1` respectively.
@@ -24,10 +30,133 @@ impl AbstractCurve for NurbsCurve {
             let mut v = derivatives[k].xyz();
             for i in 1..=k {
                 let b = num_integer::binomial(k, i);
-                v -= b as f64 * derivatives[i].w * CK[k - 1];
+                v -= b as f64 * derivatives[i].w * CK[k - i];
             }
             CK[k] = v / derivatives[0].w;
         }
         CK
     }
+
+    fn domain(&self) -> (f64, f64) {
+        (self.min_u(), self.max_u())
+    }
+}
+
+impl NurbsCurve {
+    /// Builds an exact circular arc, as a rational quadratic curve.
+    ///
+    /// The arc is centered at `center` in the plane spanned by `x` and `y`
+    /// (which should be orthonormal), starting at angle `start` (measured
+    /// from `x` towards `y`) and sweeping through `sweep` radians, which is
+    /// clamped to one full turn.  The curve's parameter runs from 0 to 1.
+    ///
+    /// Algorithm A7.1
+    pub fn arc(center: DVec3, x: DVec3, y: DVec3, radius: f64, start: f64, sweep: f64) -> Self {
+        // A negative sweep is a positive sweep in the mirrored plane
+        if sweep < 0.0 {
+            return Self::arc(center, x, -y, radius, -start, -sweep);
+        }
+        let sweep = sweep.min(2.0 * PI);
+        let narcs = ((sweep / FRAC_PI_2).ceil() as usize).clamp(1, 4);
+        let dtheta = sweep / narcs as f64;
+        let w1 = (dtheta / 2.0).cos();
+
+        let at = |angle: f64, r: f64| center + r * (x * angle.cos() + y * angle.sin());
+        let mut control_points = vec![at(start, radius).push(1.0)];
+        for i in 0..narcs {
+            let angle = start + dtheta * i as f64;
+            // The middle point is where the tangents at either end of the
+            // segment meet, which is along the bisector.
+            let mid = at(angle + dtheta / 2.0, radius / w1);
+            control_points.push((mid * w1).push(w1));
+            control_points.push(at(angle + dtheta, radius).push(1.0));
+        }
+
+        let mut knots = vec![0.0];
+        let mut multiplicities = vec![3];
+        for i in 1..narcs {
+            knots.push(i as f64 / narcs as f64);
+            multiplicities.push(2);
+        }
+        knots.push(1.0);
+        multiplicities.push(3);
+        let knots = KnotVector::from_multiplicities_unchecked(2, &knots, &multiplicities);
+        Self::new_unchecked(true, knots, control_points)
+    }
+
+    /// Builds an exact full circle, as in [`arc`](Self::arc)
+    pub fn circle(center: DVec3, x: DVec3, y: DVec3, radius: f64) -> Self {
+        Self::arc(center, x, y, radius, 0.0, 2.0 * PI)
+    }
+
+    /// Finds the point on the curve which is closest to `p`, returning its
+    /// parameter and position.
+    ///
+    /// Each knot span is sampled, and every sample which is closer than its
+    /// neighbors is refined with Newton's method until a step moves the
+    /// point by less than `tol`.  Steps are kept within the span, since the
+    /// derivatives jump at knots, and are halved until they don't move away
+    /// from `p`.  If Newton's method diverges anyway, this returns the closest
+    /// point which it (or the sampling) found.
+    ///
+    /// Section 6.1
+    pub fn project_point(&self, p: DVec3, tol: f64) -> (f64, DVec3) {
+        let (min_u, max_u) = (self.min_u(), self.max_u());
+        let mut best = (min_u, self.point(min_u));
+        let mut best_dist = (best.1 - p).norm();
+        for w in self.knots.unique_knots().windows(2) {
+            let (a, b) = (w[0].0, w[1].0);
+            if a < min_u || b > max_u {
+                continue;
+            }
+            let samples: Vec<(f64, f64)> = (0..=PROJECTION_SAMPLES)
+                .map(|i| {
+                    let u = a + (b - a) * i as f64 / PROJECTION_SAMPLES as f64;
+                    (u, (self.point(u) - p).norm())
+                })
+                .collect();
+            for (i, (u, d)) in samples.iter().enumerate() {
+                let before = i.checked_sub(1).map_or(f64::INFINITY, |j| samples[j].1);
+                let after = samples.get(i + 1).map_or(f64::INFINITY, |s| s.1);
+                if *d > before || *d > after {
+                    continue;
+                }
+                let (u, q) = self.refine_projection(p, *u, (a, b), tol);
+                let d = (q - p).norm();
+                if d < best_dist {
+                    best = (u, q);
+                    best_dist = d;
+                }
+            }
+        }
+        best
+    }
+
+    /// Refines a guess at the closest point to `p` with Newton's method,
+    /// staying within the knot span `[a, b]`, as in
+    /// [`project_point`](Self::project_point)
+    fn refine_projection(&self, p: DVec3, u: f64, (a, b): (f64, f64), tol: f64) -> (f64, DVec3) {
+        // Derivatives at the end of the span are taken from just inside of
+        // it, since evaluating at a knot uses the next span
+        let inside = b - (b - a) * 1e-9;
+        projection::refine_projection(
+            p,
+            u,
+            tol,
+            |u| self.point(u),
+            |u| u.clamp(a, b),
+            |u, r| {
+                let d = self.derivatives::<2>(u.min(inside));
+                let (c_p, c_pp) = (d[1], d[2]);
+                // Where the curve bends away from `p`, Newton's method would
+                // head for a maximum, so fall back to a Gauss-Newton step
+                let mut denom = c_pp.dot(&r) + c_p.norm_squared();
+                if denom.is_nan() || denom <= 0.0 {
+                    denom = c_p.norm_squared();
+                }
+                let step = -c_p.dot(&r) / denom;
+                step.is_finite().then(|| (step, (step * c_p).norm()))
+            },
+        )
+    }
 }
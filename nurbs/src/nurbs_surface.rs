@@ -1,8 +1,16 @@
-use crate::{abstract_surface::AbstractSurface, nd_surface::NdBsplineSurface, VecF};
+use crate::{
+    abstract_surface::{point_bounds, AbstractSurface},
+    nd_surface::NdBsplineSurface,
+    projection, KnotVector, VecF,
+};
 use nalgebra_glm::{DVec2, DVec3};
 
 pub type NurbsSurface = NdBsplineSurface<4>;
 
+/// Number of samples along each side of a knot span when looking for
+/// starting points for [`NurbsSurface::project_point`]
+const PROJECTION_SAMPLES: usize = 4;
+
 impl AbstractSurface for NurbsSurface {
     fn point(&self, uv: DVec2) -> DVec3 {
         let p = self.surface_point(uv);
@@ -36,4 +44,143 @@ impl AbstractSurface for NurbsSurface {
         }
         SKL
     }
+
+    /// Control points are stored in homogeneous coordinates, so they're
+    /// projected before taking their bounds (which is valid for the positive
+    /// weights that STEP requires)
+    fn control_bounds(&self) -> (DVec3, DVec3) {
+        point_bounds(
+            self.control_points()
+                .iter()
+                .flatten()
+                .map(|p| p.xyz() / p.w),
+        )
+    }
+
+    fn domain(&self) -> (DVec2, DVec2) {
+        (
+            DVec2::new(self.min_u(), self.min_v()),
+            DVec2::new(self.max_u(), self.max_v()),
+        )
+    }
+}
+
+impl NurbsSurface {
+    /// Finds the point on the surface which is closest to `p`, returning its
+    /// `(u, v)` parameters and position.
+    ///
+    /// Each patch between knots is sampled on a coarse grid, and every sample
+    /// which is closer than its neighbors is refined with Newton's method
+    /// until a step moves the point by less than `tol`.  Steps are clamped
+    /// to the patch, so a closest point beyond the edge of the surface ends
+    /// up on its boundary, and are halved until they don't move away from
+    /// `p`.
+    ///
+    /// Section 6.1
+    pub fn project_point(&self, p: DVec3, tol: f64) -> (DVec2, DVec3) {
+        let spans = |knots: &KnotVector, lo: f64, hi: f64| -> Vec<(f64, f64)> {
+            knots
+                .unique_knots()
+                .windows(2)
+                .map(|w| (w[0].0, w[1].0))
+                .filter(|(a, b)| *a >= lo && *b <= hi)
+                .collect()
+        };
+        let us = spans(&self.u_knots, self.min_u(), self.max_u());
+        let vs = spans(&self.v_knots, self.min_v(), self.max_v());
+
+        let corner = DVec2::new(self.min_u(), self.min_v());
+        let mut best = (corner, self.point(corner));
+        let mut best_dist = (best.1 - p).norm();
+        let n = PROJECTION_SAMPLES;
+        for &(ua, ub) in &us {
+            for &(va, vb) in &vs {
+                let samples: Vec<(DVec2, f64)> = (0..=n)
+                    .flat_map(|i| (0..=n).map(move |j| (i, j)))
+                    .map(|(i, j)| {
+                        let uv = DVec2::new(
+                            ua + (ub - ua) * i as f64 / n as f64,
+                            va + (vb - va) * j as f64 / n as f64,
+                        );
+                        (uv, (self.point(uv) - p).norm())
+                    })
+                    .collect();
+                let dist = |i: Option<usize>, j: Option<usize>| match (i, j) {
+                    (Some(i), Some(j)) if i <= n && j <= n => samples[i * (n + 1) + j].1,
+                    _ => f64::INFINITY,
+                };
+                for i in 0..=n {
+                    for j in 0..=n {
+                        let (uv, d) = samples[i * (n + 1) + j];
+                        if d > dist(i.checked_sub(1), Some(j))
+                            || d > dist(Some(i + 1), Some(j))
+                            || d > dist(Some(i), j.checked_sub(1))
+                            || d > dist(Some(i), Some(j + 1))
+                        {
+                            continue;
+                        }
+                        let (uv, q) = self.refine_projection(p, uv, (ua, ub), (va, vb), tol);
+                        let d = (q - p).norm();
+                        if d < best_dist {
+                            best = (uv, q);
+                            best_dist = d;
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Refines a guess at the closest point to `p` with Newton's method,
+    /// staying within the patch `[ua, ub] x [va, vb]`, as in
+    /// [`project_point`](Self::project_point)
+    fn refine_projection(
+        &self,
+        p: DVec3,
+        uv: DVec2,
+        (ua, ub): (f64, f64),
+        (va, vb): (f64, f64),
+        tol: f64,
+    ) -> (DVec2, DVec3) {
+        // Derivatives on the far edges of the patch are taken from just
+        // inside of it, since evaluating at a knot uses the next patch
+        let inside = DVec2::new(ub - (ub - ua) * 1e-9, vb - (vb - va) * 1e-9);
+        projection::refine_projection(
+            p,
+            uv,
+            tol,
+            |uv| self.point(uv),
+            |uv| DVec2::new(uv.x.clamp(ua, ub), uv.y.clamp(va, vb)),
+            |uv, r| {
+                let d = self.derivatives::<2>(uv.inf(&inside));
+                let (su, sv) = (d[1][0], d[0][1]);
+                let (suu, suv, svv) = (d[2][0], d[1][1], d[0][2]);
+                let f = DVec2::new(-r.dot(&su), -r.dot(&sv));
+
+                // Solve the Newton system J * step = f, where J is the
+                // Jacobian of (r . Su, r . Sv).  Where the surface bends away
+                // from `p`, J isn't positive definite and Newton's method
+                // would head for a maximum or saddle, so fall back to a
+                // Gauss-Newton step, then to steepest descent if the
+                // derivatives are parallel.
+                let gauss_newton = (su.norm_squared(), su.dot(&sv), sv.norm_squared());
+                let newton = (
+                    gauss_newton.0 + r.dot(&suu),
+                    gauss_newton.1 + r.dot(&suv),
+                    gauss_newton.2 + r.dot(&svv),
+                );
+                let solve = |(a, b, c): (f64, f64, f64)| {
+                    let det = a * c - b * b;
+                    (a > 0.0 && det > f64::EPSILON * a * c)
+                        .then(|| DVec2::new(c * f.x - b * f.y, a * f.y - b * f.x) / det)
+                };
+                solve(newton)
+                    .or_else(|| solve(gauss_newton))
+                    .or_else(|| Some(f / (gauss_newton.0 + gauss_newton.2)))
+                    .filter(|s| s.x.is_finite() && s.y.is_finite())
+                    .map(|s| (s, (s.x * su + s.y * sv).norm()))
+            },
+        )
+    }
 }
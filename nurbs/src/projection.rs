@@ -0,0 +1,68 @@
+use std::ops::{Add, Mul};
+
+use nalgebra_glm::DVec3;
+
+/// Maximum number of Newton steps when projecting a point onto a curve or
+/// surface
+const PROJECTION_STEPS: usize = 32;
+
+/// Number of times that a Newton step which doesn't get closer is halved
+/// before giving up
+const PROJECTION_HALVINGS: usize = 20;
+
+/// Refines a guess `x` at the parameter of the closest point to `p` with
+/// Newton's method, returning the parameter and its point.
+///
+/// `point` evaluates the curve or surface, and `clamp` keeps a parameter
+/// within the span or patch being searched.  `step` is called with the
+/// current parameter and the vector from `p` to its point, and returns the
+/// Newton step, along with how far (to first order) that step would move
+/// the point; it returns `None` if no step can be taken.
+///
+/// The iteration stops once the point is within `tol` of `p`, or once a
+/// step moves it by less than `tol`.
+pub(crate) fn refine_projection<T>(
+    p: DVec3,
+    mut x: T,
+    tol: f64,
+    point: impl Fn(T) -> DVec3,
+    clamp: impl Fn(T) -> T,
+    mut step: impl FnMut(T, DVec3) -> Option<(T, f64)>,
+) -> (T, DVec3)
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    let mut q = point(x);
+    for _ in 0..PROJECTION_STEPS {
+        let r = q - p;
+        if r.norm() <= tol {
+            break;
+        }
+        let Some((mut dx, moved)) = step(x, r) else {
+            break;
+        };
+        // Judge convergence by the full step, since a step which had to be
+        // shortened is far from converging
+        let converged = moved <= tol;
+        // Distances stop changing as Newton's method converges, so steps
+        // within `tol` of the current distance are taken
+        let mut next = None;
+        for _ in 0..PROJECTION_HALVINGS {
+            let y = clamp(x + dx);
+            let q_y = point(y);
+            if (q_y - p).norm() <= r.norm() + tol {
+                next = Some((y, q_y));
+                break;
+            }
+            dx = dx * 0.5;
+        }
+        let Some((y, q_y)) = next else {
+            break;
+        };
+        (x, q) = (y, q_y);
+        if converged {
+            break;
+        }
+    }
+    (x, q)
+}
@@ -92,6 +92,141 @@ where
         self.u_from_point_newtons_method(p, best_u)
     }
 
+    pub fn curve(&self) -> &NdBsplineCurve<N> {
+        &self.curve
+    }
+
+    /// Checks whether the curve is closed, so that its parameter wraps
+    /// around from the end of the knot range back to the start
+    pub fn is_periodic(&self) -> bool {
+        !self.curve.open
+    }
+
+    /// Returns the length of the curve's parameter range
+    pub fn period(&self) -> f64 {
+        self.curve.knots.period()
+    }
+
+    /// Samples a closed curve from `u_start` to `u_end`, either of which may
+    /// be outside of the knot range: the parameter wraps around, so the
+    /// polyline can cross the curve's seam (or go around it more than once).
+    pub fn as_polyline_periodic(
+        &self,
+        u_start: f64,
+        u_end: f64,
+        num_points_per_knot: usize,
+    ) -> Vec<DVec3> {
+        let (u_min, u_max) = if u_start < u_end {
+            (u_start, u_end)
+        } else {
+            (u_end, u_start)
+        };
+        let (min_u, period) = (self.curve.min_u(), self.period());
+        let wrap = |u: f64| min_u + (u - min_u).rem_euclid(period);
+
+        // Build a grid over a single period, then repeat it as needed
+        assert!(num_points_per_knot > 0);
+        let mut grid = vec![];
+        for i in 0..self.curve.knots.len() - 1 {
+            let (a, b) = (self.curve.knots[i], self.curve.knots[i + 1]);
+            if a == b || a < min_u || b > min_u + period {
+                continue;
+            }
+            for u in 0..num_points_per_knot {
+                let frac = (u as f64) / (num_points_per_knot as f64);
+                grid.push(a * (1.0 - frac) + b * frac);
+            }
+        }
+
+        let mut result = vec![self.curve.point(wrap(u_min))];
+        let first = ((u_min - min_u) / period).floor() as i64;
+        let last = ((u_max - min_u) / period).ceil() as i64;
+        for k in first..=last {
+            for u in &grid {
+                let u = u + k as f64 * period;
+                if u > u_min && u < u_max {
+                    result.push(self.curve.point(wrap(u)));
+                }
+            }
+        }
+        result.push(self.curve.point(wrap(u_max)));
+
+        if u_start > u_end {
+            result.reverse();
+        }
+        result
+    }
+
+    /// Samples the curve from `u_start` to `u_end` as a polyline which
+    /// strays no more than about `chord_tol` from it, as in
+    /// [`AbstractCurve::tessellate_range`].
+    ///
+    /// The range is split at each knot (where the curve may have a kink)
+    /// before it's tessellated.  On a closed curve, either end may be
+    /// outside of the knot range, as in
+    /// [`as_polyline_periodic`](Self::as_polyline_periodic).
+    pub fn tessellate(&self, u_start: f64, u_end: f64, chord_tol: f64) -> Vec<DVec3> {
+        let (u_min, u_max) = if u_start < u_end {
+            (u_start, u_end)
+        } else {
+            (u_end, u_start)
+        };
+        let (min_u, period) = (self.curve.min_u(), self.period());
+        // Only a closed curve's parameter wraps around
+        let (first, last) = if self.is_periodic() {
+            (
+                ((u_min - min_u) / period).floor() as i64,
+                ((u_max - min_u) / period).ceil() as i64,
+            )
+        } else {
+            (0, 0)
+        };
+
+        let knots: Vec<f64> = self
+            .curve
+            .knots
+            .unique_knots()
+            .into_iter()
+            .map(|(u, _)| u)
+            .filter(|u| *u >= min_u && *u <= min_u + period)
+            .collect();
+        let mut cuts = vec![u_min];
+        for k in first..=last {
+            for u in &knots {
+                let u = u + k as f64 * period;
+                if u > u_min && u < u_max {
+                    cuts.push(u);
+                }
+            }
+        }
+        cuts.push(u_max);
+        // The end of one period is the start of the next
+        cuts.dedup();
+
+        let mut result = vec![];
+        for w in cuts.windows(2) {
+            let shift = if self.is_periodic() {
+                ((w[0] + w[1]) / 2.0 - min_u).div_euclid(period) * period
+            } else {
+                0.0
+            };
+            let pts = self
+                .curve
+                .tessellate_range(w[0] - shift, w[1] - shift, chord_tol);
+            let skip = usize::from(!result.is_empty());
+            result.extend(pts.into_iter().skip(skip));
+        }
+        if result.is_empty() {
+            let p = self.curve.point(u_min);
+            result = vec![p, p];
+        }
+
+        if u_start > u_end {
+            result.reverse();
+        }
+        result
+    }
+
     pub fn as_polyline(&self, u_start: f64, u_end: f64, num_points_per_knot: usize) -> Vec<DVec3> {
         let (u_min, u_max) = if u_start < u_end {
             (u_start, u_end)
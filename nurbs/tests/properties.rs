@@ -0,0 +1,774 @@
+//! Property-based checks of curve and surface evaluation against analytic
+//! references
+use std::f64::consts::PI;
+
+use nalgebra_glm::{DVec2, DVec3, DVec4};
+use nurbs::{
+    AbstractCurve, AbstractSurface, BsplineCurve, KnotVector, NurbsCurve, NurbsError, NurbsSurface,
+    SampledCurve,
+};
+use proptest::prelude::*;
+
+/// Builds a clamped knot vector on `[0, 1]` from a degree and a list of
+/// interior knots on a grid of tenths, capping each multiplicity at `p` so
+/// that the curve stays continuous.
+fn knot_vector(p: usize, interior: &[u8]) -> KnotVector {
+    let mut knots = vec![0.0];
+    let mut mults = vec![p + 1];
+    let mut interior = interior.to_vec();
+    interior.sort_unstable();
+    for k in interior {
+        let u = k as f64 / 10.0;
+        if *knots.last().unwrap() == u {
+            let m = mults.last_mut().unwrap();
+            *m = (*m + 1).min(p);
+        } else {
+            knots.push(u);
+            mults.push(1);
+        }
+    }
+    knots.push(1.0);
+    mults.push(p + 1);
+    KnotVector::from_multiplicities(p, &knots, &mults).unwrap()
+}
+
+fn knots() -> impl Strategy<Value = KnotVector> {
+    (1usize..=4, prop::collection::vec(1u8..=9, 0..=6))
+        .prop_map(|(p, interior)| knot_vector(p, &interior))
+}
+
+fn point() -> impl Strategy<Value = DVec3> {
+    (-10.0..10.0, -10.0..10.0, -10.0..10.0).prop_map(|(x, y, z)| DVec3::new(x, y, z))
+}
+
+fn bspline() -> impl Strategy<Value = BsplineCurve> {
+    knots().prop_flat_map(|knots| {
+        let n = knots.num_control_points();
+        prop::collection::vec(point(), n)
+            .prop_map(move |pts| BsplineCurve::new(true, knots.clone(), pts).unwrap())
+    })
+}
+
+fn nurbs() -> impl Strategy<Value = NurbsCurve> {
+    knots().prop_flat_map(|knots| {
+        let n = knots.num_control_points();
+        prop::collection::vec((point(), 0.5..2.0), n).prop_map(move |pts: Vec<(DVec3, f64)>| {
+            let pts: Vec<DVec4> = pts.into_iter().map(|(p, w)| (p * w).push(w)).collect();
+            NurbsCurve::new(true, knots.clone(), pts).unwrap()
+        })
+    })
+}
+
+/// Builds a b-spline curve on a uniform, unclamped knot vector, whose
+/// parameter range is `[p, n]` for `n` control points
+fn unclamped() -> impl Strategy<Value = BsplineCurve> {
+    (1usize..=4, 0usize..=5).prop_flat_map(|(p, extra)| {
+        let n = p + 1 + extra;
+        let knots: Vec<f64> = (0..n + p + 1).map(|i| i as f64).collect();
+        let knots = KnotVector::from_multiplicities(p, &knots, &vec![1; knots.len()]).unwrap();
+        prop::collection::vec(point(), n)
+            .prop_map(move |pts| BsplineCurve::new(true, knots.clone(), pts).unwrap())
+    })
+}
+
+/// Returns true if `u` is at least `h` away from every knot, so that finite
+/// differences don't straddle a discontinuity in the derivatives
+fn away_from_knots(knots: &KnotVector, u: f64, h: f64) -> bool {
+    knots.iter().all(|k| (k - u).abs() > h)
+}
+
+fn assert_close(a: DVec3, b: DVec3, tol: f64) -> Result<(), TestCaseError> {
+    let err = (a - b).norm();
+    prop_assert!(
+        err <= tol * (1.0 + b.norm()),
+        "{:?} != {:?} (error {})",
+        a,
+        b,
+        err
+    );
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn partition_of_unity(knots in knots(), u in 0.0..=1.0) {
+        let basis = knots.basis_functions(u);
+        prop_assert_eq!(basis.len(), knots.degree() + 1);
+        prop_assert!(basis.iter().all(|n| *n >= -1e-12), "{:?}", basis);
+        let sum: f64 = basis.iter().sum();
+        prop_assert!((sum - 1.0).abs() < 1e-9, "sum = {}", sum);
+    }
+
+    #[test]
+    fn convex_hull(curve in bspline(), u in 0.0..=1.0) {
+        // The point lies in the bounding box of the p + 1 control points
+        // which affect this span
+        let knots = &curve.knots;
+        let span = knots.find_span(u);
+        let active = &curve.control_points()[span - knots.degree()..=span];
+        let lo = active.iter().fold(DVec3::repeat(f64::INFINITY), |a, b| a.inf(b));
+        let hi = active.iter().fold(DVec3::repeat(-f64::INFINITY), |a, b| a.sup(b));
+        let p = curve.point(u);
+        for i in 0..3 {
+            prop_assert!(p[i] >= lo[i] - 1e-9 && p[i] <= hi[i] + 1e-9,
+                         "{:?} outside {:?} to {:?}", p, lo, hi);
+        }
+    }
+
+    #[test]
+    fn derivatives_match_differences(curve in nurbs(), u in 0.01..0.99) {
+        let h = 1e-6;
+        prop_assume!(away_from_knots(&curve.knots, u, 2.0 * h));
+        let d = curve.derivatives::<1>(u);
+        assert_close(d[0], curve.point(u), 1e-12)?;
+        let diff = (curve.point(u + h) - curve.point(u - h)) / (2.0 * h);
+        assert_close(d[1], diff, 1e-4)?;
+    }
+
+    #[test]
+    fn knot_insertion(curve in nurbs(), k in 0.01..0.99, r in 1usize..=4, u in 0.0..=1.0) {
+        let refined = curve.insert_knot(k, r);
+        prop_assert!(refined.control_points().len() >= curve.control_points().len());
+        prop_assert_eq!(
+            refined.control_points().len(),
+            refined.knots.num_control_points()
+        );
+        let knots: Vec<f64> = refined.knots.iter().copied().collect();
+        prop_assert!(knots.windows(2).all(|w| w[0] <= w[1]), "{:?}", knots);
+        assert_close(refined.point(u), curve.point(u), 1e-9)?;
+    }
+
+    #[test]
+    fn knot_removal(curve in nurbs(), k in 0.01..0.99) {
+        // Inserting a knot and then removing it gives back the same curve
+        let refined = curve.insert_knot(k, 1);
+        prop_assume!(refined.control_points().len() > curve.control_points().len());
+        let removed = refined.try_remove_knot(k, 1e-9).unwrap();
+        let knots = |c: &NurbsCurve| c.knots.iter().copied().collect::<Vec<f64>>();
+        prop_assert_eq!(knots(&removed), knots(&curve));
+        for (a, b) in removed.control_points().iter().zip(curve.control_points()) {
+            prop_assert!((a - b).norm() <= 1e-9 * (1.0 + b.norm()), "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn splitting(curve in nurbs(), t in 0.01..0.99, frac in 0.0..=1.0) {
+        let (left, right) = curve.split(t).unwrap();
+        prop_assert_eq!((left.min_u(), left.max_u()), (0.0, t));
+        prop_assert_eq!((right.min_u(), right.max_u()), (t, 1.0));
+        for half in [&left, &right] {
+            prop_assert_eq!(
+                half.control_points().len(),
+                half.knots.num_control_points()
+            );
+            let u = half.min_u() + frac * (half.max_u() - half.min_u());
+            assert_close(half.point(u), curve.point(u), 1e-9)?;
+        }
+        assert_close(left.point(left.domain().1), right.point(right.domain().0), 1e-9)?;
+    }
+
+    #[test]
+    fn projection(curve in nurbs(), u in 0.0..=1.0, offset in point()) {
+        // Sampling can miss a narrow dip in distance, so the result is only
+        // guaranteed to be a local minimum which is no farther away than
+        // a grid of samples through each knot span
+        let p = curve.point(u) + offset;
+        let (t, q) = curve.project_point(p, 1e-12);
+        assert_close(curve.point(t), q, 1e-12)?;
+        let d = (q - p).norm();
+        for (a, b) in curve.knots.iter().zip(curve.knots.iter().skip(1)) {
+            for i in 0..=8 {
+                let s = curve.point(a + (b - a) * i as f64 / 8.0);
+                prop_assert!(d <= (s - p).norm() + 1e-9, "{} > {}", d, (s - p).norm());
+            }
+        }
+        // Away from knots, the offset is perpendicular to the curve
+        if away_from_knots(&curve.knots, t, 1e-6) {
+            let tangent = curve.derivatives::<1>(t)[1];
+            let cos = tangent.dot(&(q - p)) / (tangent.norm() * d);
+            prop_assert!(cos.abs() < 1e-6, "cos = {}", cos);
+        }
+    }
+
+    #[test]
+    fn clamping(curve in unclamped(), frac in 0.0..=1.0) {
+        let clamped = curve.clamped();
+        prop_assert!(clamped.knots.is_clamped());
+        prop_assert_eq!(clamped.min_u(), curve.min_u());
+        prop_assert_eq!(clamped.max_u(), curve.max_u());
+        prop_assert_eq!(
+            clamped.control_points().len(),
+            clamped.knots.num_control_points()
+        );
+
+        let u = curve.min_u() + frac * (curve.max_u() - curve.min_u());
+        assert_close(clamped.point(u), curve.point(u), 1e-9)?;
+        let pts = clamped.control_points();
+        assert_close(pts[0], curve.point(curve.min_u()), 1e-9)?;
+        assert_close(pts[pts.len() - 1], curve.point(curve.max_u()), 1e-9)?;
+    }
+
+    #[test]
+    fn degree_elevation(curve in nurbs(), t in 1usize..=3, u in 0.0..=1.0) {
+        let elevated = curve.elevate_degree(t);
+        prop_assert_eq!(
+            elevated.knots.degree(),
+            curve.knots.degree() + t
+        );
+        prop_assert_eq!(
+            elevated.control_points().len(),
+            elevated.knots.num_control_points()
+        );
+        // Every distinct knot gains t in multiplicity, and no more
+        for ((a, m), (b, n)) in curve.knots.unique_knots().into_iter().zip(elevated.knots.unique_knots()) {
+            prop_assert_eq!(a, b);
+            prop_assert_eq!(m + t, n);
+        }
+        assert_close(elevated.point(u), curve.point(u), 1e-9)?;
+    }
+
+    #[test]
+    fn circle(
+        center in point(),
+        radius in 0.1..10.0,
+        start in -PI..PI,
+        sweep in -2.5 * PI..2.5 * PI,
+        u in 0.0..=1.0,
+    ) {
+        prop_assume!(sweep.abs() > 1e-3);
+        let (x, y) = (DVec3::x(), DVec3::z());
+        let arc = NurbsCurve::arc(center, x, y, radius, start, sweep);
+
+        // Every point is on the circle, in the plane of the circle
+        let p = arc.point(u) - center;
+        prop_assert!((p.norm() - radius).abs() < 1e-9 * radius.max(1.0),
+                     "|{:?}| != {}", p, radius);
+        prop_assert!(p.y.abs() < 1e-9);
+
+        // The ends are where the angles say they should be
+        let at = |a: f64| center + radius * (x * a.cos() + y * a.sin());
+        let sweep = sweep.clamp(-2.0 * PI, 2.0 * PI);
+        assert_close(arc.point(0.0), at(start), 1e-9)?;
+        assert_close(arc.point(1.0), at(start + sweep), 1e-9)?;
+
+        // The angle follows the parameter, though not uniformly, since the
+        // rational parameterization speeds up and slows down within each arc
+        let angle = |u: f64| {
+            let p = arc.point(u) - center;
+            p.dot(&y).atan2(p.dot(&x))
+        };
+        let a = angle(u) - angle(0.0);
+        let expected = (a - sweep * u + PI).rem_euclid(2.0 * PI) - PI;
+        prop_assert!(expected.abs() < PI / 4.0, "{} vs {}", a, sweep * u);
+    }
+}
+
+#[test]
+fn full_circle() {
+    let c = NurbsCurve::circle(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0);
+    assert_eq!(c.control_points().len(), 9);
+    for i in 0..=100 {
+        let p = c.point(i as f64 / 100.0);
+        assert!((p.norm() - 2.0).abs() < 1e-12);
+        assert_eq!(p.z, 0.0);
+    }
+    let w: Vec<_> = c.control_points().iter().map(|p| p.w).collect();
+    assert!((w[1] - 0.5f64.sqrt()).abs() < 1e-12);
+}
+
+#[test]
+fn knot_insertion_exact() {
+    // A clamped cubic with an interior knot at 0.5
+    let knots = KnotVector::from_multiplicities(3, &[0.0, 0.5, 1.0], &[4, 1, 4]).unwrap();
+    let pts = vec![
+        DVec3::new(0.0, 0.0, 0.0),
+        DVec3::new(1.0, 2.0, 0.0),
+        DVec3::new(2.0, -1.0, 1.0),
+        DVec3::new(3.0, 3.0, -2.0),
+        DVec3::new(4.0, 0.0, 0.0),
+    ];
+    let curve = BsplineCurve::new(true, knots, pts).unwrap();
+    let same = |c: &BsplineCurve| {
+        for i in 0..=20 {
+            let u = i as f64 / 20.0;
+            let err = (c.curve_point(u) - curve.curve_point(u)).norm();
+            assert!(err < 1e-10, "error {} at {}", err, u);
+        }
+    };
+
+    // A new knot, inserted once and then up to the degree
+    for (u, r, added) in [
+        (0.3, 1, 1),
+        (0.3, 3, 3),
+        (0.3, 5, 3),
+        (0.5, 1, 1),
+        (0.5, 3, 2),
+    ] {
+        let refined = curve.insert_knot(u, r);
+        assert_eq!(
+            refined.control_points().len(),
+            curve.control_points().len() + added
+        );
+        assert_eq!(refined.knots.len(), curve.knots.len() + added);
+        let knots: Vec<f64> = refined.knots.iter().copied().collect();
+        assert!(knots.windows(2).all(|w| w[0] <= w[1]), "{:?}", knots);
+        same(&refined);
+    }
+
+    // Inserting at the ends of the range changes nothing
+    for u in [0.0, 1.0, -1.0, 2.0] {
+        let refined = curve.insert_knot(u, 1);
+        assert_eq!(refined.knots.len(), curve.knots.len());
+        same(&refined);
+    }
+
+    // A knot at full multiplicity interpolates its control point
+    let refined = curve.insert_knot(0.3, 3);
+    let i = refined.knots.iter().position(|u| *u == 0.3).unwrap();
+    let p = refined.control_points()[i - 1];
+    assert!((p - curve.curve_point(0.3)).norm() < 1e-10);
+}
+
+#[test]
+fn degree_elevation_exact() {
+    let knots = KnotVector::from_multiplicities(1, &[0.0, 0.4, 1.0], &[2, 1, 2]).unwrap();
+    let line = BsplineCurve::new(
+        true,
+        knots,
+        vec![
+            DVec3::new(0.0, 0.0, 0.0),
+            DVec3::new(1.0, 2.0, 0.0),
+            DVec3::new(3.0, 1.0, -1.0),
+        ],
+    )
+    .unwrap();
+    let knots = KnotVector::from_multiplicities(2, &[0.0, 0.5, 1.0], &[3, 1, 3]).unwrap();
+    let quadratic = BsplineCurve::new(
+        true,
+        knots,
+        vec![
+            DVec3::new(0.0, 0.0, 0.0),
+            DVec3::new(1.0, 2.0, 0.0),
+            DVec3::new(3.0, 1.0, -1.0),
+            DVec3::new(4.0, 3.0, 2.0),
+        ],
+    )
+    .unwrap();
+    let arc = NurbsCurve::arc(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0, 0.0, 1.5 * PI);
+    let weighted = NurbsCurve::new(
+        true,
+        line.knots.clone(),
+        vec![
+            DVec4::new(0.0, 0.0, 0.0, 1.0),
+            DVec4::new(2.0, 4.0, 0.0, 2.0),
+            DVec4::new(1.5, 0.5, -0.5, 0.5),
+        ],
+    )
+    .unwrap();
+
+    fn check<const D: usize>(curve: &nurbs::NdBsplineCurve<D>, degree: usize) {
+        assert_eq!(curve.knots.degree(), degree);
+        let up = curve.elevate_degree(1);
+        assert_eq!(up.knots.degree(), degree + 1);
+        for i in 0..=50 {
+            let u = i as f64 / 50.0;
+            let err = (up.curve_point(u) - curve.curve_point(u)).norm();
+            assert!(err < 1e-10, "error {} at {}", err, u);
+        }
+    }
+    check(&line, 1);
+    check(&quadratic, 2);
+    check(&weighted, 1);
+    check(&arc, 2);
+
+    // Raising a straight segment keeps it straight, with evenly spaced
+    // control points
+    let knots = KnotVector::from_multiplicities(1, &[0.0, 1.0], &[2, 2]).unwrap();
+    let segment = BsplineCurve::new(true, knots, vec![DVec3::zeros(), DVec3::new(3.0, 0.0, 0.0)])
+        .unwrap()
+        .elevate_degree(2);
+    let xs: Vec<f64> = segment.control_points().iter().map(|p| p.x).collect();
+    assert_eq!(xs, vec![0.0, 1.0, 2.0, 3.0]);
+
+    // A step function stays a step function
+    let knots = KnotVector::from_multiplicities(0, &[0.0, 0.5, 1.0], &[1, 1, 1]).unwrap();
+    let step = BsplineCurve::new(true, knots, vec![DVec3::zeros(), DVec3::x()]).unwrap();
+    let up = step.elevate_degree(2);
+    assert_eq!(up.knots.degree(), 2);
+    assert_eq!(up.control_points().len(), 6);
+    for u in [0.0, 0.25, 0.49, 0.51, 0.75, 1.0] {
+        assert!((up.curve_point(u) - step.curve_point(u)).norm() < 1e-12);
+    }
+}
+
+#[test]
+fn circle_projection() {
+    let c = NurbsCurve::circle(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0);
+    for i in 0..16 {
+        let a = i as f64 * PI / 8.0 + 0.1;
+        let dir = DVec3::new(a.cos(), a.sin(), 0.0);
+        for p in [dir * 5.0, dir * 0.5 + DVec3::z(), dir * 2.0] {
+            let (u, q) = c.project_point(p, 1e-12);
+            assert!((q - dir * 2.0).norm() < 1e-9, "{:?} -> {:?}", p, q);
+            assert!((c.point(u) - q).norm() < 1e-12);
+        }
+    }
+
+    // Every point on the circle is as close to its center, so any of them
+    // will do, but the result must be on the circle
+    let (_, q) = c.project_point(DVec3::zeros(), 1e-9);
+    assert!((q.norm() - 2.0).abs() < 1e-9);
+
+    // Points on a smooth curve project onto themselves
+    let knots = KnotVector::from_multiplicities(2, &[0.0, 0.5, 1.0], &[3, 1, 3]).unwrap();
+    let curve = NurbsCurve::new(
+        true,
+        knots,
+        vec![
+            DVec4::new(0.0, 0.0, 0.0, 1.0),
+            DVec4::new(2.0, 4.0, 0.0, 2.0),
+            DVec4::new(3.0, 1.0, -1.0, 1.0),
+            DVec4::new(4.0, 3.0, 2.0, 1.0),
+        ],
+    )
+    .unwrap();
+    for i in 0..=20 {
+        let u = i as f64 / 20.0;
+        let (t, q) = curve.project_point(curve.point(u), 1e-12);
+        assert!((t - u).abs() < 1e-9, "{} != {}", t, u);
+        assert!((q - curve.point(u)).norm() < 1e-9);
+    }
+
+    // Beyond the ends of an open arc, the nearest end is picked
+    let arc = NurbsCurve::arc(DVec3::zeros(), DVec3::x(), DVec3::y(), 1.0, 0.0, PI / 2.0);
+    let (u, q) = arc.project_point(DVec3::new(1.0, -3.0, 0.0), 1e-9);
+    assert_eq!(u, 0.0);
+    assert!((q - DVec3::x()).norm() < 1e-12);
+}
+
+/// Builds a sphere around the origin (or a wedge of one, from the +X axis
+/// around +Z by `sweep`) as the product of two arcs, with latitude along `u`
+/// and longitude along `v`
+fn sphere(radius: f64, sweep: f64) -> NurbsSurface {
+    let meridian = NurbsCurve::arc(DVec3::zeros(), DVec3::x(), DVec3::z(), 1.0, -PI / 2.0, PI);
+    let parallel = NurbsCurve::arc(DVec3::zeros(), DVec3::x(), DVec3::y(), radius, 0.0, sweep);
+    let control_points = meridian
+        .control_points()
+        .iter()
+        .map(|m| {
+            parallel
+                .control_points()
+                .iter()
+                .map(|c| DVec4::new(m.x * c.x, m.x * c.y, m.z * c.w * radius, m.w * c.w))
+                .collect()
+        })
+        .collect();
+    NurbsSurface::new(
+        true,
+        true,
+        meridian.knots.clone(),
+        parallel.knots.clone(),
+        control_points,
+    )
+    .unwrap()
+}
+
+#[test]
+fn sphere_projection() {
+    let s = sphere(2.0, 2.0 * PI);
+    for i in 0..8 {
+        let lon = i as f64 * PI / 4.0 + 0.1;
+        for j in 0..5 {
+            let lat = (j as f64 - 2.0) * PI / 5.0;
+            let dir = DVec3::new(lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin());
+            for p in [dir * 5.0, dir * 0.5, dir * 2.0] {
+                let (uv, q) = s.project_point(p, 1e-12);
+                assert!((q - dir * 2.0).norm() < 1e-9, "{:?} -> {:?}", p, q);
+                assert!((s.point(uv) - q).norm() < 1e-12);
+            }
+        }
+    }
+
+    // The poles are where the surface's v derivative vanishes
+    for z in [-3.0, 3.0] {
+        let (_, q) = s.project_point(DVec3::new(0.0, 0.0, z), 1e-12);
+        assert!((q - DVec3::new(0.0, 0.0, z.signum() * 2.0)).norm() < 1e-9);
+    }
+
+    // Beyond the edge of a wedge, the closest point is on its boundary
+    let wedge = sphere(1.0, PI / 2.0);
+    let p = DVec3::new(-2.0, 2.0, 0.0);
+    let (uv, q) = wedge.project_point(p, 1e-12);
+    assert!((q - DVec3::y()).norm() < 1e-9, "{:?}", q);
+    assert_eq!(uv.y, wedge.max_v());
+    let (uv, q) = wedge.project_point(DVec3::new(-1.0, -1.0, -1.0), 1e-12);
+    assert_eq!(uv.x, wedge.min_u());
+    assert!((q + DVec3::z()).norm() < 1e-12);
+}
+
+#[test]
+fn sphere_curvature() {
+    for r in [0.5, 2.0, 3.0] {
+        let s = sphere(r, 2.0 * PI);
+        for i in 1..8 {
+            for j in 0..8 {
+                let uv = DVec2::new(i as f64 / 8.0, j as f64 / 8.0 + 0.01);
+                let (k, h) = s.curvature(uv);
+                assert!((k - 1.0 / (r * r)).abs() < 1e-9, "{:?}: {}", uv, k);
+                // Latitude and longitude make a normal which points inwards,
+                // towards where the sphere bends
+                assert!((h - 1.0 / r).abs() < 1e-9, "{:?}: {}", uv, h);
+                assert!((s.normal(uv) + s.point(uv) / r).norm() < 1e-9);
+            }
+        }
+    }
+}
+
+#[test]
+fn sphere_tessellation() {
+    let tol = 0.01;
+    let s = sphere(1.0, 2.0 * PI);
+    let (verts, tris) = s.tessellate(tol);
+    for p in &verts {
+        assert!((p.norm() - 1.0).abs() < 1e-12, "{:?}", p);
+    }
+    // Every triangle is within tolerance of the sphere, and winds around the
+    // surface's normal, which points inwards
+    let mut edges = std::collections::HashMap::new();
+    for &[a, b, c] in &tris {
+        let (pa, pb, pc) = (verts[a], verts[b], verts[c]);
+        for p in [
+            (pa + pb) / 2.0,
+            (pb + pc) / 2.0,
+            (pc + pa) / 2.0,
+            (pa + pb + pc) / 3.0,
+        ] {
+            assert!(1.0 - p.norm() <= tol, "{:?}", p);
+        }
+        assert!((pb - pa).cross(&(pc - pa)).dot(&pa) <= 1e-12);
+        for e in [(a, b), (b, c), (c, a)] {
+            *edges.entry(e).or_insert(0) += 1;
+        }
+    }
+    // The mesh doesn't crack: each edge is matched by one in the opposite
+    // direction, except along the seam and at the poles, where the vertices
+    // are duplicated
+    for &(a, b) in edges.keys() {
+        if edges.contains_key(&(b, a)) {
+            continue;
+        }
+        let (pa, pb) = (verts[a], verts[b]);
+        let seam = |p: DVec3| p.y.abs() < 1e-12 && p.x >= 0.0;
+        let pole = |p: DVec3| p.z.abs() > 1.0 - 1e-12;
+        assert!(
+            (seam(pa) && seam(pb)) || pole(pa) || pole(pb),
+            "{:?} {:?}",
+            pa,
+            pb
+        );
+    }
+}
+
+#[test]
+fn knot_removal_tolerance() {
+    // A polyline with a kink at its middle knot, which can't be removed
+    // without moving the corner
+    let knots = KnotVector::from_multiplicities(1, &[0.0, 0.25, 1.0], &[2, 1, 2]).unwrap();
+    let kinked = BsplineCurve::new(
+        false,
+        knots.clone(),
+        vec![
+            DVec3::zeros(),
+            DVec3::new(3.0, 0.0, 0.0),
+            DVec3::new(3.0, 4.0, 0.0),
+        ],
+    )
+    .unwrap();
+    assert!(kinked.try_remove_knot(0.25, 1e-6).is_none());
+
+    // Removing it anyway cuts the corner, by less than the tolerance
+    let cut = kinked.try_remove_knot(0.25, 10.0).unwrap();
+    assert_eq!(cut.control_points().len(), 2);
+    assert_eq!(cut.knots.len(), 4);
+
+    // The same knot on a straight line is redundant
+    let straight = BsplineCurve::new(
+        false,
+        knots,
+        vec![
+            DVec3::zeros(),
+            DVec3::new(1.0, 0.0, 0.0),
+            DVec3::new(4.0, 0.0, 0.0),
+        ],
+    )
+    .unwrap();
+    let line = straight.try_remove_knot(0.25, 1e-12).unwrap();
+    assert_eq!(
+        line.control_points(),
+        [DVec3::zeros(), DVec3::new(4.0, 0.0, 0.0)]
+    );
+
+    // Ends of the domain and parameters which aren't knots can't be removed
+    for u in [0.0, 1.0, 0.5] {
+        assert!(straight.try_remove_knot(u, 1.0).is_none());
+    }
+
+    // Each of a circle's double knots can be removed once, but not twice,
+    // since the circle needs a corner in its control polygon there
+    let c = NurbsCurve::circle(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0);
+    assert!(c.try_remove_knot(0.25, 1e-9).is_none());
+    let refined = c.insert_knot(0.6, 2);
+    let once = refined.try_remove_knot(0.6, 1e-9).unwrap();
+    let twice = once.try_remove_knot(0.6, 1e-9).unwrap();
+    assert_eq!(twice.control_points().len(), c.control_points().len());
+    assert!(twice.try_remove_knot(0.6, 1e-9).is_none());
+    for i in 0..=10 {
+        let u = i as f64 / 10.0;
+        assert!((twice.point(u) - c.point(u)).norm() < 1e-9);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let curve = NurbsCurve::arc(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0, 0.3, 4.0);
+    let json = serde_json::to_string(&curve).unwrap();
+    let back: NurbsCurve = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.open, curve.open);
+    assert_eq!(back.knots.degree(), curve.knots.degree());
+    for i in 0..=10 {
+        let u = i as f64 / 10.0;
+        let (a, b) = (back.curve_point(u), curve.curve_point(u));
+        assert!((a - b).norm() < 1e-12, "{:?} != {:?}", a, b);
+    }
+
+    let knots = KnotVector::from_multiplicities(2, &[0.0, 0.4, 1.0], &[3, 1, 3]).unwrap();
+    let pts = (0..4)
+        .map(|i| DVec3::new(i as f64, (i * i) as f64, 1.0))
+        .collect();
+    let curve = BsplineCurve::new(true, knots, pts).unwrap();
+    let json = serde_json::to_string(&curve).unwrap();
+    let back: BsplineCurve = serde_json::from_str(&json).unwrap();
+    for i in 0..=10 {
+        let u = i as f64 / 10.0;
+        assert!((back.curve_point(u) - curve.curve_point(u)).norm() < 1e-12);
+    }
+
+    let surf = sphere(2.0, PI);
+    let json = serde_json::to_string(&surf).unwrap();
+    let back: NurbsSurface = serde_json::from_str(&json).unwrap();
+    for i in 0..=4 {
+        for j in 0..=4 {
+            let uv = DVec2::new(i as f64 / 4.0, j as f64 / 4.0);
+            assert!((back.surface_point(uv) - surf.surface_point(uv)).norm() < 1e-12);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_validation() {
+    let knots = KnotVector::from_multiplicities(1, &[0.0, 1.0], &[2, 2]).unwrap();
+    let line = BsplineCurve::new(true, knots, vec![DVec3::zeros(), DVec3::x()]).unwrap();
+    let json = serde_json::to_value(&line).unwrap();
+
+    // Knots which go backwards are rejected
+    let mut bad = json.clone();
+    bad["knots"]["U"] = serde_json::json!([0.0, 0.0, 1.0, 0.5]);
+    let err = serde_json::from_value::<BsplineCurve>(bad).unwrap_err();
+    assert!(
+        err.to_string().contains("smaller than the knot before"),
+        "{}",
+        err
+    );
+    let err = serde_json::from_value::<KnotVector>(serde_json::json!({
+        "U": [0.0, 1.0, 0.5, 1.0],
+        "p": 1,
+    }))
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("smaller than the knot before"),
+        "{}",
+        err
+    );
+
+    // As are too few knots, or control points which don't match them
+    let mut bad = json.clone();
+    bad["knots"]["U"] = serde_json::json!([0.0, 1.0]);
+    assert!(serde_json::from_value::<BsplineCurve>(bad).is_err());
+    let mut bad = json;
+    bad["control_points"] = serde_json::json!([[0.0, 0.0, 0.0]]);
+    assert!(serde_json::from_value::<BsplineCurve>(bad).is_err());
+
+    let surf = serde_json::to_value(sphere(1.0, PI)).unwrap();
+    let mut bad = surf.clone();
+    bad["control_points"].as_array_mut().unwrap().pop();
+    assert!(serde_json::from_value::<NurbsSurface>(bad).is_err());
+    let mut bad = surf;
+    bad["v_knots"]["U"][0] = serde_json::json!(2.0);
+    assert!(serde_json::from_value::<NurbsSurface>(bad).is_err());
+}
+
+#[test]
+fn split_out_of_domain() {
+    let c = NurbsCurve::circle(DVec3::zeros(), DVec3::x(), DVec3::y(), 2.0);
+    for t in [0.0, 1.0, -0.5, 1.5, f64::NAN] {
+        assert_eq!(c.split(t).unwrap_err(), NurbsError::SplitOutOfDomain);
+    }
+
+    // Splitting at an existing knot of full multiplicity only divides the
+    // control points
+    let (left, right) = c.split(0.25).unwrap();
+    assert_eq!(left.control_points().len(), 3);
+    assert_eq!(right.control_points().len(), 7);
+    assert!((left.point(0.25) - DVec3::new(0.0, 2.0, 0.0)).norm() < 1e-12);
+}
+
+#[test]
+fn periodic_polyline() {
+    // A uniform cubic with its first three control points repeated at the
+    // end, which is a closed loop around the origin
+    let square = [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)];
+    let pts: Vec<DVec3> = (0..7)
+        .map(|i| DVec3::new(square[i % 4].0, square[i % 4].1, 0.0))
+        .collect();
+    let knots: Vec<f64> = (0..11).map(|i| i as f64).collect();
+    let knots = KnotVector::from_multiplicities(3, &knots, &[1; 11]).unwrap();
+    let curve = SampledCurve::new(BsplineCurve::new(false, knots, pts).unwrap());
+    assert!(curve.is_periodic());
+    assert_eq!(curve.period(), 4.0);
+
+    // Walking across the seam gives the same points as walking up to the
+    // end of the range and then on from the start
+    let across = curve.as_polyline_periodic(6.5, 7.5, 8);
+    let mut split = curve.as_polyline(6.5, 7.0, 8);
+    split.extend(curve.as_polyline(3.0, 3.5, 8).into_iter().skip(1));
+    assert_eq!(across.len(), split.len());
+    for (a, b) in across.iter().zip(&split) {
+        assert!((a - b).norm() < 1e-12, "{:?} != {:?}", a, b);
+    }
+
+    // A full loop in either direction ends where it starts
+    for (start, end) in [(4.25, 8.25), (4.25, 0.25)] {
+        let lap = curve.as_polyline_periodic(start, end, 8);
+        assert_eq!(lap.len(), 4 * 8 + 1);
+        assert!((lap[0] - lap[lap.len() - 1]).norm() < 1e-12);
+    }
+
+    // Tessellating to a tolerance also wraps across the seam, splitting at
+    // the same knots either way
+    let across = curve.tessellate(6.5, 7.5, 1e-4);
+    let mut split = curve.tessellate(6.5, 7.0, 1e-4);
+    split.extend(curve.tessellate(3.0, 3.5, 1e-4).into_iter().skip(1));
+    assert_eq!(across.len(), split.len());
+    for (a, b) in across.iter().zip(&split) {
+        assert!((a - b).norm() < 1e-12, "{:?} != {:?}", a, b);
+    }
+    let c = curve.curve();
+    for (start, end) in [(4.25, 8.25), (4.25, 0.25)] {
+        let lap = curve.tessellate(start, end, 1e-4);
+        assert!((lap[0] - c.point(start)).norm() < 1e-12);
+        assert!((lap[0] - lap[lap.len() - 1]).norm() < 1e-12);
+        assert!(lap.len() > curve.tessellate(start, end, 1e-2).len());
+    }
+}
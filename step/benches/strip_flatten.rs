@@ -0,0 +1,74 @@
+//! Compares serial and parallel flattening of a large generated STEP file.
+//!
+//! Run with `cargo bench -p step --bench strip_flatten`
+use std::time::{Duration, Instant};
+
+use step::{fixture, step_file::StepFile};
+
+/// The original byte-by-byte implementation, for comparison
+fn baseline(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'/' => {
+                if i + 1 < data.len() && data[i + 1] == b'*' {
+                    i = memchr::memchr_iter(b'/', &data[i + 2..])
+                        .map(|j| i + 2 + j)
+                        .find(|&k| k > i + 2 && data[k - 1] == b'*')
+                        .unwrap_or(data.len());
+                }
+            }
+            c if c.is_ascii_whitespace() => (),
+            c => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Returns the fastest of several runs
+fn time<F: FnMut() -> Vec<u8>>(mut f: F) -> (Duration, Vec<u8>) {
+    let mut best = Duration::MAX;
+    let mut out = vec![];
+    for _ in 0..5 {
+        let start = Instant::now();
+        out = f();
+        best = best.min(start.elapsed());
+    }
+    (best, out)
+}
+
+fn main() {
+    // Each copy of the assembly is about 13 MB
+    let parts = (0..1000)
+        .map(|i| fixture::filleted_block(10.0, 2.0).translate([i as f64 * 12.0, 0.0, 0.0]))
+        .collect();
+    let text = fixture::assembly(parts).to_step();
+    let data = text.repeat(8).into_bytes();
+    let mb = data.len() as f64 / 1e6;
+    println!("Flattening {:.0} MB", mb);
+
+    let (base, expected) = time(|| baseline(&data));
+    let (serial, a) = time(|| StepFile::strip_flatten_serial(&data));
+    let (parallel, b) = time(|| StepFile::strip_flatten(&data));
+    assert_eq!(a, expected, "Serial output differs from baseline");
+    assert_eq!(b, expected, "Parallel output differs from baseline");
+
+    let report = |name, t: Duration| {
+        println!(
+            "  {:<10}{:>8.1} ms {:>8.0} MB/s",
+            name,
+            t.as_secs_f64() * 1e3,
+            mb / t.as_secs_f64()
+        );
+    };
+    println!("With {} threads:", rayon::current_num_threads());
+    report("baseline", base);
+    report("serial", serial);
+    report("parallel", parallel);
+    println!(
+        "  speedup   {:>8.1}x",
+        base.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
@@ -17,7 +17,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let data = std::fs::read(input)?;
     let flat = StepFile::strip_flatten(&data);
-    let entities = StepFile::parse(&flat);
+    let entities = StepFile::parse(&flat)?;
     println!("Got {} entities", entities.0.len());
 
     let end = SystemTime::now();
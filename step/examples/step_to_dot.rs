@@ -35,7 +35,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let start = std::time::SystemTime::now();
     let data = std::fs::read(input)?;
     let flat = StepFile::strip_flatten(&data);
-    let entities = StepFile::parse(&flat);
+    let entities = StepFile::parse(&flat)?;
     let end = std::time::SystemTime::now();
     let since_the_epoch = end.duration_since(start)
         .expect("Time went backwards");
@@ -37035,7 +37035,7 @@ pub enum Entity<'a> {
     VolumeUnit(VolumeUnit_<'a>),
     XorExpression(XorExpression_<'a>),
     ComplexEntity(Vec<Entity<'a>>),
-    _FailedToParse,
+    _FailedToParse(&'a str),
     _EmptySlot,
 }
 impl<'a> ParseFromChunks<'a> for Entity<'a> {
@@ -39605,4 +39605,1841 @@ impl<'a> Entity<'a> {
         };
         out
     }
-}
+    pub fn keyword(&self) -> Option<&'static str> {
+        match self {
+            Entity::AbsFunction(_) => Some("ABS_FUNCTION"),
+            Entity::AcosFunction(_) => Some("ACOS_FUNCTION"),
+            Entity::Action(_) => Some("ACTION"),
+            Entity::ActionAssignment(_) => Some("ACTION_ASSIGNMENT"),
+            Entity::ActionDirective(_) => Some("ACTION_DIRECTIVE"),
+            Entity::ActionMethod(_) => Some("ACTION_METHOD"),
+            Entity::ActionMethodRelationship(_) => Some("ACTION_METHOD_RELATIONSHIP"),
+            Entity::ActionProperty(_) => Some("ACTION_PROPERTY"),
+            Entity::ActionPropertyRepresentation(_) => Some("ACTION_PROPERTY_REPRESENTATION"),
+            Entity::ActionRelationship(_) => Some("ACTION_RELATIONSHIP"),
+            Entity::ActionRequestAssignment(_) => Some("ACTION_REQUEST_ASSIGNMENT"),
+            Entity::ActionRequestSolution(_) => Some("ACTION_REQUEST_SOLUTION"),
+            Entity::ActionRequestStatus(_) => Some("ACTION_REQUEST_STATUS"),
+            Entity::ActionResource(_) => Some("ACTION_RESOURCE"),
+            Entity::ActionResourceRequirement(_) => Some("ACTION_RESOURCE_REQUIREMENT"),
+            Entity::ActionResourceType(_) => Some("ACTION_RESOURCE_TYPE"),
+            Entity::ActionStatus(_) => Some("ACTION_STATUS"),
+            Entity::Address(_) => Some("ADDRESS"),
+            Entity::AdvancedBrepShapeRepresentation(_) => Some("ADVANCED_BREP_SHAPE_REPRESENTATION"),
+            Entity::AdvancedFace(_) => Some("ADVANCED_FACE"),
+            Entity::AlternateProductRelationship(_) => Some("ALTERNATE_PRODUCT_RELATIONSHIP"),
+            Entity::AmountOfSubstanceMeasureWithUnit(_) => Some("AMOUNT_OF_SUBSTANCE_MEASURE_WITH_UNIT"),
+            Entity::AmountOfSubstanceUnit(_) => Some("AMOUNT_OF_SUBSTANCE_UNIT"),
+            Entity::AndExpression(_) => Some("AND_EXPRESSION"),
+            Entity::AngularDimension(_) => Some("ANGULAR_DIMENSION"),
+            Entity::AngularLocation(_) => Some("ANGULAR_LOCATION"),
+            Entity::AngularSize(_) => Some("ANGULAR_SIZE"),
+            Entity::AngularityTolerance(_) => Some("ANGULARITY_TOLERANCE"),
+            Entity::AnnotationCurveOccurrence(_) => Some("ANNOTATION_CURVE_OCCURRENCE"),
+            Entity::AnnotationFillArea(_) => Some("ANNOTATION_FILL_AREA"),
+            Entity::AnnotationFillAreaOccurrence(_) => Some("ANNOTATION_FILL_AREA_OCCURRENCE"),
+            Entity::AnnotationOccurrence(_) => Some("ANNOTATION_OCCURRENCE"),
+            Entity::AnnotationOccurrenceAssociativity(_) => Some("ANNOTATION_OCCURRENCE_ASSOCIATIVITY"),
+            Entity::AnnotationOccurrenceRelationship(_) => Some("ANNOTATION_OCCURRENCE_RELATIONSHIP"),
+            Entity::AnnotationPlane(_) => Some("ANNOTATION_PLANE"),
+            Entity::AnnotationSubfigureOccurrence(_) => Some("ANNOTATION_SUBFIGURE_OCCURRENCE"),
+            Entity::AnnotationSymbol(_) => Some("ANNOTATION_SYMBOL"),
+            Entity::AnnotationSymbolOccurrence(_) => Some("ANNOTATION_SYMBOL_OCCURRENCE"),
+            Entity::AnnotationText(_) => Some("ANNOTATION_TEXT"),
+            Entity::AnnotationTextCharacter(_) => Some("ANNOTATION_TEXT_CHARACTER"),
+            Entity::AnnotationTextOccurrence(_) => Some("ANNOTATION_TEXT_OCCURRENCE"),
+            Entity::Apex(_) => Some("APEX"),
+            Entity::ApplicationContext(_) => Some("APPLICATION_CONTEXT"),
+            Entity::ApplicationContextElement(_) => Some("APPLICATION_CONTEXT_ELEMENT"),
+            Entity::ApplicationContextRelationship(_) => Some("APPLICATION_CONTEXT_RELATIONSHIP"),
+            Entity::ApplicationProtocolDefinition(_) => Some("APPLICATION_PROTOCOL_DEFINITION"),
+            Entity::AppliedActionAssignment(_) => Some("APPLIED_ACTION_ASSIGNMENT"),
+            Entity::AppliedActionRequestAssignment(_) => Some("APPLIED_ACTION_REQUEST_ASSIGNMENT"),
+            Entity::AppliedApprovalAssignment(_) => Some("APPLIED_APPROVAL_ASSIGNMENT"),
+            Entity::AppliedArea(_) => Some("APPLIED_AREA"),
+            Entity::AppliedCertificationAssignment(_) => Some("APPLIED_CERTIFICATION_ASSIGNMENT"),
+            Entity::AppliedClassificationAssignment(_) => Some("APPLIED_CLASSIFICATION_ASSIGNMENT"),
+            Entity::AppliedContractAssignment(_) => Some("APPLIED_CONTRACT_ASSIGNMENT"),
+            Entity::AppliedDateAndTimeAssignment(_) => Some("APPLIED_DATE_AND_TIME_ASSIGNMENT"),
+            Entity::AppliedDateAssignment(_) => Some("APPLIED_DATE_ASSIGNMENT"),
+            Entity::AppliedDocumentReference(_) => Some("APPLIED_DOCUMENT_REFERENCE"),
+            Entity::AppliedDocumentUsageConstraintAssignment(_) => Some("APPLIED_DOCUMENT_USAGE_CONSTRAINT_ASSIGNMENT"),
+            Entity::AppliedEffectivityAssignment(_) => Some("APPLIED_EFFECTIVITY_ASSIGNMENT"),
+            Entity::AppliedEventOccurrenceAssignment(_) => Some("APPLIED_EVENT_OCCURRENCE_ASSIGNMENT"),
+            Entity::AppliedExternalIdentificationAssignment(_) => Some("APPLIED_EXTERNAL_IDENTIFICATION_ASSIGNMENT"),
+            Entity::AppliedGroupAssignment(_) => Some("APPLIED_GROUP_ASSIGNMENT"),
+            Entity::AppliedIdentificationAssignment(_) => Some("APPLIED_IDENTIFICATION_ASSIGNMENT"),
+            Entity::AppliedIneffectivityAssignment(_) => Some("APPLIED_INEFFECTIVITY_ASSIGNMENT"),
+            Entity::AppliedNameAssignment(_) => Some("APPLIED_NAME_ASSIGNMENT"),
+            Entity::AppliedOrganizationAssignment(_) => Some("APPLIED_ORGANIZATION_ASSIGNMENT"),
+            Entity::AppliedOrganizationalProjectAssignment(_) => Some("APPLIED_ORGANIZATIONAL_PROJECT_ASSIGNMENT"),
+            Entity::AppliedPersonAndOrganizationAssignment(_) => Some("APPLIED_PERSON_AND_ORGANIZATION_ASSIGNMENT"),
+            Entity::AppliedPresentedItem(_) => Some("APPLIED_PRESENTED_ITEM"),
+            Entity::AppliedSecurityClassificationAssignment(_) => Some("APPLIED_SECURITY_CLASSIFICATION_ASSIGNMENT"),
+            Entity::AppliedTimeIntervalAssignment(_) => Some("APPLIED_TIME_INTERVAL_ASSIGNMENT"),
+            Entity::Approval(_) => Some("APPROVAL"),
+            Entity::ApprovalAssignment(_) => Some("APPROVAL_ASSIGNMENT"),
+            Entity::ApprovalDateTime(_) => Some("APPROVAL_DATE_TIME"),
+            Entity::ApprovalPersonOrganization(_) => Some("APPROVAL_PERSON_ORGANIZATION"),
+            Entity::ApprovalRelationship(_) => Some("APPROVAL_RELATIONSHIP"),
+            Entity::ApprovalRole(_) => Some("APPROVAL_ROLE"),
+            Entity::ApprovalStatus(_) => Some("APPROVAL_STATUS"),
+            Entity::ApproximationTolerance(_) => Some("APPROXIMATION_TOLERANCE"),
+            Entity::ApproximationToleranceDeviation(_) => Some("APPROXIMATION_TOLERANCE_DEVIATION"),
+            Entity::ApproximationToleranceParameter(_) => Some("APPROXIMATION_TOLERANCE_PARAMETER"),
+            Entity::AreaInSet(_) => Some("AREA_IN_SET"),
+            Entity::AreaMeasureWithUnit(_) => Some("AREA_MEASURE_WITH_UNIT"),
+            Entity::AreaUnit(_) => Some("AREA_UNIT"),
+            Entity::AsinFunction(_) => Some("ASIN_FUNCTION"),
+            Entity::AssemblyComponentUsage(_) => Some("ASSEMBLY_COMPONENT_USAGE"),
+            Entity::AssemblyComponentUsageSubstitute(_) => Some("ASSEMBLY_COMPONENT_USAGE_SUBSTITUTE"),
+            Entity::AtanFunction(_) => Some("ATAN_FUNCTION"),
+            Entity::AttributeClassificationAssignment(_) => Some("ATTRIBUTE_CLASSIFICATION_ASSIGNMENT"),
+            Entity::AttributeLanguageAssignment(_) => Some("ATTRIBUTE_LANGUAGE_ASSIGNMENT"),
+            Entity::AttributeValueAssignment(_) => Some("ATTRIBUTE_VALUE_ASSIGNMENT"),
+            Entity::AttributeValueRole(_) => Some("ATTRIBUTE_VALUE_ROLE"),
+            Entity::Axis1Placement(_) => Some("AXIS1_PLACEMENT"),
+            Entity::Axis2Placement2d(_) => Some("AXIS2_PLACEMENT_2D"),
+            Entity::Axis2Placement3d(_) => Some("AXIS2_PLACEMENT_3D"),
+            Entity::BSplineCurve(_) => Some("B_SPLINE_CURVE"),
+            Entity::BSplineCurveWithKnots(_) => Some("B_SPLINE_CURVE_WITH_KNOTS"),
+            Entity::BSplineSurface(_) => Some("B_SPLINE_SURFACE"),
+            Entity::BSplineSurfaceWithKnots(_) => Some("B_SPLINE_SURFACE_WITH_KNOTS"),
+            Entity::BackgroundColour(_) => Some("BACKGROUND_COLOUR"),
+            Entity::BarringHole(_) => Some("BARRING_HOLE"),
+            Entity::Bead(_) => Some("BEAD"),
+            Entity::BeadEnd(_) => Some("BEAD_END"),
+            Entity::BezierCurve(_) => Some("BEZIER_CURVE"),
+            Entity::BezierSurface(_) => Some("BEZIER_SURFACE"),
+            Entity::BinaryBooleanExpression(_) => Some("BINARY_BOOLEAN_EXPRESSION"),
+            Entity::BinaryFunctionCall(_) => Some("BINARY_FUNCTION_CALL"),
+            Entity::BinaryGenericExpression(_) => Some("BINARY_GENERIC_EXPRESSION"),
+            Entity::BinaryNumericExpression(_) => Some("BINARY_NUMERIC_EXPRESSION"),
+            Entity::Block(_) => Some("BLOCK"),
+            Entity::BooleanDefinedFunction(_) => Some("BOOLEAN_DEFINED_FUNCTION"),
+            Entity::BooleanExpression(_) => Some("BOOLEAN_EXPRESSION"),
+            Entity::BooleanLiteral(_) => Some("BOOLEAN_LITERAL"),
+            Entity::BooleanResult(_) => Some("BOOLEAN_RESULT"),
+            Entity::BooleanVariable(_) => Some("BOOLEAN_VARIABLE"),
+            Entity::Boss(_) => Some("BOSS"),
+            Entity::BossTop(_) => Some("BOSS_TOP"),
+            Entity::BoundaryCurve(_) => Some("BOUNDARY_CURVE"),
+            Entity::BoundedCurve(_) => Some("BOUNDED_CURVE"),
+            Entity::BoundedPcurve(_) => Some("BOUNDED_PCURVE"),
+            Entity::BoundedSurface(_) => Some("BOUNDED_SURFACE"),
+            Entity::BoundedSurfaceCurve(_) => Some("BOUNDED_SURFACE_CURVE"),
+            Entity::BoxDomain(_) => Some("BOX_DOMAIN"),
+            Entity::BoxedHalfSpace(_) => Some("BOXED_HALF_SPACE"),
+            Entity::BrepWithVoids(_) => Some("BREP_WITH_VOIDS"),
+            Entity::CalendarDate(_) => Some("CALENDAR_DATE"),
+            Entity::CameraImage(_) => Some("CAMERA_IMAGE"),
+            Entity::CameraImage2dWithScale(_) => Some("CAMERA_IMAGE_2D_WITH_SCALE"),
+            Entity::CameraImage3dWithScale(_) => Some("CAMERA_IMAGE_3D_WITH_SCALE"),
+            Entity::CameraModel(_) => Some("CAMERA_MODEL"),
+            Entity::CameraModelD2(_) => Some("CAMERA_MODEL_D2"),
+            Entity::CameraModelD3(_) => Some("CAMERA_MODEL_D3"),
+            Entity::CameraModelD3WithHlhsr(_) => Some("CAMERA_MODEL_D3_WITH_HLHSR"),
+            Entity::CameraUsage(_) => Some("CAMERA_USAGE"),
+            Entity::CartesianPoint(_) => Some("CARTESIAN_POINT"),
+            Entity::CartesianTransformationOperator(_) => Some("CARTESIAN_TRANSFORMATION_OPERATOR"),
+            Entity::CartesianTransformationOperator2d(_) => Some("CARTESIAN_TRANSFORMATION_OPERATOR_2D"),
+            Entity::CartesianTransformationOperator3d(_) => Some("CARTESIAN_TRANSFORMATION_OPERATOR_3D"),
+            Entity::CelsiusTemperatureMeasureWithUnit(_) => Some("CELSIUS_TEMPERATURE_MEASURE_WITH_UNIT"),
+            Entity::CentreOfSymmetry(_) => Some("CENTRE_OF_SYMMETRY"),
+            Entity::Certification(_) => Some("CERTIFICATION"),
+            Entity::CertificationAssignment(_) => Some("CERTIFICATION_ASSIGNMENT"),
+            Entity::CertificationType(_) => Some("CERTIFICATION_TYPE"),
+            Entity::Chamfer(_) => Some("CHAMFER"),
+            Entity::ChamferOffset(_) => Some("CHAMFER_OFFSET"),
+            Entity::CharacterGlyphSymbol(_) => Some("CHARACTER_GLYPH_SYMBOL"),
+            Entity::CharacterizedClass(_) => Some("CHARACTERIZED_CLASS"),
+            Entity::CharacterizedObject(_) => Some("CHARACTERIZED_OBJECT"),
+            Entity::Circle(_) => Some("CIRCLE"),
+            Entity::CircularClosedProfile(_) => Some("CIRCULAR_CLOSED_PROFILE"),
+            Entity::CircularPattern(_) => Some("CIRCULAR_PATTERN"),
+            Entity::CircularRunoutTolerance(_) => Some("CIRCULAR_RUNOUT_TOLERANCE"),
+            Entity::Class(_) => Some("CLASS"),
+            Entity::ClassSystem(_) => Some("CLASS_SYSTEM"),
+            Entity::ClassUsageEffectivityContextAssignment(_) => Some("CLASS_USAGE_EFFECTIVITY_CONTEXT_ASSIGNMENT"),
+            Entity::ClassificationAssignment(_) => Some("CLASSIFICATION_ASSIGNMENT"),
+            Entity::ClassificationRole(_) => Some("CLASSIFICATION_ROLE"),
+            Entity::ClosedPathProfile(_) => Some("CLOSED_PATH_PROFILE"),
+            Entity::ClosedShell(_) => Some("CLOSED_SHELL"),
+            Entity::CoaxialityTolerance(_) => Some("COAXIALITY_TOLERANCE"),
+            Entity::Colour(_) => Some("COLOUR"),
+            Entity::ColourRgb(_) => Some("COLOUR_RGB"),
+            Entity::ColourSpecification(_) => Some("COLOUR_SPECIFICATION"),
+            Entity::CommonDatum(_) => Some("COMMON_DATUM"),
+            Entity::ComparisonEqual(_) => Some("COMPARISON_EQUAL"),
+            Entity::ComparisonExpression(_) => Some("COMPARISON_EXPRESSION"),
+            Entity::ComparisonGreater(_) => Some("COMPARISON_GREATER"),
+            Entity::ComparisonGreaterEqual(_) => Some("COMPARISON_GREATER_EQUAL"),
+            Entity::ComparisonLess(_) => Some("COMPARISON_LESS"),
+            Entity::ComparisonLessEqual(_) => Some("COMPARISON_LESS_EQUAL"),
+            Entity::ComparisonNotEqual(_) => Some("COMPARISON_NOT_EQUAL"),
+            Entity::CompositeCurve(_) => Some("COMPOSITE_CURVE"),
+            Entity::CompositeCurveOnSurface(_) => Some("COMPOSITE_CURVE_ON_SURFACE"),
+            Entity::CompositeCurveSegment(_) => Some("COMPOSITE_CURVE_SEGMENT"),
+            Entity::CompositeHole(_) => Some("COMPOSITE_HOLE"),
+            Entity::CompositeShapeAspect(_) => Some("COMPOSITE_SHAPE_ASPECT"),
+            Entity::CompositeText(_) => Some("COMPOSITE_TEXT"),
+            Entity::CompositeTextWithAssociatedCurves(_) => Some("COMPOSITE_TEXT_WITH_ASSOCIATED_CURVES"),
+            Entity::CompositeTextWithBlankingBox(_) => Some("COMPOSITE_TEXT_WITH_BLANKING_BOX"),
+            Entity::CompositeTextWithExtent(_) => Some("COMPOSITE_TEXT_WITH_EXTENT"),
+            Entity::CompoundFeature(_) => Some("COMPOUND_FEATURE"),
+            Entity::CompoundRepresentationItem(_) => Some("COMPOUND_REPRESENTATION_ITEM"),
+            Entity::CompoundShapeRepresentation(_) => Some("COMPOUND_SHAPE_REPRESENTATION"),
+            Entity::ConcatExpression(_) => Some("CONCAT_EXPRESSION"),
+            Entity::ConcentricityTolerance(_) => Some("CONCENTRICITY_TOLERANCE"),
+            Entity::ConceptFeatureOperator(_) => Some("CONCEPT_FEATURE_OPERATOR"),
+            Entity::ConceptFeatureRelationship(_) => Some("CONCEPT_FEATURE_RELATIONSHIP"),
+            Entity::ConceptFeatureRelationshipWithCondition(_) => Some("CONCEPT_FEATURE_RELATIONSHIP_WITH_CONDITION"),
+            Entity::ConditionalConceptFeature(_) => Some("CONDITIONAL_CONCEPT_FEATURE"),
+            Entity::ConfigurableItem(_) => Some("CONFIGURABLE_ITEM"),
+            Entity::ConfigurationDefinition(_) => Some("CONFIGURATION_DEFINITION"),
+            Entity::ConfigurationDesign(_) => Some("CONFIGURATION_DESIGN"),
+            Entity::ConfigurationEffectivity(_) => Some("CONFIGURATION_EFFECTIVITY"),
+            Entity::ConfigurationInterpolation(_) => Some("CONFIGURATION_INTERPOLATION"),
+            Entity::ConfigurationItem(_) => Some("CONFIGURATION_ITEM"),
+            Entity::ConfiguredEffectivityAssignment(_) => Some("CONFIGURED_EFFECTIVITY_ASSIGNMENT"),
+            Entity::ConfiguredEffectivityContextAssignment(_) => Some("CONFIGURED_EFFECTIVITY_CONTEXT_ASSIGNMENT"),
+            Entity::Conic(_) => Some("CONIC"),
+            Entity::ConicalSurface(_) => Some("CONICAL_SURFACE"),
+            Entity::ConnectedEdgeSet(_) => Some("CONNECTED_EDGE_SET"),
+            Entity::ConnectedFaceSet(_) => Some("CONNECTED_FACE_SET"),
+            Entity::ConnectedFaceSubSet(_) => Some("CONNECTED_FACE_SUB_SET"),
+            Entity::ConstructiveGeometryRepresentation(_) => Some("CONSTRUCTIVE_GEOMETRY_REPRESENTATION"),
+            Entity::ConstructiveGeometryRepresentationRelationship(_) => Some("CONSTRUCTIVE_GEOMETRY_REPRESENTATION_RELATIONSHIP"),
+            Entity::ContactRatioRepresentation(_) => Some("CONTACT_RATIO_REPRESENTATION"),
+            Entity::ContextDependentInvisibility(_) => Some("CONTEXT_DEPENDENT_INVISIBILITY"),
+            Entity::ContextDependentOverRidingStyledItem(_) => Some("CONTEXT_DEPENDENT_OVER_RIDING_STYLED_ITEM"),
+            Entity::ContextDependentShapeRepresentation(_) => Some("CONTEXT_DEPENDENT_SHAPE_REPRESENTATION"),
+            Entity::ContextDependentUnit(_) => Some("CONTEXT_DEPENDENT_UNIT"),
+            Entity::Contract(_) => Some("CONTRACT"),
+            Entity::ContractAssignment(_) => Some("CONTRACT_ASSIGNMENT"),
+            Entity::ContractType(_) => Some("CONTRACT_TYPE"),
+            Entity::ConversionBasedUnit(_) => Some("CONVERSION_BASED_UNIT"),
+            Entity::CoordinatedUniversalTimeOffset(_) => Some("COORDINATED_UNIVERSAL_TIME_OFFSET"),
+            Entity::CosFunction(_) => Some("COS_FUNCTION"),
+            Entity::CsgShapeRepresentation(_) => Some("CSG_SHAPE_REPRESENTATION"),
+            Entity::CsgSolid(_) => Some("CSG_SOLID"),
+            Entity::Curve(_) => Some("CURVE"),
+            Entity::CurveBoundedSurface(_) => Some("CURVE_BOUNDED_SURFACE"),
+            Entity::CurveDimension(_) => Some("CURVE_DIMENSION"),
+            Entity::CurveReplica(_) => Some("CURVE_REPLICA"),
+            Entity::CurveStyle(_) => Some("CURVE_STYLE"),
+            Entity::CurveStyleFont(_) => Some("CURVE_STYLE_FONT"),
+            Entity::CurveStyleFontPattern(_) => Some("CURVE_STYLE_FONT_PATTERN"),
+            Entity::CurveStyleRendering(_) => Some("CURVE_STYLE_RENDERING"),
+            Entity::CurveSweptSolidShapeRepresentation(_) => Some("CURVE_SWEPT_SOLID_SHAPE_REPRESENTATION"),
+            Entity::CylindricalPair(_) => Some("CYLINDRICAL_PAIR"),
+            Entity::CylindricalPairRange(_) => Some("CYLINDRICAL_PAIR_RANGE"),
+            Entity::CylindricalPairValue(_) => Some("CYLINDRICAL_PAIR_VALUE"),
+            Entity::CylindricalSurface(_) => Some("CYLINDRICAL_SURFACE"),
+            Entity::CylindricityTolerance(_) => Some("CYLINDRICITY_TOLERANCE"),
+            Entity::DataEnvironment(_) => Some("DATA_ENVIRONMENT"),
+            Entity::Date(_) => Some("DATE"),
+            Entity::DateAndTime(_) => Some("DATE_AND_TIME"),
+            Entity::DateAndTimeAssignment(_) => Some("DATE_AND_TIME_ASSIGNMENT"),
+            Entity::DateAssignment(_) => Some("DATE_ASSIGNMENT"),
+            Entity::DateRole(_) => Some("DATE_ROLE"),
+            Entity::DateTimeRole(_) => Some("DATE_TIME_ROLE"),
+            Entity::DatedEffectivity(_) => Some("DATED_EFFECTIVITY"),
+            Entity::Datum(_) => Some("DATUM"),
+            Entity::DatumFeature(_) => Some("DATUM_FEATURE"),
+            Entity::DatumFeatureCallout(_) => Some("DATUM_FEATURE_CALLOUT"),
+            Entity::DatumReference(_) => Some("DATUM_REFERENCE"),
+            Entity::DatumTarget(_) => Some("DATUM_TARGET"),
+            Entity::DatumTargetCallout(_) => Some("DATUM_TARGET_CALLOUT"),
+            Entity::DefaultToleranceTable(_) => Some("DEFAULT_TOLERANCE_TABLE"),
+            Entity::DefaultToleranceTableCell(_) => Some("DEFAULT_TOLERANCE_TABLE_CELL"),
+            Entity::DefinedCharacterGlyph(_) => Some("DEFINED_CHARACTER_GLYPH"),
+            Entity::DefinedFunction(_) => Some("DEFINED_FUNCTION"),
+            Entity::DefinedSymbol(_) => Some("DEFINED_SYMBOL"),
+            Entity::DefinitionalRepresentation(_) => Some("DEFINITIONAL_REPRESENTATION"),
+            Entity::DegeneratePcurve(_) => Some("DEGENERATE_PCURVE"),
+            Entity::DegenerateToroidalSurface(_) => Some("DEGENERATE_TOROIDAL_SURFACE"),
+            Entity::DerivedShapeAspect(_) => Some("DERIVED_SHAPE_ASPECT"),
+            Entity::DerivedUnit(_) => Some("DERIVED_UNIT"),
+            Entity::DerivedUnitElement(_) => Some("DERIVED_UNIT_ELEMENT"),
+            Entity::DerivedUnitVariable(_) => Some("DERIVED_UNIT_VARIABLE"),
+            Entity::DescriptionAttribute(_) => Some("DESCRIPTION_ATTRIBUTE"),
+            Entity::DescriptiveRepresentationItem(_) => Some("DESCRIPTIVE_REPRESENTATION_ITEM"),
+            Entity::DiameterDimension(_) => Some("DIAMETER_DIMENSION"),
+            Entity::DimensionCallout(_) => Some("DIMENSION_CALLOUT"),
+            Entity::DimensionCalloutComponentRelationship(_) => Some("DIMENSION_CALLOUT_COMPONENT_RELATIONSHIP"),
+            Entity::DimensionCalloutRelationship(_) => Some("DIMENSION_CALLOUT_RELATIONSHIP"),
+            Entity::DimensionCurve(_) => Some("DIMENSION_CURVE"),
+            Entity::DimensionCurveDirectedCallout(_) => Some("DIMENSION_CURVE_DIRECTED_CALLOUT"),
+            Entity::DimensionCurveTerminator(_) => Some("DIMENSION_CURVE_TERMINATOR"),
+            Entity::DimensionPair(_) => Some("DIMENSION_PAIR"),
+            Entity::DimensionRelatedToleranceZoneElement(_) => Some("DIMENSION_RELATED_TOLERANCE_ZONE_ELEMENT"),
+            Entity::DimensionTextAssociativity(_) => Some("DIMENSION_TEXT_ASSOCIATIVITY"),
+            Entity::DimensionalCharacteristicRepresentation(_) => Some("DIMENSIONAL_CHARACTERISTIC_REPRESENTATION"),
+            Entity::DimensionalExponents(_) => Some("DIMENSIONAL_EXPONENTS"),
+            Entity::DimensionalLocation(_) => Some("DIMENSIONAL_LOCATION"),
+            Entity::DimensionalLocationWithPath(_) => Some("DIMENSIONAL_LOCATION_WITH_PATH"),
+            Entity::DimensionalSize(_) => Some("DIMENSIONAL_SIZE"),
+            Entity::DimensionalSizeWithPath(_) => Some("DIMENSIONAL_SIZE_WITH_PATH"),
+            Entity::DirectedAction(_) => Some("DIRECTED_ACTION"),
+            Entity::DirectedAngle(_) => Some("DIRECTED_ANGLE"),
+            Entity::DirectedDimensionalLocation(_) => Some("DIRECTED_DIMENSIONAL_LOCATION"),
+            Entity::Direction(_) => Some("DIRECTION"),
+            Entity::DirectionShapeRepresentation(_) => Some("DIRECTION_SHAPE_REPRESENTATION"),
+            Entity::DivExpression(_) => Some("DIV_EXPRESSION"),
+            Entity::Document(_) => Some("DOCUMENT"),
+            Entity::DocumentFile(_) => Some("DOCUMENT_FILE"),
+            Entity::DocumentProductAssociation(_) => Some("DOCUMENT_PRODUCT_ASSOCIATION"),
+            Entity::DocumentProductEquivalence(_) => Some("DOCUMENT_PRODUCT_EQUIVALENCE"),
+            Entity::DocumentReference(_) => Some("DOCUMENT_REFERENCE"),
+            Entity::DocumentRelationship(_) => Some("DOCUMENT_RELATIONSHIP"),
+            Entity::DocumentRepresentationType(_) => Some("DOCUMENT_REPRESENTATION_TYPE"),
+            Entity::DocumentType(_) => Some("DOCUMENT_TYPE"),
+            Entity::DocumentUsageConstraint(_) => Some("DOCUMENT_USAGE_CONSTRAINT"),
+            Entity::DocumentUsageConstraintAssignment(_) => Some("DOCUMENT_USAGE_CONSTRAINT_ASSIGNMENT"),
+            Entity::DocumentUsageRole(_) => Some("DOCUMENT_USAGE_ROLE"),
+            Entity::DraughtingAnnotationOccurrence(_) => Some("DRAUGHTING_ANNOTATION_OCCURRENCE"),
+            Entity::DraughtingCallout(_) => Some("DRAUGHTING_CALLOUT"),
+            Entity::DraughtingCalloutRelationship(_) => Some("DRAUGHTING_CALLOUT_RELATIONSHIP"),
+            Entity::DraughtingElements(_) => Some("DRAUGHTING_ELEMENTS"),
+            Entity::DraughtingModel(_) => Some("DRAUGHTING_MODEL"),
+            Entity::DraughtingModelItemAssociation(_) => Some("DRAUGHTING_MODEL_ITEM_ASSOCIATION"),
+            Entity::DraughtingPreDefinedColour(_) => Some("DRAUGHTING_PRE_DEFINED_COLOUR"),
+            Entity::DraughtingPreDefinedCurveFont(_) => Some("DRAUGHTING_PRE_DEFINED_CURVE_FONT"),
+            Entity::DraughtingPreDefinedTextFont(_) => Some("DRAUGHTING_PRE_DEFINED_TEXT_FONT"),
+            Entity::DraughtingSpecificationReference(_) => Some("DRAUGHTING_SPECIFICATION_REFERENCE"),
+            Entity::DraughtingSubfigureRepresentation(_) => Some("DRAUGHTING_SUBFIGURE_REPRESENTATION"),
+            Entity::DraughtingSymbolRepresentation(_) => Some("DRAUGHTING_SYMBOL_REPRESENTATION"),
+            Entity::DraughtingTextLiteralWithDelineation(_) => Some("DRAUGHTING_TEXT_LITERAL_WITH_DELINEATION"),
+            Entity::DraughtingTitle(_) => Some("DRAUGHTING_TITLE"),
+            Entity::DrawingDefinition(_) => Some("DRAWING_DEFINITION"),
+            Entity::DrawingRevision(_) => Some("DRAWING_REVISION"),
+            Entity::DrawingRevisionSequence(_) => Some("DRAWING_REVISION_SEQUENCE"),
+            Entity::DrawingSheetLayout(_) => Some("DRAWING_SHEET_LAYOUT"),
+            Entity::DrawingSheetRevision(_) => Some("DRAWING_SHEET_REVISION"),
+            Entity::DrawingSheetRevisionUsage(_) => Some("DRAWING_SHEET_REVISION_USAGE"),
+            Entity::Edge(_) => Some("EDGE"),
+            Entity::EdgeBasedWireframeModel(_) => Some("EDGE_BASED_WIREFRAME_MODEL"),
+            Entity::EdgeBasedWireframeShapeRepresentation(_) => Some("EDGE_BASED_WIREFRAME_SHAPE_REPRESENTATION"),
+            Entity::EdgeCurve(_) => Some("EDGE_CURVE"),
+            Entity::EdgeLoop(_) => Some("EDGE_LOOP"),
+            Entity::EdgeRound(_) => Some("EDGE_ROUND"),
+            Entity::Effectivity(_) => Some("EFFECTIVITY"),
+            Entity::EffectivityAssignment(_) => Some("EFFECTIVITY_ASSIGNMENT"),
+            Entity::EffectivityContextAssignment(_) => Some("EFFECTIVITY_CONTEXT_ASSIGNMENT"),
+            Entity::EffectivityContextRole(_) => Some("EFFECTIVITY_CONTEXT_ROLE"),
+            Entity::EffectivityRelationship(_) => Some("EFFECTIVITY_RELATIONSHIP"),
+            Entity::ElectricCurrentMeasureWithUnit(_) => Some("ELECTRIC_CURRENT_MEASURE_WITH_UNIT"),
+            Entity::ElectricCurrentUnit(_) => Some("ELECTRIC_CURRENT_UNIT"),
+            Entity::ElementDelivery(_) => Some("ELEMENT_DELIVERY"),
+            Entity::ElementarySurface(_) => Some("ELEMENTARY_SURFACE"),
+            Entity::Ellipse(_) => Some("ELLIPSE"),
+            Entity::Environment(_) => Some("ENVIRONMENT"),
+            Entity::EqualsExpression(_) => Some("EQUALS_EXPRESSION"),
+            Entity::EvaluatedDegeneratePcurve(_) => Some("EVALUATED_DEGENERATE_PCURVE"),
+            Entity::EventOccurrence(_) => Some("EVENT_OCCURRENCE"),
+            Entity::EventOccurrenceAssignment(_) => Some("EVENT_OCCURRENCE_ASSIGNMENT"),
+            Entity::EventOccurrenceContextAssignment(_) => Some("EVENT_OCCURRENCE_CONTEXT_ASSIGNMENT"),
+            Entity::EventOccurrenceContextRole(_) => Some("EVENT_OCCURRENCE_CONTEXT_ROLE"),
+            Entity::EventOccurrenceRole(_) => Some("EVENT_OCCURRENCE_ROLE"),
+            Entity::ExclusiveProductConceptFeatureCategory(_) => Some("EXCLUSIVE_PRODUCT_CONCEPT_FEATURE_CATEGORY"),
+            Entity::ExecutedAction(_) => Some("EXECUTED_ACTION"),
+            Entity::ExpFunction(_) => Some("EXP_FUNCTION"),
+            Entity::Expression(_) => Some("EXPRESSION"),
+            Entity::ExpressionConversionBasedUnit(_) => Some("EXPRESSION_CONVERSION_BASED_UNIT"),
+            Entity::Extension(_) => Some("EXTENSION"),
+            Entity::ExternalIdentificationAssignment(_) => Some("EXTERNAL_IDENTIFICATION_ASSIGNMENT"),
+            Entity::ExternalSource(_) => Some("EXTERNAL_SOURCE"),
+            Entity::ExternallyDefinedCharacterGlyph(_) => Some("EXTERNALLY_DEFINED_CHARACTER_GLYPH"),
+            Entity::ExternallyDefinedClass(_) => Some("EXTERNALLY_DEFINED_CLASS"),
+            Entity::ExternallyDefinedCurveFont(_) => Some("EXTERNALLY_DEFINED_CURVE_FONT"),
+            Entity::ExternallyDefinedDimensionDefinition(_) => Some("EXTERNALLY_DEFINED_DIMENSION_DEFINITION"),
+            Entity::ExternallyDefinedFeatureDefinition(_) => Some("EXTERNALLY_DEFINED_FEATURE_DEFINITION"),
+            Entity::ExternallyDefinedGeneralProperty(_) => Some("EXTERNALLY_DEFINED_GENERAL_PROPERTY"),
+            Entity::ExternallyDefinedHatchStyle(_) => Some("EXTERNALLY_DEFINED_HATCH_STYLE"),
+            Entity::ExternallyDefinedItem(_) => Some("EXTERNALLY_DEFINED_ITEM"),
+            Entity::ExternallyDefinedItemRelationship(_) => Some("EXTERNALLY_DEFINED_ITEM_RELATIONSHIP"),
+            Entity::ExternallyDefinedStyle(_) => Some("EXTERNALLY_DEFINED_STYLE"),
+            Entity::ExternallyDefinedSymbol(_) => Some("EXTERNALLY_DEFINED_SYMBOL"),
+            Entity::ExternallyDefinedTextFont(_) => Some("EXTERNALLY_DEFINED_TEXT_FONT"),
+            Entity::ExternallyDefinedTileStyle(_) => Some("EXTERNALLY_DEFINED_TILE_STYLE"),
+            Entity::ExtrudedAreaSolid(_) => Some("EXTRUDED_AREA_SOLID"),
+            Entity::ExtrudedFaceSolid(_) => Some("EXTRUDED_FACE_SOLID"),
+            Entity::Face(_) => Some("FACE"),
+            Entity::FaceBasedSurfaceModel(_) => Some("FACE_BASED_SURFACE_MODEL"),
+            Entity::FaceBound(_) => Some("FACE_BOUND"),
+            Entity::FaceOuterBound(_) => Some("FACE_OUTER_BOUND"),
+            Entity::FaceShapeRepresentation(_) => Some("FACE_SHAPE_REPRESENTATION"),
+            Entity::FaceSurface(_) => Some("FACE_SURFACE"),
+            Entity::FacetedBrep(_) => Some("FACETED_BREP"),
+            Entity::FacetedBrepShapeRepresentation(_) => Some("FACETED_BREP_SHAPE_REPRESENTATION"),
+            Entity::FeatureComponentDefinition(_) => Some("FEATURE_COMPONENT_DEFINITION"),
+            Entity::FeatureComponentRelationship(_) => Some("FEATURE_COMPONENT_RELATIONSHIP"),
+            Entity::FeatureDefinition(_) => Some("FEATURE_DEFINITION"),
+            Entity::FeatureInPanel(_) => Some("FEATURE_IN_PANEL"),
+            Entity::FeaturePattern(_) => Some("FEATURE_PATTERN"),
+            Entity::FeaturedShape(_) => Some("FEATURED_SHAPE"),
+            Entity::FillAreaStyle(_) => Some("FILL_AREA_STYLE"),
+            Entity::FillAreaStyleColour(_) => Some("FILL_AREA_STYLE_COLOUR"),
+            Entity::FillAreaStyleHatching(_) => Some("FILL_AREA_STYLE_HATCHING"),
+            Entity::FillAreaStyleTileSymbolWithStyle(_) => Some("FILL_AREA_STYLE_TILE_SYMBOL_WITH_STYLE"),
+            Entity::FillAreaStyleTiles(_) => Some("FILL_AREA_STYLE_TILES"),
+            Entity::Fillet(_) => Some("FILLET"),
+            Entity::FlatnessTolerance(_) => Some("FLATNESS_TOLERANCE"),
+            Entity::FormatFunction(_) => Some("FORMAT_FUNCTION"),
+            Entity::FoundedItem(_) => Some("FOUNDED_ITEM"),
+            Entity::FoundedKinematicPath(_) => Some("FOUNDED_KINEMATIC_PATH"),
+            Entity::FullyConstrainedPair(_) => Some("FULLY_CONSTRAINED_PAIR"),
+            Entity::FunctionallyDefinedTransformation(_) => Some("FUNCTIONALLY_DEFINED_TRANSFORMATION"),
+            Entity::GearPair(_) => Some("GEAR_PAIR"),
+            Entity::GearPairRange(_) => Some("GEAR_PAIR_RANGE"),
+            Entity::GearPairValue(_) => Some("GEAR_PAIR_VALUE"),
+            Entity::GeneralFeature(_) => Some("GENERAL_FEATURE"),
+            Entity::GeneralMaterialProperty(_) => Some("GENERAL_MATERIAL_PROPERTY"),
+            Entity::GeneralProperty(_) => Some("GENERAL_PROPERTY"),
+            Entity::GeneralPropertyAssociation(_) => Some("GENERAL_PROPERTY_ASSOCIATION"),
+            Entity::GeneralPropertyRelationship(_) => Some("GENERAL_PROPERTY_RELATIONSHIP"),
+            Entity::GenericCharacterGlyphSymbol(_) => Some("GENERIC_CHARACTER_GLYPH_SYMBOL"),
+            Entity::GenericExpression(_) => Some("GENERIC_EXPRESSION"),
+            Entity::GenericLiteral(_) => Some("GENERIC_LITERAL"),
+            Entity::GenericVariable(_) => Some("GENERIC_VARIABLE"),
+            Entity::GeometricAlignment(_) => Some("GEOMETRIC_ALIGNMENT"),
+            Entity::GeometricCurveSet(_) => Some("GEOMETRIC_CURVE_SET"),
+            Entity::GeometricIntersection(_) => Some("GEOMETRIC_INTERSECTION"),
+            Entity::GeometricItemSpecificUsage(_) => Some("GEOMETRIC_ITEM_SPECIFIC_USAGE"),
+            Entity::GeometricRepresentationContext(_) => Some("GEOMETRIC_REPRESENTATION_CONTEXT"),
+            Entity::GeometricRepresentationItem(_) => Some("GEOMETRIC_REPRESENTATION_ITEM"),
+            Entity::GeometricSet(_) => Some("GEOMETRIC_SET"),
+            Entity::GeometricTolerance(_) => Some("GEOMETRIC_TOLERANCE"),
+            Entity::GeometricToleranceRelationship(_) => Some("GEOMETRIC_TOLERANCE_RELATIONSHIP"),
+            Entity::GeometricToleranceWithDatumReference(_) => Some("GEOMETRIC_TOLERANCE_WITH_DATUM_REFERENCE"),
+            Entity::GeometricToleranceWithDefinedUnit(_) => Some("GEOMETRIC_TOLERANCE_WITH_DEFINED_UNIT"),
+            Entity::GeometricalToleranceCallout(_) => Some("GEOMETRICAL_TOLERANCE_CALLOUT"),
+            Entity::GeometricallyBounded2dWireframeRepresentation(_) => Some("GEOMETRICALLY_BOUNDED_2D_WIREFRAME_REPRESENTATION"),
+            Entity::GeometricallyBoundedSurfaceShapeRepresentation(_) => Some("GEOMETRICALLY_BOUNDED_SURFACE_SHAPE_REPRESENTATION"),
+            Entity::GeometricallyBoundedWireframeShapeRepresentation(_) => Some("GEOMETRICALLY_BOUNDED_WIREFRAME_SHAPE_REPRESENTATION"),
+            Entity::GlobalUncertaintyAssignedContext(_) => Some("GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT"),
+            Entity::GlobalUnitAssignedContext(_) => Some("GLOBAL_UNIT_ASSIGNED_CONTEXT"),
+            Entity::Group(_) => Some("GROUP"),
+            Entity::GroupAssignment(_) => Some("GROUP_ASSIGNMENT"),
+            Entity::GroupRelationship(_) => Some("GROUP_RELATIONSHIP"),
+            Entity::HalfSpaceSolid(_) => Some("HALF_SPACE_SOLID"),
+            Entity::HardnessRepresentation(_) => Some("HARDNESS_REPRESENTATION"),
+            Entity::HiddenElementOverRidingStyledItem(_) => Some("HIDDEN_ELEMENT_OVER_RIDING_STYLED_ITEM"),
+            Entity::HoleBottom(_) => Some("HOLE_BOTTOM"),
+            Entity::HoleInPanel(_) => Some("HOLE_IN_PANEL"),
+            Entity::HomokineticPair(_) => Some("HOMOKINETIC_PAIR"),
+            Entity::Hyperbola(_) => Some("HYPERBOLA"),
+            Entity::IdAttribute(_) => Some("ID_ATTRIBUTE"),
+            Entity::IdentificationAssignment(_) => Some("IDENTIFICATION_ASSIGNMENT"),
+            Entity::IdentificationRole(_) => Some("IDENTIFICATION_ROLE"),
+            Entity::InclusionProductConceptFeature(_) => Some("INCLUSION_PRODUCT_CONCEPT_FEATURE"),
+            Entity::IndexExpression(_) => Some("INDEX_EXPRESSION"),
+            Entity::InitialState(_) => Some("INITIAL_STATE"),
+            Entity::InstancedFeature(_) => Some("INSTANCED_FEATURE"),
+            Entity::IntLiteral(_) => Some("INT_LITERAL"),
+            Entity::IntNumericVariable(_) => Some("INT_NUMERIC_VARIABLE"),
+            Entity::IntValueFunction(_) => Some("INT_VALUE_FUNCTION"),
+            Entity::IntegerDefinedFunction(_) => Some("INTEGER_DEFINED_FUNCTION"),
+            Entity::InterpolatedConfigurationSequence(_) => Some("INTERPOLATED_CONFIGURATION_SEQUENCE"),
+            Entity::IntersectionCurve(_) => Some("INTERSECTION_CURVE"),
+            Entity::IntervalExpression(_) => Some("INTERVAL_EXPRESSION"),
+            Entity::Invisibility(_) => Some("INVISIBILITY"),
+            Entity::ItemDefinedTransformation(_) => Some("ITEM_DEFINED_TRANSFORMATION"),
+            Entity::ItemIdentifiedRepresentationUsage(_) => Some("ITEM_IDENTIFIED_REPRESENTATION_USAGE"),
+            Entity::Joggle(_) => Some("JOGGLE"),
+            Entity::JoggleTermination(_) => Some("JOGGLE_TERMINATION"),
+            Entity::KinematicAnalysisConsistency(_) => Some("KINEMATIC_ANALYSIS_CONSISTENCY"),
+            Entity::KinematicAnalysisResult(_) => Some("KINEMATIC_ANALYSIS_RESULT"),
+            Entity::KinematicControl(_) => Some("KINEMATIC_CONTROL"),
+            Entity::KinematicFrameBackgroundRepresentation(_) => Some("KINEMATIC_FRAME_BACKGROUND_REPRESENTATION"),
+            Entity::KinematicFrameBackgroundRepresentationAssociation(_) => Some("KINEMATIC_FRAME_BACKGROUND_REPRESENTATION_ASSOCIATION"),
+            Entity::KinematicFrameBasedTransformation(_) => Some("KINEMATIC_FRAME_BASED_TRANSFORMATION"),
+            Entity::KinematicGroundRepresentation(_) => Some("KINEMATIC_GROUND_REPRESENTATION"),
+            Entity::KinematicJoint(_) => Some("KINEMATIC_JOINT"),
+            Entity::KinematicLink(_) => Some("KINEMATIC_LINK"),
+            Entity::KinematicLinkRepresentation(_) => Some("KINEMATIC_LINK_REPRESENTATION"),
+            Entity::KinematicLinkRepresentationAssociation(_) => Some("KINEMATIC_LINK_REPRESENTATION_ASSOCIATION"),
+            Entity::KinematicLinkRepresentationRelation(_) => Some("KINEMATIC_LINK_REPRESENTATION_RELATION"),
+            Entity::KinematicPair(_) => Some("KINEMATIC_PAIR"),
+            Entity::KinematicPath(_) => Some("KINEMATIC_PATH"),
+            Entity::KinematicPropertyDefinition(_) => Some("KINEMATIC_PROPERTY_DEFINITION"),
+            Entity::KinematicPropertyRepresentationRelation(_) => Some("KINEMATIC_PROPERTY_REPRESENTATION_RELATION"),
+            Entity::KinematicStructure(_) => Some("KINEMATIC_STRUCTURE"),
+            Entity::KnownSource(_) => Some("KNOWN_SOURCE"),
+            Entity::Language(_) => Some("LANGUAGE"),
+            Entity::LanguageAssignment(_) => Some("LANGUAGE_ASSIGNMENT"),
+            Entity::LeaderCurve(_) => Some("LEADER_CURVE"),
+            Entity::LeaderDirectedCallout(_) => Some("LEADER_DIRECTED_CALLOUT"),
+            Entity::LeaderDirectedDimension(_) => Some("LEADER_DIRECTED_DIMENSION"),
+            Entity::LeaderTerminator(_) => Some("LEADER_TERMINATOR"),
+            Entity::LengthFunction(_) => Some("LENGTH_FUNCTION"),
+            Entity::LengthMeasureWithUnit(_) => Some("LENGTH_MEASURE_WITH_UNIT"),
+            Entity::LengthUnit(_) => Some("LENGTH_UNIT"),
+            Entity::LightSource(_) => Some("LIGHT_SOURCE"),
+            Entity::LightSourceAmbient(_) => Some("LIGHT_SOURCE_AMBIENT"),
+            Entity::LightSourceDirectional(_) => Some("LIGHT_SOURCE_DIRECTIONAL"),
+            Entity::LightSourcePositional(_) => Some("LIGHT_SOURCE_POSITIONAL"),
+            Entity::LightSourceSpot(_) => Some("LIGHT_SOURCE_SPOT"),
+            Entity::LikeExpression(_) => Some("LIKE_EXPRESSION"),
+            Entity::LimitsAndFits(_) => Some("LIMITS_AND_FITS"),
+            Entity::Line(_) => Some("LINE"),
+            Entity::LineProfileTolerance(_) => Some("LINE_PROFILE_TOLERANCE"),
+            Entity::LinearDimension(_) => Some("LINEAR_DIMENSION"),
+            Entity::LiteralNumber(_) => Some("LITERAL_NUMBER"),
+            Entity::LocalTime(_) => Some("LOCAL_TIME"),
+            Entity::LocationShapeRepresentation(_) => Some("LOCATION_SHAPE_REPRESENTATION"),
+            Entity::Locator(_) => Some("LOCATOR"),
+            Entity::Log10Function(_) => Some("LOG10_FUNCTION"),
+            Entity::Log2Function(_) => Some("LOG2_FUNCTION"),
+            Entity::LogFunction(_) => Some("LOG_FUNCTION"),
+            Entity::Loop(_) => Some("LOOP"),
+            Entity::LotEffectivity(_) => Some("LOT_EFFECTIVITY"),
+            Entity::LuminousIntensityMeasureWithUnit(_) => Some("LUMINOUS_INTENSITY_MEASURE_WITH_UNIT"),
+            Entity::LuminousIntensityUnit(_) => Some("LUMINOUS_INTENSITY_UNIT"),
+            Entity::MakeFromUsageOption(_) => Some("MAKE_FROM_USAGE_OPTION"),
+            Entity::ManifoldSolidBrep(_) => Some("MANIFOLD_SOLID_BREP"),
+            Entity::ManifoldSubsurfaceShapeRepresentation(_) => Some("MANIFOLD_SUBSURFACE_SHAPE_REPRESENTATION"),
+            Entity::ManifoldSurfaceShapeRepresentation(_) => Some("MANIFOLD_SURFACE_SHAPE_REPRESENTATION"),
+            Entity::MappedItem(_) => Some("MAPPED_ITEM"),
+            Entity::MassMeasureWithUnit(_) => Some("MASS_MEASURE_WITH_UNIT"),
+            Entity::MassUnit(_) => Some("MASS_UNIT"),
+            Entity::MaterialDesignation(_) => Some("MATERIAL_DESIGNATION"),
+            Entity::MaterialDesignationCharacterization(_) => Some("MATERIAL_DESIGNATION_CHARACTERIZATION"),
+            Entity::MaterialProperty(_) => Some("MATERIAL_PROPERTY"),
+            Entity::MaterialPropertyRepresentation(_) => Some("MATERIAL_PROPERTY_REPRESENTATION"),
+            Entity::MaximumFunction(_) => Some("MAXIMUM_FUNCTION"),
+            Entity::MeasureQualification(_) => Some("MEASURE_QUALIFICATION"),
+            Entity::MeasureRepresentationItem(_) => Some("MEASURE_REPRESENTATION_ITEM"),
+            Entity::MeasureWithUnit(_) => Some("MEASURE_WITH_UNIT"),
+            Entity::MechanicalDesignGeometricPresentationArea(_) => Some("MECHANICAL_DESIGN_GEOMETRIC_PRESENTATION_AREA"),
+            Entity::MechanicalDesignGeometricPresentationRepresentation(_) => Some("MECHANICAL_DESIGN_GEOMETRIC_PRESENTATION_REPRESENTATION"),
+            Entity::Mechanism(_) => Some("MECHANISM"),
+            Entity::MechanismBasePlacement(_) => Some("MECHANISM_BASE_PLACEMENT"),
+            Entity::MinimumFunction(_) => Some("MINIMUM_FUNCTION"),
+            Entity::MinusExpression(_) => Some("MINUS_EXPRESSION"),
+            Entity::MinusFunction(_) => Some("MINUS_FUNCTION"),
+            Entity::ModExpression(_) => Some("MOD_EXPRESSION"),
+            Entity::ModifiedGeometricTolerance(_) => Some("MODIFIED_GEOMETRIC_TOLERANCE"),
+            Entity::ModifiedPattern(_) => Some("MODIFIED_PATTERN"),
+            Entity::MomentsOfInertiaRepresentation(_) => Some("MOMENTS_OF_INERTIA_REPRESENTATION"),
+            Entity::MotionLinkRelationship(_) => Some("MOTION_LINK_RELATIONSHIP"),
+            Entity::MultExpression(_) => Some("MULT_EXPRESSION"),
+            Entity::MultiLanguageAttributeAssignment(_) => Some("MULTI_LANGUAGE_ATTRIBUTE_ASSIGNMENT"),
+            Entity::MultipleArityBooleanExpression(_) => Some("MULTIPLE_ARITY_BOOLEAN_EXPRESSION"),
+            Entity::MultipleArityFunctionCall(_) => Some("MULTIPLE_ARITY_FUNCTION_CALL"),
+            Entity::MultipleArityGenericExpression(_) => Some("MULTIPLE_ARITY_GENERIC_EXPRESSION"),
+            Entity::MultipleArityNumericExpression(_) => Some("MULTIPLE_ARITY_NUMERIC_EXPRESSION"),
+            Entity::NameAssignment(_) => Some("NAME_ASSIGNMENT"),
+            Entity::NameAttribute(_) => Some("NAME_ATTRIBUTE"),
+            Entity::NamedUnit(_) => Some("NAMED_UNIT"),
+            Entity::NamedUnitVariable(_) => Some("NAMED_UNIT_VARIABLE"),
+            Entity::NextAssemblyUsageOccurrence(_) => Some("NEXT_ASSEMBLY_USAGE_OCCURRENCE"),
+            Entity::NgonClosedProfile(_) => Some("NGON_CLOSED_PROFILE"),
+            Entity::NonManifoldSurfaceShapeRepresentation(_) => Some("NON_MANIFOLD_SURFACE_SHAPE_REPRESENTATION"),
+            Entity::NotExpression(_) => Some("NOT_EXPRESSION"),
+            Entity::NumericDefinedFunction(_) => Some("NUMERIC_DEFINED_FUNCTION"),
+            Entity::NumericExpression(_) => Some("NUMERIC_EXPRESSION"),
+            Entity::NumericVariable(_) => Some("NUMERIC_VARIABLE"),
+            Entity::ObjectRole(_) => Some("OBJECT_ROLE"),
+            Entity::OddFunction(_) => Some("ODD_FUNCTION"),
+            Entity::OffsetCurve2d(_) => Some("OFFSET_CURVE_2D"),
+            Entity::OffsetCurve3d(_) => Some("OFFSET_CURVE_3D"),
+            Entity::OffsetSurface(_) => Some("OFFSET_SURFACE"),
+            Entity::OneDirectionRepeatFactor(_) => Some("ONE_DIRECTION_REPEAT_FACTOR"),
+            Entity::OpenPathProfile(_) => Some("OPEN_PATH_PROFILE"),
+            Entity::OpenShell(_) => Some("OPEN_SHELL"),
+            Entity::OrExpression(_) => Some("OR_EXPRESSION"),
+            Entity::OrdinateDimension(_) => Some("ORDINATE_DIMENSION"),
+            Entity::Organization(_) => Some("ORGANIZATION"),
+            Entity::OrganizationAssignment(_) => Some("ORGANIZATION_ASSIGNMENT"),
+            Entity::OrganizationRelationship(_) => Some("ORGANIZATION_RELATIONSHIP"),
+            Entity::OrganizationRole(_) => Some("ORGANIZATION_ROLE"),
+            Entity::OrganizationalAddress(_) => Some("ORGANIZATIONAL_ADDRESS"),
+            Entity::OrganizationalProject(_) => Some("ORGANIZATIONAL_PROJECT"),
+            Entity::OrganizationalProjectAssignment(_) => Some("ORGANIZATIONAL_PROJECT_ASSIGNMENT"),
+            Entity::OrganizationalProjectRelationship(_) => Some("ORGANIZATIONAL_PROJECT_RELATIONSHIP"),
+            Entity::OrganizationalProjectRole(_) => Some("ORGANIZATIONAL_PROJECT_ROLE"),
+            Entity::OrientedClosedShell(_) => Some("ORIENTED_CLOSED_SHELL"),
+            Entity::OrientedEdge(_) => Some("ORIENTED_EDGE"),
+            Entity::OrientedFace(_) => Some("ORIENTED_FACE"),
+            Entity::OrientedOpenShell(_) => Some("ORIENTED_OPEN_SHELL"),
+            Entity::OrientedPath(_) => Some("ORIENTED_PATH"),
+            Entity::OrientedSurface(_) => Some("ORIENTED_SURFACE"),
+            Entity::OuterBoundaryCurve(_) => Some("OUTER_BOUNDARY_CURVE"),
+            Entity::OverRidingStyledItem(_) => Some("OVER_RIDING_STYLED_ITEM"),
+            Entity::PackageProductConceptFeature(_) => Some("PACKAGE_PRODUCT_CONCEPT_FEATURE"),
+            Entity::PairActuator(_) => Some("PAIR_ACTUATOR"),
+            Entity::PairValue(_) => Some("PAIR_VALUE"),
+            Entity::Parabola(_) => Some("PARABOLA"),
+            Entity::ParallelOffset(_) => Some("PARALLEL_OFFSET"),
+            Entity::ParallelismTolerance(_) => Some("PARALLELISM_TOLERANCE"),
+            Entity::ParametricRepresentationContext(_) => Some("PARAMETRIC_REPRESENTATION_CONTEXT"),
+            Entity::PartialCircularProfile(_) => Some("PARTIAL_CIRCULAR_PROFILE"),
+            Entity::Path(_) => Some("PATH"),
+            Entity::PathFeatureComponent(_) => Some("PATH_FEATURE_COMPONENT"),
+            Entity::PathShapeRepresentation(_) => Some("PATH_SHAPE_REPRESENTATION"),
+            Entity::PatternOffsetMembership(_) => Some("PATTERN_OFFSET_MEMBERSHIP"),
+            Entity::PatternOmitMembership(_) => Some("PATTERN_OMIT_MEMBERSHIP"),
+            Entity::Pcurve(_) => Some("PCURVE"),
+            Entity::PerpendicularTo(_) => Some("PERPENDICULAR_TO"),
+            Entity::PerpendicularityTolerance(_) => Some("PERPENDICULARITY_TOLERANCE"),
+            Entity::Person(_) => Some("PERSON"),
+            Entity::PersonAndOrganization(_) => Some("PERSON_AND_ORGANIZATION"),
+            Entity::PersonAndOrganizationAddress(_) => Some("PERSON_AND_ORGANIZATION_ADDRESS"),
+            Entity::PersonAndOrganizationAssignment(_) => Some("PERSON_AND_ORGANIZATION_ASSIGNMENT"),
+            Entity::PersonAndOrganizationRole(_) => Some("PERSON_AND_ORGANIZATION_ROLE"),
+            Entity::PersonalAddress(_) => Some("PERSONAL_ADDRESS"),
+            Entity::PhysicallyModelledProductDefinition(_) => Some("PHYSICALLY_MODELLED_PRODUCT_DEFINITION"),
+            Entity::PlacedDatumTargetFeature(_) => Some("PLACED_DATUM_TARGET_FEATURE"),
+            Entity::PlacedFeature(_) => Some("PLACED_FEATURE"),
+            Entity::Placement(_) => Some("PLACEMENT"),
+            Entity::PlanarBox(_) => Some("PLANAR_BOX"),
+            Entity::PlanarCurvePair(_) => Some("PLANAR_CURVE_PAIR"),
+            Entity::PlanarCurvePairRange(_) => Some("PLANAR_CURVE_PAIR_RANGE"),
+            Entity::PlanarExtent(_) => Some("PLANAR_EXTENT"),
+            Entity::PlanarPair(_) => Some("PLANAR_PAIR"),
+            Entity::PlanarPairRange(_) => Some("PLANAR_PAIR_RANGE"),
+            Entity::PlanarPairValue(_) => Some("PLANAR_PAIR_VALUE"),
+            Entity::PlanarShapeRepresentation(_) => Some("PLANAR_SHAPE_REPRESENTATION"),
+            Entity::Plane(_) => Some("PLANE"),
+            Entity::PlaneAngleMeasureWithUnit(_) => Some("PLANE_ANGLE_MEASURE_WITH_UNIT"),
+            Entity::PlaneAngleUnit(_) => Some("PLANE_ANGLE_UNIT"),
+            Entity::PlusExpression(_) => Some("PLUS_EXPRESSION"),
+            Entity::PlusMinusTolerance(_) => Some("PLUS_MINUS_TOLERANCE"),
+            Entity::Pocket(_) => Some("POCKET"),
+            Entity::PocketBottom(_) => Some("POCKET_BOTTOM"),
+            Entity::Point(_) => Some("POINT"),
+            Entity::PointOnCurve(_) => Some("POINT_ON_CURVE"),
+            Entity::PointOnPlanarCurvePair(_) => Some("POINT_ON_PLANAR_CURVE_PAIR"),
+            Entity::PointOnPlanarCurvePairRange(_) => Some("POINT_ON_PLANAR_CURVE_PAIR_RANGE"),
+            Entity::PointOnPlanarCurvePairValue(_) => Some("POINT_ON_PLANAR_CURVE_PAIR_VALUE"),
+            Entity::PointOnSurface(_) => Some("POINT_ON_SURFACE"),
+            Entity::PointOnSurfacePair(_) => Some("POINT_ON_SURFACE_PAIR"),
+            Entity::PointOnSurfacePairRange(_) => Some("POINT_ON_SURFACE_PAIR_RANGE"),
+            Entity::PointOnSurfacePairValue(_) => Some("POINT_ON_SURFACE_PAIR_VALUE"),
+            Entity::PointPlacementShapeRepresentation(_) => Some("POINT_PLACEMENT_SHAPE_REPRESENTATION"),
+            Entity::PointReplica(_) => Some("POINT_REPLICA"),
+            Entity::PointStyle(_) => Some("POINT_STYLE"),
+            Entity::PolyLoop(_) => Some("POLY_LOOP"),
+            Entity::Polyline(_) => Some("POLYLINE"),
+            Entity::PositionTolerance(_) => Some("POSITION_TOLERANCE"),
+            Entity::PowerExpression(_) => Some("POWER_EXPRESSION"),
+            Entity::PreDefinedColour(_) => Some("PRE_DEFINED_COLOUR"),
+            Entity::PreDefinedCurveFont(_) => Some("PRE_DEFINED_CURVE_FONT"),
+            Entity::PreDefinedDimensionSymbol(_) => Some("PRE_DEFINED_DIMENSION_SYMBOL"),
+            Entity::PreDefinedGeometricalToleranceSymbol(_) => Some("PRE_DEFINED_GEOMETRICAL_TOLERANCE_SYMBOL"),
+            Entity::PreDefinedItem(_) => Some("PRE_DEFINED_ITEM"),
+            Entity::PreDefinedMarker(_) => Some("PRE_DEFINED_MARKER"),
+            Entity::PreDefinedPointMarkerSymbol(_) => Some("PRE_DEFINED_POINT_MARKER_SYMBOL"),
+            Entity::PreDefinedPresentationStyle(_) => Some("PRE_DEFINED_PRESENTATION_STYLE"),
+            Entity::PreDefinedSurfaceConditionSymbol(_) => Some("PRE_DEFINED_SURFACE_CONDITION_SYMBOL"),
+            Entity::PreDefinedSymbol(_) => Some("PRE_DEFINED_SYMBOL"),
+            Entity::PreDefinedTerminatorSymbol(_) => Some("PRE_DEFINED_TERMINATOR_SYMBOL"),
+            Entity::PreDefinedTextFont(_) => Some("PRE_DEFINED_TEXT_FONT"),
+            Entity::PrecisionQualifier(_) => Some("PRECISION_QUALIFIER"),
+            Entity::PresentationArea(_) => Some("PRESENTATION_AREA"),
+            Entity::PresentationLayerAssignment(_) => Some("PRESENTATION_LAYER_ASSIGNMENT"),
+            Entity::PresentationRepresentation(_) => Some("PRESENTATION_REPRESENTATION"),
+            Entity::PresentationSet(_) => Some("PRESENTATION_SET"),
+            Entity::PresentationSize(_) => Some("PRESENTATION_SIZE"),
+            Entity::PresentationStyleAssignment(_) => Some("PRESENTATION_STYLE_ASSIGNMENT"),
+            Entity::PresentationStyleByContext(_) => Some("PRESENTATION_STYLE_BY_CONTEXT"),
+            Entity::PresentationView(_) => Some("PRESENTATION_VIEW"),
+            Entity::PresentedItem(_) => Some("PRESENTED_ITEM"),
+            Entity::PresentedItemRepresentation(_) => Some("PRESENTED_ITEM_REPRESENTATION"),
+            Entity::PrismaticPair(_) => Some("PRISMATIC_PAIR"),
+            Entity::PrismaticPairRange(_) => Some("PRISMATIC_PAIR_RANGE"),
+            Entity::PrismaticPairValue(_) => Some("PRISMATIC_PAIR_VALUE"),
+            Entity::ProcessOperation(_) => Some("PROCESS_OPERATION"),
+            Entity::ProcessPlan(_) => Some("PROCESS_PLAN"),
+            Entity::ProcessProductAssociation(_) => Some("PROCESS_PRODUCT_ASSOCIATION"),
+            Entity::ProcessPropertyAssociation(_) => Some("PROCESS_PROPERTY_ASSOCIATION"),
+            Entity::Product(_) => Some("PRODUCT"),
+            Entity::ProductCategory(_) => Some("PRODUCT_CATEGORY"),
+            Entity::ProductCategoryRelationship(_) => Some("PRODUCT_CATEGORY_RELATIONSHIP"),
+            Entity::ProductClass(_) => Some("PRODUCT_CLASS"),
+            Entity::ProductConcept(_) => Some("PRODUCT_CONCEPT"),
+            Entity::ProductConceptContext(_) => Some("PRODUCT_CONCEPT_CONTEXT"),
+            Entity::ProductConceptFeature(_) => Some("PRODUCT_CONCEPT_FEATURE"),
+            Entity::ProductConceptFeatureAssociation(_) => Some("PRODUCT_CONCEPT_FEATURE_ASSOCIATION"),
+            Entity::ProductConceptFeatureCategory(_) => Some("PRODUCT_CONCEPT_FEATURE_CATEGORY"),
+            Entity::ProductConceptFeatureCategoryUsage(_) => Some("PRODUCT_CONCEPT_FEATURE_CATEGORY_USAGE"),
+            Entity::ProductConceptRelationship(_) => Some("PRODUCT_CONCEPT_RELATIONSHIP"),
+            Entity::ProductContext(_) => Some("PRODUCT_CONTEXT"),
+            Entity::ProductDefinition(_) => Some("PRODUCT_DEFINITION"),
+            Entity::ProductDefinitionContext(_) => Some("PRODUCT_DEFINITION_CONTEXT"),
+            Entity::ProductDefinitionContextAssociation(_) => Some("PRODUCT_DEFINITION_CONTEXT_ASSOCIATION"),
+            Entity::ProductDefinitionContextRole(_) => Some("PRODUCT_DEFINITION_CONTEXT_ROLE"),
+            Entity::ProductDefinitionEffectivity(_) => Some("PRODUCT_DEFINITION_EFFECTIVITY"),
+            Entity::ProductDefinitionFormation(_) => Some("PRODUCT_DEFINITION_FORMATION"),
+            Entity::ProductDefinitionFormationRelationship(_) => Some("PRODUCT_DEFINITION_FORMATION_RELATIONSHIP"),
+            Entity::ProductDefinitionFormationWithSpecifiedSource(_) => Some("PRODUCT_DEFINITION_FORMATION_WITH_SPECIFIED_SOURCE"),
+            Entity::ProductDefinitionOccurrenceRelationship(_) => Some("PRODUCT_DEFINITION_OCCURRENCE_RELATIONSHIP"),
+            Entity::ProductDefinitionProcess(_) => Some("PRODUCT_DEFINITION_PROCESS"),
+            Entity::ProductDefinitionRelationship(_) => Some("PRODUCT_DEFINITION_RELATIONSHIP"),
+            Entity::ProductDefinitionResource(_) => Some("PRODUCT_DEFINITION_RESOURCE"),
+            Entity::ProductDefinitionShape(_) => Some("PRODUCT_DEFINITION_SHAPE"),
+            Entity::ProductDefinitionSubstitute(_) => Some("PRODUCT_DEFINITION_SUBSTITUTE"),
+            Entity::ProductDefinitionUsage(_) => Some("PRODUCT_DEFINITION_USAGE"),
+            Entity::ProductDefinitionWithAssociatedDocuments(_) => Some("PRODUCT_DEFINITION_WITH_ASSOCIATED_DOCUMENTS"),
+            Entity::ProductIdentification(_) => Some("PRODUCT_IDENTIFICATION"),
+            Entity::ProductProcessPlan(_) => Some("PRODUCT_PROCESS_PLAN"),
+            Entity::ProductRelatedProductCategory(_) => Some("PRODUCT_RELATED_PRODUCT_CATEGORY"),
+            Entity::ProductSpecification(_) => Some("PRODUCT_SPECIFICATION"),
+            Entity::ProjectedZoneDefinition(_) => Some("PROJECTED_ZONE_DEFINITION"),
+            Entity::ProjectionCurve(_) => Some("PROJECTION_CURVE"),
+            Entity::ProjectionDirectedCallout(_) => Some("PROJECTION_DIRECTED_CALLOUT"),
+            Entity::PromissoryUsageOccurrence(_) => Some("PROMISSORY_USAGE_OCCURRENCE"),
+            Entity::PropertyDefinition(_) => Some("PROPERTY_DEFINITION"),
+            Entity::PropertyDefinitionRelationship(_) => Some("PROPERTY_DEFINITION_RELATIONSHIP"),
+            Entity::PropertyDefinitionRepresentation(_) => Some("PROPERTY_DEFINITION_REPRESENTATION"),
+            Entity::PropertyProcess(_) => Some("PROPERTY_PROCESS"),
+            Entity::QualifiedRepresentationItem(_) => Some("QUALIFIED_REPRESENTATION_ITEM"),
+            Entity::QualitativeUncertainty(_) => Some("QUALITATIVE_UNCERTAINTY"),
+            Entity::QuantifiedAssemblyComponentUsage(_) => Some("QUANTIFIED_ASSEMBLY_COMPONENT_USAGE"),
+            Entity::QuasiUniformCurve(_) => Some("QUASI_UNIFORM_CURVE"),
+            Entity::QuasiUniformSurface(_) => Some("QUASI_UNIFORM_SURFACE"),
+            Entity::RackAndPinionPair(_) => Some("RACK_AND_PINION_PAIR"),
+            Entity::RackAndPinionPairRange(_) => Some("RACK_AND_PINION_PAIR_RANGE"),
+            Entity::RackAndPinionPairValue(_) => Some("RACK_AND_PINION_PAIR_VALUE"),
+            Entity::RadiusDimension(_) => Some("RADIUS_DIMENSION"),
+            Entity::RatioMeasureWithUnit(_) => Some("RATIO_MEASURE_WITH_UNIT"),
+            Entity::RatioUnit(_) => Some("RATIO_UNIT"),
+            Entity::RationalBSplineCurve(_) => Some("RATIONAL_B_SPLINE_CURVE"),
+            Entity::RationalBSplineSurface(_) => Some("RATIONAL_B_SPLINE_SURFACE"),
+            Entity::RealDefinedFunction(_) => Some("REAL_DEFINED_FUNCTION"),
+            Entity::RealLiteral(_) => Some("REAL_LITERAL"),
+            Entity::RealNumericVariable(_) => Some("REAL_NUMERIC_VARIABLE"),
+            Entity::RectangularClosedProfile(_) => Some("RECTANGULAR_CLOSED_PROFILE"),
+            Entity::RectangularCompositeSurface(_) => Some("RECTANGULAR_COMPOSITE_SURFACE"),
+            Entity::RectangularPattern(_) => Some("RECTANGULAR_PATTERN"),
+            Entity::RectangularTrimmedSurface(_) => Some("RECTANGULAR_TRIMMED_SURFACE"),
+            Entity::ReferencedModifiedDatum(_) => Some("REFERENCED_MODIFIED_DATUM"),
+            Entity::RelativeEventOccurrence(_) => Some("RELATIVE_EVENT_OCCURRENCE"),
+            Entity::RepItemGroup(_) => Some("REP_ITEM_GROUP"),
+            Entity::ReparametrisedCompositeCurveSegment(_) => Some("REPARAMETRISED_COMPOSITE_CURVE_SEGMENT"),
+            Entity::ReplicateFeature(_) => Some("REPLICATE_FEATURE"),
+            Entity::Representation(_) => Some("REPRESENTATION"),
+            Entity::RepresentationContext(_) => Some("REPRESENTATION_CONTEXT"),
+            Entity::RepresentationItem(_) => Some("REPRESENTATION_ITEM"),
+            Entity::RepresentationMap(_) => Some("REPRESENTATION_MAP"),
+            Entity::RepresentationRelationship(_) => Some("REPRESENTATION_RELATIONSHIP"),
+            Entity::RepresentationRelationshipWithTransformation(_) => Some("REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION"),
+            Entity::RequirementForActionResource(_) => Some("REQUIREMENT_FOR_ACTION_RESOURCE"),
+            Entity::ResourceProperty(_) => Some("RESOURCE_PROPERTY"),
+            Entity::ResourcePropertyRepresentation(_) => Some("RESOURCE_PROPERTY_REPRESENTATION"),
+            Entity::ResourceRequirementType(_) => Some("RESOURCE_REQUIREMENT_TYPE"),
+            Entity::ResultingPath(_) => Some("RESULTING_PATH"),
+            Entity::Retention(_) => Some("RETENTION"),
+            Entity::RevolutePair(_) => Some("REVOLUTE_PAIR"),
+            Entity::RevolutePairRange(_) => Some("REVOLUTE_PAIR_RANGE"),
+            Entity::RevolutePairValue(_) => Some("REVOLUTE_PAIR_VALUE"),
+            Entity::RevolvedAreaSolid(_) => Some("REVOLVED_AREA_SOLID"),
+            Entity::RevolvedFaceSolid(_) => Some("REVOLVED_FACE_SOLID"),
+            Entity::Rib(_) => Some("RIB"),
+            Entity::RightAngularWedge(_) => Some("RIGHT_ANGULAR_WEDGE"),
+            Entity::RightCircularCone(_) => Some("RIGHT_CIRCULAR_CONE"),
+            Entity::RightCircularCylinder(_) => Some("RIGHT_CIRCULAR_CYLINDER"),
+            Entity::RoleAssociation(_) => Some("ROLE_ASSOCIATION"),
+            Entity::RollingCurvePair(_) => Some("ROLLING_CURVE_PAIR"),
+            Entity::RollingCurvePairValue(_) => Some("ROLLING_CURVE_PAIR_VALUE"),
+            Entity::RollingSurfacePair(_) => Some("ROLLING_SURFACE_PAIR"),
+            Entity::RollingSurfacePairValue(_) => Some("ROLLING_SURFACE_PAIR_VALUE"),
+            Entity::RotationAboutDirection(_) => Some("ROTATION_ABOUT_DIRECTION"),
+            Entity::RoundHole(_) => Some("ROUND_HOLE"),
+            Entity::RoundedUProfile(_) => Some("ROUNDED_U_PROFILE"),
+            Entity::RoundnessTolerance(_) => Some("ROUNDNESS_TOLERANCE"),
+            Entity::RuledSurfaceSweptAreaSolid(_) => Some("RULED_SURFACE_SWEPT_AREA_SOLID"),
+            Entity::RunoutZoneDefinition(_) => Some("RUNOUT_ZONE_DEFINITION"),
+            Entity::RunoutZoneOrientation(_) => Some("RUNOUT_ZONE_ORIENTATION"),
+            Entity::RunoutZoneOrientationReferenceDirection(_) => Some("RUNOUT_ZONE_ORIENTATION_REFERENCE_DIRECTION"),
+            Entity::ScrewPair(_) => Some("SCREW_PAIR"),
+            Entity::ScrewPairRange(_) => Some("SCREW_PAIR_RANGE"),
+            Entity::ScrewPairValue(_) => Some("SCREW_PAIR_VALUE"),
+            Entity::SeamCurve(_) => Some("SEAM_CURVE"),
+            Entity::SeamEdge(_) => Some("SEAM_EDGE"),
+            Entity::SecurityClassification(_) => Some("SECURITY_CLASSIFICATION"),
+            Entity::SecurityClassificationAssignment(_) => Some("SECURITY_CLASSIFICATION_ASSIGNMENT"),
+            Entity::SecurityClassificationLevel(_) => Some("SECURITY_CLASSIFICATION_LEVEL"),
+            Entity::SerialNumberedEffectivity(_) => Some("SERIAL_NUMBERED_EFFECTIVITY"),
+            Entity::ShapeAspect(_) => Some("SHAPE_ASPECT"),
+            Entity::ShapeAspectAssociativity(_) => Some("SHAPE_ASPECT_ASSOCIATIVITY"),
+            Entity::ShapeAspectDerivingRelationship(_) => Some("SHAPE_ASPECT_DERIVING_RELATIONSHIP"),
+            Entity::ShapeAspectRelationship(_) => Some("SHAPE_ASPECT_RELATIONSHIP"),
+            Entity::ShapeAspectTransition(_) => Some("SHAPE_ASPECT_TRANSITION"),
+            Entity::ShapeDefiningRelationship(_) => Some("SHAPE_DEFINING_RELATIONSHIP"),
+            Entity::ShapeDefinitionRepresentation(_) => Some("SHAPE_DEFINITION_REPRESENTATION"),
+            Entity::ShapeDimensionRepresentation(_) => Some("SHAPE_DIMENSION_REPRESENTATION"),
+            Entity::ShapeRepresentation(_) => Some("SHAPE_REPRESENTATION"),
+            Entity::ShapeRepresentationRelationship(_) => Some("SHAPE_REPRESENTATION_RELATIONSHIP"),
+            Entity::ShapeRepresentationWithParameters(_) => Some("SHAPE_REPRESENTATION_WITH_PARAMETERS"),
+            Entity::ShellBasedSurfaceModel(_) => Some("SHELL_BASED_SURFACE_MODEL"),
+            Entity::SiUnit(_) => Some("SI_UNIT"),
+            Entity::SimpleBooleanExpression(_) => Some("SIMPLE_BOOLEAN_EXPRESSION"),
+            Entity::SimpleGenericExpression(_) => Some("SIMPLE_GENERIC_EXPRESSION"),
+            Entity::SimpleNumericExpression(_) => Some("SIMPLE_NUMERIC_EXPRESSION"),
+            Entity::SimplePairRange(_) => Some("SIMPLE_PAIR_RANGE"),
+            Entity::SimpleStringExpression(_) => Some("SIMPLE_STRING_EXPRESSION"),
+            Entity::SinFunction(_) => Some("SIN_FUNCTION"),
+            Entity::SlashExpression(_) => Some("SLASH_EXPRESSION"),
+            Entity::SlidingCurvePair(_) => Some("SLIDING_CURVE_PAIR"),
+            Entity::SlidingCurvePairValue(_) => Some("SLIDING_CURVE_PAIR_VALUE"),
+            Entity::SlidingSurfacePair(_) => Some("SLIDING_SURFACE_PAIR"),
+            Entity::SlidingSurfacePairValue(_) => Some("SLIDING_SURFACE_PAIR_VALUE"),
+            Entity::Slot(_) => Some("SLOT"),
+            Entity::SlotEnd(_) => Some("SLOT_END"),
+            Entity::SolidAngleMeasureWithUnit(_) => Some("SOLID_ANGLE_MEASURE_WITH_UNIT"),
+            Entity::SolidAngleUnit(_) => Some("SOLID_ANGLE_UNIT"),
+            Entity::SolidModel(_) => Some("SOLID_MODEL"),
+            Entity::SolidReplica(_) => Some("SOLID_REPLICA"),
+            Entity::SpecifiedHigherUsageOccurrence(_) => Some("SPECIFIED_HIGHER_USAGE_OCCURRENCE"),
+            Entity::Sphere(_) => Some("SPHERE"),
+            Entity::SphericalPair(_) => Some("SPHERICAL_PAIR"),
+            Entity::SphericalPairRange(_) => Some("SPHERICAL_PAIR_RANGE"),
+            Entity::SphericalPairValue(_) => Some("SPHERICAL_PAIR_VALUE"),
+            Entity::SphericalSurface(_) => Some("SPHERICAL_SURFACE"),
+            Entity::SqlMappableDefinedFunction(_) => Some("SQL_MAPPABLE_DEFINED_FUNCTION"),
+            Entity::SquareRootFunction(_) => Some("SQUARE_ROOT_FUNCTION"),
+            Entity::SquareUProfile(_) => Some("SQUARE_U_PROFILE"),
+            Entity::StandardUncertainty(_) => Some("STANDARD_UNCERTAINTY"),
+            Entity::StraightnessTolerance(_) => Some("STRAIGHTNESS_TOLERANCE"),
+            Entity::StringDefinedFunction(_) => Some("STRING_DEFINED_FUNCTION"),
+            Entity::StringExpression(_) => Some("STRING_EXPRESSION"),
+            Entity::StringLiteral(_) => Some("STRING_LITERAL"),
+            Entity::StringVariable(_) => Some("STRING_VARIABLE"),
+            Entity::StructuredDimensionCallout(_) => Some("STRUCTURED_DIMENSION_CALLOUT"),
+            Entity::StyledItem(_) => Some("STYLED_ITEM"),
+            Entity::Subedge(_) => Some("SUBEDGE"),
+            Entity::Subface(_) => Some("SUBFACE"),
+            Entity::SubstringExpression(_) => Some("SUBSTRING_EXPRESSION"),
+            Entity::Surface(_) => Some("SURFACE"),
+            Entity::SurfaceConditionCallout(_) => Some("SURFACE_CONDITION_CALLOUT"),
+            Entity::SurfaceCurve(_) => Some("SURFACE_CURVE"),
+            Entity::SurfaceCurveSweptAreaSolid(_) => Some("SURFACE_CURVE_SWEPT_AREA_SOLID"),
+            Entity::SurfaceOfLinearExtrusion(_) => Some("SURFACE_OF_LINEAR_EXTRUSION"),
+            Entity::SurfaceOfRevolution(_) => Some("SURFACE_OF_REVOLUTION"),
+            Entity::SurfacePair(_) => Some("SURFACE_PAIR"),
+            Entity::SurfacePairRange(_) => Some("SURFACE_PAIR_RANGE"),
+            Entity::SurfacePatch(_) => Some("SURFACE_PATCH"),
+            Entity::SurfaceProfileTolerance(_) => Some("SURFACE_PROFILE_TOLERANCE"),
+            Entity::SurfaceRenderingProperties(_) => Some("SURFACE_RENDERING_PROPERTIES"),
+            Entity::SurfaceReplica(_) => Some("SURFACE_REPLICA"),
+            Entity::SurfaceSideStyle(_) => Some("SURFACE_SIDE_STYLE"),
+            Entity::SurfaceStyleBoundary(_) => Some("SURFACE_STYLE_BOUNDARY"),
+            Entity::SurfaceStyleControlGrid(_) => Some("SURFACE_STYLE_CONTROL_GRID"),
+            Entity::SurfaceStyleFillArea(_) => Some("SURFACE_STYLE_FILL_AREA"),
+            Entity::SurfaceStyleParameterLine(_) => Some("SURFACE_STYLE_PARAMETER_LINE"),
+            Entity::SurfaceStyleReflectanceAmbient(_) => Some("SURFACE_STYLE_REFLECTANCE_AMBIENT"),
+            Entity::SurfaceStyleReflectanceAmbientDiffuse(_) => Some("SURFACE_STYLE_REFLECTANCE_AMBIENT_DIFFUSE"),
+            Entity::SurfaceStyleReflectanceAmbientDiffuseSpecular(_) => Some("SURFACE_STYLE_REFLECTANCE_AMBIENT_DIFFUSE_SPECULAR"),
+            Entity::SurfaceStyleRendering(_) => Some("SURFACE_STYLE_RENDERING"),
+            Entity::SurfaceStyleRenderingWithProperties(_) => Some("SURFACE_STYLE_RENDERING_WITH_PROPERTIES"),
+            Entity::SurfaceStyleSegmentationCurve(_) => Some("SURFACE_STYLE_SEGMENTATION_CURVE"),
+            Entity::SurfaceStyleSilhouette(_) => Some("SURFACE_STYLE_SILHOUETTE"),
+            Entity::SurfaceStyleTransparent(_) => Some("SURFACE_STYLE_TRANSPARENT"),
+            Entity::SurfaceStyleUsage(_) => Some("SURFACE_STYLE_USAGE"),
+            Entity::SurfaceTextureRepresentation(_) => Some("SURFACE_TEXTURE_REPRESENTATION"),
+            Entity::SweptAreaSolid(_) => Some("SWEPT_AREA_SOLID"),
+            Entity::SweptDiskSolid(_) => Some("SWEPT_DISK_SOLID"),
+            Entity::SweptFaceSolid(_) => Some("SWEPT_FACE_SOLID"),
+            Entity::SweptSurface(_) => Some("SWEPT_SURFACE"),
+            Entity::SymbolColour(_) => Some("SYMBOL_COLOUR"),
+            Entity::SymbolRepresentation(_) => Some("SYMBOL_REPRESENTATION"),
+            Entity::SymbolRepresentationMap(_) => Some("SYMBOL_REPRESENTATION_MAP"),
+            Entity::SymbolStyle(_) => Some("SYMBOL_STYLE"),
+            Entity::SymbolTarget(_) => Some("SYMBOL_TARGET"),
+            Entity::SymmetricShapeAspect(_) => Some("SYMMETRIC_SHAPE_ASPECT"),
+            Entity::SymmetryTolerance(_) => Some("SYMMETRY_TOLERANCE"),
+            Entity::TactileAppearanceRepresentation(_) => Some("TACTILE_APPEARANCE_REPRESENTATION"),
+            Entity::TanFunction(_) => Some("TAN_FUNCTION"),
+            Entity::Tangent(_) => Some("TANGENT"),
+            Entity::Taper(_) => Some("TAPER"),
+            Entity::TeeProfile(_) => Some("TEE_PROFILE"),
+            Entity::TerminatorSymbol(_) => Some("TERMINATOR_SYMBOL"),
+            Entity::TextLiteral(_) => Some("TEXT_LITERAL"),
+            Entity::TextLiteralWithAssociatedCurves(_) => Some("TEXT_LITERAL_WITH_ASSOCIATED_CURVES"),
+            Entity::TextLiteralWithBlankingBox(_) => Some("TEXT_LITERAL_WITH_BLANKING_BOX"),
+            Entity::TextLiteralWithDelineation(_) => Some("TEXT_LITERAL_WITH_DELINEATION"),
+            Entity::TextLiteralWithExtent(_) => Some("TEXT_LITERAL_WITH_EXTENT"),
+            Entity::TextStringRepresentation(_) => Some("TEXT_STRING_REPRESENTATION"),
+            Entity::TextStyle(_) => Some("TEXT_STYLE"),
+            Entity::TextStyleForDefinedFont(_) => Some("TEXT_STYLE_FOR_DEFINED_FONT"),
+            Entity::TextStyleWithBoxCharacteristics(_) => Some("TEXT_STYLE_WITH_BOX_CHARACTERISTICS"),
+            Entity::TextStyleWithMirror(_) => Some("TEXT_STYLE_WITH_MIRROR"),
+            Entity::TextStyleWithSpacing(_) => Some("TEXT_STYLE_WITH_SPACING"),
+            Entity::ThermodynamicTemperatureMeasureWithUnit(_) => Some("THERMODYNAMIC_TEMPERATURE_MEASURE_WITH_UNIT"),
+            Entity::ThermodynamicTemperatureUnit(_) => Some("THERMODYNAMIC_TEMPERATURE_UNIT"),
+            Entity::Thread(_) => Some("THREAD"),
+            Entity::TimeInterval(_) => Some("TIME_INTERVAL"),
+            Entity::TimeIntervalAssignment(_) => Some("TIME_INTERVAL_ASSIGNMENT"),
+            Entity::TimeIntervalBasedEffectivity(_) => Some("TIME_INTERVAL_BASED_EFFECTIVITY"),
+            Entity::TimeIntervalRole(_) => Some("TIME_INTERVAL_ROLE"),
+            Entity::TimeIntervalWithBounds(_) => Some("TIME_INTERVAL_WITH_BOUNDS"),
+            Entity::TimeMeasureWithUnit(_) => Some("TIME_MEASURE_WITH_UNIT"),
+            Entity::TimeUnit(_) => Some("TIME_UNIT"),
+            Entity::ToleranceValue(_) => Some("TOLERANCE_VALUE"),
+            Entity::ToleranceZone(_) => Some("TOLERANCE_ZONE"),
+            Entity::ToleranceZoneDefinition(_) => Some("TOLERANCE_ZONE_DEFINITION"),
+            Entity::ToleranceZoneForm(_) => Some("TOLERANCE_ZONE_FORM"),
+            Entity::TopologicalRepresentationItem(_) => Some("TOPOLOGICAL_REPRESENTATION_ITEM"),
+            Entity::ToroidalSurface(_) => Some("TOROIDAL_SURFACE"),
+            Entity::Torus(_) => Some("TORUS"),
+            Entity::TotalRunoutTolerance(_) => Some("TOTAL_RUNOUT_TOLERANCE"),
+            Entity::TransitionFeature(_) => Some("TRANSITION_FEATURE"),
+            Entity::TrimmedCurve(_) => Some("TRIMMED_CURVE"),
+            Entity::TwoDirectionRepeatFactor(_) => Some("TWO_DIRECTION_REPEAT_FACTOR"),
+            Entity::TypeQualifier(_) => Some("TYPE_QUALIFIER"),
+            Entity::UnaryBooleanExpression(_) => Some("UNARY_BOOLEAN_EXPRESSION"),
+            Entity::UnaryFunctionCall(_) => Some("UNARY_FUNCTION_CALL"),
+            Entity::UnaryGenericExpression(_) => Some("UNARY_GENERIC_EXPRESSION"),
+            Entity::UnaryNumericExpression(_) => Some("UNARY_NUMERIC_EXPRESSION"),
+            Entity::UncertaintyAssignedRepresentation(_) => Some("UNCERTAINTY_ASSIGNED_REPRESENTATION"),
+            Entity::UncertaintyMeasureWithUnit(_) => Some("UNCERTAINTY_MEASURE_WITH_UNIT"),
+            Entity::UncertaintyQualifier(_) => Some("UNCERTAINTY_QUALIFIER"),
+            Entity::UnconstrainedPair(_) => Some("UNCONSTRAINED_PAIR"),
+            Entity::UnconstrainedPairValue(_) => Some("UNCONSTRAINED_PAIR_VALUE"),
+            Entity::UniformCurve(_) => Some("UNIFORM_CURVE"),
+            Entity::UniformSurface(_) => Some("UNIFORM_SURFACE"),
+            Entity::UniversalPair(_) => Some("UNIVERSAL_PAIR"),
+            Entity::UniversalPairRange(_) => Some("UNIVERSAL_PAIR_RANGE"),
+            Entity::UniversalPairValue(_) => Some("UNIVERSAL_PAIR_VALUE"),
+            Entity::ValueFunction(_) => Some("VALUE_FUNCTION"),
+            Entity::ValueRange(_) => Some("VALUE_RANGE"),
+            Entity::ValueRepresentationItem(_) => Some("VALUE_REPRESENTATION_ITEM"),
+            Entity::Variable(_) => Some("VARIABLE"),
+            Entity::VariableSemantics(_) => Some("VARIABLE_SEMANTICS"),
+            Entity::Vector(_) => Some("VECTOR"),
+            Entity::VectorStyle(_) => Some("VECTOR_STYLE"),
+            Entity::VeeProfile(_) => Some("VEE_PROFILE"),
+            Entity::VersionedActionRequest(_) => Some("VERSIONED_ACTION_REQUEST"),
+            Entity::VersionedActionRequestRelationship(_) => Some("VERSIONED_ACTION_REQUEST_RELATIONSHIP"),
+            Entity::Vertex(_) => Some("VERTEX"),
+            Entity::VertexLoop(_) => Some("VERTEX_LOOP"),
+            Entity::VertexPoint(_) => Some("VERTEX_POINT"),
+            Entity::ViewVolume(_) => Some("VIEW_VOLUME"),
+            Entity::VisualAppearanceRepresentation(_) => Some("VISUAL_APPEARANCE_REPRESENTATION"),
+            Entity::VolumeMeasureWithUnit(_) => Some("VOLUME_MEASURE_WITH_UNIT"),
+            Entity::VolumeUnit(_) => Some("VOLUME_UNIT"),
+            Entity::XorExpression(_) => Some("XOR_EXPRESSION"),
+            _ => None,
+        }
+    }
+}
+pub const KEYWORDS: &[&str] = &[
+    "ABS_FUNCTION",
+    "ACOS_FUNCTION",
+    "ACTION",
+    "ACTION_ASSIGNMENT",
+    "ACTION_DIRECTIVE",
+    "ACTION_METHOD",
+    "ACTION_METHOD_RELATIONSHIP",
+    "ACTION_PROPERTY",
+    "ACTION_PROPERTY_REPRESENTATION",
+    "ACTION_RELATIONSHIP",
+    "ACTION_REQUEST_ASSIGNMENT",
+    "ACTION_REQUEST_SOLUTION",
+    "ACTION_REQUEST_STATUS",
+    "ACTION_RESOURCE",
+    "ACTION_RESOURCE_REQUIREMENT",
+    "ACTION_RESOURCE_TYPE",
+    "ACTION_STATUS",
+    "ADDRESS",
+    "ADVANCED_BREP_SHAPE_REPRESENTATION",
+    "ADVANCED_FACE",
+    "ALTERNATE_PRODUCT_RELATIONSHIP",
+    "AMOUNT_OF_SUBSTANCE_MEASURE_WITH_UNIT",
+    "AMOUNT_OF_SUBSTANCE_UNIT",
+    "AND_EXPRESSION",
+    "ANGULAR_DIMENSION",
+    "ANGULAR_LOCATION",
+    "ANGULAR_SIZE",
+    "ANGULARITY_TOLERANCE",
+    "ANNOTATION_CURVE_OCCURRENCE",
+    "ANNOTATION_FILL_AREA",
+    "ANNOTATION_FILL_AREA_OCCURRENCE",
+    "ANNOTATION_OCCURRENCE",
+    "ANNOTATION_OCCURRENCE_ASSOCIATIVITY",
+    "ANNOTATION_OCCURRENCE_RELATIONSHIP",
+    "ANNOTATION_PLANE",
+    "ANNOTATION_SUBFIGURE_OCCURRENCE",
+    "ANNOTATION_SYMBOL",
+    "ANNOTATION_SYMBOL_OCCURRENCE",
+    "ANNOTATION_TEXT",
+    "ANNOTATION_TEXT_CHARACTER",
+    "ANNOTATION_TEXT_OCCURRENCE",
+    "APEX",
+    "APPLICATION_CONTEXT",
+    "APPLICATION_CONTEXT_ELEMENT",
+    "APPLICATION_CONTEXT_RELATIONSHIP",
+    "APPLICATION_PROTOCOL_DEFINITION",
+    "APPLIED_ACTION_ASSIGNMENT",
+    "APPLIED_ACTION_REQUEST_ASSIGNMENT",
+    "APPLIED_APPROVAL_ASSIGNMENT",
+    "APPLIED_AREA",
+    "APPLIED_CERTIFICATION_ASSIGNMENT",
+    "APPLIED_CLASSIFICATION_ASSIGNMENT",
+    "APPLIED_CONTRACT_ASSIGNMENT",
+    "APPLIED_DATE_AND_TIME_ASSIGNMENT",
+    "APPLIED_DATE_ASSIGNMENT",
+    "APPLIED_DOCUMENT_REFERENCE",
+    "APPLIED_DOCUMENT_USAGE_CONSTRAINT_ASSIGNMENT",
+    "APPLIED_EFFECTIVITY_ASSIGNMENT",
+    "APPLIED_EVENT_OCCURRENCE_ASSIGNMENT",
+    "APPLIED_EXTERNAL_IDENTIFICATION_ASSIGNMENT",
+    "APPLIED_GROUP_ASSIGNMENT",
+    "APPLIED_IDENTIFICATION_ASSIGNMENT",
+    "APPLIED_INEFFECTIVITY_ASSIGNMENT",
+    "APPLIED_NAME_ASSIGNMENT",
+    "APPLIED_ORGANIZATION_ASSIGNMENT",
+    "APPLIED_ORGANIZATIONAL_PROJECT_ASSIGNMENT",
+    "APPLIED_PERSON_AND_ORGANIZATION_ASSIGNMENT",
+    "APPLIED_PRESENTED_ITEM",
+    "APPLIED_SECURITY_CLASSIFICATION_ASSIGNMENT",
+    "APPLIED_TIME_INTERVAL_ASSIGNMENT",
+    "APPROVAL",
+    "APPROVAL_ASSIGNMENT",
+    "APPROVAL_DATE_TIME",
+    "APPROVAL_PERSON_ORGANIZATION",
+    "APPROVAL_RELATIONSHIP",
+    "APPROVAL_ROLE",
+    "APPROVAL_STATUS",
+    "APPROXIMATION_TOLERANCE",
+    "APPROXIMATION_TOLERANCE_DEVIATION",
+    "APPROXIMATION_TOLERANCE_PARAMETER",
+    "AREA_IN_SET",
+    "AREA_MEASURE_WITH_UNIT",
+    "AREA_UNIT",
+    "ASIN_FUNCTION",
+    "ASSEMBLY_COMPONENT_USAGE",
+    "ASSEMBLY_COMPONENT_USAGE_SUBSTITUTE",
+    "ATAN_FUNCTION",
+    "ATTRIBUTE_CLASSIFICATION_ASSIGNMENT",
+    "ATTRIBUTE_LANGUAGE_ASSIGNMENT",
+    "ATTRIBUTE_VALUE_ASSIGNMENT",
+    "ATTRIBUTE_VALUE_ROLE",
+    "AXIS1_PLACEMENT",
+    "AXIS2_PLACEMENT_2D",
+    "AXIS2_PLACEMENT_3D",
+    "B_SPLINE_CURVE",
+    "B_SPLINE_CURVE_WITH_KNOTS",
+    "B_SPLINE_SURFACE",
+    "B_SPLINE_SURFACE_WITH_KNOTS",
+    "BACKGROUND_COLOUR",
+    "BARRING_HOLE",
+    "BEAD",
+    "BEAD_END",
+    "BEZIER_CURVE",
+    "BEZIER_SURFACE",
+    "BINARY_BOOLEAN_EXPRESSION",
+    "BINARY_FUNCTION_CALL",
+    "BINARY_GENERIC_EXPRESSION",
+    "BINARY_NUMERIC_EXPRESSION",
+    "BLOCK",
+    "BOOLEAN_DEFINED_FUNCTION",
+    "BOOLEAN_EXPRESSION",
+    "BOOLEAN_LITERAL",
+    "BOOLEAN_RESULT",
+    "BOOLEAN_VARIABLE",
+    "BOSS",
+    "BOSS_TOP",
+    "BOUNDARY_CURVE",
+    "BOUNDED_CURVE",
+    "BOUNDED_PCURVE",
+    "BOUNDED_SURFACE",
+    "BOUNDED_SURFACE_CURVE",
+    "BOX_DOMAIN",
+    "BOXED_HALF_SPACE",
+    "BREP_WITH_VOIDS",
+    "CALENDAR_DATE",
+    "CAMERA_IMAGE",
+    "CAMERA_IMAGE_2D_WITH_SCALE",
+    "CAMERA_IMAGE_3D_WITH_SCALE",
+    "CAMERA_MODEL",
+    "CAMERA_MODEL_D2",
+    "CAMERA_MODEL_D3",
+    "CAMERA_MODEL_D3_WITH_HLHSR",
+    "CAMERA_USAGE",
+    "CARTESIAN_POINT",
+    "CARTESIAN_TRANSFORMATION_OPERATOR",
+    "CARTESIAN_TRANSFORMATION_OPERATOR_2D",
+    "CARTESIAN_TRANSFORMATION_OPERATOR_3D",
+    "CELSIUS_TEMPERATURE_MEASURE_WITH_UNIT",
+    "CENTRE_OF_SYMMETRY",
+    "CERTIFICATION",
+    "CERTIFICATION_ASSIGNMENT",
+    "CERTIFICATION_TYPE",
+    "CHAMFER",
+    "CHAMFER_OFFSET",
+    "CHARACTER_GLYPH_SYMBOL",
+    "CHARACTERIZED_CLASS",
+    "CHARACTERIZED_OBJECT",
+    "CIRCLE",
+    "CIRCULAR_CLOSED_PROFILE",
+    "CIRCULAR_PATTERN",
+    "CIRCULAR_RUNOUT_TOLERANCE",
+    "CLASS",
+    "CLASS_SYSTEM",
+    "CLASS_USAGE_EFFECTIVITY_CONTEXT_ASSIGNMENT",
+    "CLASSIFICATION_ASSIGNMENT",
+    "CLASSIFICATION_ROLE",
+    "CLOSED_PATH_PROFILE",
+    "CLOSED_SHELL",
+    "COAXIALITY_TOLERANCE",
+    "COLOUR",
+    "COLOUR_RGB",
+    "COLOUR_SPECIFICATION",
+    "COMMON_DATUM",
+    "COMPARISON_EQUAL",
+    "COMPARISON_EXPRESSION",
+    "COMPARISON_GREATER",
+    "COMPARISON_GREATER_EQUAL",
+    "COMPARISON_LESS",
+    "COMPARISON_LESS_EQUAL",
+    "COMPARISON_NOT_EQUAL",
+    "COMPOSITE_CURVE",
+    "COMPOSITE_CURVE_ON_SURFACE",
+    "COMPOSITE_CURVE_SEGMENT",
+    "COMPOSITE_HOLE",
+    "COMPOSITE_SHAPE_ASPECT",
+    "COMPOSITE_TEXT",
+    "COMPOSITE_TEXT_WITH_ASSOCIATED_CURVES",
+    "COMPOSITE_TEXT_WITH_BLANKING_BOX",
+    "COMPOSITE_TEXT_WITH_EXTENT",
+    "COMPOUND_FEATURE",
+    "COMPOUND_REPRESENTATION_ITEM",
+    "COMPOUND_SHAPE_REPRESENTATION",
+    "CONCAT_EXPRESSION",
+    "CONCENTRICITY_TOLERANCE",
+    "CONCEPT_FEATURE_OPERATOR",
+    "CONCEPT_FEATURE_RELATIONSHIP",
+    "CONCEPT_FEATURE_RELATIONSHIP_WITH_CONDITION",
+    "CONDITIONAL_CONCEPT_FEATURE",
+    "CONFIGURABLE_ITEM",
+    "CONFIGURATION_DEFINITION",
+    "CONFIGURATION_DESIGN",
+    "CONFIGURATION_EFFECTIVITY",
+    "CONFIGURATION_INTERPOLATION",
+    "CONFIGURATION_ITEM",
+    "CONFIGURED_EFFECTIVITY_ASSIGNMENT",
+    "CONFIGURED_EFFECTIVITY_CONTEXT_ASSIGNMENT",
+    "CONIC",
+    "CONICAL_SURFACE",
+    "CONNECTED_EDGE_SET",
+    "CONNECTED_FACE_SET",
+    "CONNECTED_FACE_SUB_SET",
+    "CONSTRUCTIVE_GEOMETRY_REPRESENTATION",
+    "CONSTRUCTIVE_GEOMETRY_REPRESENTATION_RELATIONSHIP",
+    "CONTACT_RATIO_REPRESENTATION",
+    "CONTEXT_DEPENDENT_INVISIBILITY",
+    "CONTEXT_DEPENDENT_OVER_RIDING_STYLED_ITEM",
+    "CONTEXT_DEPENDENT_SHAPE_REPRESENTATION",
+    "CONTEXT_DEPENDENT_UNIT",
+    "CONTRACT",
+    "CONTRACT_ASSIGNMENT",
+    "CONTRACT_TYPE",
+    "CONVERSION_BASED_UNIT",
+    "COORDINATED_UNIVERSAL_TIME_OFFSET",
+    "COS_FUNCTION",
+    "CSG_SHAPE_REPRESENTATION",
+    "CSG_SOLID",
+    "CURVE",
+    "CURVE_BOUNDED_SURFACE",
+    "CURVE_DIMENSION",
+    "CURVE_REPLICA",
+    "CURVE_STYLE",
+    "CURVE_STYLE_FONT",
+    "CURVE_STYLE_FONT_PATTERN",
+    "CURVE_STYLE_RENDERING",
+    "CURVE_SWEPT_SOLID_SHAPE_REPRESENTATION",
+    "CYLINDRICAL_PAIR",
+    "CYLINDRICAL_PAIR_RANGE",
+    "CYLINDRICAL_PAIR_VALUE",
+    "CYLINDRICAL_SURFACE",
+    "CYLINDRICITY_TOLERANCE",
+    "DATA_ENVIRONMENT",
+    "DATE",
+    "DATE_AND_TIME",
+    "DATE_AND_TIME_ASSIGNMENT",
+    "DATE_ASSIGNMENT",
+    "DATE_ROLE",
+    "DATE_TIME_ROLE",
+    "DATED_EFFECTIVITY",
+    "DATUM",
+    "DATUM_FEATURE",
+    "DATUM_FEATURE_CALLOUT",
+    "DATUM_REFERENCE",
+    "DATUM_TARGET",
+    "DATUM_TARGET_CALLOUT",
+    "DEFAULT_TOLERANCE_TABLE",
+    "DEFAULT_TOLERANCE_TABLE_CELL",
+    "DEFINED_CHARACTER_GLYPH",
+    "DEFINED_FUNCTION",
+    "DEFINED_SYMBOL",
+    "DEFINITIONAL_REPRESENTATION",
+    "DEGENERATE_PCURVE",
+    "DEGENERATE_TOROIDAL_SURFACE",
+    "DERIVED_SHAPE_ASPECT",
+    "DERIVED_UNIT",
+    "DERIVED_UNIT_ELEMENT",
+    "DERIVED_UNIT_VARIABLE",
+    "DESCRIPTION_ATTRIBUTE",
+    "DESCRIPTIVE_REPRESENTATION_ITEM",
+    "DIAMETER_DIMENSION",
+    "DIMENSION_CALLOUT",
+    "DIMENSION_CALLOUT_COMPONENT_RELATIONSHIP",
+    "DIMENSION_CALLOUT_RELATIONSHIP",
+    "DIMENSION_CURVE",
+    "DIMENSION_CURVE_DIRECTED_CALLOUT",
+    "DIMENSION_CURVE_TERMINATOR",
+    "DIMENSION_PAIR",
+    "DIMENSION_RELATED_TOLERANCE_ZONE_ELEMENT",
+    "DIMENSION_TEXT_ASSOCIATIVITY",
+    "DIMENSIONAL_CHARACTERISTIC_REPRESENTATION",
+    "DIMENSIONAL_EXPONENTS",
+    "DIMENSIONAL_LOCATION",
+    "DIMENSIONAL_LOCATION_WITH_PATH",
+    "DIMENSIONAL_SIZE",
+    "DIMENSIONAL_SIZE_WITH_PATH",
+    "DIRECTED_ACTION",
+    "DIRECTED_ANGLE",
+    "DIRECTED_DIMENSIONAL_LOCATION",
+    "DIRECTION",
+    "DIRECTION_SHAPE_REPRESENTATION",
+    "DIV_EXPRESSION",
+    "DOCUMENT",
+    "DOCUMENT_FILE",
+    "DOCUMENT_PRODUCT_ASSOCIATION",
+    "DOCUMENT_PRODUCT_EQUIVALENCE",
+    "DOCUMENT_REFERENCE",
+    "DOCUMENT_RELATIONSHIP",
+    "DOCUMENT_REPRESENTATION_TYPE",
+    "DOCUMENT_TYPE",
+    "DOCUMENT_USAGE_CONSTRAINT",
+    "DOCUMENT_USAGE_CONSTRAINT_ASSIGNMENT",
+    "DOCUMENT_USAGE_ROLE",
+    "DRAUGHTING_ANNOTATION_OCCURRENCE",
+    "DRAUGHTING_CALLOUT",
+    "DRAUGHTING_CALLOUT_RELATIONSHIP",
+    "DRAUGHTING_ELEMENTS",
+    "DRAUGHTING_MODEL",
+    "DRAUGHTING_MODEL_ITEM_ASSOCIATION",
+    "DRAUGHTING_PRE_DEFINED_COLOUR",
+    "DRAUGHTING_PRE_DEFINED_CURVE_FONT",
+    "DRAUGHTING_PRE_DEFINED_TEXT_FONT",
+    "DRAUGHTING_SPECIFICATION_REFERENCE",
+    "DRAUGHTING_SUBFIGURE_REPRESENTATION",
+    "DRAUGHTING_SYMBOL_REPRESENTATION",
+    "DRAUGHTING_TEXT_LITERAL_WITH_DELINEATION",
+    "DRAUGHTING_TITLE",
+    "DRAWING_DEFINITION",
+    "DRAWING_REVISION",
+    "DRAWING_REVISION_SEQUENCE",
+    "DRAWING_SHEET_LAYOUT",
+    "DRAWING_SHEET_REVISION",
+    "DRAWING_SHEET_REVISION_USAGE",
+    "EDGE",
+    "EDGE_BASED_WIREFRAME_MODEL",
+    "EDGE_BASED_WIREFRAME_SHAPE_REPRESENTATION",
+    "EDGE_CURVE",
+    "EDGE_LOOP",
+    "EDGE_ROUND",
+    "EFFECTIVITY",
+    "EFFECTIVITY_ASSIGNMENT",
+    "EFFECTIVITY_CONTEXT_ASSIGNMENT",
+    "EFFECTIVITY_CONTEXT_ROLE",
+    "EFFECTIVITY_RELATIONSHIP",
+    "ELECTRIC_CURRENT_MEASURE_WITH_UNIT",
+    "ELECTRIC_CURRENT_UNIT",
+    "ELEMENT_DELIVERY",
+    "ELEMENTARY_SURFACE",
+    "ELLIPSE",
+    "ENVIRONMENT",
+    "EQUALS_EXPRESSION",
+    "EVALUATED_DEGENERATE_PCURVE",
+    "EVENT_OCCURRENCE",
+    "EVENT_OCCURRENCE_ASSIGNMENT",
+    "EVENT_OCCURRENCE_CONTEXT_ASSIGNMENT",
+    "EVENT_OCCURRENCE_CONTEXT_ROLE",
+    "EVENT_OCCURRENCE_ROLE",
+    "EXCLUSIVE_PRODUCT_CONCEPT_FEATURE_CATEGORY",
+    "EXECUTED_ACTION",
+    "EXP_FUNCTION",
+    "EXPRESSION",
+    "EXPRESSION_CONVERSION_BASED_UNIT",
+    "EXTENSION",
+    "EXTERNAL_IDENTIFICATION_ASSIGNMENT",
+    "EXTERNAL_SOURCE",
+    "EXTERNALLY_DEFINED_CHARACTER_GLYPH",
+    "EXTERNALLY_DEFINED_CLASS",
+    "EXTERNALLY_DEFINED_CURVE_FONT",
+    "EXTERNALLY_DEFINED_DIMENSION_DEFINITION",
+    "EXTERNALLY_DEFINED_FEATURE_DEFINITION",
+    "EXTERNALLY_DEFINED_GENERAL_PROPERTY",
+    "EXTERNALLY_DEFINED_HATCH_STYLE",
+    "EXTERNALLY_DEFINED_ITEM",
+    "EXTERNALLY_DEFINED_ITEM_RELATIONSHIP",
+    "EXTERNALLY_DEFINED_STYLE",
+    "EXTERNALLY_DEFINED_SYMBOL",
+    "EXTERNALLY_DEFINED_TEXT_FONT",
+    "EXTERNALLY_DEFINED_TILE_STYLE",
+    "EXTRUDED_AREA_SOLID",
+    "EXTRUDED_FACE_SOLID",
+    "FACE",
+    "FACE_BASED_SURFACE_MODEL",
+    "FACE_BOUND",
+    "FACE_OUTER_BOUND",
+    "FACE_SHAPE_REPRESENTATION",
+    "FACE_SURFACE",
+    "FACETED_BREP",
+    "FACETED_BREP_SHAPE_REPRESENTATION",
+    "FEATURE_COMPONENT_DEFINITION",
+    "FEATURE_COMPONENT_RELATIONSHIP",
+    "FEATURE_DEFINITION",
+    "FEATURE_IN_PANEL",
+    "FEATURE_PATTERN",
+    "FEATURED_SHAPE",
+    "FILL_AREA_STYLE",
+    "FILL_AREA_STYLE_COLOUR",
+    "FILL_AREA_STYLE_HATCHING",
+    "FILL_AREA_STYLE_TILE_SYMBOL_WITH_STYLE",
+    "FILL_AREA_STYLE_TILES",
+    "FILLET",
+    "FLATNESS_TOLERANCE",
+    "FORMAT_FUNCTION",
+    "FOUNDED_ITEM",
+    "FOUNDED_KINEMATIC_PATH",
+    "FULLY_CONSTRAINED_PAIR",
+    "FUNCTIONALLY_DEFINED_TRANSFORMATION",
+    "GEAR_PAIR",
+    "GEAR_PAIR_RANGE",
+    "GEAR_PAIR_VALUE",
+    "GENERAL_FEATURE",
+    "GENERAL_MATERIAL_PROPERTY",
+    "GENERAL_PROPERTY",
+    "GENERAL_PROPERTY_ASSOCIATION",
+    "GENERAL_PROPERTY_RELATIONSHIP",
+    "GENERIC_CHARACTER_GLYPH_SYMBOL",
+    "GENERIC_EXPRESSION",
+    "GENERIC_LITERAL",
+    "GENERIC_VARIABLE",
+    "GEOMETRIC_ALIGNMENT",
+    "GEOMETRIC_CURVE_SET",
+    "GEOMETRIC_INTERSECTION",
+    "GEOMETRIC_ITEM_SPECIFIC_USAGE",
+    "GEOMETRIC_REPRESENTATION_CONTEXT",
+    "GEOMETRIC_REPRESENTATION_ITEM",
+    "GEOMETRIC_SET",
+    "GEOMETRIC_TOLERANCE",
+    "GEOMETRIC_TOLERANCE_RELATIONSHIP",
+    "GEOMETRIC_TOLERANCE_WITH_DATUM_REFERENCE",
+    "GEOMETRIC_TOLERANCE_WITH_DEFINED_UNIT",
+    "GEOMETRICAL_TOLERANCE_CALLOUT",
+    "GEOMETRICALLY_BOUNDED_2D_WIREFRAME_REPRESENTATION",
+    "GEOMETRICALLY_BOUNDED_SURFACE_SHAPE_REPRESENTATION",
+    "GEOMETRICALLY_BOUNDED_WIREFRAME_SHAPE_REPRESENTATION",
+    "GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT",
+    "GLOBAL_UNIT_ASSIGNED_CONTEXT",
+    "GROUP",
+    "GROUP_ASSIGNMENT",
+    "GROUP_RELATIONSHIP",
+    "HALF_SPACE_SOLID",
+    "HARDNESS_REPRESENTATION",
+    "HIDDEN_ELEMENT_OVER_RIDING_STYLED_ITEM",
+    "HOLE_BOTTOM",
+    "HOLE_IN_PANEL",
+    "HOMOKINETIC_PAIR",
+    "HYPERBOLA",
+    "ID_ATTRIBUTE",
+    "IDENTIFICATION_ASSIGNMENT",
+    "IDENTIFICATION_ROLE",
+    "INCLUSION_PRODUCT_CONCEPT_FEATURE",
+    "INDEX_EXPRESSION",
+    "INITIAL_STATE",
+    "INSTANCED_FEATURE",
+    "INT_LITERAL",
+    "INT_NUMERIC_VARIABLE",
+    "INT_VALUE_FUNCTION",
+    "INTEGER_DEFINED_FUNCTION",
+    "INTERPOLATED_CONFIGURATION_SEQUENCE",
+    "INTERSECTION_CURVE",
+    "INTERVAL_EXPRESSION",
+    "INVISIBILITY",
+    "ITEM_DEFINED_TRANSFORMATION",
+    "ITEM_IDENTIFIED_REPRESENTATION_USAGE",
+    "JOGGLE",
+    "JOGGLE_TERMINATION",
+    "KINEMATIC_ANALYSIS_CONSISTENCY",
+    "KINEMATIC_ANALYSIS_RESULT",
+    "KINEMATIC_CONTROL",
+    "KINEMATIC_FRAME_BACKGROUND_REPRESENTATION",
+    "KINEMATIC_FRAME_BACKGROUND_REPRESENTATION_ASSOCIATION",
+    "KINEMATIC_FRAME_BASED_TRANSFORMATION",
+    "KINEMATIC_GROUND_REPRESENTATION",
+    "KINEMATIC_JOINT",
+    "KINEMATIC_LINK",
+    "KINEMATIC_LINK_REPRESENTATION",
+    "KINEMATIC_LINK_REPRESENTATION_ASSOCIATION",
+    "KINEMATIC_LINK_REPRESENTATION_RELATION",
+    "KINEMATIC_PAIR",
+    "KINEMATIC_PATH",
+    "KINEMATIC_PROPERTY_DEFINITION",
+    "KINEMATIC_PROPERTY_REPRESENTATION_RELATION",
+    "KINEMATIC_STRUCTURE",
+    "KNOWN_SOURCE",
+    "LANGUAGE",
+    "LANGUAGE_ASSIGNMENT",
+    "LEADER_CURVE",
+    "LEADER_DIRECTED_CALLOUT",
+    "LEADER_DIRECTED_DIMENSION",
+    "LEADER_TERMINATOR",
+    "LENGTH_FUNCTION",
+    "LENGTH_MEASURE_WITH_UNIT",
+    "LENGTH_UNIT",
+    "LIGHT_SOURCE",
+    "LIGHT_SOURCE_AMBIENT",
+    "LIGHT_SOURCE_DIRECTIONAL",
+    "LIGHT_SOURCE_POSITIONAL",
+    "LIGHT_SOURCE_SPOT",
+    "LIKE_EXPRESSION",
+    "LIMITS_AND_FITS",
+    "LINE",
+    "LINE_PROFILE_TOLERANCE",
+    "LINEAR_DIMENSION",
+    "LITERAL_NUMBER",
+    "LOCAL_TIME",
+    "LOCATION_SHAPE_REPRESENTATION",
+    "LOCATOR",
+    "LOG10_FUNCTION",
+    "LOG2_FUNCTION",
+    "LOG_FUNCTION",
+    "LOOP",
+    "LOT_EFFECTIVITY",
+    "LUMINOUS_INTENSITY_MEASURE_WITH_UNIT",
+    "LUMINOUS_INTENSITY_UNIT",
+    "MAKE_FROM_USAGE_OPTION",
+    "MANIFOLD_SOLID_BREP",
+    "MANIFOLD_SUBSURFACE_SHAPE_REPRESENTATION",
+    "MANIFOLD_SURFACE_SHAPE_REPRESENTATION",
+    "MAPPED_ITEM",
+    "MASS_MEASURE_WITH_UNIT",
+    "MASS_UNIT",
+    "MATERIAL_DESIGNATION",
+    "MATERIAL_DESIGNATION_CHARACTERIZATION",
+    "MATERIAL_PROPERTY",
+    "MATERIAL_PROPERTY_REPRESENTATION",
+    "MAXIMUM_FUNCTION",
+    "MEASURE_QUALIFICATION",
+    "MEASURE_REPRESENTATION_ITEM",
+    "MEASURE_WITH_UNIT",
+    "MECHANICAL_DESIGN_GEOMETRIC_PRESENTATION_AREA",
+    "MECHANICAL_DESIGN_GEOMETRIC_PRESENTATION_REPRESENTATION",
+    "MECHANISM",
+    "MECHANISM_BASE_PLACEMENT",
+    "MINIMUM_FUNCTION",
+    "MINUS_EXPRESSION",
+    "MINUS_FUNCTION",
+    "MOD_EXPRESSION",
+    "MODIFIED_GEOMETRIC_TOLERANCE",
+    "MODIFIED_PATTERN",
+    "MOMENTS_OF_INERTIA_REPRESENTATION",
+    "MOTION_LINK_RELATIONSHIP",
+    "MULT_EXPRESSION",
+    "MULTI_LANGUAGE_ATTRIBUTE_ASSIGNMENT",
+    "MULTIPLE_ARITY_BOOLEAN_EXPRESSION",
+    "MULTIPLE_ARITY_FUNCTION_CALL",
+    "MULTIPLE_ARITY_GENERIC_EXPRESSION",
+    "MULTIPLE_ARITY_NUMERIC_EXPRESSION",
+    "NAME_ASSIGNMENT",
+    "NAME_ATTRIBUTE",
+    "NAMED_UNIT",
+    "NAMED_UNIT_VARIABLE",
+    "NEXT_ASSEMBLY_USAGE_OCCURRENCE",
+    "NGON_CLOSED_PROFILE",
+    "NON_MANIFOLD_SURFACE_SHAPE_REPRESENTATION",
+    "NOT_EXPRESSION",
+    "NUMERIC_DEFINED_FUNCTION",
+    "NUMERIC_EXPRESSION",
+    "NUMERIC_VARIABLE",
+    "OBJECT_ROLE",
+    "ODD_FUNCTION",
+    "OFFSET_CURVE_2D",
+    "OFFSET_CURVE_3D",
+    "OFFSET_SURFACE",
+    "ONE_DIRECTION_REPEAT_FACTOR",
+    "OPEN_PATH_PROFILE",
+    "OPEN_SHELL",
+    "OR_EXPRESSION",
+    "ORDINATE_DIMENSION",
+    "ORGANIZATION",
+    "ORGANIZATION_ASSIGNMENT",
+    "ORGANIZATION_RELATIONSHIP",
+    "ORGANIZATION_ROLE",
+    "ORGANIZATIONAL_ADDRESS",
+    "ORGANIZATIONAL_PROJECT",
+    "ORGANIZATIONAL_PROJECT_ASSIGNMENT",
+    "ORGANIZATIONAL_PROJECT_RELATIONSHIP",
+    "ORGANIZATIONAL_PROJECT_ROLE",
+    "ORIENTED_CLOSED_SHELL",
+    "ORIENTED_EDGE",
+    "ORIENTED_FACE",
+    "ORIENTED_OPEN_SHELL",
+    "ORIENTED_PATH",
+    "ORIENTED_SURFACE",
+    "OUTER_BOUNDARY_CURVE",
+    "OVER_RIDING_STYLED_ITEM",
+    "PACKAGE_PRODUCT_CONCEPT_FEATURE",
+    "PAIR_ACTUATOR",
+    "PAIR_VALUE",
+    "PARABOLA",
+    "PARALLEL_OFFSET",
+    "PARALLELISM_TOLERANCE",
+    "PARAMETRIC_REPRESENTATION_CONTEXT",
+    "PARTIAL_CIRCULAR_PROFILE",
+    "PATH",
+    "PATH_FEATURE_COMPONENT",
+    "PATH_SHAPE_REPRESENTATION",
+    "PATTERN_OFFSET_MEMBERSHIP",
+    "PATTERN_OMIT_MEMBERSHIP",
+    "PCURVE",
+    "PERPENDICULAR_TO",
+    "PERPENDICULARITY_TOLERANCE",
+    "PERSON",
+    "PERSON_AND_ORGANIZATION",
+    "PERSON_AND_ORGANIZATION_ADDRESS",
+    "PERSON_AND_ORGANIZATION_ASSIGNMENT",
+    "PERSON_AND_ORGANIZATION_ROLE",
+    "PERSONAL_ADDRESS",
+    "PHYSICALLY_MODELLED_PRODUCT_DEFINITION",
+    "PLACED_DATUM_TARGET_FEATURE",
+    "PLACED_FEATURE",
+    "PLACEMENT",
+    "PLANAR_BOX",
+    "PLANAR_CURVE_PAIR",
+    "PLANAR_CURVE_PAIR_RANGE",
+    "PLANAR_EXTENT",
+    "PLANAR_PAIR",
+    "PLANAR_PAIR_RANGE",
+    "PLANAR_PAIR_VALUE",
+    "PLANAR_SHAPE_REPRESENTATION",
+    "PLANE",
+    "PLANE_ANGLE_MEASURE_WITH_UNIT",
+    "PLANE_ANGLE_UNIT",
+    "PLUS_EXPRESSION",
+    "PLUS_MINUS_TOLERANCE",
+    "POCKET",
+    "POCKET_BOTTOM",
+    "POINT",
+    "POINT_ON_CURVE",
+    "POINT_ON_PLANAR_CURVE_PAIR",
+    "POINT_ON_PLANAR_CURVE_PAIR_RANGE",
+    "POINT_ON_PLANAR_CURVE_PAIR_VALUE",
+    "POINT_ON_SURFACE",
+    "POINT_ON_SURFACE_PAIR",
+    "POINT_ON_SURFACE_PAIR_RANGE",
+    "POINT_ON_SURFACE_PAIR_VALUE",
+    "POINT_PLACEMENT_SHAPE_REPRESENTATION",
+    "POINT_REPLICA",
+    "POINT_STYLE",
+    "POLY_LOOP",
+    "POLYLINE",
+    "POSITION_TOLERANCE",
+    "POWER_EXPRESSION",
+    "PRE_DEFINED_COLOUR",
+    "PRE_DEFINED_CURVE_FONT",
+    "PRE_DEFINED_DIMENSION_SYMBOL",
+    "PRE_DEFINED_GEOMETRICAL_TOLERANCE_SYMBOL",
+    "PRE_DEFINED_ITEM",
+    "PRE_DEFINED_MARKER",
+    "PRE_DEFINED_POINT_MARKER_SYMBOL",
+    "PRE_DEFINED_PRESENTATION_STYLE",
+    "PRE_DEFINED_SURFACE_CONDITION_SYMBOL",
+    "PRE_DEFINED_SYMBOL",
+    "PRE_DEFINED_TERMINATOR_SYMBOL",
+    "PRE_DEFINED_TEXT_FONT",
+    "PRECISION_QUALIFIER",
+    "PRESENTATION_AREA",
+    "PRESENTATION_LAYER_ASSIGNMENT",
+    "PRESENTATION_REPRESENTATION",
+    "PRESENTATION_SET",
+    "PRESENTATION_SIZE",
+    "PRESENTATION_STYLE_ASSIGNMENT",
+    "PRESENTATION_STYLE_BY_CONTEXT",
+    "PRESENTATION_VIEW",
+    "PRESENTED_ITEM",
+    "PRESENTED_ITEM_REPRESENTATION",
+    "PRISMATIC_PAIR",
+    "PRISMATIC_PAIR_RANGE",
+    "PRISMATIC_PAIR_VALUE",
+    "PROCESS_OPERATION",
+    "PROCESS_PLAN",
+    "PROCESS_PRODUCT_ASSOCIATION",
+    "PROCESS_PROPERTY_ASSOCIATION",
+    "PRODUCT",
+    "PRODUCT_CATEGORY",
+    "PRODUCT_CATEGORY_RELATIONSHIP",
+    "PRODUCT_CLASS",
+    "PRODUCT_CONCEPT",
+    "PRODUCT_CONCEPT_CONTEXT",
+    "PRODUCT_CONCEPT_FEATURE",
+    "PRODUCT_CONCEPT_FEATURE_ASSOCIATION",
+    "PRODUCT_CONCEPT_FEATURE_CATEGORY",
+    "PRODUCT_CONCEPT_FEATURE_CATEGORY_USAGE",
+    "PRODUCT_CONCEPT_RELATIONSHIP",
+    "PRODUCT_CONTEXT",
+    "PRODUCT_DEFINITION",
+    "PRODUCT_DEFINITION_CONTEXT",
+    "PRODUCT_DEFINITION_CONTEXT_ASSOCIATION",
+    "PRODUCT_DEFINITION_CONTEXT_ROLE",
+    "PRODUCT_DEFINITION_EFFECTIVITY",
+    "PRODUCT_DEFINITION_FORMATION",
+    "PRODUCT_DEFINITION_FORMATION_RELATIONSHIP",
+    "PRODUCT_DEFINITION_FORMATION_WITH_SPECIFIED_SOURCE",
+    "PRODUCT_DEFINITION_OCCURRENCE_RELATIONSHIP",
+    "PRODUCT_DEFINITION_PROCESS",
+    "PRODUCT_DEFINITION_RELATIONSHIP",
+    "PRODUCT_DEFINITION_RESOURCE",
+    "PRODUCT_DEFINITION_SHAPE",
+    "PRODUCT_DEFINITION_SUBSTITUTE",
+    "PRODUCT_DEFINITION_USAGE",
+    "PRODUCT_DEFINITION_WITH_ASSOCIATED_DOCUMENTS",
+    "PRODUCT_IDENTIFICATION",
+    "PRODUCT_PROCESS_PLAN",
+    "PRODUCT_RELATED_PRODUCT_CATEGORY",
+    "PRODUCT_SPECIFICATION",
+    "PROJECTED_ZONE_DEFINITION",
+    "PROJECTION_CURVE",
+    "PROJECTION_DIRECTED_CALLOUT",
+    "PROMISSORY_USAGE_OCCURRENCE",
+    "PROPERTY_DEFINITION",
+    "PROPERTY_DEFINITION_RELATIONSHIP",
+    "PROPERTY_DEFINITION_REPRESENTATION",
+    "PROPERTY_PROCESS",
+    "QUALIFIED_REPRESENTATION_ITEM",
+    "QUALITATIVE_UNCERTAINTY",
+    "QUANTIFIED_ASSEMBLY_COMPONENT_USAGE",
+    "QUASI_UNIFORM_CURVE",
+    "QUASI_UNIFORM_SURFACE",
+    "RACK_AND_PINION_PAIR",
+    "RACK_AND_PINION_PAIR_RANGE",
+    "RACK_AND_PINION_PAIR_VALUE",
+    "RADIUS_DIMENSION",
+    "RATIO_MEASURE_WITH_UNIT",
+    "RATIO_UNIT",
+    "RATIONAL_B_SPLINE_CURVE",
+    "RATIONAL_B_SPLINE_SURFACE",
+    "REAL_DEFINED_FUNCTION",
+    "REAL_LITERAL",
+    "REAL_NUMERIC_VARIABLE",
+    "RECTANGULAR_CLOSED_PROFILE",
+    "RECTANGULAR_COMPOSITE_SURFACE",
+    "RECTANGULAR_PATTERN",
+    "RECTANGULAR_TRIMMED_SURFACE",
+    "REFERENCED_MODIFIED_DATUM",
+    "RELATIVE_EVENT_OCCURRENCE",
+    "REP_ITEM_GROUP",
+    "REPARAMETRISED_COMPOSITE_CURVE_SEGMENT",
+    "REPLICATE_FEATURE",
+    "REPRESENTATION",
+    "REPRESENTATION_CONTEXT",
+    "REPRESENTATION_ITEM",
+    "REPRESENTATION_MAP",
+    "REPRESENTATION_RELATIONSHIP",
+    "REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION",
+    "REQUIREMENT_FOR_ACTION_RESOURCE",
+    "RESOURCE_PROPERTY",
+    "RESOURCE_PROPERTY_REPRESENTATION",
+    "RESOURCE_REQUIREMENT_TYPE",
+    "RESULTING_PATH",
+    "RETENTION",
+    "REVOLUTE_PAIR",
+    "REVOLUTE_PAIR_RANGE",
+    "REVOLUTE_PAIR_VALUE",
+    "REVOLVED_AREA_SOLID",
+    "REVOLVED_FACE_SOLID",
+    "RIB",
+    "RIGHT_ANGULAR_WEDGE",
+    "RIGHT_CIRCULAR_CONE",
+    "RIGHT_CIRCULAR_CYLINDER",
+    "ROLE_ASSOCIATION",
+    "ROLLING_CURVE_PAIR",
+    "ROLLING_CURVE_PAIR_VALUE",
+    "ROLLING_SURFACE_PAIR",
+    "ROLLING_SURFACE_PAIR_VALUE",
+    "ROTATION_ABOUT_DIRECTION",
+    "ROUND_HOLE",
+    "ROUNDED_U_PROFILE",
+    "ROUNDNESS_TOLERANCE",
+    "RULED_SURFACE_SWEPT_AREA_SOLID",
+    "RUNOUT_ZONE_DEFINITION",
+    "RUNOUT_ZONE_ORIENTATION",
+    "RUNOUT_ZONE_ORIENTATION_REFERENCE_DIRECTION",
+    "SCREW_PAIR",
+    "SCREW_PAIR_RANGE",
+    "SCREW_PAIR_VALUE",
+    "SEAM_CURVE",
+    "SEAM_EDGE",
+    "SECURITY_CLASSIFICATION",
+    "SECURITY_CLASSIFICATION_ASSIGNMENT",
+    "SECURITY_CLASSIFICATION_LEVEL",
+    "SERIAL_NUMBERED_EFFECTIVITY",
+    "SHAPE_ASPECT",
+    "SHAPE_ASPECT_ASSOCIATIVITY",
+    "SHAPE_ASPECT_DERIVING_RELATIONSHIP",
+    "SHAPE_ASPECT_RELATIONSHIP",
+    "SHAPE_ASPECT_TRANSITION",
+    "SHAPE_DEFINING_RELATIONSHIP",
+    "SHAPE_DEFINITION_REPRESENTATION",
+    "SHAPE_DIMENSION_REPRESENTATION",
+    "SHAPE_REPRESENTATION",
+    "SHAPE_REPRESENTATION_RELATIONSHIP",
+    "SHAPE_REPRESENTATION_WITH_PARAMETERS",
+    "SHELL_BASED_SURFACE_MODEL",
+    "SI_UNIT",
+    "SIMPLE_BOOLEAN_EXPRESSION",
+    "SIMPLE_GENERIC_EXPRESSION",
+    "SIMPLE_NUMERIC_EXPRESSION",
+    "SIMPLE_PAIR_RANGE",
+    "SIMPLE_STRING_EXPRESSION",
+    "SIN_FUNCTION",
+    "SLASH_EXPRESSION",
+    "SLIDING_CURVE_PAIR",
+    "SLIDING_CURVE_PAIR_VALUE",
+    "SLIDING_SURFACE_PAIR",
+    "SLIDING_SURFACE_PAIR_VALUE",
+    "SLOT",
+    "SLOT_END",
+    "SOLID_ANGLE_MEASURE_WITH_UNIT",
+    "SOLID_ANGLE_UNIT",
+    "SOLID_MODEL",
+    "SOLID_REPLICA",
+    "SPECIFIED_HIGHER_USAGE_OCCURRENCE",
+    "SPHERE",
+    "SPHERICAL_PAIR",
+    "SPHERICAL_PAIR_RANGE",
+    "SPHERICAL_PAIR_VALUE",
+    "SPHERICAL_SURFACE",
+    "SQL_MAPPABLE_DEFINED_FUNCTION",
+    "SQUARE_ROOT_FUNCTION",
+    "SQUARE_U_PROFILE",
+    "STANDARD_UNCERTAINTY",
+    "STRAIGHTNESS_TOLERANCE",
+    "STRING_DEFINED_FUNCTION",
+    "STRING_EXPRESSION",
+    "STRING_LITERAL",
+    "STRING_VARIABLE",
+    "STRUCTURED_DIMENSION_CALLOUT",
+    "STYLED_ITEM",
+    "SUBEDGE",
+    "SUBFACE",
+    "SUBSTRING_EXPRESSION",
+    "SURFACE",
+    "SURFACE_CONDITION_CALLOUT",
+    "SURFACE_CURVE",
+    "SURFACE_CURVE_SWEPT_AREA_SOLID",
+    "SURFACE_OF_LINEAR_EXTRUSION",
+    "SURFACE_OF_REVOLUTION",
+    "SURFACE_PAIR",
+    "SURFACE_PAIR_RANGE",
+    "SURFACE_PATCH",
+    "SURFACE_PROFILE_TOLERANCE",
+    "SURFACE_RENDERING_PROPERTIES",
+    "SURFACE_REPLICA",
+    "SURFACE_SIDE_STYLE",
+    "SURFACE_STYLE_BOUNDARY",
+    "SURFACE_STYLE_CONTROL_GRID",
+    "SURFACE_STYLE_FILL_AREA",
+    "SURFACE_STYLE_PARAMETER_LINE",
+    "SURFACE_STYLE_REFLECTANCE_AMBIENT",
+    "SURFACE_STYLE_REFLECTANCE_AMBIENT_DIFFUSE",
+    "SURFACE_STYLE_REFLECTANCE_AMBIENT_DIFFUSE_SPECULAR",
+    "SURFACE_STYLE_RENDERING",
+    "SURFACE_STYLE_RENDERING_WITH_PROPERTIES",
+    "SURFACE_STYLE_SEGMENTATION_CURVE",
+    "SURFACE_STYLE_SILHOUETTE",
+    "SURFACE_STYLE_TRANSPARENT",
+    "SURFACE_STYLE_USAGE",
+    "SURFACE_TEXTURE_REPRESENTATION",
+    "SWEPT_AREA_SOLID",
+    "SWEPT_DISK_SOLID",
+    "SWEPT_FACE_SOLID",
+    "SWEPT_SURFACE",
+    "SYMBOL_COLOUR",
+    "SYMBOL_REPRESENTATION",
+    "SYMBOL_REPRESENTATION_MAP",
+    "SYMBOL_STYLE",
+    "SYMBOL_TARGET",
+    "SYMMETRIC_SHAPE_ASPECT",
+    "SYMMETRY_TOLERANCE",
+    "TACTILE_APPEARANCE_REPRESENTATION",
+    "TAN_FUNCTION",
+    "TANGENT",
+    "TAPER",
+    "TEE_PROFILE",
+    "TERMINATOR_SYMBOL",
+    "TEXT_LITERAL",
+    "TEXT_LITERAL_WITH_ASSOCIATED_CURVES",
+    "TEXT_LITERAL_WITH_BLANKING_BOX",
+    "TEXT_LITERAL_WITH_DELINEATION",
+    "TEXT_LITERAL_WITH_EXTENT",
+    "TEXT_STRING_REPRESENTATION",
+    "TEXT_STYLE",
+    "TEXT_STYLE_FOR_DEFINED_FONT",
+    "TEXT_STYLE_WITH_BOX_CHARACTERISTICS",
+    "TEXT_STYLE_WITH_MIRROR",
+    "TEXT_STYLE_WITH_SPACING",
+    "THERMODYNAMIC_TEMPERATURE_MEASURE_WITH_UNIT",
+    "THERMODYNAMIC_TEMPERATURE_UNIT",
+    "THREAD",
+    "TIME_INTERVAL",
+    "TIME_INTERVAL_ASSIGNMENT",
+    "TIME_INTERVAL_BASED_EFFECTIVITY",
+    "TIME_INTERVAL_ROLE",
+    "TIME_INTERVAL_WITH_BOUNDS",
+    "TIME_MEASURE_WITH_UNIT",
+    "TIME_UNIT",
+    "TOLERANCE_VALUE",
+    "TOLERANCE_ZONE",
+    "TOLERANCE_ZONE_DEFINITION",
+    "TOLERANCE_ZONE_FORM",
+    "TOPOLOGICAL_REPRESENTATION_ITEM",
+    "TOROIDAL_SURFACE",
+    "TORUS",
+    "TOTAL_RUNOUT_TOLERANCE",
+    "TRANSITION_FEATURE",
+    "TRIMMED_CURVE",
+    "TWO_DIRECTION_REPEAT_FACTOR",
+    "TYPE_QUALIFIER",
+    "UNARY_BOOLEAN_EXPRESSION",
+    "UNARY_FUNCTION_CALL",
+    "UNARY_GENERIC_EXPRESSION",
+    "UNARY_NUMERIC_EXPRESSION",
+    "UNCERTAINTY_ASSIGNED_REPRESENTATION",
+    "UNCERTAINTY_MEASURE_WITH_UNIT",
+    "UNCERTAINTY_QUALIFIER",
+    "UNCONSTRAINED_PAIR",
+    "UNCONSTRAINED_PAIR_VALUE",
+    "UNIFORM_CURVE",
+    "UNIFORM_SURFACE",
+    "UNIVERSAL_PAIR",
+    "UNIVERSAL_PAIR_RANGE",
+    "UNIVERSAL_PAIR_VALUE",
+    "VALUE_FUNCTION",
+    "VALUE_RANGE",
+    "VALUE_REPRESENTATION_ITEM",
+    "VARIABLE",
+    "VARIABLE_SEMANTICS",
+    "VECTOR",
+    "VECTOR_STYLE",
+    "VEE_PROFILE",
+    "VERSIONED_ACTION_REQUEST",
+    "VERSIONED_ACTION_REQUEST_RELATIONSHIP",
+    "VERTEX",
+    "VERTEX_LOOP",
+    "VERTEX_POINT",
+    "VIEW_VOLUME",
+    "VISUAL_APPEARANCE_REPRESENTATION",
+    "VOLUME_MEASURE_WITH_UNIT",
+    "VOLUME_UNIT",
+    "XOR_EXPRESSION",
+];
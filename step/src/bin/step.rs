@@ -0,0 +1,206 @@
+use std::process::ExitCode;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::json;
+use step::{
+    diff::{diff, ChangeCounts, DiffOptions, DiffReport},
+    step_file::{ParseOptions, StepFile},
+};
+
+fn counts_json(c: &ChangeCounts) -> serde_json::Value {
+    json!({
+        "added": c.added,
+        "removed": c.removed,
+        "modified": c.modified,
+    })
+}
+
+fn report_json(r: &DiffReport) -> serde_json::Value {
+    let by_type: serde_json::Map<_, _> = r
+        .by_type()
+        .iter()
+        .map(|(t, c)| (t.to_string(), counts_json(c)))
+        .collect();
+    let by_product: Vec<_> = r
+        .by_product()
+        .iter()
+        .map(|(p, c)| json!({ "product": p, "counts": counts_json(c) }))
+        .collect();
+    let changes: Vec<_> = r
+        .changes
+        .iter()
+        .map(|c| {
+            json!({
+                "kind": c.kind.as_str(),
+                "type": c.entity_type,
+                "a": c.a,
+                "b": c.b,
+                "product": c.product,
+            })
+        })
+        .collect();
+    json!({
+        "summary": counts_json(&r.counts()),
+        "unchanged": r.unchanged,
+        "by_type": by_type,
+        "by_product": by_product,
+        "changes": changes,
+    })
+}
+
+fn print_summary(r: &DiffReport) {
+    let describe = |c: &ChangeCounts| {
+        [
+            (c.added, "added"),
+            (c.removed, "removed"),
+            (c.modified, "modified"),
+        ]
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, s)| format!("{} {}", n, s))
+        .collect::<Vec<_>>()
+        .join(", ")
+    };
+    if r.is_empty() {
+        println!("No differences ({} entities)", r.unchanged);
+        return;
+    }
+    println!("{} ({} unchanged)", describe(&r.counts()), r.unchanged);
+    println!("By product:");
+    for (p, c) in r.by_product() {
+        println!("  {}: {}", p.unwrap_or("(none)"), describe(&c));
+    }
+    println!("By type:");
+    for (t, c) in r.by_type() {
+        println!("  {}: {}", t, describe(&c));
+    }
+    println!("Changes:");
+    for c in &r.changes {
+        let id = |i: Option<usize>| {
+            i.map(|i| format!("#{}", i))
+                .unwrap_or_else(|| "-".to_owned())
+        };
+        println!(
+            "  {:<8} {:>8} {:>8}  {}",
+            c.kind.as_str(),
+            id(c.a),
+            id(c.b),
+            c.entity_type
+        );
+    }
+}
+
+fn run_diff(matches: &ArgMatches) -> Result<bool, Box<dyn std::error::Error>> {
+    let read = |name| -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let path = matches.value_of(name).expect("Paths are required");
+        let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(StepFile::strip_flatten(&data))
+    };
+    let (a, b) = (read("a")?, read("b")?);
+    let (a, b) = (StepFile::parse(&a)?, StepFile::parse(&b)?);
+
+    let mut opts = DiffOptions::default();
+    if let Some(t) = matches.value_of("tolerance") {
+        match t.parse::<f64>() {
+            Ok(t) if t >= 0.0 && t.is_finite() => opts.tolerance = t,
+            _ => return Err(format!("Invalid tolerance {:?}", t).into()),
+        }
+    }
+    let report = diff(&a, &b, &opts);
+    if matches.is_present("json") {
+        println!("{}", serde_json::to_string_pretty(&report_json(&report))?);
+    } else {
+        print_summary(&report);
+    }
+    Ok(report.is_empty())
+}
+
+fn run_show(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let path = matches.value_of("input").expect("Path is required");
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    let flat = StepFile::strip_flatten(&data);
+    let opts = ParseOptions {
+        retain_source: true,
+    };
+    let step = StepFile::parse_with(&flat, &opts)?;
+
+    let id = matches.value_of("id").expect("Id is required");
+    let id: usize = id
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| format!("Invalid entity id {:?}", id))?;
+    let text = step
+        .source_text(id)
+        .ok_or_else(|| format!("No entity #{} in {}", id, path))?;
+    let depth = match matches.value_of("depth") {
+        Some(d) => d
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid depth {:?}", d))?,
+        None => 1,
+    };
+    println!("{}\n", text);
+    println!("#{} = {}", id, step.0[id].pretty_with_depth(&step, depth));
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let matches = App::new("step")
+        .author("Matt Keeter <matt@formlabs.com>")
+        .about("Tools for working with STEP files")
+        .subcommand_required(true)
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compares the entities in two STEP files")
+                .arg(Arg::with_name("a").required(true).help("Original file"))
+                .arg(Arg::with_name("b").required(true).help("Changed file"))
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Prints a JSON report instead of a summary"),
+                )
+                .arg(
+                    Arg::with_name("tolerance")
+                        .long("tolerance")
+                        .takes_value(true)
+                        .help("Largest difference between real numbers which counts as equal"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Prints an entity's record, and how it was parsed")
+                .arg(Arg::with_name("input").required(true).help("STEP file"))
+                .arg(
+                    Arg::with_name("id")
+                        .required(true)
+                        .help("Entity id, e.g. '#123'"),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .takes_value(true)
+                        .help("How many levels of referenced entities to expand (default 1)"),
+                ),
+        )
+        .get_matches();
+
+    // Like diff(1), exit with 0 if the files match, 1 if they differ, and 2
+    // if something went wrong
+    match matches.subcommand() {
+        Some(("diff", m)) => match run_diff(m) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::from(1),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::from(2)
+            }
+        },
+        Some(("show", m)) => match run_show(m) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        _ => unreachable!("A subcommand is required"),
+    }
+}
@@ -0,0 +1,818 @@
+//! Programmatic construction of STEP files.
+//!
+//! A [`StepBuilder`] writes geometry and topology entities through typed
+//! helpers, which return [`Id`]s of the matching `ap214` types, so that
+//! (for example) a face can only be built on a surface.  Ids are allocated
+//! sequentially from `#1`.  The scaffolding which every file needs (an
+//! application context, units, a product, and a shape representation for
+//! the solids) is written automatically by [`StepBuilder::finish`], which
+//! also checks every reference against the schema.
+//!
+//! ```
+//! use step::builder::StepBuilder;
+//! let mut b = StepBuilder::new("square");
+//! let v: Vec<_> = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].iter()
+//!     .map(|p| b.vertex([p[0], p[1], 0.0]))
+//!     .collect();
+//! let edges: Vec<_> = (0..4)
+//!     .map(|i| {
+//!         let (a, c) = (v[i], v[(i + 1) % 4]);
+//!         let line = b.line(b.vertex_position(a), b.vertex_position(c));
+//!         (b.edge_curve(a, c, line), true)
+//!     })
+//!     .collect();
+//! let lp = b.edge_loop(&edges);
+//! let axis = b.axis2_placement([0.0; 3], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+//! let plane = b.plane(axis);
+//! let face = b.advanced_face(plane, &[lp]);
+//! let shell = b.closed_shell(&[face]);
+//! b.manifold_solid(shell);
+//! let text = b.finish().unwrap();
+//! assert!(text.starts_with("ISO-10303-21;"));
+//! ```
+//!
+//! How the text is laid out (line endings and wrapping, keyword case, the
+//! format of real numbers, and the header's timestamp) is picked with
+//! [`WriteOptions`], for downstream readers which are picky about it.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    ap214::*,
+    fixture::refs,
+    id::Id,
+    lexer::Lexer,
+};
+
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum BuildError {
+    #[error("#{from} refers to #{to}, which doesn't exist")]
+    MissingReference { from: usize, to: usize },
+
+    #[error("#{from} refers to #{to}, which is a {found} rather than a {expected}")]
+    WrongType {
+        from: usize,
+        to: usize,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("No solids were added")]
+    Empty,
+
+    #[error("#{id} has a number which is infinite or NaN")]
+    NonFinite { id: usize },
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum KeywordCase {
+    Upper,
+    /// Lowercase entity and type keywords, e.g. `cartesian_point(...)`.
+    /// Part 21 only allows uppercase, so this is for readers which expect
+    /// otherwise; such files can be read back after
+    /// [`StepFile::uppercase_keywords`](crate::step_file::StepFile::uppercase_keywords).
+    Lower,
+}
+
+/// Placeholder which is written instead of the time, so that the same
+/// model always gives the same file
+pub const TIMESTAMP_PLACEHOLDER: &str = "1970-01-01T00:00:00";
+
+/// Layout of the text written by a [`StepBuilder`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct WriteOptions {
+    pub line_ending: LineEnding,
+    /// Longest line (not counting its ending), or `None` to write each
+    /// record on one line.  Longer records are continued on the next line,
+    /// which readers ignore.  Lines are only broken between tokens, so a
+    /// token which is longer than this (e.g. a long string) gets a line of
+    /// its own.
+    pub wrap: Option<usize>,
+    pub keyword_case: KeywordCase,
+    /// Digits after the decimal point in real numbers, or `None` for the
+    /// fewest digits which read back as the same number
+    pub precision: Option<usize>,
+    /// Writes every real number with an exponent (e.g. `1.5E0`), rather
+    /// than only very large and very small ones
+    pub exponent: bool,
+    /// Writes the current time into the header, rather than
+    /// [`TIMESTAMP_PLACEHOLDER`]
+    pub timestamp: bool,
+}
+
+impl Default for WriteOptions {
+    /// Compact, reproducible output: LF line endings, no wrapping,
+    /// uppercase keywords, exact reals, and a placeholder timestamp
+    fn default() -> Self {
+        Self {
+            line_ending: LineEnding::Lf,
+            wrap: None,
+            keyword_case: KeywordCase::Upper,
+            precision: None,
+            exponent: false,
+            timestamp: false,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// Output for the strictest readers, following the conventions of
+    /// ISO 10303-21: CRLF line endings, lines of at most 72 characters,
+    /// uppercase keywords, reals in exponent form, and a real timestamp
+    pub fn conformance() -> Self {
+        Self {
+            line_ending: LineEnding::CrLf,
+            wrap: Some(72),
+            keyword_case: KeywordCase::Upper,
+            precision: None,
+            exponent: true,
+            timestamp: true,
+        }
+    }
+
+    /// Formats a real number, which always has a decimal point (as Part 21
+    /// requires), or returns `None` if it's infinite or NaN, which Part 21
+    /// can't represent
+    pub fn real(&self, x: f64) -> Option<String> {
+        if !x.is_finite() {
+            return None;
+        }
+        let s = match (self.precision, self.exponent) {
+            (None, false) => format!("{:?}", x),
+            (None, true) => format!("{:E}", x),
+            (Some(p), false) => format!("{:.*}", p, x),
+            (Some(p), true) => format!("{:.*E}", p, x),
+        };
+        let (mantissa, exp) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s.as_str(), None),
+        };
+        let mut out = mantissa.to_owned();
+        if !out.contains('.') {
+            out.push('.');
+        }
+        if let Some(e) = exp {
+            out += &format!("E{}", e);
+        }
+        Some(out)
+    }
+
+    /// Writes lines of text with the chosen keyword case, wrapping, and
+    /// line endings
+    fn lines<'a, I: IntoIterator<Item=&'a str>>(&self, lines: I) -> String {
+        let mut out = String::new();
+        for line in lines {
+            let line = match self.keyword_case {
+                KeywordCase::Upper => line.to_owned(),
+                KeywordCase::Lower => lowercase_keywords(line),
+            };
+            let width = self.wrap.unwrap_or(usize::MAX).max(1);
+            // Breaks go before whitespace, so that it counts towards the
+            // length of the line which it starts
+            let (mut start, mut prev, mut len) = (0, 0, 0);
+            for t in Lexer::new(&line).map_while(Result::ok) {
+                let n = line[prev..t.end()].chars().count();
+                if len > 0 && len + n > width {
+                    out += &line[start..prev];
+                    out += self.line_ending.as_str();
+                    (start, len) = (prev, n);
+                } else {
+                    len += n;
+                }
+                prev = t.end();
+            }
+            out += &line[start..];
+            out += self.line_ending.as_str();
+        }
+        out
+    }
+}
+
+/// Lowercases keywords which are followed by an opening bracket (i.e.
+/// entity and type names), leaving strings, enumerations, and section
+/// markers alone
+fn lowercase_keywords(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        if !quoted && (c.is_ascii_alphanumeric() || c == '_') {
+            word.push(c);
+            continue;
+        }
+        if c == '(' && word.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            word.make_ascii_lowercase();
+        }
+        out += &word;
+        word.clear();
+        if c == '\'' {
+            quoted = !quoted;
+        }
+        out.push(c);
+    }
+    out + &word
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 date and time (UTC)
+fn iso_timestamp(secs: u64) -> String {
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    // Converts days to a civil date, treating years as starting in March
+    // so that leap days fall at the end
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year, month, day, rem / 3600, rem / 60 % 60, rem % 60)
+}
+
+/// An entity type which the builder writes, with its STEP keyword
+pub trait Writable {
+    const KEYWORD: &'static str;
+}
+
+/// Entity types which can be the geometry of an edge
+pub trait CurveType: Writable {}
+
+/// Entity types which can be the geometry of a face
+pub trait SurfaceType: Writable {}
+
+macro_rules! writable {
+    ($($t:ident => $k:literal),* $(,)?) => {
+        $(impl Writable for $t<'_> {
+            const KEYWORD: &'static str = $k;
+        })*
+    };
+}
+
+writable!(
+    AdvancedFace_ => "ADVANCED_FACE",
+    Axis2Placement3d_ => "AXIS2_PLACEMENT_3D",
+    CartesianPoint_ => "CARTESIAN_POINT",
+    Circle_ => "CIRCLE",
+    ClosedShell_ => "CLOSED_SHELL",
+    CylindricalSurface_ => "CYLINDRICAL_SURFACE",
+    Direction_ => "DIRECTION",
+    EdgeCurve_ => "EDGE_CURVE",
+    EdgeLoop_ => "EDGE_LOOP",
+    Line_ => "LINE",
+    ManifoldSolidBrep_ => "MANIFOLD_SOLID_BREP",
+    Plane_ => "PLANE",
+    Vector_ => "VECTOR",
+    VertexPoint_ => "VERTEX_POINT",
+);
+impl CurveType for Line_<'_> {}
+impl CurveType for Circle_<'_> {}
+impl SurfaceType for Plane_<'_> {}
+impl SurfaceType for CylindricalSurface_<'_> {}
+
+/// Checks whether an entity type is the given type or one of its subtypes
+fn is_a(keyword: &str, ancestor: &str) -> bool {
+    keyword == ancestor
+        || superclasses_of(keyword).iter().any(|s| is_a(s, ancestor))
+}
+
+/// A single entity, before it's written out
+struct Record {
+    /// Keyword of the entity, or `None` for complex entities
+    keyword: Option<&'static str>,
+    text: String,
+    /// References to other entities, along with the type which each must
+    /// have (or be a subtype of)
+    refs: Vec<(usize, &'static str)>,
+}
+
+/// Builds a STEP file one entity at a time (see the [module docs](self))
+pub struct StepBuilder {
+    name: String,
+    opts: WriteOptions,
+    records: Vec<Record>,
+    /// Position of each vertex, so that edges can be built between them
+    vertices: Vec<(usize, [f64; 3])>,
+    solids: Vec<usize>,
+    /// First entity with a number which is infinite or NaN
+    non_finite: Option<usize>,
+}
+
+impl StepBuilder {
+    /// Starts a new file, whose product has the given name
+    pub fn new(name: &str) -> Self {
+        Self::with_options(name, WriteOptions::default())
+    }
+
+    /// Starts a new file, which is laid out as set by `opts`
+    pub fn with_options(name: &str, opts: WriteOptions) -> Self {
+        Self {
+            name: name.to_owned(),
+            opts,
+            records: vec![],
+            vertices: vec![],
+            solids: vec![],
+            non_finite: None,
+        }
+    }
+
+    /// Formats a real number, recording the entity that's about to be
+    /// written if the number can't be (which [`finish`](Self::finish)
+    /// reports)
+    fn real(&mut self, x: f64) -> String {
+        self.opts.real(x).unwrap_or_else(|| {
+            self.non_finite.get_or_insert(self.records.len() + 1);
+            "0.".to_owned()
+        })
+    }
+
+    /// Writes a simple entity with the given arguments, where each of `refs`
+    /// is an entity that's referred to (and the type which it must have)
+    fn push<T: Writable>(&mut self, args: String, refs: Vec<(usize, &'static str)>) -> Id<T> {
+        self.records.push(Record {
+            keyword: Some(T::KEYWORD),
+            text: format!("{}({})", T::KEYWORD, args),
+            refs,
+        });
+        Id::new(self.records.len())
+    }
+
+    /// Writes an entity without checking its references, for scaffolding
+    fn push_raw(&mut self, text: String) -> usize {
+        self.records.push(Record { keyword: None, text, refs: vec![] });
+        self.records.len()
+    }
+
+    pub fn point(&mut self, x: f64, y: f64, z: f64) -> CartesianPoint<'static> {
+        let args = format!("'',({},{},{})", self.real(x), self.real(y), self.real(z));
+        self.push(args, vec![])
+    }
+
+    /// Writes a direction, which needn't be normalized
+    pub fn direction(&mut self, d: [f64; 3]) -> Direction<'static> {
+        let args = format!("'',({},{},{})",
+                           self.real(d[0]), self.real(d[1]), self.real(d[2]));
+        self.push(args, vec![])
+    }
+
+    /// Writes a placement at `origin`, with the given Z and X axes
+    pub fn axis2_placement(&mut self, origin: [f64; 3], z: [f64; 3], x: [f64; 3])
+        -> Axis2Placement3d<'static>
+    {
+        let p = self.point(origin[0], origin[1], origin[2]);
+        let (z, x) = (self.direction(z), self.direction(x));
+        self.push(format!("'',#{},#{},#{}", p.0, z.0, x.0),
+                  vec![(p.0, "CARTESIAN_POINT"), (z.0, "DIRECTION"),
+                       (x.0, "DIRECTION")])
+    }
+
+    /// Writes the (unbounded) line through two points, which must differ.
+    /// Its parameter runs from 0 at `a` to 1 at `b`.
+    pub fn line(&mut self, a: [f64; 3], b: [f64; 3]) -> Line<'static> {
+        let d = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+        assert!(len > 0.0, "Line endpoints must differ");
+        let dir = self.direction([d[0] / len, d[1] / len, d[2] / len]);
+        let args = format!("'',#{},{}", dir.0, self.real(len));
+        let vec: Vector = self.push(args, vec![(dir.0, "DIRECTION")]);
+        let p = self.point(a[0], a[1], a[2]);
+        self.push(format!("'',#{},#{}", p.0, vec.0),
+                  vec![(p.0, "CARTESIAN_POINT"), (vec.0, "VECTOR")])
+    }
+
+    /// Writes a circle in the XY plane of a placement
+    pub fn circle(&mut self, axis: Axis2Placement3d<'static>, radius: f64)
+        -> Circle<'static>
+    {
+        let args = format!("'',#{},{}", axis.0, self.real(radius));
+        self.push(args, vec![(axis.0, "AXIS2_PLACEMENT_3D")])
+    }
+
+    /// Writes a plane, whose normal is the placement's Z axis
+    pub fn plane(&mut self, axis: Axis2Placement3d<'static>) -> Plane<'static> {
+        self.push(format!("'',#{}", axis.0), vec![(axis.0, "AXIS2_PLACEMENT_3D")])
+    }
+
+    /// Writes a cylinder around the Z axis of a placement
+    pub fn cylindrical_surface(&mut self, axis: Axis2Placement3d<'static>,
+                               radius: f64) -> CylindricalSurface<'static>
+    {
+        let args = format!("'',#{},{}", axis.0, self.real(radius));
+        self.push(args, vec![(axis.0, "AXIS2_PLACEMENT_3D")])
+    }
+
+    /// Writes a vertex at the given position
+    pub fn vertex(&mut self, p: [f64; 3]) -> VertexPoint<'static> {
+        let pt = self.point(p[0], p[1], p[2]);
+        let v = self.push(format!("'',#{}", pt.0), vec![(pt.0, "CARTESIAN_POINT")]);
+        self.vertices.push((v.0, p));
+        v
+    }
+
+    /// Returns the position of a vertex which was written by
+    /// [`vertex`](Self::vertex)
+    ///
+    /// # Panics
+    /// If the vertex wasn't written by this builder
+    pub fn vertex_position(&self, v: VertexPoint<'static>) -> [f64; 3] {
+        self.vertices.iter()
+            .find(|(i, _)| *i == v.0)
+            .map(|(_, p)| *p)
+            .expect("Unknown vertex")
+    }
+
+    /// Writes an edge from `a` to `b` along a curve, in the curve's own
+    /// direction
+    pub fn edge_curve<C: CurveType>(&mut self, a: VertexPoint<'static>,
+                                    b: VertexPoint<'static>, curve: Id<C>)
+        -> EdgeCurve<'static>
+    {
+        self.push(format!("'',#{},#{},#{},.T.", a.0, b.0, curve.0),
+                  vec![(a.0, "VERTEX"), (b.0, "VERTEX"), (curve.0, "CURVE")])
+    }
+
+    /// Writes a loop of edges, each of which is used forwards (from its
+    /// start to its end) if its flag is true and backwards otherwise
+    pub fn edge_loop(&mut self, edges: &[(EdgeCurve<'static>, bool)])
+        -> EdgeLoop<'static>
+    {
+        let oriented: Vec<usize> = edges.iter()
+            .map(|(e, forward)| {
+                let sense = if *forward { ".T." } else { ".F." };
+                self.records.push(Record {
+                    keyword: Some("ORIENTED_EDGE"),
+                    text: format!("ORIENTED_EDGE('',*,*,#{},{})", e.0, sense),
+                    refs: vec![(e.0, "EDGE")],
+                });
+                self.records.len()
+            })
+            .collect();
+        let checks = oriented.iter().map(|i| (*i, "ORIENTED_EDGE")).collect();
+        self.push(format!("'',({})", refs(oriented)), checks)
+    }
+
+    /// Writes a face on a surface, bounded by the given loops.  The first
+    /// loop is the outer boundary, and the rest are holes.
+    pub fn advanced_face<S: SurfaceType>(&mut self, surface: Id<S>,
+                                         loops: &[EdgeLoop<'static>])
+        -> AdvancedFace<'static>
+    {
+        let bounds: Vec<usize> = loops.iter().enumerate()
+            .map(|(i, l)| {
+                let keyword = if i == 0 { "FACE_OUTER_BOUND" } else { "FACE_BOUND" };
+                self.records.push(Record {
+                    keyword: Some(keyword),
+                    text: format!("{}('',#{},.T.)", keyword, l.0),
+                    refs: vec![(l.0, "LOOP")],
+                });
+                self.records.len()
+            })
+            .collect();
+        let mut checks: Vec<_> = bounds.iter().map(|i| (*i, "FACE_BOUND")).collect();
+        checks.push((surface.0, "SURFACE"));
+        self.push(format!("'',({}),#{},.T.", refs(bounds), surface.0), checks)
+    }
+
+    pub fn closed_shell(&mut self, faces: &[AdvancedFace<'static>])
+        -> ClosedShell<'static>
+    {
+        let checks = faces.iter().map(|f| (f.0, "FACE")).collect();
+        self.push(format!("'',({})", refs(faces.iter().map(|f| f.0))), checks)
+    }
+
+    /// Writes a solid, which is added to the file's shape representation
+    pub fn manifold_solid(&mut self, shell: ClosedShell<'static>)
+        -> ManifoldSolidBrep<'static>
+    {
+        let out: ManifoldSolidBrep = self.push(
+            format!("'{}',#{}", self.name, shell.0), vec![(shell.0, "CLOSED_SHELL")]);
+        self.solids.push(out.0);
+        out
+    }
+
+    /// Checks every reference, returning the first which is missing or of
+    /// the wrong type
+    fn check(&self) -> Result<(), BuildError> {
+        for (i, r) in self.records.iter().enumerate() {
+            let from = i + 1;
+            for &(to, expected) in &r.refs {
+                let target = to.checked_sub(1).and_then(|j| self.records.get(j))
+                    .ok_or(BuildError::MissingReference { from, to })?;
+                let found = target.keyword.unwrap_or("complex entity");
+                if !is_a(found, expected) {
+                    return Err(BuildError::WrongType { from, to, expected, found });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the scaffolding around the solids (units, contexts, a product,
+    /// and its shape representation), checks every reference, then returns
+    /// the file as Part 21 text.
+    ///
+    /// A [`StepFile`](crate::step_file::StepFile) borrows the text which
+    /// it was parsed from, so the text is returned instead; it can be
+    /// parsed after [`strip_flatten`](crate::step_file::StepFile::strip_flatten).
+    pub fn finish(mut self) -> Result<String, BuildError> {
+        if self.solids.is_empty() {
+            return Err(BuildError::Empty);
+        }
+        if let Some(id) = self.non_finite {
+            return Err(BuildError::NonFinite { id });
+        }
+        self.check()?;
+
+        let mm = self.push_raw(
+            "(LENGTH_UNIT() NAMED_UNIT(*) SI_UNIT(.MILLI.,.METRE.))".to_owned());
+        let rad = self.push_raw(
+            "(NAMED_UNIT(*) PLANE_ANGLE_UNIT() SI_UNIT($,.RADIAN.))".to_owned());
+        let sr = self.push_raw(
+            "(NAMED_UNIT(*) SI_UNIT($,.STERADIAN.) SOLID_ANGLE_UNIT())".to_owned());
+        let tol = self.push_raw(format!(
+            "UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(1.E-07),#{},\
+             'distance_accuracy_value','confusion accuracy')", mm));
+        let ctx = self.push_raw(format!(
+            "(GEOMETRIC_REPRESENTATION_CONTEXT(3) \
+             GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT((#{})) \
+             GLOBAL_UNIT_ASSIGNED_CONTEXT((#{},#{},#{})) \
+             REPRESENTATION_CONTEXT('',''))", tol, mm, rad, sr));
+
+        let app = self.push_raw("APPLICATION_CONTEXT('automotive_design')".to_owned());
+        self.push_raw(format!(
+            "APPLICATION_PROTOCOL_DEFINITION('international standard',\
+             'automotive_design',2000,#{})", app));
+        let pc = self.push_raw(format!("PRODUCT_CONTEXT('',#{},'mechanical')", app));
+        let p = self.push_raw(format!("PRODUCT('{0}','{0}','',(#{1}))", self.name, pc));
+        let pdf = self.push_raw(format!("PRODUCT_DEFINITION_FORMATION('','',#{})", p));
+        let pdc = self.push_raw(format!(
+            "PRODUCT_DEFINITION_CONTEXT('part definition',#{},'design')", app));
+        let pd = self.push_raw(format!(
+            "PRODUCT_DEFINITION('design','',#{},#{})", pdf, pdc));
+
+        let origin = self.axis2_placement([0.0; 3], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+        let rep = self.push_raw(format!(
+            "SHAPE_REPRESENTATION('',(#{}),#{})", origin.0, ctx));
+        let pds = self.push_raw(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", pd));
+        self.push_raw(format!("SHAPE_DEFINITION_REPRESENTATION(#{},#{})", pds, rep));
+        let items = refs(self.solids.iter().copied().chain([origin.0]));
+        let abs = self.push_raw(format!(
+            "ADVANCED_BREP_SHAPE_REPRESENTATION('',({}),#{})", items, ctx));
+        self.push_raw(format!(
+            "SHAPE_REPRESENTATION_RELATIONSHIP('','',#{},#{})", rep, abs));
+
+        let time = if self.opts.timestamp {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            iso_timestamp(now.as_secs())
+        } else {
+            TIMESTAMP_PLACEHOLDER.to_owned()
+        };
+        let mut lines = vec![
+            "ISO-10303-21;".to_owned(),
+            "HEADER;".to_owned(),
+            "FILE_DESCRIPTION((''),'2;1');".to_owned(),
+            format!("FILE_NAME('{}','{}',(''),(''),'foxtrot','foxtrot','');", self.name, time),
+            "FILE_SCHEMA(('AUTOMOTIVE_DESIGN { 1 0 10303 214 1 1 1 1 }'));".to_owned(),
+            "ENDSEC;".to_owned(),
+            "DATA;".to_owned(),
+        ];
+        for (i, r) in self.records.iter().enumerate() {
+            lines.push(format!("#{}={};", i + 1, r.text));
+        }
+        lines.push("ENDSEC;".to_owned());
+        lines.push("END-ISO-10303-21;".to_owned());
+        Ok(self.opts.lines(lines.iter().map(|s| s.as_str())))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step_file::StepFile;
+
+    /// Builds a unit square on the XY plane, facing +Z, as a one-face solid
+    fn square() -> StepBuilder {
+        square_with(WriteOptions::default())
+    }
+
+    fn square_with(opts: WriteOptions) -> StepBuilder {
+        let mut b = StepBuilder::with_options("square", opts);
+        let corners = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        let v: Vec<_> = corners.iter().map(|p| b.vertex(*p)).collect();
+        let edges: Vec<_> = (0..4)
+            .map(|i| {
+                let line = b.line(corners[i], corners[(i + 1) % 4]);
+                (b.edge_curve(v[i], v[(i + 1) % 4], line), true)
+            })
+            .collect();
+        let lp = b.edge_loop(&edges);
+        let axis = b.axis2_placement([0.0; 3], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+        let plane = b.plane(axis);
+        let face = b.advanced_face(plane, &[lp]);
+        let shell = b.closed_shell(&[face]);
+        b.manifold_solid(shell);
+        b
+    }
+
+    #[test]
+    fn test_square() {
+        let text = square().finish().unwrap();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        assert!(step.0.iter().all(|e| !matches!(e, Entity::_FailedToParse(_))),
+                "{}", text);
+        let count = |f: fn(&Entity) -> bool| step.0.iter().filter(|e| f(e)).count();
+        assert_eq!(count(|e| matches!(e, Entity::AdvancedFace(_))), 1);
+        assert_eq!(count(|e| matches!(e, Entity::OrientedEdge(_))), 4);
+        assert_eq!(count(|e| matches!(e, Entity::ManifoldSolidBrep(_))), 1);
+        assert_eq!(count(|e| matches!(e, Entity::AdvancedBrepShapeRepresentation(_))), 1);
+
+        // Ids are allocated sequentially, starting with the first vertex
+        assert!(text.contains("#1=CARTESIAN_POINT('',(0.0,0.0,0.0));\n\
+                               #2=VERTEX_POINT('',#1);\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(StepBuilder::new("empty").finish(), Err(BuildError::Empty));
+
+        // A reference to an entity which doesn't exist
+        let mut b = square();
+        let v = b.vertex([0.0; 3]);
+        let line = b.line([0.0; 3], [1.0, 0.0, 0.0]);
+        let e = b.edge_curve(v, Id::new(1000), line);
+        assert_eq!(b.finish(), Err(BuildError::MissingReference { from: e.0, to: 1000 }));
+
+        // A reference to an entity of the wrong type, which can only be made
+        // by casting an id
+        let mut b = square();
+        let p = b.point(0.0, 0.0, 0.0);
+        let line = b.line([0.0; 3], [1.0, 0.0, 0.0]);
+        let e = b.edge_curve(p.cast(), p.cast(), line);
+        assert_eq!(b.finish(), Err(BuildError::WrongType {
+            from: e.0, to: p.0, expected: "VERTEX", found: "CARTESIAN_POINT",
+        }));
+
+        // Numbers which can't be written
+        let mut b = square();
+        let p = b.point(0.0, f64::NAN, 0.0);
+        b.point(f64::INFINITY, 0.0, 0.0);
+        assert_eq!(b.finish(), Err(BuildError::NonFinite { id: p.0 }));
+        let mut b = square();
+        let axis = b.axis2_placement([0.0; 3], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+        let c = b.circle(axis, f64::INFINITY);
+        assert_eq!(b.finish(), Err(BuildError::NonFinite { id: c.0 }));
+    }
+
+    /// Writes the square with the given options
+    fn write(opts: WriteOptions) -> String {
+        square_with(opts).finish().unwrap()
+    }
+
+    /// Returns the text of every token, which is the same however the text
+    /// is wrapped (unless a token is split).  The lexer is only for the data
+    /// section, so this stops at `END-ISO-10303-21`.
+    fn tokens(text: &str) -> Vec<&str> {
+        Lexer::new(text).map_while(Result::ok).map(|t| t.text).collect()
+    }
+
+    /// Parses text, returning its entity table for comparison
+    fn entities(text: &str) -> String {
+        let mut flat = StepFile::strip_flatten(text.as_bytes());
+        StepFile::uppercase_keywords(&mut flat);
+        let step = StepFile::parse(&flat).unwrap();
+        assert!(step.0.iter().all(|e| !matches!(e, Entity::_FailedToParse(_))),
+                "{}", text);
+        format!("{:?}", step.0)
+    }
+
+    #[test]
+    fn test_write_options() {
+        let plain = square().finish().unwrap();
+        assert_eq!(plain, write(WriteOptions::default()));
+        assert!(!plain.contains('\r'));
+        assert!(plain.contains(&format!("'{}'", TIMESTAMP_PLACEHOLDER)));
+
+        let crlf = write(WriteOptions {
+            line_ending: LineEnding::CrLf, ..Default::default()
+        });
+        assert_eq!(crlf, plain.replace('\n', "\r\n"));
+
+        // Long records are split across lines, which are all short enough
+        // unless they're a single long token (e.g. the schema's name)
+        let wrapped = write(WriteOptions { wrap: Some(30), ..Default::default() });
+        assert!(wrapped.lines().all(|l| l.len() <= 30 || tokens(l).len() == 1));
+        assert!(wrapped.lines().count() > plain.lines().count());
+        assert_eq!(wrapped.replace('\n', ""), plain.replace('\n', ""));
+        assert_eq!(tokens(&wrapped), tokens(&plain));
+
+        // Only entity and type keywords change case
+        let lower = write(WriteOptions {
+            keyword_case: KeywordCase::Lower, ..Default::default()
+        });
+        assert!(lower.contains("#1=cartesian_point('',(0.0,0.0,0.0));"), "{}", lower);
+        assert!(lower.contains("si_unit(.MILLI.,.METRE.)"), "{}", lower);
+        assert!(lower.contains("length_measure(1.E-07)"), "{}", lower);
+        assert!(lower.contains("\nDATA;\n"));
+        assert!(lower.contains("'automotive_design'"));
+        assert!(!lower.contains("CARTESIAN_POINT"));
+
+        let fixed = write(WriteOptions { precision: Some(3), ..Default::default() });
+        assert!(fixed.contains("#1=CARTESIAN_POINT('',(0.000,0.000,0.000));"), "{}", fixed);
+        let exp = write(WriteOptions { exponent: true, ..Default::default() });
+        assert!(exp.contains("#1=CARTESIAN_POINT('',(0.E0,0.E0,0.E0));"), "{}", exp);
+
+        // The timestamp is the only thing which changes between runs
+        let stamped = write(WriteOptions { timestamp: true, ..Default::default() });
+        assert!(!stamped.contains(TIMESTAMP_PLACEHOLDER));
+        assert_eq!(stamped.lines().count(), plain.lines().count());
+
+        let strict = write(WriteOptions::conformance());
+        assert!(strict.split("\r\n").all(|l| l.len() <= 72));
+        assert!(!strict.replace("\r\n", "").contains('\n'));
+        assert_eq!(tokens(&strict), tokens(&strict.replace("\r\n", "")));
+
+        // Every variant reads back as the same entities
+        let expected = entities(&plain);
+        for text in [crlf, wrapped, lower, fixed, exp, stamped, strict] {
+            assert_eq!(entities(&text), expected);
+        }
+    }
+
+    #[test]
+    fn test_wrap_between_tokens() {
+        let opts = WriteOptions { wrap: Some(12), ..Default::default() };
+        let line = "#12=CARTESIAN_POINT('a (long) name, with spaces',\
+                    (1.25E-3,-12.5,.T.));";
+        let wrapped = opts.lines([line]);
+        assert_eq!(wrapped.replace('\n', ""), line);
+        assert_eq!(tokens(&wrapped), tokens(line));
+        // Tokens which are too long get a line to themselves, and the rest
+        // are packed into the limit
+        for l in wrapped.lines() {
+            assert!(l.len() <= 12 || tokens(l).len() == 1, "{:?}", l);
+        }
+        assert!(wrapped.contains("\n'a (long) name, with spaces'\n"), "{}", wrapped);
+
+        // Escaped quotes don't end a string, and whitespace starts a line
+        let opts = |wrap| WriteOptions { wrap: Some(wrap), ..Default::default() };
+        assert_eq!(opts(8).lines(["('it''s',$) (A)"]), "('it''s'\n,$) (A)\n");
+        assert_eq!(opts(3).lines(["$,$ (A)"]), "$,$\n (A\n)\n");
+        assert_eq!(opts(3).lines([""]), "\n");
+    }
+
+    #[test]
+    fn test_real() {
+        let opts = |precision, exponent| WriteOptions {
+            precision, exponent, ..Default::default()
+        };
+        assert_eq!(opts(None, false).real(1.0), Some("1.0".to_owned()));
+        assert_eq!(opts(None, false).real(1e-7), Some("1.E-7".to_owned()));
+        assert_eq!(opts(None, false).real(2.5e-8), Some("2.5E-8".to_owned()));
+        assert_eq!(opts(None, true).real(1.0), Some("1.E0".to_owned()));
+        assert_eq!(opts(None, true).real(-1250.0), Some("-1.25E3".to_owned()));
+        assert_eq!(opts(Some(2), false).real(1.0 / 3.0), Some("0.33".to_owned()));
+        assert_eq!(opts(Some(0), false).real(2.0), Some("2.".to_owned()));
+        assert_eq!(opts(Some(2), true).real(1250.0), Some("1.25E3".to_owned()));
+        assert_eq!(opts(Some(0), true).real(1250.0), Some("1.E3".to_owned()));
+
+        // Part 21 has no way to write these
+        for x in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            assert_eq!(opts(None, false).real(x), None);
+            assert_eq!(opts(Some(2), true).real(x), None);
+        }
+    }
+
+    #[test]
+    fn test_iso_timestamp() {
+        assert_eq!(iso_timestamp(0), TIMESTAMP_PLACEHOLDER);
+        assert_eq!(iso_timestamp(951_782_400), "2000-02-29T00:00:00");
+        assert_eq!(iso_timestamp(1_700_000_000), "2023-11-14T22:13:20");
+    }
+
+    #[test]
+    fn test_is_a() {
+        assert!(is_a("LINE", "CURVE"));
+        assert!(is_a("PLANE", "SURFACE"));
+        assert!(is_a("FACE_OUTER_BOUND", "FACE_BOUND"));
+        assert!(is_a("VERTEX_POINT", "VERTEX"));
+        assert!(!is_a("CARTESIAN_POINT", "VERTEX"));
+    }
+}
@@ -0,0 +1,520 @@
+//! Validation of a STEP file against the `WHERE` rules of an EXPRESS schema.
+//!
+//! Each entity instance is mapped to the schema entity with the same name,
+//! and its attribute values are bound into an
+//! [`express::eval::Context`].  Attribute values are recovered from the
+//! parsed entity's `Debug` representation (as in [`crate::diff`]), since
+//! the generated types have no other form of reflection.  Derived
+//! attributes are computed from the schema's `DERIVE` clauses where
+//! possible, then every `WHERE` rule of the entity and its supertypes is
+//! evaluated.
+//!
+//! Rules which evaluate to `UNKNOWN` are satisfied, per the EXPRESS spec.
+//! Rules which can't be evaluated (e.g. because they call a user-defined
+//! function) are reported as [`RuleResult::NotEvaluated`] rather than
+//! guessed at.
+use std::collections::HashMap;
+
+use express::{
+    eval::{Context, Evaluator, Instance, Logical, Value},
+    parse::{AttributeDecl, Declaration, DeclarationOrRuleDecl, EntityDecl, SchemaDecl},
+};
+
+use crate::{ap214::Entity, step_file::StepFile};
+
+/// Outcome of a rule which didn't pass
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleResult {
+    /// The rule evaluated to `FALSE`
+    False,
+    /// The rule couldn't be evaluated, for the given reason
+    NotEvaluated(String),
+}
+
+/// A `WHERE` rule which failed (or couldn't be checked) for an instance
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleViolation {
+    /// Entity instance id
+    pub id: usize,
+    /// Schema entity which declares the rule, which may be a supertype of
+    /// the instance's entity
+    pub entity: String,
+    /// Rule label, or its 1-based position if it's unlabelled
+    pub rule: String,
+    pub result: RuleResult,
+}
+
+/// Checks every entity instance in the file against the `WHERE` rules of
+/// its entity (and supertypes) in the schema.  Instances of entities which
+/// aren't in the schema are skipped.
+pub fn validate_against_schema(s: &StepFile, schema: &SchemaDecl) -> Vec<RuleViolation> {
+    let entities: HashMap<&str, &EntityDecl> = schema
+        .body
+        .declarations
+        .iter()
+        .filter_map(|d| match d {
+            DeclarationOrRuleDecl::Declaration(Declaration::Entity(e)) => Some((e.0 .0 .0, e)),
+            _ => None,
+        })
+        .collect();
+
+    // Bind every instance, remembering the schema entities (including
+    // supertypes, in declaration order) which apply to each one
+    let mut ctx = Context::default();
+    let mut applicable = vec![];
+    for (id, e) in s.0.iter().enumerate() {
+        let parts = match e {
+            Entity::ComplexEntity(v) => v.iter().collect(),
+            Entity::_EmptySlot | Entity::_FailedToParse(_) => continue,
+            e => vec![e],
+        };
+        let mut instance = Instance::default();
+        let mut decls = vec![];
+        for part in parts {
+            let keyword = match part.keyword() {
+                Some(k) => k.to_lowercase(),
+                None => continue,
+            };
+            bind_attributes(part, &mut instance);
+            ancestors(&entities, &keyword, &mut decls);
+            if instance.entity.is_empty() {
+                instance.entity = keyword;
+            }
+        }
+        ctx.instances.insert(id, instance);
+        if !decls.is_empty() {
+            applicable.push((id, decls));
+        }
+    }
+
+    // Derived attributes may depend on derived attributes of other
+    // instances, so keep trying to compute them until nothing changes
+    loop {
+        let mut changed = false;
+        for (id, decls) in &applicable {
+            ctx.this = Some(Value::Entity(*id));
+            for (name, expr) in decls.iter().flat_map(|e| derived(e)) {
+                if ctx.instances[id].attributes.contains_key(name) {
+                    continue;
+                }
+                if let Ok(v) = Evaluator::new(&ctx).eval(expr) {
+                    let i = ctx.instances.get_mut(id).unwrap();
+                    i.attributes.insert(name.to_owned(), v);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut out = vec![];
+    for (id, decls) in &applicable {
+        ctx.this = Some(Value::Entity(*id));
+        for decl in decls {
+            let rules = decl.1.where_.iter().flat_map(|w| w.0.iter());
+            for (i, rule) in rules.enumerate() {
+                let result = match Evaluator::new(&ctx).eval_logical(&rule.expression) {
+                    Ok(Logical::True) | Ok(Logical::Unknown) => continue,
+                    Ok(Logical::False) => RuleResult::False,
+                    Err(e) => RuleResult::NotEvaluated(e.to_string()),
+                };
+                out.push(RuleViolation {
+                    id: *id,
+                    entity: decl.0 .0 .0.to_owned(),
+                    rule: rule
+                        .rule_label_id
+                        .map_or_else(|| (i + 1).to_string(), |r| r.0.to_owned()),
+                    result,
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Pushes the declarations of an entity and its supertypes (supertypes
+/// first), skipping any which are already present
+fn ancestors<'a, 'b>(
+    entities: &HashMap<&str, &'b EntityDecl<'a>>,
+    name: &str,
+    out: &mut Vec<&'b EntityDecl<'a>>,
+) {
+    let e = match entities.get(name) {
+        Some(e) => *e,
+        None => return,
+    };
+    if out.iter().any(|d| std::ptr::eq(*d, e)) {
+        return;
+    }
+    for sup in e.0 .1 .1.iter().flat_map(|s| s.0.iter()) {
+        ancestors(entities, sup.0, out);
+    }
+    out.push(e);
+}
+
+/// Returns the derived attributes of an entity, by name.  Attributes which
+/// redeclare a supertype's attribute are stored under the original name.
+fn derived<'a, 'b>(
+    e: &'b EntityDecl<'a>,
+) -> impl Iterator<Item = (&'a str, &'b express::parse::Expression<'a>)> {
+    e.1.derive.iter().flat_map(|d| d.0.iter()).map(|d| {
+        let name = match &d.0 {
+            AttributeDecl::Id(i) => i.0,
+            AttributeDecl::Redeclared(r) => r.1.map_or(r.0 .1 .0 .0, |i| i.0),
+        };
+        (name, &d.2)
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A value parsed from an entity's `Debug` representation
+#[derive(Debug, PartialEq)]
+pub(crate) enum Node<'a> {
+    /// A bare identifier, e.g. an enum variant, `None`, or `true`
+    Name(&'a str),
+    Number(&'a str),
+    String(String),
+    List(Vec<Node<'a>>),
+    Tuple(&'a str, Vec<Node<'a>>),
+    Struct(&'a str, Vec<(&'a str, Node<'a>)>),
+}
+
+/// Recursive-descent parser for `Debug` output
+struct Reader<'a> {
+    s: &'a str,
+    i: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.as_bytes().get(self.i).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.i += 1;
+        }
+    }
+
+    /// Consumes `c` (after any whitespace), returning whether it was there
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.i += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while<F: Fn(u8) -> bool>(&mut self, f: F) -> &'a str {
+        let start = self.i;
+        while self.peek().is_some_and(&f) {
+            self.i += 1;
+        }
+        &self.s[start..self.i]
+    }
+
+    /// Parses a comma-separated list of items, up to the `close` character
+    fn items<T, F: FnMut(&mut Self) -> Option<T>>(
+        &mut self,
+        close: u8,
+        mut f: F,
+    ) -> Option<Vec<T>> {
+        let mut out = vec![];
+        while !self.eat(close) {
+            out.push(f(self)?);
+            if !self.eat(b',') {
+                return self.eat(close).then_some(out);
+            }
+        }
+        Some(out)
+    }
+
+    fn node(&mut self) -> Option<Node<'a>> {
+        self.skip_ws();
+        match self.peek()? {
+            b'"' => self.string().map(Node::String),
+            b'[' => {
+                self.i += 1;
+                self.items(b']', Self::node).map(Node::List)
+            }
+            c if c.is_ascii_digit() || c == b'-' => {
+                Some(Node::Number(self.take_while(|c| {
+                    c.is_ascii_alphanumeric() || b"-+.".contains(&c)
+                })))
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == b'_');
+                // Skip generic parameters, e.g. `PhantomData<&()>`
+                if self.peek() == Some(b'<') {
+                    let mut depth = 0;
+                    while let Some(c) = self.peek() {
+                        self.i += 1;
+                        depth += (c == b'<') as i32 - (c == b'>') as i32;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                }
+                if self.eat(b'(') {
+                    self.items(b')', Self::node).map(|v| Node::Tuple(name, v))
+                } else if self.eat(b'{') {
+                    self.items(b'}', |r| {
+                        r.skip_ws();
+                        let field = r.take_while(|c| c.is_ascii_alphanumeric() || c == b'_');
+                        if !r.eat(b':') {
+                            return None;
+                        }
+                        Some((field, r.node()?))
+                    })
+                    .map(|v| Node::Struct(name, v))
+                } else {
+                    Some(Node::Name(name))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses a quoted string, undoing `Debug` escapes
+    fn string(&mut self) -> Option<String> {
+        let mut out = String::new();
+        let mut chars = self.s[self.i + 1..].char_indices();
+        while let Some((j, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.i += j + 2;
+                    return Some(out);
+                }
+                '\\' => match chars.next()?.1 {
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    '0' => out.push('\0'),
+                    'u' => {
+                        let hex: String = chars
+                            .by_ref()
+                            .map(|(_, c)| c)
+                            .skip(1)
+                            .take_while(|c| *c != '}')
+                            .collect();
+                        out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+        None
+    }
+}
+
+/// Parses a value from its `Debug` representation
+pub(crate) fn debug_node(s: &str) -> Option<Node<'_>> {
+    Reader { s, i: 0 }.node()
+}
+
+/// Converts a `CamelCase` enum variant into its `snake_case` EXPRESS name
+fn snake_case(s: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in s.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+fn to_value(n: &Node) -> Value {
+    match n {
+        Node::String(s) => Value::String(s.clone()),
+        Node::Number(t) => match t.parse::<i64>() {
+            Ok(i) if !t.contains(['.', 'e', 'E']) => Value::Integer(i),
+            _ => t.parse().map_or(Value::Indeterminate, Value::Real),
+        },
+        Node::List(v) => Value::Aggregate(v.iter().map(to_value).collect()),
+        Node::Name("None") | Node::Name("Derived") => Value::Indeterminate,
+        Node::Name("true") => Value::Logical(Logical::True),
+        Node::Name("false") => Value::Logical(Logical::False),
+        Node::Name("inf") | Node::Name("NaN") => Value::Indeterminate,
+        Node::Name(n) => Value::Enumeration(snake_case(n)),
+        Node::Tuple("Id", v) => match v.first() {
+            Some(Node::Number(t)) => t.parse().map_or(Value::Indeterminate, Value::Entity),
+            _ => Value::Indeterminate,
+        },
+        Node::Tuple("Logical", v) if v.len() == 1 => match to_value(&v[0]) {
+            l @ Value::Logical(_) => l,
+            _ => Value::Logical(Logical::Unknown),
+        },
+        Node::Tuple(_, v) => {
+            // Newtypes and select variants are unwrapped, ignoring markers
+            let mut v: Vec<Value> = v
+                .iter()
+                .filter(|n| **n != Node::Name("PhantomData"))
+                .map(to_value)
+                .collect();
+            if v.len() == 1 {
+                v.pop().unwrap()
+            } else {
+                Value::Aggregate(v)
+            }
+        }
+        Node::Struct(..) => Value::Indeterminate,
+    }
+}
+
+/// Adds the attributes of a (non-complex) entity to an instance
+fn bind_attributes(e: &Entity, instance: &mut Instance) {
+    let text = format!("{:?}", e);
+    let fields = match (Reader { s: &text, i: 0 }).node() {
+        Some(Node::Tuple(_, mut v)) if v.len() == 1 => match v.pop() {
+            Some(Node::Struct(_, fields)) => fields,
+            _ => return,
+        },
+        _ => return,
+    };
+    for (name, node) in fields {
+        if name == "_marker" {
+            continue;
+        }
+        // Attributes inherited with the same name from different parents
+        // are stored as `parent__name`
+        let name = name.rsplit("__").next().unwrap_or(name);
+        instance.attributes.insert(name.to_owned(), to_value(&node));
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use express::parse::{parse, strip_comments_and_lower};
+
+    /// A few entities from AP214, with their WHERE rules.  The real rule on
+    /// `uncertainty_measure_with_unit` calls `valid_measure_value`, which is
+    /// inlined here.
+    const SCHEMA: &str = r#"
+SCHEMA test_schema;
+ENTITY representation_item;
+  name : label;
+END_ENTITY;
+ENTITY geometric_representation_item SUBTYPE OF (representation_item);
+DERIVE
+  dim : dimension_count := dimension_of(SELF);
+END_ENTITY;
+ENTITY cartesian_point SUBTYPE OF (geometric_representation_item);
+  coordinates : LIST [1:3] OF length_measure;
+WHERE
+  wr1 : SIZEOF(coordinates) = 3;
+END_ENTITY;
+ENTITY direction SUBTYPE OF (geometric_representation_item);
+  direction_ratios : LIST [2:3] OF REAL;
+DERIVE
+  n : INTEGER := SIZEOF(direction_ratios);
+WHERE
+  wr1 : SIZEOF(QUERY(tmp <* direction_ratios | (tmp <> 0.0))) > 0;
+  wr2 : n >= 2;
+END_ENTITY;
+ENTITY placement SUBTYPE OF (geometric_representation_item);
+  location : cartesian_point;
+END_ENTITY;
+ENTITY axis2_placement_3d SUBTYPE OF (placement);
+  axis : OPTIONAL direction;
+  ref_direction : OPTIONAL direction;
+WHERE
+  wr1 : SELF\placement.location.dim = 3;
+  wr2 : (NOT EXISTS(axis)) OR (axis.n = 3);
+END_ENTITY;
+ENTITY measure_with_unit;
+  value_component : measure_value;
+  unit_component : unit;
+END_ENTITY;
+ENTITY uncertainty_measure_with_unit SUBTYPE OF (measure_with_unit);
+  name : label;
+  description : OPTIONAL text;
+WHERE
+  wr1 : SELF\measure_with_unit.value_component > 0.0;
+END_ENTITY;
+END_SCHEMA;
+"#;
+
+    fn validate(text: &str) -> Vec<RuleViolation> {
+        let schema = strip_comments_and_lower(SCHEMA.as_bytes());
+        let mut syntax = parse(&schema).unwrap();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        validate_against_schema(&step, &syntax.0.remove(0))
+    }
+
+    #[test]
+    fn test_reader() {
+        let text = r#"Foo(Foo_ { a: Some(Id(3, PhantomData<*const a::B_<'_>>)), b: [1.5, -2], c: "x\"y", d: Logical(None), e: Unspecified, _marker: PhantomData<&()> })"#;
+        let mut e = Instance::default();
+        let fields = match (Reader { s: text, i: 0 }).node() {
+            Some(Node::Tuple("Foo", mut v)) => match v.pop() {
+                Some(Node::Struct("Foo_", fields)) => fields,
+                n => panic!("{:?}", n),
+            },
+            n => panic!("{:?}", n),
+        };
+        for (k, n) in &fields {
+            e.attributes.insert(k.to_string(), to_value(n));
+        }
+        assert_eq!(e.attributes["a"], Value::Entity(3));
+        assert_eq!(
+            e.attributes["b"],
+            Value::Aggregate(vec![Value::Real(1.5), Value::Integer(-2)])
+        );
+        assert_eq!(e.attributes["c"], Value::String("x\"y".to_owned()));
+        assert_eq!(e.attributes["d"], Value::Logical(Logical::Unknown));
+        assert_eq!(
+            e.attributes["e"],
+            Value::Enumeration("unspecified".to_owned())
+        );
+        assert_eq!(snake_case("ContSameGradient"), "cont_same_gradient");
+    }
+
+    #[test]
+    fn test_valid() {
+        // Placements can't have their WHERE rule checked, since `dim` is
+        // derived through a user-defined function
+        let out = validate(&fixture::cube(10.0).to_step());
+        assert!(!out.is_empty());
+        for v in &out {
+            assert_eq!(v.entity, "axis2_placement_3d");
+            assert_eq!(v.rule, "wr1");
+            match &v.result {
+                RuleResult::NotEvaluated(e) => assert!(e.contains("dim"), "{}", e),
+                r => panic!("Unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let text = fixture::cube(10.0)
+            .to_step()
+            .replace("LENGTH_MEASURE(1.E-07)", "LENGTH_MEASURE(-1.E-07)")
+            .replace("DIRECTION('',(0.0,0.0,1.0))", "DIRECTION('',(0.0,0.0,0.0))");
+        let out: Vec<_> = validate(&text)
+            .into_iter()
+            .filter(|v| v.result == RuleResult::False)
+            .collect();
+        assert!(out
+            .iter()
+            .any(|v| v.entity == "uncertainty_measure_with_unit" && v.rule == "wr1"));
+        assert!(out
+            .iter()
+            .any(|v| v.entity == "direction" && v.rule == "wr1"));
+        assert!(out
+            .iter()
+            .all(|v| v.entity == "direction" || v.entity == "uncertainty_measure_with_unit"));
+    }
+}
@@ -0,0 +1,286 @@
+//! Reports which entity types in a STEP file are understood.
+//!
+//! Every keyword in the file is counted and classified as parsed, unparsed
+//! (the keyword is unknown, or some instances failed to parse), or ignored
+//! (parsed, but not used by a particular consumer).  Consumers like the
+//! triangulator provide their own list of the keywords they use; see
+//! [`CoverageReport::consumed_by`].
+use std::collections::BTreeMap;
+
+use crate::{
+    ap214::{superclasses_of, Entity, KEYWORDS},
+    step_file::StepFile,
+};
+
+/// How well an entity type is handled
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Support {
+    /// Every instance was parsed (and is used by the consumer, if any)
+    Parsed,
+    /// Every instance was parsed, but the consumer doesn't use them
+    Ignored,
+    /// At least one instance failed to parse, or the keyword is unknown
+    Unparsed,
+}
+
+impl Support {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Support::Parsed => "parsed",
+            Support::Ignored => "ignored",
+            Support::Unparsed => "unparsed",
+        }
+    }
+}
+
+/// Coverage of a single entity type
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EntityCoverage {
+    /// Entity keyword, e.g. `CARTESIAN_POINT`
+    pub keyword: String,
+    /// Number of instances, including parts of complex entities
+    pub count: usize,
+    /// Number of instances which failed to parse
+    pub failed: usize,
+    pub support: Support,
+    /// Whether the keyword is one which the parser understands
+    pub known: bool,
+    /// Whether this is a geometric or topological item, i.e. something
+    /// which could end up in a drawing of the model
+    pub geometric: bool,
+}
+
+/// Result of [`report`]
+#[derive(Clone, Debug, Default)]
+pub struct CoverageReport {
+    /// One entry per keyword in the file, sorted by keyword
+    pub entities: Vec<EntityCoverage>,
+}
+
+impl CoverageReport {
+    /// Marks parsed entity types which aren't in `consumed` as ignored
+    pub fn consumed_by(mut self, consumed: &[&str]) -> Self {
+        for e in &mut self.entities {
+            if e.support == Support::Parsed
+                && !consumed.contains(&e.keyword.as_str())
+            {
+                e.support = Support::Ignored;
+            }
+        }
+        self
+    }
+
+    /// Looks up the coverage of a single keyword
+    pub fn get(&self, keyword: &str) -> Option<&EntityCoverage> {
+        self.entities.iter().find(|e| e.keyword == keyword)
+    }
+
+    /// Returns geometric entity types which are ignored or unparsed, most
+    /// common first.  When a model comes out empty, these are the likely
+    /// culprits.
+    pub fn unsupported_geometry(&self) -> Vec<&EntityCoverage> {
+        let mut out: Vec<_> = self.entities.iter()
+            .filter(|e| e.geometric && e.support != Support::Parsed)
+            .collect();
+        out.sort_by(|a, b| b.count.cmp(&a.count)
+            .then_with(|| a.keyword.cmp(&b.keyword)));
+        out
+    }
+}
+
+/// Counts and classifies every entity type in a STEP file.  Every
+/// successfully-parsed type is reported as [`Support::Parsed`]; use
+/// [`CoverageReport::consumed_by`] to find types which are then ignored.
+pub fn report(s: &StepFile) -> CoverageReport {
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for e in &s.0 {
+        match e {
+            Entity::_EmptySlot => (),
+            Entity::_FailedToParse(text) => {
+                for k in leaf_keywords(keywords_of(text)) {
+                    let c = counts.entry(k).or_default();
+                    c.0 += 1;
+                    c.1 += 1;
+                }
+            }
+            Entity::ComplexEntity(v) => {
+                let ks = v.iter().filter_map(|e| e.keyword()).collect();
+                for k in leaf_keywords(ks) {
+                    counts.entry(k).or_default().0 += 1;
+                }
+            }
+            e => if let Some(k) = e.keyword() {
+                counts.entry(k).or_default().0 += 1;
+            },
+        }
+    }
+    let entities = counts.into_iter()
+        .map(|(k, (count, failed))| {
+            let known = KEYWORDS.contains(&k);
+            EntityCoverage {
+                keyword: k.to_owned(),
+                count,
+                failed,
+                support: if failed > 0 || !known {
+                    Support::Unparsed
+                } else {
+                    Support::Parsed
+                },
+                known,
+                geometric: is_geometric(k),
+            }
+        })
+        .collect();
+    CoverageReport { entities }
+}
+
+/// Finds the keywords in the text of an entity which failed to parse.
+/// This is a single keyword for simple entities, or a list of keywords for
+/// complex entities, e.g. `(NAMED_UNIT(*)LENGTH_UNIT()SI_UNIT(.MILLI.,.METRE.))`
+fn keywords_of(text: &str) -> Vec<&str> {
+    let is_name = |c: u8| c == b'_' || c.is_ascii_alphanumeric();
+    let b = text.as_bytes();
+    if b.first() != Some(&b'(') {
+        let end = b.iter().position(|c| !is_name(*c)).unwrap_or(b.len());
+        return if end == 0 { vec![] } else { vec![&text[..end]] };
+    }
+    let mut out = vec![];
+    let mut depth = 0;
+    let mut i = 0;
+    while i < b.len() {
+        match b[i] {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            // Escaped quotes ('') are handled as two adjacent strings
+            b'\'' => match b[i + 1..].iter().position(|c| *c == b'\'') {
+                Some(j) => i += j + 1,
+                None => break,
+            },
+            c if depth == 1 && is_name(c) => {
+                let start = i;
+                while i < b.len() && is_name(b[i]) {
+                    i += 1;
+                }
+                out.push(&text[start..i]);
+                continue;
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Returns every supertype of a keyword, including itself
+pub(crate) fn ancestors(k: &str) -> Vec<&str> {
+    let mut out = vec![k];
+    let mut i = 0;
+    while i < out.len() {
+        for s in superclasses_of(out[i]) {
+            if !out.contains(s) {
+                out.push(s);
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Removes keywords from a complex entity which are supertypes of other
+/// parts of the same entity, e.g. `NAMED_UNIT` alongside `SI_UNIT`, since
+/// they don't add a new type of entity.
+fn leaf_keywords(ks: Vec<&str>) -> Vec<&str> {
+    ks.iter()
+        .filter(|k| !ks.iter().any(|o| o != *k && ancestors(o).contains(k)))
+        .copied()
+        .collect()
+}
+
+fn is_geometric(k: &str) -> bool {
+    ancestors(k).iter().any(|a| matches!(*a,
+        "GEOMETRIC_REPRESENTATION_ITEM" | "TOPOLOGICAL_REPRESENTATION_ITEM"))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn report_text(text: &str) -> CoverageReport {
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        report(&step)
+    }
+
+    #[test]
+    fn test_keywords_of() {
+        assert_eq!(keywords_of("PLANE('',#3)"), vec!["PLANE"]);
+        assert_eq!(keywords_of("('"), Vec::<&str>::new());
+        assert_eq!(
+            keywords_of("(NAMED_UNIT(*)LENGTH_UNIT()SI_UNIT(.MILLI.,.METRE.))"),
+            vec!["NAMED_UNIT", "LENGTH_UNIT", "SI_UNIT"]);
+        // Names in strings and nested lists are skipped
+        assert_eq!(keywords_of("(A('B(',(C(1)))D())"), vec!["A", "D"]);
+        assert_eq!(
+            leaf_keywords(vec!["NAMED_UNIT", "LENGTH_UNIT", "SI_UNIT"]),
+            vec!["LENGTH_UNIT", "SI_UNIT"]);
+    }
+
+    #[test]
+    fn test_report() {
+        let text = fixture::cube(1.0).to_step();
+        let plane = text.lines()
+            .find(|line| line.contains("=PLANE("))
+            .unwrap();
+        let id = &plane[..plane.find('=').unwrap()];
+        let axis = plane.rsplit('#').next().unwrap().trim_end_matches(");");
+        let next = text.lines()
+            .filter_map(|line| line.strip_prefix('#')?.split('=').next()?
+                .parse::<usize>().ok())
+            .max()
+            .unwrap() + 1;
+        // Swap one of the planes for a surface of revolution, and add an
+        // entity with an unknown keyword and one which fails to parse
+        let text = text
+            .replace(plane, &format!(
+                "{}=SURFACE_OF_REVOLUTION('',#{},#{});", id, axis, axis))
+            .replace("ENDSEC;\nEND-ISO", &format!(
+                "#{}=FANCY_SURFACE('',#1);\n\
+                 #{}=DIRECTION('',(0.,0.,1.,0.,0.));\nENDSEC;\nEND-ISO",
+                next, next + 1));
+        let r = report_text(&text);
+
+        let rev = r.get("SURFACE_OF_REVOLUTION").unwrap();
+        assert_eq!((rev.count, rev.support), (1, Support::Parsed));
+        assert!(rev.known && rev.geometric);
+        let plane = r.get("PLANE").unwrap();
+        assert_eq!(plane.count, 5);
+
+        let fancy = r.get("FANCY_SURFACE").unwrap();
+        assert_eq!((fancy.failed, fancy.support), (1, Support::Unparsed));
+        assert!(!fancy.known && !fancy.geometric);
+        let dir = r.get("DIRECTION").unwrap();
+        assert_eq!((dir.failed, dir.support), (1, Support::Unparsed));
+        assert!(dir.known && dir.count > 1);
+
+        // Parts of complex entities are counted, without their supertypes
+        assert!(r.get("SI_UNIT").is_some());
+        assert!(r.get("NAMED_UNIT").is_none());
+
+        let r = r.consumed_by(&["PLANE", "DIRECTION"]);
+        assert_eq!(r.get("PLANE").unwrap().support, Support::Parsed);
+        assert_eq!(r.get("SURFACE_OF_REVOLUTION").unwrap().support,
+                   Support::Ignored);
+        assert_eq!(r.get("DIRECTION").unwrap().support, Support::Unparsed);
+        let unsupported: Vec<_> = r.unsupported_geometry().iter()
+            .map(|e| e.keyword.as_str())
+            .collect();
+        assert!(unsupported.contains(&"SURFACE_OF_REVOLUTION"));
+        assert!(unsupported.contains(&"DIRECTION"));
+        assert!(!unsupported.contains(&"PLANE"));
+        assert!(!unsupported.contains(&"FANCY_SURFACE"));
+        assert!(r.entities.windows(2).all(|w| w[0].keyword < w[1].keyword));
+    }
+}
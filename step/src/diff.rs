@@ -0,0 +1,724 @@
+//! Semantic comparison of two STEP files.
+//!
+//! Entity ids are arbitrary, so they can't be compared directly.  Instead,
+//! each entity is reduced to its type, its attribute values, and the list
+//! of entities that it refers to.  Entities are then paired up between the
+//! two files: first where a whole subtree is identical (and unique), then
+//! by walking down from the roots of the entity graph and pairing children
+//! in the same position.  An entity is only reported as modified if its own
+//! attributes changed; parents of a moved point are unchanged, as long as
+//! they still refer to the (paired) point.
+//!
+//! The header section isn't stored in [`StepFile`], so volatile fields like
+//! timestamps and originating systems never show up as differences.
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use crate::{ap214::Entity, step_file::StepFile};
+
+/// Options for [`diff`]
+#[derive(Copy, Clone, Debug)]
+pub struct DiffOptions {
+    /// Largest difference between two real numbers for them to count as
+    /// equal
+    pub tolerance: f64,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self { tolerance: 1e-9 }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+/// A single entity which differs between the two files
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    /// Entity type, e.g. `CartesianPoint`
+    pub entity_type: String,
+    /// Entity id in the first file, unless the entity was added
+    pub a: Option<usize>,
+    /// Entity id in the second file, unless the entity was removed
+    pub b: Option<usize>,
+    /// Name of the product whose shape or definition contains the entity,
+    /// or `None` if it isn't part of exactly one product.  For modified
+    /// entities, this is the name in the second file.
+    pub product: Option<String>,
+}
+
+/// Number of changes of each kind in one group
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChangeCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+}
+
+impl ChangeCounts {
+    fn add(&mut self, kind: ChangeKind) {
+        match kind {
+            ChangeKind::Added => self.added += 1,
+            ChangeKind::Removed => self.removed += 1,
+            ChangeKind::Modified => self.modified += 1,
+        }
+    }
+}
+
+/// Result of [`diff`]
+#[derive(Clone, Debug, Default)]
+pub struct DiffReport {
+    /// Changed entities, sorted by kind, then by id
+    pub changes: Vec<Change>,
+    /// Number of entities which were paired up and are unchanged
+    pub unchanged: usize,
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns total counts for each kind of change
+    pub fn counts(&self) -> ChangeCounts {
+        let mut out = ChangeCounts::default();
+        for c in &self.changes {
+            out.add(c.kind);
+        }
+        out
+    }
+
+    /// Groups changes by entity type
+    pub fn by_type(&self) -> BTreeMap<&str, ChangeCounts> {
+        let mut out: BTreeMap<_, ChangeCounts> = BTreeMap::new();
+        for c in &self.changes {
+            out.entry(c.entity_type.as_str()).or_default().add(c.kind);
+        }
+        out
+    }
+
+    /// Groups changes by product, with changes outside of any single
+    /// product under `None`
+    pub fn by_product(&self) -> BTreeMap<Option<&str>, ChangeCounts> {
+        let mut out: BTreeMap<_, ChangeCounts> = BTreeMap::new();
+        for c in &self.changes {
+            out.entry(c.product.as_deref()).or_default().add(c.kind);
+        }
+        out
+    }
+}
+
+/// Compares two STEP files, returning the entities which were added,
+/// removed, or modified
+pub fn diff(a: &StepFile, b: &StepFile, opts: &DiffOptions) -> DiffReport {
+    let ga = Graph::new(a, opts.tolerance);
+    let gb = Graph::new(b, opts.tolerance);
+    let mut m = Matching::new(&ga, &gb);
+
+    // Identical subtrees which only appear once in each file
+    let mut count: HashMap<u64, (usize, usize)> = HashMap::new();
+    for i in ga.live() {
+        count.entry(ga.deep[i]).or_default().0 += 1;
+    }
+    for j in gb.live() {
+        count.entry(gb.deep[j]).or_default().1 += 1;
+    }
+    let mut unique: HashMap<u64, usize> = HashMap::new();
+    for j in gb.live() {
+        if count[&gb.deep[j]] == (1, 1) {
+            unique.insert(gb.deep[j], j);
+        }
+    }
+    for i in ga.live() {
+        if let Some(&j) = unique.get(&ga.deep[i]) {
+            m.pair(i, j);
+        }
+    }
+
+    // Roots, paired by attributes and then by type
+    let roots_a: Vec<usize> = ga.live().filter(|&i| ga.parents[i] == 0).collect();
+    let roots_b: Vec<usize> = gb.live().filter(|&j| gb.parents[j] == 0).collect();
+    m.pair_by(&roots_a, &roots_b, |g, i| (g.kind[i].clone(), g.shallow[i]));
+    m.pair_by(&roots_a, &roots_b, |g, i| (g.kind[i].clone(), 0));
+
+    // Walk down from every pair, pairing children by position
+    m.propagate();
+
+    // Anything left over is paired by contents, if possible
+    let rest_a: Vec<usize> = ga.live().filter(|&i| m.a_to_b[i].is_none()).collect();
+    let rest_b: Vec<usize> = gb.live().filter(|&j| m.b_to_a[j].is_none()).collect();
+    m.pair_by(&rest_a, &rest_b, |g, i| (String::new(), g.deep[i]));
+    m.pair_by(&rest_a, &rest_b, |g, i| (g.kind[i].clone(), g.shallow[i]));
+    m.propagate();
+
+    let products_a = products(a, &ga);
+    let products_b = products(b, &gb);
+    let mut report = DiffReport::default();
+    for i in ga.live() {
+        match m.a_to_b[i] {
+            None => report.changes.push(Change {
+                kind: ChangeKind::Removed,
+                entity_type: ga.kind[i].clone(),
+                a: Some(i),
+                b: None,
+                product: products_a[i].clone(),
+            }),
+            Some(j) if !m.same(i, j, opts.tolerance) => report.changes.push(Change {
+                kind: ChangeKind::Modified,
+                entity_type: gb.kind[j].clone(),
+                a: Some(i),
+                b: Some(j),
+                product: products_b[j].clone(),
+            }),
+            Some(_) => report.unchanged += 1,
+        }
+    }
+    for j in gb.live().filter(|&j| m.b_to_a[j].is_none()) {
+        report.changes.push(Change {
+            kind: ChangeKind::Added,
+            entity_type: gb.kind[j].clone(),
+            a: None,
+            b: Some(j),
+            product: products_b[j].clone(),
+        });
+    }
+    report.changes.sort_by_key(|c| (c.kind, c.a, c.b));
+    report
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// One piece of an entity's attributes
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Text(String),
+    Real(f64),
+    Ref(usize),
+}
+
+/// Finds the index just past the bracket which closes the one at `i`
+fn skip_brackets(b: &[u8], mut i: usize, open: u8, close: u8) -> usize {
+    let mut depth = 0;
+    while i < b.len() {
+        if b[i] == open {
+            depth += 1;
+        } else if b[i] == close {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+        i += 1;
+    }
+    i
+}
+
+/// Splits an entity's `Debug` representation into text, real numbers, and
+/// references to other entities.  Type markers are dropped.
+fn tokenize(s: &str) -> Vec<Token> {
+    let b = s.as_bytes();
+    let mut out = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+    while i < b.len() {
+        let c = b[i];
+        if c == b'"' {
+            let start = i;
+            i += 1;
+            while i < b.len() && b[i] != b'"' {
+                i += if b[i] == b'\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(b.len());
+            text += &s[start..i];
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let start = i;
+            while i < b.len() && (b[i].is_ascii_alphanumeric() || b[i] == b'_') {
+                i += 1;
+            }
+            let word = &s[start..i];
+            if word == "PhantomData" && b.get(i) == Some(&b'<') {
+                i = skip_brackets(b, i, b'<', b'>');
+            } else if word == "Id"
+                && b.get(i) == Some(&b'(')
+                && b.get(i + 1).is_some_and(u8::is_ascii_digit)
+            {
+                let digits = s[i + 1..].bytes().take_while(u8::is_ascii_digit).count();
+                let id = s[i + 1..i + 1 + digits].parse().unwrap_or(usize::MAX);
+                i = skip_brackets(b, i, b'(', b')');
+                if !text.is_empty() {
+                    out.push(Token::Text(std::mem::take(&mut text)));
+                }
+                out.push(Token::Ref(id));
+            } else {
+                text += word;
+            }
+        } else if c.is_ascii_digit() || (c == b'-' && b.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+            let start = i;
+            i += 1;
+            while i < b.len() && (b[i].is_ascii_digit() || b[i] == b'.') {
+                i += 1;
+            }
+            if i < b.len() && b[i] == b'e' {
+                i += 1;
+                if i < b.len() && b[i] == b'-' {
+                    i += 1;
+                }
+                while i < b.len() && b[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let n = &s[start..i];
+            match n.parse::<f64>() {
+                Ok(x) if n.contains(['.', 'e']) => {
+                    if !text.is_empty() {
+                        out.push(Token::Text(std::mem::take(&mut text)));
+                    }
+                    out.push(Token::Real(x));
+                }
+                // Integers are compared exactly
+                _ => text += n,
+            }
+        } else {
+            if !c.is_ascii_whitespace() {
+                text.push(c as char);
+            }
+            i += 1;
+        }
+    }
+    if !text.is_empty() {
+        out.push(Token::Text(text));
+    }
+    out
+}
+
+fn hash_token<H: Hasher>(t: &Token, tol: f64, h: &mut H) {
+    match t {
+        Token::Text(s) => s.hash(h),
+        // Reals are hashed in tolerance-sized buckets, so nearly-equal
+        // values usually (but not always) hash the same
+        Token::Real(x) if tol > 0.0 => ((x / tol).round() as i64).hash(h),
+        Token::Real(x) => x.to_bits().hash(h),
+        Token::Ref(_) => 0u8.hash(h),
+    }
+}
+
+/// A STEP file reduced to what's needed for comparison
+struct Graph {
+    /// Entity type for each id, or an empty string for empty slots
+    kind: Vec<String>,
+    tokens: Vec<Vec<Token>>,
+    /// Entities referred to by each entity, in attribute order
+    refs: Vec<Vec<usize>>,
+    /// Number of live entities which refer to each entity
+    parents: Vec<usize>,
+    /// Hash of each entity's type and attributes, ignoring references
+    shallow: Vec<u64>,
+    /// Hash of each entity's whole subtree
+    deep: Vec<u64>,
+}
+
+impl Graph {
+    fn new(s: &StepFile, tol: f64) -> Self {
+        let n = s.0.len();
+        let mut kind = Vec::with_capacity(n);
+        let mut tokens = Vec::with_capacity(n);
+        for e in &s.0 {
+            if matches!(e, Entity::_EmptySlot) {
+                kind.push(String::new());
+                tokens.push(vec![]);
+            } else {
+                let d = format!("{:?}", e);
+                kind.push(d.split('(').next().unwrap_or("").to_owned());
+                tokens.push(tokenize(&d));
+            }
+        }
+        let refs: Vec<Vec<usize>> = tokens
+            .iter()
+            .map(|t| {
+                t.iter()
+                    .filter_map(|t| match t {
+                        Token::Ref(r) => Some(*r),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut parents = vec![0; n];
+        for r in refs.iter().flatten() {
+            if let Some(p) = parents.get_mut(*r) {
+                *p += 1;
+            }
+        }
+        let shallow = (0..n)
+            .map(|i| {
+                let mut h = std::collections::hash_map::DefaultHasher::new();
+                kind[i].hash(&mut h);
+                for t in &tokens[i] {
+                    hash_token(t, tol, &mut h);
+                }
+                h.finish()
+            })
+            .collect();
+
+        let mut g = Self {
+            kind,
+            tokens,
+            refs,
+            parents,
+            shallow,
+            deep: vec![],
+        };
+        g.deep = g.deep_hashes();
+        g
+    }
+
+    fn live(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.kind.len()).filter(|&i| !self.kind[i].is_empty())
+    }
+
+    /// Hashes each entity's subtree, with references replaced by the hash
+    /// of whatever they point to, so that ids don't matter
+    fn deep_hashes(&self) -> Vec<u64> {
+        const MISSING: u64 = 0;
+        const CYCLE: u64 = 1;
+        let n = self.kind.len();
+        let mut out: Vec<Option<u64>> = vec![None; n];
+        let mut visiting = vec![false; n];
+        for root in 0..n {
+            // Iterative post-order traversal, since chains can be long
+            let mut stack = vec![root];
+            while let Some(&i) = stack.last() {
+                if out[i].is_some() {
+                    stack.pop();
+                    continue;
+                }
+                visiting[i] = true;
+                let todo: Vec<usize> = self.refs[i]
+                    .iter()
+                    .copied()
+                    .filter(|&r| r < n && out[r].is_none() && !visiting[r])
+                    .collect();
+                if !todo.is_empty() {
+                    stack.extend(todo);
+                    continue;
+                }
+                let mut h = std::collections::hash_map::DefaultHasher::new();
+                self.shallow[i].hash(&mut h);
+                for r in &self.refs[i] {
+                    let child = match out.get(*r) {
+                        None => MISSING,
+                        Some(Some(c)) => *c,
+                        Some(None) => CYCLE,
+                    };
+                    child.hash(&mut h);
+                }
+                out[i] = Some(h.finish());
+                visiting[i] = false;
+                stack.pop();
+            }
+        }
+        out.into_iter().map(|h| h.unwrap_or(MISSING)).collect()
+    }
+}
+
+/// A partial pairing of entities between two files
+struct Matching<'a> {
+    a: &'a Graph,
+    b: &'a Graph,
+    a_to_b: Vec<Option<usize>>,
+    b_to_a: Vec<Option<usize>>,
+    queue: VecDeque<(usize, usize)>,
+}
+
+impl<'a> Matching<'a> {
+    fn new(a: &'a Graph, b: &'a Graph) -> Self {
+        Self {
+            a,
+            b,
+            a_to_b: vec![None; a.kind.len()],
+            b_to_a: vec![None; b.kind.len()],
+            queue: VecDeque::new(),
+        }
+    }
+
+    fn pair(&mut self, i: usize, j: usize) {
+        self.a_to_b[i] = Some(j);
+        self.b_to_a[j] = Some(i);
+        self.queue.push_back((i, j));
+    }
+
+    /// Pairs up unpaired entities with matching keys, in id order
+    fn pair_by<K, F>(&mut self, a: &[usize], b: &[usize], key: F)
+    where
+        K: Eq + Hash,
+        F: Fn(&Graph, usize) -> K,
+    {
+        let mut candidates: HashMap<K, VecDeque<usize>> = HashMap::new();
+        for &j in b.iter().filter(|&&j| self.b_to_a[j].is_none()) {
+            candidates.entry(key(self.b, j)).or_default().push_back(j);
+        }
+        let unpaired: Vec<usize> = a.iter().copied().filter(|&i| self.a_to_b[i].is_none()).collect();
+        for i in unpaired {
+            if let Some(j) = candidates.get_mut(&key(self.a, i)).and_then(|c| c.pop_front()) {
+                self.pair(i, j);
+            }
+        }
+    }
+
+    /// Pairs the children of every queued pair by position, if they're of
+    /// the same type and haven't been paired yet
+    fn propagate(&mut self) {
+        while let Some((i, j)) = self.queue.pop_front() {
+            let (ra, rb) = (&self.a.refs[i], &self.b.refs[j]);
+            if ra.len() != rb.len() {
+                continue;
+            }
+            for (&ci, &cj) in ra.iter().zip(rb.iter()) {
+                if ci < self.a_to_b.len()
+                    && cj < self.b_to_a.len()
+                    && self.a_to_b[ci].is_none()
+                    && self.b_to_a[cj].is_none()
+                    && !self.a.kind[ci].is_empty()
+                    && self.a.kind[ci] == self.b.kind[cj]
+                {
+                    self.pair(ci, cj);
+                }
+            }
+        }
+    }
+
+    /// Checks whether a paired entity is unchanged, comparing reals with a
+    /// tolerance and references by pairing
+    fn same(&self, i: usize, j: usize, tol: f64) -> bool {
+        if self.a.deep[i] == self.b.deep[j] && self.a.tokens[i] == self.b.tokens[j] {
+            return true;
+        }
+        let (ta, tb) = (&self.a.tokens[i], &self.b.tokens[j]);
+        self.a.kind[i] == self.b.kind[j]
+            && ta.len() == tb.len()
+            && ta.iter().zip(tb).all(|(x, y)| match (x, y) {
+                (Token::Text(x), Token::Text(y)) => x == y,
+                (Token::Real(x), Token::Real(y)) => (x - y).abs() <= tol,
+                (Token::Ref(x), Token::Ref(y)) => match self.a_to_b.get(*x) {
+                    Some(p) => *p == Some(*y),
+                    // Dangling references match each other
+                    None => *y >= self.b_to_a.len(),
+                },
+                _ => false,
+            })
+    }
+}
+
+/// Finds the name of the product which owns each entity.
+///
+/// A product owns everything reachable from its shape definition
+/// representations, including shape representations linked to them by
+/// (untransformed) shape representation relationships.  Entities reachable
+/// from more than one product, like shared contexts, have no owner.
+fn products(s: &StepFile, g: &Graph) -> Vec<Option<String>> {
+    #[derive(Clone, PartialEq)]
+    enum Owner {
+        None,
+        One(usize),
+        Shared,
+    }
+    let n = s.0.len();
+    let mut owner = vec![Owner::None; n];
+    let mut names = vec![];
+
+    let links: Vec<(usize, usize)> = s
+        .0
+        .iter()
+        .filter_map(|e| match e {
+            Entity::ShapeRepresentationRelationship(r) => Some((r.rep_1.0, r.rep_2.0)),
+            _ => None,
+        })
+        .collect();
+
+    for (sdr, _) in s
+        .0
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| matches!(e, Entity::ShapeDefinitionRepresentation(_)))
+    {
+        let mut seen = vec![false; n];
+        let mut todo = vec![sdr];
+        loop {
+            while let Some(i) = todo.pop() {
+                if i < n && !seen[i] {
+                    seen[i] = true;
+                    todo.extend(g.refs[i].iter().copied());
+                }
+            }
+            for &(r1, r2) in &links {
+                if r1 < n && r2 < n && seen[r1] != seen[r2] {
+                    todo.push(if seen[r1] { r2 } else { r1 });
+                }
+            }
+            if todo.is_empty() {
+                break;
+            }
+        }
+        let name = (0..n).filter(|&i| seen[i]).find_map(|i| match &s.0[i] {
+            Entity::Product(p) => Some(p.name.0.to_owned()),
+            _ => None,
+        });
+        let Some(name) = name else { continue };
+        let k = names.len();
+        names.push(name);
+        for i in (0..n).filter(|&i| seen[i]) {
+            owner[i] = match owner[i] {
+                Owner::None => Owner::One(k),
+                Owner::One(j) if j == k => Owner::One(k),
+                _ => Owner::Shared,
+            };
+        }
+    }
+    owner
+        .into_iter()
+        .map(|o| match o {
+            Owner::One(k) => Some(names[k].clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn diff_text(a: &str, b: &str) -> DiffReport {
+        let fa = StepFile::strip_flatten(a.as_bytes());
+        let fb = StepFile::strip_flatten(b.as_bytes());
+        let sa = StepFile::parse(&fa).unwrap();
+        let sb = StepFile::parse(&fb).unwrap();
+        diff(&sa, &sb, &DiffOptions::default())
+    }
+
+    /// Returns the id of the `n`th entity whose line contains `pat`
+    fn find(text: &str, pat: &str, n: usize) -> usize {
+        let line = text.lines().filter(|l| l.contains(pat)).nth(n).unwrap();
+        line[1..line.find('=').unwrap()].parse().unwrap()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize(r#"Foo(Foo_ { a: Label("x\"(", PhantomData<&()>), b: [1.5, -2e-7], c: Id(12, PhantomData<*const X_<'_>>), d: 3 })"#),
+            vec![
+                Token::Text(r#"Foo(Foo_{a:Label("x\"(",),b:["#.to_owned()),
+                Token::Real(1.5),
+                Token::Text(",".to_owned()),
+                Token::Real(-2e-7),
+                Token::Text("],c:".to_owned()),
+                Token::Ref(12),
+                Token::Text(",d:3})".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_identical() {
+        let text = fixture::assembly(vec![
+            fixture::cube(1.0).color([1.0, 0.0, 0.0]),
+            fixture::cylinder(1.0, 2.0).translate([3.0, 0.0, 0.0]),
+        ])
+        .to_step();
+        let r = diff_text(&text, &text);
+        assert!(r.is_empty(), "{:?}", r.changes);
+        assert!(r.unchanged > 100);
+
+        // A different header doesn't count
+        let other = text.replace("'foxtrot test fixture'", "'something else'");
+        assert!(diff_text(&text, &other).is_empty());
+    }
+
+    #[test]
+    fn test_moved_point_and_renamed_product() {
+        let a = fixture::cube(1.0).to_step();
+
+        // Move the first vertex, and rename the product
+        let line = a.lines().find(|l| l.contains("=VERTEX_POINT(")).unwrap();
+        let point: usize = line[line.find(",#").unwrap() + 2..line.len() - 2]
+            .parse()
+            .unwrap();
+        let b = a
+            .replace(
+                &format!("#{}=CARTESIAN_POINT('',(0.0,0.0,0.0));", point),
+                &format!("#{}=CARTESIAN_POINT('',(0.0,0.0,-0.5));", point),
+            )
+            .replace("PRODUCT('cube','cube'", "PRODUCT('cube','block'");
+        assert_ne!(a, b);
+
+        let r = diff_text(&a, &b);
+        let product = find(&a, "=PRODUCT(", 0);
+        assert_eq!(
+            r.changes,
+            vec![
+                Change {
+                    kind: ChangeKind::Modified,
+                    entity_type: "Product".to_owned(),
+                    a: Some(product),
+                    b: Some(product),
+                    product: Some("block".to_owned()),
+                },
+                Change {
+                    kind: ChangeKind::Modified,
+                    entity_type: "CartesianPoint".to_owned(),
+                    a: Some(point),
+                    b: Some(point),
+                    product: Some("block".to_owned()),
+                },
+            ]
+        );
+        assert_eq!(
+            r.counts(),
+            ChangeCounts {
+                added: 0,
+                removed: 0,
+                modified: 2
+            }
+        );
+        assert_eq!(r.by_type()["CartesianPoint"].modified, 1);
+        assert_eq!(r.by_product()[&Some("block")].modified, 2);
+
+        // Tiny changes are within the tolerance
+        let c = a.replace(
+            &format!("#{}=CARTESIAN_POINT('',(0.0,0.0,0.0));", point),
+            &format!("#{}=CARTESIAN_POINT('',(0.0,0.0,1.E-12));", point),
+        );
+        assert_ne!(a, c);
+        assert!(diff_text(&a, &c).is_empty());
+    }
+
+    #[test]
+    fn test_added_color() {
+        let a = fixture::cube(1.0).to_step();
+        let b = fixture::cube(1.0).color([0.0, 1.0, 0.0]).to_step();
+        let r = diff_text(&a, &b);
+        let counts = r.counts();
+        assert_eq!(counts.removed + counts.modified, 0, "{:?}", r.changes);
+        assert_eq!(counts.added, 9);
+        assert_eq!(r.by_type()["ColourRgb"].added, 1);
+        assert!(r.changes.iter().all(|c| c.product.is_none()));
+    }
+}
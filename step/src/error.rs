@@ -0,0 +1,30 @@
+/// Errors which can occur while splitting a STEP file into entities or
+/// tokens.
+///
+/// Byte offsets are into the text being parsed; when parsing a whole file,
+/// this is the flattened data (see
+/// [`StepFile::strip_flatten`](crate::step_file::StepFile::strip_flatten)),
+/// which has comments and whitespace removed.
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum Error {
+    #[error("Unterminated string starting at byte {0}")]
+    UnterminatedString(usize),
+
+    #[error("Unexpected character at byte {0}")]
+    UnexpectedCharacter(usize),
+
+    #[error("Malformed entity record at byte {0}")]
+    MalformedRecord(usize),
+
+    #[error("Unknown entity type at byte {0}")]
+    UnknownEntity(usize),
+
+    #[error("Could not parse attribute {index} at byte {offset}")]
+    InvalidAttribute { index: usize, offset: usize },
+
+    #[error("Missing ';' after the block starting at byte {0}")]
+    MissingSemicolon(usize),
+
+    #[error("Entity id #{0} is larger than the file")]
+    IdTooLarge(usize),
+}
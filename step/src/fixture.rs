@@ -0,0 +1,633 @@
+//! Builders for small, well-understood STEP files, for use in tests.
+//!
+//! Every shape is a prism: a closed profile in the XY plane made of lines
+//! and counter-clockwise arcs, extruded along +Z.  The top of the prism may
+//! be [slanted](Part::slant), which turns its arcs into ellipses.  Parts
+//! are written as solids by default, but can also be written as [open
+//! surface models](Part::open) or [wireframes](Part::wireframe).  The builders write the
+//! whole entity graph (B-rep topology, geometry, product structure,
+//! contexts and units) as Part 21 text, which can be fed straight into
+//! [`StepFile::strip_flatten`](crate::step_file::StepFile::strip_flatten).
+//!
+//! ```
+//! use step::fixture;
+//! let text = fixture::assembly(vec![
+//!     fixture::cube(10.0).color([1.0, 0.0, 0.0]),
+//!     fixture::cylinder(2.0, 5.0).translate([20.0, 0.0, 0.0]),
+//! ]).to_step();
+//! assert!(text.starts_with("ISO-10303-21;"));
+//! ```
+
+/// One piece of a prism's profile, running from `start` to the start of
+/// the following segment
+#[derive(Clone, Debug)]
+enum Segment {
+    Line { start: [f64; 2] },
+    /// Counter-clockwise arc around `center`
+    Arc { start: [f64; 2], center: [f64; 2] },
+}
+
+impl Segment {
+    fn start(&self) -> [f64; 2] {
+        match self {
+            Segment::Line { start } | Segment::Arc { start, .. } => *start,
+        }
+    }
+}
+
+/// How a part's geometry is represented
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Kind {
+    /// A `MANIFOLD_SOLID_BREP`
+    Solid,
+    /// A `SHELL_BASED_SURFACE_MODEL`, without the top face
+    Surface,
+    /// A `GEOMETRIC_CURVE_SET` of trimmed curves along the edges
+    Wireframe,
+}
+
+/// A single solid part, built by one of the functions in this module
+#[derive(Clone, Debug)]
+pub struct Part {
+    name: String,
+    profile: Vec<Segment>,
+    height: f64,
+    slope: f64,
+    kind: Kind,
+    offset: [f64; 3],
+    color: Option<[f64; 3]>,
+}
+
+/// An assembly of parts, built by [`assembly`]
+#[derive(Clone, Debug)]
+pub struct Assembly {
+    children: Vec<Part>,
+}
+
+/// Builds a cube with one corner at the origin
+pub fn cube(size: f64) -> Part {
+    Part::new("cube", vec![
+        Segment::Line { start: [0.0, 0.0] },
+        Segment::Line { start: [size, 0.0] },
+        Segment::Line { start: [size, size] },
+        Segment::Line { start: [0.0, size] },
+    ], size)
+}
+
+/// Builds a cylinder standing on the XY plane, centered on the Z axis.
+///
+/// The side is split into two half-cylinders, so that there's no seam edge.
+pub fn cylinder(radius: f64, height: f64) -> Part {
+    let center = [0.0, 0.0];
+    Part::new("cylinder", vec![
+        Segment::Arc { start: [radius, 0.0], center },
+        Segment::Arc { start: [-radius, 0.0], center },
+    ], height)
+}
+
+/// Builds a cube with one corner at the origin and its four vertical edges
+/// rounded off with the given radius, which must be less than half of the
+/// size.
+pub fn filleted_block(size: f64, radius: f64) -> Part {
+    let (r, s) = (radius, size);
+    Part::new("filleted_block", vec![
+        Segment::Line { start: [r, 0.0] },
+        Segment::Arc { start: [s - r, 0.0], center: [s - r, r] },
+        Segment::Line { start: [s, r] },
+        Segment::Arc { start: [s, s - r], center: [s - r, s - r] },
+        Segment::Line { start: [s - r, s] },
+        Segment::Arc { start: [r, s], center: [r, s - r] },
+        Segment::Line { start: [0.0, s - r] },
+        Segment::Arc { start: [0.0, r], center: [r, r] },
+    ], size)
+}
+
+/// Builds an assembly, in which each child is placed by a transform rather
+/// than by moving its geometry
+pub fn assembly(children: Vec<Part>) -> Assembly {
+    Assembly { children }
+}
+
+/// Builds a 10mm [`cube`] with semantic and presentation PMI, in the style
+/// of AP242: a 10mm distance between the faces at x = 0 (`#136`) and
+/// x = 10 (`#112`), a flatness tolerance on the top face (`#160`), datum
+/// feature A on the bottom face (`#148`), and a text annotation presenting
+/// the distance (`#1016`).  Its strings have no spaces, since
+/// [`StepFile::strip_flatten`](crate::step_file::StepFile::strip_flatten)
+/// removes them.
+pub fn cube_with_pmi() -> String {
+    const PMI: &str = "\
+#1000=SHAPE_ASPECT('left','',#168,.T.);
+#1001=GEOMETRIC_ITEM_SPECIFIC_USAGE('','',#1000,#170,#136);
+#1002=SHAPE_ASPECT('right','',#168,.T.);
+#1003=GEOMETRIC_ITEM_SPECIFIC_USAGE('','',#1002,#170,#112);
+#1004=DIMENSIONAL_LOCATION('linear_distance','width',#1000,#1002);
+#1005=MEASURE_REPRESENTATION_ITEM('nominal_value',LENGTH_MEASURE(10.),#1);
+#1006=SHAPE_DIMENSION_REPRESENTATION('',(#1005),#5);
+#1007=DIMENSIONAL_CHARACTERISTIC_REPRESENTATION(#1004,#1006);
+#1008=SHAPE_ASPECT('top','',#168,.T.);
+#1009=GEOMETRIC_ITEM_SPECIFIC_USAGE('','',#1008,#170,#160);
+#1010=LENGTH_MEASURE_WITH_UNIT(LENGTH_MEASURE(0.05),#1);
+#1011=FLATNESS_TOLERANCE('flatness','',#1010,#1008);
+#1012=DATUM_FEATURE('A','',#168,.T.);
+#1013=GEOMETRIC_ITEM_SPECIFIC_USAGE('','',#1012,#170,#148);
+#1014=DRAUGHTING_PRE_DEFINED_TEXT_FONT('ISO3098');
+#1015=TEXT_LITERAL('','10mm',#166,'baseline',.RIGHT.,#1014);
+#1016=ANNOTATION_TEXT_OCCURRENCE('distance_text',(),#1015);
+#1017=DRAUGHTING_MODEL('pmi',(#1016),#5);
+#1018=DRAUGHTING_MODEL_ITEM_ASSOCIATION('','',#1004,#1017,#1016);
+";
+    cube(10.0).to_step().replace("ENDSEC;\nEND-ISO", &format!("{}ENDSEC;\nEND-ISO", PMI))
+}
+
+/// Builds a 10mm [`cube`] which is corrupted by a reference cycle: the
+/// edge along its bottom front (`#33`, shared by faces `#100` and `#148`)
+/// runs along a trimmed curve (`#1000`) whose basis curve is another
+/// trimmed curve (`#1001`), whose basis curve is the first one.
+pub fn cube_with_cycle() -> String {
+    const CYCLE: &str = "\
+#1000=TRIMMED_CURVE('',#1001,(PARAMETER_VALUE(0.)),(PARAMETER_VALUE(10.)),.T.,.PARAMETER.);
+#1001=TRIMMED_CURVE('',#1000,(PARAMETER_VALUE(0.)),(PARAMETER_VALUE(10.)),.T.,.PARAMETER.);
+";
+    cube(10.0).to_step()
+        .replace("#33=EDGE_CURVE('',#14,#16,#32,.T.);",
+                 "#33=EDGE_CURVE('',#14,#16,#1000,.T.);")
+        .replace("ENDSEC;\nEND-ISO", &format!("{}ENDSEC;\nEND-ISO", CYCLE))
+}
+
+impl Part {
+    fn new(name: &str, profile: Vec<Segment>, height: f64) -> Self {
+        Self {
+            name: name.to_owned(),
+            profile,
+            height,
+            slope: 0.0,
+            kind: Kind::Solid,
+            offset: [0.0; 3],
+            color: None,
+        }
+    }
+
+    /// Moves the part by the given offset
+    pub fn translate(mut self, offset: [f64; 3]) -> Self {
+        for (o, d) in self.offset.iter_mut().zip(offset) {
+            *o += d;
+        }
+        self
+    }
+
+    /// Tilts the top face, so that the part gets taller by `slope` for
+    /// every unit along +X.  The top must stay above the bottom everywhere.
+    pub fn slant(mut self, slope: f64) -> Self {
+        self.slope = slope;
+        self
+    }
+
+    /// Writes the part as a surface model (an `OPEN_SHELL` in a
+    /// `SHELL_BASED_SURFACE_MODEL`), leaving off its top face
+    pub fn open(mut self) -> Self {
+        self.kind = Kind::Surface;
+        self
+    }
+
+    /// Writes the part as a wireframe drawing: a `GEOMETRIC_CURVE_SET` of
+    /// its edges, with no faces at all
+    pub fn wireframe(mut self) -> Self {
+        self.kind = Kind::Wireframe;
+        self
+    }
+
+    /// Assigns an RGB color (with channels from 0 to 1) to the part
+    pub fn color(mut self, rgb: [f64; 3]) -> Self {
+        self.color = Some(rgb);
+        self
+    }
+
+    /// Returns the number of faces in the part's B-rep or surface model
+    pub fn num_faces(&self) -> usize {
+        match self.kind {
+            Kind::Solid => self.profile.len() + 2,
+            Kind::Surface => self.profile.len() + 1,
+            Kind::Wireframe => 0,
+        }
+    }
+
+    /// Writes the part as a standalone STEP file, with its offset applied
+    /// directly to the geometry
+    pub fn to_step(&self) -> String {
+        let mut w = Writer::default();
+        let ctx = w.context();
+        let pd = w.product(&self.name);
+        let brep = w.solid(self, self.offset);
+        let origin = w.axis([0.0; 3], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+        w.shape(pd, ctx, origin, brep, self.kind);
+        w.styles(ctx, &[(brep, self.color)]);
+        w.finish(&self.name)
+    }
+}
+
+impl Assembly {
+    /// Returns the total number of faces in the assembly's B-reps
+    pub fn num_faces(&self) -> usize {
+        self.children.iter().map(Part::num_faces).sum()
+    }
+
+    /// Writes the assembly as a STEP file
+    pub fn to_step(&self) -> String {
+        let mut w = Writer::default();
+        let ctx = w.context();
+        let asm_pd = w.product("assembly");
+        let origin = w.axis([0.0; 3], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+        let placements: Vec<usize> = self.children.iter()
+            .map(|c| w.axis(c.offset, [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]))
+            .collect();
+        let asm_rep = w.push(format!("SHAPE_REPRESENTATION('',({}),#{})",
+            refs(std::iter::once(origin).chain(placements.iter().copied())),
+            ctx));
+        let asm_pds = w.push(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", asm_pd));
+        w.push(format!("SHAPE_DEFINITION_REPRESENTATION(#{},#{})", asm_pds, asm_rep));
+
+        let mut styles = vec![];
+        for (i, (child, placement)) in self.children.iter().zip(placements).enumerate() {
+            let pd = w.product(&format!("{}{}", child.name, i));
+            let brep = w.solid(child, [0.0; 3]);
+            let rep = w.shape(pd, ctx, origin, brep, child.kind);
+            styles.push((brep, child.color));
+
+            let nauo = w.push(format!(
+                "NEXT_ASSEMBLY_USAGE_OCCURRENCE('{}','','',#{},#{},$)", i, asm_pd, pd));
+            let pds = w.push(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", nauo));
+            let t = w.push(format!(
+                "ITEM_DEFINED_TRANSFORMATION('','',#{},#{})", origin, placement));
+            let rr = w.push(format!(
+                "(REPRESENTATION_RELATIONSHIP('','',#{},#{}) \
+                 REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION(#{}) \
+                 SHAPE_REPRESENTATION_RELATIONSHIP())", rep, asm_rep, t));
+            w.push(format!("CONTEXT_DEPENDENT_SHAPE_REPRESENTATION(#{},#{})", rr, pds));
+        }
+        w.styles(ctx, &styles);
+        w.finish("assembly")
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Formats a real number, which must have a decimal point in Part 21
+pub(crate) fn real(x: f64) -> String {
+    let s = format!("{:?}", x);
+    match s.find('e') {
+        Some(i) if !s[..i].contains('.') => format!("{}.E{}", &s[..i], &s[i + 1..]),
+        Some(i) => format!("{}E{}", &s[..i], &s[i + 1..]),
+        None => s,
+    }
+}
+
+/// Formats a list of entity references, without the surrounding brackets
+pub(crate) fn refs<I: IntoIterator<Item=usize>>(ids: I) -> String {
+    ids.into_iter().map(|i| format!("#{}", i)).collect::<Vec<_>>().join(",")
+}
+
+/// Formats an `ORIENTED_EDGE`'s arguments
+fn oriented(edge: usize, forward: bool) -> String {
+    format!("ORIENTED_EDGE('',*,*,#{},{})", edge, if forward { ".T." } else { ".F." })
+}
+
+/// Returns the normal and X axis of a plane which rises by `slope` along +X
+fn tilted(slope: f64) -> ([f64; 3], [f64; 3]) {
+    let k = (1.0 + slope * slope).sqrt();
+    ([-slope / k, 0.0, 1.0 / k], [1.0 / k, 0.0, slope / k])
+}
+
+/// Accumulates entities, numbering them from 1 as they're pushed
+#[derive(Default)]
+struct Writer {
+    entities: Vec<String>,
+}
+
+impl Writer {
+    fn push(&mut self, e: String) -> usize {
+        self.entities.push(e);
+        self.entities.len()
+    }
+
+    fn point(&mut self, p: [f64; 3]) -> usize {
+        self.push(format!("CARTESIAN_POINT('',({},{},{}))",
+            real(p[0]), real(p[1]), real(p[2])))
+    }
+
+    fn direction(&mut self, d: [f64; 3]) -> usize {
+        self.push(format!("DIRECTION('',({},{},{}))",
+            real(d[0]), real(d[1]), real(d[2])))
+    }
+
+    fn axis(&mut self, origin: [f64; 3], z: [f64; 3], x: [f64; 3]) -> usize {
+        let p = self.point(origin);
+        let z = self.direction(z);
+        let x = self.direction(x);
+        self.push(format!("AXIS2_PLACEMENT_3D('',#{},#{},#{})", p, z, x))
+    }
+
+    /// Writes a representation context in millimeters and radians
+    fn context(&mut self) -> usize {
+        let mm = self.push("(LENGTH_UNIT() NAMED_UNIT(*) SI_UNIT(.MILLI.,.METRE.))".to_owned());
+        let rad = self.push("(NAMED_UNIT(*) PLANE_ANGLE_UNIT() SI_UNIT($,.RADIAN.))".to_owned());
+        let sr = self.push("(NAMED_UNIT(*) SI_UNIT($,.STERADIAN.) SOLID_ANGLE_UNIT())".to_owned());
+        let tol = self.push(format!(
+            "UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(1.E-07),#{},\
+             'distance_accuracy_value','confusion accuracy')", mm));
+        self.push(format!(
+            "(GEOMETRIC_REPRESENTATION_CONTEXT(3) \
+             GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT((#{})) \
+             GLOBAL_UNIT_ASSIGNED_CONTEXT((#{},#{},#{})) \
+             REPRESENTATION_CONTEXT('',''))", tol, mm, rad, sr))
+    }
+
+    /// Writes a product and its definition, returning the definition
+    fn product(&mut self, name: &str) -> usize {
+        let app = self.push("APPLICATION_CONTEXT('automotive_design')".to_owned());
+        self.push(format!(
+            "APPLICATION_PROTOCOL_DEFINITION('international standard',\
+             'automotive_design',2000,#{})", app));
+        let pc = self.push(format!("PRODUCT_CONTEXT('',#{},'mechanical')", app));
+        let p = self.push(format!("PRODUCT('{0}','{0}','',(#{1}))", name, pc));
+        let pdf = self.push(format!("PRODUCT_DEFINITION_FORMATION('','',#{})", p));
+        let pdc = self.push(format!(
+            "PRODUCT_DEFINITION_CONTEXT('part definition',#{},'design')", app));
+        self.push(format!("PRODUCT_DEFINITION('design','',#{},#{})", pdf, pdc))
+    }
+
+    /// Attaches a B-rep (or other representation item) to a product
+    /// definition, returning the (plain) shape representation which
+    /// assemblies should refer to
+    fn shape(&mut self, pd: usize, ctx: usize, origin: usize, brep: usize,
+             kind: Kind) -> usize
+    {
+        let rep = self.push(format!("SHAPE_REPRESENTATION('',(#{}),#{})", origin, ctx));
+        let pds = self.push(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", pd));
+        self.push(format!("SHAPE_DEFINITION_REPRESENTATION(#{},#{})", pds, rep));
+        let abs_type = match kind {
+            Kind::Solid => "ADVANCED_BREP_SHAPE_REPRESENTATION",
+            Kind::Surface => "MANIFOLD_SURFACE_SHAPE_REPRESENTATION",
+            Kind::Wireframe => "GEOMETRICALLY_BOUNDED_WIREFRAME_SHAPE_REPRESENTATION",
+        };
+        let abs = self.push(format!(
+            "{}('',(#{},#{}),#{})", abs_type, brep, origin, ctx));
+        self.push(format!("SHAPE_REPRESENTATION_RELATIONSHIP('','',#{},#{})", rep, abs));
+        rep
+    }
+
+    /// Writes colors for any B-reps which have them
+    fn styles(&mut self, ctx: usize, items: &[(usize, Option<[f64; 3]>)]) {
+        let mut styled = vec![];
+        for (brep, rgb) in items {
+            let Some(rgb) = rgb else { continue };
+            let c = self.push(format!("COLOUR_RGB('',{},{},{})",
+                real(rgb[0]), real(rgb[1]), real(rgb[2])));
+            let fasc = self.push(format!("FILL_AREA_STYLE_COLOUR('',#{})", c));
+            let fas = self.push(format!("FILL_AREA_STYLE('',(#{}))", fasc));
+            let ssfa = self.push(format!("SURFACE_STYLE_FILL_AREA(#{})", fas));
+            let sss = self.push(format!("SURFACE_SIDE_STYLE('',(#{}))", ssfa));
+            let ssu = self.push(format!("SURFACE_STYLE_USAGE(.BOTH.,#{})", sss));
+            let psa = self.push(format!("PRESENTATION_STYLE_ASSIGNMENT((#{}))", ssu));
+            styled.push(self.push(format!("STYLED_ITEM('color',(#{}),#{})", psa, brep)));
+        }
+        if !styled.is_empty() {
+            self.push(format!(
+                "MECHANICAL_DESIGN_GEOMETRIC_PRESENTATION_REPRESENTATION('',({}),#{})",
+                refs(styled), ctx));
+        }
+    }
+
+    /// Writes an edge, as in [`Writer::curve`]
+    fn edge(&mut self, a: (usize, [f64; 3]), b: (usize, [f64; 3]),
+            center: Option<[f64; 3]>, slope: f64) -> usize
+    {
+        let geom = self.curve(a.1, b.1, center, slope);
+        self.push(format!("EDGE_CURVE('',#{},#{},#{},.T.)", a.0, b.0, geom))
+    }
+
+    /// Writes a curve trimmed to run between two points, as in
+    /// [`Writer::curve`]
+    fn trimmed(&mut self, a: [f64; 3], b: [f64; 3],
+               center: Option<[f64; 3]>, slope: f64) -> usize
+    {
+        let geom = self.curve(a, b, center, slope);
+        let (pa, pb) = (self.point(a), self.point(b));
+        self.push(format!("TRIMMED_CURVE('',#{},(#{}),(#{}),.T.,.CARTESIAN.)",
+                          geom, pa, pb))
+    }
+
+    /// Writes the (unbounded) curve through two points, which is an arc if
+    /// `center` is given and a line otherwise.  Arcs on a plane tilted by
+    /// `slope` (see [`Part::slant`]) are written as ellipses.
+    fn curve(&mut self, a: [f64; 3], b: [f64; 3],
+             center: Option<[f64; 3]>, slope: f64) -> usize
+    {
+        match center {
+            Some(c) if slope != 0.0 => {
+                let r = ((a[0] - c[0]).powi(2) + (a[1] - c[1]).powi(2)).sqrt();
+                let (z, x) = tilted(slope);
+                let axis = self.axis(c, z, x);
+                let k = (1.0 + slope * slope).sqrt();
+                self.push(format!("ELLIPSE('',#{},{},{})", axis, real(r * k), real(r)))
+            }
+            Some(c) => {
+                let r = ((a[0] - c[0]).powi(2) + (a[1] - c[1]).powi(2)).sqrt();
+                let axis = self.axis(c, [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+                self.push(format!("CIRCLE('',#{},{})", axis, real(r)))
+            }
+            None => {
+                let d = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+                let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+                let dir = self.direction([d[0] / len, d[1] / len, d[2] / len]);
+                let vec = self.push(format!("VECTOR('',#{},{})", dir, real(len)));
+                let p = self.point(a);
+                self.push(format!("LINE('',#{},#{})", p, vec))
+            }
+        }
+    }
+
+    /// Writes a face bounded by the given oriented edges
+    fn face(&mut self, edges: Vec<String>, surface: usize) -> usize {
+        let edges: Vec<usize> = edges.into_iter().map(|e| self.push(e)).collect();
+        let l = self.push(format!("EDGE_LOOP('',({}))", refs(edges)));
+        let b = self.push(format!("FACE_OUTER_BOUND('',#{},.T.)", l));
+        self.push(format!("ADVANCED_FACE('',(#{}),#{},.T.)", b, surface))
+    }
+
+    /// Writes a part's solid, moved by `offset`, returning the B-rep (or
+    /// the surface model or curve set, depending on the part's kind)
+    fn solid(&mut self, part: &Part, offset: [f64; 3]) -> usize {
+        let n = part.profile.len();
+        let (z0, z1) = (offset[2], offset[2] + part.height);
+        let at = |p: [f64; 2], z: f64| [p[0] + offset[0], p[1] + offset[1], z];
+        let top_z = |p: [f64; 2]| z1 + part.slope * p[0];
+
+        let vertex = |w: &mut Self, p: [f64; 3]| {
+            let pt = w.point(p);
+            (w.push(format!("VERTEX_POINT('',#{})", pt)), p)
+        };
+        let bottom: Vec<_> = part.profile.iter()
+            .map(|s| vertex(self, at(s.start(), z0)))
+            .collect();
+        let top: Vec<_> = part.profile.iter()
+            .map(|s| vertex(self, at(s.start(), top_z(s.start()))))
+            .collect();
+
+        // Wireframes only have curves, so the vertices above are unused
+        let edge = |w: &mut Self, a: (usize, [f64; 3]), b: (usize, [f64; 3]),
+                    center: Option<[f64; 3]>, slope: f64| match part.kind {
+            Kind::Wireframe => w.trimmed(a.1, b.1, center, slope),
+            _ => w.edge(a, b, center, slope),
+        };
+        let mut bottom_edges = vec![];
+        let mut top_edges = vec![];
+        let mut side_edges = vec![];
+        for (i, s) in part.profile.iter().enumerate() {
+            let j = (i + 1) % n;
+            let center = match s {
+                Segment::Line { .. } => None,
+                Segment::Arc { center, .. } => Some(*center),
+            };
+            bottom_edges.push(edge(self, bottom[i], bottom[j],
+                                   center.map(|c| at(c, z0)), 0.0));
+            top_edges.push(edge(self, top[i], top[j],
+                                center.map(|c| at(c, top_z(c))), part.slope));
+            side_edges.push(edge(self, bottom[i], top[i], None, 0.0));
+        }
+        if part.kind == Kind::Wireframe {
+            let curves = bottom_edges.iter().chain(&top_edges).chain(&side_edges);
+            return self.push(format!("GEOMETRIC_CURVE_SET('{}',({}))",
+                                     part.name, refs(curves.copied())));
+        }
+
+        let mut faces = vec![];
+        for (i, s) in part.profile.iter().enumerate() {
+            let j = (i + 1) % n;
+            let surface = match s {
+                Segment::Line { start } => {
+                    let end = part.profile[j].start();
+                    let d = [end[0] - start[0], end[1] - start[1]];
+                    let len = (d[0] * d[0] + d[1] * d[1]).sqrt();
+                    let (dx, dy) = (d[0] / len, d[1] / len);
+                    let axis = self.axis(bottom[i].1, [dy, -dx, 0.0], [dx, dy, 0.0]);
+                    self.push(format!("PLANE('',#{})", axis))
+                }
+                Segment::Arc { start, center } => {
+                    let r = ((start[0] - center[0]).powi(2)
+                           + (start[1] - center[1]).powi(2)).sqrt();
+                    let axis = self.axis(at(*center, z0), [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+                    self.push(format!("CYLINDRICAL_SURFACE('',#{},{})", axis, real(r)))
+                }
+            };
+            faces.push(self.face(vec![
+                oriented(bottom_edges[i], true),
+                oriented(side_edges[j], true),
+                oriented(top_edges[i], false),
+                oriented(side_edges[i], false),
+            ], surface));
+        }
+
+        let axis = self.axis(at([0.0, 0.0], z0), [0.0, 0.0, -1.0], [1.0, 0.0, 0.0]);
+        let plane = self.push(format!("PLANE('',#{})", axis));
+        let edges = bottom_edges.iter().rev().map(|e| oriented(*e, false)).collect();
+        faces.push(self.face(edges, plane));
+
+        let (z, x) = tilted(part.slope);
+        if part.kind == Kind::Surface {
+            let shell = self.push(format!("OPEN_SHELL('',({}))", refs(faces)));
+            return self.push(format!("SHELL_BASED_SURFACE_MODEL('{}',(#{}))",
+                                     part.name, shell));
+        }
+        let axis = self.axis(at([0.0, 0.0], z1), z, x);
+        let plane = self.push(format!("PLANE('',#{})", axis));
+        let edges = top_edges.iter().map(|e| oriented(*e, true)).collect();
+        faces.push(self.face(edges, plane));
+
+        let shell = self.push(format!("CLOSED_SHELL('',({}))", refs(faces)));
+        self.push(format!("MANIFOLD_SOLID_BREP('{}',#{})", part.name, shell))
+    }
+
+    /// Wraps up the entities with a header
+    fn finish(self, name: &str) -> String {
+        let mut out = format!("ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION(('foxtrot test fixture'),'2;1');
+FILE_NAME('{}','',(''),(''),'foxtrot','foxtrot','');
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN {{ 1 0 10303 214 1 1 1 1 }}'));
+ENDSEC;
+DATA;
+", name);
+        for (i, e) in self.entities.iter().enumerate() {
+            out += &format!("#{}={};\n", i + 1, e);
+        }
+        out += "ENDSEC;\nEND-ISO-10303-21;\n";
+        out
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ap214::Entity, step_file::StepFile};
+
+    fn parse_count(text: &str, f: fn(&Entity) -> bool) -> usize {
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        assert!(step.0.iter().all(|e| !matches!(e, Entity::_FailedToParse(_))),
+                "{}", text);
+        step.0.iter().filter(|e| f(e)).count()
+    }
+
+    #[test]
+    fn test_real() {
+        assert_eq!(real(1.0), "1.0");
+        assert_eq!(real(-0.25), "-0.25");
+        assert_eq!(real(1e-7), "1.E-7");
+        assert_eq!(real(1.5e20), "1.5E20");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for part in [cube(1.0), cylinder(1.0, 2.0), filleted_block(4.0, 1.0)] {
+            let faces = parse_count(&part.to_step(),
+                                    |e| matches!(e, Entity::AdvancedFace(_)));
+            assert_eq!(faces, part.num_faces());
+        }
+
+        let asm = assembly(vec![
+            cube(1.0).color([1.0, 0.0, 0.0]),
+            cylinder(1.0, 2.0).translate([3.0, 0.0, 0.0]),
+        ]);
+        let text = asm.to_step();
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::AdvancedFace(_))),
+                   asm.num_faces());
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::ManifoldSolidBrep(_))),
+                   2);
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::StyledItem(_))), 1);
+        assert_eq!(parse_count(&text, |e| matches!(e,
+            Entity::RepresentationRelationshipWithTransformation(_))), 2);
+
+        // Slanting a cylinder turns its top arcs into ellipses
+        let text = cylinder(1.0, 2.0).slant(0.5).to_step();
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::Ellipse(_))), 2);
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::Circle(_))), 2);
+
+        // Surface models lose their top face, and wireframes have only
+        // trimmed curves
+        let part = cube(1.0).open();
+        let text = part.to_step();
+        assert_eq!(part.num_faces(), 5);
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::AdvancedFace(_))), 5);
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::OpenShell(_))), 1);
+        assert_eq!(parse_count(&text, |e| matches!(e,
+            Entity::ShellBasedSurfaceModel(_))), 1);
+
+        let text = cylinder(1.0, 2.0).wireframe().to_step();
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::AdvancedFace(_))), 0);
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::TrimmedCurve(_))), 6);
+        assert_eq!(parse_count(&text, |e| matches!(e, Entity::GeometricCurveSet(_))), 1);
+    }
+}
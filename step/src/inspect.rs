@@ -0,0 +1,285 @@
+//! Human-readable printing of parsed entities, for debugging.
+//!
+//! As in [`crate::check`], attribute values are recovered from each
+//! entity's `Debug` representation.  Newtype wrappers and type markers are
+//! dropped, so a point's coordinates print as `[1.0, 2.0, 3.0]` rather than
+//! a list of `LengthMeasure`s, and references to other entities print as
+//! `#id`, optionally followed by the referenced entity itself.
+use std::fmt::Write;
+
+use crate::{
+    ap214::Entity,
+    check::{debug_node, Node},
+    step_file::StepFile,
+};
+
+impl<'a> Entity<'a> {
+    /// Prints the entity's attributes, one per line, expanding the entities
+    /// which it refers to directly (but not their own references)
+    pub fn pretty(&self, s: &StepFile) -> String {
+        self.pretty_with_depth(s, 1)
+    }
+
+    /// Prints the entity's attributes, one per line, expanding referenced
+    /// entities up to `depth` levels deep.  With a depth of 0, references
+    /// are only printed as ids.  References which lead back to an entity
+    /// that's already being expanded are marked as cycles, rather than
+    /// being expanded again.
+    pub fn pretty_with_depth(&self, s: &StepFile, depth: usize) -> String {
+        let mut out = String::new();
+        // The entity's own id (if it's from this file) starts off the path
+        let path = s.0.iter().position(|e| std::ptr::eq(e, self));
+        let mut p = Printer { s, path: path.into_iter().collect() };
+        p.write_entity(&mut out, self, depth, 0);
+        out
+    }
+}
+
+/// State for printing an entity
+struct Printer<'a, 'b> {
+    s: &'b StepFile<'a>,
+    /// Entities which are currently being expanded
+    path: Vec<usize>,
+}
+
+impl Printer<'_, '_> {
+    fn write_entity(&mut self, out: &mut String, e: &Entity, depth: usize, indent: usize) {
+        let text = format!("{:?}", e);
+        match debug_node(&text) {
+            Some(n) => self.write_entity_node(out, &n, depth, indent),
+            None => out.push_str(&text),
+        }
+    }
+
+    /// Writes an entity which has already been parsed from its `Debug`
+    /// representation, i.e. an enum variant wrapping the entity's struct
+    fn write_entity_node(&mut self, out: &mut String, n: &Node, depth: usize, indent: usize) {
+        match n {
+            Node::Tuple("ComplexEntity", v) => {
+                out.push_str("ComplexEntity");
+                for part in v.iter().flat_map(|p| match p {
+                    Node::List(parts) => parts.as_slice(),
+                    p => std::slice::from_ref(p),
+                }) {
+                    newline(out, indent + 1);
+                    out.push_str("- ");
+                    self.write_entity_node(out, part, depth, indent + 1);
+                }
+            }
+            Node::Tuple(name, v) => match v.as_slice() {
+                [Node::Struct(_, fields)] => {
+                    out.push_str(name);
+                    for (field, value) in fields.iter().filter(|(f, _)| *f != "_marker") {
+                        newline(out, indent + 1);
+                        write!(out, "{}:", field).unwrap();
+                        self.write_value(out, value, depth, indent + 1);
+                    }
+                }
+                // Entities without a struct (e.g. those which failed to
+                // parse) keep their variant name, rather than being unwrapped
+                _ => {
+                    write!(out, "{}(", name).unwrap();
+                    write_list(out, v.iter().filter(|n| !is_marker(n)));
+                    out.push(')');
+                }
+            },
+            n => write_inline(out, n),
+        }
+    }
+
+    /// Writes an attribute value after a separator (e.g. `name:`), which
+    /// may span multiple lines if it contains references that are expanded
+    fn write_value(&mut self, out: &mut String, n: &Node, depth: usize, indent: usize) {
+        if !self.expands(n, depth) {
+            out.push(' ');
+            write_inline(out, n);
+            return;
+        }
+        match unwrap(n) {
+            Node::Tuple("Id", v) => {
+                let id = reference(v).unwrap();
+                if self.path.contains(&id) {
+                    write!(out, " #{} (cycle)", id).unwrap();
+                } else {
+                    write!(out, " #{} = ", id).unwrap();
+                    self.path.push(id);
+                    self.write_entity(out, &self.s.0[id], depth - 1, indent);
+                    self.path.pop();
+                }
+            }
+            Node::List(v) => {
+                for item in v {
+                    newline(out, indent + 1);
+                    out.push('-');
+                    self.write_value(out, item, depth, indent + 1);
+                }
+            }
+            // Tuples with multiple values are rare (and only hold simple
+            // values), so they're written on one line
+            n => {
+                out.push(' ');
+                write_inline(out, n);
+            }
+        }
+    }
+
+    /// Checks whether a value refers to an entity which would be expanded
+    fn expands(&self, n: &Node, depth: usize) -> bool {
+        if depth == 0 {
+            return false;
+        }
+        match unwrap(n) {
+            Node::Tuple("Id", v) => reference(v)
+                .and_then(|i| self.s.0.get(i))
+                .is_some_and(|e| !matches!(e, Entity::_EmptySlot)),
+            Node::List(v) => v.iter().any(|n| self.expands(n, depth)),
+            _ => false,
+        }
+    }
+}
+
+/// Writes a value on a single line, with references as bare ids
+fn write_inline(out: &mut String, n: &Node) {
+    match unwrap(n) {
+        Node::Name(t) | Node::Number(t) => out.push_str(t),
+        Node::String(t) => write!(out, "{:?}", t).unwrap(),
+        Node::Tuple("Id", v) if reference(v).is_some() => {
+            write!(out, "#{}", reference(v).unwrap()).unwrap()
+        }
+        Node::List(v) => {
+            out.push('[');
+            write_list(out, v.iter());
+            out.push(']');
+        }
+        Node::Tuple(name, v) => {
+            write!(out, "{}(", name).unwrap();
+            write_list(out, v.iter().filter(|n| !is_marker(n)));
+            out.push(')');
+        }
+        Node::Struct(name, fields) => {
+            write!(out, "{} {{ ", name).unwrap();
+            for (i, (f, v)) in fields.iter().filter(|(f, _)| *f != "_marker").enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write!(out, "{}: ", f).unwrap();
+                write_inline(out, v);
+            }
+            out.push_str(" }");
+        }
+    }
+}
+
+fn write_list<'a, 'b: 'a, I: Iterator<Item = &'a Node<'b>>>(out: &mut String, items: I) {
+    for (i, n) in items.enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_inline(out, n);
+    }
+}
+
+fn newline(out: &mut String, indent: usize) {
+    out.push('\n');
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn is_marker(n: &Node) -> bool {
+    *n == Node::Name("PhantomData")
+}
+
+/// Returns the id held by the values of an `Id` tuple
+fn reference(v: &[Node]) -> Option<usize> {
+    match v.first() {
+        Some(Node::Number(t)) => t.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Strips newtype wrappers (and select variants) which hold a single value,
+/// e.g. `LengthMeasure(1.0)` or `Label("name")`
+fn unwrap<'a, 'b>(mut n: &'a Node<'b>) -> &'a Node<'b> {
+    while let Node::Tuple(name, v) = n {
+        let mut values = v.iter().filter(|n| !is_marker(n));
+        match (values.next(), values.next()) {
+            (Some(inner), None) if *name != "Id" => n = inner,
+            _ => break,
+        }
+    }
+    n
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &[u8]) -> Vec<u8> {
+        StepFile::strip_flatten(data)
+    }
+
+    #[test]
+    fn test_pretty_vertex() {
+        let flat = parse(b"DATA;
+            #1=CARTESIAN_POINT('p',(1.,2.,3.));
+            #2=VERTEX_POINT('v',#1);
+            ENDSEC;");
+        let step = StepFile::parse(&flat).unwrap();
+        assert_eq!(step.0[2].pretty(&step), "\
+VertexPoint
+  representation_item__name: \"v\"
+  vertex_geometry: #1 = CartesianPoint
+    name: \"p\"
+    coordinates: [1.0, 2.0, 3.0]");
+        assert_eq!(step.0[2].pretty_with_depth(&step, 0), "\
+VertexPoint
+  representation_item__name: \"v\"
+  vertex_geometry: #1");
+    }
+
+    #[test]
+    fn test_pretty_lists() {
+        let flat = parse(b"DATA;
+            #1=CARTESIAN_POINT('',(0.,0.,0.));
+            #2=CARTESIAN_POINT('',(1.,0.,0.));
+            #3=POLYLINE('',(#1,#2,#9));
+            #4=CARTESIAN_POINT('');
+            ENDSEC;");
+        let step = StepFile::parse(&flat).unwrap();
+        assert_eq!(step.0[3].pretty(&step), "\
+Polyline
+  name: \"\"
+  points:
+    - #1 = CartesianPoint
+      name: \"\"
+      coordinates: [0.0, 0.0, 0.0]
+    - #2 = CartesianPoint
+      name: \"\"
+      coordinates: [1.0, 0.0, 0.0]
+    - #9");
+        assert_eq!(step.0[3].pretty_with_depth(&step, 0), "\
+Polyline
+  name: \"\"
+  points: [#1, #2, #9]");
+
+        // Entities which failed to parse are printed as-is
+        assert_eq!(step.0[4].pretty(&step),
+                   "_FailedToParse(\"CARTESIAN_POINT('')\")");
+    }
+
+    #[test]
+    fn test_pretty_cycle() {
+        let flat = parse(b"DATA;
+            #1=TRIMMED_CURVE('a',#2,(PARAMETER_VALUE(0.)),(PARAMETER_VALUE(1.)),.T.,.PARAMETER.);
+            #2=TRIMMED_CURVE('b',#1,(PARAMETER_VALUE(0.)),(PARAMETER_VALUE(1.)),.T.,.PARAMETER.);
+            ENDSEC;");
+        let step = StepFile::parse(&flat).unwrap();
+        let text = step.0[1].pretty_with_depth(&step, 100);
+        assert!(text.contains("basis_curve: #2 = TrimmedCurve"), "{}", text);
+        assert!(text.contains("basis_curve: #1 (cycle)"), "{}", text);
+        assert_eq!(text.matches("TrimmedCurve").count(), 2);
+    }
+}
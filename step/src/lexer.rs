@@ -0,0 +1,388 @@
+//! Tokenizer for the data section of a STEP file.
+//!
+//! [`Lexer`] splits text into [`Token`]s, skipping whitespace and comments.
+//! Tokens borrow their text from the input and carry their byte offsets, so
+//! lexing doesn't allocate; values (e.g. a string with escaped quotes) are
+//! only decoded when asked for.  Newlines are recorded as they're skipped,
+//! so that offsets can be turned into line and column numbers afterwards.
+use std::borrow::Cow;
+use std::ops::Range;
+
+use memchr::{memchr, memchr_iter};
+
+use crate::error::Error;
+
+/// What sort of thing a [`Token`] is
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Kind {
+    /// Entity instance name, e.g. `#12`
+    Instance,
+    /// Entity or type name, e.g. `CARTESIAN_POINT`
+    Keyword,
+    /// Quoted string, e.g. `'it''s'`
+    String,
+    /// Binary literal, e.g. `"0F"`
+    Binary,
+    /// Number with a decimal point or exponent, e.g. `1.E-007`
+    Real,
+    Integer,
+    /// Enumeration value (including logicals), e.g. `.T.`
+    Enum,
+    /// `$`, for an unset optional attribute
+    Null,
+    /// `*`, for a derived attribute
+    Derived,
+    Equals,
+    Comma,
+    Semicolon,
+    Open,
+    Close,
+}
+
+/// A single token, with its text exactly as written
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Token<'a> {
+    pub kind: Kind,
+    pub text: &'a str,
+    /// Byte offset of the token's first character
+    pub start: usize,
+}
+
+impl<'a> Token<'a> {
+    /// Returns the byte offset just past the token's last character
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end()
+    }
+
+    /// Returns the number of an instance name, or `None` if this isn't an
+    /// instance name (or its number doesn't fit)
+    pub fn instance(&self) -> Option<usize> {
+        match self.kind {
+            Kind::Instance => self.text[1..].parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of a string, with escaped quotes (`''`) and
+    /// backslashes (`\\`) resolved.  This only allocates if there's an
+    /// escape to resolve.
+    pub fn string(&self) -> Option<Cow<'a, str>> {
+        if self.kind != Kind::String {
+            return None;
+        }
+        let inner = &self.text[1..self.text.len() - 1];
+        Some(if inner.contains("''") || inner.contains("\\\\") {
+            Cow::Owned(inner.replace("''", "'").replace("\\\\", "\\"))
+        } else {
+            Cow::Borrowed(inner)
+        })
+    }
+
+    /// Returns the value of a real or integer
+    pub fn real(&self) -> Option<f64> {
+        match self.kind {
+            Kind::Real | Kind::Integer => fast_float::parse(self.text).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn integer(&self) -> Option<i64> {
+        match self.kind {
+            Kind::Integer => self.text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of an enumeration value, without its dots
+    pub fn enum_tag(&self) -> Option<&'a str> {
+        match self.kind {
+            Kind::Enum => Some(&self.text[1..self.text.len() - 1]),
+            _ => None,
+        }
+    }
+}
+
+/// Positions of the newlines in a file, for converting byte offsets into
+/// line and column numbers
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LineIndex(Vec<usize>);
+
+impl LineIndex {
+    /// Builds an index of every newline in some text
+    pub fn new(data: &[u8]) -> Self {
+        Self(memchr_iter(b'\n', data).collect())
+    }
+
+    /// Returns the line and column of a byte offset, both starting from 1.
+    /// Columns are counted in bytes.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.0.partition_point(|n| *n < offset);
+        let start = if line == 0 { 0 } else { self.0[line - 1] + 1 };
+        (line + 1, offset - start + 1)
+    }
+}
+
+/// Returns the offset just past a run of characters matching `f`
+fn run_end<F: Fn(u8) -> bool>(bytes: &[u8], from: usize, f: F) -> usize {
+    from + bytes[from..].iter().take_while(|c| f(**c)).count()
+}
+
+/// Iterator over the tokens in some text.
+///
+/// After an error, the iterator is finished.
+pub struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+    lines: LineIndex,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self { text, pos: 0, lines: LineIndex::default() }
+    }
+
+    /// Returns the byte offset of the next character to be lexed
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the newlines which have been passed so far.  Once the lexer
+    /// is finished, this covers the whole text.
+    pub fn lines(&self) -> &LineIndex {
+        &self.lines
+    }
+
+    pub fn into_lines(self) -> LineIndex {
+        self.lines
+    }
+
+    /// Records the newlines in part of the text
+    fn newlines(&mut self, range: Range<usize>) {
+        let bytes = &self.text.as_bytes()[range.clone()];
+        self.lines.0.extend(memchr_iter(b'\n', bytes).map(|i| i + range.start));
+    }
+
+    /// Skips whitespace and comments.  As in
+    /// [`strip_flatten`](crate::step_file::StepFile::strip_flatten), an
+    /// unterminated comment runs to the end of the text.
+    fn skip(&mut self) {
+        let bytes = self.text.as_bytes();
+        while self.pos < bytes.len() {
+            match bytes[self.pos] {
+                b'\n' => {
+                    self.lines.0.push(self.pos);
+                    self.pos += 1;
+                }
+                c if c.is_ascii_whitespace() => self.pos += 1,
+                b'/' if bytes.get(self.pos + 1) == Some(&b'*') => {
+                    let start = self.pos;
+                    self.pos = memchr_iter(b'/', &bytes[start + 2..])
+                        .map(|j| start + 2 + j)
+                        .find(|&k| k > start + 2 && bytes[k - 1] == b'*')
+                        .map_or(bytes.len(), |k| k + 1);
+                    self.newlines(start..self.pos);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Returns the offset just past a quoted string or binary literal
+    fn quoted(&mut self, start: usize) -> Result<usize, Error> {
+        let bytes = self.text.as_bytes();
+        let quote = bytes[start];
+        let mut i = start + 1;
+        let end = loop {
+            match memchr(quote, &bytes[i..]) {
+                // Doubled quotes are escaped, so keep going
+                Some(j) if quote == b'\'' && bytes.get(i + j + 1) == Some(&b'\'') => i += j + 2,
+                Some(j) => break i + j + 1,
+                None => return Err(Error::UnterminatedString(start)),
+            }
+        };
+        self.newlines(start..end);
+        Ok(end)
+    }
+
+    /// Returns the end and kind of a number
+    fn number(&self, start: usize) -> Result<(usize, Kind), Error> {
+        let bytes = self.text.as_bytes();
+        let digit = |c: u8| c.is_ascii_digit();
+        let sign = matches!(bytes[start], b'+' | b'-') as usize;
+        let mut i = run_end(bytes, start + sign, digit);
+        if i == start + sign {
+            return Err(Error::UnexpectedCharacter(start));
+        }
+        let mut kind = Kind::Integer;
+        if bytes.get(i) == Some(&b'.') {
+            kind = Kind::Real;
+            i = run_end(bytes, i + 1, digit);
+        }
+        if matches!(bytes.get(i), Some(b'E' | b'e')) {
+            let sign = matches!(bytes.get(i + 1), Some(b'+' | b'-')) as usize;
+            let j = run_end(bytes, i + 1 + sign, digit);
+            if j == i + 1 + sign {
+                return Err(Error::UnexpectedCharacter(i));
+            }
+            kind = Kind::Real;
+            i = j;
+        }
+        Ok((i, kind))
+    }
+
+    fn lex(&mut self) -> Result<Option<Token<'a>>, Error> {
+        self.skip();
+        let bytes = self.text.as_bytes();
+        let start = self.pos;
+        let c = match bytes.get(start) {
+            Some(c) => *c,
+            None => return Ok(None),
+        };
+        let word = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
+        let (end, kind) = match c {
+            b'#' => {
+                let end = run_end(bytes, start + 1, |c| c.is_ascii_digit());
+                if end == start + 1 {
+                    return Err(Error::UnexpectedCharacter(start));
+                }
+                (end, Kind::Instance)
+            }
+            b'A'..=b'Z' | b'a'..=b'z' | b'_' | b'!' => {
+                (run_end(bytes, start + 1, word), Kind::Keyword)
+            }
+            b'\'' | b'"' => {
+                let kind = if c == b'"' { Kind::Binary } else { Kind::String };
+                (self.quoted(start)?, kind)
+            }
+            b'0'..=b'9' | b'+' | b'-' => self.number(start)?,
+            b'.' => {
+                let end = run_end(bytes, start + 1, word);
+                if bytes.get(end) != Some(&b'.') {
+                    return Err(Error::UnexpectedCharacter(start));
+                }
+                (end + 1, Kind::Enum)
+            }
+            b'$' => (start + 1, Kind::Null),
+            b'*' => (start + 1, Kind::Derived),
+            b'=' => (start + 1, Kind::Equals),
+            b',' => (start + 1, Kind::Comma),
+            b';' => (start + 1, Kind::Semicolon),
+            b'(' => (start + 1, Kind::Open),
+            b')' => (start + 1, Kind::Close),
+            _ => return Err(Error::UnexpectedCharacter(start)),
+        };
+        self.pos = end;
+        Ok(Some(Token { kind, text: &self.text[start..end], start }))
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.lex() {
+            Ok(t) => t.map(Ok),
+            Err(e) => {
+                // Record the rest of the newlines, then stop
+                self.newlines(self.pos..self.text.len());
+                self.pos = self.text.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<Kind> {
+        Lexer::new(text).map(|t| t.unwrap().kind).collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        use Kind::*;
+        assert_eq!(
+            kinds("#12=CARTESIAN_POINT('',(0.,-1.5E-3,+2));"),
+            [Instance, Equals, Keyword, Open, String, Comma, Open,
+             Real, Comma, Real, Comma, Integer, Close, Close, Semicolon]);
+        assert_eq!(
+            kinds("(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.))"),
+            [Open, Keyword, Open, Close, Keyword, Open, Derived, Close,
+             Keyword, Open, Enum, Comma, Enum, Close, Close]);
+        assert_eq!(kinds("$,.T.,\"0F\",1E5,!USER_DEFINED"),
+                   [Null, Comma, Enum, Comma, Binary, Comma, Real, Comma, Keyword]);
+
+        // Offsets are into the original text, including skipped whitespace
+        let tokens: Vec<Token> = Lexer::new("#1 = /* c */ FOO( 'a' )")
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(tokens.iter().map(|t| t.text).collect::<Vec<_>>(),
+                   ["#1", "=", "FOO", "(", "'a'", ")"]);
+        assert_eq!(tokens[2].span(), 13..16);
+        assert_eq!(tokens[4].start, 18);
+    }
+
+    #[test]
+    fn test_values() {
+        let token = |text| Lexer::new(text).next().unwrap().unwrap();
+        assert_eq!(token("#123").instance(), Some(123));
+        assert_eq!(token("#99999999999999999999999").instance(), None);
+        assert_eq!(token("FOO").instance(), None);
+        assert_eq!(token("1.E-007").real(), Some(1e-7));
+        assert_eq!(token("-12").real(), Some(-12.0));
+        assert_eq!(token("-12").integer(), Some(-12));
+        assert_eq!(token("12.").integer(), None);
+        assert_eq!(token(".UNKNOWN.").enum_tag(), Some("UNKNOWN"));
+
+        // Strings are only copied if they have escapes in them
+        assert!(matches!(token("'a;b'").string(), Some(Cow::Borrowed("a;b"))));
+        assert_eq!(token("'it''s'").string().unwrap(), "it's");
+        assert_eq!(token("'a\\\\b'").string().unwrap(), "a\\b");
+        assert_eq!(token("''''").string().unwrap(), "'");
+        assert_eq!(token("''").string().unwrap(), "");
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors = |text| Lexer::new(text)
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+        assert_eq!(errors("#1=A('abc);"), [Error::UnterminatedString(5)]);
+        assert_eq!(errors("#1=A(#);"), [Error::UnexpectedCharacter(5)]);
+        assert_eq!(errors("#1=A(.T,1);"), [Error::UnexpectedCharacter(5)]);
+        assert_eq!(errors("#1=A(1.E);"), [Error::UnexpectedCharacter(7)]);
+        assert_eq!(errors("#1=A(-);"), [Error::UnexpectedCharacter(5)]);
+        // Lexing stops after an error
+        assert_eq!(errors("@@"), [Error::UnexpectedCharacter(0)]);
+        // Unterminated comments run to the end
+        assert_eq!(kinds("A/*B"), [Kind::Keyword]);
+        assert_eq!(kinds("A/*/B*/C"), [Kind::Keyword, Kind::Keyword]);
+    }
+
+    #[test]
+    fn test_line_index() {
+        let text = "#1=A('x\ny');\n/* a\ncomment */\n  #2=B(1.);\n";
+        let mut lexer = Lexer::new(text);
+        let tokens: Vec<Token> = lexer.by_ref().map(Result::unwrap).collect();
+        let lines = lexer.into_lines();
+        assert_eq!(lines, LineIndex::new(text.as_bytes()));
+        assert_eq!(lines.line_col(0), (1, 1));
+        assert_eq!(lines.line_col(tokens[4].end()), (2, 3));
+        let b = tokens.iter().find(|t| t.text == "B").unwrap();
+        assert_eq!(lines.line_col(b.start), (5, 6));
+        // Newlines count as the end of their line
+        assert_eq!(lines.line_col(7), (1, 8));
+        assert_eq!(lines.line_col(8), (2, 1));
+
+        // Flattened text has no newlines, so every offset is on one line
+        assert_eq!(LineIndex::default().line_col(100), (1, 101));
+    }
+}
@@ -1,4 +1,18 @@
+pub mod builder;
+pub mod check;
+pub mod coverage;
+pub mod diff;
+pub mod error;
+pub mod fixture;
+pub mod inspect;
+pub mod lexer;
+pub mod organization;
 pub mod parse;
+pub mod pmi;
 pub mod step_file;
+pub mod units;
+pub mod walk;
 pub mod ap214; // autogenerated!
 pub mod id;
+
+pub use crate::error::Error;
@@ -0,0 +1,170 @@
+//! Extracts how a STEP file organizes its items: presentation layers
+//! (`PRESENTATION_LAYER_ASSIGNMENT`) and groups (`GROUP`, assigned with
+//! `APPLIED_GROUP_ASSIGNMENT`).
+//!
+//! Layers and groups can hold any kind of item (faces, shells, solids,
+//! styled items, ...), so this only reports the ids which were assigned;
+//! it's up to the caller to decide what those ids mean.
+use std::collections::BTreeMap;
+
+use crate::{
+    ap214::*,
+    pmi::parts,
+    step_file::StepFile,
+};
+
+/// A single `PRESENTATION_LAYER_ASSIGNMENT`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    /// Entity id of the layer assignment
+    pub id: usize,
+    pub name: String,
+    pub description: String,
+    /// Ids of the assigned items, in file order.  An item may be assigned
+    /// to several layers, in which case it's listed in each.
+    pub item_ids: Vec<usize>,
+}
+
+/// A `GROUP` (or subtype), with the items from every assignment to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    /// Entity id of the group
+    pub id: usize,
+    pub name: String,
+    pub description: Option<String>,
+    /// Ids of the assigned items, sorted and without duplicates
+    pub item_ids: Vec<usize>,
+}
+
+/// Lists the presentation layers in a STEP file, in entity order
+pub fn layers(s: &StepFile) -> Vec<Layer> {
+    s.0.iter().enumerate()
+        .filter_map(|(id, e)| match e {
+            Entity::PresentationLayerAssignment(a) => Some(Layer {
+                id,
+                name: a.name.0.to_owned(),
+                description: a.description.0.to_owned(),
+                item_ids: a.assigned_items.iter().map(|i| i.0).collect(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Name and description of a group, which may be part of a complex entity
+fn group_name<'a>(e: &Entity<'a>) -> Option<(&'a str, Option<&'a str>)> {
+    parts(e).iter().find_map(|e| {
+        let (name, description) = match e {
+            Entity::Group(g) => (g.name.0, &g.description),
+            Entity::Class(g) => (g.name.0, &g.description),
+            Entity::ClassSystem(g) => (g.name.0, &g.description),
+            Entity::Language(g) => (g.name.0, &g.description),
+            Entity::ProductConceptFeatureCategory(g) => (g.name.0, &g.description),
+            Entity::RepItemGroup(g) => (g.group__name.0, &g.description),
+            _ => return None,
+        };
+        Some((name, description.as_ref().map(|d| d.0)))
+    })
+}
+
+/// Lists the groups in a STEP file which have items assigned to them, in
+/// entity order.  Assignments which refer to something other than a group
+/// are skipped.
+pub fn groups(s: &StepFile) -> Vec<Group> {
+    let mut items: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for e in s.0.iter().flat_map(parts) {
+        let (group, assigned) = match e {
+            Entity::AppliedGroupAssignment(a) =>
+                (a.assigned_group.0, a.items.iter().map(|i| i.0).collect::<Vec<_>>()),
+            Entity::ProductConceptFeatureCategoryUsage(a) =>
+                (a.assigned_group.0, a.items.iter().map(|i| i.0).collect()),
+            _ => continue,
+        };
+        items.entry(group).or_default().extend(assigned);
+    }
+    items.into_iter()
+        .filter_map(|(id, mut item_ids)| {
+            let (name, description) = group_name(s.0.get(id)?)?;
+            item_ids.sort_unstable();
+            item_ids.dedup();
+            Some(Group {
+                id,
+                name: name.to_owned(),
+                description: description.map(str::to_owned),
+                item_ids,
+            })
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    /// Appends entities to a 10mm cube from [`fixture::cube`], whose faces
+    /// are `#100`, `#112`, `#124`, `#136`, `#148`, and `#160`
+    fn cube_with(extra: &str) -> String {
+        fixture::cube(10.0).to_step()
+            .replace("ENDSEC;\nEND-ISO", &format!("{}ENDSEC;\nEND-ISO", extra))
+    }
+
+    #[test]
+    fn test_layers() {
+        let text = cube_with("\
+#1000=PRESENTATION_LAYER_ASSIGNMENT('sides','',(#100,#112));
+#1001=PRESENTATION_LAYER_ASSIGNMENT('ends','caps',(#148,#160,#112));
+");
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let layers = layers(&step);
+        assert_eq!(layers, vec![
+            Layer {
+                id: 1000,
+                name: "sides".to_owned(),
+                description: "".to_owned(),
+                item_ids: vec![100, 112],
+            },
+            Layer {
+                id: 1001,
+                name: "ends".to_owned(),
+                description: "caps".to_owned(),
+                item_ids: vec![148, 160, 112],
+            },
+        ]);
+        assert!(groups(&step).is_empty());
+    }
+
+    #[test]
+    fn test_groups() {
+        let text = cube_with("\
+#1000=GROUP('fixtures',$);
+#1001=APPLIED_GROUP_ASSIGNMENT(#1000,(#160,#148));
+#1002=APPLIED_GROUP_ASSIGNMENT(#1000,(#148,#100));
+#1003=GROUP('unused','nothing');
+#1004=CLASS('solids','all');
+#1005=APPLIED_GROUP_ASSIGNMENT(#1004,(#162));
+#1006=APPLIED_GROUP_ASSIGNMENT(#100,(#162));
+");
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let groups = groups(&step);
+        assert_eq!(groups, vec![
+            Group {
+                id: 1000,
+                name: "fixtures".to_owned(),
+                description: None,
+                item_ids: vec![100, 148, 160],
+            },
+            Group {
+                id: 1004,
+                name: "solids".to_owned(),
+                description: Some("all".to_owned()),
+                item_ids: vec![162],
+            },
+        ]);
+        assert!(layers(&step).is_empty());
+    }
+}
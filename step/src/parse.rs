@@ -3,15 +3,18 @@ use nom::{
     branch::{alt},
     bytes::complete::{is_not, tag},
     character::complete::{char, digit1},
-    combinator::{map, map_res, opt},
+    combinator::{map, map_res, opt, rest},
     error::*,
     sequence::{delimited, preceded, tuple},
     multi::{separated_list0},
 };
-use memchr::{memchr, memchr3};
 use arrayvec::ArrayVec;
 
-use crate::{id::{Id, HasId}, ap214::{Entity, superclasses_of}};
+use crate::{
+    id::{Id, HasId},
+    ap214::{Entity, superclasses_of},
+    lexer::{Kind, Lexer, Token},
+};
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -87,7 +90,9 @@ impl<'a, T: Parse<'a>, const CAP: usize> Parse<'a> for ArrayVec<T, CAP> {
             e => e?,
         };
         s = s_;
-        out.push(o);
+        if out.try_push(o).is_err() {
+            return nom_err(s, ErrorKind::TooLarge);
+        }
 
         loop {
             let (s_, _) = match char(',')(s) {
@@ -100,7 +105,10 @@ impl<'a, T: Parse<'a>, const CAP: usize> Parse<'a> for ArrayVec<T, CAP> {
                 e => e?,
             };
             s = s_;
-            out.push(o);
+            // Too many items (e.g. a 4D point) is a parse error, not a panic
+            if out.try_push(o).is_err() {
+                return nom_err(s, ErrorKind::TooLarge);
+            }
         }
         let (s, _) = char(')')(s)?;
         Ok((s, out))
@@ -142,6 +150,19 @@ impl<'a, T> Parse<'a> for Id<T> {
     }
 }
 
+/// Runs the literal sub-parsers on arbitrary text, ignoring the results.
+/// This is only public so that the fuzzers can reach it.
+#[doc(hidden)]
+pub fn fuzz_literals(s: &str) {
+    let _ = <&str>::parse(s);
+    let _ = f64::parse(s);
+    let _ = i64::parse(s);
+    let _ = bool::parse(s);
+    let _ = Logical::parse(s);
+    let _ = Id::<()>::parse(s);
+    let _ = parse_enum_tag(s);
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 pub(crate) trait ParseFromChunks<'a> {
@@ -155,11 +176,14 @@ impl<'a, T: ParseFromChunks<'a>> Parse<'a> for T {
 }
 
 // Simple struct so we can use param_from_chunks::<Derived> to parse a '*'
-// optionally followed by a comma
+// optionally followed by a comma.  Older exporters sometimes write a
+// reference instead (e.g. for a CONVERSION_BASED_UNIT's dimensions, which
+// earlier versions of the schema didn't derive), which is ignored.
 pub struct Derived;
 impl<'a> Parse<'a> for Derived {
     fn parse(s: &str) -> IResult<Self> {
-        map(char('*'), |_| Derived)(s)
+        alt((map(char('*'), |_| Derived),
+             map(Id::<()>::parse, |_| Derived)))(s)
     }
 }
 
@@ -201,13 +225,135 @@ pub(crate) fn parse_enum_tag(s: &str) -> IResult<&str> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
-pub(crate) fn parse_entity_decl(s: &[u8]) -> IResult<(usize, Entity)> {
-    let s = match std::str::from_utf8(s) {
-        Ok(s) => s,
-        Err(_) => return nom_err("", ErrorKind::Escaped), // TODO correct code?
+/// Parses an entity record, e.g. `#12=VERTEX_POINT('',#11);`
+///
+/// The record is split into its keyword and attributes by the [`Lexer`],
+/// then each attribute is parsed in turn; complex mappings are handed off
+/// to [`parse_complex_mapping`].  Byte offsets in errors are into the record.
+pub(crate) fn parse_entity_decl(s: &[u8]) -> Result<(usize, Entity), crate::Error> {
+    let s = std::str::from_utf8(s)
+        .map_err(|e| crate::Error::UnexpectedCharacter(e.valid_up_to()))?;
+    let tokens = Lexer::new(s).collect::<Result<Vec<_>, _>>()?;
+    let malformed = |i: usize| crate::Error::MalformedRecord(
+        tokens.get(i).map_or(s.len(), |t| t.start));
+
+    let id = match tokens.get(..2) {
+        Some([i, eq]) if eq.kind == Kind::Equals => i.instance()
+            .ok_or(crate::Error::MalformedRecord(i.start))?,
+        _ => return Err(malformed(0)),
+    };
+    if tokens.get(2).map(|t| t.kind) == Some(Kind::Open) {
+        return parse_complex_mapping(&s[tokens[2].start..])
+            .map(|(_, e)| (id, e))
+            .map_err(|e| attribute_error(s, &tokens, e));
+    }
+    let (kw, open) = match tokens.get(2..4) {
+        Some([kw, open]) if kw.kind == Kind::Keyword && open.kind == Kind::Open =>
+            (kw, open),
+        _ => return Err(malformed(2)),
+    };
+
+    // Split the attributes at top-level commas, keeping the separators (and
+    // the closing parenthesis) as chunks of their own, which is the shape
+    // that `Entity::parse_chunks` expects.
+    let mut chunks = vec![&s[kw.start..open.end()]];
+    let mut depth = 1;
+    let mut attr: Option<(usize, usize)> = None;
+    let mut end = None;
+    for (i, t) in tokens.iter().enumerate().skip(4) {
+        match t.kind {
+            Kind::Open => depth += 1,
+            Kind::Close => depth -= 1,
+            _ => (),
+        }
+        if depth == 0 || (depth == 1 && t.kind == Kind::Comma) {
+            if let Some((a, b)) = attr.take() {
+                chunks.push(&s[a..b]);
+            }
+            chunks.push(t.text);
+            if depth == 0 {
+                end = Some(i);
+                break;
+            }
+        } else {
+            let start = attr.map_or(t.start, |(a, _)| a);
+            attr = Some((start, t.end()));
+        }
+    }
+    let end = end.ok_or(crate::Error::MalformedRecord(s.len()))?;
+    match &tokens[end + 1..] {
+        [] => (),
+        [t] if t.kind == Kind::Semicolon => (),
+        [t, ..] => return Err(crate::Error::MalformedRecord(t.start)),
+    }
+
+    Entity::parse_chunks(&chunks)
+        .map(|(_, e)| (id, e))
+        .map_err(|e| attribute_error(s, &tokens, e))
+}
+
+/// Converts a `nom` error from parsing the record `s` into an error which
+/// points at the attribute where parsing failed.
+fn attribute_error(s: &str, tokens: &[Token], e: nom::Err<Error<&str>>)
+    -> crate::Error
+{
+    // Every chunk is a slice of the record, so the failing input's position
+    // within it is where parsing stopped
+    let at = match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            let p = (e.input.as_ptr() as usize).wrapping_sub(s.as_ptr() as usize);
+            p.min(s.len())
+        },
+        nom::Err::Incomplete(_) => s.len(),
     };
-    map(tuple((Id::<()>::parse, char('='), Entity::parse)),
-        |(i, _, e)| (i.0, e))(s)
+
+    // Entity attributes are one level down from the record, or two levels
+    // down within a complex mapping
+    let level = if tokens.get(2).map(|t| t.kind) == Some(Kind::Open) { 2 } else { 1 };
+    let mut depth = 0;
+    let mut index = 0;
+    let mut start = None;
+    let mut in_attrs = false;
+    let mut rest = tokens.iter().skip(2).peekable();
+    while let Some(t) = rest.next_if(|t| t.start < at) {
+        match t.kind {
+            Kind::Open => {
+                depth += 1;
+                if depth == level {
+                    in_attrs = true;
+                    index = 0;
+                    start = None;
+                    continue;
+                }
+            },
+            Kind::Close => {
+                depth -= 1;
+                in_attrs &= depth >= level;
+            },
+            Kind::Comma if depth == level => {
+                index += 1;
+                start = None;
+                continue;
+            },
+            _ => (),
+        }
+        start.get_or_insert(t.start);
+    }
+
+    let next = rest.peek().filter(|t| t.start == at).map(|t| t.kind);
+    if next == Some(Kind::Keyword) && depth < level {
+        crate::Error::UnknownEntity(at)
+    } else if !in_attrs {
+        crate::Error::MalformedRecord(at)
+    } else if start.is_some() && depth == level
+        && matches!(next, Some(Kind::Comma | Kind::Close))
+    {
+        // The attribute before this one parsed, so there are too many or too
+        // few attributes
+        crate::Error::InvalidAttribute { index: index + 1, offset: at }
+    } else {
+        crate::Error::InvalidAttribute { index, offset: start.unwrap_or(at) }
+    }
 }
 
 pub(crate) fn parse_entity_fallback(s: &[u8]) -> IResult<(usize, Entity)> {
@@ -215,7 +361,9 @@ pub(crate) fn parse_entity_fallback(s: &[u8]) -> IResult<(usize, Entity)> {
         Ok(s) => s,
         Err(_) => return nom_err("", ErrorKind::Escaped),
     };
-    map(Id::<()>::parse, |i| (i.0, Entity::_FailedToParse))(s)
+    // Keep the entity's text, so that we can still tell what it was
+    map(tuple((Id::<()>::parse, opt(char('=')), rest)),
+        |(i, _, r)| (i.0, Entity::_FailedToParse(r.trim_end_matches(';'))))(s)
 }
 
 pub(crate) fn parse_complex_mapping(s: &str) -> IResult<Entity> {
@@ -226,52 +374,45 @@ pub(crate) fn parse_complex_mapping(s: &str) -> IResult<Entity> {
     // Map from sub-entity name to the str slice which contains the name plus
     // the open parens, used for parsing slices
     let mut name_tags: HashMap<&str, &str> = HashMap::new();
-    let bstr = s.as_bytes();
     let mut depth = 0;
-    let mut index = 0;
     let mut args_start = 0;
     let mut name: &str = "";
-    loop {
-        let next = match memchr3(b'(', b')', b'\'', &bstr[index..]) {
-            Some(i) => i,
-            None => return nom_err(s, ErrorKind::Alt),
+    let mut prev: Option<Token> = None;
+    let mut closed = false;
+    for t in Lexer::new(s) {
+        let t = match t {
+            Ok(t) => t,
+            Err(_) => return nom_err(s, ErrorKind::Char),
         };
-        match bstr[index + next] {
-            b'(' => {
+        match t.kind {
+            Kind::Open => {
                 if depth == 1 {
-                    let name_slice = &bstr[index..(index + next)];
-                    name = std::str::from_utf8(name_slice)
-                        .expect("Could not convert back to name");
-                    args_start = index + next + 1;
-                    let name_tag_slice = &bstr[index..(index + next + 1)];
-                    let name_tag = std::str::from_utf8(name_tag_slice)
-                        .expect("Could not convert tag back to name");
-                    name_tags.insert(name, name_tag);
+                    // Sub-entities are a keyword followed by their arguments
+                    let start = match prev {
+                        Some(p) if p.kind == Kind::Keyword => p.start,
+                        _ => t.start,
+                    };
+                    name = &s[start..t.start];
+                    args_start = t.end();
+                    name_tags.insert(name, &s[start..args_start]);
                 }
                 depth += 1;
             },
-            b')' => {
+            Kind::Close => {
                 depth -= 1;
                 if depth == 1 {
-                    let arg_slice = &bstr[args_start..(index + next)];
-                    let args = std::str::from_utf8(arg_slice)
-                        .expect("Could not convert args");
-                    subentities.insert(name, args);
+                    subentities.insert(name, &s[args_start..t.start]);
                 } else if depth == 0 {
+                    closed = true;
                     break;
                 }
             },
-            b'\'' => {
-                // TODO: handle escaped quotes
-                let j = match memchr(b'\'', &bstr[(index + next + 1)..]) {
-                    Some(j) => j,
-                    None => return nom_err(s, ErrorKind::Char),
-                };
-                index += j + 1;
-            }
-            c => panic!("Invalid char: {}", c),
+            _ => (),
         }
-        index += next + 1;
+        prev = Some(t);
+    }
+    if !closed {
+        return nom_err(s, ErrorKind::Alt);
     }
     // Filter out the list of subclasses to those which aren't a parent of
     // another item in the set; these are our potential leafs.
@@ -307,9 +448,13 @@ pub(crate) fn parse_complex_mapping(s: &str) -> IResult<Entity> {
         }
         let mut new_decl: Vec<&str> = vec![name_tags.get(leaf).unwrap()];
         for c in chain.iter().rev() {
-            if !subentities[c].is_empty() {
-                new_decl.push(subentities[c]);
-                new_decl.push(if *c == leaf { ")" } else { "," });
+            // Superclasses may be missing from malformed mappings
+            match subentities.get(c) {
+                Some(args) if !args.is_empty() => {
+                    new_decl.push(args);
+                    new_decl.push(if *c == leaf { ")" } else { "," });
+                },
+                _ => (),
             }
         }
         leaf_entities.push(Entity::parse_chunks(&new_decl)?.1)
@@ -335,4 +480,198 @@ mod tests {
         parse_entity_decl(b"#395359=UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(1.E-007),#395356,'distance_accuracy_value','confusion accuracy');").unwrap();
         parse_entity_decl(b"#1632=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.));").unwrap();
     }
+
+    #[test]
+    fn test_entity_decl_errors() {
+        use crate::Error;
+        let e = |s: &str| parse_entity_decl(s.as_bytes()).unwrap_err();
+        // Not a logical
+        assert_eq!(e("#1=ADVANCED_FACE('',(#2),#3,.X.);"),
+                   Error::InvalidAttribute { index: 3, offset: 28 });
+        // Not an instance inside of a list
+        assert_eq!(e("#1=ADVANCED_FACE('',(#2,'a'),#3,.T.);"),
+                   Error::InvalidAttribute { index: 1, offset: 20 });
+        // Too many and too few attributes
+        assert_eq!(e("#1=VERTEX_POINT('',#2,#3);"),
+                   Error::InvalidAttribute { index: 2, offset: 21 });
+        assert_eq!(e("#1=VERTEX_POINT('');"),
+                   Error::InvalidAttribute { index: 1, offset: 18 });
+        assert_eq!(e("#1=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,#4));"),
+                   Error::InvalidAttribute { index: 1, offset: 46 });
+        assert_eq!(e("#1=NOT_AN_ENTITY(#2);"), Error::UnknownEntity(3));
+        assert_eq!(e("#1=PRODUCT('abc);"), Error::UnterminatedString(11));
+        assert_eq!(e("#1=VERTEX_POINT('',#2"), Error::MalformedRecord(21));
+        assert_eq!(e("#1=VERTEX_POINT('',#2)#3;"), Error::MalformedRecord(22));
+        assert_eq!(e("#1 VERTEX_POINT('',#2);"), Error::MalformedRecord(0));
+    }
+
+    #[test]
+    fn test_id_overflow() {
+        assert!(Id::<()>::parse("#99999999999999999999999").is_err());
+        assert_eq!(Id::<()>::parse("#12").unwrap().1, Id::new(12));
+    }
+
+    #[test]
+    fn test_complex_mapping_missing_superclass() {
+        // SI_UNIT's superclass NAMED_UNIT is missing, which used to panic
+        let _ = parse_entity_decl(b"#1=(LENGTH_UNIT()SI_UNIT(.MILLI.,.METRE.));");
+    }
+
+    #[test]
+    fn test_conversion_based_unit() {
+        // The dimensions are derived, but are given as a reference here
+        let (i, e) = parse_entity_decl(
+            b"#7=(CONVERSION_BASED_UNIT('INCH',#2)LENGTH_UNIT()NAMED_UNIT(#3));").unwrap();
+        assert_eq!(i, 7);
+        match e {
+            Entity::ConversionBasedUnit(u) => assert_eq!(u.name.0, "INCH"),
+            e => panic!("Unexpected entity {:?}", e),
+        }
+    }
+
+    /// The original `memchr`-based implementation of `parse_complex_mapping`,
+    /// which finds the sub-entities in the same way as the lexer-based one
+    fn reference_complex_mapping(s: &str) -> IResult<Entity> {
+        let mut subentities: HashMap<&str, &str> = HashMap::new();
+        let mut name_tags: HashMap<&str, &str> = HashMap::new();
+        let bstr = s.as_bytes();
+        let mut depth = 0;
+        let mut index = 0;
+        let mut args_start = 0;
+        let mut name: &str = "";
+        loop {
+            let next = match memchr::memchr3(b'(', b')', b'\'', &bstr[index..]) {
+                Some(i) => i,
+                None => return nom_err(s, ErrorKind::Alt),
+            };
+            match bstr[index + next] {
+                b'(' => {
+                    if depth == 1 {
+                        name = &s[index..(index + next)];
+                        args_start = index + next + 1;
+                        name_tags.insert(name, &s[index..(index + next + 1)]);
+                    }
+                    depth += 1;
+                },
+                b')' => {
+                    depth -= 1;
+                    if depth == 1 {
+                        subentities.insert(name, &s[args_start..(index + next)]);
+                    } else if depth == 0 {
+                        break;
+                    }
+                },
+                _ => {
+                    let j = match memchr::memchr(b'\'', &bstr[(index + next + 1)..]) {
+                        Some(j) => j,
+                        None => return nom_err(s, ErrorKind::Char),
+                    };
+                    index += j + 1;
+                }
+            }
+            index += next + 1;
+        }
+        let mut potential_leafs: Vec<&str> = subentities.keys().copied()
+            .filter(|k| !subentities.keys().any(|j| superclasses_of(j).contains(k)))
+            .filter(|k| !subentities[k].is_empty())
+            .collect();
+        potential_leafs.sort();
+        let mut leaf_entities = vec![];
+        for leaf in potential_leafs.into_iter() {
+            let mut chain = vec![leaf];
+            loop {
+                let sup = superclasses_of(chain.last().unwrap());
+                match sup.len() {
+                    0 => break,
+                    1 => chain.push(sup[0]),
+                    _ => return nom_err(s, ErrorKind::LengthValue),
+                }
+            }
+            let mut new_decl: Vec<&str> = vec![name_tags.get(leaf).unwrap()];
+            for c in chain.iter().rev() {
+                match subentities.get(c) {
+                    Some(args) if !args.is_empty() => {
+                        new_decl.push(args);
+                        new_decl.push(if *c == leaf { ")" } else { "," });
+                    },
+                    _ => (),
+                }
+            }
+            leaf_entities.push(Entity::parse_chunks(&new_decl)?.1)
+        }
+        if leaf_entities.len() == 1 {
+            Ok(("", leaf_entities.pop().unwrap()))
+        } else {
+            Ok(("", Entity::ComplexEntity(leaf_entities)))
+        }
+    }
+
+    /// The original `nom`-based implementation of `parse_entity_decl`
+    fn reference_entity_decl(s: &str) -> IResult<(usize, Entity)> {
+        let (s, (i, _)) = tuple((Id::<()>::parse, char('=')))(s)?;
+        let (s, e) = if s.starts_with('(') {
+            reference_complex_mapping(s)?
+        } else {
+            Entity::parse(s)?
+        };
+        Ok((s, (i.0, e)))
+    }
+
+    /// Checks that every record in a file parses to the same entity as it
+    /// did with the reference parser, returning the number of records
+    fn check_equivalent(text: &str) -> usize {
+        let flat = crate::step_file::StepFile::strip_flatten(text.as_bytes());
+        let opts = crate::step_file::ParseOptions { retain_source: true };
+        let step = crate::step_file::StepFile::parse_with(&flat, &opts).unwrap();
+        let mut n = 0;
+        for i in 0..step.0.len() {
+            let Some(record) = step.source_text(i) else { continue };
+            let expected = reference_entity_decl(record);
+            let actual = parse_entity_decl(record.as_bytes());
+            match (expected, actual) {
+                (Ok(a), Ok(b)) => assert_eq!(format!("{:?}", a.1), format!("{:?}", b)),
+                (a, b) => assert_eq!(a.is_ok(), b.is_ok(), "{}", record),
+            }
+            n += 1;
+        }
+        n
+    }
+
+    #[test]
+    fn test_lexer_equivalence() {
+        use crate::fixture;
+        let corpus = [
+            fixture::cube(1.0).to_step(),
+            fixture::cylinder(1.0, 2.0).color([1.0, 0.0, 0.0]).to_step(),
+            fixture::filleted_block(10.0, 2.0).open().to_step(),
+            fixture::assembly(vec![
+                fixture::cube(1.0).wireframe(),
+                fixture::cylinder(0.5, 1.0).slant(0.2).translate([3.0, 0.0, 0.0]),
+            ]).to_step(),
+            fixture::cube_with_pmi(),
+            fixture::cube_with_cycle(),
+            include_str!("../../examples/abstract_pca.step").to_owned(),
+            include_str!("../../examples/cube_hole.step").to_owned(),
+            include_str!("../../examples/cuboid.step").to_owned(),
+            include_str!("../../examples/pump_manifold.step").to_owned(),
+        ];
+        for text in &corpus {
+            assert!(check_equivalent(text) > 0);
+        }
+
+        // Complex mappings with quoted parentheses, missing superclasses,
+        // and malformed nesting
+        for record in [
+            "#1=(NAMED_UNIT(*)PLANE_ANGLE_UNIT()SI_UNIT($,.RADIAN.));",
+            "#2=(CONVERSION_BASED_UNIT('(IN)',#1)LENGTH_UNIT()NAMED_UNIT(#3));",
+            "#3=(LENGTH_UNIT()SI_UNIT(.MILLI.,.METRE.));",
+            "#4=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.)",
+            "#5=(LENGTH_UNIT(('a'))NAMED_UNIT(*));",
+            "#6=();",
+            "#7=(REPRESENTATION_ITEM('it''s')GEOMETRIC_REPRESENTATION_ITEM());",
+        ] {
+            let wrapped = format!("DATA;{}ENDSEC;", record);
+            check_equivalent(&wrapped);
+        }
+    }
 }
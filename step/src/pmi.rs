@@ -0,0 +1,401 @@
+//! Summarizes the product and manufacturing information (PMI) in a STEP
+//! file: dimensions, geometric tolerances, datums, and the annotations and
+//! draughting models which present them.
+//!
+//! PMI isn't rendered, but it's useful to know what's there.  Semantic PMI
+//! is attached to the model through shape aspects, which are tied to
+//! geometric items (usually faces) by `GEOMETRIC_ITEM_SPECIFIC_USAGE`;
+//! presentation (annotations) is tied to the semantic PMI by
+//! `DRAUGHTING_MODEL_ITEM_ASSOCIATION`.
+use std::collections::HashMap;
+
+use log::warn;
+
+use crate::{
+    ap214::*,
+    coverage::ancestors,
+    step_file::StepFile,
+    walk::GraphWalker,
+};
+
+/// Broad category of a PMI entity
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum PmiKind {
+    /// A `DIMENSIONAL_SIZE` or `DIMENSIONAL_LOCATION` (or subtype)
+    Dimension,
+    /// A `GEOMETRIC_TOLERANCE` (or subtype)
+    Tolerance,
+    /// A `DATUM`, `DATUM_FEATURE`, or `DATUM_TARGET`
+    Datum,
+    /// An `ANNOTATION_OCCURRENCE` (or subtype)
+    Annotation,
+    /// A `DRAUGHTING_CALLOUT` (or subtype), which groups annotations
+    Callout,
+    /// A `DRAUGHTING_MODEL`, which collects presentation items
+    DraughtingModel,
+}
+
+impl PmiKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PmiKind::Dimension => "dimension",
+            PmiKind::Tolerance => "tolerance",
+            PmiKind::Datum => "datum",
+            PmiKind::Annotation => "annotation",
+            PmiKind::Callout => "callout",
+            PmiKind::DraughtingModel => "draughting model",
+        }
+    }
+
+    /// Classifies an entity keyword, returning `None` if it's not PMI
+    fn of(keyword: &str) -> Option<Self> {
+        let a = ancestors(keyword);
+        let is = |k| a.contains(&k);
+        if is("GEOMETRIC_TOLERANCE") {
+            Some(PmiKind::Tolerance)
+        } else if is("DIMENSIONAL_SIZE") || is("DIMENSIONAL_LOCATION") {
+            Some(PmiKind::Dimension)
+        } else if ["DATUM", "DATUM_FEATURE", "DATUM_TARGET"].contains(&keyword) {
+            Some(PmiKind::Datum)
+        } else if is("ANNOTATION_OCCURRENCE") {
+            Some(PmiKind::Annotation)
+        } else if is("DRAUGHTING_CALLOUT") {
+            Some(PmiKind::Callout)
+        } else if is("DRAUGHTING_MODEL") {
+            Some(PmiKind::DraughtingModel)
+        } else {
+            None
+        }
+    }
+}
+
+/// A single piece of PMI
+#[derive(Clone, Debug, PartialEq)]
+pub struct PmiItem {
+    /// Entity id, i.e. `#id` in the file
+    pub id: usize,
+    pub kind: PmiKind,
+    /// Entity keyword, e.g. `FLATNESS_TOLERANCE`
+    pub keyword: &'static str,
+    pub name: String,
+    /// Text of an annotation, or the description of a dimension or
+    /// tolerance, if there is one
+    pub text: Option<String>,
+    /// Nominal value of a dimension, or magnitude of a tolerance, in the
+    /// file's units
+    pub value: Option<f64>,
+    /// Ids of the geometric items (usually faces or edges) which this is
+    /// attached to, sorted.  Annotations inherit the geometry of the PMI
+    /// which they present.
+    pub geometry: Vec<usize>,
+}
+
+/// Relationships between entities, gathered in one pass over the file
+#[derive(Default)]
+struct Links {
+    /// Shape aspect to the geometric items which it identifies
+    usages: HashMap<usize, Vec<usize>>,
+    /// Shape aspect to its child shape aspects
+    children: HashMap<usize, Vec<usize>>,
+    /// Dimension to its `SHAPE_DIMENSION_REPRESENTATION`
+    representations: HashMap<usize, usize>,
+    /// Annotation to the PMI which it presents
+    presents: HashMap<usize, Vec<usize>>,
+}
+
+impl Links {
+    fn new(s: &StepFile) -> Self {
+        let mut out = Self::default();
+        for e in s.0.iter().flat_map(parts) {
+            match e {
+                Entity::GeometricItemSpecificUsage(u) => out.usages
+                    .entry(u.definition.0).or_default()
+                    .push(u.identified_item.0),
+                Entity::ItemIdentifiedRepresentationUsage(u) => out.usages
+                    .entry(u.definition.0).or_default()
+                    .push(u.identified_item.0),
+                Entity::DraughtingModelItemAssociation(u) => out.presents
+                    .entry(u.identified_item.0).or_default()
+                    .push(u.definition.0),
+                Entity::ShapeAspectRelationship(r) => out.children
+                    .entry(r.relating_shape_aspect.0).or_default()
+                    .push(r.related_shape_aspect.0),
+                Entity::DimensionalCharacteristicRepresentation(r) => {
+                    out.representations.insert(r.dimension.0, r.representation.0);
+                }
+                _ => (),
+            }
+        }
+        out
+    }
+
+    /// Returns the geometric items identified by some shape aspects and
+    /// their children
+    fn geometry<I: IntoIterator<Item=usize>>(&self, aspects: I, out: &mut Vec<usize>) {
+        let r = GraphWalker::default().walk(
+            aspects,
+            |a| self.children.get(&a).into_iter().flatten().copied(),
+            |a| out.extend(self.usages.get(&a).into_iter().flatten()));
+        // Cycles of shape aspects are harmless, since each is only visited
+        // once, but they're worth knowing about
+        if let Err(e) = r {
+            warn!("Could not walk shape aspects: {}", e);
+        }
+    }
+}
+
+/// Returns the parts of an entity: itself, or its leaves if it's complex
+pub(crate) fn parts<'a, 'b>(e: &'b Entity<'a>) -> &'b [Entity<'a>] {
+    match e {
+        Entity::ComplexEntity(v) => v,
+        e => std::slice::from_ref(e),
+    }
+}
+
+/// Matches an entity against a list of variants which share fields,
+/// evaluating `$body` with `$t` bound to the variant's data
+macro_rules! fields {
+    ($e:expr, [$($v:ident),* $(,)?], |$t:ident| $body:expr) => {
+        match $e {
+            $(Entity::$v($t) => Some($body),)*
+            _ => None,
+        }
+    };
+}
+
+/// Name, description, magnitude, and shape aspect of a tolerance
+fn tolerance<'a>(e: &Entity<'a>) -> Option<(&'a str, &'a str, usize, usize)> {
+    fields!(e, [
+        GeometricTolerance, AngularityTolerance, CircularRunoutTolerance,
+        CoaxialityTolerance, ConcentricityTolerance, CylindricityTolerance,
+        FlatnessTolerance, GeometricToleranceWithDatumReference,
+        GeometricToleranceWithDefinedUnit, LineProfileTolerance,
+        ModifiedGeometricTolerance, ParallelismTolerance,
+        PerpendicularityTolerance, PositionTolerance, RoundnessTolerance,
+        StraightnessTolerance, SurfaceProfileTolerance, SymmetryTolerance,
+        TotalRunoutTolerance,
+    ], |t| (t.name.0, t.description.0, t.magnitude.0, t.toleranced_shape_aspect.0))
+}
+
+/// Name, description, and shape aspects of a dimension
+fn dimension<'a>(e: &Entity<'a>) -> Option<(&'a str, Option<&'a str>, Vec<usize>)> {
+    fields!(e, [DimensionalSize, AngularSize, DimensionalSizeWithPath],
+            |d| (d.name.0, None, vec![d.applies_to.0]))
+    .or_else(|| fields!(e, [
+        DimensionalLocation, AngularLocation, DimensionalLocationWithPath,
+        DirectedDimensionalLocation,
+    ], |d| (d.name.0, d.description.as_ref().map(|t| t.0),
+            vec![d.relating_shape_aspect.0, d.related_shape_aspect.0])))
+}
+
+/// Name and description of a datum, which is its own shape aspect
+fn datum<'a>(e: &Entity<'a>) -> Option<(&'a str, Option<&'a str>)> {
+    fields!(e, [Datum, DatumFeature, DatumTarget],
+            |d| (d.name.0, d.description.as_ref().map(|t| t.0)))
+}
+
+/// Name and presented item of an annotation
+fn annotation<'a>(e: &Entity<'a>) -> Option<(&'a str, usize)> {
+    fields!(e, [
+        AnnotationOccurrence, AnnotationCurveOccurrence,
+        AnnotationFillAreaOccurrence, AnnotationSymbolOccurrence,
+        AnnotationSubfigureOccurrence, AnnotationTextOccurrence,
+        DimensionCurve, DraughtingAnnotationOccurrence, LeaderCurve,
+        ProjectionCurve, TerminatorSymbol,
+    ], |a| (a.name.0, a.item.0))
+    .or_else(|| fields!(e, [AnnotationPlane],
+                        |a| (a.representation_item__name.0, a.item.0)))
+}
+
+/// Returns the numerical part of a measure, if it's a number
+fn measure_value(v: &MeasureValue) -> Option<f64> {
+    Some(match v {
+        MeasureValue::AreaMeasure(m) => m.0,
+        MeasureValue::ContextDependentMeasure(m) => m.0,
+        MeasureValue::CountMeasure(m) => m.0,
+        MeasureValue::LengthMeasure(m) => m.0,
+        MeasureValue::NumericMeasure(m) => m.0,
+        MeasureValue::ParameterValue(m) => m.0,
+        MeasureValue::PlaneAngleMeasure(m) => m.0,
+        MeasureValue::RatioMeasure(m) => m.0,
+        MeasureValue::TimeMeasure(m) => m.0,
+        MeasureValue::VolumeMeasure(m) => m.0,
+        _ => return None,
+    })
+}
+
+/// Returns the value of a measure entity (or of the first measure in a
+/// complex entity)
+fn measure(e: &Entity) -> Option<f64> {
+    parts(e).iter().find_map(|e| {
+        let v = fields!(e, [
+            MeasureWithUnit, LengthMeasureWithUnit, PlaneAngleMeasureWithUnit,
+            MeasureRepresentationItem,
+        ], |m| &m.value_component)?;
+        measure_value(v)
+    })
+}
+
+/// Returns the first measure among the items of a dimension's
+/// `SHAPE_DIMENSION_REPRESENTATION`
+fn dimension_value(s: &StepFile, rep: usize) -> Option<f64> {
+    let items = parts(s.0.get(rep)?).iter().find_map(|e| match e {
+        Entity::ShapeDimensionRepresentation(r) => Some(&r.items),
+        _ => None,
+    })?;
+    items.iter().find_map(|i| s.0.get(i.0).and_then(measure))
+}
+
+/// Returns the text of a presented item, if it's a `TEXT_LITERAL`
+fn literal(s: &StepFile, item: usize) -> Option<String> {
+    parts(s.0.get(item)?).iter().find_map(|e| match e {
+        Entity::TextLiteral(t) => Some(t.literal.0.to_owned()),
+        _ => None,
+    })
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_owned())
+    }
+}
+
+/// Describes a single entity (or part of a complex entity), returning
+/// `None` if it's not PMI
+fn describe(s: &StepFile, links: &Links, id: usize, e: &Entity) -> Option<PmiItem> {
+    let keyword = e.keyword()?;
+    let kind = PmiKind::of(keyword)?;
+    let mut item = PmiItem {
+        id,
+        kind,
+        keyword,
+        name: String::new(),
+        text: None,
+        value: None,
+        geometry: vec![],
+    };
+    let mut aspects = vec![];
+    match kind {
+        PmiKind::Tolerance => if let Some((name, desc, mag, aspect)) = tolerance(e) {
+            item.name = name.to_owned();
+            item.text = non_empty(desc);
+            item.value = s.0.get(mag).and_then(measure);
+            aspects.push(aspect);
+        },
+        PmiKind::Dimension => if let Some((name, desc, a)) = dimension(e) {
+            item.name = name.to_owned();
+            item.text = desc.and_then(non_empty);
+            item.value = links.representations.get(&id)
+                .and_then(|r| dimension_value(s, *r));
+            aspects = a;
+        },
+        PmiKind::Datum => if let Some((name, desc)) = datum(e) {
+            item.name = name.to_owned();
+            item.text = desc.and_then(non_empty);
+            aspects.push(id);
+        },
+        PmiKind::Annotation => if let Some((name, presented)) = annotation(e) {
+            item.name = name.to_owned();
+            item.text = literal(s, presented);
+        },
+        PmiKind::Callout => if let Entity::DraughtingCallout(c) = e {
+            item.name = c.name.0.to_owned();
+        },
+        PmiKind::DraughtingModel => if let Entity::DraughtingModel(m) = e {
+            item.name = m.name.0.to_owned();
+        },
+    }
+    links.geometry(aspects, &mut item.geometry);
+    item.geometry.sort_unstable();
+    item.geometry.dedup();
+    Some(item)
+}
+
+/// Lists the PMI in a STEP file, in entity order
+pub fn summary(s: &StepFile) -> Vec<PmiItem> {
+    let links = Links::new(s);
+    let mut out: Vec<PmiItem> = s.0.iter().enumerate()
+        .filter_map(|(id, e)| {
+            parts(e).iter().find_map(|e| describe(s, &links, id, e))
+        })
+        .collect();
+
+    // Annotations are attached to whatever the PMI they present is
+    // attached to, which may also be a bare shape aspect
+    let geometry: HashMap<usize, Vec<usize>> = out.iter()
+        .map(|i| (i.id, i.geometry.clone()))
+        .collect();
+    for item in out.iter_mut().filter(|i| i.kind == PmiKind::Annotation) {
+        for p in links.presents.get(&item.id).into_iter().flatten() {
+            match geometry.get(p) {
+                Some(g) => item.geometry.extend(g),
+                None => links.geometry([*p], &mut item.geometry),
+            }
+        }
+        item.geometry.sort_unstable();
+        item.geometry.dedup();
+    }
+    out
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn test_summary() {
+        let text = fixture::cube_with_pmi();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        for i in 1000..1019 {
+            assert!(!matches!(step.0[i], Entity::_FailedToParse(_)), "#{} failed to parse", i);
+        }
+
+        let pmi = summary(&step);
+        let kinds: Vec<_> = pmi.iter().map(|i| (i.id, i.kind)).collect();
+        assert_eq!(kinds, [
+            (1004, PmiKind::Dimension),
+            (1011, PmiKind::Tolerance),
+            (1012, PmiKind::Datum),
+            (1016, PmiKind::Annotation),
+            (1017, PmiKind::DraughtingModel),
+        ]);
+
+        let dim = &pmi[0];
+        assert_eq!(dim.keyword, "DIMENSIONAL_LOCATION");
+        assert_eq!(dim.name, "linear_distance");
+        assert_eq!(dim.text.as_deref(), Some("width"));
+        assert_eq!(dim.value, Some(10.0));
+        assert_eq!(dim.geometry, [112, 136]);
+
+        let tol = &pmi[1];
+        assert_eq!(tol.keyword, "FLATNESS_TOLERANCE");
+        assert_eq!(tol.value, Some(0.05));
+        assert_eq!(tol.geometry, [160]);
+
+        assert_eq!(pmi[2].name, "A");
+        assert_eq!(pmi[2].geometry, [148]);
+
+        // The annotation's text comes from its literal, and its geometry
+        // from the dimension which it presents
+        let note = &pmi[3];
+        assert_eq!(note.text.as_deref(), Some("10mm"));
+        assert_eq!(note.geometry, dim.geometry);
+        assert_eq!(note.value, None);
+
+        assert!(pmi[4].geometry.is_empty());
+    }
+
+    #[test]
+    fn test_no_pmi() {
+        let text = fixture::cube(1.0).to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        assert!(summary(&step).is_empty());
+    }
+}
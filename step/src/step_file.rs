@@ -1,4 +1,4 @@
-use memchr::{memchr, memchr2, memchr_iter};
+use memchr::{memchr, memchr2, memchr3, memchr_iter};
 use log::warn;
 
 #[cfg(feature = "rayon")]
@@ -6,17 +6,38 @@ use rayon::prelude::*;
 
 use crate::{
     ap214::Entity,
+    error::Error,
     id::Id,
     parse::{parse_entity_decl, parse_entity_fallback},
 };
 
+/// Options for [`StepFile::parse_with`]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ParseOptions {
+    /// Keeps each entity's record, so that it can be looked up with
+    /// [`StepFile::source_text`]
+    pub retain_source: bool,
+}
+
+/// A parsed STEP file, with entities stored densely by id.  The second
+/// field holds each entity's record (in the flattened data), if it was
+/// retained while parsing.
 #[derive(Debug)]
-pub struct StepFile<'a>(pub Vec<Entity<'a>>);
+pub struct StepFile<'a>(pub Vec<Entity<'a>>, Vec<&'a [u8]>);
 impl<'a> StepFile<'a> {
     /// Parses a STEP file from a raw array of bytes
     /// `data` must be preprocessed by [`strip_flatten`] first
-    pub fn parse(data: &'a [u8]) -> Self {
-        let blocks = Self::into_blocks(data);
+    ///
+    /// Individual entities which fail to parse are recorded as
+    /// [`Entity::_FailedToParse`]; an error is only returned if the file
+    /// can't be split into entities at all.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        Self::parse_with(data, &ParseOptions::default())
+    }
+
+    /// Parses a STEP file, as in [`parse`](Self::parse), with extra options
+    pub fn parse_with(data: &'a [u8], opts: &ParseOptions) -> Result<Self, Error> {
+        let blocks = Self::into_blocks(data)?;
         let data_start = blocks.iter()
             .position(|b| b == b"DATA;")
             .unwrap_or(0) + 1;
@@ -29,72 +50,129 @@ impl<'a> StepFile<'a> {
         // single-threaded mode in WASM builds, because there's no thread
         // pool.
         let block_iter = {
-            let block_slice = &blocks[data_start..data_end];
+            let block_slice = blocks.get(data_start..data_end).unwrap_or(&[]);
             #[cfg(feature = "rayon")]
             { block_slice.par_iter() }
             #[cfg(not(feature = "rayon"))]
             { block_slice.iter() }
         };
 
-        let parsed: Vec<(usize, Entity)> = block_iter
+        let parsed: Vec<(usize, Entity, &[u8])> = block_iter
             .filter_map(|b| parse_entity_decl(b)
                 .or_else(|e| {
-                    warn!("Failed to parse {}: {:?}",
+                    warn!("Failed to parse {}: {}",
                         std::str::from_utf8(b).unwrap_or("[INVALID UTF-8]"),
                               e);
-                    parse_entity_fallback(b)
+                    parse_entity_fallback(b).map(|(_, r)| r)
                 })
-                .ok())
-            .map(|b| b.1)
+                .ok()
+                .map(|(i, e)| (i, e, *b)))
             .collect();
 
         // Awkward construction because `Entity` is not `Clone`
         let max_id = parsed.iter().map(|b| b.0).max().unwrap_or(0);
+
+        // Entities are stored densely by id, so a huge id would mean a huge
+        // allocation.  Every entity takes up at least one byte, so ids in a
+        // reasonably-numbered file are never larger than the file itself.
+        if max_id > data.len() {
+            return Err(Error::IdTooLarge(max_id));
+        }
         let mut out: Vec<Entity> = (0..=max_id)
             .map(|_| Entity::_EmptySlot)
             .collect();
 
+        let mut source = vec![];
+        if opts.retain_source {
+            source.resize(out.len(), &data[..0]);
+        }
         for p in parsed.into_iter() {
             out[p.0] = p.1;
+            if opts.retain_source {
+                source[p.0] = p.2;
+            }
         }
 
-        Self(out)
+        Ok(Self(out, source))
+    }
+
+    /// Returns the record which defined an entity, e.g.
+    /// `#12=VERTEX_POINT('',#11);`, with whitespace and comments stripped.
+    ///
+    /// Returns `None` if the file was parsed without
+    /// [`ParseOptions::retain_source`], or if there's no such entity.
+    pub fn source_text(&self, id: usize) -> Option<&'a str> {
+        self.1.get(id)
+            .filter(|b| !b.is_empty())
+            .and_then(|b| std::str::from_utf8(b).ok())
+    }
+
+    /// Parses a STEP file, as in [`parse`](Self::parse)
+    ///
+    /// # Panics
+    /// If the file can't be split into entities
+    pub fn parse_unchecked(data: &'a [u8]) -> Self {
+        Self::parse(data).expect("Could not parse STEP file")
     }
 
     /// Flattens a STEP file, removing comments and whitespace
+    ///
+    /// With the `rayon` feature, large files are split into chunks which are
+    /// flattened in parallel; the result is identical to
+    /// [`strip_flatten_serial`](Self::strip_flatten_serial).
     pub fn strip_flatten(data: &[u8]) -> Vec<u8> {
-        let mut out = Vec::with_capacity(data.len());
-        let mut i = 0;
-        while i < data.len() {
-            match data[i] {
-                b'/' => if i + 1 < data.len() && data[i + 1] == b'*' {
-                    for j in memchr_iter(b'/', &data[i + 2..]) {
-                        if data[i + j + 1] == b'*' {
-                            i += j + 2;
-                            break;
-                        }
-                    }
-                }
-                // TODO: don't skip whitespace inside of strings
-                c if c.is_ascii_whitespace() => (),
-                c => out.push(c),
+        #[cfg(feature = "rayon")]
+        {
+            let threads = rayon::current_num_threads();
+            if threads > 1 && data.len() >= PARALLEL_THRESHOLD {
+                let chunk = (data.len() / (4 * threads)).max(MIN_CHUNK_SIZE);
+                return strip_flatten_chunked(data, chunk);
             }
-            i += 1;
         }
+        Self::strip_flatten_serial(data)
+    }
+
+    /// Flattens a STEP file on a single thread, removing comments and
+    /// whitespace
+    pub fn strip_flatten_serial(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len());
+        flatten_into(data, &mut out);
         out
     }
 
+    /// Uppercases everything outside of strings, so that files written
+    /// with lowercase keywords (which Part 21 doesn't allow, but some
+    /// writers produce) can be parsed.  This should be run after
+    /// [`strip_flatten`](Self::strip_flatten).
+    pub fn uppercase_keywords(data: &mut [u8]) {
+        let mut quoted = false;
+        for c in data.iter_mut() {
+            if *c == b'\'' {
+                quoted = !quoted;
+            } else if !quoted {
+                c.make_ascii_uppercase();
+            }
+        }
+    }
+
     /// Splits a STEP file into individual blocks.  The input must be pre-processed
     /// by [`strip_flatten`] beforehand.
-    fn into_blocks(data: &[u8]) -> Vec<&[u8]> {
+    fn into_blocks(data: &[u8]) -> Result<Vec<&[u8]>, Error> {
         let mut blocks = Vec::new();
         let mut i = 0;
         let mut start = 0;
         while i < data.len() {
-            let next = memchr2(b'\'', b';', &data[i..]).unwrap();
+            let next = memchr2(b'\'', b';', &data[i..])
+                .ok_or(Error::MissingSemicolon(start))?;
             match data[i + next] {
-                // Skip over quoted blocks
-                b'\'' => i += next + memchr(b'\'', &data[i + next..]).unwrap() + 1,
+                // Skip over quoted blocks.  Escaped quotes ('') are handled
+                // as two adjacent strings.
+                b'\'' => {
+                    let quote = i + next;
+                    let end = memchr(b'\'', &data[quote + 1..])
+                        .ok_or(Error::UnterminatedString(quote))?;
+                    i = quote + end + 2;
+                },
                 b';' => {
                     blocks.push(&data[start..=(i + next)]);
 
@@ -104,11 +182,13 @@ impl<'a> StepFile<'a> {
                 _ => unreachable!(),
             }
         }
-        blocks
+        Ok(blocks)
     }
 
+    /// Looks up an entity of a particular type, returning `None` if the id is
+    /// out of range or refers to an entity of a different type
     pub fn entity<T: FromEntity<'a>>(&'a self, i: Id<T>) -> Option<&'a T> {
-        T::try_from_entity(&self.0[i.0])
+        self.0.get(i.0).and_then(T::try_from_entity)
     }
 }
 
@@ -123,3 +203,288 @@ impl<'a, T> std::ops::Index<Id<T>> for StepFile<'a> {
 pub trait FromEntity<'a> {
     fn try_from_entity(e: &'a Entity<'a>) -> Option<&'a Self>;
 }
+
+/// Files smaller than this are flattened on a single thread
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD: usize = 4 << 20;
+
+/// Smallest chunk which is handed to a worker thread
+#[cfg(feature = "rayon")]
+const MIN_CHUNK_SIZE: usize = 1 << 20;
+
+/// Given the index of the '/' which opens a comment, returns the index of the
+/// closing '/' (which can't share its '*' with the opening "/*"), or the end
+/// of the data if the comment is never closed.
+fn comment_end(data: &[u8], start: usize) -> usize {
+    memchr_iter(b'/', &data[start + 2..])
+        .map(|j| start + 2 + j)
+        .find(|&k| k > start + 2 && data[k - 1] == b'*')
+        .unwrap_or(data.len())
+}
+
+/// Appends `data` to `out`, skipping comments, whitespace, and stray slashes.
+/// This isn't aware of strings, so those are also removed within them.
+fn flatten_into(data: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < data.len() {
+        // Copy everything up to the next newline, space, or slash in one go.
+        // Other whitespace is rare, so we check for it within each run
+        // rather than stopping at it.
+        let end = memchr3(b'\n', b' ', b'/', &data[i..])
+            .map_or(data.len(), |j| i + j);
+        let run = &data[i..end];
+        if memchr3(b'\r', b'\t', b'\x0c', run).is_some() {
+            out.extend(run.iter().filter(|c| !c.is_ascii_whitespace()));
+        } else {
+            out.extend_from_slice(run);
+        }
+
+        i = end;
+        if i < data.len() {
+            // Slashes are dropped, whether or not they open a comment
+            if data[i] == b'/' && data.get(i + 1) == Some(&b'*') {
+                i = comment_end(data, i);
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Returns chunk boundaries, which start with 0, end with `data.len()`, and
+/// are otherwise just after the first semicolon past each multiple of
+/// `chunk` which isn't inside a comment.  Since flattening only has state
+/// within comments, each chunk can then be flattened independently.
+#[cfg(feature = "rayon")]
+fn chunk_boundaries(data: &[u8], chunk: usize) -> Vec<usize> {
+    // Pre-scan for comments, which are rare, using the same rules as
+    // `flatten_into`
+    let mut comments = vec![];
+    let mut i = 0;
+    while let Some(j) = memchr(b'/', &data[i..]) {
+        let start = i + j;
+        if data.get(start + 1) == Some(&b'*') {
+            let end = comment_end(data, start);
+            comments.push(start..end + 1);
+            i = end + 1;
+        } else {
+            i = start + 1;
+        }
+        if i >= data.len() {
+            break;
+        }
+    }
+
+    let mut out = vec![0];
+    let mut i = chunk;
+    while i < data.len() {
+        let Some(j) = memchr(b';', &data[i..]) else { break };
+        let semi = i + j;
+
+        // If this semicolon is commented out, keep looking after the comment
+        let c = comments.partition_point(|c| c.end <= semi);
+        if comments.get(c).is_some_and(|c| c.contains(&semi)) {
+            i = comments[c].end;
+            continue;
+        }
+        out.push(semi + 1);
+        i = semi + 1 + chunk;
+    }
+    if *out.last().unwrap() < data.len() {
+        out.push(data.len());
+    }
+    out
+}
+
+/// Flattens a file by splitting it into chunks of roughly `chunk` bytes, then
+/// flattening each chunk in parallel
+#[cfg(feature = "rayon")]
+fn strip_flatten_chunked(data: &[u8], chunk: usize) -> Vec<u8> {
+    let bounds = chunk_boundaries(data, chunk);
+    let parts: Vec<Vec<u8>> = bounds
+        .par_windows(2)
+        .map(|w| {
+            let mut out = Vec::with_capacity(w[1] - w[0]);
+            flatten_into(&data[w[0]..w[1]], &mut out);
+            out
+        })
+        .collect();
+    parts.concat()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &[u8]) -> Result<usize, Error> {
+        let flat = StepFile::strip_flatten(data);
+        StepFile::parse(&flat).map(|s| s.0.len())
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = b"ISO-10303-21;HEADER;ENDSEC;DATA;
+            #1=CARTESIAN_POINT('a;b',(0.,0.,0.));
+            #2=CARTESIAN_POINT('it''s',(1.,0.,0.));
+            ENDSEC;END-ISO-10303-21;";
+        let flat = StepFile::strip_flatten(data);
+        let step = StepFile::parse(&flat).unwrap();
+        assert_eq!(step.0.len(), 3);
+        assert!(matches!(step.0[1], Entity::CartesianPoint(..)));
+        // The entity parser doesn't understand escaped quotes, but the block
+        // is still split correctly (rather than swallowing the next entity)
+        assert!(!matches!(step.0[2], Entity::_EmptySlot));
+
+        // Out-of-range ids are missing, rather than panicking
+        let id: Id<crate::ap214::CartesianPoint_> = Id::new(12);
+        assert!(step.entity(id).is_none());
+    }
+
+    #[test]
+    fn test_source_text() {
+        let data = b"ISO-10303-21;HEADER;ENDSEC;DATA;
+            #1=CARTESIAN_POINT('a;b',(0.,0.,0.));
+            /* a comment */
+            #3 = VERTEX_POINT('', #1);
+            #4=NOT_AN_ENTITY(1);
+            ENDSEC;END-ISO-10303-21;";
+        let flat = StepFile::strip_flatten(data);
+        let opts = ParseOptions { retain_source: true };
+        let step = StepFile::parse_with(&flat, &opts).unwrap();
+        assert_eq!(step.source_text(1),
+                   Some("#1=CARTESIAN_POINT('a;b',(0.,0.,0.));"));
+        assert_eq!(step.source_text(3), Some("#3=VERTEX_POINT('',#1);"));
+        assert_eq!(step.source_text(4), Some("#4=NOT_AN_ENTITY(1);"));
+        assert_eq!(step.source_text(2), None);
+        assert_eq!(step.source_text(5), None);
+
+        // Every record parses back into the same entity
+        for (i, e) in step.0.iter().enumerate().skip(1) {
+            if let Some(text) = step.source_text(i) {
+                let wrapped = format!("DATA;{}ENDSEC;", text);
+                let again = StepFile::parse(wrapped.as_bytes()).unwrap();
+                assert_eq!(format!("{:?}", again.0[i]), format!("{:?}", e));
+            }
+        }
+
+        // Nothing is kept by default
+        let step = StepFile::parse(&flat).unwrap();
+        assert_eq!(step.source_text(1), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse(b"ISO-10303-21;DATA;#1=CARTESIAN_POINT('abc,(0.,0.,0.));"),
+            Err(Error::UnterminatedString(37)));
+        assert_eq!(
+            parse(b"ISO-10303-21;DATA;#1=CARTESIAN_POINT('',(0.,0.,0.))"),
+            Err(Error::MissingSemicolon(18)));
+        assert_eq!(parse(b""), Ok(1));
+    }
+
+    #[test]
+    fn test_fuzz_regressions() {
+        // A direction with too many components
+        let flat = StepFile::strip_flatten(b"DATA;#1=DIRECTION('',(0.,0.,1.,0.,0.));ENDSEC;");
+        let step = StepFile::parse(&flat).unwrap();
+        assert!(matches!(step.0[1], Entity::_FailedToParse("DIRECTION('',(0.,0.,1.,0.,0.))")));
+
+        // An id which would need a huge allocation
+        assert_eq!(
+            parse(b"DATA;#99999999999=CARTESIAN_POINT('',(0.,0.,0.));ENDSEC;"),
+            Err(Error::IdTooLarge(99999999999)));
+
+        // Comments which are never closed, or which look closed too early
+        assert_eq!(StepFile::strip_flatten(b"a/*"), b"a");
+        assert_eq!(StepFile::strip_flatten(b"a/*/b*/c"), b"ac");
+        assert_eq!(StepFile::strip_flatten(b"a/**/c"), b"ac");
+    }
+
+    /// The original byte-by-byte implementation of `strip_flatten`, which the
+    /// faster versions must match exactly
+    fn reference(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len());
+        let mut i = 0;
+        while i < data.len() {
+            match data[i] {
+                b'/' => if i + 1 < data.len() && data[i + 1] == b'*' {
+                    i = memchr_iter(b'/', &data[i + 2..])
+                        .map(|j| i + 2 + j)
+                        .find(|&k| k > i + 2 && data[k - 1] == b'*')
+                        .unwrap_or(data.len());
+                }
+                c if c.is_ascii_whitespace() => (),
+                c => out.push(c),
+            }
+            i += 1;
+        }
+        out
+    }
+
+    /// Checks that every implementation matches the reference, splitting the
+    /// data into chunks of many different sizes
+    fn check_flatten(data: &[u8]) {
+        let expected = reference(data);
+        assert_eq!(StepFile::strip_flatten_serial(data), expected,
+                   "{:?}", String::from_utf8_lossy(data));
+        assert_eq!(StepFile::strip_flatten(data), expected);
+        #[cfg(feature = "rayon")]
+        for chunk in 1..=data.len().min(40) {
+            let bounds = chunk_boundaries(data, chunk);
+            assert_eq!(bounds[0], 0);
+            assert_eq!(*bounds.last().unwrap(), data.len());
+            assert!(bounds.windows(2).all(|w| w[0] < w[1]), "{:?}", bounds);
+            assert_eq!(strip_flatten_chunked(data, chunk), expected,
+                       "{:?} with chunk {}",
+                       String::from_utf8_lossy(data), chunk);
+        }
+    }
+
+    #[test]
+    fn test_flatten_tricky() {
+        for data in [
+            &b""[..],
+            b";",
+            b"/",
+            b"/*",
+            b"a;/",
+            b"a;/*",
+            b"a/*/b*/c",
+            b"a/**/c",
+            b"a/**//*b*/c;d",
+            b"#1=A(';/*');\n#2=B('*/;');\n",
+            b"#1=A('a b\tc');\r\n/* ; ; */#2=B(1.,\x0c2.);\r\n",
+            b"#1=A(1.);/* unterminated ; comment\n#2=B(2.);",
+            b"#1=A(1.);/*;*/;/*/;*/#2=B('it''s; fine');  /x;//**/",
+        ] {
+            check_flatten(data);
+        }
+        check_flatten(crate::fixture::filleted_block(10.0, 2.0).to_step()
+            .as_bytes());
+    }
+
+    #[test]
+    fn test_uppercase_keywords() {
+        let mut data = b"#1=cartesian_point('it''s a point',(1.e0,2.,3.));".to_vec();
+        StepFile::uppercase_keywords(&mut data);
+        assert_eq!(&data[..], &b"#1=CARTESIAN_POINT('it''s a point',(1.E0,2.,3.));"[..]);
+    }
+
+    #[test]
+    fn test_flatten_random() {
+        // Random soup of the bytes which matter for flattening, generated
+        // with a fixed LCG so the test is reproducible
+        let alphabet = b"ab;/*'\n\r\t \x0c";
+        let mut seed = 12345u64;
+        for len in 0..200 {
+            let data: Vec<u8> = (0..len).map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                alphabet[(seed >> 33) as usize % alphabet.len()]
+            }).collect();
+            check_flatten(&data);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Physical quantities, for displaying measurements.
+//!
+//! Values are stored in SI units (metres, square metres, or cubic metres),
+//! so converting from a model's own units has to scale areas and volumes by
+//! the square and cube of its length factor.
+use std::fmt;
+
+/// Number of significant digits shown by default (whole numbers keep all of
+/// their digits)
+const SIGNIFICANT: usize = 4;
+
+/// What a [`Quantity`] measures
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Dimension {
+    Length,
+    Area,
+    Volume,
+}
+
+impl Dimension {
+    /// Returns the power of length, e.g. 2 for areas
+    pub fn power(&self) -> i32 {
+        match self {
+            Dimension::Length => 1,
+            Dimension::Area => 2,
+            Dimension::Volume => 3,
+        }
+    }
+}
+
+/// A length, area, or volume, stored in SI units.
+///
+/// Its `Display` implementation picks whichever of µm, mm, or m suits the
+/// value, with four significant digits (or as many as the precision asks
+/// for), e.g. "12.5 mm²".  The alternate form (`{:#}`) only uses ASCII, e.g.
+/// "12.5 mm^2", for fonts without the extra characters.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub dimension: Dimension,
+}
+
+/// Units which are picked from when displaying a quantity, as their length
+/// in metres and their symbols (with and without non-ASCII characters)
+const UNITS: [(f64, &str, &str); 3] = [(1e-6, "µm", "um"), (1e-3, "mm", "mm"), (1.0, "m", "m")];
+
+impl Quantity {
+    pub fn length(metres: f64) -> Self {
+        Self {
+            value: metres,
+            dimension: Dimension::Length,
+        }
+    }
+
+    pub fn area(square_metres: f64) -> Self {
+        Self {
+            value: square_metres,
+            dimension: Dimension::Area,
+        }
+    }
+
+    pub fn volume(cubic_metres: f64) -> Self {
+        Self {
+            value: cubic_metres,
+            dimension: Dimension::Volume,
+        }
+    }
+
+    /// Converts a value in a model's units, where one unit of length is
+    /// `metres` long (e.g. 0.0254 for a model in inches)
+    pub fn from_model(value: f64, dimension: Dimension, metres: f64) -> Self {
+        Self {
+            value: value * metres.powi(dimension.power()),
+            dimension,
+        }
+    }
+
+    /// Converts back into a model's units, where one unit of length is
+    /// `metres` long
+    pub fn to_model(&self, metres: f64) -> f64 {
+        self.value / metres.powi(self.dimension.power())
+    }
+
+    /// Returns the index into [`UNITS`] of the unit used to display this
+    /// quantity, once it's rounded to `digits` significant digits:  the
+    /// largest unit in which the value is at least one.  Micrometres are
+    /// only used for lengths, since tiny areas and volumes read better as
+    /// fractions of mm² or mm³ than as millions of µm² or µm³.
+    fn unit(&self, digits: usize) -> usize {
+        let rounded = round(self.value.abs(), digits);
+        let smallest = match self.dimension {
+            Dimension::Length => 0,
+            _ => 1,
+        };
+        UNITS
+            .iter()
+            .rposition(|(m, _, _)| rounded >= m.powi(self.dimension.power()) * (1.0 - 1e-9))
+            .unwrap_or(0)
+            .max(smallest)
+    }
+}
+
+/// Rounds a number to some significant digits
+fn round(x: f64, digits: usize) -> f64 {
+    if x == 0.0 || !x.is_finite() {
+        return x;
+    }
+    let scale = 10f64.powi(digits as i32 - 1 - x.abs().log10().floor() as i32);
+    (x * scale).round() / scale
+}
+
+/// Formats a number to some significant digits, without trailing zeros
+fn number(x: f64, digits: usize) -> String {
+    if x == 0.0 || !x.is_finite() {
+        return format!("{}", x);
+    }
+    let decimals = (digits as i32 - 1 - x.abs().log10().floor() as i32).max(0);
+    let s = format!("{:.*}", decimals as usize, x);
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_owned()
+    } else {
+        s
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = f.precision().unwrap_or(SIGNIFICANT).max(1);
+        let power = self.dimension.power();
+        let (metres, symbol, ascii) = if self.value.is_finite() && self.value != 0.0 {
+            UNITS[self.unit(digits)]
+        } else {
+            UNITS[1]
+        };
+        let value = self.value / metres.powi(power);
+        let symbol = if f.alternate() { ascii } else { symbol };
+        let exponent = match (power, f.alternate()) {
+            (1, _) => "",
+            (2, false) => "²",
+            (2, true) => "^2",
+            (_, false) => "³",
+            (_, true) => "^3",
+        };
+        write!(f, "{} {}{}", number(value, digits), symbol, exponent)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lengths() {
+        assert_eq!(Quantity::length(0.0125).to_string(), "12.5 mm");
+        assert_eq!(Quantity::length(2.5).to_string(), "2.5 m");
+        assert_eq!(Quantity::length(2.5e-5).to_string(), "25 µm");
+        assert_eq!(format!("{:#}", Quantity::length(2.5e-5)), "25 um");
+        assert_eq!(Quantity::length(-0.004).to_string(), "-4 mm");
+        assert_eq!(Quantity::length(0.0).to_string(), "0 mm");
+        assert_eq!(Quantity::length(1234.5678).to_string(), "1235 m");
+    }
+
+    #[test]
+    fn test_boundaries() {
+        // Values which round up to the next unit are shown in it
+        assert_eq!(Quantity::length(0.9999).to_string(), "999.9 mm");
+        assert_eq!(format!("{:.3}", Quantity::length(0.9999)), "1 m");
+        assert_eq!(Quantity::length(0.99999).to_string(), "1 m");
+        assert_eq!(Quantity::length(1.0).to_string(), "1 m");
+        assert_eq!(Quantity::length(0.001).to_string(), "1 mm");
+        assert_eq!(Quantity::length(0.00099999).to_string(), "1 mm");
+        assert_eq!(Quantity::length(0.0009999).to_string(), "999.9 µm");
+
+        // Areas and volumes switch at the same sizes as lengths
+        assert_eq!(Quantity::area(0.01).to_string(), "10000 mm²");
+        assert_eq!(Quantity::area(1.0).to_string(), "1 m²");
+        assert_eq!(format!("{:#}", Quantity::area(0.99999999)), "1 m^2");
+        assert_eq!(format!("{:#}", Quantity::volume(1e-9)), "1 mm^3");
+        assert_eq!(format!("{:#}", Quantity::volume(0.5e-9)), "0.5 mm^3");
+        assert_eq!(Quantity::area(2.5e-13).to_string(), "0.00000025 mm²");
+    }
+
+    #[test]
+    fn test_model_units() {
+        // Millimetres
+        let q = Quantity::from_model(25.0, Dimension::Area, 1e-3);
+        assert_eq!(q.to_string(), "25 mm²");
+        assert!((q.value - 25e-6).abs() < 1e-18);
+
+        // Inches, where areas and volumes scale with the square and cube
+        let inch = 0.0254;
+        let length = Quantity::from_model(1.0, Dimension::Length, inch);
+        let area = Quantity::from_model(1.0, Dimension::Area, inch);
+        let volume = Quantity::from_model(1.0, Dimension::Volume, inch);
+        assert_eq!(length.to_string(), "25.4 mm");
+        assert_eq!(area.to_string(), "645.2 mm²");
+        assert_eq!(volume.to_string(), "16387 mm³");
+        assert!((area.value - inch * inch).abs() < 1e-15);
+        assert!((volume.value - inch * inch * inch).abs() < 1e-18);
+
+        // Converting back gives the original values
+        for q in [length, area, volume] {
+            assert!((q.to_model(inch) - 1.0).abs() < 1e-12);
+        }
+        let q = Quantity::volume(1.0);
+        assert!((q.to_model(1e-3) - 1e9).abs() < 1e-3);
+    }
+}
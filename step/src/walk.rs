@@ -0,0 +1,246 @@
+//! Guarded traversal of the entity graph.
+//!
+//! References between entities should form a DAG, but a corrupt (or
+//! malicious) file can contain cycles, or chains of references which are
+//! long enough to overflow the stack if they're followed recursively.
+//! [`GraphWalker`] walks iteratively, skipping references which would close
+//! a cycle and giving up once it's gone too deep or visited too many nodes,
+//! so that callers get an error rather than hanging or crashing.
+use std::collections::{HashMap, HashSet};
+
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum WalkError {
+    #[error("Reference cycle through #{0}")]
+    CycleDetected(usize),
+
+    #[error("Traversal budget exceeded at #{0}")]
+    BudgetExceeded(usize),
+}
+
+impl WalkError {
+    /// Returns the entity at which the walk went wrong
+    pub fn id(&self) -> usize {
+        match self {
+            WalkError::CycleDetected(i) | WalkError::BudgetExceeded(i) => *i,
+        }
+    }
+}
+
+/// Limits for a traversal of the entity graph
+#[derive(Copy, Clone, Debug)]
+pub struct GraphWalker {
+    /// Longest chain of references which is followed from a root
+    pub max_depth: usize,
+    /// Largest number of nodes which are visited in total
+    pub max_nodes: usize,
+}
+
+impl Default for GraphWalker {
+    fn default() -> Self {
+        Self { max_depth: 1024, max_nodes: 1 << 24 }
+    }
+}
+
+/// A node whose children are being walked, with the remaining children in
+/// reverse order
+struct Frame<T> {
+    node: usize,
+    children: Vec<T>,
+}
+
+impl<T> Frame<T> {
+    fn new<I: IntoIterator<Item=T>>(node: usize, children: I) -> Self {
+        let mut children: Vec<T> = children.into_iter().collect();
+        children.reverse();
+        Self { node, children }
+    }
+}
+
+impl GraphWalker {
+    /// Walks depth-first from each root, calling `visit` on every node the
+    /// first time that it's reached (before its children), with children
+    /// found by calling `children`.
+    ///
+    /// References which lead back to a node on the current path are
+    /// skipped, and the first of them is returned as
+    /// [`WalkError::CycleDetected`] once the walk is done.  If the budget
+    /// runs out, the walk stops immediately with
+    /// [`WalkError::BudgetExceeded`].
+    pub fn walk<R, C, I, V>(&self, roots: R, mut children: C, mut visit: V)
+        -> Result<(), WalkError>
+        where R: IntoIterator<Item=usize>,
+              C: FnMut(usize) -> I,
+              I: IntoIterator<Item=usize>,
+              V: FnMut(usize),
+    {
+        // `true` while a node is on the current path, then `false` once all
+        // of its children are done
+        let mut on_path: HashMap<usize, bool> = HashMap::new();
+        let mut stack: Vec<Frame<usize>> = vec![];
+        let mut cycle = None;
+        for root in roots {
+            let mut next = Some(root);
+            loop {
+                if let Some(n) = next.take() {
+                    match on_path.get(&n) {
+                        Some(true) => { cycle = cycle.or(Some(n)); }
+                        Some(false) => (),
+                        None => {
+                            self.check(n, stack.len(), on_path.len())?;
+                            on_path.insert(n, true);
+                            visit(n);
+                            stack.push(Frame::new(n, children(n)));
+                        }
+                    }
+                }
+                let Some(top) = stack.last_mut() else { break };
+                next = top.children.pop();
+                if next.is_none() {
+                    on_path.insert(top.node, false);
+                    stack.pop();
+                }
+            }
+        }
+        cycle.map_or(Ok(()), |c| Err(WalkError::CycleDetected(c)))
+    }
+
+    /// Returns every node which is reachable from the roots (including the
+    /// roots themselves), in the order that [`walk`](Self::walk) visits
+    /// them, along with the first cycle (if any)
+    pub fn reachable<R, C, I>(&self, roots: R, children: C)
+        -> (Vec<usize>, Result<(), WalkError>)
+        where R: IntoIterator<Item=usize>,
+              C: FnMut(usize) -> I,
+              I: IntoIterator<Item=usize>,
+    {
+        let mut out = vec![];
+        let r = self.walk(roots, children, |n| out.push(n));
+        (out, r)
+    }
+
+    /// Walks every path from the roots, rather than every node, so that a
+    /// node which is reachable in several ways is visited once per path.
+    /// Each path carries a value (e.g. a transform), which is passed to
+    /// `visit` and used to build the values of its children.
+    ///
+    /// Cycles and budgets are handled as in [`walk`](Self::walk); since
+    /// nodes are visited more than once, the node budget also guards
+    /// against an exponential number of paths.
+    pub fn walk_paths<T, R, C, I, V>(&self, roots: R, mut children: C,
+                                     mut visit: V) -> Result<(), WalkError>
+        where R: IntoIterator<Item=(usize, T)>,
+              C: FnMut(usize, &T) -> I,
+              I: IntoIterator<Item=(usize, T)>,
+              V: FnMut(usize, &T),
+    {
+        let mut path: HashSet<usize> = HashSet::new();
+        let mut stack: Vec<Frame<(usize, T)>> = vec![];
+        let mut visited = 0;
+        let mut cycle = None;
+        for root in roots {
+            let mut next = Some(root);
+            loop {
+                if let Some((n, t)) = next.take() {
+                    if path.contains(&n) {
+                        cycle = cycle.or(Some(n));
+                    } else {
+                        self.check(n, stack.len(), visited)?;
+                        visited += 1;
+                        path.insert(n);
+                        visit(n, &t);
+                        stack.push(Frame::new(n, children(n, &t)));
+                    }
+                }
+                let Some(top) = stack.last_mut() else { break };
+                next = top.children.pop();
+                if next.is_none() {
+                    path.remove(&top.node);
+                    stack.pop();
+                }
+            }
+        }
+        cycle.map_or(Ok(()), |c| Err(WalkError::CycleDetected(c)))
+    }
+
+    /// Checks whether a node at the given depth, after `visited` others,
+    /// is within budget
+    fn check(&self, node: usize, depth: usize, visited: usize)
+        -> Result<(), WalkError>
+    {
+        if depth >= self.max_depth || visited >= self.max_nodes {
+            Err(WalkError::BudgetExceeded(node))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a `children` function from an adjacency list
+    fn graph(edges: &[(usize, usize)]) -> impl Fn(usize) -> Vec<usize> + '_ {
+        move |n| edges.iter().filter(|e| e.0 == n).map(|e| e.1).collect()
+    }
+
+    #[test]
+    fn test_walk() {
+        // A diamond, whose bottom is only visited once
+        let edges = [(1, 2), (1, 3), (2, 4), (3, 4), (4, 5)];
+        let w = GraphWalker::default();
+        assert_eq!(w.reachable([1], graph(&edges)), (vec![1, 2, 4, 5, 3], Ok(())));
+        assert_eq!(w.reachable([3, 1], graph(&edges)), (vec![3, 4, 5, 1, 2], Ok(())));
+    }
+
+    #[test]
+    fn test_cycle() {
+        // The cycle is skipped, but everything else is still visited
+        let edges = [(1, 2), (2, 3), (3, 2), (1, 4)];
+        let w = GraphWalker::default();
+        assert_eq!(w.reachable([1], graph(&edges)),
+                   (vec![1, 2, 3, 4], Err(WalkError::CycleDetected(2))));
+
+        // A node which refers to itself
+        assert_eq!(w.reachable([7], graph(&[(7, 7)])),
+                   (vec![7], Err(WalkError::CycleDetected(7))));
+
+        // Paths are cut off at the cycle, rather than looping forever
+        let mut seen = vec![];
+        let r = w.walk_paths([(1, 0)], |n, d| {
+                                 let d = *d;
+                                 graph(&edges)(n).into_iter().map(move |c| (c, d + 1))
+                             },
+                             |n, d| seen.push((n, *d)));
+        assert_eq!(r, Err(WalkError::CycleDetected(2)));
+        assert_eq!(seen, [(1, 0), (2, 1), (3, 2), (4, 1)]);
+    }
+
+    #[test]
+    fn test_budget() {
+        // A long chain, which would overflow the stack if walked recursively
+        let n = 200_000;
+        let chain = |i: usize| (i < n).then_some(i + 1);
+        let w = GraphWalker { max_depth: usize::MAX, max_nodes: usize::MAX };
+        let (out, r) = w.reachable([0], chain);
+        assert_eq!((out.len(), r), (n + 1, Ok(())));
+
+        let w = GraphWalker { max_depth: 10, ..GraphWalker::default() };
+        let (out, r) = w.reachable([0], chain);
+        assert_eq!((out.len(), r), (10, Err(WalkError::BudgetExceeded(10))));
+
+        // Every path through a ladder of diamonds is visited, which is
+        // exponential in its height
+        let ladder = |i: usize| if i < 60 { vec![i + 1, i + 1] } else { vec![] };
+        let w = GraphWalker { max_nodes: 1000, ..GraphWalker::default() };
+        let mut count = 0;
+        assert_eq!(w.walk_paths([(0, ())], |i, _| ladder(i).into_iter()
+                                    .map(|c| (c, ())),
+                                |_, _| count += 1),
+                   Err(WalkError::BudgetExceeded(60)));
+        assert_eq!(count, 1000);
+        assert_eq!(w.reachable([0], ladder).0.len(), 61);
+    }
+}
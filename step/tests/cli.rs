@@ -0,0 +1,78 @@
+//! Runs the `step diff` subcommand against generated fixtures
+#![cfg(feature = "cli")]
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use step::fixture;
+
+fn write(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("step-cli-{}-{}", std::process::id(), name));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+fn run(args: &[&OsStr]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_step"))
+        .arg("diff")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_diff() {
+    let a = fixture::cube(1.0).to_step();
+    let b = a
+        .replacen(
+            "=CARTESIAN_POINT('',(0.0,0.0,0.0));",
+            "=CARTESIAN_POINT('',(0.0,0.0,-0.5));",
+            1,
+        )
+        .replace("PRODUCT('cube','cube'", "PRODUCT('cube','block'");
+    assert_ne!(a, b);
+    let (pa, pb) = (write("a.step", &a), write("b.step", &b));
+
+    // Identical files exit successfully
+    let out = run(&[pa.as_os_str(), pa.as_os_str()]);
+    assert_eq!(out.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&out.stdout).starts_with("No differences"));
+
+    let out = run(&[pa.as_os_str(), pb.as_os_str()]);
+    assert_eq!(out.status.code(), Some(1));
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.starts_with("2 modified"), "{}", text);
+    assert!(text.contains("CartesianPoint: 1 modified"), "{}", text);
+    assert!(text.contains("Product: 1 modified"), "{}", text);
+
+    let out = run(&[pa.as_os_str(), pb.as_os_str(), OsStr::new("--json")]);
+    assert_eq!(out.status.code(), Some(1));
+    let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(json["summary"]["modified"], 2);
+    assert_eq!(json["summary"]["added"], 0);
+    assert_eq!(json["summary"]["removed"], 0);
+    let changes = json["changes"].as_array().unwrap();
+    let types: Vec<_> = changes
+        .iter()
+        .map(|c| c["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, ["Product", "CartesianPoint"]);
+    assert!(changes.iter().all(|c| c["product"] == "block"));
+
+    // A loose enough tolerance hides the moved point
+    let out = run(&[
+        pa.as_os_str(),
+        pb.as_os_str(),
+        OsStr::new("--tolerance"),
+        OsStr::new("1"),
+    ]);
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.starts_with("1 modified"), "{}", text);
+
+    // Missing files are reported as errors
+    let out = run(&[pa.as_os_str(), OsStr::new("does-not-exist.step")]);
+    assert_eq!(out.status.code(), Some(2));
+
+    std::fs::remove_file(pa).unwrap();
+    std::fs::remove_file(pb).unwrap();
+}
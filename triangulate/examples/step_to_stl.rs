@@ -1,7 +1,6 @@
 use clap::{App, Arg};
 
-use step::step_file::StepFile;
-use triangulate::triangulate::triangulate;
+use triangulate::load::{load_mesh, TriangulateOptions};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
@@ -21,22 +20,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .get_matches();
     let input = matches.value_of("input").expect("Could not get input file");
 
-    let start = std::time::SystemTime::now();
-    let data = std::fs::read(input)?;
-    let flat = StepFile::strip_flatten(&data);
-    let entities = StepFile::parse(&flat);
-    let end = std::time::SystemTime::now();
-    let since_the_epoch = end.duration_since(start).expect("Time went backwards");
-    println!("Loaded + parsed in {:?}", since_the_epoch);
-
-    let start = std::time::SystemTime::now();
-    let tri = triangulate(&entities);
-    let end = std::time::SystemTime::now();
-    let since_the_epoch = end.duration_since(start).expect("Time went backwards");
-    println!("Triangulated in {:?}", since_the_epoch);
+    let (scene, report) = load_mesh(input, &TriangulateOptions::default())?;
+    for t in &report.timings {
+        println!("{:<14} {:?}", t.stage, t.duration);
+    }
+    if report.parse.failed > 0 {
+        println!(
+            "Skipped {} of {} entities",
+            report.parse.failed, report.parse.entities
+        );
+    }
 
     if let Some(o) = matches.value_of("output") {
-        tri.0.save_stl(o)?;
+        scene.mesh.save_stl(o)?;
     }
 
     Ok(())
@@ -0,0 +1,515 @@
+//! Bounding volume hierarchy over a mesh's triangles, for CPU ray-casting
+//! and closest-point queries (e.g. picking an orbit center or snapping a
+//! measurement without a GPU readback).
+//!
+//! The tree is built by splitting triangles at the median centroid along
+//! the longest axis of their bounds, and is stored as a flat list of nodes
+//! in depth-first order.
+use nalgebra_glm::DVec3;
+
+use crate::mesh::{Face, Mesh};
+
+#[cfg(feature = "rayon")]
+use rayon::join;
+
+/// Nodes with this many triangles or fewer become leaves
+const LEAF_SIZE: usize = 4;
+
+/// Subtrees with at least this many triangles are built in parallel
+#[cfg(feature = "rayon")]
+const PARALLEL_SIZE: usize = 4096;
+
+/// A node in the tree, covering the box from `lo` to `hi`.  Leaves hold
+/// `count` triangles starting at `index` in [`MeshBvh::triangles`];
+/// interior nodes (with `count == 0`) have their first child right after
+/// them and their second child at `index`.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+struct Node {
+    lo: DVec3,
+    hi: DVec3,
+    index: u32,
+    count: u32,
+}
+
+/// A triangle's vertex positions, copied out of the mesh so that queries
+/// don't need it
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+struct BvhTriangle {
+    verts: [DVec3; 3],
+    /// Index into [`Mesh::triangles`]
+    index: u32,
+}
+
+/// The result of a ray-cast or closest-point query
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    /// For ray-casts, the distance along the ray in multiples of its
+    /// direction; for closest-point queries, the distance to the point
+    pub t: f64,
+    pub point: DVec3,
+    /// Index into [`Mesh::triangles`]
+    pub triangle: usize,
+    /// Index of the STEP entity for the face which contains the triangle,
+    /// if the mesh has face information
+    pub face_id: Option<usize>,
+    /// Weights of the triangle's three vertices at `point`
+    pub barycentric: DVec3,
+}
+
+/// Acceleration structure for ray-casts and closest-point queries against
+/// a [`Mesh`], which is built by [`Mesh::build_bvh`]
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeshBvh {
+    nodes: Vec<Node>,
+    triangles: Vec<BvhTriangle>,
+    faces: Vec<Face>,
+}
+
+impl MeshBvh {
+    pub fn new(mesh: &Mesh) -> Self {
+        let mut triangles: Vec<BvhTriangle> = mesh
+            .triangles
+            .iter()
+            .enumerate()
+            .map(|(i, t)| BvhTriangle {
+                verts: [0, 1, 2].map(|j| mesh.verts[t.verts[j] as usize].pos),
+                index: i.try_into().expect("Too many triangles"),
+            })
+            .collect();
+        let nodes = if triangles.is_empty() {
+            vec![]
+        } else {
+            build(&mut triangles, 0)
+        };
+        MeshBvh {
+            nodes,
+            triangles,
+            faces: mesh.faces.clone(),
+        }
+    }
+
+    /// Returns the number of triangles in the tree
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    /// Returns the nearest triangle hit by a ray, at or after its origin.
+    /// `dir` doesn't need to be normalized; rays which lie in the plane of
+    /// a triangle don't hit it, though they can still hit its edges from
+    /// neighboring triangles.
+    pub fn raycast(&self, origin: DVec3, dir: DVec3) -> Option<Hit> {
+        let mut best: Option<(f64, &BvhTriangle, DVec3)> = None;
+        let mut stack = vec![];
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            let limit = best.map(|b| b.0).unwrap_or(f64::INFINITY);
+            match ray_box(origin, dir, node.lo, node.hi) {
+                Some(t) if t <= limit => (),
+                _ => continue,
+            }
+            if node.count > 0 {
+                let start = node.index as usize;
+                for tri in &self.triangles[start..start + node.count as usize] {
+                    if let Some((t, bary)) = ray_triangle(origin, dir, &tri.verts) {
+                        if best.map(|b| t < b.0).unwrap_or(true) {
+                            best = Some((t, tri, bary));
+                        }
+                    }
+                }
+            } else {
+                stack.push(node.index as usize);
+                stack.push(i + 1);
+            }
+        }
+        best.map(|(t, tri, barycentric)| self.hit(t, tri, barycentric))
+    }
+
+    /// Returns the point on the mesh which is closest to `p`, or `None` if
+    /// the mesh is empty
+    pub fn closest_point(&self, p: DVec3) -> Option<Hit> {
+        let mut best: Option<(f64, &BvhTriangle, DVec3)> = None;
+        let mut stack = vec![];
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            let limit = best.map(|b| b.0).unwrap_or(f64::INFINITY);
+            if box_distance_sq(p, node.lo, node.hi) > limit {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.index as usize;
+                for tri in &self.triangles[start..start + node.count as usize] {
+                    let bary = closest_on_triangle(p, &tri.verts);
+                    let d = (interpolate(&tri.verts, bary) - p).norm_squared();
+                    if best.map(|b| d < b.0).unwrap_or(true) {
+                        best = Some((d, tri, bary));
+                    }
+                }
+            } else {
+                // Visit the nearer child first, so that it tightens the
+                // limit before the other child is checked
+                let (a, b) = (i + 1, node.index as usize);
+                let da = box_distance_sq(p, self.nodes[a].lo, self.nodes[a].hi);
+                let db = box_distance_sq(p, self.nodes[b].lo, self.nodes[b].hi);
+                if da < db {
+                    stack.extend([b, a]);
+                } else {
+                    stack.extend([a, b]);
+                }
+            }
+        }
+        best.map(|(d, tri, barycentric)| self.hit(d.sqrt(), tri, barycentric))
+    }
+
+    fn hit(&self, t: f64, tri: &BvhTriangle, barycentric: DVec3) -> Hit {
+        let triangle = tri.index as usize;
+        let f = self.faces.partition_point(|f| f.triangles.end <= triangle);
+        let face_id = self
+            .faces
+            .get(f)
+            .filter(|f| f.triangles.contains(&triangle))
+            .map(|f| f.id);
+        Hit {
+            t,
+            point: interpolate(&tri.verts, barycentric),
+            triangle,
+            face_id,
+            barycentric,
+        }
+    }
+}
+
+/// Builds the subtree for a set of triangles, which start at `offset` in
+/// the final triangle list.  Child indices are relative to the subtree's
+/// first node.
+fn build(tris: &mut [BvhTriangle], offset: usize) -> Vec<Node> {
+    let (mut lo, mut hi) = (DVec3::repeat(f64::INFINITY), DVec3::repeat(-f64::INFINITY));
+    let (mut clo, mut chi) = (lo, hi);
+    for t in tris.iter() {
+        for v in &t.verts {
+            lo = lo.inf(v);
+            hi = hi.sup(v);
+        }
+        let c = centroid(t);
+        clo = clo.inf(&c);
+        chi = chi.sup(&c);
+    }
+    if tris.len() <= LEAF_SIZE || clo == chi {
+        return vec![Node {
+            lo,
+            hi,
+            index: offset as u32,
+            count: tris.len() as u32,
+        }];
+    }
+
+    let axis = (chi - clo).imax();
+    let mid = tris.len() / 2;
+    tris.select_nth_unstable_by(mid, |a, b| centroid(a)[axis].total_cmp(&centroid(b)[axis]));
+    let (a, b) = tris.split_at_mut(mid);
+
+    #[cfg(feature = "rayon")]
+    let (left, right) = if a.len() + b.len() >= PARALLEL_SIZE {
+        join(|| build(a, offset), || build(b, offset + mid))
+    } else {
+        (build(a, offset), build(b, offset + mid))
+    };
+    #[cfg(not(feature = "rayon"))]
+    let (left, right) = (build(a, offset), build(b, offset + mid));
+
+    let right_start = 1 + left.len();
+    let mut out = Vec::with_capacity(right_start + right.len());
+    out.push(Node {
+        lo,
+        hi,
+        index: right_start as u32,
+        count: 0,
+    });
+    let shift = |n: Node, by: usize| Node {
+        index: if n.count == 0 {
+            n.index + by as u32
+        } else {
+            n.index
+        },
+        ..n
+    };
+    out.extend(left.into_iter().map(|n| shift(n, 1)));
+    out.extend(right.into_iter().map(|n| shift(n, right_start)));
+    out
+}
+
+fn centroid(t: &BvhTriangle) -> DVec3 {
+    (t.verts[0] + t.verts[1] + t.verts[2]) / 3.0
+}
+
+fn interpolate(verts: &[DVec3; 3], bary: DVec3) -> DVec3 {
+    verts[0] * bary.x + verts[1] * bary.y + verts[2] * bary.z
+}
+
+/// Returns the distance along a ray at which it enters a box (or zero if
+/// it starts inside), or `None` if it misses.  Boxes are closed, so rays
+/// which graze a face or edge of the box still hit it.
+fn ray_box(origin: DVec3, dir: DVec3, lo: DVec3, hi: DVec3) -> Option<f64> {
+    let (mut near, mut far) = (0.0, f64::INFINITY);
+    for i in 0..3 {
+        if dir[i] == 0.0 {
+            // Parallel to this pair of slabs, so it's either always or
+            // never between them
+            if origin[i] < lo[i] || origin[i] > hi[i] {
+                return None;
+            }
+            continue;
+        }
+        let a = (lo[i] - origin[i]) / dir[i];
+        let b = (hi[i] - origin[i]) / dir[i];
+        near = a.min(b).max(near);
+        far = a.max(b).min(far);
+        if near > far {
+            return None;
+        }
+    }
+    Some(near)
+}
+
+/// Intersects a ray with a triangle (Möller–Trumbore), returning the
+/// distance along the ray and the barycentric coordinates of the hit.
+/// Edges and vertices count as part of the triangle.
+fn ray_triangle(origin: DVec3, dir: DVec3, verts: &[DVec3; 3]) -> Option<(f64, DVec3)> {
+    let e1 = verts[1] - verts[0];
+    let e2 = verts[2] - verts[0];
+    let p = dir.cross(&e2);
+    let det = e1.dot(&p);
+    if det == 0.0 {
+        return None;
+    }
+    let s = origin - verts[0];
+    let u = s.dot(&p) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&e1);
+    let v = dir.dot(&q) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(&q) / det;
+    (t >= 0.0).then(|| (t, DVec3::new(1.0 - u - v, u, v)))
+}
+
+/// Returns the squared distance from a point to a box, which is zero for
+/// points inside it
+fn box_distance_sq(p: DVec3, lo: DVec3, hi: DVec3) -> f64 {
+    (lo - p).sup(&(p - hi)).sup(&DVec3::zeros()).norm_squared()
+}
+
+/// Returns the barycentric coordinates of the point on a triangle which is
+/// closest to `p`, following Ericson's _Real-Time Collision Detection_
+/// (§5.1.5)
+fn closest_on_triangle(p: DVec3, verts: &[DVec3; 3]) -> DVec3 {
+    let [a, b, c] = *verts;
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return DVec3::new(1.0, 0.0, 0.0);
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return DVec3::new(0.0, 1.0, 0.0);
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return DVec3::new(1.0 - v, v, 0.0);
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return DVec3::new(0.0, 0.0, 1.0);
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return DVec3::new(1.0 - w, 0.0, w);
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return DVec3::new(0.0, 1.0 - w, w);
+    }
+    let denom = va + vb + vc;
+    if denom == 0.0 {
+        // Degenerate triangle, where every region test failed
+        return DVec3::new(1.0, 0.0, 0.0);
+    }
+    let (v, w) = (vb / denom, vc / denom);
+    DVec3::new(1.0 - v - w, v, w)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use step::{fixture, step_file::StepFile};
+
+    fn mesh(part: fixture::Part) -> Mesh {
+        let text = part.to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        crate::triangulate::triangulate(&step).0
+    }
+
+    /// Checks every triangle, without the tree
+    fn brute_force(mesh: &Mesh, origin: DVec3, dir: DVec3) -> Option<(f64, usize)> {
+        let mut best: Option<(f64, usize)> = None;
+        for (i, t) in mesh.triangles.iter().enumerate() {
+            let verts = [0, 1, 2].map(|j| mesh.verts[t.verts[j] as usize].pos);
+            if let Some((t, _)) = ray_triangle(origin, dir, &verts) {
+                if best.map(|b| t < b.0).unwrap_or(true) {
+                    best = Some((t, i));
+                }
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_raycast_cube() {
+        let mesh = mesh(fixture::cube(1.0));
+        let bvh = mesh.build_bvh();
+        assert_eq!(bvh.len(), mesh.triangles.len());
+
+        // Shoot at the center of each face from outside the cube
+        let center = DVec3::repeat(0.5);
+        for axis in 0..3 {
+            for sign in [-1.0, 1.0] {
+                let mut dir = DVec3::zeros();
+                dir[axis] = sign;
+                let origin = center - dir * 2.0;
+                let hit = bvh.raycast(origin, dir).unwrap();
+                assert!((hit.t - 1.5).abs() < 1e-12, "{:?}", hit);
+                let expected = center - dir * 0.5;
+                assert!((hit.point - expected).norm() < 1e-12);
+                assert!((hit.barycentric.sum() - 1.0).abs() < 1e-12);
+                assert!(hit.face_id.is_some());
+
+                let (t, _) = brute_force(&mesh, origin, dir).unwrap();
+                assert_eq!(hit.t, t);
+
+                // From inside, the ray hits the opposite face
+                let hit = bvh.raycast(center, dir).unwrap();
+                assert!((hit.t - 0.5).abs() < 1e-12);
+
+                // Pointing away from the cube, it misses
+                assert!(bvh.raycast(origin, -dir).is_none());
+            }
+        }
+
+        // Scattered rays agree with brute force
+        let mut seed = 1u64;
+        let mut rand = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        for _ in 0..200 {
+            let origin = DVec3::new(rand(), rand(), rand()) * 4.0 - DVec3::repeat(1.5);
+            let target = DVec3::new(rand(), rand(), rand()) * 1.2 - DVec3::repeat(0.1);
+            let dir = target - origin;
+            let hit = bvh.raycast(origin, dir).map(|h| h.t);
+            assert_eq!(hit, brute_force(&mesh, origin, dir).map(|b| b.0));
+        }
+    }
+
+    #[test]
+    fn test_raycast_grazing() {
+        let mesh = mesh(fixture::cube(1.0));
+        let bvh = mesh.build_bvh();
+
+        // A ray in the plane of the top face misses its triangles, but
+        // hits the edge where the near side meets the top
+        let origin = DVec3::new(-1.0, 0.5, 1.0);
+        let hit = bvh.raycast(origin, DVec3::x()).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-12);
+        assert!((hit.point - DVec3::new(0.0, 0.5, 1.0)).norm() < 1e-12);
+        let (t, i) = brute_force(&mesh, origin, DVec3::x()).unwrap();
+        assert_eq!(hit.t, t);
+        let n = |i: usize| {
+            let v = mesh.triangles[i].verts;
+            let [a, b, c] = [0, 1, 2].map(|j| mesh.verts[v[j] as usize].pos);
+            (b - a).cross(&(c - a)).normalize()
+        };
+        assert_eq!(n(hit.triangle).x.abs(), 1.0);
+        assert_eq!(n(i).x.abs(), 1.0);
+
+        // Just above the top face, the ray misses entirely
+        let origin = DVec3::new(-1.0, 0.5, 1.0 + 1e-9);
+        assert!(bvh.raycast(origin, DVec3::x()).is_none());
+        assert!(brute_force(&mesh, origin, DVec3::x()).is_none());
+    }
+
+    #[test]
+    fn test_closest_point() {
+        let mesh = mesh(fixture::cube(1.0));
+        let bvh = mesh.build_bvh();
+
+        let hit = bvh.closest_point(DVec3::new(0.5, 0.5, 3.0)).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-12);
+        assert!((hit.point - DVec3::new(0.5, 0.5, 1.0)).norm() < 1e-12);
+
+        // Beyond a corner, the corner itself is closest
+        let hit = bvh.closest_point(DVec3::new(2.0, 2.0, 2.0)).unwrap();
+        assert!((hit.point - DVec3::repeat(1.0)).norm() < 1e-12);
+        assert!((hit.t - 3f64.sqrt()).abs() < 1e-12);
+
+        // From inside, the nearest face wins
+        let hit = bvh.closest_point(DVec3::new(0.5, 0.9, 0.5)).unwrap();
+        assert!((hit.t - 0.1).abs() < 1e-12);
+        assert!((hit.point.y - 1.0).abs() < 1e-12);
+
+        let empty = Mesh::default().build_bvh();
+        assert!(empty.is_empty());
+        assert!(empty.closest_point(DVec3::zeros()).is_none());
+        assert!(empty.raycast(DVec3::zeros(), DVec3::x()).is_none());
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_serialize() {
+        let mesh = mesh(fixture::cube(1.0));
+        let bvh = mesh.build_bvh();
+        let data = bincode::serialize(&bvh).unwrap();
+        let copy: MeshBvh = bincode::deserialize(&data).unwrap();
+        let (origin, dir) = (DVec3::new(0.3, 0.4, 2.0), -DVec3::z());
+        assert_eq!(copy.raycast(origin, dir), bvh.raycast(origin, dir));
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        // A mesh with many triangles builds a multi-level tree, which finds
+        // the same hits as brute force
+        let mesh = mesh(fixture::cylinder(5.0, 10.0));
+        let bvh = mesh.build_bvh();
+        assert!(bvh.nodes.len() > 1);
+        for i in 0..32 {
+            let a = i as f64 * std::f64::consts::PI / 16.0;
+            let origin = DVec3::new(20.0 * a.cos(), 20.0 * a.sin(), 3.0 + i as f64 * 0.1);
+            let dir = DVec3::new(0.0, 0.0, 5.0) - origin;
+            let hit = bvh.raycast(origin, dir).unwrap();
+            assert_eq!(Some(hit.t), brute_force(&mesh, origin, dir).map(|b| b.0));
+            assert!((hit.point.xy().norm() - 5.0).abs() < 0.1);
+        }
+    }
+}
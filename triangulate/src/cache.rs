@@ -0,0 +1,340 @@
+//! On-disk cache of triangulated meshes, so that reopening a large file can
+//! skip parsing and triangulation.
+//!
+//! Entries are keyed by a hash of the file's contents, the options which
+//! affect triangulation, and [`CACHE_VERSION`], so an edited file or a
+//! different tolerance misses the cache instead of returning a stale mesh.
+//! Unreadable entries are treated as misses, and are overwritten when the
+//! mesh is recomputed.
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crop::Aabb,
+    grid::MeshTopology,
+    load::{ParseReport, TriangulateOptions},
+    mesh::Mesh,
+    stats::Stats,
+};
+
+/// Version of the cache's file format.  This must be bumped whenever the
+/// serialized types change (or triangulation changes its output), which
+/// invalidates every existing entry.
+pub const CACHE_VERSION: u32 = 7;
+
+/// Magic bytes at the start of every cache entry
+const MAGIC: &[u8; 4] = b"FXMC";
+
+/// Returns the default cache directory, e.g. `~/.cache/foxtrot/meshes` on
+/// Linux, or `None` if the platform doesn't have one
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("foxtrot").join("meshes"))
+}
+
+/// A variant of 64-bit FNV-1a which consumes eight bytes at a time, mixing
+/// the high bits back down after each multiply.  Unlike `std`'s
+/// `DefaultHasher`, this is stable across platforms and compiler versions.
+struct Hasher(u64);
+
+impl Hasher {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+    fn write_u64(&mut self, w: u64) {
+        self.0 = (self.0 ^ w).wrapping_mul(0x100000001b3);
+        self.0 ^= self.0 >> 29;
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for c in &mut chunks {
+            self.write_u64(u64::from_le_bytes(c.try_into().unwrap()));
+        }
+        let mut tail = [0; 8];
+        tail[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+        self.write_u64(u64::from_le_bytes(tail));
+    }
+}
+
+fn key_for(
+    version: u32,
+    data: &[u8],
+    tolerance: Option<f64>,
+    crop: Option<(&Aabb, bool)>,
+    topology: MeshTopology,
+) -> String {
+    let mut h = Hasher::new();
+    h.write(&version.to_le_bytes());
+    match tolerance {
+        Some(t) => {
+            h.write(&[1]);
+            h.write(&t.to_bits().to_le_bytes());
+        }
+        None => h.write(&[0]),
+    }
+    h.write(data);
+
+    // Cropping is hashed after the data, so that keys for uncropped meshes
+    // are unchanged from before cropping existed
+    if let Some((aabb, clip)) = crop {
+        for f in aabb.min.iter().chain(aabb.max.iter()) {
+            h.write(&f.to_bits().to_le_bytes());
+        }
+        h.write(&[clip as u8]);
+    }
+
+    // As is the mesh topology, when it's not the default
+    match topology {
+        MeshTopology::Triangles => (),
+        MeshTopology::QuadDominant => h.write(b"quads"),
+        MeshTopology::Grid => h.write(b"grid"),
+    }
+    format!("v{}-{:016x}-{:x}", version, h.0, data.len())
+}
+
+/// Returns the cache key for a file's contents and triangulation options.
+/// The progress callback doesn't change the mesh, so it's not included.
+pub fn key(data: &[u8], opts: &TriangulateOptions) -> String {
+    let crop = opts.crop.as_ref().map(|aabb| (aabb, opts.clip));
+    key_for(CACHE_VERSION, data, opts.tolerance, crop, opts.topology)
+}
+
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    mesh: &'a Mesh,
+    parse: &'a ParseReport,
+    stats: &'a Stats,
+}
+
+/// A cached mesh, along with the parts of the [`LoadReport`] which aren't
+/// timings
+///
+/// [`LoadReport`]: crate::load::LoadReport
+#[derive(Deserialize)]
+pub(crate) struct Entry {
+    pub mesh: Mesh,
+    pub parse: ParseReport,
+    pub stats: Stats,
+}
+
+fn entry_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{}.mesh", key))
+}
+
+fn decode(data: &[u8]) -> Result<Entry, String> {
+    if data.len() < 8 || &data[..4] != MAGIC {
+        return Err("missing header".to_owned());
+    }
+    let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    if version != CACHE_VERSION {
+        return Err(format!("version {} (expected {})", version, CACHE_VERSION));
+    }
+    bincode::deserialize(&data[8..]).map_err(|e| e.to_string())
+}
+
+/// Looks up a mesh in the cache.  Missing entries return `None`, as do
+/// corrupted ones, which are logged and removed.
+pub(crate) fn load(dir: &Path, key: &str) -> Option<Entry> {
+    let path = entry_path(dir, key);
+    let data = match std::fs::read(&path) {
+        Ok(d) => d,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            log::warn!("Could not read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    match decode(&data) {
+        Ok(entry) => {
+            log::info!("Loaded mesh from {}", path.display());
+            Some(entry)
+        }
+        Err(e) => {
+            log::warn!("Ignoring invalid cache entry {}: {}", path.display(), e);
+            let _ = std::fs::remove_file(&path);
+            None
+        }
+    }
+}
+
+/// Writes a mesh to the cache.  Failures are logged rather than returned,
+/// since the mesh itself was loaded successfully.
+pub(crate) fn store(dir: &Path, key: &str, mesh: &Mesh, parse: &ParseReport, stats: &Stats) {
+    let path = entry_path(dir, key);
+    let entry = EntryRef { mesh, parse, stats };
+    let mut data = MAGIC.to_vec();
+    data.extend(CACHE_VERSION.to_le_bytes());
+    if let Err(e) = bincode::serialize_into(&mut data, &entry) {
+        log::warn!("Could not serialize mesh for cache: {}", e);
+        return;
+    }
+
+    // Write to a temporary file then rename it, so that other processes
+    // never see a partial entry
+    let tmp = dir.join(format!("{}.{}.tmp", key, std::process::id()));
+    let r = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&tmp, &data))
+        .and_then(|_| std::fs::rename(&tmp, &path));
+    if let Err(e) = r {
+        log::warn!("Could not write {}: {}", path.display(), e);
+        let _ = std::fs::remove_file(&tmp);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mesh::{Triangle, Vertex},
+        units::LengthUnit,
+    };
+    use nalgebra_glm::{DVec3, U32Vec3};
+
+    /// Returns an empty scratch directory
+    fn scratch(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("foxtrot-cache-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn triangle() -> Mesh {
+        let v = |x, y| Vertex {
+            pos: DVec3::new(x, y, 0.0),
+            norm: DVec3::z(),
+            color: DVec3::repeat(0.5),
+        };
+        Mesh {
+            verts: vec![v(0.0, 0.0), v(1.0, 0.0), v(0.0, 1.0)],
+            triangles: vec![Triangle {
+                verts: U32Vec3::new(0, 1, 2),
+            }],
+            ..Mesh::default()
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        let opts = TriangulateOptions::default();
+        let k = key(b"ISO-10303-21;", &opts);
+        assert_eq!(k, key(b"ISO-10303-21;", &opts));
+        assert!(k.starts_with(&format!("v{}-", CACHE_VERSION)));
+
+        // The contents, tolerance, and version all change the key
+        assert_ne!(k, key(b"ISO-10303-22;", &opts));
+        let fine = TriangulateOptions {
+            tolerance: Some(0.01),
+            ..Default::default()
+        };
+        let coarse = TriangulateOptions {
+            tolerance: Some(0.1),
+            ..Default::default()
+        };
+        assert_ne!(k, key(b"ISO-10303-21;", &fine));
+        assert_ne!(key(b"ISO-10303-21;", &fine), key(b"ISO-10303-21;", &coarse));
+        assert_ne!(
+            k,
+            key_for(
+                CACHE_VERSION + 1,
+                b"ISO-10303-21;",
+                None,
+                None,
+                MeshTopology::Triangles
+            )
+        );
+
+        // So do the crop box and whether to clip to it
+        let cropped = TriangulateOptions {
+            crop: Some("0,0,0,1,1,1".parse().unwrap()),
+            ..Default::default()
+        };
+        let clipped = TriangulateOptions {
+            crop: cropped.crop,
+            clip: true,
+            ..Default::default()
+        };
+        assert_ne!(k, key(b"ISO-10303-21;", &cropped));
+        assert_ne!(
+            key(b"ISO-10303-21;", &cropped),
+            key(b"ISO-10303-21;", &clipped)
+        );
+
+        // And the mesh topology
+        let quads = TriangulateOptions {
+            topology: MeshTopology::QuadDominant,
+            ..Default::default()
+        };
+        let grid = TriangulateOptions {
+            topology: MeshTopology::Grid,
+            ..Default::default()
+        };
+        assert_ne!(k, key(b"ISO-10303-21;", &quads));
+        assert_ne!(key(b"ISO-10303-21;", &quads), key(b"ISO-10303-21;", &grid));
+
+        // Keys are stable across runs and platforms
+        assert_eq!(
+            key_for(1, b"", None, None, MeshTopology::Triangles),
+            "v1-6db4a3a95fcc68e1-0"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = scratch("round-trip");
+        assert!(load(&dir, "a").is_none());
+
+        let parse = ParseReport {
+            entities: 10,
+            failed: 1,
+            length_unit: Some(LengthUnit::Inch),
+        };
+        let stats = Stats {
+            num_faces: 1,
+            ..Stats::default()
+        };
+        store(&dir, "a", &triangle(), &parse, &stats);
+        let e = load(&dir, "a").unwrap();
+        assert_eq!(e.mesh.triangles.len(), 1);
+        assert_eq!(e.mesh.verts[1].pos, DVec3::x());
+        assert_eq!(e.parse, parse);
+        assert_eq!(e.stats.num_faces, 1);
+        assert!(load(&dir, "b").is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let dir = scratch("corrupted");
+        let store_a = || {
+            store(
+                &dir,
+                "a",
+                &triangle(),
+                &ParseReport::default(),
+                &Stats::default(),
+            )
+        };
+        store_a();
+        let path = entry_path(&dir, "a");
+        let good = std::fs::read(&path).unwrap();
+
+        // Truncated data, a bad header, and an old version are all misses,
+        // and the bad entry is removed
+        for bad in [
+            good[..good.len() / 2].to_vec(),
+            b"garbage".to_vec(),
+            [&good[..4], &0u32.to_le_bytes()[..], &good[8..]].concat(),
+        ] {
+            std::fs::write(&path, bad).unwrap();
+            assert!(load(&dir, "a").is_none());
+            assert!(!path.exists());
+        }
+
+        // Storing again recovers
+        store_a();
+        assert!(load(&dir, "a").is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+//! Coordinate conventions, for moving meshes out of STEP's Z-up,
+//! millimeter, right-handed coordinates into whatever a downstream tool
+//! expects.
+//!
+//! A convention is applied with [`Mesh::apply_convention`], either at load
+//! time (see
+//! [`TriangulateOptions::convention`](crate::load::TriangulateOptions::convention))
+//! or when exporting (see
+//! [`ExportOptions::convention`](crate::export::ExportOptions::convention)).
+use std::str::FromStr;
+
+use nalgebra_glm::DMat4;
+
+#[cfg(doc)]
+use crate::mesh::Mesh;
+
+/// A coordinate axis
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl FromStr for Axis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "x" => Ok(Axis::X),
+            "y" => Ok(Axis::Y),
+            "z" => Ok(Axis::Z),
+            _ => Err(format!("Invalid axis {:?} (expected x, y, or z)", s)),
+        }
+    }
+}
+
+/// Where the model's up axis ends up, how it's scaled, and whether it's
+/// mirrored into a left-handed coordinate system
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CoordinateConvention {
+    /// Output axis which the model's +Z is rotated onto.  The rotation
+    /// cycles the axes, so X-up maps (X, Y, Z) to (Y, Z, X) and Y-up maps
+    /// them to (X, -Z, Y).
+    pub up: Axis,
+    /// Scale from model units to output units, which must be positive
+    /// (e.g. 0.001 for millimeters to meters)
+    pub scale: f64,
+    /// If set, the output is mirrored into a left-handed coordinate
+    /// system, by negating Y when Z is up and Z otherwise.  Triangle
+    /// winding is reversed to match, so faces keep pointing outwards.
+    pub flip_handedness: bool,
+}
+
+impl Default for CoordinateConvention {
+    fn default() -> Self {
+        Self::STEP
+    }
+}
+
+impl CoordinateConvention {
+    /// STEP's own convention (Z-up and right-handed, in model units), which
+    /// leaves meshes unchanged
+    pub const STEP: Self = Self {
+        up: Axis::Z,
+        scale: 1.0,
+        flip_handedness: false,
+    };
+
+    /// glTF's convention: Y-up and right-handed, in meters
+    pub const GLTF: Self = Self {
+        up: Axis::Y,
+        scale: 0.001,
+        flip_handedness: false,
+    };
+
+    /// Unity's convention: Y-up and left-handed, in meters
+    pub const UNITY: Self = Self {
+        up: Axis::Y,
+        scale: 0.001,
+        flip_handedness: true,
+    };
+
+    /// Unreal's convention: Z-up and left-handed, in centimeters
+    pub const UNREAL: Self = Self {
+        up: Axis::Z,
+        scale: 0.1,
+        flip_handedness: true,
+    };
+
+    /// Every named convention, for [`CoordinateConvention::named`]
+    pub const NAMED: [(&'static str, Self); 4] = [
+        ("step", Self::STEP),
+        ("gltf", Self::GLTF),
+        ("unity", Self::UNITY),
+        ("unreal", Self::UNREAL),
+    ];
+
+    /// Looks up a convention by name (case-insensitive)
+    pub fn named(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::NAMED
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, c)| *c)
+    }
+
+    /// Checks whether this convention leaves meshes unchanged
+    pub fn is_identity(&self) -> bool {
+        *self == Self::STEP
+    }
+
+    /// Returns the rotation (and mirroring, if handedness is flipped),
+    /// without the scale
+    pub fn rotation(&self) -> DMat4 {
+        #[rustfmt::skip]
+        let mut m = match self.up {
+            Axis::X => DMat4::new(
+                0.0, 0.0, 1.0, 0.0,
+                1.0, 0.0, 0.0, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0,
+            ),
+            Axis::Y => DMat4::new(
+                1.0, 0.0, 0.0, 0.0,
+                0.0, 0.0, 1.0, 0.0,
+                0.0, -1.0, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0,
+            ),
+            Axis::Z => DMat4::identity(),
+        };
+        if self.flip_handedness {
+            let row = if self.up == Axis::Z { 1 } else { 2 };
+            for c in 0..3 {
+                m[(row, c)] = -m[(row, c)];
+            }
+        }
+        m
+    }
+
+    /// Returns the full transform from model coordinates to output
+    /// coordinates
+    pub fn matrix(&self) -> DMat4 {
+        let mut m = self.rotation();
+        for r in 0..3 {
+            for c in 0..3 {
+                m[(r, c)] *= self.scale;
+            }
+        }
+        m
+    }
+}
+
+/// Parses a named convention (see [`CoordinateConvention::NAMED`])
+impl FromStr for CoordinateConvention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Self::named(s).ok_or_else(|| {
+            let names: Vec<_> = Self::NAMED.iter().map(|(n, _)| *n).collect();
+            format!(
+                "Unknown coordinate convention {:?} (expected one of {})",
+                s,
+                names.join(", ")
+            )
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{Mesh, Part, Triangle, Vertex};
+    use nalgebra_glm::{DVec3, U32Vec3};
+
+    /// Builds a mesh with a single vertex, at (1, 2, 3) with normal +X
+    fn point() -> Mesh {
+        let mut mesh = Mesh::default();
+        mesh.verts.push(Vertex {
+            pos: DVec3::new(1.0, 2.0, 3.0),
+            norm: DVec3::x(),
+            color: DVec3::zeros(),
+        });
+        mesh
+    }
+
+    /// Returns the signed volume of a closed mesh
+    fn volume(mesh: &Mesh) -> f64 {
+        mesh.triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.verts[t.verts[i] as usize].pos);
+                a.dot(&b.cross(&c)) / 6.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_named() {
+        assert_eq!(
+            CoordinateConvention::named("glTF"),
+            Some(CoordinateConvention::GLTF)
+        );
+        assert_eq!("step".parse(), Ok(CoordinateConvention::STEP));
+        assert!("blender".parse::<CoordinateConvention>().is_err());
+        assert_eq!("Y".parse(), Ok(Axis::Y));
+        assert!("w".parse::<Axis>().is_err());
+        assert!(CoordinateConvention::default().is_identity());
+        assert!(!CoordinateConvention::GLTF.is_identity());
+    }
+
+    #[test]
+    fn test_presets() {
+        let cases = [
+            (CoordinateConvention::STEP, [1.0, 2.0, 3.0], [1.0, 0.0, 0.0]),
+            (
+                CoordinateConvention::GLTF,
+                [0.001, 0.003, -0.002],
+                [1.0, 0.0, 0.0],
+            ),
+            (
+                CoordinateConvention::UNITY,
+                [0.001, 0.003, 0.002],
+                [1.0, 0.0, 0.0],
+            ),
+            (
+                CoordinateConvention::UNREAL,
+                [0.1, -0.2, 0.3],
+                [1.0, 0.0, 0.0],
+            ),
+            (
+                CoordinateConvention {
+                    up: Axis::X,
+                    scale: 2.0,
+                    flip_handedness: false,
+                },
+                [6.0, 2.0, 4.0],
+                [0.0, 1.0, 0.0],
+            ),
+        ];
+        for (c, pos, norm) in cases {
+            let mut mesh = point();
+            mesh.curvature = Some(vec![1.0]);
+            mesh.apply_convention(&c);
+            let v = mesh.verts[0];
+            assert!(
+                (v.pos - DVec3::from(pos)).norm() < 1e-12,
+                "{:?}: {:?}",
+                c,
+                v.pos
+            );
+            assert!(
+                (v.norm - DVec3::from(norm)).norm() < 1e-12,
+                "{:?}: {:?}",
+                c,
+                v.norm
+            );
+            assert!((mesh.curvature.unwrap()[0] as f64 - 1.0 / c.scale).abs() < 1e-3);
+        }
+
+        // The up axis ends up pointing up
+        for (_, c) in CoordinateConvention::NAMED {
+            let up = c.rotation() * DVec3::z().push(0.0);
+            let i = match c.up {
+                Axis::X => 0,
+                Axis::Y => 1,
+                Axis::Z => 2,
+            };
+            assert_eq!(up[i], 1.0, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_winding() {
+        // A tetrahedron with outward-facing triangles, placed as a part
+        let mut mesh = Mesh::default();
+        for p in [DVec3::zeros(), DVec3::x(), DVec3::y(), DVec3::z()] {
+            mesh.verts.push(Vertex {
+                pos: p,
+                norm: p,
+                color: DVec3::zeros(),
+            });
+        }
+        for t in [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]] {
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::from(t),
+            });
+        }
+        mesh.parts.push(Part {
+            shape: 0,
+            triangles: 0..4,
+            transform: DMat4::identity(),
+        });
+        let v = volume(&mesh);
+        assert!((v - 1.0 / 6.0).abs() < 1e-12);
+
+        for (name, c) in CoordinateConvention::NAMED {
+            let mut out = mesh.clone();
+            out.apply_convention(&c);
+            let expected = v * c.scale.powi(3);
+            assert!(
+                (volume(&out) - expected).abs() < 1e-12 * expected.max(1.0),
+                "{}: {}",
+                name,
+                volume(&out)
+            );
+            assert_eq!(out.parts[0].transform, c.matrix());
+            assert_eq!(out.open_edges(), mesh.open_edges());
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Which STEP entity types the triangulator uses, for coverage reports
+//! (see [`step::coverage`])
+use std::path::Path;
+
+pub use step::coverage::{CoverageReport, EntityCoverage, Support};
+use step::step_file::StepFile;
+
+use crate::load::LoadError;
+
+/// Keywords of every entity type which is read while triangulating.  Other
+/// entities are parsed, but have no effect on the mesh.
+pub const CONSUMED: &[&str] = &[
+    "ADVANCED_BREP_SHAPE_REPRESENTATION",
+    "ADVANCED_FACE",
+    "AXIS2_PLACEMENT_3D",
+    "BREP_WITH_VOIDS",
+    "B_SPLINE_CURVE_WITH_KNOTS",
+    "B_SPLINE_SURFACE_WITH_KNOTS",
+    "CARTESIAN_POINT",
+    "CIRCLE",
+    "CLOSED_SHELL",
+    "COLOUR_RGB",
+    "COMPOSITE_CURVE",
+    "COMPOSITE_CURVE_SEGMENT",
+    "CONICAL_SURFACE",
+    "CYLINDRICAL_SURFACE",
+    "DEFINITIONAL_REPRESENTATION",
+    "DIRECTION",
+    "EDGE_CURVE",
+    "EDGE_LOOP",
+    "ELLIPSE",
+    "FACE_BOUND",
+    "FACE_OUTER_BOUND",
+    "FILL_AREA_STYLE",
+    "FILL_AREA_STYLE_COLOUR",
+    "GEOMETRICALLY_BOUNDED_SURFACE_SHAPE_REPRESENTATION",
+    "GEOMETRICALLY_BOUNDED_WIREFRAME_SHAPE_REPRESENTATION",
+    "GEOMETRIC_CURVE_SET",
+    "GEOMETRIC_SET",
+    "HYPERBOLA",
+    "ITEM_DEFINED_TRANSFORMATION",
+    "LINE",
+    "MANIFOLD_SOLID_BREP",
+    "MANIFOLD_SURFACE_SHAPE_REPRESENTATION",
+    "MECHANICAL_DESIGN_GEOMETRIC_PRESENTATION_REPRESENTATION",
+    "OPEN_SHELL",
+    "ORIENTED_EDGE",
+    "PARABOLA",
+    "PCURVE",
+    "PLANE",
+    "POLYLINE",
+    "PRESENTATION_STYLE_ASSIGNMENT",
+    "RATIONAL_B_SPLINE_CURVE",
+    "RATIONAL_B_SPLINE_SURFACE",
+    "REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION",
+    "SEAM_CURVE",
+    "SHAPE_REPRESENTATION",
+    "SHAPE_REPRESENTATION_RELATIONSHIP",
+    "SHELL_BASED_SURFACE_MODEL",
+    "SPHERICAL_SURFACE",
+    "STYLED_ITEM",
+    "SURFACE_CURVE",
+    "SURFACE_SIDE_STYLE",
+    "SURFACE_STYLE_FILL_AREA",
+    "SURFACE_STYLE_USAGE",
+    "TOROIDAL_SURFACE",
+    "TRIMMED_CURVE",
+    "VECTOR",
+    "VERTEX_LOOP",
+    "VERTEX_POINT",
+];
+
+/// Reports coverage of a STEP file, with entity types that the
+/// triangulator doesn't use marked as ignored
+pub fn report(s: &StepFile) -> CoverageReport {
+    step::coverage::report(s).consumed_by(CONSUMED)
+}
+
+/// Reads and parses a STEP file, then reports its coverage as in
+/// [`report`].  This works even for files which produce an empty mesh.
+pub fn report_file(path: impl AsRef<Path>) -> Result<CoverageReport, LoadError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|source| LoadError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let flat = StepFile::strip_flatten(&data);
+    let step = StepFile::parse(&flat)?;
+    Ok(report(&step))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use step::{ap214::KEYWORDS, fixture};
+
+    #[test]
+    fn test_consumed() {
+        for k in CONSUMED {
+            assert!(KEYWORDS.contains(k), "{} is not an entity", k);
+        }
+        assert!(CONSUMED.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_report() {
+        // One face is on a surface of revolution, which is parsed but not
+        // used by the triangulator
+        let text = fixture::cube(1.0).to_step();
+        let plane = text.lines().find(|l| l.contains("=PLANE(")).unwrap();
+        let (id, args) = plane.split_once("=PLANE('',").unwrap();
+        let axis = args.trim_end_matches(");");
+        let text = text.replace(
+            plane,
+            &format!("{}=SURFACE_OF_REVOLUTION('',{},{});", id, axis, axis),
+        );
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let r = report(&step);
+
+        let support = |k| r.get(k).unwrap().support;
+        assert_eq!(support("SURFACE_OF_REVOLUTION"), Support::Ignored);
+        assert_eq!(support("PLANE"), Support::Parsed);
+        assert_eq!(support("ADVANCED_FACE"), Support::Parsed);
+        assert_eq!(support("PRODUCT"), Support::Ignored);
+        let unsupported: Vec<_> = r
+            .unsupported_geometry()
+            .iter()
+            .map(|e| e.keyword.as_str())
+            .collect();
+        assert_eq!(unsupported, ["SURFACE_OF_REVOLUTION"]);
+    }
+}
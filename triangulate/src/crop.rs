@@ -0,0 +1,273 @@
+//! Cropping models to a box, so that only the region of interest in a huge
+//! model is triangulated.
+//!
+//! Faces are tested against the box before they're meshed (see
+//! [`TriangulateOptions::crop`](crate::load::TriangulateOptions::crop)),
+//! using a conservative bounding box for each face; faces which straddle the
+//! box are kept whole, unless [`clip`] is used to drop their outside
+//! triangles afterwards.
+use std::str::FromStr;
+
+use nalgebra_glm::{DMat4, DVec3, DVec4};
+
+use crate::mesh::{Face, Mesh, Part, Triangle};
+
+/// An axis-aligned bounding box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: DVec3,
+    pub max: DVec3,
+}
+
+impl Aabb {
+    /// Builds a box from two opposite corners, in any order
+    pub fn new(a: DVec3, b: DVec3) -> Self {
+        Self {
+            min: a.inf(&b),
+            max: a.sup(&b),
+        }
+    }
+
+    /// Returns the bounding box of a set of points, or `None` if it's empty
+    pub fn from_points(pts: impl IntoIterator<Item = DVec3>) -> Option<Self> {
+        let mut pts = pts.into_iter();
+        let first = pts.next()?;
+        Some(pts.fold(Self::new(first, first), |b, p| Self {
+            min: b.min.inf(&p),
+            max: b.max.sup(&p),
+        }))
+    }
+
+    /// Checks whether two boxes overlap (including touching)
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    pub fn contains(&self, p: DVec3) -> bool {
+        (0..3).all(|i| self.min[i] <= p[i] && p[i] <= self.max[i])
+    }
+
+    /// Returns the smallest box containing both boxes
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    /// Grows the box by `d` in every direction
+    pub fn padded(&self, d: f64) -> Self {
+        Self {
+            min: self.min.add_scalar(-d),
+            max: self.max.add_scalar(d),
+        }
+    }
+
+    /// Returns the bounding box of this box after an affine transform
+    pub fn transformed(&self, mat: &DMat4) -> Self {
+        let corners = (0..8).map(|i| {
+            let pick = |bit: usize, axis: usize| {
+                if i & (1 << bit) == 0 {
+                    self.min[axis]
+                } else {
+                    self.max[axis]
+                }
+            };
+            (mat * DVec4::new(pick(0, 0), pick(1, 1), pick(2, 2), 1.0)).xyz()
+        });
+        Self::from_points(corners).unwrap()
+    }
+}
+
+/// Parses a box from six comma-separated numbers, `x0,y0,z0,x1,y1,z1`,
+/// which are two opposite corners
+impl FromStr for Aabb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let v = s
+            .split(',')
+            .map(|c| c.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid crop box {:?}: {}", s, e))?;
+        match v.as_slice() {
+            [x0, y0, z0, x1, y1, z1] if v.iter().all(|f| f.is_finite()) => Ok(Self::new(
+                DVec3::new(*x0, *y0, *z0),
+                DVec3::new(*x1, *y1, *z1),
+            )),
+            [_, _, _, _, _, _] => Err(format!("Invalid crop box {:?}: not finite", s)),
+            _ => Err(format!(
+                "Invalid crop box {:?}: expected x0,y0,z0,x1,y1,z1",
+                s
+            )),
+        }
+    }
+}
+
+/// Returns a copy of the mesh without the triangles whose bounding boxes
+/// are entirely outside of `aabb`, and without unused vertices.  Triangles
+/// which cross the box's boundary are kept whole.  Wires and grids are kept
+/// if any of their points are inside the box, and quads are kept if both of
+/// their triangles are.
+pub fn clip(mesh: &Mesh, aabb: &Aabb) -> Mesh {
+    let keep: Vec<bool> = mesh
+        .triangles
+        .iter()
+        .map(|t| {
+            let pts = t.verts.iter().map(|i| mesh.verts[*i as usize].pos);
+            Aabb::from_points(pts).unwrap().intersects(aabb)
+        })
+        .collect();
+
+    // Number of kept triangles before each triangle, used to remap the
+    // part and face ranges
+    let mut before = Vec::with_capacity(keep.len() + 1);
+    before.push(0);
+    for k in &keep {
+        before.push(before.last().unwrap() + *k as usize);
+    }
+    let remap = |r: &std::ops::Range<usize>| before[r.start]..before[r.end];
+
+    let mut out = Mesh {
+        curvature: mesh.curvature.as_ref().map(|_| vec![]),
+        colors: mesh.colors.as_ref().map(|_| vec![]),
+        layers: mesh.layers.clone(),
+        ..Mesh::default()
+    };
+    let mut verts: Vec<Option<u32>> = vec![None; mesh.verts.len()];
+    for (t, _) in mesh.triangles.iter().zip(&keep).filter(|(_, k)| **k) {
+        let verts = t.verts.map(|v| {
+            *verts[v as usize].get_or_insert_with(|| {
+                if let (Some(src), Some(dst)) = (&mesh.curvature, &mut out.curvature) {
+                    dst.push(src[v as usize]);
+                }
+                if let (Some(src), Some(dst)) = (&mesh.colors, &mut out.colors) {
+                    dst.push(src[v as usize]);
+                }
+                out.verts.push(mesh.verts[v as usize]);
+                (out.verts.len() - 1) as u32
+            })
+        });
+        out.triangles.push(Triangle { verts });
+    }
+    out.parts = mesh
+        .parts
+        .iter()
+        .map(|p| Part {
+            triangles: remap(&p.triangles),
+            ..p.clone()
+        })
+        .filter(|p| !p.triangles.is_empty())
+        .collect();
+    out.faces = mesh
+        .faces
+        .iter()
+        .map(|f| Face {
+            id: f.id,
+            triangles: remap(&f.triangles),
+        })
+        .filter(|f| !f.triangles.is_empty())
+        .collect();
+    out.wires = mesh
+        .wires
+        .iter()
+        .filter(|w| w.points.iter().any(|p| aabb.contains(*p)))
+        .cloned()
+        .collect();
+    out.grids = mesh
+        .grids
+        .iter()
+        .filter(|g| g.points.iter().any(|p| aabb.contains(*p)))
+        .cloned()
+        .collect();
+    out.quads = mesh
+        .quads
+        .iter()
+        .filter(|q| keep[**q] && keep[**q + 1])
+        .map(|q| before[*q])
+        .collect();
+    out
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{Vertex, Wire};
+    use nalgebra_glm::U32Vec3;
+
+    #[test]
+    fn test_parse() {
+        let b: Aabb = "1,2,3, 4,5,-6".parse().unwrap();
+        assert_eq!(b.min, DVec3::new(1.0, 2.0, -6.0));
+        assert_eq!(b.max, DVec3::new(4.0, 5.0, 3.0));
+        assert!("1,2,3".parse::<Aabb>().is_err());
+        assert!("1,2,3,4,5,x".parse::<Aabb>().is_err());
+        assert!("1,2,3,4,5,inf".parse::<Aabb>().is_err());
+    }
+
+    #[test]
+    fn test_intersects() {
+        let a = Aabb::new(DVec3::zeros(), DVec3::repeat(1.0));
+        let b = Aabb::new(DVec3::repeat(0.5), DVec3::repeat(2.0));
+        let c = Aabb::new(DVec3::new(1.5, 0.0, 0.0), DVec3::new(2.0, 1.0, 1.0));
+        assert!(a.intersects(&b) && b.intersects(&a));
+        assert!(!a.intersects(&c) && !c.intersects(&a));
+        assert!(a.intersects(&c.padded(0.5)));
+        assert_eq!(a.union(&c).max, DVec3::new(2.0, 1.0, 1.0));
+
+        // Rotating a unit cube by 45° about Z makes it wider
+        let rot = nalgebra_glm::rotation(std::f64::consts::FRAC_PI_4, &DVec3::z());
+        let r = a.transformed(&rot);
+        assert!((r.max.x - r.min.x - 2f64.sqrt()).abs() < 1e-12);
+        assert!((r.max.z - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_clip() {
+        // Two parts with one face each, at x = 0 and x = 10
+        let mut mesh = Mesh::default();
+        for (i, x) in [0.0, 10.0].into_iter().enumerate() {
+            for d in [DVec3::zeros(), DVec3::x(), DVec3::y()] {
+                mesh.verts.push(Vertex {
+                    pos: d + DVec3::new(x, 0.0, 0.0),
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+            let j = 3 * i as u32;
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(j, j + 1, j + 2),
+            });
+            mesh.parts.push(Part {
+                shape: i,
+                triangles: i..(i + 1),
+                transform: DMat4::identity(),
+            });
+            mesh.faces.push(Face {
+                id: 100 + i,
+                triangles: i..(i + 1),
+            });
+            mesh.wires.push(Wire {
+                id: 200 + i,
+                points: vec![DVec3::new(x, 0.0, 0.0), DVec3::new(x, 1.0, 0.0)],
+            });
+        }
+        mesh.curvature = Some(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let b = Aabb::new(DVec3::new(9.0, -1.0, -1.0), DVec3::new(20.0, 1.0, 1.0));
+        let out = clip(&mesh, &b);
+        assert_eq!(out.triangles.len(), 1);
+        assert_eq!(out.triangles[0].verts, U32Vec3::new(0, 1, 2));
+        assert_eq!(out.verts[0].pos, DVec3::new(10.0, 0.0, 0.0));
+        assert_eq!(out.curvature, Some(vec![4.0, 5.0, 6.0]));
+        assert_eq!(out.parts.len(), 1);
+        assert_eq!(out.parts[0].shape, 1);
+        assert_eq!(out.parts[0].triangles, 0..1);
+        assert_eq!(out.faces.len(), 1);
+        assert_eq!(out.faces[0].id, 101);
+        assert_eq!(out.wires.len(), 1);
+        assert_eq!(out.wires[0].id, 201);
+    }
+}
@@ -4,6 +4,42 @@ use nalgebra_glm as glm;
 use crate::surface::Surface;
 use nurbs::{AbstractCurve, NdBsplineCurve, SampledCurve};
 
+/// Number of points in a full circle, when no tolerance is given
+const CIRCLE_POINTS: usize = 64;
+
+/// Upper bound on the number of points in a single arc, which keeps tiny
+/// tolerances from producing absurdly dense meshes
+const MAX_ARC_POINTS: usize = 4096;
+
+/// When no tolerance is given, parabolas and hyperbolas are sampled to this
+/// fraction of the distance between their ends
+const CONIC_TOLERANCE: f64 = 1e-3;
+
+/// Parabolas and hyperbolas start out with this many segments, which are
+/// then subdivided until they're within tolerance
+const CONIC_SEGMENTS: usize = 4;
+
+/// Returns the number of points (including both ends) used to sample an
+/// arc of the given radius spanning `angle` radians.
+///
+/// With a tolerance, segments are short enough that the arc never strays
+/// more than `tolerance` from them.  There are always at least 4 points.
+pub fn arc_points(radius: f64, angle: f64, tolerance: Option<f64>) -> usize {
+    let count = match tolerance {
+        Some(tol) if tol > 0.0 && radius > 0.0 => {
+            // The gap between an arc and its chord is r * (1 - cos(θ / 2))
+            let max_angle = 2.0 * (1.0 - (tol / radius).min(1.0)).acos();
+            (angle / max_angle).ceil() + 1.0
+        }
+        _ => (CIRCLE_POINTS as f64 * angle / (2.0 * std::f64::consts::PI)).round(),
+    };
+    if count.is_finite() {
+        (count as usize).clamp(4, MAX_ARC_POINTS)
+    } else {
+        4
+    }
+}
+
 #[derive(Debug)]
 pub enum Curve {
     // TODO: move this to a standalone struct?
@@ -14,8 +50,39 @@ pub enum Curve {
         dir: bool,
     },
     Line,
-    BsplineCurveWithKnots(SampledCurve<3>),
-    NurbsCurve(SampledCurve<4>),
+    /// Parabolas and hyperbolas, stored like an ellipse as a transform to
+    /// and from a flat "conic plane".  In the cplane, a parabola is the
+    /// curve `(t², 2t)` and a hyperbola is `(cosh(t), sinh(t))`.  Both are
+    /// open, so they're always walked from start to end.
+    Parabola {
+        cplane_from_world: DMat4,
+        world_from_cplane: DMat4,
+    },
+    Hyperbola {
+        cplane_from_world: DMat4,
+        world_from_cplane: DMat4,
+    },
+    /// B-spline and NURBS curves.  `closed` and `dir` match the fields of
+    /// an ellipse, and are only used if the curve is periodic, to decide
+    /// which way to walk around it.
+    BsplineCurveWithKnots {
+        curve: SampledCurve<3>,
+        closed: bool,
+        dir: bool,
+    },
+    NurbsCurve {
+        curve: SampledCurve<4>,
+        closed: bool,
+        dir: bool,
+    },
+    /// Straight segments through a list of points, where point `i` is at
+    /// parameter `i`.  `closed` and `dir` are only used if the first and
+    /// last points are the same.
+    Polyline {
+        points: Vec<DVec3>,
+        closed: bool,
+        dir: bool,
+    },
 }
 
 impl Curve {
@@ -28,14 +95,8 @@ impl Curve {
         closed: bool,
         dir: bool,
     ) -> Self {
-        // Build a rotation matrix to go from flat (XY) to 3D space
-        let world_from_eplane = Surface::make_affine_transform(
-            axis,
-            radius1 * ref_direction,
-            radius2 * axis.cross(&ref_direction),
-            location,
-        );
-        let eplane_from_world = world_from_eplane.try_inverse().expect("Could not invert");
+        let (world_from_eplane, eplane_from_world) =
+            conic_transforms(location, axis, ref_direction, radius1, radius2);
         Self::Ellipse {
             world_from_eplane,
             eplane_from_world,
@@ -55,27 +116,307 @@ impl Curve {
         Self::new_ellipse(location, axis, ref_direction, radius, radius, closed, dir)
     }
 
+    /// Builds a parabola with its vertex at `location`, opening towards
+    /// `ref_direction`
+    pub fn new_parabola(
+        location: DVec3,
+        axis: DVec3,
+        ref_direction: DVec3,
+        focal_dist: f64,
+    ) -> Self {
+        let (world_from_cplane, cplane_from_world) =
+            conic_transforms(location, axis, ref_direction, focal_dist, focal_dist);
+        Self::Parabola {
+            world_from_cplane,
+            cplane_from_world,
+        }
+    }
+
+    /// Builds one branch of a hyperbola centered on `location`, with its
+    /// vertex at `location + semi_axis * ref_direction`
+    pub fn new_hyperbola(
+        location: DVec3,
+        axis: DVec3,
+        ref_direction: DVec3,
+        semi_axis: f64,
+        semi_imag_axis: f64,
+    ) -> Self {
+        let (world_from_cplane, cplane_from_world) =
+            conic_transforms(location, axis, ref_direction, semi_axis, semi_imag_axis);
+        Self::Hyperbola {
+            world_from_cplane,
+            cplane_from_world,
+        }
+    }
+
     pub fn new_line() -> Self {
         Self::Line
     }
 
-    fn curve_points<const N: usize>(u: DVec3, v: DVec3, curve: &SampledCurve<N>) -> Vec<DVec3>
+    pub fn new_polyline(points: Vec<DVec3>, closed: bool, dir: bool) -> Self {
+        Self::Polyline {
+            points,
+            closed,
+            dir,
+        }
+    }
+
+    /// Evaluates the curve at a parameter value, which is an angle (in
+    /// radians) for circles and ellipses.  Lines don't store enough data to
+    /// be evaluated, so they return `None`.
+    pub fn point_at(&self, t: f64) -> Option<DVec3> {
+        match self {
+            Self::Line => None,
+            Self::Ellipse {
+                world_from_eplane, ..
+            } => Some((world_from_eplane * DVec4::new(t.cos(), t.sin(), 0.0, 1.0)).xyz()),
+            Self::Parabola {
+                world_from_cplane, ..
+            } => Some((world_from_cplane * DVec4::new(t * t, 2.0 * t, 0.0, 1.0)).xyz()),
+            Self::Hyperbola {
+                world_from_cplane, ..
+            } => Some((world_from_cplane * DVec4::new(t.cosh(), t.sinh(), 0.0, 1.0)).xyz()),
+            Self::BsplineCurveWithKnots { curve, .. } => Some(curve.curve().point(t)),
+            Self::NurbsCurve { curve, .. } => Some(curve.curve().point(t)),
+            Self::Polyline { points, .. } => Some(polyline_point(points, t)),
+        }
+    }
+
+    /// Returns the derivative of the curve with respect to its parameter,
+    /// or `None` for lines
+    pub fn derivative_at(&self, t: f64) -> Option<DVec3> {
+        let d = match self {
+            Self::Line => return None,
+            Self::Ellipse {
+                world_from_eplane, ..
+            } => world_from_eplane * DVec4::new(-t.sin(), t.cos(), 0.0, 0.0),
+            Self::Parabola {
+                world_from_cplane, ..
+            } => world_from_cplane * DVec4::new(2.0 * t, 2.0, 0.0, 0.0),
+            Self::Hyperbola {
+                world_from_cplane, ..
+            } => world_from_cplane * DVec4::new(t.sinh(), t.cosh(), 0.0, 0.0),
+            Self::BsplineCurveWithKnots { curve, .. } => {
+                return Some(curve.curve().derivatives::<1>(t)[1])
+            }
+            Self::NurbsCurve { curve, .. } => return Some(curve.curve().derivatives::<1>(t)[1]),
+            Self::Polyline { points, .. } => {
+                let n = points.len() - 1;
+                let i = (t.clamp(0.0, n as f64).floor() as usize).min(n.saturating_sub(1));
+                return Some(points.get(i + 1).map_or(DVec3::zeros(), |p| p - points[i]));
+            }
+        };
+        Some(d.xyz())
+    }
+
+    /// Returns the parameter of a point on a parabola or hyperbola, which
+    /// only depends on its position along the cplane's Y axis
+    fn conic_param(&self, p: DVec3) -> Option<f64> {
+        match self {
+            Self::Parabola {
+                cplane_from_world, ..
+            } => Some((cplane_from_world * DVec4::new(p.x, p.y, p.z, 1.0)).y / 2.0),
+            Self::Hyperbola {
+                cplane_from_world, ..
+            } => Some(
+                (cplane_from_world * DVec4::new(p.x, p.y, p.z, 1.0))
+                    .y
+                    .asinh(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Finds the closest point on the curve to `p`
+    pub fn project(&self, p: DVec3) -> DVec3 {
+        match self {
+            Self::Line => p,
+            Self::Ellipse {
+                eplane_from_world, ..
+            } => {
+                let e = eplane_from_world * DVec4::new(p.x, p.y, p.z, 1.0);
+                self.point_at(e.y.atan2(e.x)).unwrap()
+            }
+            Self::Parabola { .. } | Self::Hyperbola { .. } => {
+                self.point_at(self.conic_param(p).unwrap()).unwrap()
+            }
+            Self::BsplineCurveWithKnots { curve, .. } => curve.curve().point(curve.u_from_point(p)),
+            Self::NurbsCurve { curve, .. } => curve.curve().point(curve.u_from_point(p)),
+            Self::Polyline { points, .. } => polyline_point(points, polyline_param(points, p)),
+        }
+    }
+
+    /// Returns the start and end of the curve's natural parameter range.
+    /// Circles and ellipses start and end at parameter 0; lines, parabolas
+    /// and hyperbolas are unbounded, so they return `None`.
+    pub fn ends(&self) -> Option<(DVec3, DVec3)> {
+        match self {
+            Self::Line | Self::Parabola { .. } | Self::Hyperbola { .. } => None,
+            Self::Ellipse { .. } => self.point_at(0.0).map(|p| (p, p)),
+            Self::BsplineCurveWithKnots { curve, .. } => {
+                let c = curve.curve();
+                Some((c.point(c.min_u()), c.point(c.max_u())))
+            }
+            Self::NurbsCurve { curve, .. } => {
+                let c = curve.curve();
+                Some((c.point(c.min_u()), c.point(c.max_u())))
+            }
+            Self::Polyline { points, .. } => Some((*points.first()?, *points.last()?)),
+        }
+    }
+
+    /// Samples a parabola or hyperbola from `u` to `v`, splitting segments
+    /// in half until their midpoints are within `tolerance` of the curve
+    fn conic_points(&self, u: DVec3, v: DVec3, tolerance: Option<f64>) -> Vec<DVec3> {
+        let t_start = self.conic_param(u).unwrap();
+        let t_end = self.conic_param(v).unwrap();
+        let tol = match tolerance {
+            Some(tol) if tol > 0.0 => tol,
+            _ => (v - u).norm() * CONIC_TOLERANCE,
+        };
+        let mut out = vec![u];
+        let mut prev = (t_start, u);
+        for i in 1..=CONIC_SEGMENTS {
+            let t = t_start + (t_end - t_start) * i as f64 / CONIC_SEGMENTS as f64;
+            let p = if i == CONIC_SEGMENTS {
+                v
+            } else {
+                self.point_at(t).unwrap()
+            };
+            self.subdivide(prev, (t, p), tol, 0, &mut out);
+            prev = (t, p);
+        }
+        out
+    }
+
+    /// Recursively splits the segment from `a` to `b` (each of which is a
+    /// parameter and its point), pushing every point after `a`
+    fn subdivide(
+        &self,
+        a: (f64, DVec3),
+        b: (f64, DVec3),
+        tol: f64,
+        depth: u32,
+        out: &mut Vec<DVec3>,
+    ) {
+        let t = (a.0 + b.0) / 2.0;
+        let p = self.point_at(t).unwrap();
+        let d = b.1 - a.1;
+        let frac = if d.norm_squared() > 0.0 {
+            ((p - a.1).dot(&d) / d.norm_squared()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        // Each level doubles the number of points, so this stops at
+        // MAX_ARC_POINTS
+        let max_depth = (MAX_ARC_POINTS / CONIC_SEGMENTS).ilog2();
+        if depth < max_depth && (a.1 + d * frac - p).norm() > tol {
+            self.subdivide(a, (t, p), tol, depth + 1, out);
+            self.subdivide((t, p), b, tol, depth + 1, out);
+        } else {
+            out.push(b.1);
+        }
+    }
+
+    fn polyline_points(
+        u: DVec3,
+        v: DVec3,
+        points: &[DVec3],
+        closed: bool,
+        dir: bool,
+    ) -> Vec<DVec3> {
+        let t_start = polyline_param(points, u);
+        let mut t_end = polyline_param(points, v);
+        let n = points.len() - 1;
+        let period = n as f64;
+        if is_loop(points) {
+            if closed {
+                t_end = if dir {
+                    t_start + period
+                } else {
+                    t_start - period
+                };
+            } else if dir && t_end <= t_start {
+                t_end += period;
+            } else if !dir && t_end >= t_start {
+                t_end -= period;
+            }
+        }
+
+        // Every vertex strictly between the two ends is included, wrapping
+        // around if the polyline is a loop
+        let (lo, hi) = (t_start.min(t_end), t_start.max(t_end));
+        let mut inner: Vec<DVec3> = ((lo.floor() as i64)..=(hi.ceil() as i64))
+            .filter(|i| (*i as f64) > lo && (*i as f64) < hi)
+            .map(|i| points[i.rem_euclid(n as i64) as usize])
+            .collect();
+        if t_start > t_end {
+            inner.reverse();
+        }
+        let mut out = vec![u];
+        out.extend(inner);
+        out.push(v);
+        out
+    }
+
+    fn curve_points<const N: usize>(
+        u: DVec3,
+        v: DVec3,
+        curve: &SampledCurve<N>,
+        closed: bool,
+        dir: bool,
+        tolerance: Option<f64>,
+    ) -> Vec<DVec3>
     where
         NdBsplineCurve<N>: AbstractCurve,
     {
         let t_start = curve.u_from_point(u);
-        let t_end = curve.u_from_point(v);
-        let mut c = curve.as_polyline(t_start, t_end, 8);
+        let mut t_end = curve.u_from_point(v);
+        if curve.is_periodic() {
+            // Pick the end parameter so that we walk in the edge's direction,
+            // wrapping across the seam if needed
+            let period = curve.period();
+            if closed {
+                t_end = if dir {
+                    t_start + period
+                } else {
+                    t_start - period
+                };
+            } else if dir && t_end <= t_start {
+                t_end += period;
+            } else if !dir && t_end >= t_start {
+                t_end -= period;
+            }
+        }
+        let mut c = match tolerance {
+            Some(tol) if tol > 0.0 => curve.tessellate(t_start, t_end, tol),
+            _ if curve.is_periodic() => curve.as_polyline_periodic(t_start, t_end, 8),
+            _ => curve.as_polyline(t_start, t_end, 8),
+        };
         c[0] = u;
         *c.last_mut().unwrap() = v;
         c
     }
 
-    pub fn build(&self, u: DVec3, v: DVec3) -> Vec<DVec3> {
+    /// Samples the curve between two points.  For curves other than lines
+    /// and polylines, `tolerance` is the largest allowed distance between
+    /// the curve and the resulting polyline; without it, full circles get 64
+    /// segments and splines get 8 per knot span.
+    pub fn build(&self, u: DVec3, v: DVec3, tolerance: Option<f64>) -> Vec<DVec3> {
         match self {
             Self::Line => vec![u, v],
-            Self::BsplineCurveWithKnots(curve) => Self::curve_points(u, v, curve),
-            Self::NurbsCurve(curve) => Self::curve_points(u, v, curve),
+            Self::Parabola { .. } | Self::Hyperbola { .. } => self.conic_points(u, v, tolerance),
+            Self::BsplineCurveWithKnots { curve, closed, dir } => {
+                Self::curve_points(u, v, curve, *closed, *dir, tolerance)
+            }
+            Self::NurbsCurve { curve, closed, dir } => {
+                Self::curve_points(u, v, curve, *closed, *dir, tolerance)
+            }
+            Self::Polyline {
+                points,
+                closed,
+                dir,
+            } => Self::polyline_points(u, v, points, *closed, *dir),
             Self::Ellipse {
                 eplane_from_world,
                 world_from_eplane,
@@ -103,11 +444,12 @@ impl Curve {
                     v_ang -= PI2;
                 }
 
-                const N: usize = 64;
-                let count = 4.max(
-                    (N as f64 * (u_ang - v_ang).abs() / (2.0 * std::f64::consts::PI)).round()
-                        as usize,
-                );
+                let radius = world_from_eplane
+                    .column(0)
+                    .xyz()
+                    .norm()
+                    .max(world_from_eplane.column(1).xyz().norm());
+                let count = arc_points(radius, (u_ang - v_ang).abs(), tolerance);
 
                 let mut out_world = vec![u];
                 // Walk around the circle, using the true positions for start
@@ -127,3 +469,137 @@ impl Curve {
         }
     }
 }
+
+/// Builds transforms to and from the flat plane of a conic, with the
+/// plane's X and Y axes scaled to `x_scale` and `y_scale`
+fn conic_transforms(
+    location: DVec3,
+    axis: DVec3,
+    ref_direction: DVec3,
+    x_scale: f64,
+    y_scale: f64,
+) -> (DMat4, DMat4) {
+    // Build a rotation matrix to go from flat (XY) to 3D space
+    let world_from_plane = Surface::make_affine_transform(
+        axis,
+        x_scale * ref_direction,
+        y_scale * axis.cross(&ref_direction),
+        location,
+    );
+    let plane_from_world = world_from_plane.try_inverse().expect("Could not invert");
+    (world_from_plane, plane_from_world)
+}
+
+/// Checks whether a polyline is a closed loop, i.e. its first and last points
+/// are the same (up to rounding)
+fn is_loop(points: &[DVec3]) -> bool {
+    let scale = points.iter().map(|p| p.amax()).fold(1.0, f64::max);
+    points.len() > 2 && (points[0] - points[points.len() - 1]).norm() <= 1e-9 * scale
+}
+
+/// Evaluates a polyline at parameter `t`, where point `i` is at parameter
+/// `i`.  Out-of-range parameters are clamped.
+fn polyline_point(points: &[DVec3], t: f64) -> DVec3 {
+    let n = points.len() - 1;
+    let t = t.clamp(0.0, n as f64);
+    let i = (t.floor() as usize).min(n.saturating_sub(1));
+    if n == 0 {
+        points[0]
+    } else {
+        glm::lerp(&points[i], &points[i + 1], t - i as f64)
+    }
+}
+
+/// Returns the parameter of the closest point on a polyline to `p`
+fn polyline_param(points: &[DVec3], p: DVec3) -> f64 {
+    let mut best = (f64::INFINITY, 0.0);
+    for (i, w) in points.windows(2).enumerate() {
+        let d = w[1] - w[0];
+        let frac = if d.norm_squared() > 0.0 {
+            ((p - w[0]).dot(&d) / d.norm_squared()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let dist = (w[0] + d * frac - p).norm_squared();
+        if dist < best.0 {
+            best = (dist, i as f64 + frac);
+        }
+    }
+    best.1
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_arc_points() {
+        // Without a tolerance, a full circle gets a fixed number of points
+        assert_eq!(arc_points(1.0, 2.0 * PI, None), 64);
+        assert_eq!(arc_points(1000.0, 2.0 * PI, None), 64);
+        assert_eq!(arc_points(1.0, 0.01, None), 4);
+
+        // With a tolerance, the chord gap stays within bounds
+        for (r, tol) in [(1.0, 0.05), (10.0, 0.05), (10.0, 0.001), (0.5, 0.2)] {
+            let n = arc_points(r, PI, Some(tol));
+            let step = PI / (n - 1) as f64;
+            assert!(r * (1.0 - (step / 2.0).cos()) <= tol, "{} {}", r, tol);
+        }
+        // Tighter tolerances and larger radii need more points
+        assert!(arc_points(10.0, PI, Some(0.001)) > arc_points(10.0, PI, Some(0.05)));
+        assert!(arc_points(10.0, PI, Some(0.05)) > arc_points(1.0, PI, Some(0.05)));
+
+        // A tolerance larger than the radius still gives a sane curve, and
+        // a tiny one is capped
+        assert_eq!(arc_points(1.0, PI, Some(5.0)), 4);
+        assert_eq!(arc_points(1.0, PI, Some(1e-12)), MAX_ARC_POINTS);
+    }
+
+    #[test]
+    fn test_conics() {
+        let (o, z, x) = (DVec3::new(1.0, 2.0, 3.0), DVec3::z(), DVec3::x());
+        let parabola = Curve::new_parabola(o, z, x, 0.5);
+        let hyperbola = Curve::new_hyperbola(o, z, x, 2.0, 1.0);
+        let ellipse = Curve::new_ellipse(o, z, x, 2.0, 1.0, false, true);
+
+        // Points land on the implicit curves, relative to the origin
+        for t in [-2.0, -0.5, 0.0, 0.3, 1.5] {
+            let p = parabola.point_at(t).unwrap() - o;
+            assert!((p.y * p.y - 4.0 * 0.5 * p.x).abs() < 1e-12);
+            let p = hyperbola.point_at(t).unwrap() - o;
+            assert!((p.x * p.x / 4.0 - p.y * p.y - 1.0).abs() < 1e-12);
+            assert!(p.x > 0.0);
+        }
+        assert_eq!(parabola.ends(), None);
+        assert_eq!(hyperbola.ends(), None);
+
+        // Derivatives match finite differences
+        let h = 1e-6;
+        for c in [&parabola, &hyperbola, &ellipse] {
+            for t in [-1.0, 0.0, 0.7] {
+                let d = (c.point_at(t + h).unwrap() - c.point_at(t - h).unwrap()) / (2.0 * h);
+                assert!((d - c.derivative_at(t).unwrap()).norm() < 1e-6);
+            }
+        }
+
+        // Sampling stays within tolerance, walking from start to end
+        for c in [&parabola, &hyperbola] {
+            let (u, v) = (c.point_at(-1.5).unwrap(), c.point_at(2.0).unwrap());
+            let coarse = c.build(u, v, Some(0.01));
+            let pts = c.build(u, v, Some(1e-4));
+            assert!(pts.len() > coarse.len());
+            assert_eq!((pts[0], pts[pts.len() - 1]), (u, v));
+            for w in pts.windows(2) {
+                let (ta, tb) = (c.conic_param(w[0]).unwrap(), c.conic_param(w[1]).unwrap());
+                assert!(tb > ta);
+                let mid = c.point_at((ta + tb) / 2.0).unwrap();
+                let d = (w[1] - w[0]).normalize();
+                let off = (mid - w[0]) - d * (mid - w[0]).dot(&d);
+                assert!(off.norm() <= 1e-4, "{}", off.norm());
+            }
+        }
+    }
+}
@@ -0,0 +1,1033 @@
+use std::convert::TryInto;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use nalgebra_glm::{DVec3, U32Vec3};
+use serde_json::json;
+
+use crate::{
+    convention::CoordinateConvention,
+    load::LoadError,
+    mesh::{Mesh, Triangle, Vertex},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error("Could not pick an export format for {0:?} (expected .stl, .obj, .ply, or .glb)")]
+    UnknownFormat(PathBuf),
+
+    #[error("Could not write {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// The mesh to export could not be loaded
+    #[error(transparent)]
+    Load(#[from] LoadError),
+}
+
+/// Mesh file formats which we can write
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Format {
+    /// Binary STL
+    Stl,
+    /// Wavefront OBJ, with one group per part
+    Obj,
+    /// Binary PLY, with vertex normals and colors
+    Ply,
+    /// Binary glTF, with one primitive per part
+    Glb,
+}
+
+impl Format {
+    /// Every supported format
+    pub const ALL: [Self; 4] = [Format::Stl, Format::Obj, Format::Ply, Format::Glb];
+
+    /// Picks a format based on a file's extension (case-insensitive)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_extension(path.extension()?.to_str()?)
+    }
+
+    /// Picks a format from an extension (without the leading `.`), ignoring
+    /// case
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let ext = ext.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|f| f.extension() == ext)
+    }
+
+    /// Returns the usual file extension for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Stl => "stl",
+            Format::Obj => "obj",
+            Format::Ply => "ply",
+            Format::Glb => "glb",
+        }
+    }
+
+    /// Returns the coordinate convention which files in this format use
+    /// unless told otherwise.  glTF is specified as Y-up and in meters;
+    /// the other formats have no convention, so they keep STEP's.
+    pub fn default_convention(&self) -> CoordinateConvention {
+        match self {
+            Format::Glb => CoordinateConvention::GLTF,
+            Format::Stl | Format::Obj | Format::Ply => CoordinateConvention::STEP,
+        }
+    }
+}
+
+/// Extra data to include when exporting a mesh
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ExportOptions {
+    /// If set, OBJ and glTF files also include the mesh's boundary edges
+    /// and its feature edges which are sharper than this angle (in
+    /// degrees), as line primitives.  Other formats can't store lines, so
+    /// they ignore this.
+    pub edge_angle: Option<f64>,
+    /// Coordinate convention to write in, which overrides the format's
+    /// [default](Format::default_convention).  The mesh being exported is
+    /// assumed to be in STEP's own coordinates.
+    pub convention: Option<CoordinateConvention>,
+}
+
+/// Writes the mesh to a file, picking the format from its extension
+pub fn save(mesh: &Mesh, path: &Path) -> Result<(), ExportError> {
+    let format =
+        Format::from_path(path).ok_or_else(|| ExportError::UnknownFormat(path.to_owned()))?;
+    save_as(mesh, path, format)
+}
+
+/// Writes the mesh to a file in the given format
+pub fn save_as(mesh: &Mesh, path: &Path, format: Format) -> Result<(), ExportError> {
+    save_with(mesh, path, format, &ExportOptions::default())
+}
+
+/// Writes the mesh to a file in the given format, with extra options
+pub fn save_with(
+    mesh: &Mesh,
+    path: &Path,
+    format: Format,
+    opts: &ExportOptions,
+) -> Result<(), ExportError> {
+    let convention = opts
+        .convention
+        .unwrap_or_else(|| format.default_convention());
+    let converted;
+    let mesh = if convention.is_identity() {
+        mesh
+    } else {
+        let mut m = mesh.clone();
+        m.apply_convention(&convention);
+        converted = m;
+        &converted
+    };
+    let write = || -> std::io::Result<()> {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let lines = match (format, opts.edge_angle) {
+            (Format::Obj | Format::Glb, Some(angle)) => edge_lines(mesh, angle),
+            _ => vec![],
+        };
+        match format {
+            Format::Stl => write_stl(mesh, &mut out)?,
+            Format::Obj => write_obj_with_lines(mesh, &lines, &mut out)?,
+            Format::Ply => write_ply(mesh, &mut out)?,
+            Format::Glb => write_glb_with_lines(mesh, &lines, &mut out)?,
+        }
+        out.flush()
+    };
+    write().map_err(|source| ExportError::Io {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+/// Returns the mesh's boundary and feature edges, joined into polylines of
+/// vertex indices
+pub fn edge_lines(mesh: &Mesh, angle_deg: f64) -> Vec<Vec<u32>> {
+    let mut edges = mesh.boundary_edges();
+    edges.extend(mesh.feature_edges(angle_deg));
+    mesh.polylines(&edges)
+}
+
+/// Returns the positions of each triangle's vertices
+fn triangle_positions(mesh: &Mesh) -> impl Iterator<Item = [DVec3; 3]> + '_ {
+    mesh.triangles
+        .iter()
+        .map(move |t| [0, 1, 2].map(|i| mesh.verts[t.verts[i] as usize].pos))
+}
+
+/// Writes the mesh as a binary STL, with per-facet normals
+pub fn write_stl<W: Write>(mesh: &Mesh, out: &mut W) -> std::io::Result<()> {
+    let mut header = [b' '; 80];
+    let name = b"foxtrot";
+    header[..name.len()].copy_from_slice(name);
+    out.write_all(&header)?;
+
+    let n: u32 = mesh.triangles.len().try_into().expect("Too many triangles");
+    out.write_all(&n.to_le_bytes())?;
+
+    let write_vec = |out: &mut W, v: DVec3| -> std::io::Result<()> {
+        for f in [v.x, v.y, v.z] {
+            out.write_all(&(f as f32).to_le_bytes())?;
+        }
+        Ok(())
+    };
+    for [a, b, c] in triangle_positions(mesh) {
+        let norm = (b - a).cross(&(c - a));
+        let norm = if norm.norm() > 0.0 {
+            norm.normalize()
+        } else {
+            norm
+        };
+        write_vec(out, norm)?;
+        for v in [a, b, c] {
+            write_vec(out, v)?;
+        }
+        out.write_all(&[0, 0])?; // attributes
+    }
+    Ok(())
+}
+
+/// Writes the mesh as an ASCII OBJ file, with vertex normals and one group
+/// per part (if the mesh has part information).  Unlike the other formats,
+/// this writes [quads](Mesh::quads) as four-sided faces.
+pub fn write_obj<W: Write>(mesh: &Mesh, out: &mut W) -> std::io::Result<()> {
+    write_obj_with_lines(mesh, &[], out)
+}
+
+/// Writes the mesh as an ASCII OBJ file, followed by a group of polylines
+/// (e.g. from [`edge_lines`]) which index into the mesh's vertices
+pub fn write_obj_with_lines<W: Write>(
+    mesh: &Mesh,
+    lines: &[Vec<u32>],
+    out: &mut W,
+) -> std::io::Result<()> {
+    writeln!(out, "# Exported by foxtrot")?;
+    for v in &mesh.verts {
+        writeln!(out, "v {} {} {}", v.pos.x, v.pos.y, v.pos.z)?;
+    }
+    for v in &mesh.verts {
+        writeln!(out, "vn {} {} {}", v.norm.x, v.norm.y, v.norm.z)?;
+    }
+
+    // OBJ indices are 1-based, and we use the same index for the position
+    // and normal of each vertex.  Pairs of triangles which form quads are
+    // written as a single face.
+    let write_faces = |out: &mut W, range: std::ops::Range<usize>| -> std::io::Result<()> {
+        let mut t = range.start;
+        while t < range.end {
+            if t + 1 < range.end && mesh.quads.binary_search(&t).is_ok() {
+                let [a, b, c, d] = mesh.quad(t).map(|v| v + 1);
+                writeln!(out, "f {}//{} {}//{} {}//{} {}//{}", a, a, b, b, c, c, d, d)?;
+                t += 2;
+            } else {
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.triangles[t].verts[i] + 1);
+                writeln!(out, "f {}//{} {}//{} {}//{}", a, a, b, b, c, c)?;
+                t += 1;
+            }
+        }
+        Ok(())
+    };
+    if mesh.parts.is_empty() {
+        write_faces(out, 0..mesh.triangles.len())?;
+    } else {
+        for (i, p) in mesh.parts.iter().enumerate() {
+            writeln!(out, "g part{}", i)?;
+            write_faces(out, p.triangles.clone())?;
+        }
+    }
+    if !lines.is_empty() {
+        writeln!(out, "g edges")?;
+        for line in lines {
+            write!(out, "l")?;
+            for v in line {
+                write!(out, " {}", v + 1)?;
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the triangle ranges to write as separate groups:  one per part,
+/// or a single range if the mesh has no part information
+fn part_ranges(mesh: &Mesh) -> Vec<std::ops::Range<usize>> {
+    if mesh.parts.is_empty() {
+        std::iter::once(0..mesh.triangles.len()).collect()
+    } else {
+        mesh.parts.iter().map(|p| p.triangles.clone()).collect()
+    }
+}
+
+/// Writes the mesh as a binary (little-endian) PLY file, with vertex
+/// normals and colors.  Colors include alpha if the mesh has a color
+/// channel.
+pub fn write_ply<W: Write>(mesh: &Mesh, out: &mut W) -> std::io::Result<()> {
+    writeln!(out, "ply")?;
+    writeln!(out, "format binary_little_endian 1.0")?;
+    writeln!(out, "comment Exported by foxtrot")?;
+    writeln!(out, "element vertex {}", mesh.verts.len())?;
+    for p in ["x", "y", "z", "nx", "ny", "nz"] {
+        writeln!(out, "property float {}", p)?;
+    }
+    let channels = if mesh.colors.is_some() { 4 } else { 3 };
+    for p in &["red", "green", "blue", "alpha"][..channels] {
+        writeln!(out, "property uchar {}", p)?;
+    }
+    writeln!(out, "element face {}", mesh.triangles.len())?;
+    writeln!(out, "property list uchar uint vertex_indices")?;
+    writeln!(out, "end_header")?;
+
+    for (v, c) in mesh.verts.iter().zip(mesh.vertex_colors()) {
+        for f in [v.pos.x, v.pos.y, v.pos.z, v.norm.x, v.norm.y, v.norm.z] {
+            out.write_all(&(f as f32).to_le_bytes())?;
+        }
+        out.write_all(&c[..channels])?;
+    }
+    for t in &mesh.triangles {
+        out.write_all(&[3])?;
+        for i in t.verts.iter() {
+            out.write_all(&i.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the mesh as a binary glTF 2.0 file, with vertex normals and
+/// colors (as RGBA if the mesh has a color channel, or RGB otherwise).
+/// Each part becomes a separate primitive of a single mesh; since
+/// vertex positions are already transformed, there's a single node with no
+/// transform of its own.
+pub fn write_glb<W: Write>(mesh: &Mesh, out: &mut W) -> std::io::Result<()> {
+    write_glb_with_lines(mesh, &[], out)
+}
+
+/// Writes the mesh as a binary glTF 2.0 file, plus one more primitive which
+/// draws polylines (e.g. from [`edge_lines`]) as line segments
+pub fn write_glb_with_lines<W: Write>(
+    mesh: &Mesh,
+    lines: &[Vec<u32>],
+    out: &mut W,
+) -> std::io::Result<()> {
+    let n = mesh.verts.len();
+    let mut bin = Vec::with_capacity(n * 36 + mesh.triangles.len() * 12);
+    let mut lo = [f32::INFINITY; 3];
+    let mut hi = [-f32::INFINITY; 3];
+    for v in &mesh.verts {
+        for (i, f) in [v.pos.x, v.pos.y, v.pos.z].into_iter().enumerate() {
+            let f = f as f32;
+            lo[i] = lo[i].min(f);
+            hi[i] = hi[i].max(f);
+            bin.extend_from_slice(&f.to_le_bytes());
+        }
+    }
+    for v in &mesh.verts {
+        for f in [v.norm.x, v.norm.y, v.norm.z] {
+            bin.extend_from_slice(&(f as f32).to_le_bytes());
+        }
+    }
+    let color_offset = bin.len();
+    match &mesh.colors {
+        Some(colors) => {
+            for c in colors {
+                for b in c {
+                    bin.extend_from_slice(&(*b as f32 / 255.0).to_le_bytes());
+                }
+            }
+        }
+        None => {
+            for v in &mesh.verts {
+                for f in [v.color.x, v.color.y, v.color.z] {
+                    bin.extend_from_slice(&(f.clamp(0.0, 1.0) as f32).to_le_bytes());
+                }
+            }
+        }
+    }
+    let index_offset = bin.len();
+    for t in &mesh.triangles {
+        for i in t.verts.iter() {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+    }
+    let num_segments: usize = lines.iter().map(|l| l.len().saturating_sub(1)).sum();
+    for w in lines.iter().flat_map(|l| l.windows(2)) {
+        for i in w {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+    }
+
+    // Accessors 0-2 are the vertex attributes, followed by one index
+    // accessor per primitive.  glTF doesn't allow empty meshes or buffers,
+    // so an empty mesh produces a scene with no nodes.
+    let float = |view: usize, ty: &str| {
+        json!({
+            "bufferView": view,
+            "componentType": 5126, // FLOAT
+            "count": n,
+            "type": ty,
+        })
+    };
+    let color_type = if mesh.colors.is_some() {
+        "VEC4"
+    } else {
+        "VEC3"
+    };
+    let mut accessors = vec![float(0, "VEC3"), float(1, "VEC3"), float(2, color_type)];
+    accessors[0]["min"] = json!(lo);
+    accessors[0]["max"] = json!(hi);
+    let mut primitives = vec![];
+    for r in part_ranges(mesh).into_iter().filter(|r| !r.is_empty()) {
+        primitives.push(json!({
+            "attributes": { "POSITION": 0, "NORMAL": 1, "COLOR_0": 2 },
+            "indices": accessors.len(),
+            "mode": 4, // TRIANGLES
+        }));
+        accessors.push(json!({
+            "bufferView": 3,
+            "byteOffset": r.start * 12,
+            "componentType": 5125, // UNSIGNED_INT
+            "count": r.len() * 3,
+            "type": "SCALAR",
+        }));
+    }
+    if num_segments > 0 && !primitives.is_empty() {
+        primitives.push(json!({
+            "attributes": { "POSITION": 0 },
+            "indices": accessors.len(),
+            "mode": 1, // LINES
+        }));
+        accessors.push(json!({
+            "bufferView": 3,
+            "byteOffset": mesh.triangles.len() * 12,
+            "componentType": 5125, // UNSIGNED_INT
+            "count": num_segments * 2,
+            "type": "SCALAR",
+        }));
+    }
+    let view = |offset: usize, length: usize, target: u32| {
+        json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": length,
+            "target": target,
+        })
+    };
+    let mut json = json!({
+        "asset": { "version": "2.0", "generator": "foxtrot" },
+        "scene": 0,
+        "scenes": [{ "nodes": [] }],
+    });
+    if !primitives.is_empty() {
+        json["scenes"][0]["nodes"] = json!([0]);
+        json["nodes"] = json!([{ "mesh": 0 }]);
+        json["meshes"] = json!([{ "primitives": primitives }]);
+        json["accessors"] = json!(accessors);
+        json["bufferViews"] = json!([
+            view(0, n * 12, 34962), // ARRAY_BUFFER
+            view(n * 12, n * 12, 34962),
+            view(color_offset, index_offset - color_offset, 34962),
+            view(index_offset, bin.len() - index_offset, 34963), // ELEMENT_ARRAY_BUFFER
+        ]);
+        json["buffers"] = json!([{ "byteLength": bin.len() }]);
+    } else {
+        bin.clear();
+    }
+
+    // Chunks are padded to 4-byte alignment, JSON with spaces and the
+    // binary chunk with zeros
+    let mut json = serde_json::to_vec(&json).expect("Could not serialize glTF");
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+    let bin_chunk = if bin.is_empty() { 0 } else { 8 + bin.len() };
+    let total: u32 = (12 + 8 + json.len() + bin_chunk)
+        .try_into()
+        .expect("Mesh is too large for GLB");
+    out.write_all(b"glTF")?;
+    out.write_all(&2u32.to_le_bytes())?;
+    out.write_all(&total.to_le_bytes())?;
+    for (chunk, kind) in [(&json, b"JSON"), (&bin, b"BIN\0")] {
+        if chunk.is_empty() {
+            continue;
+        }
+        out.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        out.write_all(kind)?;
+        out.write_all(chunk)?;
+    }
+    Ok(())
+}
+
+/// Reads triangles back from a binary STL.  This is mostly useful for
+/// checking that exported files round-trip.
+pub fn read_stl(data: &[u8]) -> std::io::Result<Vec<[DVec3; 3]>> {
+    use std::io::{Error, ErrorKind};
+    let bad = |msg| Error::new(ErrorKind::InvalidData, msg);
+    let count = data.get(80..84).ok_or_else(|| bad("STL is too short"))?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    let body = &data[84..];
+    if body.len() != count * 50 {
+        return Err(bad("STL has the wrong length for its triangle count"));
+    }
+    let f = |b: &[u8]| f32::from_le_bytes(b.try_into().unwrap()) as f64;
+    Ok(body
+        .chunks_exact(50)
+        .map(|c| {
+            // Skip the normal, then read three vertices
+            [0, 1, 2].map(|i| {
+                let v = &c[12 + i * 12..24 + i * 12];
+                DVec3::new(f(&v[0..4]), f(&v[4..8]), f(&v[8..12]))
+            })
+        })
+        .collect())
+}
+
+/// Reads triangles back from an OBJ file.  Only `v` and `f` statements are
+/// supported, which is enough to read our own output; quads are split into
+/// two triangles along their first diagonal.
+pub fn read_obj(text: &str) -> std::io::Result<Vec<[DVec3; 3]>> {
+    use std::io::{Error, ErrorKind};
+    let bad = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+    let mut verts = vec![];
+    let mut tris = vec![];
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let xyz: Vec<f64> = words
+                    .map(|w| {
+                        w.parse()
+                            .map_err(|_| bad(format!("Invalid vertex {:?}", line)))
+                    })
+                    .collect::<Result<_, _>>()?;
+                if xyz.len() < 3 {
+                    return Err(bad(format!("Invalid vertex {:?}", line)));
+                }
+                verts.push(DVec3::new(xyz[0], xyz[1], xyz[2]));
+            }
+            Some("f") => {
+                let idx: Vec<usize> = words
+                    .map(|w| {
+                        w.split('/')
+                            .next()
+                            .and_then(|i| i.parse::<usize>().ok())
+                            .filter(|i| *i >= 1 && *i <= verts.len())
+                            .ok_or_else(|| bad(format!("Invalid face {:?}", line)))
+                    })
+                    .collect::<Result<_, _>>()?;
+                match idx.len() {
+                    3 => tris.push([0, 1, 2].map(|i| verts[idx[i] - 1])),
+                    4 => {
+                        tris.push([0, 1, 2].map(|i| verts[idx[i] - 1]));
+                        tris.push([0, 2, 3].map(|i| verts[idx[i] - 1]));
+                    }
+                    _ => return Err(bad(format!("Unsupported face {:?}", line))),
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(tris)
+}
+
+/// Reads triangles back from a binary little-endian PLY file (see
+/// [`read_ply_mesh`])
+pub fn read_ply(data: &[u8]) -> std::io::Result<Vec<[DVec3; 3]>> {
+    let mesh = read_ply_mesh(data)?;
+    Ok(triangle_positions(&mesh).collect())
+}
+
+/// Reads a mesh back from a binary little-endian PLY file.  Vertices may
+/// have any number of scalar properties, of which positions, normals, and
+/// colors are used; if there's an `alpha` property, the mesh gets a color
+/// channel.  Faces must be a single list of triangles, which is enough to
+/// read our own output.
+pub fn read_ply_mesh(data: &[u8]) -> std::io::Result<Mesh> {
+    use std::io::{Error, ErrorKind};
+    let bad = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+    let size = |ty: &str| match ty {
+        "char" | "uchar" | "int8" | "uint8" => Some(1),
+        "short" | "ushort" | "int16" | "uint16" => Some(2),
+        "int" | "uint" | "float" | "int32" | "uint32" | "float32" => Some(4),
+        "double" | "float64" => Some(8),
+        _ => None,
+    };
+
+    const END: &[u8] = b"end_header\n";
+    let end = data
+        .windows(END.len())
+        .position(|w| w == END)
+        .ok_or_else(|| bad("PLY has no end_header".to_owned()))?;
+    let header =
+        std::str::from_utf8(&data[..end]).map_err(|_| bad("PLY header is not UTF-8".to_owned()))?;
+    let body = &data[end + END.len()..];
+
+    let mut lines = header.lines();
+    if lines.next() != Some("ply") {
+        return Err(bad("Missing PLY magic".to_owned()));
+    }
+    let (mut num_verts, mut num_faces) = (0, 0);
+    let mut stride = 0;
+    // Offsets of the float properties (position then normal), followed by
+    // the byte properties (color then alpha)
+    const FLOATS: [&str; 6] = ["x", "y", "z", "nx", "ny", "nz"];
+    const BYTES: [&str; 4] = ["red", "green", "blue", "alpha"];
+    let mut floats = [None; 6];
+    let mut bytes = [None; 4];
+    let mut element = "";
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", "binary_little_endian", _] => (),
+            ["format", f, _] => return Err(bad(format!("Unsupported PLY format {:?}", f))),
+            ["element", name, n] => {
+                element = name;
+                let n = n
+                    .parse()
+                    .map_err(|_| bad(format!("Invalid element {:?}", line)))?;
+                match *name {
+                    "vertex" => num_verts = n,
+                    "face" => num_faces = n,
+                    _ => return Err(bad(format!("Unsupported element {:?}", name))),
+                }
+            }
+            ["property", "list", "uchar", "uint" | "int", _] if element == "face" => (),
+            ["property", ty, name] if element == "vertex" => {
+                let s = size(ty).ok_or_else(|| bad(format!("Unknown type {:?}", ty)))?;
+                if let Some(i) = FLOATS.iter().position(|p| p == name) {
+                    if *ty != "float" {
+                        return Err(bad(format!("Expected float for {:?}", name)));
+                    }
+                    floats[i] = Some(stride);
+                } else if let Some(i) = BYTES.iter().position(|p| p == name) {
+                    if *ty != "uchar" {
+                        return Err(bad(format!("Expected uchar for {:?}", name)));
+                    }
+                    bytes[i] = Some(stride);
+                }
+                stride += s;
+            }
+            ["property", ..] => return Err(bad(format!("Unsupported property {:?}", line))),
+            _ => (),
+        }
+    }
+    if floats[..3].iter().any(Option::is_none) {
+        return Err(bad("PLY vertices have no position".to_owned()));
+    }
+
+    // Missing properties are read as zero
+    let f = |b: &[u8], i: Option<usize>| {
+        i.map_or(0.0, |i| {
+            f32::from_le_bytes(b[i..i + 4].try_into().unwrap()) as f64
+        })
+    };
+    let vec3 = |b: &[u8], i: &[Option<usize>]| DVec3::new(f(b, i[0]), f(b, i[1]), f(b, i[2]));
+    let rgba = |b: &[u8]| bytes.map(|i| i.map_or(0, |i| b[i]));
+    let vertex_data = body
+        .get(..num_verts * stride)
+        .ok_or_else(|| bad("PLY is too short for its vertices".to_owned()))?;
+    let mut mesh = Mesh::default();
+    for b in vertex_data.chunks_exact(stride) {
+        let [r, g, b_, _] = rgba(b).map(|c| c as f64 / 255.0);
+        mesh.verts.push(Vertex {
+            pos: vec3(b, &floats[..3]),
+            norm: vec3(b, &floats[3..]),
+            color: DVec3::new(r, g, b_),
+        });
+    }
+    if bytes[3].is_some() {
+        mesh.colors = Some(vertex_data.chunks_exact(stride).map(rgba).collect());
+    }
+
+    let faces = &body[num_verts * stride..];
+    if faces.len() != num_faces * 13 {
+        return Err(bad("PLY has the wrong length for its face count".to_owned()));
+    }
+    for c in faces.chunks_exact(13) {
+        if c[0] != 3 {
+            return Err(bad("Non-triangular face in PLY".to_owned()));
+        }
+        let mut verts = U32Vec3::zeros();
+        for (i, v) in verts.iter_mut().enumerate() {
+            *v = u32::from_le_bytes(c[1 + i * 4..5 + i * 4].try_into().unwrap());
+            if *v as usize >= num_verts {
+                return Err(bad(format!("Invalid vertex index {}", v)));
+            }
+        }
+        mesh.triangles.push(Triangle { verts });
+    }
+    Ok(mesh)
+}
+
+/// Reads triangles back from a binary glTF file.  Only indexed triangle
+/// primitives with float positions and `u32` indices are supported, which
+/// is enough to read our own output; line primitives are skipped.
+pub fn read_glb(data: &[u8]) -> std::io::Result<Vec<[DVec3; 3]>> {
+    use std::io::{Error, ErrorKind};
+    let bad = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_owned());
+    let u32_at = |i: usize| -> std::io::Result<usize> {
+        let b = data.get(i..i + 4).ok_or_else(|| bad("GLB is too short"))?;
+        Ok(u32::from_le_bytes(b.try_into().unwrap()) as usize)
+    };
+    if data.get(0..4) != Some(b"glTF") || u32_at(4)? != 2 {
+        return Err(bad("Not a glTF 2.0 binary"));
+    }
+    if u32_at(8)? != data.len() {
+        return Err(bad("GLB has the wrong length"));
+    }
+    let json_len = u32_at(12)?;
+    if data.get(16..20) != Some(b"JSON") {
+        return Err(bad("GLB is missing its JSON chunk"));
+    }
+    let json: serde_json::Value = data
+        .get(20..20 + json_len)
+        .and_then(|j| serde_json::from_slice(j).ok())
+        .ok_or_else(|| bad("Invalid GLB JSON"))?;
+    // The binary chunk is optional, and omitted for empty meshes
+    let bin_start = 20 + json_len;
+    let bin = if bin_start == data.len() {
+        &[]
+    } else {
+        let bin_len = u32_at(bin_start)?;
+        data.get(bin_start + 8..bin_start + 8 + bin_len)
+            .ok_or_else(|| bad("GLB is too short for its binary chunk"))?
+    };
+
+    // Returns the bytes and element count for an accessor, checking that
+    // it has the expected component type
+    let accessor = |i: &serde_json::Value, ty: u64| -> std::io::Result<(&[u8], usize)> {
+        let field = |v: &serde_json::Value, k| v.get(k).and_then(|v| v.as_u64());
+        let a = i
+            .as_u64()
+            .and_then(|i| json["accessors"].get(i as usize))
+            .ok_or_else(|| bad("Invalid accessor"))?;
+        if field(a, "componentType") != Some(ty) {
+            return Err(bad("Unsupported accessor type"));
+        }
+        let view = field(a, "bufferView")
+            .and_then(|v| json["bufferViews"].get(v as usize))
+            .ok_or_else(|| bad("Invalid buffer view"))?;
+        let start =
+            (field(view, "byteOffset").unwrap_or(0) + field(a, "byteOffset").unwrap_or(0)) as usize;
+        let count = field(a, "count").ok_or_else(|| bad("Accessor has no count"))? as usize;
+        let len = count * if ty == 5126 { 12 } else { 4 };
+        let bytes = bin
+            .get(start..start + len)
+            .ok_or_else(|| bad("Accessor is out of bounds"))?;
+        Ok((bytes, count))
+    };
+
+    let f = |b: &[u8]| f32::from_le_bytes(b.try_into().unwrap()) as f64;
+    let mut out = vec![];
+    let meshes = json["meshes"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    for p in meshes
+        .iter()
+        .flat_map(|m| m["primitives"].as_array())
+        .flatten()
+    {
+        match p.get("mode").and_then(|m| m.as_u64()).unwrap_or(4) {
+            4 => (),
+            1..=3 => continue, // LINES, LINE_LOOP, LINE_STRIP
+            _ => return Err(bad("Non-triangle primitive")),
+        }
+        let (pos, _) = accessor(&p["attributes"]["POSITION"], 5126)?;
+        let verts: Vec<DVec3> = pos
+            .chunks_exact(12)
+            .map(|b| DVec3::new(f(&b[0..4]), f(&b[4..8]), f(&b[8..12])))
+            .collect();
+        let (indices, count) = accessor(&p["indices"], 5125)?;
+        if count % 3 != 0 {
+            return Err(bad("Index count is not a multiple of 3"));
+        }
+        for t in indices.chunks_exact(12) {
+            let mut tri = [DVec3::zeros(); 3];
+            for (i, v) in tri.iter_mut().enumerate() {
+                let j = u32::from_le_bytes(t[i * 4..i * 4 + 4].try_into().unwrap());
+                *v = *verts
+                    .get(j as usize)
+                    .ok_or_else(|| bad("Invalid vertex index"))?;
+            }
+            out.push(tri);
+        }
+    }
+    Ok(out)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Part;
+    use nalgebra_glm::DMat4;
+
+    /// Builds a mesh with two single-triangle parts
+    fn two_part_mesh() -> Mesh {
+        let mut mesh = Mesh::default();
+        for (i, z) in [0.0, 1.5].iter().enumerate() {
+            for d in [DVec3::zeros(), DVec3::x(), DVec3::y()] {
+                mesh.verts.push(Vertex {
+                    pos: d + DVec3::new(0.0, 0.0, *z),
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+            let j = 3 * i as u32;
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(j, j + 1, j + 2),
+            });
+            mesh.parts.push(Part {
+                shape: i,
+                triangles: i..(i + 1),
+                transform: DMat4::identity(),
+            });
+        }
+        mesh
+    }
+
+    fn expected(mesh: &Mesh) -> Vec<[DVec3; 3]> {
+        triangle_positions(mesh).collect()
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("a.stl")), Some(Format::Stl));
+        assert_eq!(Format::from_path(Path::new("dir/a.STL")), Some(Format::Stl));
+        assert_eq!(Format::from_path(Path::new("a.obj")), Some(Format::Obj));
+        assert_eq!(Format::from_path(Path::new("a.ply")), Some(Format::Ply));
+        assert_eq!(Format::from_path(Path::new("a.Glb")), Some(Format::Glb));
+        assert_eq!(Format::from_path(Path::new("a.step")), None);
+        assert_eq!(Format::from_path(Path::new("stl")), None);
+        for f in Format::ALL {
+            assert_eq!(Format::from_extension(f.extension()), Some(f));
+        }
+        assert_eq!(Format::from_extension("GLB"), Some(Format::Glb));
+    }
+
+    #[test]
+    fn test_stl_round_trip() {
+        let mesh = two_part_mesh();
+        let mut out = vec![];
+        write_stl(&mesh, &mut out).unwrap();
+        assert_eq!(out.len(), 84 + 50 * 2);
+        assert_eq!(read_stl(&out).unwrap(), expected(&mesh));
+
+        // Facet normals are computed from the winding order
+        let n = f32::from_le_bytes(out[92..96].try_into().unwrap());
+        assert_eq!(n, 1.0);
+
+        // Truncated files are rejected
+        assert!(read_stl(&out[..100]).is_err());
+        assert!(read_stl(&out[..10]).is_err());
+    }
+
+    #[test]
+    fn test_obj_round_trip() {
+        let mesh = two_part_mesh();
+        let mut out = vec![];
+        write_obj(&mesh, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(read_obj(&text).unwrap(), expected(&mesh));
+        assert!(text.contains("g part0\n"));
+        assert!(text.contains("g part1\n"));
+
+        assert!(read_obj("v 0 0 0\nf 1 2 3\n").is_err());
+        assert!(read_obj("v 0 0\n").is_err());
+    }
+
+    #[test]
+    fn test_obj_quads() {
+        // A unit square, as a quad made of two triangles
+        let mut mesh = Mesh::default();
+        for p in [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]] {
+            mesh.verts.push(Vertex {
+                pos: DVec3::new(p[0], p[1], 0.0),
+                norm: DVec3::z(),
+                color: DVec3::zeros(),
+            });
+        }
+        for t in [[0, 1, 2], [0, 2, 3]] {
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::from(t),
+            });
+        }
+        mesh.quads.push(0);
+        assert_eq!(mesh.quad(0), [0, 1, 2, 3]);
+
+        let mut out = vec![];
+        write_obj(&mesh, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("f 1//1 2//2 3//3 4//4\n"), "{}", text);
+        assert_eq!(read_obj(&text).unwrap(), expected(&mesh));
+
+        // Other formats write the quad's triangles
+        let mut out = vec![];
+        write_stl(&mesh, &mut out).unwrap();
+        assert_eq!(read_stl(&out).unwrap(), expected(&mesh));
+    }
+
+    #[test]
+    fn test_ply_round_trip() {
+        let mut mesh = two_part_mesh();
+        mesh.verts[0].color = DVec3::new(1.0, 0.5, 0.0);
+        let mut out = vec![];
+        write_ply(&mesh, &mut out).unwrap();
+        assert!(out.starts_with(b"ply\nformat binary_little_endian 1.0\n"));
+        assert_eq!(read_ply(&out).unwrap(), expected(&mesh));
+
+        // The first vertex's color comes right after its position and normal
+        let body = out.windows(11).position(|w| w == b"end_header\n").unwrap() + 11;
+        assert_eq!(out[body + 24..body + 27], [255, 128, 0]);
+
+        assert!(read_ply(&out[..out.len() - 1]).is_err());
+        assert!(read_ply(b"ply\nformat ascii 1.0\nend_header\n").is_err());
+
+        // Without a color channel, colors are opaque and there's no alpha
+        let read = read_ply_mesh(&out).unwrap();
+        assert!(read.colors.is_none());
+        assert_eq!(read.vertex_colors(), mesh.vertex_colors());
+        assert_eq!(read.verts[0].norm, DVec3::z());
+    }
+
+    #[test]
+    fn test_ply_color_channel() {
+        let mut mesh = two_part_mesh();
+        let colors: Vec<[u8; 4]> = (0..6u8).map(|i| [i, 2 * i, 255 - i, 40 * i]).collect();
+        mesh.colors = Some(colors.clone());
+        let mut out = vec![];
+        write_ply(&mesh, &mut out).unwrap();
+        let end = out.windows(11).position(|w| w == b"end_header\n").unwrap();
+        let header = std::str::from_utf8(&out[..end]).unwrap();
+        assert!(header.contains("property uchar alpha\n"));
+
+        // Colors (including alpha) survive the round trip, along with the
+        // triangles
+        let read = read_ply_mesh(&out).unwrap();
+        assert_eq!(read.colors, Some(colors));
+        assert_eq!(read_ply(&out).unwrap(), expected(&mesh));
+
+        // Combining with a mesh that has no color channel keeps the channel,
+        // with that mesh's vertex colors filled in
+        let mut plain = two_part_mesh();
+        plain.verts[0].color = DVec3::new(0.0, 1.0, 0.0);
+        let combined = Mesh::combine(mesh, plain);
+        let mut out = vec![];
+        write_ply(&combined, &mut out).unwrap();
+        let read = read_ply_mesh(&out).unwrap();
+        assert_eq!(read.colors.as_ref().unwrap()[6], [0, 255, 0, 255]);
+        assert_eq!(read.colors, combined.colors);
+    }
+
+    #[test]
+    fn test_glb_round_trip() {
+        let mesh = two_part_mesh();
+        let mut out = vec![];
+        write_glb(&mesh, &mut out).unwrap();
+        assert_eq!(&out[0..4], b"glTF");
+        assert_eq!(out.len() % 4, 0);
+        assert_eq!(read_glb(&out).unwrap(), expected(&mesh));
+
+        // Each part is a separate primitive
+        let json_len = u32::from_le_bytes(out[12..16].try_into().unwrap()) as usize;
+        let json: serde_json::Value = serde_json::from_slice(&out[20..20 + json_len]).unwrap();
+        assert_eq!(json["meshes"][0]["primitives"].as_array().unwrap().len(), 2);
+        assert_eq!(json["accessors"][0]["max"][2], 1.5);
+        assert_eq!(json["accessors"][2]["type"], "VEC3");
+
+        // A color channel is written as RGBA
+        let mut colored = mesh.clone();
+        colored.colors = Some(vec![[255, 0, 0, 128]; 6]);
+        let mut out = vec![];
+        write_glb(&colored, &mut out).unwrap();
+        assert_eq!(read_glb(&out).unwrap(), expected(&mesh));
+        let json_len = u32::from_le_bytes(out[12..16].try_into().unwrap()) as usize;
+        let json: serde_json::Value = serde_json::from_slice(&out[20..20 + json_len]).unwrap();
+        assert_eq!(json["accessors"][2]["type"], "VEC4");
+        assert_eq!(json["bufferViews"][2]["byteLength"], 6 * 16);
+
+        // An empty mesh is still a valid file
+        let mut empty = vec![];
+        write_glb(&Mesh::default(), &mut empty).unwrap();
+        assert!(read_glb(&empty).unwrap().is_empty());
+
+        assert!(read_glb(&out[..out.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn test_edge_lines() {
+        // Each triangle is open, so its boundary is a closed loop
+        let mesh = two_part_mesh();
+        let lines = edge_lines(&mesh, 30.0);
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.len() == 4 && l[0] == l[3]));
+
+        let mut out = vec![];
+        write_obj_with_lines(&mesh, &lines, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(read_obj(&text).unwrap(), expected(&mesh));
+        assert!(text.contains("g edges\n"));
+        assert!(text.contains("\nl 1 2 3 1\n"));
+        assert_eq!(text.lines().filter(|l| l.starts_with("l ")).count(), 2);
+
+        // Lines are an extra primitive, which readers can skip
+        let mut out = vec![];
+        write_glb_with_lines(&mesh, &lines, &mut out).unwrap();
+        assert_eq!(read_glb(&out).unwrap(), expected(&mesh));
+        let json_len = u32::from_le_bytes(out[12..16].try_into().unwrap()) as usize;
+        let json: serde_json::Value = serde_json::from_slice(&out[20..20 + json_len]).unwrap();
+        let prims = json["meshes"][0]["primitives"].as_array().unwrap();
+        assert_eq!(prims.len(), 3);
+        assert_eq!(prims[2]["mode"], 1);
+        assert_eq!(json["accessors"][5]["count"], 12);
+        assert_eq!(json["accessors"][5]["byteOffset"], 24);
+    }
+
+    #[test]
+    fn test_save_convention() {
+        let mesh = two_part_mesh();
+        let dir = std::env::temp_dir();
+        let path = |ext| dir.join(format!("foxtrot-convention-{}.{}", std::process::id(), ext));
+        let gltf = |[a, b, c]: [DVec3; 3]| [a, b, c].map(|p| DVec3::new(p.x, p.z, -p.y) * 0.001);
+
+        // glTF defaults to Y-up meters, while STL keeps STEP's coordinates
+        let (stl, glb) = (path("stl"), path("glb"));
+        save(&mesh, &stl).unwrap();
+        save(&mesh, &glb).unwrap();
+        let read = |p: &Path| std::fs::read(p).unwrap();
+        assert_eq!(read_stl(&read(&stl)).unwrap(), expected(&mesh));
+        let tris: Vec<_> = expected(&mesh).into_iter().map(gltf).collect();
+        for (a, b) in read_glb(&read(&glb)).unwrap().iter().zip(&tris) {
+            for i in 0..3 {
+                assert!((a[i] - b[i]).norm() < 1e-9, "{:?} != {:?}", a, b);
+            }
+        }
+
+        // Both defaults can be overridden
+        let opts = ExportOptions {
+            convention: Some(CoordinateConvention::STEP),
+            ..Default::default()
+        };
+        save_with(&mesh, &glb, Format::Glb, &opts).unwrap();
+        assert_eq!(read_glb(&read(&glb)).unwrap(), expected(&mesh));
+        let opts = ExportOptions {
+            convention: Some(CoordinateConvention::GLTF),
+            ..Default::default()
+        };
+        save_with(&mesh, &stl, Format::Stl, &opts).unwrap();
+        assert_eq!(read_stl(&read(&stl)).unwrap().len(), tris.len());
+        assert!((read_stl(&read(&stl)).unwrap()[1][0] - tris[1][0]).norm() < 1e-6);
+
+        std::fs::remove_file(&stl).unwrap();
+        std::fs::remove_file(&glb).unwrap();
+    }
+
+    #[test]
+    fn test_save_unknown_format() {
+        let mesh = two_part_mesh();
+        let path = std::env::temp_dir().join("foxtrot-export-test.step");
+        assert!(matches!(
+            save(&mesh, &path),
+            Err(ExportError::UnknownFormat(..))
+        ));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,633 @@
+//! Structured sample grids, for tools (e.g. simulation preprocessors) which
+//! would rather see each face as rows and columns of samples than as a
+//! triangle soup.
+//!
+//! A face's grid samples its surface's own (u, v) parameters over the range
+//! spanned by the face's boundary, without trimming, and marks which samples
+//! are on the face.  Planes are parameterized by position, cylinders and
+//! cones by angle and height, spheres by longitude and latitude, tori by
+//! their major and minor angles, and splines by their knot parameters.
+//!
+//! The grid is either returned as-is (see [`MeshTopology::Grid`]) or used to
+//! build quads (see [`MeshTopology::QuadDominant`]).
+use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
+use std::ops::Range;
+use std::str::FromStr;
+
+use nalgebra_glm::{DMat4, DVec2, DVec3};
+use nurbs::AbstractSurface;
+
+use crate::{
+    curve::arc_points,
+    mesh::{FaceGrid, Vertex},
+    surface::Surface,
+};
+
+#[cfg(doc)]
+use crate::mesh::Mesh;
+
+/// Number of cells along the longer side of a grid on a surface which
+/// doesn't curve, since there's no tolerance to pick the cell size from
+const FLAT_CELLS: usize = 16;
+
+/// Largest number of cells along either side of a grid
+const MAX_CELLS: usize = 1024;
+
+/// Distance (as a fraction of the grid's size) within which a sample is on
+/// the face's boundary
+const BOUNDARY_EPSILON: f64 = 1e-7;
+
+/// Distance (in cells) from the face's boundary within which samples aren't
+/// added to the triangulation when building quads
+const NEAR_CELLS: f64 = 0.25;
+
+/// Which kind of mesh to build from each face
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum MeshTopology {
+    /// Triangles only
+    #[default]
+    Triangles,
+    /// Triangles, with each pair which fills a cell of the face's sample
+    /// grid listed as a quad (see [`Mesh::quads`]).  Cells which are cut by
+    /// the face's boundary stay as triangles.
+    QuadDominant,
+    /// Triangles, along with each face's untrimmed sample grid (see
+    /// [`Mesh::grids`])
+    Grid,
+}
+
+impl FromStr for MeshTopology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "triangles" => Ok(MeshTopology::Triangles),
+            "quads" | "quad-dominant" => Ok(MeshTopology::QuadDominant),
+            "grid" => Ok(MeshTopology::Grid),
+            _ => Err(format!(
+                "Invalid mesh topology {:?} (expected triangles, quads, or grid)",
+                s
+            )),
+        }
+    }
+}
+
+/// How a grid sample relates to its face
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Sample {
+    Outside,
+    Inside,
+    /// Inside, but closer to the boundary than [`NEAR_CELLS`]
+    Near,
+    /// On the face's boundary, and perhaps on one of its points (given as
+    /// an index into the face's boundary points)
+    Boundary(Option<usize>),
+}
+
+/// Maps between points on a surface and its (u, v) parameters
+struct Chart<'a> {
+    surf: &'a Surface,
+    /// Transform from the surface's own coordinates to the world
+    mat: DMat4,
+    /// Radius of a cone at v = 0, which the surface doesn't store
+    radius: f64,
+}
+
+impl<'a> Chart<'a> {
+    /// Builds a chart for a prepared surface (see
+    /// [`Surface::lower_vertices`]), given the points on a face's boundary
+    fn new(surf: &'a Surface, boundary: &[Vertex]) -> Option<Self> {
+        let mat = match surf {
+            Surface::Plane { mat_i, .. } => mat_i.try_inverse()?,
+            Surface::Cylinder { mat, .. }
+            | Surface::Cone { mat, .. }
+            | Surface::Sphere { mat, .. }
+            | Surface::Torus { mat, .. } => *mat,
+            Surface::Bspline(..) | Surface::Nurbs(..) => DMat4::identity(),
+        };
+        let radius = match surf {
+            Surface::Cone { mat_i, angle, .. } if !boundary.is_empty() => {
+                boundary
+                    .iter()
+                    .map(|v| {
+                        let p = mat_i * v.pos.push(1.0);
+                        p.xy().norm() - p.z * angle.tan()
+                    })
+                    .sum::<f64>()
+                    / boundary.len() as f64
+            }
+            _ => 0.0,
+        };
+        Some(Self { surf, mat, radius })
+    }
+
+    /// Returns whether each parameter is an angle, which wraps around
+    fn periodic(&self) -> (bool, bool) {
+        match self.surf {
+            Surface::Cylinder { .. } | Surface::Cone { .. } | Surface::Sphere { .. } => {
+                (true, false)
+            }
+            Surface::Torus { .. } => (true, true),
+            _ => (false, false),
+        }
+    }
+
+    /// Returns the parameters of a point on the surface, given its 3D
+    /// position and its 2D position from [`Surface::lower_vertices`]
+    fn params(&self, p: DVec3, lowered: (f64, f64)) -> DVec2 {
+        let local = |mat_i: &DMat4| (mat_i * p.push(1.0)).xyz();
+        match self.surf {
+            Surface::Plane { mat_i, .. } => local(mat_i).xy(),
+            Surface::Cylinder { mat_i, .. } | Surface::Cone { mat_i, .. } => {
+                let q = local(mat_i);
+                DVec2::new(q.y.atan2(q.x), q.z)
+            }
+            Surface::Sphere { mat_i, radius, .. } => {
+                let q = local(mat_i);
+                DVec2::new(q.y.atan2(q.x), (q.z / radius).clamp(-1.0, 1.0).asin())
+            }
+            Surface::Torus {
+                mat_i,
+                major_radius,
+                ..
+            } => {
+                // The torus's axis is its local X axis (see `Surface::lower`)
+                let q = local(mat_i);
+                DVec2::new(q.y.atan2(q.z), q.x.atan2(q.yz().norm() - major_radius))
+            }
+            Surface::Bspline(s) => DVec2::new(lowered.0, lowered.1 / s.surf.aspect_ratio()),
+            Surface::Nurbs(s) => DVec2::new(lowered.0, lowered.1 / s.surf.aspect_ratio()),
+        }
+    }
+
+    /// Returns the point on the surface at the given parameters
+    fn point(&self, uv: DVec2) -> DVec3 {
+        let (u, v) = (uv.x, uv.y);
+        let q = match self.surf {
+            Surface::Plane { .. } => DVec3::new(u, v, 0.0),
+            Surface::Cylinder { radius, .. } => DVec3::new(radius * u.cos(), radius * u.sin(), v),
+            Surface::Cone { angle, .. } => {
+                let r = self.radius + v * angle.tan();
+                DVec3::new(r * u.cos(), r * u.sin(), v)
+            }
+            Surface::Sphere { radius, .. } => {
+                DVec3::new(v.cos() * u.cos(), v.cos() * u.sin(), v.sin()) * *radius
+            }
+            Surface::Torus {
+                major_radius,
+                minor_radius,
+                ..
+            } => {
+                let d = major_radius + minor_radius * v.cos();
+                DVec3::new(minor_radius * v.sin(), d * u.sin(), d * u.cos())
+            }
+            Surface::Bspline(s) => return s.surf.point(uv),
+            Surface::Nurbs(s) => return s.surf.point(uv),
+        };
+        (self.mat * q.push(1.0)).xyz()
+    }
+
+    /// Returns the length of the line between two sets of parameters, by
+    /// sampling the surface
+    fn length(&self, a: DVec2, b: DVec2) -> f64 {
+        const STEPS: usize = 32;
+        let at = |i: usize| self.point(a + (b - a) * (i as f64 / STEPS as f64));
+        (0..STEPS).map(|i| (at(i + 1) - at(i)).norm()).sum()
+    }
+
+    /// Picks the number of cells along u and v for a grid spanning the
+    /// given range of parameters.  Directions in which the surface curves
+    /// are split as finely as an arc of the same radius would be (see
+    /// [`arc_points`]); straight directions get cells of about the same
+    /// size.
+    fn cells(&self, lo: DVec2, hi: DVec2, tol: Option<f64>) -> (usize, usize) {
+        let d = hi - lo;
+        let mid = (lo + hi) / 2.0;
+
+        // Length of the grid's center line along each direction, and the
+        // radius of curvature (if any) in that direction
+        let (u, v) = match self.surf {
+            Surface::Cylinder { radius, .. } => ((radius * d.x, Some(*radius)), (d.y, None)),
+            Surface::Cone { angle, .. } => {
+                let r = (self.radius + lo.y * angle.tan())
+                    .abs()
+                    .max((self.radius + hi.y * angle.tan()).abs());
+                ((r * d.x, Some(r)), (d.y / angle.cos(), None))
+            }
+            Surface::Sphere { radius, .. } => {
+                ((radius * d.x, Some(*radius)), (radius * d.y, Some(*radius)))
+            }
+            Surface::Torus {
+                major_radius,
+                minor_radius,
+                ..
+            } => {
+                let r = major_radius + minor_radius;
+                (
+                    (r * d.x, Some(r)),
+                    (minor_radius * d.y, Some(*minor_radius)),
+                )
+            }
+            _ => (
+                (
+                    self.length(DVec2::new(lo.x, mid.y), DVec2::new(hi.x, mid.y)),
+                    None,
+                ),
+                (
+                    self.length(DVec2::new(mid.x, lo.y), DVec2::new(mid.x, hi.y)),
+                    None,
+                ),
+            ),
+        };
+        let curved = |(len, r): (f64, Option<f64>)| {
+            r.map(|r| (len, arc_points(r, len / r, tol).saturating_sub(1)))
+        };
+        let size = match (curved(u), curved(v)) {
+            (Some((a, n)), Some((b, m))) => (a / n as f64).min(b / m as f64),
+            (Some((a, n)), None) | (None, Some((a, n))) => a / n as f64,
+            (None, None) => u.0.max(v.0) / FLAT_CELLS as f64,
+        };
+        let count = |dir: (f64, Option<f64>)| {
+            let n = match curved(dir) {
+                Some((_, n)) => n,
+                None if size > 0.0 && size.is_finite() => (dir.0 / size).ceil() as usize,
+                None => 1,
+            };
+            n.clamp(1, MAX_CELLS)
+        };
+        (count(u), count(v))
+    }
+}
+
+/// Removes jumps of a full turn between consecutive angles
+fn unwrap(vals: impl Iterator<Item = f64>) -> Vec<f64> {
+    let mut out: Vec<f64> = vec![];
+    for v in vals {
+        out.push(match out.last() {
+            Some(prev) => v - ((v - prev) / TAU).round() * TAU,
+            None => v,
+        });
+    }
+    out
+}
+
+/// Returns the distance from a point to a line segment
+fn segment_distance(p: DVec2, a: DVec2, b: DVec2) -> f64 {
+    let ab = b - a;
+    let t = if ab.norm_squared() > 0.0 {
+        ((p - a).dot(&ab) / ab.norm_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (a + ab * t - p).norm()
+}
+
+/// Samples a face's surface on a regular grid in its (u, v) parameters.
+///
+/// `boundary` and `pts` are the face's boundary points, in 3D and as
+/// returned by [`Surface::lower_vertices`], and `loops` lists the ranges of
+/// points which form closed loops.  Returns the grid, along with how each
+/// sample relates to the face, or `None` if the face's boundary doesn't
+/// span an area in parameter space.
+pub(crate) fn sample_face(
+    id: usize,
+    surf: &Surface,
+    boundary: &[Vertex],
+    pts: &[(f64, f64)],
+    loops: &[Range<usize>],
+    tol: Option<f64>,
+) -> Option<(FaceGrid, Vec<Sample>)> {
+    let chart = Chart::new(surf, boundary)?;
+    let (u_periodic, v_periodic) = chart.periodic();
+
+    // Move each loop into parameter space, unwrapping angles so that loops
+    // are continuous.  Loops which wind all the way around a periodic
+    // direction (e.g. the rim of a cylinder without a seam) don't close, so
+    // they're tracked separately.
+    let mut polys: Vec<(Vec<DVec2>, Vec<usize>, bool)> = vec![];
+    for r in loops.iter().filter(|r| r.len() >= 2) {
+        let uv: Vec<DVec2> = r
+            .clone()
+            .map(|i| chart.params(boundary[i].pos, pts[i]))
+            .collect();
+        let continuous = |vals: Vec<f64>, periodic: bool| {
+            if periodic {
+                unwrap(vals.into_iter())
+            } else {
+                vals
+            }
+        };
+        let us = continuous(uv.iter().map(|p| p.x).collect(), u_periodic);
+        let vs = continuous(uv.iter().map(|p| p.y).collect(), v_periodic);
+        let mut poly: Vec<DVec2> = us
+            .iter()
+            .zip(&vs)
+            .map(|(u, v)| DVec2::new(*u, *v))
+            .collect();
+
+        // Line up later loops with the first, so that holes land inside
+        if let Some((first, ..)) = polys.first() {
+            let shift = |a: f64, b: f64| ((a - b) / TAU).round() * TAU;
+            let d = DVec2::new(
+                if u_periodic {
+                    shift(first[0].x, poly[0].x)
+                } else {
+                    0.0
+                },
+                if v_periodic {
+                    shift(first[0].y, poly[0].y)
+                } else {
+                    0.0
+                },
+            );
+            poly.iter_mut().for_each(|p| *p += d);
+        }
+
+        // Check whether the closing edge makes a full turn, which means that
+        // the loop winds around the surface
+        let last = *poly.last().unwrap();
+        let mut end = poly[0];
+        if u_periodic {
+            end.x = unwrap([last.x, end.x].into_iter())[1];
+        }
+        if v_periodic {
+            end.y = unwrap([last.y, end.y].into_iter())[1];
+        }
+        let winds = (end - poly[0]).norm() > PI;
+        let mut index: Vec<usize> = r.clone().collect();
+        if winds {
+            poly.push(end);
+            index.push(r.start);
+        }
+        polys.push((poly, index, winds));
+    }
+
+    let (mut lo, mut hi) = (DVec2::repeat(f64::INFINITY), DVec2::repeat(-f64::INFINITY));
+    for p in polys.iter().flat_map(|(poly, ..)| poly) {
+        lo = lo.inf(p);
+        hi = hi.sup(p);
+    }
+    let size = hi - lo;
+    if !(size.x > 0.0 && size.y > 0.0 && size.x.is_finite() && size.y.is_finite()) {
+        return None;
+    }
+    let (nu, nv) = chart.cells(lo, hi, tol);
+    let (cols, rows) = (nu + 1, nv + 1);
+
+    // Work in coordinates where the grid is a unit square
+    let norm = |p: &DVec2| (p - lo).component_div(&size);
+    let at = |r: usize, c: usize| DVec2::new(c as f64 / nu as f64, r as f64 / nv as f64);
+    let mut samples = vec![Sample::Outside; rows * cols];
+
+    // Scan each row for crossings with the boundary, then use the even-odd
+    // rule.  Loops which wind around the surface are closed by running off
+    // past the top of the grid, so that the area between two of them is
+    // inside.
+    let mut edges: Vec<(DVec2, DVec2)> = vec![];
+    for (poly, _, winds) in &polys {
+        let mut poly: Vec<DVec2> = poly.iter().map(norm).collect();
+        if *winds {
+            let (first, last) = (poly[0], *poly.last().unwrap());
+            poly.push(DVec2::new(last.x, 3.0));
+            poly.push(DVec2::new(first.x, 3.0));
+        }
+        for (i, a) in poly.iter().enumerate() {
+            edges.push((*a, poly[(i + 1) % poly.len()]));
+        }
+    }
+    for r in 0..rows {
+        let y = at(r, 0).y;
+        let mut xs: Vec<f64> = edges
+            .iter()
+            .filter(|(a, b)| (a.y > y) != (b.y > y))
+            .map(|(a, b)| a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y))
+            .collect();
+        xs.sort_by(f64::total_cmp);
+        for c in 0..cols {
+            let x = at(r, c).x;
+            if xs.partition_point(|s| *s < x) % 2 == 1 {
+                samples[r * cols + c] = Sample::Inside;
+            }
+        }
+    }
+
+    // Then find the samples which are on the boundary, and whether they're
+    // on any of its points, checking only the samples near each segment.
+    // Samples which are inside but close to the boundary are marked too,
+    // since they'd make slivers if they were added to the triangulation.
+    let eps = BOUNDARY_EPSILON;
+    let cell = DVec2::new(nu as f64, nv as f64);
+    for (poly, index, winds) in &polys {
+        let n = poly.len();
+        let segments = if *winds { n - 1 } else { n };
+        for i in 0..segments {
+            let j = (i + 1) % n;
+            let (a, b) = (norm(&poly[i]), norm(&poly[j]));
+            let span = |lo: f64, hi: f64, n: usize| {
+                let f = |t: f64| (t * n as f64).clamp(0.0, n as f64);
+                (f(lo).floor() as usize).saturating_sub(1)..=(f(hi).ceil() as usize + 1).min(n)
+            };
+            for r in span(a.y.min(b.y), a.y.max(b.y), nv) {
+                for c in span(a.x.min(b.x), a.x.max(b.x), nu) {
+                    let p = at(r, c);
+                    let s = &mut samples[r * cols + c];
+                    if segment_distance(p, a, b) > eps {
+                        let d = segment_distance(
+                            p.component_mul(&cell),
+                            a.component_mul(&cell),
+                            b.component_mul(&cell),
+                        );
+                        if *s == Sample::Inside && d < NEAR_CELLS {
+                            *s = Sample::Near;
+                        }
+                    } else if (p - a).norm() <= eps {
+                        *s = Sample::Boundary(Some(index[i]));
+                    } else if (p - b).norm() <= eps {
+                        *s = Sample::Boundary(Some(index[j]));
+                    } else if !matches!(s, Sample::Boundary(Some(_))) {
+                        *s = Sample::Boundary(None);
+                    }
+                }
+            }
+        }
+    }
+
+    let points = (0..rows)
+        .flat_map(|r| (0..cols).map(move |c| (r, c)))
+        .map(|(r, c)| chart.point(lo + at(r, c).component_mul(&size)))
+        .collect();
+    let grid = FaceGrid {
+        id,
+        rows,
+        cols,
+        lo,
+        hi,
+        points,
+        mask: samples.iter().map(|s| *s != Sample::Outside).collect(),
+    };
+    Some((grid, samples))
+}
+
+/// Adds the samples which are inside a face (and not too close to its
+/// boundary) as Steiner points, so that the triangulation follows the grid.  Returns the index of the point
+/// (in `pts`) at each sample, which is the boundary point for samples which
+/// land on one, or `None` for samples which aren't in the triangulation.
+pub(crate) fn add_samples(
+    surf: &Surface,
+    grid: &FaceGrid,
+    samples: &[Sample],
+    pts: &mut Vec<(f64, f64)>,
+    verts: &mut Vec<Vertex>,
+) -> Vec<Option<usize>> {
+    let size = grid.hi - grid.lo;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, s)| match s {
+            Sample::Outside | Sample::Near | Sample::Boundary(None) => None,
+            Sample::Boundary(Some(j)) => Some(*j),
+            Sample::Inside => {
+                let pos = grid.points[i];
+                let (r, c) = (i / grid.cols, i % grid.cols);
+                let uv = grid.lo
+                    + DVec2::new(
+                        size.x * c as f64 / (grid.cols - 1) as f64,
+                        size.y * r as f64 / (grid.rows - 1) as f64,
+                    );
+                let (lowered, norm) = match surf {
+                    Surface::Bspline(s) => (
+                        DVec2::new(uv.x, uv.y * s.surf.aspect_ratio()),
+                        surf.normal(pos, uv),
+                    ),
+                    Surface::Nurbs(s) => (
+                        DVec2::new(uv.x, uv.y * s.surf.aspect_ratio()),
+                        surf.normal(pos, uv),
+                    ),
+                    _ => {
+                        let lowered = surf.lower(pos).ok()?;
+                        (lowered, surf.normal(pos, lowered))
+                    }
+                };
+                pts.push((lowered.x, lowered.y));
+                verts.push(Vertex {
+                    pos,
+                    norm,
+                    color: DVec3::zeros(),
+                });
+                Some(pts.len() - 1)
+            }
+        })
+        .collect()
+}
+
+/// Pairs up triangles which fill a cell of a grid into quads.
+///
+/// `corners` is the index of the triangulation's point at each grid sample
+/// (row by row, with `cols` samples per row), or `None` for samples which
+/// aren't in the triangulation.  Returns the triangles reordered so that
+/// each quad's two triangles come first, one after the other, along with
+/// the number of quads.
+pub(crate) fn pair_quads(
+    cols: usize,
+    corners: &[Option<usize>],
+    triangles: Vec<(usize, usize, usize)>,
+) -> (Vec<(usize, usize, usize)>, usize) {
+    let key = |a: usize, b: usize, c: usize| {
+        let mut k = [a, b, c];
+        k.sort_unstable();
+        k
+    };
+    let lookup: HashMap<[usize; 3], usize> = triangles
+        .iter()
+        .enumerate()
+        .map(|(i, (a, b, c))| (key(*a, *b, *c), i))
+        .collect();
+
+    let rows = corners.len().checked_div(cols).unwrap_or(0);
+    let mut used = vec![false; triangles.len()];
+    let mut out = Vec::with_capacity(triangles.len());
+    for r in 0..rows.saturating_sub(1) {
+        for c in 0..cols - 1 {
+            let q = [(r, c), (r, c + 1), (r + 1, c + 1), (r + 1, c)]
+                .map(|(r, c)| corners[r * cols + c]);
+            let q = match q {
+                [Some(a), Some(b), Some(c), Some(d)] => [a, b, c, d],
+                _ => continue,
+            };
+            if (0..4).any(|i| q[i + 1..].contains(&q[i])) {
+                continue;
+            }
+            // A cell can be split along either diagonal
+            for [t1, t2] in [[[0, 1, 2], [0, 2, 3]], [[0, 1, 3], [1, 2, 3]]] {
+                let find = |t: [usize; 3]| lookup.get(&key(q[t[0]], q[t[1]], q[t[2]])).copied();
+                if let (Some(i), Some(j)) = (find(t1), find(t2)) {
+                    if !used[i] && !used[j] {
+                        used[i] = true;
+                        used[j] = true;
+                        out.push(triangles[i]);
+                        out.push(triangles[j]);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    let quads = out.len() / 2;
+    out.extend(
+        triangles
+            .into_iter()
+            .zip(used)
+            .filter(|(_, u)| !u)
+            .map(|(t, _)| t),
+    );
+    (out, quads)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("Grid".parse(), Ok(MeshTopology::Grid));
+        assert_eq!("quads".parse(), Ok(MeshTopology::QuadDominant));
+        assert_eq!(MeshTopology::default(), MeshTopology::Triangles);
+        assert!("hexes".parse::<MeshTopology>().is_err());
+    }
+
+    #[test]
+    fn test_pair_quads() {
+        // A 3x2 grid of samples (two cells), where the right-hand cell is
+        // split by an extra point in its middle
+        let corners = [0, 1, 2, 3, 4, 5].map(Some);
+        let tris = vec![
+            (0, 4, 3),
+            (1, 2, 6),
+            (0, 1, 4),
+            (2, 5, 6),
+            (5, 4, 6),
+            (4, 1, 6),
+        ];
+        let (out, quads) = pair_quads(3, &corners, tris.clone());
+        assert_eq!(quads, 1);
+        assert_eq!(out[..2], [(0, 1, 4), (0, 4, 3)]);
+        assert_eq!(out.len(), tris.len());
+
+        // Cells with missing or repeated corners stay as triangles
+        let corners = [Some(0), Some(1), None, Some(3), Some(4), Some(5)];
+        assert_eq!(pair_quads(3, &corners, tris.clone()).1, 1);
+        let corners = [0, 0, 2, 0, 4, 5].map(Some);
+        assert_eq!(pair_quads(3, &corners, tris).1, 0);
+    }
+
+    #[test]
+    fn test_unwrap() {
+        let out = unwrap([3.0, -3.0, -2.0, 3.0].into_iter());
+        assert!((out[1] - (TAU - 3.0)).abs() < 1e-12, "{:?}", out);
+        assert!((out[2] - (TAU - 2.0)).abs() < 1e-12, "{:?}", out);
+        assert!((out[3] - 3.0).abs() < 1e-12, "{:?}", out);
+    }
+}
@@ -1,8 +1,20 @@
+pub mod bvh;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod convention;
+pub mod coverage;
+pub mod crop;
 pub mod curve;
+pub mod export;
+pub mod grid;
+pub mod load;
 pub mod mesh;
+pub mod pcurve;
+pub mod section;
 pub mod stats;
 pub mod surface;
 pub mod triangulate;
+pub mod units;
 
 #[derive(thiserror::Error, Debug, Eq, PartialEq)]
 pub enum Error {
@@ -12,6 +24,15 @@ pub enum Error {
     #[error("Could not convert into a Surface")]
     UnknownSurfaceType,
 
+    #[error("Surface #{0} is not supported, and the face's boundary is too far from planar to triangulate without it")]
+    NonPlanarFallback(usize),
+
+    #[error("Curve #{0} is unbounded, so it can't be a segment of a composite curve")]
+    UnboundedCurve(usize),
+
+    #[error("Could not find the end of a trimmed curve")]
+    InvalidTrim,
+
     #[error("Could not convert into a Curve")]
     UnknownCurveType,
 
@@ -21,9 +42,24 @@ pub enum Error {
     #[error("Self-intersecting NURBS and b-spline surfaces are not implemented")]
     SelfIntersectingSurface,
 
-    #[error("Closed NURBS and b-spline curves are not implemented")]
-    ClosedCurve,
+    #[error("#{id} is missing or is not a {expected}")]
+    InvalidEntity { id: usize, expected: &'static str },
+
+    #[error("Face bound #{0} has no points")]
+    EmptyBound(usize),
+
+    #[error("Got a negative {what} in #{id}")]
+    Negative { id: usize, what: &'static str },
+
+    #[error("Could not resolve the references of #{id}: {source}")]
+    Graph {
+        id: usize,
+        source: step::walk::WalkError,
+    },
 
-    #[error("Self-intersecting NURBS and b-spline curves are not implemented")]
-    SelfIntersectingCurve,
+    #[error("Invalid b-spline data in #{id}: {source}")]
+    Nurbs {
+        id: usize,
+        source: nurbs::NurbsError,
+    },
 }
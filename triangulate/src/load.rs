@@ -0,0 +1,556 @@
+use std::path::{Path, PathBuf};
+
+// `std::time::Instant` panics on wasm32-unknown-unknown, so we use a crate
+// which falls back to `performance.now()` in the browser (and re-exports
+// the standard library's types everywhere else).
+use web_time::{Duration, Instant};
+
+use step::{ap214::Entity, step_file::StepFile};
+
+use crate::{
+    convention::CoordinateConvention,
+    crop::{self, Aabb},
+    grid::MeshTopology,
+    mesh::Mesh,
+    stats::Stats,
+    triangulate::{triangulate_faces, triangulate_streaming},
+    units::{length_unit, LengthUnit},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoadError {
+    #[error("Could not read {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// The file could not be split into entities
+    #[error(transparent)]
+    Step(#[from] step::Error),
+
+    #[error("No STEP entities could be parsed")]
+    Parse,
+
+    /// Every face failed to triangulate, and there were no curves.  This is
+    /// the first face's error.
+    #[error(transparent)]
+    Triangulate(#[from] crate::Error),
+
+    #[error("The file contains no triangulated geometry or curves")]
+    Empty,
+
+    /// The parser or triangulator panicked, which happens on malformed
+    /// files.  The panic's message is included.
+    #[error("{0}")]
+    Panicked(String),
+}
+
+fn no_progress(_done: usize, _total: usize) {}
+
+fn no_solid(_solid: &Mesh) {}
+
+/// Options for [`load_mesh`]
+pub struct TriangulateOptions<'a> {
+    /// Called with the number of solids finished and the total number of
+    /// solids.  With the `rayon` feature, this may be called from several
+    /// threads at once.
+    pub progress: &'a (dyn Fn(usize, usize) + Sync),
+    /// Largest allowed distance between a circular or elliptical edge and
+    /// its polyline, in model units.  If `None`, a fixed number of
+    /// segments is used.
+    pub tolerance: Option<f64>,
+    /// Called with each solid's triangles as soon as they're finished (see
+    /// [`triangulate_streaming`]), which lets a viewer draw partial
+    /// results.  Meshes loaded from the cache arrive all at once, so this
+    /// isn't called for them.
+    pub on_solid: &'a (dyn Fn(&Mesh) + Sync),
+    /// If set, faces which are entirely outside of this box (in model
+    /// coordinates) are skipped before meshing, and counted in
+    /// [`Stats::num_cropped`].  Faces which cross the box are kept whole.
+    pub crop: Option<Aabb>,
+    /// If set along with `crop`, triangles outside of the crop box are
+    /// also removed from the finished mesh (see [`crop::clip`])
+    pub clip: bool,
+    /// Coordinate convention for the finished mesh, which is applied after
+    /// cropping (so the crop box is in model coordinates) and after the
+    /// cache (so one cached mesh serves every convention).  By default,
+    /// meshes are left in STEP's own coordinates.
+    pub convention: CoordinateConvention,
+    /// Whether to pair triangles into quads, or to sample each face on a
+    /// grid, as well as triangulating it (see [`MeshTopology`])
+    pub topology: MeshTopology,
+    /// Directory in which to cache triangulated meshes (see
+    /// [`cache`](crate::cache)).  If `None`, every load parses and
+    /// triangulates from scratch.
+    #[cfg(feature = "cache")]
+    pub cache: Option<&'a Path>,
+}
+
+impl Default for TriangulateOptions<'_> {
+    fn default() -> Self {
+        Self {
+            progress: &no_progress,
+            tolerance: None,
+            on_solid: &no_solid,
+            crop: None,
+            clip: false,
+            convention: CoordinateConvention::default(),
+            topology: MeshTopology::default(),
+            #[cfg(feature = "cache")]
+            cache: None,
+        }
+    }
+}
+
+/// Summary of the entities parsed from a STEP file
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseReport {
+    /// Number of entities in the file's `DATA` section
+    pub entities: usize,
+    /// Number of entities which could not be parsed (and were skipped)
+    pub failed: usize,
+    /// Unit of length which the mesh's coordinates are in, if the file
+    /// declares one that we recognize
+    pub length_unit: Option<LengthUnit>,
+}
+
+impl ParseReport {
+    fn new(step: &StepFile) -> Self {
+        let mut out = Self::default();
+        for e in &step.0 {
+            match e {
+                Entity::_EmptySlot => (),
+                Entity::_FailedToParse(_) => {
+                    out.entities += 1;
+                    out.failed += 1;
+                }
+                _ => out.entities += 1,
+            }
+        }
+        out.length_unit = length_unit(step);
+        out
+    }
+}
+
+/// Time spent in a single stage of loading
+#[derive(Copy, Clone, Debug)]
+pub struct Timing {
+    /// One of `read`, `strip_flatten`, `parse`, or `triangulate`.  With a
+    /// cache, there are also `hash`, `cache_read`, and (after a miss)
+    /// `cache_write` stages.
+    pub stage: &'static str,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+/// Geometry loaded from a STEP file
+pub struct Scene {
+    /// Triangles from every solid and surface, and wires from every curve
+    pub mesh: Mesh,
+    /// Unit of length which the mesh's coordinates are in, if the file
+    /// declares one that we recognize
+    pub length_unit: Option<LengthUnit>,
+}
+
+/// Everything we learned while loading a scene, other than the scene itself
+pub struct LoadReport {
+    pub parse: ParseReport,
+    pub stats: Stats,
+    /// Timings for each stage, in the order they ran
+    pub timings: Vec<Timing>,
+}
+
+/// Returns a readable message from a panic payload
+fn panic_message(e: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = e.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = e.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown error".to_owned()
+    }
+}
+
+/// Runs a function, recording its timing and catching panics
+fn stage<T, F: FnOnce() -> T>(
+    name: &'static str,
+    timings: &mut Vec<Timing>,
+    f: F,
+) -> Result<T, LoadError> {
+    let start = Instant::now();
+    let out = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+        .map_err(|e| LoadError::Panicked(panic_message(e.as_ref())));
+    timings.push(Timing {
+        stage: name,
+        start,
+        duration: start.elapsed(),
+    });
+    out
+}
+
+/// Reads, parses, and triangulates a STEP file.
+///
+/// Files which can't be read, can't be split into entities, contain no STEP
+/// entities, or produce no triangles or wires are reported as errors, as are panics
+/// from the parser and triangulator.  If nothing was produced because every
+/// face failed, the first face's error is returned.
+pub fn load_mesh(
+    path: impl AsRef<Path>,
+    opts: &TriangulateOptions,
+) -> Result<(Scene, LoadReport), LoadError> {
+    let path = path.as_ref();
+    let mut timings = vec![];
+    let data =
+        stage("read", &mut timings, || std::fs::read(path))?.map_err(|source| LoadError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+    load_bytes(&data, opts, timings)
+}
+
+/// Parses and triangulates a STEP file which has already been read into
+/// memory (e.g. in the browser, where there's no filesystem).
+///
+/// Errors are as in [`load_mesh`], except that the report has no `read`
+/// stage.
+pub fn load_mesh_from_bytes(
+    data: &[u8],
+    opts: &TriangulateOptions,
+) -> Result<(Scene, LoadReport), LoadError> {
+    load_bytes(data, opts, vec![])
+}
+
+/// Reads and parses a STEP file, then triangulates only the given faces (as
+/// indices of `ADVANCED_FACE` entities) for splicing into a mesh which was
+/// loaded from it (see [`triangulate_faces`]).  The cache isn't used.
+///
+/// Errors are as in [`load_mesh`], except that producing no triangles
+/// isn't one.
+pub fn load_faces(
+    path: impl AsRef<Path>,
+    faces: &[usize],
+    opts: &TriangulateOptions,
+) -> Result<(Mesh, Stats), LoadError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|source| LoadError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let mut timings = vec![];
+    let flat = stage("strip_flatten", &mut timings, || {
+        StepFile::strip_flatten(&data)
+    })?;
+    let step = stage("parse", &mut timings, || StepFile::parse(&flat))??;
+    stage("triangulate", &mut timings, || {
+        triangulate_faces(&step, faces, opts.tolerance, opts.topology)
+    })
+}
+
+/// Parses and triangulates a STEP file, appending to a list of existing
+/// timings
+fn load_bytes(
+    data: &[u8],
+    opts: &TriangulateOptions,
+    mut timings: Vec<Timing>,
+) -> Result<(Scene, LoadReport), LoadError> {
+    #[cfg(feature = "cache")]
+    let cache = match opts.cache {
+        Some(dir) => {
+            let key = stage("hash", &mut timings, || crate::cache::key(data, opts))?;
+            let hit = stage("cache_read", &mut timings, || crate::cache::load(dir, &key))?;
+            if let Some(mut e) = hit {
+                e.mesh.apply_convention(&opts.convention);
+                let scene = Scene {
+                    mesh: e.mesh,
+                    length_unit: e.parse.length_unit,
+                };
+                let report = LoadReport {
+                    parse: e.parse,
+                    stats: e.stats,
+                    timings,
+                };
+                return Ok((scene, report));
+            }
+            Some((dir, key))
+        }
+        None => None,
+    };
+
+    let flat = stage("strip_flatten", &mut timings, || {
+        StepFile::strip_flatten(data)
+    })?;
+    let step = stage("parse", &mut timings, || StepFile::parse(&flat))??;
+    let parse = ParseReport::new(&step);
+    if parse.entities == parse.failed {
+        return Err(LoadError::Parse);
+    }
+    let (mut mesh, stats) = stage("triangulate", &mut timings, || {
+        triangulate_streaming(
+            &step,
+            opts.tolerance,
+            opts.crop.as_ref(),
+            opts.topology,
+            opts.progress,
+            opts.on_solid,
+        )
+    })?;
+    if let (Some(aabb), true) = (&opts.crop, opts.clip) {
+        mesh = crop::clip(&mesh, aabb);
+    }
+    if mesh.triangles.is_empty() && mesh.wires.is_empty() {
+        return Err(match stats.first_error {
+            Some(e) => e.into(),
+            None => LoadError::Empty,
+        });
+    }
+    #[cfg(feature = "cache")]
+    if let Some((dir, key)) = cache {
+        stage("cache_write", &mut timings, || {
+            crate::cache::store(dir, &key, &mesh, &parse, &stats)
+        })?;
+    }
+    mesh.apply_convention(&opts.convention);
+    let scene = Scene {
+        mesh,
+        length_unit: parse.length_unit,
+    };
+    let report = LoadReport {
+        parse,
+        stats,
+        timings,
+    };
+    Ok((scene, report))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Writes a generated STEP file to a scratch path
+    fn fixture(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "foxtrot-triangulate-{}-{}.step",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, step::fixture::filleted_block(10.0, 2.0).to_step()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_mesh() {
+        let calls = AtomicUsize::new(0);
+        let progress = |_, _| {
+            calls.fetch_add(1, Ordering::Relaxed);
+        };
+        let opts = TriangulateOptions {
+            progress: &progress,
+            ..Default::default()
+        };
+        let path = fixture("load");
+        let (scene, report) = load_mesh(&path, &opts).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(scene.mesh.open_edges(), 0);
+        assert_eq!(scene.length_unit, report.parse.length_unit);
+        assert!(calls.load(Ordering::Relaxed) > 0);
+
+        assert!(report.parse.entities > 0);
+        assert_eq!(report.parse.failed, 0);
+        assert_eq!(report.stats.num_faces, 10);
+        let stages: Vec<_> = report.timings.iter().map(|t| t.stage).collect();
+        assert_eq!(stages, ["read", "strip_flatten", "parse", "triangulate"]);
+        for w in report.timings.windows(2) {
+            assert!(w[0].start <= w[1].start);
+        }
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_cache() {
+        use step::fixture;
+
+        // A large assembly, so that parsing and triangulation take long
+        // enough to be measured against loading from the cache
+        let parts = (0..100)
+            .map(|i| {
+                fixture::filleted_block(10.0, 2.0).translate([
+                    (i % 10) as f64 * 12.0,
+                    (i / 10) as f64 * 12.0,
+                    0.0,
+                ])
+            })
+            .collect();
+        let path = fixture("cache");
+        std::fs::write(&path, fixture::assembly(parts).to_step()).unwrap();
+        let dir =
+            std::env::temp_dir().join(format!("foxtrot-triangulate-{}-cache", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let opts = TriangulateOptions {
+            cache: Some(&dir),
+            ..Default::default()
+        };
+        let total = |r: &LoadReport| -> Duration { r.timings.iter().map(|t| t.duration).sum() };
+        let time = |r: &LoadReport, stage| {
+            r.timings
+                .iter()
+                .find(|t| t.stage == stage)
+                .unwrap()
+                .duration
+        };
+
+        let (cold, cold_report) = load_mesh(&path, &opts).unwrap();
+        let cold = cold.mesh;
+        let stages: Vec<_> = cold_report.timings.iter().map(|t| t.stage).collect();
+        assert_eq!(
+            stages,
+            [
+                "read",
+                "hash",
+                "cache_read",
+                "strip_flatten",
+                "parse",
+                "triangulate",
+                "cache_write"
+            ]
+        );
+
+        let (warm, warm_report) = load_mesh(&path, &opts).unwrap();
+        let warm = warm.mesh;
+        let stages: Vec<_> = warm_report.timings.iter().map(|t| t.stage).collect();
+        assert_eq!(stages, ["read", "hash", "cache_read"]);
+        assert_eq!(warm.verts.len(), cold.verts.len());
+        assert_eq!(warm.triangles.len(), cold.triangles.len());
+        assert_eq!(warm.parts.len(), 100);
+        assert_eq!(warm.faces.len(), cold.faces.len());
+        assert_eq!(warm_report.parse, cold_report.parse);
+        assert_eq!(warm_report.stats.num_faces, cold_report.stats.num_faces);
+
+        // The second load skips parsing and triangulation entirely, so it's
+        // dominated by deserialization and much faster than the first
+        let (cold_time, warm_time) = (total(&cold_report), total(&warm_report));
+        log::info!(
+            "Cold load took {:?}, warm load took {:?}",
+            cold_time,
+            warm_time
+        );
+        assert!(
+            warm_time * 4 < cold_time,
+            "{:?} vs {:?}",
+            warm_time,
+            cold_time
+        );
+        assert!(
+            time(&warm_report, "cache_read") > time(&warm_report, "hash"),
+            "{:?}",
+            warm_report.timings
+        );
+
+        // Different options miss the cache
+        let coarse = TriangulateOptions {
+            tolerance: Some(0.5),
+            ..opts
+        };
+        let (_, report) = load_mesh(&path, &coarse).unwrap();
+        assert!(report.timings.iter().any(|t| t.stage == "triangulate"));
+
+        // Without a cache, nothing is hashed or stored
+        let (_, report) = load_mesh(&path, &TriangulateOptions::default()).unwrap();
+        let stages: Vec<_> = report.timings.iter().map(|t| t.stage).collect();
+        assert_eq!(stages, ["read", "strip_flatten", "parse", "triangulate"]);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let e = std::panic::catch_unwind(|| panic!("bad entity")).unwrap_err();
+        assert_eq!(panic_message(e.as_ref()), "bad entity");
+        let e = std::panic::catch_unwind(|| panic!("bad entity #{}", 12)).unwrap_err();
+        assert_eq!(panic_message(e.as_ref()), "bad entity #12");
+
+        // Panics in a stage are reported as errors, but still timed
+        let mut timings = vec![];
+        let r: Result<(), _> = stage("parse", &mut timings, || panic!("oh no"));
+        assert!(matches!(r, Err(LoadError::Panicked(s)) if s == "oh no"));
+        assert_eq!(timings.len(), 1);
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let r = load_mesh("does/not/exist.step", &TriangulateOptions::default());
+        match r {
+            Err(LoadError::Io { path, .. }) => assert_eq!(path, Path::new("does/not/exist.step")),
+            r => panic!("Unexpected result {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_no_geometry() {
+        // A valid STEP file, with a single point and no solids
+        let data = b"ISO-10303-21;
+HEADER;
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(0.,0.,0.));
+ENDSEC;
+END-ISO-10303-21;
+";
+        let path = std::env::temp_dir().join(format!(
+            "foxtrot-triangulate-{}-point.step",
+            std::process::id()
+        ));
+        std::fs::write(&path, data).unwrap();
+        let opts = TriangulateOptions::default();
+        let r = load_mesh(&path, &opts);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(r, Err(LoadError::Empty)), "{:?}", r.err());
+
+        // Curves alone are enough to load
+        let text = step::fixture::cylinder(1.0, 2.0).wireframe().to_step();
+        let (scene, report) = load_mesh_from_bytes(text.as_bytes(), &opts).unwrap();
+        assert!(scene.mesh.triangles.is_empty());
+        assert_eq!(scene.mesh.wires.len(), 6);
+        assert_eq!(report.stats.num_wires, 6);
+
+        // Something which isn't a STEP file at all
+        let r = load_mesh_from_bytes(b"solid cube;\nendsolid cube;\n", &opts);
+        assert!(matches!(r, Err(LoadError::Parse)), "{:?}", r.err());
+    }
+
+    #[test]
+    fn test_every_face_fails() {
+        // A cube whose faces are all bounded by empty loops
+        let text: String = step::fixture::cube(10.0)
+            .to_step()
+            .lines()
+            .map(|line| match line.find("=EDGE_LOOP(") {
+                Some(i) => format!("{}=EDGE_LOOP('',());\n", &line[..i]),
+                None => format!("{}\n", line),
+            })
+            .collect();
+        let r = load_mesh_from_bytes(text.as_bytes(), &TriangulateOptions::default());
+        assert!(
+            matches!(r, Err(LoadError::Triangulate(crate::Error::EmptyBound(_)))),
+            "{:?}",
+            r.err()
+        );
+    }
+
+    #[test]
+    fn test_malformed_file() {
+        // A string which never ends
+        let opts = TriangulateOptions::default();
+        let r = load_mesh_from_bytes(b"ISO-10303-21;\nDATA;\n#1=PRODUCT('abc);\n", &opts);
+        assert!(
+            matches!(r, Err(LoadError::Step(step::Error::UnterminatedString(29)))),
+            "{:?}",
+            r.err()
+        );
+    }
+}
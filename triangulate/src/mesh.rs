@@ -1,21 +1,189 @@
-use nalgebra_glm::{DVec3, U32Vec3};
+use nalgebra_glm::{DMat3, DMat4, DVec2, DVec3, U32Vec3};
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::ops::Range;
+
+use crate::{bvh::MeshBvh, convention::CoordinateConvention};
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vertex {
     pub pos: DVec3,
     pub norm: DVec3,
     pub color: DVec3,
 }
+
+impl Vertex {
+    /// Returns the vertex's color as opaque 8-bit RGBA
+    pub fn rgba(&self) -> [u8; 4] {
+        let b = |f: f64| (f.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [b(self.color.x), b(self.color.y), b(self.color.z), 255]
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Triangle {
     pub verts: U32Vec3,
 }
 
-#[derive(Default)]
+/// A single solid or surface model placed in the mesh.  Assemblies which
+/// place the same shape more than once produce one `Part` per placement.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub struct Part {
+    /// Index of the STEP entity (e.g. a `MANIFOLD_SOLID_BREP`) which was
+    /// triangulated to build this part
+    pub shape: usize,
+    /// Range of triangles in [`Mesh::triangles`] belonging to this part
+    pub triangles: Range<usize>,
+    /// Transform which was applied to the shape's geometry to place this
+    /// part.  Parts with the same `shape` differ only by this transform,
+    /// which lets renderers share geometry between them.
+    pub transform: DMat4,
+}
+
+/// A single face from the STEP file, which was triangulated into a
+/// contiguous range of triangles.  As with [`Part`], instanced shapes
+/// produce one `Face` per placement.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub struct Face {
+    /// Index of the STEP entity (an `ADVANCED_FACE`) for this face
+    pub id: usize,
+    /// Range of triangles in [`Mesh::triangles`] belonging to this face
+    pub triangles: Range<usize>,
+}
+
+/// A curve from a `GEOMETRIC_SET` or `GEOMETRIC_CURVE_SET`, sampled into a
+/// polyline.  Wires aren't attached to any [`Part`]; each placement of a
+/// set produces its own (already-transformed) copy of every wire.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub struct Wire {
+    /// Index of the STEP entity for the curve
+    pub id: usize,
+    pub points: Vec<DVec3>,
+}
+
+/// A presentation layer from the STEP file, which lists the faces assigned
+/// to it (either directly, or through the shell or solid containing them)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub struct Layer {
+    pub name: String,
+    /// Ids of the faces on this layer (matching [`Face::id`]), sorted
+    pub faces: Vec<usize>,
+}
+
+/// A face's surface, sampled on a regular grid in its (u, v) parameters
+/// over the range spanned by the face's boundary (see [`crate::grid`]).
+/// The grid isn't trimmed; `mask` marks the samples which are on the face.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub struct FaceGrid {
+    /// Index of the STEP entity (an `ADVANCED_FACE`) for this face
+    pub id: usize,
+    /// Number of samples along v
+    pub rows: usize,
+    /// Number of samples along u
+    pub cols: usize,
+    /// Parameters of the first sample
+    pub lo: DVec2,
+    /// Parameters of the last sample
+    pub hi: DVec2,
+    /// Position of each sample, row by row
+    pub points: Vec<DVec3>,
+    /// Whether each sample is inside the face or on its boundary, row by row
+    pub mask: Vec<bool>,
+}
+
+impl FaceGrid {
+    /// Checks whether every sample is on the face, i.e. the face fills its
+    /// whole parameter range
+    pub fn is_full(&self) -> bool {
+        self.mask.iter().all(|m| *m)
+    }
+}
+
+/// Surface area and enclosed volume of a set of triangles, in model units
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MassProperties {
+    pub area: f64,
+    /// Volume enclosed by the triangles, which is only meaningful if they
+    /// form a closed surface.  It's positive when the triangles face
+    /// outwards, as they do for solids.
+    pub volume: f64,
+}
+
+impl MassProperties {
+    fn add(self, b: Self) -> Self {
+        Self {
+            area: self.area + b.area,
+            volume: self.volume + b.volume,
+        }
+    }
+}
+
+/// A box which fits around a mesh, aligned with its principal axes rather
+/// than the world axes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrientedBox {
+    pub center: DVec3,
+    /// Right-handed orthonormal axes, from the direction in which the mesh
+    /// is most spread out to the one in which it's least spread out
+    pub axes: [DVec3; 3],
+    /// Half of the box's size along each axis
+    pub half_extents: DVec3,
+}
+
+impl OrientedBox {
+    /// Returns the rigid transform which moves the box's center to the
+    /// origin and its axes onto the world X, Y, and Z axes
+    pub fn to_world(&self) -> DMat4 {
+        let mut out = DMat4::identity();
+        for (i, a) in self.axes.iter().enumerate() {
+            for j in 0..3 {
+                out[(i, j)] = a[j];
+            }
+            out[(i, 3)] = -a.dot(&self.center);
+        }
+        out
+    }
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mesh {
     pub verts: Vec<Vertex>,
     pub triangles: Vec<Triangle>,
+    pub parts: Vec<Part>,
+    /// Faces, sorted by their (non-overlapping) triangle ranges
+    pub faces: Vec<Face>,
+    /// Optional per-vertex curvature (the magnitude of the largest principal
+    /// curvature), aligned with `verts`.  Vertices with unknown curvature
+    /// are stored as NaN.
+    pub curvature: Option<Vec<f32>>,
+    /// Optional per-vertex RGBA colors, aligned with `verts`, which take
+    /// precedence over each vertex's own (opaque) `color`.  When meshes with
+    /// and without this channel are combined, the missing entries are
+    /// filled in from the vertex colors.
+    pub colors: Option<Vec<[u8; 4]>>,
+    /// Curves from wireframe and surface models, which are drawn as lines
+    /// rather than triangulated
+    pub wires: Vec<Wire>,
+    /// Presentation layers, one per distinct name.  A face which is on
+    /// several layers is listed in each.
+    pub layers: Vec<Layer>,
+    /// Quads, each stored as the index of the first of two consecutive
+    /// triangles which share an edge (see [`Mesh::quad`]), in sorted order.
+    /// Both triangles are still in `triangles`, so this only matters to
+    /// writers which can use quads.  Only built with
+    /// [`MeshTopology::QuadDominant`](crate::grid::MeshTopology::QuadDominant).
+    pub quads: Vec<usize>,
+    /// Sample grids for each face, in the same coordinates as `verts`.  Only
+    /// built with [`MeshTopology::Grid`](crate::grid::MeshTopology::Grid).
+    pub grids: Vec<FaceGrid>,
 }
 
 impl Mesh {
@@ -23,7 +191,50 @@ impl Mesh {
     // (why yes, this _is_ a monoid)
     pub fn combine(mut a: Self, b: Self) -> Self {
         let dv = a.verts.len().try_into().expect("too many triangles");
+        let dt = a.triangles.len();
+        if a.curvature.is_some() || b.curvature.is_some() {
+            let mut c = a.curvature.take().unwrap_or_default();
+            c.resize(a.verts.len(), f32::NAN);
+            c.extend(
+                b.curvature
+                    .unwrap_or_default()
+                    .into_iter()
+                    .chain(std::iter::repeat(f32::NAN))
+                    .take(b.verts.len()),
+            );
+            a.curvature = Some(c);
+        }
+        if a.colors.is_some() || b.colors.is_some() {
+            let mut c = a.colors.take().unwrap_or_default();
+            c.extend(a.verts[c.len()..].iter().map(Vertex::rgba));
+            let b_colors = b.colors.unwrap_or_default();
+            let n = b_colors.len();
+            c.extend(b_colors);
+            c.extend(b.verts[n..].iter().map(Vertex::rgba));
+            a.colors = Some(c);
+        }
         a.verts.extend(b.verts);
+        a.wires.extend(b.wires);
+        a.grids.extend(b.grids);
+        a.quads.extend(b.quads.into_iter().map(|q| q + dt));
+        for layer in b.layers {
+            match a.layers.iter_mut().find(|l| l.name == layer.name) {
+                Some(l) => {
+                    l.faces.extend(layer.faces);
+                    l.faces.sort_unstable();
+                    l.faces.dedup();
+                }
+                None => a.layers.push(layer),
+            }
+        }
+        a.parts.extend(b.parts.into_iter().map(|p| Part {
+            triangles: (p.triangles.start + dt)..(p.triangles.end + dt),
+            ..p
+        }));
+        a.faces.extend(b.faces.into_iter().map(|f| Face {
+            id: f.id,
+            triangles: (f.triangles.start + dt)..(f.triangles.end + dt),
+        }));
         a.triangles
             .extend(b.triangles.into_iter().map(|t| Triangle {
                 verts: t.verts.add_scalar(dv),
@@ -31,21 +242,1273 @@ impl Mesh {
         a
     }
 
+    /// Returns a new mesh containing only the parts for which `keep` is
+    /// true, with unused vertices removed.  Meshes without part information
+    /// are returned unchanged; otherwise, wires and grids (which don't belong
+    /// to any part) are dropped.
+    pub fn select_parts(&self, keep: &[bool]) -> Self {
+        if self.parts.is_empty() {
+            return self.clone();
+        }
+        let mut out = Mesh {
+            curvature: self.curvature.as_ref().map(|_| vec![]),
+            colors: self.colors.as_ref().map(|_| vec![]),
+            layers: self.layers.clone(),
+            ..Mesh::default()
+        };
+        let mut remap: Vec<Option<u32>> = vec![None; self.verts.len()];
+        for (p, _) in self.parts.iter().zip(keep).filter(|(_, k)| **k) {
+            let start = out.triangles.len();
+
+            // Faces are sorted, so we can find the ones in this part quickly
+            let first = self
+                .faces
+                .partition_point(|f| f.triangles.start < p.triangles.start);
+            for f in self.faces[first..]
+                .iter()
+                .take_while(|f| f.triangles.end <= p.triangles.end)
+            {
+                out.faces.push(Face {
+                    id: f.id,
+                    triangles: (f.triangles.start - p.triangles.start + start)
+                        ..(f.triangles.end - p.triangles.start + start),
+                });
+            }
+
+            let first = self.quads.partition_point(|q| *q < p.triangles.start);
+            out.quads.extend(
+                self.quads[first..]
+                    .iter()
+                    .take_while(|q| **q < p.triangles.end)
+                    .map(|q| q - p.triangles.start + start),
+            );
+
+            for t in &self.triangles[p.triangles.clone()] {
+                let verts = t.verts.map(|v| {
+                    *remap[v as usize].get_or_insert_with(|| {
+                        if let (Some(src), Some(dst)) = (&self.curvature, &mut out.curvature) {
+                            dst.push(src[v as usize]);
+                        }
+                        if let (Some(src), Some(dst)) = (&self.colors, &mut out.colors) {
+                            dst.push(src[v as usize]);
+                        }
+                        out.verts.push(self.verts[v as usize]);
+                        (out.verts.len() - 1) as u32
+                    })
+                });
+                out.triangles.push(Triangle { verts });
+            }
+            out.parts.push(Part {
+                shape: p.shape,
+                triangles: start..out.triangles.len(),
+                transform: p.transform,
+            });
+        }
+        out
+    }
+
+    /// Returns a coarser copy of the mesh, made by merging the vertices of
+    /// each face which fall into the same cube of side `cell` and dropping
+    /// the triangles which collapse.
+    ///
+    /// Vertices are only merged within a face (or, for triangles outside of
+    /// any face, within a part), so faces and parts keep their order and
+    /// their triangle ranges stay contiguous; a face may end up with no
+    /// triangles at all.  Quads are dropped, since their triangles may no
+    /// longer come in pairs.
+    pub fn decimate(&self, cell: f64) -> Self {
+        let mut out = Mesh {
+            curvature: self.curvature.as_ref().map(|_| vec![]),
+            colors: self.colors.as_ref().map(|_| vec![]),
+            wires: self.wires.clone(),
+            layers: self.layers.clone(),
+            grids: self.grids.clone(),
+            ..Mesh::default()
+        };
+
+        // Index of the face (or, failing that, the part) which contains
+        // each triangle, so that regions are never merged together
+        let mut region = vec![usize::MAX; self.triangles.len()];
+        for (i, p) in self.parts.iter().enumerate() {
+            region[p.triangles.clone()].fill(self.faces.len() + i);
+        }
+        for (i, f) in self.faces.iter().enumerate() {
+            region[f.triangles.clone()].fill(i);
+        }
+
+        // Number of triangles kept before each triangle, for rebuilding
+        // the face and part ranges
+        let mut kept = Vec::with_capacity(self.triangles.len() + 1);
+        let mut clusters: HashMap<(usize, [i64; 3]), u32> = HashMap::new();
+        for (t, r) in self.triangles.iter().zip(&region) {
+            kept.push(out.triangles.len());
+            let verts = t.verts.map(|v| {
+                let p = self.verts[v as usize].pos / cell;
+                let key = [p.x, p.y, p.z].map(|c| c.floor() as i64);
+                *clusters.entry((*r, key)).or_insert_with(|| {
+                    if let (Some(src), Some(dst)) = (&self.curvature, &mut out.curvature) {
+                        dst.push(src[v as usize]);
+                    }
+                    if let (Some(src), Some(dst)) = (&self.colors, &mut out.colors) {
+                        dst.push(src[v as usize]);
+                    }
+                    out.verts.push(self.verts[v as usize]);
+                    (out.verts.len() - 1) as u32
+                })
+            });
+            if verts[0] != verts[1] && verts[1] != verts[2] && verts[2] != verts[0] {
+                out.triangles.push(Triangle { verts });
+            }
+        }
+        kept.push(out.triangles.len());
+
+        let remap = |r: &Range<usize>| kept[r.start]..kept[r.end];
+        out.faces = self
+            .faces
+            .iter()
+            .map(|f| Face {
+                id: f.id,
+                triangles: remap(&f.triangles),
+            })
+            .collect();
+        out.parts = self
+            .parts
+            .iter()
+            .map(|p| Part {
+                triangles: remap(&p.triangles),
+                ..p.clone()
+            })
+            .collect();
+        out
+    }
+
+    /// Replaces faces with new triangulations of them, returning the indices
+    /// (into [`Mesh::faces`]) of the faces which were replaced.
+    ///
+    /// `fragments` holds the new faces in their solids' own coordinates
+    /// (as built by
+    /// [`triangulate_faces`](crate::triangulate::triangulate_faces)).  Each
+    /// one replaces every copy of the face with the same id, which is moved
+    /// into place by its part's transform and given the old face's color.
+    /// Faces which aren't already in the mesh are skipped, since there's no
+    /// part to put them in.
+    ///
+    /// New vertices are appended and the old ones are left unused, so the
+    /// triangles of other faces keep their vertex indices; only their
+    /// positions in `triangles` shift.
+    pub fn splice_faces(&mut self, fragments: &Mesh) -> Vec<usize> {
+        let mut replaced = vec![];
+        for frag in &fragments.faces {
+            let tris = &fragments.triangles[frag.triangles.clone()];
+            let mut used: Vec<u32> = tris.iter().flat_map(|t| t.verts.iter().copied()).collect();
+            used.sort_unstable();
+            used.dedup();
+            let quads: Vec<usize> = fragments
+                .quads
+                .iter()
+                .filter(|q| frag.triangles.contains(q))
+                .map(|q| q - frag.triangles.start)
+                .collect();
+
+            // Work backwards, so that shifting triangles doesn't move the
+            // copies which haven't been replaced yet
+            let copies: Vec<usize> = (0..self.faces.len())
+                .filter(|i| self.faces[*i].id == frag.id)
+                .collect();
+            let grids: Vec<&FaceGrid> =
+                fragments.grids.iter().filter(|g| g.id == frag.id).collect();
+            if !copies.is_empty() && !grids.is_empty() {
+                self.grids.retain(|g| g.id != frag.id);
+            }
+            for i in copies.into_iter().rev() {
+                let old = self.faces[i].triangles.clone();
+                let mat = self
+                    .parts
+                    .iter()
+                    .find(|p| p.triangles.contains(&old.start))
+                    .map_or_else(DMat4::identity, |p| p.transform);
+                // Parts' transforms include any coordinate convention which
+                // was applied to the mesh, which may scale or mirror it
+                let det = nalgebra_glm::mat4_to_mat3(&mat).determinant();
+                let scale = det.abs().cbrt();
+                let first = self.triangles[old.start].verts[0] as usize;
+                let color = self.verts[first].color;
+                let rgba = self.colors.as_ref().map(|c| c[first]);
+
+                let v_start = self.verts.len();
+                for v in &used {
+                    let src = &fragments.verts[*v as usize];
+                    self.verts.push(Vertex {
+                        pos: (mat * src.pos.push(1.0)).xyz(),
+                        norm: (mat * src.norm.push(0.0)).xyz() / scale,
+                        color,
+                    });
+                }
+                if let Some(c) = &mut self.curvature {
+                    c.resize(v_start, f32::NAN);
+                    c.extend(used.iter().map(|v| {
+                        fragments
+                            .curvature
+                            .as_ref()
+                            .map_or(f32::NAN, |k| k[*v as usize] / scale as f32)
+                    }));
+                }
+                if let (Some(c), Some(rgba)) = (&mut self.colors, rgba) {
+                    c.resize(self.verts.len(), rgba);
+                }
+                let remap = |v: u32| (v_start + used.binary_search(&v).unwrap()) as u32;
+                let new: Vec<Triangle> = tris
+                    .iter()
+                    .map(|t| {
+                        let mut verts = t.verts.map(remap);
+                        if det < 0.0 {
+                            verts.swap_rows(1, 2);
+                        }
+                        Triangle { verts }
+                    })
+                    .collect();
+
+                // Shift everything after the face by the change in its size
+                let end = old.start + new.len();
+                let shift = |j: usize| j + end - old.end;
+                self.triangles.splice(old.clone(), new);
+                for (j, f) in self.faces.iter_mut().enumerate() {
+                    if j == i {
+                        f.triangles = old.start..end;
+                    } else if f.triangles.start >= old.end {
+                        f.triangles = shift(f.triangles.start)..shift(f.triangles.end);
+                    }
+                }
+                for p in &mut self.parts {
+                    if p.triangles.start >= old.end {
+                        p.triangles = shift(p.triangles.start)..shift(p.triangles.end);
+                    } else if p.triangles.contains(&old.start) {
+                        p.triangles.end = shift(p.triangles.end);
+                    }
+                }
+                self.quads.retain(|q| !old.contains(q));
+                for q in &mut self.quads {
+                    if *q >= old.end {
+                        *q = shift(*q);
+                    }
+                }
+                self.quads.extend(quads.iter().map(|q| q + old.start));
+                self.quads.sort_unstable();
+
+                for g in &grids {
+                    let mut grid = (*g).clone();
+                    for p in &mut grid.points {
+                        *p = (mat * p.push(1.0)).xyz();
+                    }
+                    self.grids.push(grid);
+                }
+                replaced.push(i);
+            }
+        }
+        replaced.sort_unstable();
+        replaced
+    }
+
+    /// Moves the mesh into another coordinate convention, transforming
+    /// positions, normals, wires, grids, and part transforms, and rescaling
+    /// curvature.  If the convention flips handedness, triangle winding is
+    /// reversed so that faces keep pointing outwards.
+    pub fn apply_convention(&mut self, c: &CoordinateConvention) {
+        if c.is_identity() {
+            return;
+        }
+        self.transform(&c.matrix(), &c.rotation());
+        if let Some(curvature) = &mut self.curvature {
+            for k in curvature {
+                *k /= c.scale as f32;
+            }
+        }
+        if c.flip_handedness {
+            for t in &mut self.triangles {
+                t.verts.swap_rows(1, 2);
+            }
+        }
+    }
+
+    /// Moves the mesh by a rigid transform (e.g. from
+    /// [`Mesh::align_to_principal_axes`]), which leaves normals, curvature,
+    /// and triangle winding unchanged
+    pub fn apply_rigid(&mut self, mat: &DMat4) {
+        let mut rot = *mat;
+        for i in 0..3 {
+            rot[(i, 3)] = 0.0;
+        }
+        self.transform(mat, &rot);
+    }
+
+    /// Transforms positions (wires, grids, and part transforms included) by
+    /// `mat` and normals by `rot`
+    fn transform(&mut self, mat: &DMat4, rot: &DMat4) {
+        let apply = |m: &DMat4, p: DVec3, w: f64| (m * p.push(w)).xyz();
+        for v in &mut self.verts {
+            v.pos = apply(mat, v.pos, 1.0);
+            v.norm = apply(rot, v.norm, 0.0);
+        }
+        for w in &mut self.wires {
+            for p in &mut w.points {
+                *p = apply(mat, *p, 1.0);
+            }
+        }
+        for g in &mut self.grids {
+            for p in &mut g.points {
+                *p = apply(mat, *p, 1.0);
+            }
+        }
+        for p in &mut self.parts {
+            p.transform = mat * p.transform;
+        }
+    }
+
+    /// Returns the RGBA color of each vertex, from the color channel if
+    /// there is one, or from the vertices' own colors otherwise
+    pub fn vertex_colors(&self) -> Vec<[u8; 4]> {
+        match &self.colors {
+            Some(c) => c.clone(),
+            None => self.verts.iter().map(Vertex::rgba).collect(),
+        }
+    }
+
+    /// Returns the vertices of the quad starting at triangle `t` (an entry
+    /// in [`Mesh::quads`]), in the same winding order as its triangles
+    pub fn quad(&self, t: usize) -> [u32; 4] {
+        let (a, b) = (self.triangles[t].verts, self.triangles[t + 1].verts);
+        // Find the first triangle's corner which isn't shared, then slot
+        // the second triangle's unshared corner in across from it
+        let i = (0..3).find(|i| !b.iter().any(|v| *v == a[*i])).unwrap_or(0);
+        let d = b.iter().copied().find(|v| !a.iter().any(|u| u == v));
+        let (p, q, r) = (a[i], a[(i + 1) % 3], a[(i + 2) % 3]);
+        [r, p, q, d.unwrap_or(r)]
+    }
+
+    /// Returns the names of the layers which a face (by [`Face::id`]) is on
+    pub fn face_layers(&self, id: usize) -> Vec<&str> {
+        self.layers
+            .iter()
+            .filter(|l| l.faces.binary_search(&id).is_ok())
+            .map(|l| l.name.as_str())
+            .collect()
+    }
+
+    /// Returns the indices of the parts with at least one face on the given
+    /// layer (an index into [`Mesh::layers`])
+    pub fn layer_parts(&self, layer: usize) -> Vec<usize> {
+        let layer = match self.layers.get(layer) {
+            Some(l) => l,
+            None => return vec![],
+        };
+        self.parts
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| {
+                let first = self
+                    .faces
+                    .partition_point(|f| f.triangles.start < p.triangles.start);
+                self.faces[first..]
+                    .iter()
+                    .take_while(|f| f.triangles.end <= p.triangles.end)
+                    .any(|f| layer.faces.binary_search(&f.id).is_ok())
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the index into [`Mesh::faces`] for each vertex, or `None` for
+    /// vertices which aren't used by any face.
+    pub fn vertex_faces(&self) -> Vec<Option<usize>> {
+        let mut out = vec![None; self.verts.len()];
+        for (i, f) in self.faces.iter().enumerate() {
+            for t in &self.triangles[f.triangles.clone()] {
+                for v in t.verts.iter() {
+                    out[*v as usize] = Some(i);
+                }
+            }
+        }
+        out
+    }
+
+    /// Returns the index into [`Mesh::faces`] of the face containing a
+    /// triangle, or `None` if it isn't part of any face
+    pub fn triangle_face(&self, t: usize) -> Option<usize> {
+        let i = self.faces.partition_point(|f| f.triangles.end <= t);
+        self.faces
+            .get(i)
+            .filter(|f| f.triangles.contains(&t))
+            .map(|_| i)
+    }
+
+    /// Returns the area of a range of triangles, and the volume which they
+    /// enclose (see [`MassProperties::volume`])
+    pub fn mass_properties(&self, triangles: Range<usize>) -> MassProperties {
+        self.sum_properties(&self.triangles[triangles])
+    }
+
+    /// Returns the combined area of the given faces (as indices into
+    /// [`Mesh::faces`]), and the volume which they enclose
+    pub fn face_properties(&self, faces: &[usize]) -> MassProperties {
+        let tris: Vec<Triangle> = faces
+            .iter()
+            .flat_map(|f| &self.triangles[self.faces[*f].triangles.clone()])
+            .copied()
+            .collect();
+        self.sum_properties(&tris)
+    }
+
+    fn sum_properties(&self, tris: &[Triangle]) -> MassProperties {
+        // Volume is measured from a point near the triangles rather than
+        // the origin, which loses less precision for models far from it
+        let base = match tris.first() {
+            Some(t) => self.verts[t.verts[0] as usize].pos,
+            None => return MassProperties::default(),
+        };
+        tris.iter().fold(MassProperties::default(), |m, t| {
+            let [a, b, c] = [0, 1, 2].map(|i| self.verts[t.verts[i] as usize].pos - base);
+            m.add(MassProperties {
+                area: (b - a).cross(&(c - a)).norm() / 2.0,
+                volume: a.dot(&b.cross(&c)) / 6.0,
+            })
+        })
+    }
+
+    /// Returns the surface area and volume of a part
+    pub fn part_properties(&self, part: usize) -> MassProperties {
+        self.mass_properties(self.parts[part].triangles.clone())
+    }
+
+    /// Fits a box around the triangles, aligned with their principal axes.
+    ///
+    /// The axes are the eigenvectors of the triangles' second moment of
+    /// area (i.e. the covariance of their centroids, weighted by area, plus
+    /// each triangle's own spread), so they don't depend on how the surface
+    /// was tessellated.  Flat or linear geometry leaves some axes
+    /// undetermined; these are filled in from whichever world axis is
+    /// furthest from the known ones, so that the result is stable.  Returns
+    /// `None` if there are no triangles.
+    pub fn oriented_bounding_box(&self) -> Option<OrientedBox> {
+        let tris: Vec<[DVec3; 3]> = self
+            .triangles
+            .iter()
+            .map(|t| [0, 1, 2].map(|i| self.verts[t.verts[i] as usize].pos))
+            .collect();
+        if tris.is_empty() {
+            return None;
+        }
+        let area = |[a, b, c]: &[DVec3; 3]| (b - a).cross(&(c - a)).norm() / 2.0;
+        let total: f64 = tris.iter().map(area).sum();
+
+        // Triangles of zero area (e.g. from linear geometry) would all be
+        // ignored, so fall back to weighting every triangle equally
+        let weight = |t: &[DVec3; 3]| if total > 0.0 { area(t) } else { 1.0 };
+        let sum: f64 = tris.iter().map(weight).sum();
+        let center = tris
+            .iter()
+            .map(|t| weight(t) * (t[0] + t[1] + t[2]) / 3.0)
+            .sum::<DVec3>()
+            / sum;
+        let cov = tris
+            .iter()
+            .map(|t| {
+                let d = t.map(|p| p - center);
+                let s = d[0] + d[1] + d[2];
+                let outer = d.iter().map(|p| p * p.transpose()).sum::<DMat3>();
+                (outer + s * s.transpose()) * weight(t) / 12.0
+            })
+            .sum::<DMat3>();
+
+        let eigen = cov.symmetric_eigen();
+        let mut order = [0, 1, 2];
+        order.sort_by(|a, b| eigen.eigenvalues[*b].total_cmp(&eigen.eigenvalues[*a]));
+        let largest = eigen.eigenvalues[order[0]];
+        let known = |i: usize| largest > 0.0 && eigen.eigenvalues[order[i]] > largest * 1e-12;
+
+        // Picks a unit vector which is perpendicular to `v`, starting from
+        // the world axis which is least aligned with it
+        let complement = |v: DVec3| {
+            let axis = (0..3)
+                .min_by(|a, b| v[*a].abs().total_cmp(&v[*b].abs()))
+                .unwrap();
+            let mut w = DVec3::zeros();
+            w[axis] = 1.0;
+            (w - v * v.dot(&w)).normalize()
+        };
+        // Eigenvectors have arbitrary signs, so point each one along the
+        // positive direction of its largest component
+        let canonical = |v: DVec3| {
+            if v[v.iamax()] < 0.0 {
+                -v
+            } else {
+                v
+            }
+        };
+        let a = if known(0) {
+            canonical(eigen.eigenvectors.column(order[0]).into())
+        } else {
+            DVec3::x()
+        };
+        let b = if known(1) {
+            canonical(eigen.eigenvectors.column(order[1]).into())
+        } else {
+            complement(a)
+        };
+        let axes = [a, b, a.cross(&b)];
+
+        let mut lo = DVec3::repeat(f64::INFINITY);
+        let mut hi = DVec3::repeat(f64::NEG_INFINITY);
+        for p in tris.iter().flatten() {
+            let q = DVec3::from_fn(|i, _| axes[i].dot(&(p - center)));
+            lo = lo.inf(&q);
+            hi = hi.sup(&q);
+        }
+        let mid = (lo + hi) / 2.0;
+        Some(OrientedBox {
+            center: center + axes[0] * mid.x + axes[1] * mid.y + axes[2] * mid.z,
+            axes,
+            half_extents: (hi - lo) / 2.0,
+        })
+    }
+
+    /// Returns the rigid transform which centers the mesh on the origin,
+    /// with its longest principal axis along X and its shortest along Z
+    /// (see [`Mesh::oriented_bounding_box`]), or `None` if the mesh has no
+    /// triangles
+    pub fn align_to_principal_axes(&self) -> Option<DMat4> {
+        self.oriented_bounding_box().map(|b| b.to_world())
+    }
+
+    /// Counts edges which aren't shared by exactly two triangles, which is
+    /// zero for a watertight mesh.
+    ///
+    /// Faces are triangulated independently, so vertices are welded by
+    /// position (to within a tiny fraction of the mesh's size) first.
+    pub fn open_edges(&self) -> usize {
+        self.boundary_edges().len()
+    }
+
+    /// Returns a welded index for each vertex, which is shared by every
+    /// vertex at the same position (to within a tiny fraction of the mesh's
+    /// size)
+    fn welded_ids(&self) -> Vec<usize> {
+        let Some(first) = self.verts.first() else {
+            return vec![];
+        };
+        let (lo, hi) = self
+            .verts
+            .iter()
+            .fold((first.pos, first.pos), |(lo, hi), v| {
+                (lo.inf(&v.pos), hi.sup(&v.pos))
+            });
+        let tol = (hi - lo).norm().max(f64::MIN_POSITIVE) * 1e-7;
+
+        let mut welded = HashMap::new();
+        self.verts
+            .iter()
+            .map(|v| {
+                let key = (v.pos / tol).map(|f| f.round() as i64);
+                let n = welded.len();
+                *welded.entry((key.x, key.y, key.z)).or_insert(n)
+            })
+            .collect()
+    }
+
+    /// Calls `f(t, i, count, first)` for every edge of every triangle,
+    /// where the edge runs from corner `i` to corner `i + 1` of triangle
+    /// `t`, `count` is the number of triangles sharing the (welded) edge,
+    /// and `first` is the two lowest-numbered triangles which share it.
+    /// Degenerate edges, which collapse to a single welded vertex, are
+    /// skipped.
+    fn for_each_edge<F: FnMut(usize, usize, usize, [usize; 2])>(&self, mut f: F) {
+        let ids = self.welded_ids();
+        let key = |t: &Triangle, i: usize| {
+            let a = ids[t.verts[i] as usize];
+            let b = ids[t.verts[(i + 1) % 3] as usize];
+            (a != b).then(|| (a.min(b), a.max(b)))
+        };
+        let mut edges: HashMap<(usize, usize), (usize, [usize; 2])> = HashMap::new();
+        for (j, t) in self.triangles.iter().enumerate() {
+            for i in 0..3 {
+                if let Some(k) = key(t, i) {
+                    let e = edges.entry(k).or_insert((0, [j; 2]));
+                    if e.0 == 1 {
+                        e.1[1] = j;
+                    }
+                    e.0 += 1;
+                }
+            }
+        }
+        for (j, t) in self.triangles.iter().enumerate() {
+            for i in 0..3 {
+                if let Some(k) = key(t, i) {
+                    let (count, first) = edges[&k];
+                    f(j, i, count, first);
+                }
+            }
+        }
+    }
+
+    /// Returns the vertex indices of edges which aren't shared by exactly
+    /// two triangles, i.e. the boundaries of open surfaces.  As in
+    /// [`Mesh::open_edges`], vertices are welded by position, and each edge
+    /// is returned once (with the vertices of its first triangle).
+    pub fn boundary_edges(&self) -> Vec<(u32, u32)> {
+        let mut out = vec![];
+        self.for_each_edge(|j, i, count, first| {
+            if count != 2 && first[0] == j {
+                let t = &self.triangles[j];
+                out.push((t.verts[i], t.verts[(i + 1) % 3]));
+            }
+        });
+        out
+    }
+
+    /// Returns the vertex indices of "feature" edges, where two triangles
+    /// meet at a dihedral angle sharper than `angle_deg` (e.g. the edges of
+    /// a cube, but not the facets of a smoothly-curved surface).
+    ///
+    /// Vertices are welded by position, since faces are triangulated
+    /// independently, and each edge is returned once (with the vertices of
+    /// its first triangle).  Boundary edges aren't included; see
+    /// [`Mesh::boundary_edges`].
+    pub fn feature_edges(&self, angle_deg: f64) -> Vec<(u32, u32)> {
+        let normals: Vec<DVec3> = self
+            .triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| self.verts[t.verts[i] as usize].pos);
+                let n = (b - a).cross(&(c - a));
+                if n.norm() > 0.0 {
+                    n.normalize()
+                } else {
+                    n
+                }
+            })
+            .collect();
+        let cos = angle_deg.to_radians().cos();
+        let mut out = vec![];
+        self.for_each_edge(|j, i, count, first| {
+            let [n0, n1] = first.map(|t| normals[t]);
+            let degenerate = n0.norm() == 0.0 || n1.norm() == 0.0;
+            if count == 2 && first[0] == j && !degenerate && n0.dot(&n1) < cos {
+                let t = &self.triangles[j];
+                out.push((t.verts[i], t.verts[(i + 1) % 3]));
+            }
+        });
+        out
+    }
+
+    /// Joins edges (e.g. from [`Mesh::feature_edges`]) into polylines of
+    /// vertex indices, which continue through every (welded) vertex that's
+    /// shared by exactly two edges.  Points in the middle of straight runs
+    /// are dropped, so a straight edge becomes a single segment no matter
+    /// how finely it was triangulated.  Closed loops end at their first
+    /// point.
+    pub fn polylines(&self, edges: &[(u32, u32)]) -> Vec<Vec<u32>> {
+        let ids = self.welded_ids();
+        let mut adjacent: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, (a, b)) in edges.iter().enumerate() {
+            adjacent.entry(ids[*a as usize]).or_default().push(i);
+            adjacent.entry(ids[*b as usize]).or_default().push(i);
+        }
+
+        // Walks from a vertex along an edge, until reaching a vertex which
+        // isn't shared by exactly two edges (or has already been walked)
+        let mut used = vec![false; edges.len()];
+        let walk = |start: u32, mut e: usize, used: &mut Vec<bool>| {
+            let mut out = vec![start];
+            let mut here = ids[start as usize];
+            loop {
+                used[e] = true;
+                let (a, b) = edges[e];
+                let next = if ids[a as usize] == here { b } else { a };
+                out.push(next);
+                here = ids[next as usize];
+                match adjacent[&here].as_slice() {
+                    [x, y] if !used[*x] || !used[*y] => e = if used[*x] { *y } else { *x },
+                    _ => break,
+                }
+            }
+            out
+        };
+        let mut out = vec![];
+        // Open chains start from their ends, which are in the order of the
+        // first edge that touches them
+        for (a, b) in edges {
+            for v in [*a, *b] {
+                for e in &adjacent[&ids[v as usize]] {
+                    if adjacent[&ids[v as usize]].len() != 2 && !used[*e] {
+                        out.push(walk(v, *e, &mut used));
+                    }
+                }
+            }
+        }
+        // Anything left over is a closed loop
+        for e in 0..edges.len() {
+            if !used[e] {
+                out.push(walk(edges[e].0, e, &mut used));
+            }
+        }
+
+        for line in &mut out {
+            let pos = |v: u32| self.verts[v as usize].pos;
+            let mut kept = vec![line[0]];
+            for w in line.windows(3) {
+                let d0 = pos(w[1]) - pos(kept[kept.len() - 1]);
+                let d1 = pos(w[2]) - pos(w[1]);
+                let straight =
+                    d0.dot(&d1) > 0.0 && d0.cross(&d1).norm() <= 1e-9 * d0.norm() * d1.norm();
+                if !straight {
+                    kept.push(w[1]);
+                }
+            }
+            kept.push(line[line.len() - 1]);
+            *line = kept;
+        }
+        out
+    }
+
+    /// Builds a bounding volume hierarchy over the mesh's triangles, for
+    /// ray-casts and closest-point queries.  With the `rayon` feature, large
+    /// meshes are split up in parallel.
+    pub fn build_bvh(&self) -> MeshBvh {
+        MeshBvh::new(self)
+    }
+
     /// Writes the triangulation to a STL, for debugging
     pub fn save_stl(&self, filename: &str) -> std::io::Result<()> {
-        let mut out: Vec<u8> = vec![b'x'; 80];
-        let u: u32 = self.triangles.len().try_into().expect("Too many triangles");
-        out.extend(&u.to_le_bytes());
-        for t in self.triangles.iter() {
-            out.extend(std::iter::repeat(0).take(12)); // normal
-            for v in t.verts.iter() {
-                let v = self.verts[*v as usize];
-                out.extend(&(v.pos.x as f32).to_le_bytes());
-                out.extend(&(v.pos.y as f32).to_le_bytes());
-                out.extend(&(v.pos.z as f32).to_le_bytes());
-            }
-            out.extend(std::iter::repeat(0).take(2)); // attributes
-        }
-        std::fs::write(filename, out)
+        let mut out = std::io::BufWriter::new(std::fs::File::create(filename)?);
+        crate::export::write_stl(self, &mut out)?;
+        std::io::Write::flush(&mut out)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_edges() {
+        // Two triangles forming a square, with the shared edge's vertices
+        // duplicated (as happens between faces)
+        let mut mesh = Mesh::default();
+        let pts = [[0, 0], [1, 0], [1, 1], [0, 0], [1, 1], [0, 1]];
+        for [x, y] in pts {
+            mesh.verts.push(Vertex {
+                pos: DVec3::new(x as f64, y as f64, 0.0),
+                norm: DVec3::z(),
+                color: DVec3::zeros(),
+            });
+        }
+        for v in [[0, 1, 2], [3, 4, 5]] {
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(v[0], v[1], v[2]),
+            });
+        }
+        // Only the shared diagonal is closed
+        assert_eq!(mesh.open_edges(), 4);
+
+        // A tetrahedron is watertight, even with slightly-off positions
+        let mut mesh = Mesh::default();
+        let corners = [DVec3::zeros(), DVec3::x(), DVec3::y(), DVec3::z()];
+        for (i, tri) in [[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 3, 2]]
+            .iter()
+            .enumerate()
+        {
+            for c in tri {
+                mesh.verts.push(Vertex {
+                    pos: corners[*c] + DVec3::repeat(i as f64 * 1e-12),
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+            let j = 3 * i as u32;
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(j, j + 1, j + 2),
+            });
+        }
+        assert_eq!(mesh.open_edges(), 0);
+        assert_eq!(Mesh::default().open_edges(), 0);
+    }
+
+    /// Builds a sphere from latitude and longitude lines, as separate faces
+    /// (so that vertices are only shared after welding)
+    fn uv_sphere(slices: usize, stacks: usize) -> Mesh {
+        use std::f64::consts::PI;
+        let mut mesh = Mesh::default();
+        let push = |mesh: &mut Mesh, corners: [(usize, usize); 3]| {
+            let n = mesh.verts.len() as u32;
+            for (i, j) in corners {
+                let (theta, phi) = (
+                    i as f64 * PI / stacks as f64,
+                    j as f64 * 2.0 * PI / slices as f64,
+                );
+                let pos = DVec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                mesh.verts.push(Vertex {
+                    pos,
+                    norm: pos,
+                    color: DVec3::zeros(),
+                });
+            }
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(n, n + 1, n + 2),
+            });
+        };
+        for i in 0..stacks {
+            for j in 0..slices {
+                // Skip triangles which collapse to a line at the poles
+                if i != stacks - 1 {
+                    push(&mut mesh, [(i, j), (i + 1, j), (i + 1, j + 1)]);
+                }
+                if i != 0 {
+                    push(&mut mesh, [(i, j), (i + 1, j + 1), (i, j + 1)]);
+                }
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_feature_edges() {
+        use step::{fixture, step_file::StepFile};
+        let text = fixture::cube(1.0).to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let (mesh, _stats) = crate::triangulate::triangulate(&step);
+
+        // Face diagonals are flat, so only the cube's own edges are sharp
+        let edges = mesh.feature_edges(30.0);
+        assert_eq!(edges.len(), 12);
+        for (a, b) in &edges {
+            let d = mesh.verts[*b as usize].pos - mesh.verts[*a as usize].pos;
+            assert_eq!(d.abs().sum(), 1.0);
+            assert_eq!(d.abs().max(), 1.0);
+        }
+        assert!(mesh.feature_edges(95.0).is_empty());
+        assert!(mesh.boundary_edges().is_empty());
+        // Three edges meet at each corner, so none are joined
+        assert_eq!(mesh.polylines(&edges).len(), 12);
+
+        // A smooth surface has no feature edges, even with welding
+        let sphere = uv_sphere(16, 8);
+        assert_eq!(sphere.open_edges(), 0);
+        assert!(sphere.feature_edges(30.0).is_empty());
+        assert!(!sphere.feature_edges(10.0).is_empty());
+    }
+
+    #[test]
+    fn test_polylines() {
+        let mut mesh = Mesh::default();
+        // A straight line split into three segments, with a duplicated
+        // vertex (5) in the middle, then a corner
+        let pts = [[0, 0], [1, 0], [2, 0], [3, 0], [3, 1], [1, 0]];
+        // A separate square loop
+        let square = [[0, 5], [1, 5], [1, 6], [0, 6]];
+        for [x, y] in pts.iter().chain(square.iter()) {
+            mesh.verts.push(Vertex {
+                pos: DVec3::new(*x as f64, *y as f64, 0.0),
+                norm: DVec3::z(),
+                color: DVec3::zeros(),
+            });
+        }
+        let edges = [
+            (0, 1),
+            (5, 2),
+            (2, 3),
+            (3, 4),
+            (6, 7),
+            (7, 8),
+            (8, 9),
+            (9, 6),
+        ];
+        let lines = mesh.polylines(&edges);
+        assert_eq!(lines, vec![vec![0, 3, 4], vec![6, 7, 8, 9, 6]]);
+
+        // Reversed edges still chain together
+        let edges = [(1, 0), (2, 5), (3, 2)];
+        assert_eq!(mesh.polylines(&edges), vec![vec![0, 3]]);
+        assert!(mesh.polylines(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_select_parts() {
+        // Three parts, each of which is a single triangle, with the middle
+        // part sharing a vertex with each of its neighbors.
+        let mut mesh = Mesh::default();
+        for i in 0..7 {
+            mesh.verts.push(Vertex {
+                pos: DVec3::new(i as f64, 0.0, 0.0),
+                norm: DVec3::z(),
+                color: DVec3::zeros(),
+            });
+        }
+        for (i, v) in [[0, 1, 2], [2, 3, 4], [4, 5, 6]].iter().enumerate() {
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(v[0], v[1], v[2]),
+            });
+            mesh.parts.push(Part {
+                shape: 10 + i,
+                triangles: i..(i + 1),
+                transform: DMat4::identity(),
+            });
+            mesh.faces.push(Face {
+                id: 20 + i,
+                triangles: i..(i + 1),
+            });
+        }
+
+        let out = mesh.select_parts(&[true, false, true]);
+        assert_eq!(out.verts.len(), 6);
+        assert_eq!(out.triangles.len(), 2);
+        assert_eq!(out.parts.len(), 2);
+        assert_eq!(out.parts[1].shape, 12);
+        assert_eq!(out.parts[1].triangles, 1..2);
+        let xs: Vec<f64> = out.triangles[1]
+            .verts
+            .iter()
+            .map(|v| out.verts[*v as usize].pos.x)
+            .collect();
+        assert_eq!(xs, vec![4.0, 5.0, 6.0]);
+        assert_eq!(out.faces.len(), 2);
+        assert_eq!(out.faces[1].id, 22);
+        assert_eq!(out.faces[1].triangles, 1..2);
+
+        let out = mesh.select_parts(&[false, true, false]);
+        assert_eq!(out.verts.len(), 3);
+        assert_eq!(out.triangles[0].verts, U32Vec3::new(0, 1, 2));
+
+        assert!(mesh.select_parts(&[false; 3]).triangles.is_empty());
+    }
+
+    #[test]
+    fn test_decimate() {
+        // A strip of two faces, each a 10x1 grid of quads, which share the
+        // vertices along their common edge
+        let mut mesh = Mesh::default();
+        for i in 0..=20 {
+            for j in 0..=1 {
+                mesh.verts.push(Vertex {
+                    pos: DVec3::new(i as f64, j as f64, 0.0),
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+        }
+        for f in 0..2 {
+            let start = mesh.triangles.len();
+            for i in (f * 10)..(f * 10 + 10) {
+                let v = |di: u32, j: u32| (i as u32 + di) * 2 + j;
+                mesh.triangles.push(Triangle {
+                    verts: U32Vec3::new(v(0, 0), v(1, 0), v(1, 1)),
+                });
+                mesh.triangles.push(Triangle {
+                    verts: U32Vec3::new(v(0, 0), v(1, 1), v(0, 1)),
+                });
+            }
+            mesh.faces.push(Face {
+                id: 30 + f,
+                triangles: start..mesh.triangles.len(),
+            });
+        }
+        mesh.parts.push(Part {
+            shape: 1,
+            triangles: 0..40,
+            transform: DMat4::identity(),
+        });
+        mesh.curvature = Some((0..mesh.verts.len()).map(|i| i as f32).collect());
+        mesh.quads = vec![0, 2];
+
+        // A tiny cell changes nothing
+        let out = mesh.decimate(0.5);
+        assert_eq!(out.triangles.len(), 40);
+        // ...except that the shared edge is split between the faces
+        assert_eq!(out.verts.len(), 44);
+        assert!(out.quads.is_empty());
+
+        // Coarser cells drop triangles, but keep faces in place
+        let out = mesh.decimate(4.0);
+        assert!(out.triangles.len() < 40);
+        assert_eq!(out.faces.len(), 2);
+        assert_eq!(out.faces[0].id, 30);
+        assert_eq!(out.faces[0].triangles.start, 0);
+        assert_eq!(out.faces[0].triangles.end, out.faces[1].triangles.start);
+        assert_eq!(out.faces[1].triangles.end, out.triangles.len());
+        assert_eq!(out.parts[0].triangles, 0..out.triangles.len());
+        assert_eq!(out.curvature.as_ref().unwrap().len(), out.verts.len());
+
+        // Faces never share vertices, even when they're in the same cell
+        let faces = out.vertex_faces();
+        for (i, f) in out.faces.iter().enumerate() {
+            for t in &out.triangles[f.triangles.clone()] {
+                assert!(t.verts.iter().all(|v| faces[*v as usize] == Some(i)));
+            }
+        }
+
+        // A cell larger than the whole mesh collapses everything
+        let out = mesh.decimate(100.0);
+        assert!(out.triangles.is_empty());
+        assert!(out.faces.iter().all(|f| f.triangles.is_empty()));
+    }
+
+    #[test]
+    fn test_vertex_faces() {
+        let mut mesh = Mesh::default();
+        for i in 0..7 {
+            mesh.verts.push(Vertex {
+                pos: DVec3::new(i as f64, 0.0, 0.0),
+                norm: DVec3::z(),
+                color: DVec3::zeros(),
+            });
+        }
+        for v in [[0, 1, 2], [1, 2, 3], [4, 5, 6]] {
+            mesh.triangles.push(Triangle {
+                verts: U32Vec3::new(v[0], v[1], v[2]),
+            });
+        }
+        mesh.faces.push(Face {
+            id: 100,
+            triangles: 0..2,
+        });
+        mesh.faces.push(Face {
+            id: 200,
+            triangles: 2..3,
+        });
+        let a = Some(0);
+        let b = Some(1);
+        assert_eq!(mesh.vertex_faces(), vec![a, a, a, a, b, b, b]);
+
+        let c = Mesh::combine(mesh.clone(), mesh);
+        assert_eq!(c.faces.len(), 4);
+        assert_eq!(c.faces[3].id, 200);
+        assert_eq!(c.faces[3].triangles, 5..6);
+    }
+
+    #[test]
+    fn test_curvature_channel() {
+        let vert = Vertex {
+            pos: DVec3::zeros(),
+            norm: DVec3::z(),
+            color: DVec3::zeros(),
+        };
+        let mut a = Mesh {
+            verts: vec![vert; 3],
+            ..Mesh::default()
+        };
+        a.triangles.push(Triangle {
+            verts: U32Vec3::new(0, 1, 2),
+        });
+        a.parts.push(Part {
+            shape: 0,
+            triangles: 0..1,
+            transform: DMat4::identity(),
+        });
+        let mut b = a.clone();
+        b.curvature = Some(vec![1.0, 2.0, 3.0]);
+
+        // Combining fills in missing curvature with NaN
+        let c = Mesh::combine(a.clone(), b.clone());
+        let curv = c.curvature.as_ref().unwrap();
+        assert_eq!(curv.len(), 6);
+        assert!(curv[..3].iter().all(|c| c.is_nan()));
+        assert_eq!(&curv[3..], &[1.0, 2.0, 3.0]);
+
+        // Neither mesh has curvature, so the channel stays empty
+        assert!(Mesh::combine(a.clone(), a).curvature.is_none());
+
+        // Selecting parts keeps curvature aligned with vertices
+        let s = c.select_parts(&[false, true]);
+        assert_eq!(s.curvature, Some(vec![1.0, 2.0, 3.0]));
+    }
+    #[test]
+    fn test_color_channel() {
+        let vert = Vertex {
+            pos: DVec3::zeros(),
+            norm: DVec3::z(),
+            color: DVec3::new(1.0, 0.5, 0.0),
+        };
+        let mut a = Mesh {
+            verts: vec![vert; 3],
+            ..Mesh::default()
+        };
+        a.triangles.push(Triangle {
+            verts: U32Vec3::new(0, 1, 2),
+        });
+        a.parts.push(Part {
+            shape: 0,
+            triangles: 0..1,
+            transform: DMat4::identity(),
+        });
+        let mut b = a.clone();
+        let painted = vec![[10, 20, 30, 40], [50, 60, 70, 80], [90, 100, 110, 120]];
+        b.colors = Some(painted.clone());
+        assert_eq!(a.vertex_colors(), vec![[255, 128, 0, 255]; 3]);
+        assert_eq!(b.vertex_colors(), painted);
+
+        // Combining fills in missing colors from the vertices, in either
+        // order
+        let c = Mesh::combine(a.clone(), b.clone());
+        let colors = c.colors.as_ref().unwrap();
+        assert_eq!(colors[..3], [[255, 128, 0, 255]; 3]);
+        assert_eq!(colors[3..], painted[..]);
+        let d = Mesh::combine(b.clone(), a.clone());
+        assert_eq!(d.colors.as_ref().unwrap()[..3], painted[..]);
+        assert_eq!(d.colors.as_ref().unwrap()[3..], [[255, 128, 0, 255]; 3]);
+
+        // Neither mesh has colors, so the channel stays empty
+        assert!(Mesh::combine(a.clone(), a).colors.is_none());
+
+        // Selecting parts keeps colors aligned with vertices
+        assert_eq!(c.select_parts(&[false, true]).colors, Some(painted));
+        let s = d.select_parts(&[false, true]);
+        assert_eq!(s.colors, Some(vec![[255, 128, 0, 255]; 3]));
+        assert_eq!(s.verts.len(), 3);
+    }
+
+    #[test]
+    fn test_mass_properties() {
+        use step::{fixture, step_file::StepFile};
+        let text = fixture::cube(2.0).translate([100.0, 0.0, 0.0]).to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let mesh = crate::triangulate::triangulate(&step).0;
+        assert_eq!(mesh.faces.len(), 6);
+
+        // Every face of the cube is a 2x2 square, which is found by looking
+        // up its triangles
+        let top = (0..mesh.triangles.len())
+            .find(|t| {
+                let v = mesh.triangles[*t].verts;
+                v.iter()
+                    .all(|i| (mesh.verts[*i as usize].pos.z - 2.0).abs() < 1e-9)
+            })
+            .and_then(|t| mesh.triangle_face(t))
+            .unwrap();
+        let m = mesh.face_properties(&[top]);
+        assert!((m.area - 4.0).abs() < 1e-9, "{:?}", m);
+        let m = mesh.face_properties(&[0, 1]);
+        assert!((m.area - 8.0).abs() < 1e-9, "{:?}", m);
+        assert_eq!(mesh.face_properties(&[]), MassProperties::default());
+
+        // The whole solid is closed, so its volume is known too
+        let m = mesh.part_properties(0);
+        assert!((m.area - 24.0).abs() < 1e-9, "{:?}", m);
+        assert!((m.volume - 8.0).abs() < 1e-9, "{:?}", m);
+        let all: Vec<usize> = (0..6).collect();
+        assert!((mesh.face_properties(&all).volume - 8.0).abs() < 1e-9);
+
+        assert_eq!(mesh.triangle_face(mesh.triangles.len()), None);
+    }
+
+    /// Triangulates the cube fixture, stretched into a 4 x 2 x 1 box
+    fn box_mesh() -> Mesh {
+        use step::{fixture, step_file::StepFile};
+        let text = fixture::cube(2.0).to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let mut mesh = crate::triangulate::triangulate(&step).0;
+        for v in &mut mesh.verts {
+            v.pos = v.pos.component_mul(&DVec3::new(2.0, 1.0, 0.5));
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_oriented_bounding_box() {
+        let mut mesh = box_mesh();
+        let rot = nalgebra_glm::rotation(0.7, &DVec3::new(1.0, 2.0, 3.0).normalize());
+        let mat = nalgebra_glm::translation(&DVec3::new(5.0, -3.0, 2.0)) * rot;
+        mesh.apply_rigid(&mat);
+
+        let obb = mesh.oriented_bounding_box().unwrap();
+        let close = |a: DVec3, b: DVec3| (a - b).norm() < 1e-9;
+        assert!(
+            close(obb.half_extents, DVec3::new(2.0, 1.0, 0.5)),
+            "{:?}",
+            obb
+        );
+        let center = (mat * DVec3::new(2.0, 1.0, 0.5).push(1.0)).xyz();
+        assert!(close(obb.center, center), "{:?}", obb);
+        assert!(close(obb.axes[0].cross(&obb.axes[1]), obb.axes[2]));
+
+        // Aligning moves the box back onto the world axes, centered on the
+        // origin (though possibly flipped end to end)
+        mesh.apply_rigid(&mesh.align_to_principal_axes().unwrap());
+        let (mut lo, mut hi) = (DVec3::repeat(f64::MAX), DVec3::repeat(f64::MIN));
+        for v in &mesh.verts {
+            lo = lo.inf(&v.pos);
+            hi = hi.sup(&v.pos);
+        }
+        assert!(close(hi, DVec3::new(2.0, 1.0, 0.5)), "{:?}", hi);
+        assert!(close(lo, -hi), "{:?}", lo);
+
+        assert_eq!(Mesh::default().oriented_bounding_box(), None);
+    }
+
+    #[test]
+    fn test_oriented_bounding_box_degenerate() {
+        let mesh_of = |pts: &[[f64; 3]]| {
+            let mut mesh = Mesh::default();
+            for p in pts {
+                mesh.verts.push(Vertex {
+                    pos: DVec3::from(*p),
+                    norm: DVec3::z(),
+                    color: DVec3::zeros(),
+                });
+            }
+            for i in (0..pts.len() as u32).step_by(3) {
+                mesh.triangles.push(Triangle {
+                    verts: U32Vec3::new(i, i + 1, i + 2),
+                });
+            }
+            mesh
+        };
+        let close = |a: DVec3, b: DVec3| (a - b).norm() < 1e-9;
+
+        // A flat rectangle gets a zero-thickness box, with its normal as the
+        // last axis
+        let flat = mesh_of(&[
+            [0.0, 0.0, 1.0],
+            [4.0, 0.0, 1.0],
+            [4.0, 2.0, 1.0],
+            [0.0, 0.0, 1.0],
+            [4.0, 2.0, 1.0],
+            [0.0, 2.0, 1.0],
+        ]);
+        let obb = flat.oriented_bounding_box().unwrap();
+        assert!(
+            close(obb.half_extents, DVec3::new(2.0, 1.0, 0.0)),
+            "{:?}",
+            obb
+        );
+        assert!(close(obb.axes[0], DVec3::x()), "{:?}", obb);
+        assert!(close(obb.axes[2], DVec3::z()), "{:?}", obb);
+
+        // Collinear points only determine one axis, and the others are
+        // filled in the same way every time
+        let line = mesh_of(&[[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [3.0, 3.0, 3.0]]);
+        let obb = line.oriented_bounding_box().unwrap();
+        let diagonal = DVec3::repeat(1.0).normalize();
+        assert!(close(obb.axes[0], diagonal), "{:?}", obb);
+        assert!(obb.axes[1].dot(&diagonal).abs() < 1e-9);
+        assert!(close(obb.axes[0].cross(&obb.axes[1]), obb.axes[2]));
+        assert!(close(
+            obb.half_extents,
+            DVec3::new(1.5 * 3f64.sqrt(), 0.0, 0.0)
+        ));
+        assert_eq!(line.oriented_bounding_box(), Some(obb));
+
+        // A single point is its own box, on the world axes
+        let point = mesh_of(&[[1.0, 2.0, 3.0]; 3]);
+        let obb = point.oriented_bounding_box().unwrap();
+        assert_eq!(obb.axes, [DVec3::x(), DVec3::y(), DVec3::z()]);
+        assert_eq!(obb.half_extents, DVec3::zeros());
+        assert!(close(obb.center, DVec3::new(1.0, 2.0, 3.0)));
     }
 }
@@ -0,0 +1,656 @@
+//! Checks the p-curves of a face's edges against the edges themselves.
+//!
+//! A p-curve describes an edge in the parameter space of one of the surfaces
+//! which it bounds.  Exporters often write p-curves which disagree with the
+//! edge's 3D curve, either running in the opposite direction or sitting a
+//! whole period away from the rest of their loop on a closed surface, which
+//! shreds the face if they're taken at face value.  Each p-curve is mapped
+//! through its surface and compared with the points sampled from the edge,
+//! then repaired (or thrown away) before it's used.
+use std::f64::consts::PI;
+use std::ops::{Add, Mul};
+
+use glm::{DVec2, DVec3};
+use nalgebra_glm as glm;
+use nurbs::NdBsplineCurve;
+
+use crate::surface::Surface;
+
+/// Number of segments that a p-curve is sampled into
+const NUM_SAMPLES: usize = 32;
+
+/// Largest distance between a p-curve (mapped through its surface) and the
+/// points sampled from its edge, relative to the length of the edge
+const TOLERANCE: f64 = 0.01;
+
+/// Iterations of Newton's method when projecting a point onto a p-curve
+const MAX_ITERATIONS: usize = 32;
+
+/// A repair which was applied to a p-curve to make it agree with its edge
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub enum PcurveRepair {
+    /// The p-curve ran against the edge's curve, so it was reversed
+    Reversed,
+    /// The p-curve was shifted by a whole period of its surface, to line up
+    /// with the rest of its loop
+    Shifted,
+    /// The p-curve didn't match its edge, so the edge is projected onto the
+    /// surface instead
+    Discarded,
+}
+
+/// An orthonormal frame, built from a STEP axis placement
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Frame {
+    location: DVec3,
+    x: DVec3,
+    y: DVec3,
+    z: DVec3,
+}
+
+impl Frame {
+    pub fn new(location: DVec3, axis: DVec3, ref_direction: DVec3) -> Self {
+        let z = axis.normalize();
+        let x = (ref_direction - z * ref_direction.dot(&z)).normalize();
+        let y = z.cross(&x);
+        Self { location, x, y, z }
+    }
+
+    /// Returns the point at the given angle around (and height along) the
+    /// frame's axis, at distance `r` from it
+    fn revolve(&self, angle: f64, r: f64, height: f64) -> DVec3 {
+        self.location + r * (angle.cos() * self.x + angle.sin() * self.y) + height * self.z
+    }
+}
+
+/// The parameterization of a surface which p-curves are written in, as
+/// defined in ISO 10303-42.  Unlike [`Surface::lower`], this is the same
+/// mapping that the exporter used, so angles are in radians and splines
+/// are evaluated without scaling.
+pub(crate) enum Parameterization<'a> {
+    Plane(Frame),
+    Cylinder(Frame, f64),
+    Cone {
+        frame: Frame,
+        radius: f64,
+        semi_angle: f64,
+    },
+    Sphere(Frame, f64),
+    Torus {
+        frame: Frame,
+        major_radius: f64,
+        minor_radius: f64,
+    },
+    /// A b-spline or NURBS surface, which is evaluated directly
+    Spline(&'a Surface),
+}
+
+impl Parameterization<'_> {
+    /// Evaluates the surface, returning `None` if the point is outside of
+    /// its domain (in a direction where it doesn't wrap around)
+    pub fn point(&self, uv: DVec2) -> Option<DVec3> {
+        let (u, v) = (uv.x, uv.y);
+        Some(match self {
+            Parameterization::Plane(f) => f.location + u * f.x + v * f.y,
+            Parameterization::Cylinder(f, r) => f.revolve(u, *r, v),
+            Parameterization::Cone {
+                frame,
+                radius,
+                semi_angle,
+            } => frame.revolve(u, radius + v * semi_angle.tan(), v),
+            Parameterization::Sphere(f, r) => f.revolve(u, r * v.cos(), r * v.sin()),
+            Parameterization::Torus {
+                frame,
+                major_radius,
+                minor_radius,
+            } => frame.revolve(
+                u,
+                major_radius + minor_radius * v.cos(),
+                minor_radius * v.sin(),
+            ),
+            Parameterization::Spline(s) => {
+                let (lo, hi) = self.domain();
+                let period = self.period();
+                let mut uv = uv;
+                for i in 0..2 {
+                    if period[i] > 0.0 {
+                        uv[i] = wrap(uv[i], lo[i], hi[i]);
+                    } else if uv[i] < lo[i] - (hi[i] - lo[i]) * 1e-6
+                        || uv[i] > hi[i] + (hi[i] - lo[i]) * 1e-6
+                    {
+                        return None;
+                    }
+                }
+                s.raise(uv)?
+            }
+        })
+    }
+
+    /// Returns the period of the surface in u and v, which is zero in
+    /// directions where it doesn't wrap around
+    pub fn period(&self) -> DVec2 {
+        match self {
+            Parameterization::Plane(..) => DVec2::zeros(),
+            Parameterization::Cylinder(..)
+            | Parameterization::Cone { .. }
+            | Parameterization::Sphere(..) => DVec2::new(2.0 * PI, 0.0),
+            Parameterization::Torus { .. } => DVec2::new(2.0 * PI, 2.0 * PI),
+            Parameterization::Spline(s) => {
+                let (lo, hi) = self.domain();
+                let closed = match s {
+                    Surface::Bspline(s) => (!s.surf.u_open, !s.surf.v_open),
+                    Surface::Nurbs(s) => (!s.surf.u_open, !s.surf.v_open),
+                    _ => (false, false),
+                };
+                DVec2::new(
+                    if closed.0 { hi.x - lo.x } else { 0.0 },
+                    if closed.1 { hi.y - lo.y } else { 0.0 },
+                )
+            }
+        }
+    }
+
+    /// Returns the lower and upper corners of the surface's domain
+    fn domain(&self) -> (DVec2, DVec2) {
+        let inf = f64::INFINITY;
+        match self {
+            Parameterization::Plane(..) => (DVec2::new(-inf, -inf), DVec2::new(inf, inf)),
+            Parameterization::Cylinder(..) | Parameterization::Cone { .. } => {
+                (DVec2::new(0.0, -inf), DVec2::new(2.0 * PI, inf))
+            }
+            Parameterization::Sphere(..) => {
+                (DVec2::new(0.0, -PI / 2.0), DVec2::new(2.0 * PI, PI / 2.0))
+            }
+            Parameterization::Torus { .. } => (DVec2::zeros(), DVec2::new(2.0 * PI, 2.0 * PI)),
+            Parameterization::Spline(s) => match s {
+                Surface::Bspline(s) => (
+                    DVec2::new(s.surf.min_u(), s.surf.min_v()),
+                    DVec2::new(s.surf.max_u(), s.surf.max_v()),
+                ),
+                Surface::Nurbs(s) => (
+                    DVec2::new(s.surf.min_u(), s.surf.min_v()),
+                    DVec2::new(s.surf.max_u(), s.surf.max_v()),
+                ),
+                _ => (DVec2::new(-inf, -inf), DVec2::new(inf, inf)),
+            },
+        }
+    }
+}
+
+/// A curve in a surface's parameter space
+pub(crate) enum Curve2 {
+    Line {
+        pnt: DVec2,
+        dir: DVec2,
+    },
+    Polyline(Vec<DVec2>),
+    Bspline(NdBsplineCurve<2>),
+    /// A rational b-spline curve, with homogeneous control points
+    Nurbs(NdBsplineCurve<3>),
+}
+
+impl Curve2 {
+    fn point(&self, t: f64) -> DVec2 {
+        match self {
+            Curve2::Line { pnt, dir } => pnt + dir * t,
+            Curve2::Polyline(pts) => {
+                let i = (t.floor().max(0.0) as usize).min(pts.len().saturating_sub(2));
+                let frac = t - i as f64;
+                pts[i] * (1.0 - frac) + pts[(i + 1).min(pts.len() - 1)] * frac
+            }
+            Curve2::Bspline(c) => c.curve_point(t),
+            Curve2::Nurbs(c) => {
+                let p = c.curve_point(t);
+                DVec2::new(p.x / p.z, p.y / p.z)
+            }
+        }
+    }
+
+    /// Returns the range of the curve's parameter, or `None` if it's
+    /// unbounded
+    fn domain(&self) -> Option<(f64, f64)> {
+        match self {
+            Curve2::Line { .. } => None,
+            Curve2::Polyline(pts) => Some((0.0, pts.len().saturating_sub(1) as f64)),
+            Curve2::Bspline(c) => Some((c.min_u(), c.max_u())),
+            Curve2::Nurbs(c) => Some((c.min_u(), c.max_u())),
+        }
+    }
+
+    /// Evaluates the curve, wrapping the parameter around if the curve is
+    /// bounded (since a parameter outside of its domain only comes from
+    /// going around a closed curve)
+    fn wrapped_point(&self, t: f64) -> DVec2 {
+        match self.domain() {
+            Some((a, b)) if b > a => self.point(wrap(t, a, b)),
+            _ => self.point(t),
+        }
+    }
+
+    /// Returns the amounts by which the curve's parameter can change to
+    /// return to the same place on the surface, i.e. the lengths of its
+    /// domain if it's closed, or the steps which move a line by one period
+    /// of its surface
+    fn periods(&self, surf: &Parameterization) -> Vec<f64> {
+        match (self, self.domain()) {
+            (Curve2::Line { dir, .. }, _) => {
+                let period = surf.period();
+                (0..2)
+                    .filter(|&i| period[i] > 0.0 && dir[i].abs() > 1e-12)
+                    .map(|i| (period[i] / dir[i]).abs())
+                    .collect()
+            }
+            (_, Some((a, b))) if (self.point(a) - self.point(b)).norm() <= 1e-9 => vec![b - a],
+            _ => vec![],
+        }
+    }
+}
+
+/// Wraps `t` into the range `lo..=hi`
+fn wrap(t: f64, lo: f64, hi: f64) -> f64 {
+    if t < lo || t > hi {
+        lo + (t - lo).rem_euclid(hi - lo)
+    } else {
+        t
+    }
+}
+
+/// Finds the parameter of the point on a p-curve (mapped through its
+/// surface) which is closest to `p`, by picking the best of a set of starting
+/// points, then refining it with Newton's method
+fn project(surf: &Parameterization, c: &Curve2, p: DVec3) -> Option<f64> {
+    let f = |t: f64| surf.point(c.wrapped_point(t));
+    let dist = |t: f64| f(t).map_or(f64::INFINITY, |q| (q - p).norm());
+
+    // Start points are ordered so that ties go to the one nearest the start
+    // of the curve (or zero, for lines)
+    let seeds: Vec<f64> = match c.domain() {
+        Some((a, b)) => (0..=NUM_SAMPLES * 2)
+            .map(|i| a + (b - a) * i as f64 / (NUM_SAMPLES * 2) as f64)
+            .collect(),
+        None => {
+            let step = c.periods(surf).first().copied().unwrap_or(2.0 * PI) / 4.0;
+            std::iter::once(0.0)
+                .chain((1..=8).flat_map(|i| [i as f64 * step, -(i as f64) * step]))
+                .collect()
+        }
+    };
+    let mut t = seeds[0];
+    let mut best = dist(t);
+    for s in &seeds[1..] {
+        // Ignore improvements which are only rounding error
+        let d = dist(*s);
+        if d < best - 1e-9 * (1.0 + best) {
+            (t, best) = (*s, d);
+        }
+    }
+    if !best.is_finite() {
+        return None;
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        let h = 1e-6 * (1.0 + t.abs());
+        let deriv = (f(t + h)? - f(t - h)?) / (2.0 * h);
+        let r = p - f(t)?;
+        if deriv.norm_squared() < 1e-24 {
+            break;
+        }
+        let mut next = t + r.dot(&deriv) / deriv.norm_squared();
+        if let Some((a, b)) = c.domain() {
+            next = next.clamp(a, b);
+        }
+        let done = (next - t).abs() <= 1e-12 * (1.0 + t.abs());
+        t = next;
+        if done {
+            break;
+        }
+    }
+    Some(t)
+}
+
+/// Returns the cumulative distance along a polyline at each of its points
+fn arc_lengths(pts: &[DVec3]) -> Vec<f64> {
+    let mut out = Vec::with_capacity(pts.len());
+    let mut total = 0.0;
+    for (i, p) in pts.iter().enumerate() {
+        if i > 0 {
+            total += (p - pts[i - 1]).norm();
+        }
+        out.push(total);
+    }
+    out
+}
+
+/// Interpolates between values at the given fraction of the way along a
+/// polyline, whose cumulative lengths are in `lengths`
+fn at_fraction<T>(lengths: &[f64], values: &[T], frac: f64) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    let total = *lengths.last().unwrap();
+    if total <= 0.0 {
+        return values[0];
+    }
+    let target = frac * total;
+    let i = lengths
+        .partition_point(|d| *d < target)
+        .clamp(1, lengths.len() - 1);
+    let span = lengths[i] - lengths[i - 1];
+    let s = if span > 0.0 {
+        ((target - lengths[i - 1]) / span).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    values[i - 1] * (1.0 - s) + values[i] * s
+}
+
+/// A p-curve which matches its edge
+pub(crate) struct Fit {
+    /// Position in parameter space of each of the edge's points
+    pub uv: Vec<DVec2>,
+    /// Whether the p-curve runs against the edge's curve
+    pub reversed: bool,
+}
+
+/// Compares a p-curve with the points sampled from its edge, which run along
+/// the edge's curve if `along` is true.  Returns `None` if the p-curve (in
+/// either direction) doesn't match the points.
+pub(crate) fn fit(surf: &Parameterization, c: &Curve2, pts: &[DVec3], along: bool) -> Option<Fit> {
+    let lengths = arc_lengths(pts);
+    let length = *lengths.last()?;
+    let tol = length * TOLERANCE;
+
+    // Pick out candidate ranges of the p-curve's parameter, which start and
+    // end at the edge's ends (going either way around closed curves)
+    let mut ranges = vec![];
+    let periods = c.periods(surf);
+    if (pts[0] - pts[pts.len() - 1]).norm() <= tol {
+        match c.domain() {
+            Some((a, b)) => ranges.extend([(a, b), (b, a)]),
+            None => {
+                let t0 = project(surf, c, pts[0])?;
+                ranges.extend(periods.iter().flat_map(|p| [(t0, t0 + p), (t0, t0 - p)]));
+            }
+        }
+    } else {
+        let t0 = project(surf, c, pts[0])?;
+        let t1 = project(surf, c, pts[pts.len() - 1])?;
+        ranges.push((t0, t1));
+        ranges.extend(periods.iter().flat_map(|p| [(t0, t1 + p), (t0, t1 - p)]));
+    }
+    // Prefer ranges which run in the expected direction
+    ranges.sort_by_key(|(t0, t1)| (t1 > t0) != along);
+
+    for (t0, t1) in ranges {
+        let uv: Vec<DVec2> = (0..=NUM_SAMPLES)
+            .map(|i| c.wrapped_point(t0 + (t1 - t0) * i as f64 / NUM_SAMPLES as f64))
+            .collect();
+        let Some(mapped) = uv
+            .iter()
+            .map(|uv| surf.point(*uv))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+
+        // Compare the two polylines at matching fractions of their length
+        let mapped_lengths = arc_lengths(&mapped);
+        let matches = (0..=NUM_SAMPLES).all(|i| {
+            let frac = i as f64 / NUM_SAMPLES as f64;
+            let a = at_fraction(&lengths, pts, frac);
+            let b = at_fraction(&mapped_lengths, &mapped, frac);
+            (a - b).norm() <= tol
+        });
+        if matches {
+            let uv = lengths
+                .iter()
+                .map(|d| at_fraction(&mapped_lengths, &uv, d / length))
+                .collect();
+            return Some(Fit {
+                uv,
+                reversed: (t1 > t0) != along,
+            });
+        }
+    }
+    None
+}
+
+/// An edge within a loop, as seen by [`check_loop`]
+pub(crate) struct LoopEdge<'a> {
+    /// P-curves of the edge on the loop's surface, with their entity
+    /// indices.  Seam edges have two, one on each side of the seam.
+    pub pcurves: Vec<(usize, Curve2)>,
+    /// Points sampled from the edge, in the loop's direction
+    pub pts: &'a [DVec3],
+    /// Whether the points run along the edge's curve
+    pub along: bool,
+}
+
+/// Returns the whole number of periods by which a p-curve starting at
+/// `start` should be shifted, so that it starts where the previous edge in
+/// its loop ended (or, for the first edge, so that it's within half a
+/// period of the surface's domain)
+fn shift(surf: &Parameterization, start: DVec2, prev: Option<DVec2>) -> DVec2 {
+    let period = surf.period();
+    let (lo, hi) = surf.domain();
+    let mut out = DVec2::zeros();
+    for i in 0..2 {
+        if period[i] <= 0.0 {
+            continue;
+        }
+        let target = match prev {
+            Some(p) => p[i],
+            None => (lo[i] + hi[i]) / 2.0,
+        };
+        let k = ((target - start[i]) / period[i]).round();
+        if prev.is_some() || (target - start[i]).abs() > period[i] {
+            out[i] = k * period[i];
+        }
+    }
+    out
+}
+
+/// Checks the p-curves of each edge in a loop, in order.  Returns the
+/// position in parameter space of each edge's points (or `None` for edges
+/// without a usable p-curve), along with the repairs that were applied,
+/// keyed by p-curve.
+#[allow(clippy::type_complexity)]
+pub(crate) fn check_loop(
+    surf: &Parameterization,
+    edges: &[LoopEdge],
+) -> (Vec<Option<Vec<DVec2>>>, Vec<(usize, PcurveRepair)>) {
+    let mut out = vec![];
+    let mut repairs = vec![];
+    let mut prev: Option<DVec2> = None;
+    for e in edges {
+        // Degenerate edges (e.g. at the apex of a cone) can't be compared
+        let degenerate = e.pts.windows(2).all(|w| (w[0] - w[1]).norm() <= 1e-12);
+        if e.pcurves.is_empty() || degenerate {
+            out.push(None);
+            prev = None;
+            continue;
+        }
+
+        // Pick the p-curve which needs the fewest repairs, which matters for
+        // seams: both sides match the edge, but only one lines up with the
+        // previous edge
+        let best = e
+            .pcurves
+            .iter()
+            .filter_map(|(id, c)| {
+                let f = fit(surf, c, e.pts, e.along)?;
+                let shift = shift(surf, f.uv[0], prev);
+                Some((*id, f, shift))
+            })
+            .min_by_key(|(_, f, shift)| f.reversed as usize + (*shift != DVec2::zeros()) as usize);
+
+        match best {
+            Some((id, f, shift)) => {
+                if f.reversed {
+                    repairs.push((id, PcurveRepair::Reversed));
+                }
+                if shift != DVec2::zeros() {
+                    repairs.push((id, PcurveRepair::Shifted));
+                }
+                let uv: Vec<DVec2> = f.uv.into_iter().map(|uv| uv + shift).collect();
+                prev = uv.last().copied();
+                out.push(Some(uv));
+            }
+            None => {
+                repairs.push((e.pcurves[0].0, PcurveRepair::Discarded));
+                prev = None;
+                out.push(None);
+            }
+        }
+    }
+    (out, repairs)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> Frame {
+        Frame::new(
+            DVec3::zeros(),
+            DVec3::new(0.0, 0.0, 1.0),
+            DVec3::new(1.0, 0.0, 0.0),
+        )
+    }
+
+    /// Samples a unit circle at height `z`, from angle `a` to `b`
+    fn arc(a: f64, b: f64, z: f64) -> Vec<DVec3> {
+        (0..=16)
+            .map(|i| {
+                let t = a + (b - a) * i as f64 / 16.0;
+                DVec3::new(t.cos(), t.sin(), z)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fit_line() {
+        let surf = Parameterization::Plane(frame());
+        let pts = [DVec3::new(1.0, 1.0, 0.0), DVec3::new(3.0, 2.0, 0.0)];
+        let c = Curve2::Line {
+            pnt: DVec2::new(1.0, 1.0),
+            dir: DVec2::new(2.0, 1.0),
+        };
+        let f = fit(&surf, &c, &pts, true).unwrap();
+        assert!(!f.reversed);
+        assert!((f.uv[1] - DVec2::new(3.0, 2.0)).norm() < 1e-9);
+
+        // Running the edge backwards means that the p-curve is reversed
+        let f = fit(&surf, &c, &[pts[1], pts[0]], true).unwrap();
+        assert!(f.reversed);
+        assert!((f.uv[0] - DVec2::new(3.0, 2.0)).norm() < 1e-9);
+
+        // A p-curve which is off to the side doesn't fit at all
+        let c = Curve2::Line {
+            pnt: DVec2::new(1.0, 1.5),
+            dir: DVec2::new(2.0, 1.0),
+        };
+        assert!(fit(&surf, &c, &pts, true).is_none());
+    }
+
+    #[test]
+    fn test_fit_cylinder() {
+        let surf = Parameterization::Cylinder(frame(), 1.0);
+
+        // A half circle, whose p-curve runs along u
+        let c = Curve2::Line {
+            pnt: DVec2::new(0.0, 0.0),
+            dir: DVec2::new(1.0, 0.0),
+        };
+        let f = fit(&surf, &c, &arc(0.0, PI, 0.0), true).unwrap();
+        assert!(!f.reversed);
+        assert!((f.uv[16] - DVec2::new(PI, 0.0)).norm() < 1e-6, "{:?}", f.uv);
+
+        // If the edge goes the other way around, then so does the p-curve
+        let f = fit(&surf, &c, &arc(0.0, -PI, 0.0), true).unwrap();
+        assert!(f.reversed);
+        assert!(
+            (f.uv[8] - DVec2::new(-PI / 2.0, 0.0)).norm() < 1e-6,
+            "{:?}",
+            f.uv
+        );
+
+        // A full circle, as a polyline through (a few more than) four points
+        let c = Curve2::Polyline(
+            (0..=8)
+                .map(|i| DVec2::new(i as f64 * PI / 4.0, 1.0))
+                .collect(),
+        );
+        let mut pts = arc(0.0, 2.0 * PI, 1.0);
+        let f = fit(&surf, &c, &pts, true).unwrap();
+        assert!(!f.reversed);
+        pts.reverse();
+        assert!(fit(&surf, &c, &pts, true).unwrap().reversed);
+    }
+
+    #[test]
+    fn test_check_loop() {
+        // Half of a cylinder's side, going around the bottom, up, back
+        // around the top, and down again
+        let surf = Parameterization::Cylinder(frame(), 1.0);
+        let line = |u: f64, v: f64, du: f64, dv: f64| Curve2::Line {
+            pnt: DVec2::new(u, v),
+            dir: DVec2::new(du, dv),
+        };
+        let bottom = arc(0.0, PI, 0.0);
+        let up = [DVec3::new(-1.0, 0.0, 0.0), DVec3::new(-1.0, 0.0, 1.0)];
+        let top = arc(PI, 0.0, 1.0);
+        let down = [DVec3::new(1.0, 0.0, 1.0), DVec3::new(1.0, 0.0, 0.0)];
+        let edges = |top_pcurve: Curve2| {
+            vec![
+                LoopEdge {
+                    pcurves: vec![(1, line(0.0, 0.0, 1.0, 0.0))],
+                    pts: &bottom,
+                    along: true,
+                },
+                LoopEdge {
+                    pcurves: vec![(2, line(PI, 0.0, 0.0, 1.0))],
+                    pts: &up,
+                    along: true,
+                },
+                LoopEdge {
+                    pcurves: vec![(3, top_pcurve)],
+                    pts: &top,
+                    along: false,
+                },
+                LoopEdge {
+                    pcurves: vec![
+                        (4, line(0.0, 0.0, 0.0, 1.0)),
+                        (5, line(2.0 * PI, 0.0, 0.0, 1.0)),
+                    ],
+                    pts: &down,
+                    along: false,
+                },
+            ]
+        };
+
+        let (uv, repairs) = check_loop(&surf, &edges(line(0.0, 1.0, 1.0, 0.0)));
+        assert!(repairs.is_empty(), "{:?}", repairs);
+        let uv: Vec<_> = uv.into_iter().map(Option::unwrap).collect();
+        assert!((uv[2][0] - DVec2::new(PI, 1.0)).norm() < 1e-6);
+        assert!((uv[3][1] - DVec2::new(0.0, 0.0)).norm() < 1e-6);
+
+        // The top shifted by a period, and running the wrong way
+        let (uv, repairs) = check_loop(&surf, &edges(line(4.0 * PI, 1.0, -1.0, 0.0)));
+        assert_eq!(
+            repairs,
+            [(3, PcurveRepair::Reversed), (3, PcurveRepair::Shifted)]
+        );
+        let top = uv[2].as_ref().unwrap();
+        assert!((top[0] - DVec2::new(PI, 1.0)).norm() < 1e-6, "{:?}", top);
+        assert!((top[16] - DVec2::new(0.0, 1.0)).norm() < 1e-6, "{:?}", top);
+
+        // A p-curve on the wrong part of the surface is discarded
+        let (uv, repairs) = check_loop(&surf, &edges(line(0.0, 2.0, 1.0, 0.0)));
+        assert_eq!(repairs, [(3, PcurveRepair::Discarded)]);
+        assert!(uv[2].is_none());
+    }
+}
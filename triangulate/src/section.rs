@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use nalgebra_glm::{DVec2, DVec3};
+
+use crate::mesh::Mesh;
+
+/// A plane, defined by a point on it and its normal
+#[derive(Copy, Clone, Debug)]
+pub struct Plane {
+    pub origin: DVec3,
+    /// Unit normal, pointing towards the side which is cut away
+    pub normal: DVec3,
+}
+
+impl Plane {
+    pub fn new(origin: DVec3, normal: DVec3) -> Self {
+        Plane {
+            origin,
+            normal: normal.normalize(),
+        }
+    }
+
+    /// Returns the signed distance from the plane to a point
+    pub fn distance(&self, p: DVec3) -> f64 {
+        (p - self.origin).dot(&self.normal)
+    }
+
+    /// Returns two unit vectors which span the plane, such that
+    /// `u × v = normal`
+    fn basis(&self) -> (DVec3, DVec3) {
+        let n = self.normal;
+        let a = if n.x.abs() < 0.9 {
+            DVec3::x()
+        } else {
+            DVec3::y()
+        };
+        let u = a.cross(&n).normalize();
+        (u, n.cross(&u))
+    }
+}
+
+/// Intersects a range of triangles (e.g. one part of the mesh) with a
+/// plane, returning the closed loops where the plane cuts the surface.
+///
+/// Vertices exactly on the plane are treated as lying on its positive side,
+/// so that each crossing is counted once.  Loops follow the winding of the
+/// mesh: for a closed solid with outward-facing triangles, outer boundaries
+/// run counterclockwise when seen from the plane's positive side and holes
+/// run clockwise.  Chains which don't close up (from meshes with gaps) are
+/// dropped.
+pub fn section_loops(mesh: &Mesh, triangles: Range<usize>, plane: &Plane) -> Vec<Vec<DVec3>> {
+    let tris = &mesh.triangles[triangles];
+    let pos = |i: u32| mesh.verts[i as usize].pos;
+
+    // Points are welded on a grid which is much finer than the part,
+    // since faces which share an edge don't share vertex indices
+    let (mut lo, mut hi) = (DVec3::repeat(f64::INFINITY), DVec3::repeat(-f64::INFINITY));
+    for t in tris {
+        for i in t.verts.iter() {
+            lo = lo.inf(&pos(*i));
+            hi = hi.sup(&pos(*i));
+        }
+    }
+    if tris.is_empty() {
+        return vec![];
+    }
+    let tol = ((hi - lo).norm() * 1e-9).max(f64::MIN_POSITIVE);
+    let key = |p: DVec3| {
+        let q = p / tol;
+        (q.x.round() as i64, q.y.round() as i64, q.z.round() as i64)
+    };
+
+    // Build one segment per triangle which crosses the plane, running
+    // from the edge where it leaves the positive side (in winding order)
+    // to the edge where it enters
+    let mut segments: Vec<(DVec3, DVec3)> = vec![];
+    for t in tris {
+        let v = [0, 1, 2].map(|j| pos(t.verts[j]));
+        let d = v.map(|p| plane.distance(p));
+        let above = d.map(|d| d >= 0.0);
+        if above.iter().all(|a| *a) || above.iter().all(|a| !*a) {
+            continue;
+        }
+        let mut enter = None;
+        let mut exit = None;
+        for j in 0..3 {
+            let k = (j + 1) % 3;
+            if above[j] == above[k] {
+                continue;
+            }
+            // Always interpolate from the vertex below the plane, so that
+            // neighboring triangles compute exactly the same point
+            let (a, b) = if above[j] { (k, j) } else { (j, k) };
+            let p = v[a] + (v[b] - v[a]) * (d[a] / (d[a] - d[b]));
+            if above[k] {
+                enter = Some(p);
+            } else {
+                exit = Some(p);
+            }
+        }
+        if let (Some(b), Some(a)) = (enter, exit) {
+            if key(a) != key(b) {
+                segments.push((a, b));
+            }
+        }
+    }
+
+    // Chain segments into loops
+    let mut by_start: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, s) in segments.iter().enumerate() {
+        by_start.entry(key(s.0)).or_default().push(i);
+    }
+    let mut used = vec![false; segments.len()];
+    let mut out = vec![];
+    for first in 0..segments.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let start = key(segments[first].0);
+        let mut pts = vec![segments[first].0];
+        let mut end = segments[first].1;
+        let closed = loop {
+            if key(end) == start {
+                break true;
+            }
+            pts.push(end);
+            let next = by_start
+                .get(&key(end))
+                .and_then(|c| c.iter().copied().find(|i| !used[*i]));
+            match next {
+                Some(i) => {
+                    used[i] = true;
+                    end = segments[i].1;
+                }
+                None => break false,
+            }
+        };
+        if closed && pts.len() >= 3 {
+            out.push(pts);
+        }
+    }
+    out
+}
+
+/// Triangulates the region enclosed by section loops, returning triangles
+/// in the plane which face along its normal.  Loops nested inside other
+/// loops are treated as holes.
+pub fn cap_triangles(loops: &[Vec<DVec3>], plane: &Plane) -> Result<Vec<[DVec3; 3]>, cdt::Error> {
+    let (u, v) = plane.basis();
+    let mut pts = vec![];
+    let mut contours = vec![];
+    for l in loops {
+        let start = pts.len();
+        pts.extend(l.iter().map(|p| {
+            let d = p - plane.origin;
+            (d.dot(&u), d.dot(&v))
+        }));
+        let mut c: Vec<usize> = (start..pts.len()).collect();
+        c.push(start);
+        contours.push(c);
+    }
+    if pts.is_empty() {
+        return Ok(vec![]);
+    }
+    let tris = cdt::triangulate_contours(&pts, &contours)?;
+
+    let lift = |i: usize| plane.origin + u * pts[i].0 + v * pts[i].1;
+    Ok(tris
+        .into_iter()
+        .map(|(a, b, c)| {
+            let p = [a, b, c].map(|i| DVec2::new(pts[i].0, pts[i].1));
+            let e1 = p[1] - p[0];
+            let e2 = p[2] - p[0];
+            if e1.x * e2.y - e1.y * e2.x >= 0.0 {
+                [lift(a), lift(b), lift(c)]
+            } else {
+                [lift(a), lift(c), lift(b)]
+            }
+        })
+        .collect())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{Triangle, Vertex};
+    use nalgebra_glm::U32Vec3;
+
+    /// Appends an axis-aligned box to the mesh, with separate vertices for
+    /// each face (as the triangulator produces).  If `inward`, the faces
+    /// point into the box, as for a void.
+    fn add_box(mesh: &mut Mesh, lo: DVec3, hi: DVec3, inward: bool) {
+        let corner = |i: usize| {
+            DVec3::new(
+                if i & 1 == 0 { lo.x } else { hi.x },
+                if i & 2 == 0 { lo.y } else { hi.y },
+                if i & 4 == 0 { lo.z } else { hi.z },
+            )
+        };
+        // Corners of each face, counterclockwise when seen from outside
+        let faces = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        for f in faces {
+            let v0 = mesh.verts.len() as u32;
+            for i in f {
+                mesh.verts.push(Vertex {
+                    pos: corner(i),
+                    norm: DVec3::zeros(),
+                    color: DVec3::zeros(),
+                });
+            }
+            for t in [[0, 1, 2], [0, 2, 3]] {
+                let t = if inward { [t[0], t[2], t[1]] } else { t };
+                mesh.triangles.push(Triangle {
+                    verts: U32Vec3::new(t[0], t[1], t[2]).add_scalar(v0),
+                });
+            }
+        }
+    }
+
+    fn area(tris: &[[DVec3; 3]], normal: DVec3) -> f64 {
+        tris.iter()
+            .map(|t| (t[1] - t[0]).cross(&(t[2] - t[0])).dot(&normal) / 2.0)
+            .sum()
+    }
+
+    /// Signed area of a loop, as seen from the positive side of the plane
+    fn loop_area(l: &[DVec3], normal: DVec3) -> f64 {
+        (0..l.len())
+            .map(|i| l[i].cross(&l[(i + 1) % l.len()]).dot(&normal) / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_cube_at_mid_height() {
+        let mut mesh = Mesh::default();
+        add_box(&mut mesh, DVec3::zeros(), DVec3::repeat(1.0), false);
+        let plane = Plane::new(DVec3::new(0.0, 0.0, 0.5), DVec3::z());
+        let loops = section_loops(&mesh, 0..mesh.triangles.len(), &plane);
+        assert_eq!(loops.len(), 1);
+
+        // Each side face is split by its diagonal, adding a point in the
+        // middle of each side
+        assert_eq!(loops[0].len(), 8);
+        for p in &loops[0] {
+            assert!((p.z - 0.5).abs() < 1e-12);
+            assert!(
+                p.x.abs() < 1e-12
+                    || (p.x - 1.0).abs() < 1e-12
+                    || p.y.abs() < 1e-12
+                    || (p.y - 1.0).abs() < 1e-12
+            );
+        }
+        assert!((loop_area(&loops[0], DVec3::z()) - 1.0).abs() < 1e-12);
+
+        let cap = cap_triangles(&loops, &plane).unwrap();
+        assert!(!cap.is_empty());
+        assert!((area(&cap, DVec3::z()) - 1.0).abs() < 1e-12);
+        for t in &cap {
+            // Every triangle faces along the plane's normal
+            assert!((t[1] - t[0]).cross(&(t[2] - t[0])).z > 0.0);
+            assert!(t.iter().all(|p| (p.z - 0.5).abs() < 1e-12));
+        }
+
+        // Flipping the plane flips the cap
+        let flipped = Plane::new(DVec3::new(0.0, 0.0, 0.5), -DVec3::z());
+        let loops = section_loops(&mesh, 0..mesh.triangles.len(), &flipped);
+        let cap = cap_triangles(&loops, &flipped).unwrap();
+        assert!((area(&cap, -DVec3::z()) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_hollow_box() {
+        let mut mesh = Mesh::default();
+        add_box(&mut mesh, DVec3::zeros(), DVec3::repeat(1.0), false);
+        let v = DVec3::repeat(0.25);
+        add_box(&mut mesh, v, v * 3.0, true);
+        let plane = Plane::new(DVec3::repeat(0.5), DVec3::new(0.0, 0.0, 1.0));
+        let loops = section_loops(&mesh, 0..mesh.triangles.len(), &plane);
+        assert_eq!(loops.len(), 2);
+        let mut areas: Vec<f64> = loops.iter().map(|l| loop_area(l, DVec3::z())).collect();
+        areas.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((areas[0] + 0.25).abs() < 1e-12);
+        assert!((areas[1] - 1.0).abs() < 1e-12);
+
+        let cap = cap_triangles(&loops, &plane).unwrap();
+        assert!((area(&cap, DVec3::z()) - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_tilted_plane() {
+        let mut mesh = Mesh::default();
+        add_box(&mut mesh, DVec3::zeros(), DVec3::repeat(1.0), false);
+        let n = DVec3::new(1.0, 0.0, 1.0);
+        let plane = Plane::new(DVec3::repeat(0.5), n);
+        let loops = section_loops(&mesh, 0..mesh.triangles.len(), &plane);
+        assert_eq!(loops.len(), 1);
+        let cap = cap_triangles(&loops, &plane).unwrap();
+        // The cut is a rectangle, 1 by sqrt(2)
+        assert!((area(&cap, plane.normal) - 2f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_no_section() {
+        let mut mesh = Mesh::default();
+        add_box(&mut mesh, DVec3::zeros(), DVec3::repeat(1.0), false);
+        let plane = Plane::new(DVec3::new(0.0, 0.0, 2.0), DVec3::z());
+        assert!(section_loops(&mesh, 0..mesh.triangles.len(), &plane).is_empty());
+        assert!(section_loops(&mesh, 0..0, &plane).is_empty());
+        assert!(cap_triangles(&[], &plane).unwrap().is_empty());
+
+        // Removing a face leaves an open chain, which is dropped
+        let plane = Plane::new(DVec3::repeat(0.5), DVec3::z());
+        let mut open = Mesh::default();
+        add_box(&mut open, DVec3::zeros(), DVec3::repeat(1.0), false);
+        open.triangles.drain(4..6);
+        assert!(section_loops(&open, 0..open.triangles.len(), &plane).is_empty());
+    }
+
+    #[test]
+    fn test_plane_through_vertices() {
+        // Cutting exactly through the bottom face doesn't produce a loop,
+        // since vertices on the plane count as being above it
+        let mut mesh = Mesh::default();
+        add_box(&mut mesh, DVec3::zeros(), DVec3::repeat(1.0), false);
+        let plane = Plane::new(DVec3::zeros(), DVec3::z());
+        assert!(section_loops(&mesh, 0..mesh.triangles.len(), &plane).is_empty());
+
+        // Cutting through the top face produces the full square
+        let plane = Plane::new(DVec3::new(0.0, 0.0, 1.0), DVec3::z());
+        let loops = section_loops(&mesh, 0..mesh.triangles.len(), &plane);
+        assert_eq!(loops.len(), 1);
+        assert!((loop_area(&loops[0], DVec3::z()) - 1.0).abs() < 1e-12);
+    }
+}
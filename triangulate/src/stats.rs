@@ -1,9 +1,58 @@
+use std::collections::HashMap;
+
+use crate::{pcurve::PcurveRepair, surface::SurfaceType};
+
+/// A problem found while triangulating, which was logged and may be worth
+/// showing to the user (e.g. an unsupported entity or a face which failed
+/// to triangulate)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub level: log::Level,
+    /// Index of the STEP entity involved, if there is a single one
+    pub entity: Option<usize>,
+    pub message: String,
+}
+
 #[derive(Default)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stats {
     pub num_shells: usize,
+    /// Number of shells which were triangulated from an `OPEN_SHELL`
+    pub num_open_shells: usize,
     pub num_faces: usize,
     pub num_errors: usize,
     pub num_panics: usize,
+    /// Number of faces whose surface is unsupported, which were triangulated
+    /// on the best-fit plane of their boundary instead
+    pub num_fallbacks: usize,
+    /// Number of curves from geometric sets which were sampled into wires
+    pub num_wires: usize,
+    /// Number of faces which were skipped because they're outside of the
+    /// crop box (see [`crate::crop`])
+    pub num_cropped: usize,
+
+    /// Surface type of each face, keyed by the face's STEP entity index
+    /// (matching [`Face::id`](crate::mesh::Face::id)).  Faces whose surface
+    /// could not be converted are missing from this table.
+    pub surface_types: HashMap<usize, SurfaceType>,
+
+    /// Repairs applied to p-curves which disagreed with their edges, keyed
+    /// by the `PCURVE` entity's index (see [`crate::pcurve`]).  A p-curve may
+    /// be both reversed and shifted.
+    pub pcurve_repairs: Vec<(usize, PcurveRepair)>,
+
+    /// Warnings and errors from triangulation, in the order they were
+    /// logged (within each solid; solids may be triangulated in parallel)
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// The first error which stopped a face from being triangulated (other
+    /// than errors from the 2D triangulator, which are only counted in
+    /// `num_errors`).  [`load_mesh`](crate::load::load_mesh) returns it if
+    /// no triangles or wires were produced.  This isn't cached, since such
+    /// a mesh never is.
+    #[cfg_attr(feature = "cache", serde(skip))]
+    pub first_error: Option<crate::Error>,
 }
 
 impl Stats {
@@ -11,9 +60,24 @@ impl Stats {
     // (why yes, this _is_ a monoid)
     pub fn combine(mut a: Self, b: Self) -> Self {
         a.num_shells += b.num_shells;
+        a.num_open_shells += b.num_open_shells;
         a.num_faces += b.num_faces;
         a.num_errors += b.num_errors;
         a.num_panics += b.num_panics;
+        a.num_fallbacks += b.num_fallbacks;
+        a.num_wires += b.num_wires;
+        a.num_cropped += b.num_cropped;
+        a.surface_types.extend(b.surface_types);
+        a.pcurve_repairs.extend(b.pcurve_repairs);
+        a.diagnostics.extend(b.diagnostics);
+        a.first_error = a.first_error.or(b.first_error);
         a
     }
+
+    /// Checks whether the mesh may have visible back faces, either because
+    /// it includes open shells or because some faces failed to triangulate
+    /// (leaving holes in otherwise-closed shells).
+    pub fn is_open(&self) -> bool {
+        self.num_open_shells > 0 || self.num_errors > 0 || self.num_panics > 0
+    }
 }
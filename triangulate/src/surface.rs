@@ -1,9 +1,9 @@
 use std::f64::consts::PI;
 
-use glm::{DMat4, DVec2, DVec3, DVec4};
+use glm::{DMat3, DMat4, DVec2, DVec3, DVec4};
 use nalgebra_glm as glm;
 
-use crate::{mesh::Vertex, Error};
+use crate::{crop::Aabb, mesh::Vertex, Error};
 use nurbs::{AbstractSurface, NdBsplineSurface, SampledSurface};
 
 // Represents a surface in 3D space, with a function to project a 3D point
@@ -46,7 +46,35 @@ pub enum Surface {
     },
 }
 
+/// Broad classification of a [`Surface`], used for statistics and for
+/// debug visualization
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub enum SurfaceType {
+    Plane,
+    Cylinder,
+    Cone,
+    Sphere,
+    Torus,
+    /// B-spline or NURBS surface
+    Spline,
+    /// Unsupported surface, which was replaced by the best-fit plane of the
+    /// face's boundary
+    FallbackPlanar,
+}
+
 impl Surface {
+    pub fn surface_type(&self) -> SurfaceType {
+        match self {
+            Surface::Plane { .. } => SurfaceType::Plane,
+            Surface::Cylinder { .. } => SurfaceType::Cylinder,
+            Surface::Cone { .. } => SurfaceType::Cone,
+            Surface::Sphere { .. } => SurfaceType::Sphere,
+            Surface::Torus { .. } => SurfaceType::Torus,
+            Surface::Bspline(..) | Surface::Nurbs(..) => SurfaceType::Spline,
+        }
+    }
+
     pub fn new_sphere(location: DVec3, radius: f64) -> Self {
         Surface::Sphere {
             // mat and mat_i are built in prepare()
@@ -90,6 +118,56 @@ impl Surface {
         }
     }
 
+    /// Fits a plane to a closed loop of points by least squares, returning
+    /// the plane and the largest distance from any point to it, relative to
+    /// the loop's size.  The plane's normal is oriented so that the loop
+    /// winds counter-clockwise around it.
+    ///
+    /// Returns `None` if the points don't span a plane (e.g. there are fewer
+    /// than three, or they're all on a line).
+    pub fn new_best_fit_plane(pts: &[DVec3]) -> Option<(Self, f64)> {
+        if pts.len() < 3 || pts.iter().flat_map(|p| p.iter()).any(|c| !c.is_finite()) {
+            return None;
+        }
+        let center = pts.iter().sum::<DVec3>() / pts.len() as f64;
+        let cov = pts
+            .iter()
+            .map(|p| (p - center) * (p - center).transpose())
+            .sum::<DMat3>();
+
+        // The normal is the direction of least variance, and the reference
+        // direction is the direction of most variance
+        let eigen = cov.symmetric_eigen();
+        let mut order = [0, 1, 2];
+        order.sort_by(|a, b| eigen.eigenvalues[*a].total_cmp(&eigen.eigenvalues[*b]));
+        let mut normal: DVec3 = eigen.eigenvectors.column(order[0]).into();
+        let ref_direction: DVec3 = eigen.eigenvectors.column(order[2]).into();
+
+        // Newell's method gives the loop's winding, which picks the sign of
+        // the normal (and tells us whether the loop is degenerate)
+        let winding: DVec3 = pts
+            .iter()
+            .zip(pts.iter().cycle().skip(1))
+            .map(|(a, b)| (a - center).cross(&(b - center)))
+            .sum();
+        let size = pts.iter().map(|p| (p - center).norm()).fold(0.0, f64::max);
+        if winding.norm() <= 1e-12 * size * size {
+            return None;
+        }
+        if winding.dot(&normal) < 0.0 {
+            normal = -normal;
+        }
+
+        let deviation = pts
+            .iter()
+            .map(|p| (p - center).dot(&normal).abs())
+            .fold(0.0, f64::max);
+        Some((
+            Self::new_plane(normal, ref_direction, center),
+            deviation / size,
+        ))
+    }
+
     pub fn new_cone(axis: DVec3, ref_direction: DVec3, location: DVec3, angle: f64) -> Self {
         let mat = Self::make_rigid_transform(axis, ref_direction, location);
         Surface::Cone {
@@ -124,17 +202,30 @@ impl Surface {
         mat
     }
 
-    fn surface_lower<const N: usize>(p: DVec3, surf: &SampledSurface<N>) -> Result<DVec2, Error>
+    fn surface_lower<const N: usize>(
+        p: DVec3,
+        hint: Option<DVec2>,
+        surf: &SampledSurface<N>,
+    ) -> Result<DVec2, Error>
     where
         NdBsplineSurface<N>: AbstractSurface,
     {
-        surf.uv_from_point(p).ok_or(Error::CouldNotLower)
+        hint.and_then(|uv| surf.uv_from_point_newtons_method(p, uv))
+            .or_else(|| surf.uv_from_point(p))
+            .ok_or(Error::CouldNotLower)
     }
 
     /// Lowers a 3D point on a specific surface into a 2D space defined by
-    /// the surface type.  This should only be called from `lower_verts`,
+    /// the surface type.  This should only be called after `lower_verts`,
     /// to ensure that `prepare` is called first.
-    fn lower(&self, p: DVec3) -> Result<DVec2, Error> {
+    pub(crate) fn lower(&self, p: DVec3) -> Result<DVec2, Error> {
+        self.lower_near(p, None)
+    }
+
+    /// Lowers a 3D point, as in [`Surface::lower`].  On spline surfaces,
+    /// `hint` is a guess at the point's parameters (e.g. from a p-curve),
+    /// which is refined instead of searching the whole surface.
+    fn lower_near(&self, p: DVec3, hint: Option<DVec2>) -> Result<DVec2, Error> {
         let p_ = DVec4::new(p.x, p.y, p.z, 1.0);
         match self {
             Surface::Plane { mat_i, .. } => Ok(glm::vec4_to_vec2(&(mat_i * p_))),
@@ -203,8 +294,8 @@ impl Surface {
                 };
                 Ok(scale * DVec2::new(x, minor_angle.sin()))
             }
-            Surface::Bspline(surf) => Self::surface_lower(p, surf),
-            Surface::Nurbs(surf) => Self::surface_lower(p, surf),
+            Surface::Bspline(surf) => Self::surface_lower(p, hint, surf),
+            Surface::Nurbs(surf) => Self::surface_lower(p, hint, surf),
             Surface::Sphere { mat_i, radius, .. } => {
                 // mat_i is constructed in prepare to be a reasonable basis
                 let p = (mat_i * p_).xyz() / *radius;
@@ -276,11 +367,21 @@ impl Surface {
     }
 
     pub fn lower_vertices(&mut self, verts: &mut [Vertex]) -> Result<Vec<(f64, f64)>, Error> {
+        self.lower_vertices_with_hints(verts, &[])
+    }
+
+    /// Lowers vertices as in [`Surface::lower_vertices`], with an optional
+    /// guess at the parameters of each vertex (see [`Surface::lower_near`])
+    pub(crate) fn lower_vertices_with_hints(
+        &mut self,
+        verts: &mut [Vertex],
+        hints: &[Option<DVec2>],
+    ) -> Result<Vec<(f64, f64)>, Error> {
         self.prepare(verts);
         let mut pts = Vec::with_capacity(verts.len());
-        for v in verts {
+        for (i, v) in verts.iter_mut().enumerate() {
             // Project to the 2D subspace for triangulation
-            let proj = self.lower(v.pos)?;
+            let proj = self.lower_near(v.pos, hints.get(i).copied().flatten())?;
             // Update the surface normal
             v.norm = self.normal(v.pos, proj);
             pts.push((proj.x, proj.y));
@@ -394,10 +495,109 @@ impl Surface {
     where
         NdBsplineSurface<N>: AbstractSurface,
     {
-        // Calculate first order derivs, then cross them to get normal
-        let derivs = surf.surf.derivatives::<1>(uv);
-        let n = derivs[1][0].cross(&derivs[0][1]);
-        n.normalize()
+        surf.surf.normal(uv)
+    }
+
+    fn surface_curvature<const N: usize>(uv: DVec2, surf: &SampledSurface<N>) -> f64
+    where
+        NdBsplineSurface<N>: AbstractSurface,
+    {
+        // First and second fundamental forms, then Gaussian and mean
+        // curvature, then the larger of the two principal curvatures.
+        let d = surf.surf.derivatives::<2>(uv);
+        let (su, sv) = (d[1][0], d[0][1]);
+        let n = su.cross(&sv);
+        if n.norm() < f64::EPSILON {
+            return 0.0;
+        }
+        let n = n.normalize();
+        let (e, f, g) = (su.dot(&su), su.dot(&sv), sv.dot(&sv));
+        let (l, m, nn) = (d[2][0].dot(&n), d[1][1].dot(&n), d[0][2].dot(&n));
+        let det = e * g - f * f;
+        let k = (l * nn - m * m) / det;
+        let h = (e * nn - 2.0 * f * m + g * l) / (2.0 * det);
+        h.abs() + (h * h - k).max(0.0).sqrt()
+    }
+
+    /// Returns the magnitude of the largest principal curvature at a point
+    /// on the surface, using either the 3D or 2D position (as in
+    /// [`Surface::normal`]).  This is infinite at the tip of a cone.
+    pub fn curvature(&self, p: DVec3, uv: DVec2) -> f64 {
+        match self {
+            Surface::Plane { .. } => 0.0,
+            Surface::Cylinder { radius, .. } | Surface::Sphere { radius, .. } => 1.0 / radius,
+            Surface::Cone { mat_i, angle, .. } => {
+                let pos = mat_i * DVec4::new(p.x, p.y, p.z, 1.0);
+                angle.cos() / pos.xy().norm()
+            }
+            Surface::Torus {
+                mat_i,
+                major_radius,
+                minor_radius,
+                ..
+            } => {
+                // The first principal curvature is around the minor circle,
+                // and the second depends on the distance from the axis.
+                let p = (*mat_i * DVec4::new(p.x, p.y, p.z, 1.0)).xyz();
+                let d = p.yz().norm();
+                let k2 = (d - major_radius) / (minor_radius * d);
+                (1.0 / minor_radius).max(k2.abs())
+            }
+            Surface::Bspline(surf) => Self::surface_curvature(uv, surf),
+            Surface::Nurbs(surf) => Self::surface_curvature(uv, surf),
+        }
+    }
+
+    /// Calculates curvature for a set of vertices, given the points returned
+    /// by [`Surface::lower_vertices`] and [`Surface::add_steiner_points`]
+    pub fn vertex_curvatures(&self, verts: &[Vertex], pts: &[(f64, f64)]) -> Vec<f32> {
+        // Undo the aspect ratio scaling from `lower_vertices`
+        let aspect_ratio = match self {
+            Surface::Nurbs(surf) => surf.surf.aspect_ratio(),
+            Surface::Bspline(surf) => surf.surf.aspect_ratio(),
+            _ => 1.0,
+        };
+        verts
+            .iter()
+            .zip(pts)
+            .map(|(v, (x, y))| self.curvature(v.pos, DVec2::new(*x, *y / aspect_ratio)) as f32)
+            .collect()
+    }
+
+    /// Returns a conservative bounding box for a face on this surface, given
+    /// the points on the face's boundary, without triangulating it.
+    ///
+    /// Planar faces lie within their boundary's bounds.  Faces on
+    /// cylinders and cones may bulge out past their boundary, so the box is
+    /// padded; spheres and tori use the bounds of the whole surface, and
+    /// splines use the bounds of their control points.
+    pub fn face_bounds(&self, boundary: &[Vertex]) -> Option<Aabb> {
+        let b = Aabb::from_points(boundary.iter().map(|v| v.pos))?;
+        Some(match self {
+            Surface::Plane { .. } => b,
+            Surface::Cylinder { radius, .. } => b.padded(*radius),
+            Surface::Cone { .. } => b.padded((b.max - b.min).max()),
+            Surface::Sphere {
+                location, radius, ..
+            } => Aabb::new(location.add_scalar(-radius), location.add_scalar(*radius)),
+            Surface::Torus {
+                location,
+                major_radius,
+                minor_radius,
+                ..
+            } => {
+                let r = major_radius + minor_radius;
+                Aabb::new(location.add_scalar(-r), location.add_scalar(r))
+            }
+            Surface::Bspline(surf) => {
+                let (lo, hi) = surf.surf.control_bounds();
+                b.union(&Aabb::new(lo, hi))
+            }
+            Surface::Nurbs(surf) => {
+                let (lo, hi) = surf.surf.control_bounds();
+                b.union(&Aabb::new(lo, hi))
+            }
+        })
     }
 
     // Calculate the surface normal, using either the 3D or 2D position
@@ -447,3 +647,115 @@ impl Surface {
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitive_curvature() {
+        let uv = DVec2::zeros();
+        let plane = Surface::new_plane(DVec3::z(), DVec3::x(), DVec3::zeros());
+        assert_eq!(plane.curvature(DVec3::new(1.0, 2.0, 0.0), uv), 0.0);
+
+        let cyl = Surface::new_cylinder(DVec3::z(), DVec3::x(), DVec3::zeros(), 4.0);
+        assert_eq!(cyl.curvature(DVec3::new(4.0, 0.0, 1.0), uv), 0.25);
+
+        let sphere = Surface::new_sphere(DVec3::zeros(), 2.0);
+        assert_eq!(sphere.curvature(DVec3::new(2.0, 0.0, 0.0), uv), 0.5);
+
+        // A 45° cone has curvature cos(45°) / r at radius r from the axis
+        let cone = Surface::new_cone(DVec3::z(), DVec3::x(), DVec3::zeros(), PI / 4.0);
+        let k = cone.curvature(DVec3::new(2.0, 0.0, 2.0), uv);
+        assert!((k - (PI / 4.0).cos() / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_torus_curvature() {
+        // Torus around the local X axis (see `Surface::lower`), with the
+        // identity matrices that we'd get before `prepare` is called
+        let t = Surface::new_torus(DVec3::zeros(), DVec3::x(), 3.0, 1.0);
+        let uv = DVec2::zeros();
+        // On the outside, curvature is dominated by the minor radius
+        let k = t.curvature(DVec3::new(0.0, 0.0, 4.0), uv);
+        assert!((k - 1.0).abs() < 1e-9);
+
+        // On the inside of a fat torus, the other curvature dominates
+        let t = Surface::new_torus(DVec3::zeros(), DVec3::x(), 1.5, 1.0);
+        let k = t.curvature(DVec3::new(0.0, 0.0, 0.5), uv);
+        assert!((k - 2.0).abs() < 1e-9, "{}", k);
+    }
+
+    #[test]
+    fn test_spline_curvature() {
+        use nurbs::{BsplineSurface, KnotVector};
+        // A parabolic cylinder z = x^2, as a quadratic-by-linear patch
+        let u_knots = KnotVector::from_multiplicities(2, &[0.0, 1.0], &[3, 3]).unwrap();
+        let v_knots = KnotVector::from_multiplicities(1, &[0.0, 1.0], &[2, 2]).unwrap();
+        let pts = [(-1.0, 1.0), (0.0, -1.0), (1.0, 1.0)]
+            .iter()
+            .map(|(x, z)| vec![DVec3::new(*x, 0.0, *z), DVec3::new(*x, 1.0, *z)])
+            .collect();
+        let surf = BsplineSurface::new(false, false, u_knots, v_knots, pts).unwrap();
+        let surf = Surface::Bspline(SampledSurface::new(surf));
+
+        // At the bottom of the parabola, the curvature is 2
+        let k = surf.curvature(DVec3::zeros(), DVec2::new(0.5, 0.5));
+        assert!((k - 2.0).abs() < 1e-9, "{}", k);
+        // Further up, it flattens out: k = 2 / (1 + 4x^2)^(3/2) at x = 1
+        let k = surf.curvature(DVec3::zeros(), DVec2::new(1.0, 0.5));
+        assert!((k - 2.0 / 5f64.powf(1.5)).abs() < 1e-9, "{}", k);
+
+        // The face's bounds include the control points, which dip below the
+        // surface (and its boundary, along the top edge)
+        let top: Vec<_> = [(-1.0, 0.0), (1.0, 0.0), (1.0, 1.0), (-1.0, 1.0)]
+            .iter()
+            .map(|(x, y)| Vertex {
+                pos: DVec3::new(*x, *y, 1.0),
+                norm: DVec3::zeros(),
+                color: DVec3::zeros(),
+            })
+            .collect();
+        let b = surf.face_bounds(&top).unwrap();
+        assert_eq!(b.min, DVec3::new(-1.0, 0.0, -1.0));
+        assert_eq!(b.max, DVec3::new(1.0, 1.0, 1.0));
+        assert!(surf.face_bounds(&[]).is_none());
+    }
+
+    #[test]
+    fn test_best_fit_plane() {
+        // A square in a tilted plane, wound counter-clockwise around +Z
+        let n = DVec3::new(0.0, -1.0, 1.0).normalize();
+        let (a, b) = (DVec3::x(), n.cross(&DVec3::x()));
+        let center = DVec3::new(1.0, 2.0, 3.0);
+        let square: Vec<DVec3> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .iter()
+            .map(|(x, y)| center + a * *x + b * *y)
+            .collect();
+        let (plane, deviation) = Surface::new_best_fit_plane(&square).unwrap();
+        assert!(deviation < 1e-12);
+        let normal = plane.normal(center, DVec2::zeros());
+        assert!((normal - n).norm() < 1e-12, "{:?}", normal);
+
+        // Reversing the loop flips the normal
+        let mut reversed = square.clone();
+        reversed.reverse();
+        let (plane, _) = Surface::new_best_fit_plane(&reversed).unwrap();
+        assert!((plane.normal(center, DVec2::zeros()) + n).norm() < 1e-12);
+
+        // Lifting opposite corners gives a warped loop, whose corners are 0.5
+        // from the plane and 1.5 from the center
+        let mut warped = square.clone();
+        warped[0] += n;
+        warped[2] += n;
+        let (_, deviation) = Surface::new_best_fit_plane(&warped).unwrap();
+        assert!((deviation - 0.5 / 1.5).abs() < 1e-9, "{}", deviation);
+
+        // Degenerate loops don't have a plane
+        assert!(Surface::new_best_fit_plane(&square[..2]).is_none());
+        let line: Vec<DVec3> = (0..4).map(|i| DVec3::x() * i as f64).collect();
+        assert!(Surface::new_best_fit_plane(&line).is_none());
+    }
+}
@@ -1,30 +1,148 @@
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use glm::{DMat4, DVec3, DVec4, U32Vec3};
-use log::{error, info, warn};
+use glm::{DMat4, DVec2, DVec3, DVec4, TVec, U32Vec3};
+use log::{debug, info, warn, Level};
 use nalgebra_glm as glm;
 
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 
 use crate::{
+    crop::Aabb,
     curve::Curve,
+    grid::{self, MeshTopology},
+    load::TriangulateOptions,
     mesh,
-    mesh::{Mesh, Triangle},
-    stats::Stats,
-    surface::Surface,
+    mesh::{Face, Mesh, Part, Triangle, Wire},
+    pcurve::{self, Curve2, Frame, LoopEdge, Parameterization, PcurveRepair},
+    stats::{Diagnostic, Stats},
+    surface::{Surface, SurfaceType},
     Error,
 };
-use nurbs::{BsplineSurface, KnotVector, NurbsSurface, SampledCurve, SampledSurface};
+use nurbs::{
+    BsplineSurface, KnotVector, NdBsplineCurve, NurbsError, NurbsSurface, SampledCurve,
+    SampledSurface,
+};
 use step::{
     ap214,
     ap214::Entity,
     ap214::*,
     id::Id,
     step_file::{FromEntity, StepFile},
+    walk::GraphWalker,
 };
 
+/// Logs a message and records it in a [`Stats`], so that callers can show it
+/// to the user without scraping the log
+macro_rules! diagnose {
+    ($stats:expr, $level:expr, $entity:expr, $($arg:tt)+) => {{
+        let message = format!($($arg)+);
+        log::log!($level, "{}", message);
+        $stats.diagnostics.push(Diagnostic {
+            level: $level,
+            entity: $entity,
+            message,
+        });
+    }};
+}
+
+/// Looks up an entity, returning an error (rather than panicking) if it's
+/// missing or has the wrong type
+fn get<'a, T: FromEntity<'a>>(
+    s: &'a StepFile,
+    id: Id<T>,
+    expected: &'static str,
+) -> Result<&'a T, Error> {
+    s.entity(id)
+        .ok_or(Error::InvalidEntity { id: id.0, expected })
+}
+
+/// Converts a STEP degree and knot list into a [`KnotVector`]
+fn knot_vector(
+    id: usize,
+    degree: i64,
+    knots: &[ParameterValue],
+    multiplicities: &[i64],
+) -> Result<KnotVector, Error> {
+    let knots: Vec<f64> = knots.iter().map(|k| k.0).collect();
+    let multiplicities = multiplicities
+        .iter()
+        .map(|&k| k.try_into())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| Error::Negative {
+            id,
+            what: "multiplicity",
+        })?;
+    let degree = degree
+        .try_into()
+        .map_err(|_| Error::Negative { id, what: "degree" })?;
+    KnotVector::from_multiplicities(degree, &knots, &multiplicities)
+        .map_err(|source| Error::Nurbs { id, source })
+}
+
+/// Builds a b-spline or NURBS curve from a `B_SPLINE_CURVE_WITH_KNOTS`.
+///
+/// The total multiplicity must match the number of control points.  Closed
+/// curves are treated as periodic, so that edges can wrap across the seam;
+/// open curves are clamped, so that they start and end on their first and
+/// last control points.
+fn bspline_curve<const D: usize>(
+    id: usize,
+    c: &BSplineCurveWithKnots_,
+    control_points: Vec<TVec<f64, D>>,
+    stats: &mut Stats,
+) -> Result<NdBsplineCurve<D>, Error> {
+    let knots = knot_vector(id, c.degree, &c.knots, &c.knot_multiplicities)?;
+    if knots.len() != control_points.len() + knots.degree() + 1 {
+        return Err(Error::Nurbs {
+            id,
+            source: NurbsError::KnotCount {
+                degree: knots.degree(),
+                control_points: control_points.len(),
+                knots: knots.len(),
+            },
+        });
+    }
+    if c.self_intersect.0 == Some(true) && !stats.diagnostics.iter().any(|d| d.entity == Some(id)) {
+        diagnose!(
+            stats,
+            Level::Warn,
+            Some(id),
+            "Curve #{} is marked as self-intersecting",
+            id
+        );
+    }
+
+    let closed = c.closed_curve.0 == Some(true);
+    let curve = NdBsplineCurve::new(!closed, knots, control_points)
+        .map_err(|source| Error::Nurbs { id, source })?;
+    Ok(if closed { curve } else { curve.clamped() })
+}
+
+/// Largest distance from a face's outer boundary to its best-fit plane,
+/// relative to the size of the boundary, for which a face on an unsupported
+/// surface is triangulated on that plane instead
+const MAX_FALLBACK_DEVIATION: f64 = 0.05;
+
+/// Largest distance between two points which are treated as the same when
+/// joining up the segments of composite curves
+const JOIN_TOLERANCE: f64 = 1e-6;
+
+/// Points closer than this (relative to the size of a face's boundary in
+/// parameter space) are merged before triangulating the face
+const MERGE_EPSILON: f64 = 1e-9;
+
+/// Largest relative difference between a face's triangulated area and the
+/// area inside its boundary (in parameter space) before it's reported
+const COVERAGE_TOLERANCE: f64 = 1e-6;
+
+/// Deepest nesting of curves within curves (e.g. trimmed curves of surface
+/// curves) which is followed.  Real files only nest a few levels deep.
+const MAX_CURVE_DEPTH: usize = 64;
+
 const SAVE_DEBUG_SVGS: bool = false;
 const SAVE_PANIC_SVGS: bool = false;
 
@@ -68,6 +186,61 @@ fn transform_stack_roots<'a>(transform_stack: &TransformStack<'a>) -> Vec<Repres
 }
 
 pub fn triangulate(s: &StepFile) -> (Mesh, Stats) {
+    triangulate_with_progress(s, &|_, _| ())
+}
+
+/// Triangulates a STEP file, calling `progress(done, total)` as each solid
+/// is finished.  With the `rayon` feature, `progress` may be called from
+/// several threads at once.
+pub fn triangulate_with_progress(
+    s: &StepFile,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> (Mesh, Stats) {
+    triangulate_with_tolerance(s, None, progress)
+}
+
+/// Triangulates a STEP file, as in [`triangulate_with_progress`].
+///
+/// If `tolerance` is given, circular and elliptical edges are sampled so
+/// that they never stray more than `tolerance` (in model units) from the
+/// true curve.
+pub fn triangulate_with_tolerance(
+    s: &StepFile,
+    tolerance: Option<f64>,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> (Mesh, Stats) {
+    triangulate_streaming(
+        s,
+        tolerance,
+        None,
+        MeshTopology::Triangles,
+        progress,
+        &|_| (),
+    )
+}
+
+/// Triangulates a STEP file, as in [`triangulate_with_tolerance`], calling
+/// `on_solid` with each solid's triangles (with every transform applied) as
+/// soon as it's finished.
+///
+/// If `crop` is given, faces whose bounds (see [`Surface::face_bounds`])
+/// don't touch the box in any of their solid's placements are skipped, and
+/// solids with no faces left are dropped entirely.
+///
+/// `topology` picks whether to also pair triangles into quads, or to sample
+/// each face on a grid (see [`MeshTopology`]).
+///
+/// Solids arrive in no particular order (and, with the `rayon` feature,
+/// from several threads at once), but combining everything passed to
+/// `on_solid` gives the same triangles as the returned mesh.
+pub fn triangulate_streaming(
+    s: &StepFile,
+    tolerance: Option<f64>,
+    crop: Option<&Aabb>,
+    topology: MeshTopology,
+    progress: &(dyn Fn(usize, usize) + Sync),
+    on_solid: &(dyn Fn(&Mesh) + Sync),
+) -> (Mesh, Stats) {
     let styled_items: Vec<_> =
         s.0.iter()
             .filter_map(MechanicalDesignGeometricPresentationRepresentation_::try_from_entity)
@@ -97,9 +270,15 @@ pub fn triangulate(s: &StepFile) -> (Mesh, Stats) {
         transform_stack = build_transform_stack(s, true);
         roots = transform_stack_roots(&transform_stack);
     }
-    let mut todo: Vec<_> = roots.into_iter().map(|v| (v, DMat4::identity())).collect();
-    if todo.len() > 1 {
-        warn!("Transformation stack has more than one root!");
+    // Diagnostics from before we start triangulating individual solids
+    let mut setup = Stats::default();
+    if roots.len() > 1 {
+        diagnose!(
+            setup,
+            Level::Warn,
+            None,
+            "Transformation stack has more than one root!"
+        );
     }
 
     // Store a map of ShapeRepresentationRelationships, which some models
@@ -113,22 +292,44 @@ pub fn triangulate(s: &StepFile) -> (Mesh, Stats) {
         shape_rep_relationship.entry(r1).or_default().push(r2);
     }
 
+    // Walk down from the roots, binding each representation at the bottom of
+    // the tree to every transform that reaches it.  Children are walked
+    // last-first, which keeps instances in the order they'd be popped off a
+    // stack.
+    let children = |id: usize, mat: &DMat4| {
+        let id = Id::new(id);
+        let mut out: Vec<(usize, DMat4)> = shape_rep_relationship
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|c| (c.0, *mat))
+            .chain(
+                transform_stack
+                    .get(&id)
+                    .into_iter()
+                    .flatten()
+                    .map(|(c, next_mat)| (c.0, mat * next_mat)),
+            )
+            .collect();
+        out.reverse();
+        out
+    };
     let mut to_mesh: HashMap<Id<_>, Vec<_>> = HashMap::new();
-    while let Some((id, mat)) = todo.pop() {
-        for child in shape_rep_relationship.get(&id).unwrap_or(&vec![]) {
-            todo.push((*child, mat));
-        }
-        if let Some(children) = transform_stack.get(&id) {
-            for (child, next_mat) in children {
-                todo.push((*child, mat * next_mat));
+    let r = GraphWalker::default().walk_paths(
+        roots.iter().rev().map(|id| (id.0, DMat4::identity())),
+        children,
+        |id, mat| {
+            if transform_stack.contains_key(&Id::new(id)) {
+                return;
             }
-        } else {
-            // Bind this transform to the RepresentationItem, which is
-            // either a ManifoldSolidBrep or a ShellBasedSurfaceModel
-            let items = match &s[id] {
+            // Bind this transform to the RepresentationItem, which is a
+            // ManifoldSolidBrep, a ShellBasedSurfaceModel, or a set of curves
+            let items = match &s.0[id] {
                 Entity::AdvancedBrepShapeRepresentation(b) => &b.items,
                 Entity::ShapeRepresentation(b) => &b.items,
                 Entity::ManifoldSurfaceShapeRepresentation(b) => &b.items,
+                Entity::GeometricallyBoundedSurfaceShapeRepresentation(b) => &b.items,
+                Entity::GeometricallyBoundedWireframeShapeRepresentation(b) => &b.items,
                 e => panic!("Could not get shape from {:?}", e),
             };
 
@@ -136,12 +337,23 @@ pub fn triangulate(s: &StepFile) -> (Mesh, Stats) {
                 match &s[*m] {
                     Entity::ManifoldSolidBrep(_)
                     | Entity::BrepWithVoids(_)
-                    | Entity::ShellBasedSurfaceModel(_) => to_mesh.entry(*m).or_default().push(mat),
+                    | Entity::ShellBasedSurfaceModel(_)
+                    | Entity::GeometricSet(_)
+                    | Entity::GeometricCurveSet(_) => to_mesh.entry(*m).or_default().push(*mat),
                     Entity::Axis2Placement3d(_) => (),
-                    e => warn!("Skipping {:?}", e),
+                    e => diagnose!(setup, Level::Warn, Some(m.0), "Skipping {:?}", e),
                 }
             }
-        }
+        },
+    );
+    if let Err(e) = r {
+        diagnose!(
+            setup,
+            Level::Error,
+            Some(e.id()),
+            "Could not walk the assembly: {}",
+            e
+        );
     }
     // If there are items in breps that aren't attached to a transformation
     // chain, then draw them individually (with an identity matrix)
@@ -154,6 +366,8 @@ pub fn triangulate(s: &StepFile) -> (Mesh, Stats) {
                     Entity::ManifoldSolidBrep(_)
                         | Entity::BrepWithVoids(_)
                         | Entity::ShellBasedSurfaceModel(_)
+                        | Entity::GeometricSet(_)
+                        | Entity::GeometricCurveSet(_)
                 )
             })
             .map(|(i, _e)| Id::new(i))
@@ -170,78 +384,161 @@ pub fn triangulate(s: &StepFile) -> (Mesh, Stats) {
             (to_mesh.iter(), (Mesh::default(), Stats::default()))
         }
     };
-    let mesh_fold = to_mesh_iter.fold(
-        // Empty constructor
-        empty,
-        // Fold operation
-        |(mut mesh, mut stats), (id, mats)| {
-            let v_start = mesh.verts.len();
-            let t_start = mesh.triangles.len();
-            match &s[*id] {
-                Entity::ManifoldSolidBrep(b) => closed_shell(s, b.outer, &mut mesh, &mut stats),
-                Entity::ShellBasedSurfaceModel(b) => {
-                    for v in &b.sbsm_boundary {
-                        shell(s, *v, &mut mesh, &mut stats);
-                    }
-                }
-                Entity::BrepWithVoids(b) =>
-                // TODO: handle voids
-                {
-                    closed_shell(s, b.outer, &mut mesh, &mut stats)
-                }
-                _ => {
-                    warn!("Skipping {:?} (not a known solid)", s[*id]);
-                    return (mesh, stats);
+    let done = AtomicUsize::new(0);
+    let add_solid = |(mut mesh, mut stats): (Mesh, Stats), (id, mats): (&Id<_>, &Vec<DMat4>)| {
+        let v_start = mesh.verts.len();
+        let t_start = mesh.triangles.len();
+        let f_start = mesh.faces.len();
+        let q_start = mesh.quads.len();
+        let g_start = mesh.grids.len();
+        let crop = crop.map(|aabb| Crop { aabb, mats });
+        let crop = crop.as_ref();
+        match &s[*id] {
+            Entity::ManifoldSolidBrep(b) => {
+                closed_shell(s, b.outer, tolerance, crop, topology, &mut mesh, &mut stats)
+            }
+            Entity::ShellBasedSurfaceModel(b) => {
+                for v in &b.sbsm_boundary {
+                    shell(s, *v, tolerance, crop, topology, &mut mesh, &mut stats);
                 }
-            };
+            }
+            Entity::BrepWithVoids(b) =>
+            // TODO: handle voids
+            {
+                closed_shell(s, b.outer, tolerance, crop, topology, &mut mesh, &mut stats)
+            }
+            // Curves aren't part of any solid, so they're placed here and
+            // skip the rest of this function
+            Entity::GeometricSet(g) => {
+                geometric_set(s, &g.elements, mats, tolerance, &mut mesh, &mut stats);
+                return (mesh, stats);
+            }
+            Entity::GeometricCurveSet(g) => {
+                geometric_set(s, &g.elements, mats, tolerance, &mut mesh, &mut stats);
+                return (mesh, stats);
+            }
+            _ => {
+                diagnose!(
+                    stats,
+                    Level::Warn,
+                    Some(id.0),
+                    "Skipping {:?} (not a known solid)",
+                    s[*id]
+                );
+                return (mesh, stats);
+            }
+        };
 
-            // Pick out a color from the color map and apply it to each
-            // newly-created vertex
-            let color = brep_colors
-                .get(id)
-                .copied()
-                .unwrap_or(DVec3::new(0.5, 0.5, 0.5));
-
-            // Build copies of the mesh by copying and applying transforms
-            let v_end = mesh.verts.len();
-            let t_end = mesh.triangles.len();
-            for mat in &mats[1..] {
-                for v in v_start..v_end {
-                    let p = mesh.verts[v].pos;
-                    let p_h = DVec4::new(p.x, p.y, p.z, 1.0);
-                    let pos = (mat * p_h).xyz();
-
-                    let n = mesh.verts[v].norm;
-                    let norm = (mat * glm::vec3_to_vec4(&n)).xyz();
-
-                    mesh.verts.push(mesh::Vertex { pos, norm, color });
-                }
-                let offset = mesh.verts.len() - v_end;
-                for t in t_start..t_end {
-                    let mut tri = mesh.triangles[t];
-                    tri.verts.add_scalar_mut(offset as u32);
-                    mesh.triangles.push(tri);
-                }
+        // Solids which were cropped away entirely don't become parts
+        if crop.is_some() && mesh.triangles.len() == t_start {
+            mesh.verts.truncate(v_start);
+            if let Some(c) = &mut mesh.curvature {
+                c.truncate(v_start);
             }
+            return (mesh, stats);
+        }
 
-            // Now that we've built all of the other copies of the mesh,
-            // re-use the original mesh and apply the first transform
-            let mat = mats[0];
+        // Pick out a color from the color map and apply it to each
+        // newly-created vertex
+        let color = brep_colors
+            .get(id)
+            .copied()
+            .unwrap_or(DVec3::new(0.5, 0.5, 0.5));
+
+        // Build copies of the mesh by copying and applying transforms
+        let v_end = mesh.verts.len();
+        let t_end = mesh.triangles.len();
+        let f_end = mesh.faces.len();
+        let q_end = mesh.quads.len();
+        let g_end = mesh.grids.len();
+        let curvature = mesh.curvature.get_or_insert_with(Vec::new);
+        curvature.resize(v_end, f32::NAN);
+        mesh.parts.push(Part {
+            shape: id.0,
+            triangles: t_start..t_end,
+            transform: mats[0],
+        });
+        for mat in &mats[1..] {
             for v in v_start..v_end {
                 let p = mesh.verts[v].pos;
                 let p_h = DVec4::new(p.x, p.y, p.z, 1.0);
-                mesh.verts[v].pos = (mat * p_h).xyz();
+                let pos = (mat * p_h).xyz();
 
                 let n = mesh.verts[v].norm;
-                mesh.verts[v].norm = (mat * glm::vec3_to_vec4(&n)).xyz();
+                let norm = (mat * glm::vec3_to_vec4(&n)).xyz();
+
+                mesh.verts.push(mesh::Vertex { pos, norm, color });
+            }
+            if let Some(c) = &mut mesh.curvature {
+                c.extend_from_within(v_start..v_end);
+            }
+            let offset = mesh.verts.len() - v_end;
+            let copy_start = mesh.triangles.len();
+            for t in t_start..t_end {
+                let mut tri = mesh.triangles[t];
+                tri.verts.add_scalar_mut(offset as u32);
+                mesh.triangles.push(tri);
+            }
+            for f in f_start..f_end {
+                let f = &mesh.faces[f];
+                let face = Face {
+                    id: f.id,
+                    triangles: (f.triangles.start - t_start + copy_start)
+                        ..(f.triangles.end - t_start + copy_start),
+                };
+                mesh.faces.push(face);
+            }
+            for q in q_start..q_end {
+                mesh.quads.push(mesh.quads[q] - t_start + copy_start);
+            }
+            for g in g_start..g_end {
+                let mut grid = mesh.grids[g].clone();
+                for p in &mut grid.points {
+                    *p = (mat * p.push(1.0)).xyz();
+                }
+                mesh.grids.push(grid);
+            }
+            mesh.parts.push(Part {
+                shape: id.0,
+                triangles: copy_start..mesh.triangles.len(),
+                transform: *mat,
+            });
+        }
 
-                mesh.verts[v].color = color;
+        // Now that we've built all of the other copies of the mesh,
+        // re-use the original mesh and apply the first transform
+        let mat = mats[0];
+        for v in v_start..v_end {
+            let p = mesh.verts[v].pos;
+            let p_h = DVec4::new(p.x, p.y, p.z, 1.0);
+            mesh.verts[v].pos = (mat * p_h).xyz();
+
+            let n = mesh.verts[v].norm;
+            mesh.verts[v].norm = (mat * glm::vec3_to_vec4(&n)).xyz();
+
+            mesh.verts[v].color = color;
+        }
+        for g in &mut mesh.grids[g_start..g_end] {
+            for p in &mut g.points {
+                *p = (mat * p.push(1.0)).xyz();
             }
-            (mesh, stats)
+        }
+        (mesh, stats)
+    };
+    let mesh_fold = to_mesh_iter.fold(
+        // Empty constructor
+        empty,
+        // Fold operation.  Each solid is built in its own mesh, so that it
+        // can be handed to `on_solid` before it's appended.
+        |(acc, stats), item| {
+            let (solid, stats) = add_solid((Mesh::default(), stats), item);
+            on_solid(&solid);
+            progress(done.fetch_add(1, Ordering::Relaxed) + 1, to_mesh.len());
+            (Mesh::combine(acc, solid), stats)
         },
     );
 
-    let (mesh, stats) = {
+    let (mut mesh, stats) = {
         #[cfg(feature = "rayon")]
         {
             mesh_fold.reduce(empty, |a, b| {
@@ -254,13 +551,143 @@ pub fn triangulate(s: &StepFile) -> (Mesh, Stats) {
         }
     };
 
+    mesh.layers = layers(s, &mesh);
+
+    let stats = Stats::combine(setup, stats);
     info!("num_shells: {}", stats.num_shells);
+    info!("num_open_shells: {}", stats.num_open_shells);
     info!("num_faces: {}", stats.num_faces);
+    info!("num_wires: {}", stats.num_wires);
     info!("num_errors: {}", stats.num_errors);
     info!("num_panics: {}", stats.num_panics);
     (mesh, stats)
 }
 
+/// Triangulates individual faces, given as indices of `ADVANCED_FACE`
+/// entities, without the rest of the model.
+///
+/// The faces are left in their solids' own coordinates and aren't colored,
+/// since a face may be shared by several placements of a solid; they're
+/// meant to be spliced into a mesh of the whole model with
+/// [`Mesh::splice_faces`].
+pub fn triangulate_faces(
+    s: &StepFile,
+    faces: &[usize],
+    tolerance: Option<f64>,
+    topology: MeshTopology,
+) -> (Mesh, Stats) {
+    let mut mesh = Mesh::default();
+    let mut stats = Stats::default();
+    for f in faces {
+        if let Err(err) = advanced_face(
+            s,
+            Id::new(*f),
+            tolerance,
+            None,
+            topology,
+            &mut mesh,
+            &mut stats,
+        ) {
+            diagnose!(
+                stats,
+                Level::Error,
+                Some(*f),
+                "Failed to triangulate #{}: {}",
+                f,
+                err
+            );
+            stats.first_error.get_or_insert(err);
+        }
+    }
+    (mesh, stats)
+}
+
+/// Triangulates some of a mesh's faces again (e.g. with a finer tolerance),
+/// splicing the results in place of their old triangles.  Faces which fail
+/// to triangulate keep their old triangles.
+///
+/// Only the tolerance and topology are taken from `opts`; the mesh's
+/// existing parts already carry any crop, placement, or coordinate
+/// convention.  Returns statistics for just these faces.
+pub fn retriangulate_faces(
+    s: &StepFile,
+    mesh: &mut Mesh,
+    faces: &[usize],
+    opts: &TriangulateOptions,
+) -> Stats {
+    let (fragments, stats) = triangulate_faces(s, faces, opts.tolerance, opts.topology);
+    mesh.splice_faces(&fragments);
+    stats
+}
+
+/// Returns the faces within a layered item, looking through styled items,
+/// representations, solids, and shells
+fn layer_faces(s: &StepFile, items: &[usize]) -> Vec<usize> {
+    let children = |id: usize| -> Vec<usize> {
+        match s.0.get(id) {
+            Some(Entity::ClosedShell(c)) => c.cfs_faces.iter().map(|f| f.0).collect(),
+            Some(Entity::OpenShell(c)) => c.cfs_faces.iter().map(|f| f.0).collect(),
+            Some(Entity::ManifoldSolidBrep(b)) => vec![b.outer.0],
+            Some(Entity::BrepWithVoids(b)) => vec![b.outer.0],
+            Some(Entity::ShellBasedSurfaceModel(m)) => {
+                m.sbsm_boundary.iter().map(|b| b.0).collect()
+            }
+            Some(Entity::StyledItem(i)) => vec![i.item.0],
+            Some(Entity::OverRidingStyledItem(i)) => vec![i.item.0],
+            Some(Entity::AdvancedBrepShapeRepresentation(r)) => {
+                r.items.iter().map(|i| i.0).collect()
+            }
+            Some(Entity::ShapeRepresentation(r)) => r.items.iter().map(|i| i.0).collect(),
+            Some(Entity::ManifoldSurfaceShapeRepresentation(r)) => {
+                r.items.iter().map(|i| i.0).collect()
+            }
+            _ => vec![],
+        }
+    };
+    let (found, r) = GraphWalker::default().reachable(items.iter().copied(), children);
+    if let Err(e) = r {
+        warn!("Could not walk layer items: {}", e);
+    }
+    found
+        .into_iter()
+        .filter(|i| {
+            matches!(
+                s.0.get(*i),
+                Some(Entity::AdvancedFace(_)) | Some(Entity::FaceSurface(_))
+            )
+        })
+        .collect()
+}
+
+/// Builds the mesh's presentation layers, keeping only faces which were
+/// triangulated.  Assignments with the same name are merged, and layers
+/// without any triangulated faces are dropped.
+fn layers(s: &StepFile, mesh: &Mesh) -> Vec<mesh::Layer> {
+    let faces: HashSet<usize> = mesh.faces.iter().map(|f| f.id).collect();
+    let mut out: Vec<mesh::Layer> = vec![];
+    for layer in step::organization::layers(s) {
+        let mut found = layer_faces(s, &layer.item_ids);
+        found.retain(|f| faces.contains(f));
+        if found.is_empty() {
+            continue;
+        }
+        let l = match out.iter_mut().position(|l| l.name == layer.name) {
+            Some(i) => &mut out[i],
+            None => {
+                out.push(mesh::Layer {
+                    name: layer.name,
+                    faces: vec![],
+                });
+                out.last_mut().unwrap()
+            }
+        };
+        l.faces.extend(found);
+        l.faces.sort_unstable();
+        l.faces.dedup();
+    }
+    out
+}
+
 fn item_defined_transformation(s: &StepFile, t: Id<ItemDefinedTransformation_>) -> DMat4 {
     let i = s.entity(t).expect("Could not get ItemDefinedTransform");
 
@@ -340,29 +767,138 @@ fn axis2_placement_3d(s: &StepFile, t: Id<Axis2Placement3d_>) -> (DVec3, DVec3,
     (location, axis, ref_direction)
 }
 
-fn shell(s: &StepFile, c: Shell, mesh: &mut Mesh, stats: &mut Stats) {
+/// Samples each curve in a geometric set into a wire, with one copy per
+/// placement.  Points and surfaces in the set are skipped.
+fn geometric_set(
+    s: &StepFile,
+    elements: &[GeometricSetSelect],
+    mats: &[DMat4],
+    tol: Option<f64>,
+    mesh: &mut Mesh,
+    stats: &mut Stats,
+) {
+    for e in elements {
+        if matches!(s[*e], Entity::CartesianPoint(_)) {
+            continue;
+        }
+        match check_curve(s, e.0).and_then(|_| bounded_curve(s, e.0, e.cast(), tol, stats)) {
+            Ok(points) => {
+                stats.num_wires += 1;
+                for mat in mats {
+                    let points = points
+                        .iter()
+                        .map(|p| (mat * DVec4::new(p.x, p.y, p.z, 1.0)).xyz())
+                        .collect();
+                    mesh.wires.push(Wire { id: e.0, points });
+                }
+            }
+            Err(Error::UnknownCurveType) => {
+                diagnose!(
+                    stats,
+                    Level::Warn,
+                    Some(e.0),
+                    "Skipping {:?} (not a curve)",
+                    s[*e]
+                )
+            }
+            Err(err) => diagnose!(
+                stats,
+                Level::Error,
+                Some(e.0),
+                "Failed to sample {:?}: {}",
+                s[*e],
+                err
+            ),
+        }
+    }
+}
+
+/// The crop box, along with the placements of the solid being triangulated
+struct Crop<'a> {
+    aabb: &'a Aabb,
+    mats: &'a [DMat4],
+}
+
+impl Crop<'_> {
+    /// Checks whether a box (in the solid's own coordinates) touches the
+    /// crop box in any of the solid's placements
+    fn keeps(&self, local: &Aabb) -> bool {
+        self.mats
+            .iter()
+            .any(|m| local.transformed(m).intersects(self.aabb))
+    }
+}
+
+fn shell(
+    s: &StepFile,
+    c: Shell,
+    tol: Option<f64>,
+    crop: Option<&Crop>,
+    topology: MeshTopology,
+    mesh: &mut Mesh,
+    stats: &mut Stats,
+) {
     match &s[c] {
-        Entity::ClosedShell(_) => closed_shell(s, c.cast(), mesh, stats),
-        Entity::OpenShell(_) => open_shell(s, c.cast(), mesh, stats),
-        h => warn!("Skipping {:?} (unknown Shell type)", h),
+        Entity::ClosedShell(_) => closed_shell(s, c.cast(), tol, crop, topology, mesh, stats),
+        Entity::OpenShell(_) => open_shell(s, c.cast(), tol, crop, topology, mesh, stats),
+        h => diagnose!(
+            stats,
+            Level::Warn,
+            Some(c.0),
+            "Skipping {:?} (unknown Shell type)",
+            h
+        ),
     }
 }
 
-fn open_shell(s: &StepFile, c: OpenShell, mesh: &mut Mesh, stats: &mut Stats) {
+fn open_shell(
+    s: &StepFile,
+    c: OpenShell,
+    tol: Option<f64>,
+    crop: Option<&Crop>,
+    topology: MeshTopology,
+    mesh: &mut Mesh,
+    stats: &mut Stats,
+) {
     let cs = s.entity(c).expect("Could not get OpenShell");
     for face in &cs.cfs_faces {
-        if let Err(err) = advanced_face(s, face.cast(), mesh, stats) {
-            error!("Failed to triangulate {:?}: {}", s[*face], err);
+        if let Err(err) = advanced_face(s, face.cast(), tol, crop, topology, mesh, stats) {
+            diagnose!(
+                stats,
+                Level::Error,
+                Some(face.0),
+                "Failed to triangulate {:?}: {}",
+                s[*face],
+                err
+            );
+            stats.first_error.get_or_insert(err);
         }
     }
     stats.num_shells += 1;
+    stats.num_open_shells += 1;
 }
 
-fn closed_shell(s: &StepFile, c: ClosedShell, mesh: &mut Mesh, stats: &mut Stats) {
+fn closed_shell(
+    s: &StepFile,
+    c: ClosedShell,
+    tol: Option<f64>,
+    crop: Option<&Crop>,
+    topology: MeshTopology,
+    mesh: &mut Mesh,
+    stats: &mut Stats,
+) {
     let cs = s.entity(c).expect("Could not get ClosedShell");
     for face in &cs.cfs_faces {
-        if let Err(err) = advanced_face(s, face.cast(), mesh, stats) {
-            error!("Failed to triangulate {:?}: {}", s[*face], err);
+        if let Err(err) = advanced_face(s, face.cast(), tol, crop, topology, mesh, stats) {
+            diagnose!(
+                stats,
+                Level::Error,
+                Some(face.0),
+                "Failed to triangulate {:?}: {}",
+                s[*face],
+                err
+            );
+            stats.first_error.get_or_insert(err);
         }
     }
     stats.num_shells += 1;
@@ -371,14 +907,36 @@ fn closed_shell(s: &StepFile, c: ClosedShell, mesh: &mut Mesh, stats: &mut Stats
 fn advanced_face(
     s: &StepFile,
     f: AdvancedFace,
+    tol: Option<f64>,
+    crop: Option<&Crop>,
+    topology: MeshTopology,
     mesh: &mut Mesh,
     stats: &mut Stats,
 ) -> Result<(), Error> {
-    let face = s.entity(f).expect("Could not get AdvancedFace");
+    let face = get(s, f, "AdvancedFace")?;
     stats.num_faces += 1;
 
-    // Grab the surface, returning early if it's unimplemented
-    let mut surf = surface(s, face.face_geometry)?;
+    // Grab the surface, falling back to a plane through the face's boundary
+    // (or returning early) if it's unimplemented
+    let mut surf = match surface(s, face.face_geometry) {
+        Ok(surf) => {
+            stats.surface_types.insert(f.0, surf.surface_type());
+            surf
+        }
+        Err(e) => {
+            let surf = fallback_plane(s, face, tol, stats)?.ok_or(e)?;
+            diagnose!(
+                stats,
+                Level::Warn,
+                Some(f.0),
+                "Surface #{} is not supported; triangulating face on its boundary's plane",
+                face.face_geometry.0
+            );
+            stats.surface_types.insert(f.0, SurfaceType::FallbackPlanar);
+            stats.num_fallbacks += 1;
+            surf
+        }
+    };
 
     // This is the starting point at which we insert new vertices
     let offset = mesh.verts.len();
@@ -386,14 +944,31 @@ fn advanced_face(
     // For each contour, project from 3D down to the surface, then
     // start collecting them as constrained edges for triangulation
     let mut edges = Vec::new();
+    let mut loops = Vec::new();
     let v_start = mesh.verts.len();
     let mut num_pts = 0;
+
+    // Each boundary point may also have a position in the surface's
+    // parameter space, from its edge's p-curve (once it's been checked)
+    let param = parameterization(s, face.face_geometry, &surf);
+    let mut hints = Vec::new();
     for b in &face.bounds {
-        let bound_contours = face_bound(s, *b)?;
+        let (bound_contours, bound_edges) = face_bound(s, *b, tol, stats)?;
+        match &param {
+            Some(param) if !bound_edges.is_empty() => hints.extend(pcurve_hints(
+                s,
+                face.face_geometry.0,
+                param,
+                &bound_contours,
+                &bound_edges,
+                stats,
+            )),
+            _ => hints.resize(hints.len() + bound_contours.len(), None),
+        }
 
         match bound_contours.len() {
             // We should always have non-zero items in the contour
-            0 => panic!("Got empty contours for {:?}", face),
+            0 => return Err(Error::EmptyBound(b.0)),
 
             // Special case for a single-vertex point, which shows up in
             // cones: we push it as a Steiner point, but without any
@@ -428,10 +1003,23 @@ fn advanced_face(
                 // the start.
                 num_pts -= 1;
                 mesh.verts.pop();
+                hints.pop();
 
                 // Close the loop by returning to the starting point
                 edges.pop();
                 edges.last_mut().unwrap().1 = start;
+                loops.push(start..num_pts);
+            }
+        }
+    }
+
+    // Skip faces outside of the crop box before doing any expensive work
+    if let Some(crop) = crop {
+        if let Some(b) = surf.face_bounds(&mesh.verts[v_start..]) {
+            if !crop.keeps(&b) {
+                mesh.verts.truncate(v_start);
+                stats.num_cropped += 1;
+                return Ok(());
             }
         }
     }
@@ -441,9 +1029,34 @@ fn advanced_face(
     // _fail_ due to these points, so if that happens, we nuke the point (by
     // assigning it to the first point in the list, which causes it to get
     // deduplicated), then retry.
-    let mut pts = surf.lower_vertices(&mut mesh.verts[v_start..])?;
+    let mut pts = surf.lower_vertices_with_hints(&mut mesh.verts[v_start..], &hints)?;
     let bonus_points = pts.len();
-    surf.add_steiner_points(&mut pts, &mut mesh.verts);
+    let grid = match topology {
+        MeshTopology::Triangles => None,
+        _ => grid::sample_face(f.0, &surf, &mesh.verts[v_start..], &pts, &loops, tol),
+    };
+
+    // When building quads, the grid's samples take the place of the usual
+    // Steiner points, so that the triangulation follows the grid's cells
+    let mut corners = vec![];
+    match (&grid, topology) {
+        (Some((g, samples)), MeshTopology::QuadDominant) => {
+            corners = grid::add_samples(&surf, g, samples, &mut pts, &mut mesh.verts);
+        }
+        _ => surf.add_steiner_points(&mut pts, &mut mesh.verts),
+    }
+
+    // Near-degenerate boundaries (tiny edges, or points repeated with
+    // rounding error) confuse the triangulator, so merge points which are
+    // almost on top of each other, then drop the edges which collapse
+    cdt::merge_close_points(&mut pts, MERGE_EPSILON);
+    cdt::clean_edges(&pts, &mut edges);
+
+    // Store curvature for all of the new vertices, padding out the channel
+    // if earlier faces bailed out after adding vertices.
+    let curvature = mesh.curvature.get_or_insert_with(Vec::new);
+    curvature.resize(v_start, f32::NAN);
+    curvature.extend(surf.vertex_curvatures(&mesh.verts[v_start..], &pts));
     let result = std::panic::catch_unwind(|| {
         // TODO: this is only needed because we use pts below to save a debug
         // SVG if this panics.  Once we're confident in never panicking, we
@@ -474,9 +1087,44 @@ fn advanced_face(
             }
         }
     });
+    let t_start = mesh.triangles.len();
     match result {
         Ok(Ok(t)) => {
-            for (a, b, c) in t.triangles() {
+            let tris: Vec<_> = t.triangles().collect();
+            let err = cdt::coverage_error(&pts, &edges, &tris);
+            if err > COVERAGE_TOLERANCE {
+                diagnose!(
+                    stats,
+                    Level::Warn,
+                    Some(f.0),
+                    "Triangles of face don't cover its boundary exactly once \
+                     (area is off by {:.2}%)",
+                    err * 100.0
+                );
+            }
+            let (tris, quads) = match &grid {
+                Some((g, _)) if topology == MeshTopology::QuadDominant => {
+                    // Merged points were snapped onto the earliest point at
+                    // the same position, which is the one in the triangles
+                    let mut first = HashMap::new();
+                    for (i, (x, y)) in pts.iter().enumerate() {
+                        first.entry((x.to_bits(), y.to_bits())).or_insert(i);
+                    }
+                    let corners: Vec<_> = corners
+                        .iter()
+                        .map(|c| c.map(|i| first[&(pts[i].0.to_bits(), pts[i].1.to_bits())]))
+                        .collect();
+                    grid::pair_quads(g.cols, &corners, tris)
+                }
+                _ => (tris, 0),
+            };
+            mesh.quads.extend((0..quads).map(|q| t_start + 2 * q));
+            if topology == MeshTopology::Grid {
+                if let Some((g, _)) = grid {
+                    mesh.grids.push(g);
+                }
+            }
+            for (a, b, c) in tris {
                 let a = (a + offset) as u32;
                 let b = (b + offset) as u32;
                 let c = (c + offset) as u32;
@@ -490,16 +1138,24 @@ fn advanced_face(
             }
         }
         Ok(Err(e)) => {
-            error!(
+            diagnose!(
+                stats,
+                Level::Error,
+                Some(f.0),
                 "Got error while triangulating {}: {:?}",
-                face.face_geometry.0, e
+                face.face_geometry.0,
+                e
             );
             stats.num_errors += 1;
         }
         Err(e) => {
-            error!(
+            diagnose!(
+                stats,
+                Level::Error,
+                Some(f.0),
                 "Got panic while triangulating {}: {:?}",
-                face.face_geometry.0, e
+                face.face_geometry.0,
+                e
             );
             if SAVE_PANIC_SVGS {
                 let filename = format!("panic{}.svg", face.face_geometry.0);
@@ -508,6 +1164,13 @@ fn advanced_face(
             stats.num_panics += 1;
         }
     }
+    if mesh.triangles.len() > t_start {
+        mesh.faces.push(Face {
+            id: f.0,
+            triangles: t_start..mesh.triangles.len(),
+        });
+    }
+
     // Flip normals of new vertices, depending on the same_sense flag
     if !face.same_sense {
         for v in &mut mesh.verts[v_start..] {
@@ -517,6 +1180,41 @@ fn advanced_face(
     Ok(())
 }
 
+/// Builds the best-fit plane of a face's outer boundary, for faces whose
+/// surface can't be evaluated.  The plane's normal matches the surface
+/// normal that the face's orientation implies.
+///
+/// Returns `Ok(None)` if there's no boundary to fit, and an error if the
+/// boundary is too far from planar.
+fn fallback_plane(
+    s: &StepFile,
+    face: &AdvancedFace_,
+    tol: Option<f64>,
+    stats: &mut Stats,
+) -> Result<Option<Surface>, Error> {
+    let outer = face
+        .bounds
+        .iter()
+        .find(|b| matches!(s.0.get(b.0), Some(Entity::FaceOuterBound(_))))
+        .or_else(|| face.bounds.first());
+    let Some(outer) = outer else {
+        return Ok(None);
+    };
+    // The boundary winds around the face's normal, which is opposite to
+    // the surface normal if `same_sense` is false
+    let (mut pts, _) = face_bound(s, *outer, tol, stats)?;
+    if !face.same_sense {
+        pts.reverse();
+    }
+    let Some((plane, deviation)) = Surface::new_best_fit_plane(&pts) else {
+        return Ok(None);
+    };
+    if deviation > MAX_FALLBACK_DEVIATION {
+        return Err(Error::NonPlanarFallback(face.face_geometry.0));
+    }
+    Ok(Some(plane))
+}
+
 fn surface(s: &StepFile, surf: ap214::Surface) -> Result<Surface, Error> {
     match &s[surf] {
         Entity::CylindricalSurface(c) => {
@@ -560,40 +1258,19 @@ fn surface(s: &StepFile, surf: ap214::Surface) -> Result<Surface, Error> {
             Ok(Surface::new_sphere(location, c.radius.0 .0 .0))
         }
         Entity::BSplineSurfaceWithKnots(b) => {
-            // TODO: make KnotVector::from_multiplicies accept iterators?
-            let u_knots: Vec<f64> = b.u_knots.iter().map(|k| k.0).collect();
-            let u_multiplicities: Vec<usize> = b
-                .u_multiplicities
-                .iter()
-                .map(|&k| k.try_into().expect("Got negative multiplicity"))
-                .collect();
-            let u_knot_vec = KnotVector::from_multiplicities(
-                b.u_degree.try_into().expect("Got negative degree"),
-                &u_knots,
-                &u_multiplicities,
-            );
-
-            let v_knots: Vec<f64> = b.v_knots.iter().map(|k| k.0).collect();
-            let v_multiplicities: Vec<usize> = b
-                .v_multiplicities
-                .iter()
-                .map(|&k| k.try_into().expect("Got negative multiplicity"))
-                .collect();
-            let v_knot_vec = KnotVector::from_multiplicities(
-                b.v_degree.try_into().expect("Got negative degree"),
-                &v_knots,
-                &v_multiplicities,
-            );
+            let u_knot_vec = knot_vector(surf.0, b.u_degree, &b.u_knots, &b.u_multiplicities)?;
+            let v_knot_vec = knot_vector(surf.0, b.v_degree, &b.v_knots, &b.v_multiplicities)?;
 
             let control_points_list = control_points_2d(s, &b.control_points_list);
 
             let surf = BsplineSurface::new(
-                !b.u_closed.0.unwrap(),
-                !b.v_closed.0.unwrap(),
+                b.u_closed.0 != Some(true),
+                b.v_closed.0 != Some(true),
                 u_knot_vec,
                 v_knot_vec,
                 control_points_list,
-            );
+            )
+            .map_err(|source| Error::Nurbs { id: surf.0, source })?;
             Ok(Surface::Bspline(SampledSurface::new(surf)))
         }
         Entity::ComplexEntity(v) if v.len() == 2 => {
@@ -610,30 +1287,18 @@ fn surface(s: &StepFile, surf: ap214::Surface) -> Result<Surface, Error> {
                 return Err(Error::UnknownCurveType);
             };
 
-            // TODO: make KnotVector::from_multiplicies accept iterators?
-            let u_knots: Vec<f64> = bspline.u_knots.iter().map(|k| k.0).collect();
-            let u_multiplicities: Vec<usize> = bspline
-                .u_multiplicities
-                .iter()
-                .map(|&k| k.try_into().expect("Got negative multiplicity"))
-                .collect();
-            let u_knot_vec = KnotVector::from_multiplicities(
-                bspline.u_degree.try_into().expect("Got negative degree"),
-                &u_knots,
-                &u_multiplicities,
-            );
-
-            let v_knots: Vec<f64> = bspline.v_knots.iter().map(|k| k.0).collect();
-            let v_multiplicities: Vec<usize> = bspline
-                .v_multiplicities
-                .iter()
-                .map(|&k| k.try_into().expect("Got negative multiplicity"))
-                .collect();
-            let v_knot_vec = KnotVector::from_multiplicities(
-                bspline.v_degree.try_into().expect("Got negative degree"),
-                &v_knots,
-                &v_multiplicities,
-            );
+            let u_knot_vec = knot_vector(
+                surf.0,
+                bspline.u_degree,
+                &bspline.u_knots,
+                &bspline.u_multiplicities,
+            )?;
+            let v_knot_vec = knot_vector(
+                surf.0,
+                bspline.v_degree,
+                &bspline.v_knots,
+                &bspline.v_multiplicities,
+            )?;
 
             let control_points_list = control_points_2d(s, &bspline.control_points_list)
                 .into_iter()
@@ -647,12 +1312,13 @@ fn surface(s: &StepFile, surf: ap214::Surface) -> Result<Surface, Error> {
                 .collect();
 
             let surf = NurbsSurface::new(
-                !bspline.u_closed.0.unwrap(),
-                !bspline.v_closed.0.unwrap(),
+                bspline.u_closed.0 != Some(true),
+                bspline.v_closed.0 != Some(true),
                 u_knot_vec,
                 v_knot_vec,
                 control_points_list,
-            );
+            )
+            .map_err(|source| Error::Nurbs { id: surf.0, source })?;
             Ok(Surface::Nurbs(SampledSurface::new(surf)))
         }
         e => {
@@ -670,75 +1336,159 @@ fn control_points_2d(s: &StepFile, rows: &[Vec<CartesianPoint>]) -> Vec<Vec<DVec
     rows.iter().map(|row| control_points_1d(s, row)).collect()
 }
 
-fn face_bound(s: &StepFile, b: FaceBound) -> Result<Vec<DVec3>, Error> {
-    let (bound, orientation) = match &s[b] {
-        Entity::FaceBound(b) => (b.bound, b.orientation),
-        Entity::FaceOuterBound(b) => (b.bound, b.orientation),
-        e => panic!("Could not get bound from {:?} at {:?}", e, b),
+/// An edge within a face bound, with the range of the bound's points which
+/// were sampled from it (which overlaps its neighbors' ranges at the ends)
+struct BoundEdge {
+    edge: usize,
+    /// Whether the points run along the edge's curve
+    along: bool,
+    points: Range<usize>,
+}
+
+fn face_bound(
+    s: &StepFile,
+    b: FaceBound,
+    tol: Option<f64>,
+    stats: &mut Stats,
+) -> Result<(Vec<DVec3>, Vec<BoundEdge>), Error> {
+    let (bound, orientation) = match s.0.get(b.0) {
+        Some(Entity::FaceBound(b)) => (b.bound, b.orientation),
+        Some(Entity::FaceOuterBound(b)) => (b.bound, b.orientation),
+        _ => {
+            return Err(Error::InvalidEntity {
+                id: b.0,
+                expected: "FaceBound",
+            })
+        }
     };
-    match &s[bound] {
-        Entity::EdgeLoop(e) => {
-            let mut d = edge_loop(s, &e.edge_list)?;
+    match s.0.get(bound.0) {
+        Some(Entity::EdgeLoop(e)) => {
+            let (mut d, mut edges) = edge_loop(s, &e.edge_list, tol, stats)?;
             if !orientation {
-                d.reverse()
+                d.reverse();
+                edges.reverse();
+                for e in &mut edges {
+                    e.along = !e.along;
+                    e.points = d.len() - e.points.end..d.len() - e.points.start;
+                }
             }
-            Ok(d)
+            Ok((d, edges))
         }
-        Entity::VertexLoop(v) => {
+        Some(Entity::VertexLoop(v)) => {
             // This is an "edge loop" with a single vertex, which is
             // used for cones and not really anything else.
-            Ok(vec![vertex_point(s, v.loop_vertex)])
+            Ok((vec![vertex_point(s, v.loop_vertex)?], vec![]))
         }
-        e => panic!("{:?} is not an EdgeLoop", e),
+        _ => Err(Error::InvalidEntity {
+            id: bound.0,
+            expected: "EdgeLoop",
+        }),
     }
 }
 
-fn edge_loop(s: &StepFile, edge_list: &[OrientedEdge]) -> Result<Vec<DVec3>, Error> {
+fn edge_loop(
+    s: &StepFile,
+    edge_list: &[OrientedEdge],
+    tol: Option<f64>,
+    stats: &mut Stats,
+) -> Result<(Vec<DVec3>, Vec<BoundEdge>), Error> {
     let mut out = Vec::new();
+    let mut edges = Vec::new();
     for (i, e) in edge_list.iter().enumerate() {
         // Remove the last item from the list, since it's the beginning
         // of the following list (hopefully)
         if i > 0 {
             out.pop();
         }
-        let edge = s.entity(*e).expect("Could not get OrientedEdge");
-        let o = edge_curve(s, edge.edge_element.cast(), edge.orientation)?;
+        let edge = get(s, *e, "OrientedEdge")?;
+        let o = edge_curve(s, edge.edge_element.cast(), edge.orientation, tol, stats)?;
+        let start = out.len();
         out.extend(o.into_iter());
+        let c = get(s, edge.edge_element.cast::<EdgeCurve_>(), "EdgeCurve")?;
+        edges.push(BoundEdge {
+            edge: edge.edge_element.0,
+            along: c.same_sense == edge.orientation,
+            points: start..out.len(),
+        });
     }
-    Ok(out)
+    Ok((out, edges))
 }
 
-fn edge_curve(s: &StepFile, e: EdgeCurve, orientation: bool) -> Result<Vec<DVec3>, Error> {
-    let edge_curve = s.entity(e).expect("Could not get EdgeCurve");
-    let curve = curve(s, edge_curve, edge_curve.edge_geometry, orientation)?;
+fn edge_curve(
+    s: &StepFile,
+    e: EdgeCurve,
+    orientation: bool,
+    tol: Option<f64>,
+    stats: &mut Stats,
+) -> Result<Vec<DVec3>, Error> {
+    let edge_curve = get(s, e, "EdgeCurve")?;
+    check_curve(s, edge_curve.edge_geometry.0)?;
+    let curve = curve(
+        s,
+        e.0,
+        edge_curve.edge_geometry,
+        edge_curve.edge_start == edge_curve.edge_end,
+        edge_curve.same_sense ^ !orientation,
+        tol,
+        stats,
+    )?;
 
     let (start, end) = if orientation {
         (edge_curve.edge_start, edge_curve.edge_end)
     } else {
         (edge_curve.edge_end, edge_curve.edge_start)
     };
-    let u = vertex_point(s, start);
-    let v = vertex_point(s, end);
-    Ok(curve.build(u, v))
+    let u = vertex_point(s, start)?;
+    let v = vertex_point(s, end)?;
+    Ok(curve.build(u, v, tol))
 }
 
+/// Checks that the curves which a curve is built from (e.g. the basis of a
+/// trimmed curve) don't lead back to it, and aren't nested too deeply, so
+/// that [`curve`] and [`bounded_curve`] can safely recurse through them
+fn check_curve(s: &StepFile, id: usize) -> Result<(), Error> {
+    let children = |i: usize| -> Vec<usize> {
+        match s.0.get(i) {
+            Some(Entity::SurfaceCurve(c)) => vec![c.curve_3d.0],
+            Some(Entity::SeamCurve(c)) => vec![c.curve_3d.0],
+            Some(Entity::TrimmedCurve(c)) => vec![c.basis_curve.0],
+            Some(Entity::CompositeCurve(c)) => c.segments.iter().map(|i| i.0).collect(),
+            Some(Entity::CompositeCurveSegment(c)) => vec![c.parent_curve.0],
+            _ => vec![],
+        }
+    };
+    let walker = GraphWalker {
+        max_depth: MAX_CURVE_DEPTH,
+        ..GraphWalker::default()
+    };
+    walker
+        .walk([id], children, |_| ())
+        .map_err(|source| Error::Graph { id, source })
+}
+
+/// Converts a curve into a [`Curve`], which is sampled between an edge's
+/// vertices.  `closed` and `dir` describe that edge: whether it starts and
+/// ends at the same vertex, and whether it runs along the curve's direction.
+/// Diagnostics are recorded against the edge, whose index is `edge`.
 fn curve(
     s: &StepFile,
-    edge_curve: &ap214::EdgeCurve_,
+    edge: usize,
     curve_id: ap214::Curve,
-    orientation: bool,
+    closed: bool,
+    dir: bool,
+    tol: Option<f64>,
+    stats: &mut Stats,
 ) -> Result<Curve, Error> {
-    Ok(match &s[curve_id] {
+    let Some(c) = s.0.get(curve_id.0) else {
+        return Err(Error::InvalidEntity {
+            id: curve_id.0,
+            expected: "Curve",
+        });
+    };
+    Ok(match c {
         Entity::Circle(c) => {
             let (location, axis, ref_direction) = axis2_placement_3d(s, c.position.cast());
-            Curve::new_circle(
-                location,
-                axis,
-                ref_direction,
-                c.radius.0 .0 .0,
-                edge_curve.edge_start == edge_curve.edge_end,
-                edge_curve.same_sense ^ !orientation,
-            )
+            Curve::new_circle(location, axis, ref_direction, c.radius.0 .0 .0, closed, dir)
         }
         Entity::Ellipse(c) => {
             let (location, axis, ref_direction) = axis2_placement_3d(s, c.position.cast());
@@ -748,34 +1498,32 @@ fn curve(
                 ref_direction,
                 c.semi_axis_1.0 .0 .0,
                 c.semi_axis_2.0 .0 .0,
-                edge_curve.edge_start == edge_curve.edge_end,
-                edge_curve.same_sense ^ !orientation,
+                closed,
+                dir,
+            )
+        }
+        Entity::Parabola(c) => {
+            let (location, axis, ref_direction) = axis2_placement_3d(s, c.position.cast());
+            Curve::new_parabola(location, axis, ref_direction, c.focal_dist.0)
+        }
+        Entity::Hyperbola(c) => {
+            let (location, axis, ref_direction) = axis2_placement_3d(s, c.position.cast());
+            Curve::new_hyperbola(
+                location,
+                axis,
+                ref_direction,
+                c.semi_axis.0 .0 .0,
+                c.semi_imag_axis.0 .0 .0,
             )
         }
         Entity::BSplineCurveWithKnots(c) => {
-            if c.closed_curve.0 != Some(false) {
-                return Err(Error::ClosedCurve);
-            } else if c.self_intersect.0 != Some(false) {
-                return Err(Error::SelfIntersectingCurve);
-            }
-
             let control_points_list = control_points_1d(s, &c.control_points_list);
-
-            let knots: Vec<f64> = c.knots.iter().map(|k| k.0).collect();
-            let multiplicities: Vec<usize> = c
-                .knot_multiplicities
-                .iter()
-                .map(|&k| k.try_into().expect("Got negative multiplicity"))
-                .collect();
-            let knot_vec = KnotVector::from_multiplicities(
-                c.degree.try_into().expect("Got negative degree"),
-                &knots,
-                &multiplicities,
-            );
-
-            let curve =
-                nurbs::BsplineCurve::new(!c.closed_curve.0.unwrap(), knot_vec, control_points_list);
-            Curve::BsplineCurveWithKnots(SampledCurve::new(curve))
+            let curve = bspline_curve(curve_id.0, c, control_points_list, stats)?;
+            Curve::BsplineCurveWithKnots {
+                curve: SampledCurve::new(curve),
+                closed,
+                dir,
+            }
         }
         Entity::ComplexEntity(v) if v.len() == 2 => {
             let bspline = if let Entity::BSplineCurveWithKnots(b) = &v[0] {
@@ -790,35 +1538,37 @@ fn curve(
                 warn!("Could not get RationalBSplineCurve from {:?}", v[1]);
                 return Err(Error::UnknownCurveType);
             };
-            let knots: Vec<f64> = bspline.knots.iter().map(|k| k.0).collect();
-            let multiplicities: Vec<usize> = bspline
-                .knot_multiplicities
-                .iter()
-                .map(|&k| k.try_into().expect("Got negative multiplicity"))
-                .collect();
-            let knot_vec = KnotVector::from_multiplicities(
-                bspline.degree.try_into().expect("Got negative degree"),
-                &knots,
-                &multiplicities,
-            );
-
             let control_points_list = control_points_1d(s, &bspline.control_points_list)
                 .into_iter()
                 .zip(rational.weights_data.iter())
                 .map(|(p, w)| DVec4::new(p.x * w, p.y * w, p.z * w, *w))
                 .collect();
-
-            let curve = nurbs::NurbsCurve::new(
-                !bspline.closed_curve.0.unwrap(),
-                knot_vec,
-                control_points_list,
-            );
-            Curve::NurbsCurve(SampledCurve::new(curve))
+            let curve = bspline_curve(curve_id.0, bspline, control_points_list, stats)?;
+            Curve::NurbsCurve {
+                curve: SampledCurve::new(curve),
+                closed,
+                dir,
+            }
         }
-        Entity::SurfaceCurve(v) => curve(s, edge_curve, v.curve_3d, orientation)?,
-        Entity::SeamCurve(v) => curve(s, edge_curve, v.curve_3d, orientation)?,
+        Entity::SurfaceCurve(v) => curve(s, edge, v.curve_3d, closed, dir, tol, stats)?,
+        Entity::SeamCurve(v) => curve(s, edge, v.curve_3d, closed, dir, tol, stats)?,
         // The Line type ignores pnt / dir and just uses u and v
         Entity::Line(_) => Curve::new_line(),
+        Entity::Polyline(p) => Curve::new_polyline(control_points_1d(s, &p.points), closed, dir),
+        // The edge's vertices already pick out the trimmed part of the basis
+        // curve, so we only need to account for its direction
+        Entity::TrimmedCurve(t) => curve(
+            s,
+            edge,
+            t.basis_curve,
+            closed,
+            dir == t.sense_agreement,
+            tol,
+            stats,
+        )?,
+        Entity::CompositeCurve(c) => {
+            Curve::new_polyline(composite_curve(s, edge, c, tol, stats)?, closed, dir)
+        }
         e => {
             warn!("Could not get edge from {:?}", e);
             return Err(Error::UnknownCurveType);
@@ -826,12 +1576,1503 @@ fn curve(
     })
 }
 
-fn vertex_point(s: &StepFile, v: Vertex) -> DVec3 {
-    cartesian_point(
-        s,
-        s.entity(v.cast::<VertexPoint_>())
-            .expect("Could not get VertexPoint")
-            .vertex_geometry
-            .cast(),
-    )
+/// Finds the point at one end of a trimmed curve on its basis curve,
+/// projecting cartesian trims onto the curve and evaluating parameter trims.
+/// If both are given, the trimmed curve's preference decides.
+fn trim_point(
+    s: &StepFile,
+    t: &TrimmedCurve_,
+    trim: &[TrimmingSelect],
+    basis: &Curve,
+) -> Result<DVec3, Error> {
+    let point = trim.iter().find_map(|t| match t {
+        TrimmingSelect::CartesianPoint(p) => Some(*p),
+        _ => None,
+    });
+    let param = trim.iter().find_map(|t| match t {
+        TrimmingSelect::ParameterValue(v) => Some(v.0),
+        _ => None,
+    });
+    let point = match (point, param) {
+        (Some(p), None) => Some(p),
+        (Some(p), Some(_)) if !matches!(t.master_representation, TrimmingPreference::Parameter) => {
+            Some(p)
+        }
+        _ => None,
+    };
+    if let Some(p) = point {
+        return Ok(basis.project(cartesian_point(s, p)));
+    }
+    let Some(param) = param else {
+        return Err(Error::InvalidTrim);
+    };
+    match basis.point_at(param) {
+        Some(p) => Ok(p),
+        // Lines don't keep their geometry, so we evaluate them here
+        None => match s.0.get(t.basis_curve.0) {
+            Some(Entity::Line(l)) => {
+                let v = get(s, l.dir, "Vector")?;
+                let d = direction(s, v.orientation).normalize() * v.magnitude.0;
+                Ok(cartesian_point(s, l.pnt) + d * param)
+            }
+            _ => Err(Error::InvalidTrim),
+        },
+    }
+}
+
+/// Samples a bounded curve (which may be a segment of a composite curve)
+/// from its start to its end, in its own direction
+fn bounded_curve(
+    s: &StepFile,
+    edge: usize,
+    curve_id: ap214::Curve,
+    tol: Option<f64>,
+    stats: &mut Stats,
+) -> Result<Vec<DVec3>, Error> {
+    let (basis, dir, (start, end)) = match s.0.get(curve_id.0) {
+        Some(Entity::TrimmedCurve(t)) => {
+            let basis = curve(s, edge, t.basis_curve, false, t.sense_agreement, tol, stats)?;
+            let start = trim_point(s, t, &t.trim_1, &basis)?;
+            let end = trim_point(s, t, &t.trim_2, &basis)?;
+            (t.basis_curve, t.sense_agreement, (start, end))
+        }
+        _ => {
+            let c = curve(s, edge, curve_id, false, true, tol, stats)?;
+            let ends = c.ends().ok_or(Error::UnboundedCurve(curve_id.0))?;
+            (curve_id, true, ends)
+        }
+    };
+    let closed = (start - end).norm() <= JOIN_TOLERANCE;
+    let c = curve(s, edge, basis, closed, dir, tol, stats)?;
+    Ok(c.build(start, end, tol))
+}
+
+/// Samples a composite curve into a single polyline, by joining up its
+/// segments.  Segments which are marked as continuous should start where
+/// the previous segment ends; if they don't, we warn about it (against the
+/// edge, since that's what the user will see) and bridge the gap.
+fn composite_curve(
+    s: &StepFile,
+    edge: usize,
+    c: &CompositeCurve_,
+    tol: Option<f64>,
+    stats: &mut Stats,
+) -> Result<Vec<DVec3>, Error> {
+    let mut out: Vec<DVec3> = vec![];
+    let mut continuous = false;
+    for seg_id in &c.segments {
+        let seg = get(s, *seg_id, "CompositeCurveSegment")?;
+        let mut pts = bounded_curve(s, edge, seg.parent_curve, tol, stats)?;
+        if !seg.same_sense {
+            pts.reverse();
+        }
+        if let Some(last) = out.last() {
+            let gap = (pts[0] - last).norm();
+            if gap <= JOIN_TOLERANCE {
+                pts.remove(0);
+            } else if continuous {
+                diagnose!(
+                    stats,
+                    Level::Warn,
+                    Some(edge),
+                    "Gap of {} before composite curve segment #{}",
+                    gap,
+                    seg_id.0
+                );
+            }
+        }
+        out.extend(pts);
+        continuous = !matches!(seg.transition, TransitionCode::Discontinuous);
+    }
+    // Snap the ends of closed curves together, so that they close exactly
+    if out.len() > 2 && (out[0] - out[out.len() - 1]).norm() <= JOIN_TOLERANCE {
+        let first = out[0];
+        *out.last_mut().unwrap() = first;
+    }
+    Ok(out)
+}
+
+fn vertex_point(s: &StepFile, v: Vertex) -> Result<DVec3, Error> {
+    let v = get(s, v.cast::<VertexPoint_>(), "VertexPoint")?;
+    Ok(cartesian_point(s, v.vertex_geometry.cast()))
+}
+
+/// Builds the parameterization which p-curves on a surface are written in,
+/// returning `None` for surfaces that we can't evaluate (which also means
+/// that their faces' p-curves aren't checked)
+fn parameterization<'a>(
+    s: &StepFile,
+    surf: ap214::Surface,
+    converted: &'a Surface,
+) -> Option<Parameterization<'a>> {
+    let frame = |p| {
+        let (location, axis, ref_direction) = axis2_placement_3d(s, p);
+        Frame::new(location, axis, ref_direction)
+    };
+    Some(match s.0.get(surf.0)? {
+        Entity::Plane(p) => Parameterization::Plane(frame(p.position)),
+        Entity::CylindricalSurface(c) => {
+            Parameterization::Cylinder(frame(c.position), c.radius.0 .0 .0)
+        }
+        Entity::ConicalSurface(c) => Parameterization::Cone {
+            frame: frame(c.position),
+            radius: c.radius.0,
+            semi_angle: c.semi_angle.0,
+        },
+        Entity::SphericalSurface(c) => {
+            Parameterization::Sphere(frame(c.position), c.radius.0 .0 .0)
+        }
+        Entity::ToroidalSurface(c) => Parameterization::Torus {
+            frame: frame(c.position),
+            major_radius: c.major_radius.0 .0 .0,
+            minor_radius: c.minor_radius.0 .0 .0,
+        },
+        Entity::BSplineSurfaceWithKnots(_) | Entity::ComplexEntity(_)
+            if matches!(converted, Surface::Bspline(_) | Surface::Nurbs(_)) =>
+        {
+            Parameterization::Spline(converted)
+        }
+        _ => return None,
+    })
+}
+
+fn point_2d(s: &StepFile, p: CartesianPoint) -> Option<DVec2> {
+    let p = s.entity(p)?;
+    Some(DVec2::new(
+        p.coordinates.first()?.0,
+        p.coordinates.get(1)?.0,
+    ))
+}
+
+/// Converts a curve in a surface's parameter space, returning `None` if it's
+/// not a type of curve that we can evaluate
+fn curve_2d(s: &StepFile, id: usize, stats: &mut Stats) -> Option<Curve2> {
+    Some(match s.0.get(id)? {
+        Entity::Line(l) => {
+            let v = s.entity(l.dir)?;
+            let d = &s.entity(v.orientation)?.direction_ratios;
+            let dir = DVec2::new(*d.first()?, *d.get(1)?);
+            Curve2::Line {
+                pnt: point_2d(s, l.pnt)?,
+                dir: dir.normalize() * v.magnitude.0,
+            }
+        }
+        Entity::Polyline(p) => Curve2::Polyline(
+            p.points
+                .iter()
+                .map(|p| point_2d(s, *p))
+                .collect::<Option<_>>()?,
+        ),
+        Entity::BSplineCurveWithKnots(c) => {
+            let pts = c
+                .control_points_list
+                .iter()
+                .map(|p| point_2d(s, *p))
+                .collect::<Option<_>>()?;
+            Curve2::Bspline(bspline_curve(id, c, pts, stats).ok()?)
+        }
+        Entity::ComplexEntity(v) if v.len() == 2 => {
+            let (Entity::BSplineCurveWithKnots(b), Entity::RationalBSplineCurve(r)) =
+                (&v[0], &v[1])
+            else {
+                return None;
+            };
+            let pts = b
+                .control_points_list
+                .iter()
+                .zip(r.weights_data.iter())
+                .map(|(p, w)| point_2d(s, *p).map(|p| DVec3::new(p.x * w, p.y * w, *w)))
+                .collect::<Option<_>>()?;
+            Curve2::Nurbs(bspline_curve(id, b, pts, stats).ok()?)
+        }
+        _ => return None,
+    })
+}
+
+/// Finds the p-curves which describe an edge on the given surface
+fn edge_pcurves(
+    s: &StepFile,
+    edge: usize,
+    surface: usize,
+    stats: &mut Stats,
+) -> Vec<(usize, Curve2)> {
+    let Some(Entity::EdgeCurve(e)) = s.0.get(edge) else {
+        return vec![];
+    };
+    let associated = match s.0.get(e.edge_geometry.0) {
+        Some(Entity::SurfaceCurve(c)) => &c.associated_geometry,
+        Some(Entity::SeamCurve(c)) => &c.associated_geometry,
+        _ => return vec![],
+    };
+    let mut out = vec![];
+    for g in associated {
+        let Some(Entity::Pcurve(p)) = s.0.get(g.0) else {
+            continue;
+        };
+        if p.basis_surface.0 != surface {
+            continue;
+        }
+        let Some(rep) = s.entity(p.reference_to_curve) else {
+            continue;
+        };
+        if let Some(c) = rep.items.iter().find_map(|i| curve_2d(s, i.0, stats)) {
+            out.push((g.0, c));
+        }
+    }
+    out
+}
+
+/// Checks the p-curves of a face bound's edges against the points sampled
+/// from those edges (see [`pcurve::check_loop`]), recording any repairs.
+/// Returns the position in parameter space of each point which is on an
+/// edge with a usable p-curve.
+fn pcurve_hints(
+    s: &StepFile,
+    surface: usize,
+    param: &Parameterization,
+    pts: &[DVec3],
+    edges: &[BoundEdge],
+    stats: &mut Stats,
+) -> Vec<Option<DVec2>> {
+    let loop_edges: Vec<LoopEdge> = edges
+        .iter()
+        .map(|e| LoopEdge {
+            pcurves: edge_pcurves(s, e.edge, surface, stats),
+            pts: &pts[e.points.clone()],
+            along: e.along,
+        })
+        .collect();
+    let (uvs, repairs) = pcurve::check_loop(param, &loop_edges);
+
+    let mut out = vec![None; pts.len()];
+    for (e, uv) in edges.iter().zip(uvs) {
+        for (i, uv) in e.points.clone().zip(uv.into_iter().flatten()) {
+            out[i] = Some(uv);
+        }
+    }
+    for (id, repair) in repairs {
+        match repair {
+            PcurveRepair::Discarded => diagnose!(
+                stats,
+                Level::Warn,
+                Some(id),
+                "P-curve #{} doesn't match its edge; projecting the edge instead",
+                id
+            ),
+            r => debug!("Applied {:?} repair to p-curve #{}", r, id),
+        }
+        stats.pcurve_repairs.push((id, repair));
+    }
+    out
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        // A solid whose only face is on an unsupported surface
+        let data = b"ISO-10303-21;
+HEADER;
+ENDSEC;
+DATA;
+#1=MANIFOLD_SOLID_BREP('',#2);
+#2=CLOSED_SHELL('',(#3));
+#3=ADVANCED_FACE('',(),#4,.T.);
+#4=SURFACE_OF_REVOLUTION('',#5,#6);
+ENDSEC;
+END-ISO-10303-21;
+";
+        let flat = StepFile::strip_flatten(data);
+        let step = StepFile::parse(&flat).unwrap();
+        let (mesh, stats) = triangulate(&step);
+        assert!(mesh.triangles.is_empty());
+        assert_eq!(stats.num_faces, 1);
+
+        // The failure is recorded, as well as being logged
+        assert_eq!(stats.diagnostics.len(), 1);
+        let d = &stats.diagnostics[0];
+        assert_eq!(d.level, Level::Error);
+        assert_eq!(d.entity, Some(3));
+        assert!(d.message.contains("Failed to triangulate"), "{}", d.message);
+    }
+
+    #[test]
+    fn test_entity_errors() {
+        let data = b"ISO-10303-21;
+HEADER;
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(0.,0.,0.));
+#2=CARTESIAN_POINT('',(1.,0.,0.));
+#3=VERTEX_POINT('',#1);
+#4=VERTEX_POINT('',#2);
+#5=B_SPLINE_CURVE_WITH_KNOTS('',1,(#1,#2),.UNSPECIFIED.,.F.,.F.,(2,1),(0.,1.),.UNSPECIFIED.);
+#6=EDGE_CURVE('',#3,#4,#5,.T.);
+#7=B_SPLINE_CURVE_WITH_KNOTS('',1,(#1,#2,#1),.UNSPECIFIED.,.F.,.F.,(2,2),(0.,1.),.UNSPECIFIED.);
+#8=EDGE_CURVE('',#3,#4,#7,.T.);
+#9=EDGE_CURVE('',#3,#20,#16,.T.);
+#10=ADVANCED_FACE('',(#11),#12,.T.);
+#12=PLANE('',#13);
+#13=AXIS2_PLACEMENT_3D('',#1,#14,#15);
+#14=DIRECTION('',(0.,0.,1.));
+#15=DIRECTION('',(1.,0.,0.));
+#16=LINE('',#1,#17);
+#17=VECTOR('',#14,1.);
+ENDSEC;
+END-ISO-10303-21;
+";
+        let flat = StepFile::strip_flatten(data);
+        let step = StepFile::parse(&flat).unwrap();
+        let edge_curve = |id| edge_curve(&step, Id::new(id), true, None, &mut Stats::default());
+
+        assert_eq!(
+            edge_curve(6).unwrap_err(),
+            Error::Nurbs {
+                id: 5,
+                source: nurbs::NurbsError::TooFewKnots {
+                    degree: 1,
+                    count: 3
+                }
+            }
+        );
+        assert_eq!(
+            edge_curve(8).unwrap_err(),
+            Error::Nurbs {
+                id: 7,
+                source: nurbs::NurbsError::KnotCount {
+                    degree: 1,
+                    control_points: 3,
+                    knots: 4
+                }
+            }
+        );
+        assert_eq!(
+            edge_curve(1).unwrap_err(),
+            Error::InvalidEntity {
+                id: 1,
+                expected: "EdgeCurve"
+            }
+        );
+        assert_eq!(
+            edge_curve(9).unwrap_err(),
+            Error::InvalidEntity {
+                id: 20,
+                expected: "VertexPoint"
+            }
+        );
+
+        // Missing entities are errors, rather than panics
+        let mut mesh = Mesh::default();
+        let mut stats = Stats::default();
+        assert_eq!(
+            advanced_face(
+                &step,
+                Id::new(10),
+                None,
+                None,
+                MeshTopology::Triangles,
+                &mut mesh,
+                &mut stats
+            )
+            .unwrap_err(),
+            Error::InvalidEntity {
+                id: 11,
+                expected: "FaceBound"
+            }
+        );
+    }
+
+    /// Writes a single face on the XY plane, bounded by a straight edge from
+    /// (0, 0) to (2, 0) and a b-spline edge (`#6`) coming back, which runs
+    /// from #1 to #2 through the control point #3 at (1, 2).
+    fn open_spline_face(spline: &str) -> String {
+        format!(
+            "ISO-10303-21;
+HEADER;
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(0.,0.,0.));
+#2=CARTESIAN_POINT('',(2.,0.,0.));
+#3=CARTESIAN_POINT('',(1.,2.,0.));
+#4=VERTEX_POINT('',#1);
+#5=VERTEX_POINT('',#2);
+#6={};
+#7=EDGE_CURVE('',#4,#5,#6,.T.);
+#8=DIRECTION('',(1.,0.,0.));
+#9=VECTOR('',#8,2.);
+#10=LINE('',#1,#9);
+#11=EDGE_CURVE('',#4,#5,#10,.T.);
+#12=ORIENTED_EDGE('',*,*,#11,.T.);
+#13=ORIENTED_EDGE('',*,*,#7,.F.);
+#14=EDGE_LOOP('',(#12,#13));
+#15=FACE_OUTER_BOUND('',#14,.T.);
+#16=DIRECTION('',(0.,0.,1.));
+#17=AXIS2_PLACEMENT_3D('',#1,#16,#8);
+#18=PLANE('',#17);
+#19=ADVANCED_FACE('',(#15),#18,.T.);
+#20=CLOSED_SHELL('',(#19));
+#21=MANIFOLD_SOLID_BREP('',#20);
+ENDSEC;
+END-ISO-10303-21;
+",
+            spline
+        )
+    }
+
+    /// Writes a single face on the XY plane, bounded by one edge on a
+    /// closed, periodic cubic (`#9`) which loops around the origin.  The
+    /// curve's knots are uniform and unclamped, and its last three control
+    /// points repeat the first three; its seam is at (0, 4/3).
+    fn closed_spline_face(self_intersect: bool) -> String {
+        format!(
+            "ISO-10303-21;
+HEADER;
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(2.,0.,0.));
+#2=CARTESIAN_POINT('',(0.,2.,0.));
+#3=CARTESIAN_POINT('',(-2.,0.,0.));
+#4=CARTESIAN_POINT('',(0.,-2.,0.));
+#5=CARTESIAN_POINT('',(0.,0.,0.));
+#6=CARTESIAN_POINT('',(0.,1.3333333333333333,0.));
+#7=VERTEX_POINT('',#6);
+#9=B_SPLINE_CURVE_WITH_KNOTS('',3,(#1,#2,#3,#4,#1,#2,#3),.UNSPECIFIED.,.T.,{},\
+(1,1,1,1,1,1,1,1,1,1,1),(0.,1.,2.,3.,4.,5.,6.,7.,8.,9.,10.),.UNIFORM_KNOTS.);
+#10=EDGE_CURVE('',#7,#7,#9,.T.);
+#11=ORIENTED_EDGE('',*,*,#10,.T.);
+#12=EDGE_LOOP('',(#11));
+#13=FACE_OUTER_BOUND('',#12,.T.);
+#14=DIRECTION('',(0.,0.,1.));
+#15=DIRECTION('',(1.,0.,0.));
+#16=AXIS2_PLACEMENT_3D('',#5,#14,#15);
+#17=PLANE('',#16);
+#18=ADVANCED_FACE('',(#13),#17,.T.);
+#19=CLOSED_SHELL('',(#18));
+#20=MANIFOLD_SOLID_BREP('',#19);
+ENDSEC;
+END-ISO-10303-21;
+",
+            if self_intersect { ".T." } else { ".F." }
+        )
+    }
+
+    #[test]
+    fn test_open_spline() {
+        let (mesh, stats) = triangulate_text(&open_spline_face(
+            "B_SPLINE_CURVE_WITH_KNOTS('',2,(#1,#3,#2),.UNSPECIFIED.,.F.,.F.,\
+             (3,3),(0.,1.),.UNSPECIFIED.)",
+        ));
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+
+        // The straight edge has two points, and the curve has 9 (with its
+        // ends shared), so there are 9 points on the boundary
+        assert_eq!(mesh.triangles.len(), 7);
+        let ys: Vec<f64> = mesh.verts.iter().map(|v| v.pos.y).collect();
+        assert!(ys.iter().all(|y| (0.0..=1.0).contains(y)), "{:?}", ys);
+        assert!(ys.iter().any(|y| (y - 1.0).abs() < 1e-12), "{:?}", ys);
+    }
+
+    #[test]
+    fn test_closed_spline() {
+        let (mesh, stats) = triangulate_text(&closed_spline_face(false));
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+
+        // The edge walks once around the whole curve, starting and ending
+        // at the seam, with 8 points per knot span
+        assert_eq!(mesh.verts.len(), 4 * 8);
+        assert_eq!(mesh.triangles.len(), 4 * 8 - 2);
+        for v in &mesh.verts {
+            let r = v.pos.xy().norm();
+            assert!(r > 1.0 && r < 1.5, "{:?}", v.pos);
+        }
+
+        // Walking the seam the other way gives the same loop
+        let text = closed_spline_face(false).replace(
+            "#11=ORIENTED_EDGE('',*,*,#10,.T.)",
+            "#11=ORIENTED_EDGE('',*,*,#10,.F.)",
+        );
+        let (mesh, _) = triangulate_text(&text);
+        assert_eq!(mesh.verts.len(), 4 * 8);
+    }
+
+    #[test]
+    fn test_self_intersecting_spline() {
+        // Self-intersection is worth mentioning, but doesn't stop us from
+        // triangulating the face
+        let (mesh, stats) = triangulate_text(&closed_spline_face(true));
+        assert_eq!(mesh.triangles.len(), 4 * 8 - 2);
+        assert_eq!(stats.diagnostics.len(), 1);
+        let d = &stats.diagnostics[0];
+        assert_eq!(d.level, Level::Warn);
+        assert_eq!(d.entity, Some(9));
+        assert!(d.message.contains("self-intersecting"), "{}", d.message);
+    }
+
+    #[test]
+    fn test_inconsistent_multiplicities() {
+        // Three control points with a degree of two need six knots, but the
+        // multiplicities add up to seven
+        let text = open_spline_face(
+            "B_SPLINE_CURVE_WITH_KNOTS('',2,(#1,#3,#2),.UNSPECIFIED.,.F.,.F.,\
+             (3,1,3),(0.,0.5,1.),.UNSPECIFIED.)",
+        );
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let mut mesh = Mesh::default();
+        let mut stats = Stats::default();
+        assert_eq!(
+            advanced_face(
+                &step,
+                Id::new(19),
+                None,
+                None,
+                MeshTopology::Triangles,
+                &mut mesh,
+                &mut stats
+            )
+            .unwrap_err(),
+            Error::Nurbs {
+                id: 6,
+                source: nurbs::NurbsError::KnotCount {
+                    degree: 2,
+                    control_points: 3,
+                    knots: 7
+                }
+            }
+        );
+
+        // Triangulating the whole file reports the error
+        let (mesh, stats) = triangulate(&step);
+        assert!(mesh.triangles.is_empty());
+        assert_eq!(stats.num_panics, 0);
+        assert_eq!(stats.diagnostics.len(), 1);
+        assert_eq!(stats.diagnostics[0].level, Level::Error);
+    }
+
+    /// Writes a single face on an unsupported surface, bounded by polygons.
+    /// The first polygon is the outer bound.  Returns the text and the
+    /// face's entity index.
+    fn polygon_face(loops: &[Vec<DVec3>]) -> (String, usize) {
+        fn push(ents: &mut Vec<String>, e: String) -> usize {
+            ents.push(e);
+            ents.len()
+        }
+        let mut ents = vec!["SURFACE_OF_REVOLUTION('',#100,#100)".to_owned()];
+        let mut bounds = vec![];
+        for (i, pts) in loops.iter().enumerate() {
+            let verts: Vec<(usize, usize)> = pts
+                .iter()
+                .map(|p| {
+                    let c = format!("CARTESIAN_POINT('',({:.6},{:.6},{:.6}))", p.x, p.y, p.z);
+                    let c = push(&mut ents, c);
+                    (c, push(&mut ents, format!("VERTEX_POINT('',#{})", c)))
+                })
+                .collect();
+            let mut edges = vec![];
+            for j in 0..pts.len() {
+                let k = (j + 1) % pts.len();
+                let d = pts[k] - pts[j];
+                let n = d.normalize();
+                let dir = format!("DIRECTION('',({:.6},{:.6},{:.6}))", n.x, n.y, n.z);
+                let dir = push(&mut ents, dir);
+                let vec = push(&mut ents, format!("VECTOR('',#{},{:.6})", dir, d.norm()));
+                let line = push(&mut ents, format!("LINE('',#{},#{})", verts[j].0, vec));
+                let e = format!(
+                    "EDGE_CURVE('',#{},#{},#{},.T.)",
+                    verts[j].1, verts[k].1, line
+                );
+                let e = push(&mut ents, e);
+                edges.push(format!(
+                    "#{}",
+                    push(&mut ents, format!("ORIENTED_EDGE('',*,*,#{},.T.)", e))
+                ));
+            }
+            let l = push(&mut ents, format!("EDGE_LOOP('',({}))", edges.join(",")));
+            let kind = if i == 0 {
+                "FACE_OUTER_BOUND"
+            } else {
+                "FACE_BOUND"
+            };
+            bounds.push(format!(
+                "#{}",
+                push(&mut ents, format!("{}('',#{},.T.)", kind, l))
+            ));
+        }
+        let face = format!("ADVANCED_FACE('',({}),#1,.T.)", bounds.join(","));
+        let face = push(&mut ents, face);
+        let shell = push(&mut ents, format!("CLOSED_SHELL('',(#{}))", face));
+        push(&mut ents, format!("MANIFOLD_SOLID_BREP('',#{})", shell));
+
+        let mut out = "ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n".to_owned();
+        for (i, e) in ents.iter().enumerate() {
+            out += &format!("#{}={};\n", i + 1, e);
+        }
+        out += "ENDSEC;\nEND-ISO-10303-21;\n";
+        (out, face)
+    }
+
+    #[test]
+    fn test_fallback_planar() {
+        // A square with a square hole, in a tilted plane, on a surface that
+        // we can't evaluate.  The outer loop winds counter-clockwise around
+        // the plane's normal, and the hole winds the other way.
+        let n = DVec3::new(1.0, 1.0, 1.0).normalize();
+        let (a, b) = (DVec3::new(1.0, -1.0, 0.0).normalize(), n);
+        let b = b.cross(&a);
+        let square = |r: f64| -> Vec<DVec3> {
+            [(-r, -r), (r, -r), (r, r), (-r, r)]
+                .iter()
+                .map(|(x, y)| DVec3::new(5.0, 0.0, 0.0) + a * *x + b * *y)
+                .collect()
+        };
+        let mut hole = square(1.0);
+        hole.reverse();
+        let (text, face) = polygon_face(&[square(2.0), hole]);
+        let (mesh, stats) = triangulate_text(&text);
+
+        assert_eq!(stats.num_faces, 1);
+        assert_eq!(stats.num_fallbacks, 1);
+        assert_eq!(stats.num_errors + stats.num_panics, 0);
+        assert_eq!(stats.surface_types[&face], SurfaceType::FallbackPlanar);
+        assert_eq!(stats.diagnostics.len(), 1, "{:?}", stats.diagnostics);
+        assert_eq!(stats.diagnostics[0].level, Level::Warn);
+        assert_eq!(stats.diagnostics[0].entity, Some(face));
+
+        // Eight points make a ring of eight triangles, facing along the
+        // plane's normal
+        assert_eq!(mesh.verts.len(), 8);
+        assert_eq!(mesh.triangles.len(), 8);
+        for v in &mesh.verts {
+            assert!((v.norm - n).norm() < 1e-6, "{:?}", v.norm);
+        }
+        for t in &mesh.triangles {
+            let p = |i: usize| mesh.verts[t.verts[i] as usize].pos;
+            let tri_normal = (p(1) - p(0)).cross(&(p(2) - p(0)));
+            assert!(tri_normal.dot(&n) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_fallback_non_planar() {
+        // One turn of a helix, closed by a straight edge back to the start,
+        // is nowhere near planar
+        let helix: Vec<DVec3> = (0..12)
+            .map(|i| {
+                let t = i as f64 / 12.0;
+                let a = t * 2.0 * std::f64::consts::PI;
+                DVec3::new(a.cos(), a.sin(), 2.0 * t)
+            })
+            .collect();
+        let (text, face) = polygon_face(&[helix]);
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let mut mesh = Mesh::default();
+        let mut stats = Stats::default();
+        assert_eq!(
+            advanced_face(
+                &step,
+                Id::new(face),
+                None,
+                None,
+                MeshTopology::Triangles,
+                &mut mesh,
+                &mut stats
+            )
+            .unwrap_err(),
+            Error::NonPlanarFallback(1)
+        );
+
+        let (mesh, stats) = triangulate(&step);
+        assert!(mesh.triangles.is_empty());
+        assert_eq!(stats.num_fallbacks, 0);
+        assert!(!stats.surface_types.contains_key(&face));
+        assert_eq!(stats.diagnostics.len(), 1);
+        assert_eq!(stats.diagnostics[0].level, Level::Error);
+    }
+
+    /// Wraps a set of entities into a STEP file with a single face on the
+    /// XY plane, bounded by the given oriented edges
+    fn edge_loop_face(entities: &str, edges: &str) -> String {
+        format!(
+            "ISO-10303-21;
+HEADER;
+ENDSEC;
+DATA;
+{}
+#100=EDGE_LOOP('',({}));
+#101=FACE_OUTER_BOUND('',#100,.T.);
+#102=CARTESIAN_POINT('',(0.,0.,0.));
+#103=DIRECTION('',(0.,0.,1.));
+#104=DIRECTION('',(1.,0.,0.));
+#105=AXIS2_PLACEMENT_3D('',#102,#103,#104);
+#106=PLANE('',#105);
+#107=ADVANCED_FACE('',(#101),#106,.T.);
+#108=CLOSED_SHELL('',(#107));
+#109=MANIFOLD_SOLID_BREP('',#108);
+ENDSEC;
+END-ISO-10303-21;
+",
+            entities, edges
+        )
+    }
+
+    /// Samples the boundary of a face built by [`edge_loop_face`], checking
+    /// that it closes up, then triangulates the whole file
+    fn edge_loop_points(text: &str) -> (Vec<DVec3>, Mesh, Stats) {
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let (pts, _) = face_bound(&step, Id::new(101), None, &mut Stats::default()).unwrap();
+        assert!((pts[0] - pts[pts.len() - 1]).norm() < 1e-9, "{:?}", pts);
+        let (mesh, stats) = triangulate(&step);
+        (pts, mesh, stats)
+    }
+
+    /// Circle of radius 1 around the origin (`#9`), the line y = 0 (`#13`),
+    /// and vertices at (1, 0) (`#3`) and (-1, 0) (`#4`)
+    const CIRCLE_AND_LINE: &str = "#1=CARTESIAN_POINT('',(1.,0.,0.));
+#2=CARTESIAN_POINT('',(-1.,0.,0.));
+#3=VERTEX_POINT('',#1);
+#4=VERTEX_POINT('',#2);
+#5=CARTESIAN_POINT('',(0.,0.,0.));
+#6=DIRECTION('',(0.,0.,1.));
+#7=DIRECTION('',(1.,0.,0.));
+#8=AXIS2_PLACEMENT_3D('',#5,#6,#7);
+#9=CIRCLE('',#8,1.);
+#12=VECTOR('',#7,1.);
+#13=LINE('',#2,#12);";
+
+    #[test]
+    fn test_polyline_edges() {
+        // Two edges on the same closed polyline around a 2 x 1 rectangle,
+        // the second of which wraps around from the end back to the start
+        let text = edge_loop_face(
+            "#1=CARTESIAN_POINT('',(0.,0.,0.));
+#2=CARTESIAN_POINT('',(2.,0.,0.));
+#3=CARTESIAN_POINT('',(2.,1.,0.));
+#4=CARTESIAN_POINT('',(0.,1.,0.));
+#5=VERTEX_POINT('',#1);
+#6=POLYLINE('',(#1,#2,#3,#4,#1));
+#7=EDGE_CURVE('',#5,#9,#6,.T.);
+#8=ORIENTED_EDGE('',*,*,#7,.T.);
+#9=VERTEX_POINT('',#3);
+#10=EDGE_CURVE('',#9,#5,#6,.T.);
+#11=ORIENTED_EDGE('',*,*,#10,.T.);",
+            "#8,#11",
+        );
+        let (pts, mesh, stats) = edge_loop_points(&text);
+        let expected = [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0), (0.0, 0.0)];
+        assert_eq!(pts.len(), expected.len());
+        for (p, (x, y)) in pts.iter().zip(expected) {
+            assert_eq!(*p, DVec3::new(x, y, 0.0));
+        }
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(mesh.triangles.len(), 2);
+    }
+
+    #[test]
+    fn test_trimmed_edges() {
+        // The top half of the circle (trimmed by parameter), then back along
+        // the line (trimmed by points)
+        let entities = format!(
+            "{}
+#10=TRIMMED_CURVE('',#9,(PARAMETER_VALUE(0.)),\
+(PARAMETER_VALUE(3.141592653589793)),.T.,.PARAMETER.);
+#11=EDGE_CURVE('',#3,#4,#10,.T.);
+#14=TRIMMED_CURVE('',#13,(#2),(#1),.T.,.CARTESIAN.);
+#15=EDGE_CURVE('',#4,#3,#14,.T.);
+#16=ORIENTED_EDGE('',*,*,#11,.T.);
+#17=ORIENTED_EDGE('',*,*,#15,.T.);",
+            CIRCLE_AND_LINE
+        );
+        let (pts, mesh, stats) = edge_loop_points(&edge_loop_face(&entities, "#16,#17"));
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(pts.len(), 33);
+        assert_eq!(mesh.triangles.len(), 30);
+        assert!(pts.iter().all(|p| p.y >= 0.0 && (p.norm() - 1.0) < 1e-9));
+
+        // If the trimmed curve runs against the circle, the edge takes the
+        // bottom half instead
+        let text =
+            edge_loop_face(&entities, "#16,#17").replace(".T.,.PARAMETER.", ".F.,.PARAMETER.");
+        let (pts, _, _) = edge_loop_points(&text);
+        assert_eq!(pts.len(), 33);
+        assert!(pts.iter().all(|p| p.y <= 0.0), "{:?}", pts);
+    }
+
+    #[test]
+    fn test_composite_edge() {
+        // A single closed edge on a composite curve: the top half of the
+        // circle, then the line from (1, 0) to (-1, 0) walked backwards.  The
+        // line's trims mix a parameter and a point.
+        let composite = |end: &str| {
+            let entities = format!(
+                "{}
+#10=TRIMMED_CURVE('',#9,(PARAMETER_VALUE(0.)),\
+(PARAMETER_VALUE(3.141592653589793)),.T.,.PARAMETER.);
+#14=TRIMMED_CURVE('',#13,(PARAMETER_VALUE(2.)),({}),.T.,.UNSPECIFIED.);
+#15=COMPOSITE_CURVE_SEGMENT(.CONTINUOUS.,.T.,#10);
+#16=COMPOSITE_CURVE_SEGMENT(.CONTINUOUS.,.F.,#14);
+#17=COMPOSITE_CURVE('',(#15,#16),.F.);
+#18=EDGE_CURVE('',#3,#3,#17,.T.);
+#19=ORIENTED_EDGE('',*,*,#18,.T.);
+#20=CARTESIAN_POINT('',(-0.9,0.,0.));",
+                CIRCLE_AND_LINE, end
+            );
+            edge_loop_points(&edge_loop_face(&entities, "#19"))
+        };
+        let (pts, mesh, stats) = composite("#2");
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(pts.len(), 33);
+        assert_eq!(mesh.triangles.len(), 30);
+        assert!(pts.iter().all(|p| p.y >= 0.0 && (p.norm() - 1.0) < 1e-9));
+
+        // If the line stops short, the gap is bridged, and the warning
+        // points at the edge
+        let (pts, mesh, stats) = composite("#20");
+        assert_eq!(pts.len(), 34);
+        assert_eq!(mesh.triangles.len(), 31);
+        assert_eq!(stats.diagnostics.len(), 1, "{:?}", stats.diagnostics);
+        let d = &stats.diagnostics[0];
+        assert_eq!(d.level, Level::Warn);
+        assert_eq!(d.entity, Some(18));
+        assert!(d.message.ends_with("segment #16"), "{}", d.message);
+    }
+
+    #[test]
+    fn test_conic_edges() {
+        // The region between a conic (#6) and the vertical line x = #1,
+        // which crosses the conic at y = ±#2
+        let conic_face = |conic: &str, x: f64, y: f64| {
+            let entities = format!(
+                "#1=CARTESIAN_POINT('',({x:?},{:?},0.));
+#2=CARTESIAN_POINT('',({x:?},{y:?},0.));
+#3=VERTEX_POINT('',#1);
+#4=VERTEX_POINT('',#2);
+#5=AXIS2_PLACEMENT_3D('',#102,#103,#104);
+#6={conic};
+#7=EDGE_CURVE('',#3,#4,#6,.T.);
+#8=DIRECTION('',(0.,1.,0.));
+#9=VECTOR('',#8,1.);
+#10=LINE('',#1,#9);
+#11=EDGE_CURVE('',#3,#4,#10,.T.);
+#12=ORIENTED_EDGE('',*,*,#11,.T.);
+#13=ORIENTED_EDGE('',*,*,#7,.F.);",
+                -y
+            );
+            edge_loop_points(&edge_loop_face(&entities, "#12,#13"))
+        };
+
+        // y² = 4x, from its vertex at the origin
+        let (pts, mesh, stats) = conic_face("PARABOLA('',#5,1.)", 1.0, 2.0);
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert!(pts.len() > 8, "{:?}", pts);
+        assert_eq!(mesh.triangles.len(), pts.len() - 3);
+        for p in &pts[1..pts.len() - 1] {
+            assert!((p.y * p.y - 4.0 * p.x).abs() < 1e-9, "{:?}", p);
+        }
+
+        // x² - y² = 1, centered on the origin
+        let (pts, mesh, stats) = conic_face("HYPERBOLA('',#5,1.,1.)", 2f64.sqrt(), 1.0);
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert!(pts.len() > 8, "{:?}", pts);
+        assert_eq!(mesh.triangles.len(), pts.len() - 3);
+        for p in &pts[1..pts.len() - 1] {
+            assert!((p.x * p.x - p.y * p.y - 1.0).abs() < 1e-9, "{:?}", p);
+        }
+    }
+
+    /// Writes an edge from vertex `#a` to `#b` on the 3D curve `#curve`, with
+    /// a p-curve on the face's surface (`#106`) that's the 2D line through
+    /// `pnt` along `dir`.  Entities are numbered from `id`, with the oriented
+    /// edge (which runs forward if `forward` is set) last, at `id + 8`.
+    fn pcurve_edge(
+        id: usize,
+        (a, b, curve): (usize, usize, usize),
+        pnt: [f64; 2],
+        dir: [f64; 2],
+        forward: bool,
+    ) -> String {
+        let len = (dir[0] * dir[0] + dir[1] * dir[1]).sqrt();
+        format!(
+            "#{}=CARTESIAN_POINT('',({:?},{:?}));
+#{}=DIRECTION('',({:?},{:?}));
+#{}=VECTOR('',#{},{:?});
+#{}=LINE('',#{},#{});
+#{}=DEFINITIONAL_REPRESENTATION('',(#{}),#99);
+#{}=PCURVE('',#106,#{});
+#{}=SURFACE_CURVE('',#{},(#{}),.PCURVE_S1.);
+#{}=EDGE_CURVE('',#{},#{},#{},.T.);
+#{}=ORIENTED_EDGE('',*,*,#{},{});",
+            id,
+            pnt[0],
+            pnt[1],
+            id + 1,
+            dir[0] / len,
+            dir[1] / len,
+            id + 2,
+            id + 1,
+            len,
+            id + 3,
+            id,
+            id + 2,
+            id + 4,
+            id + 3,
+            id + 5,
+            id + 4,
+            id + 6,
+            curve,
+            id + 5,
+            id + 7,
+            a,
+            b,
+            id + 6,
+            id + 8,
+            id + 7,
+            if forward { ".T." } else { ".F." },
+        )
+    }
+
+    #[test]
+    fn test_reversed_pcurve() {
+        // A unit square on the XY plane, where the p-curve of the right-hand
+        // edge runs from top to bottom (while its edge goes upwards)
+        let corners = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        let mut entities = vec![];
+        let mut edges = vec![];
+        for (i, c) in corners.iter().enumerate() {
+            let next = corners[(i + 1) % 4];
+            let d = [next[0] - c[0], next[1] - c[1]];
+            let (pnt, dir) = if i == 1 {
+                (next, [-d[0], -d[1]])
+            } else {
+                (*c, d)
+            };
+            let id = 10 * (i + 1);
+            entities.push(format!(
+                "#{}=CARTESIAN_POINT('',({:?},{:?},0.));
+#{}=VERTEX_POINT('',#{});
+#{}=DIRECTION('',({:?},{:?},0.));
+#{}=VECTOR('',#{},1.);
+#{}=LINE('',#{},#{});",
+                i + 1,
+                c[0],
+                c[1],
+                i + 5,
+                i + 1,
+                id + 50,
+                d[0],
+                d[1],
+                id + 51,
+                id + 50,
+                id + 52,
+                i + 1,
+                id + 51,
+            ));
+            let b = (i + 1) % 4 + 5;
+            entities.push(pcurve_edge(200 + id, (i + 5, b, id + 52), pnt, dir, true));
+            edges.push(format!("#{}", 208 + id));
+        }
+        let text = edge_loop_face(&entities.join("\n"), &edges.join(","));
+        let (_, mesh, stats) = edge_loop_points(&text);
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(stats.pcurve_repairs, [(225, PcurveRepair::Reversed)]);
+        assert_eq!(mesh.triangles.len(), 2);
+    }
+
+    #[test]
+    fn test_shifted_pcurve() {
+        // Half of a cylinder's side: around the bottom from (1, 0, 0) to
+        // (-1, 0, 0), up, back around the top, then down again.  The top's
+        // p-curve is either in line with the others, or a period away.
+        let half_cylinder = |top_u: f64| {
+            let entities = [
+                format!(
+                    "{}
+#20=CARTESIAN_POINT('',(-1.,0.,1.));
+#21=CARTESIAN_POINT('',(1.,0.,1.));
+#22=VERTEX_POINT('',#20);
+#23=VERTEX_POINT('',#21);
+#24=CARTESIAN_POINT('',(0.,0.,1.));
+#25=AXIS2_PLACEMENT_3D('',#24,#6,#7);
+#26=CIRCLE('',#25,1.);
+#27=VECTOR('',#6,1.);
+#28=LINE('',#1,#27);
+#29=LINE('',#2,#27);",
+                    CIRCLE_AND_LINE
+                ),
+                pcurve_edge(30, (3, 4, 9), [0.0, 0.0], [1.0, 0.0], true),
+                pcurve_edge(
+                    40,
+                    (4, 22, 29),
+                    [std::f64::consts::PI, 0.0],
+                    [0.0, 1.0],
+                    true,
+                ),
+                pcurve_edge(50, (23, 22, 26), [top_u, 1.0], [1.0, 0.0], false),
+                pcurve_edge(60, (3, 23, 28), [0.0, 0.0], [0.0, 1.0], false),
+            ];
+            edge_loop_face(&entities.join("\n"), "#38,#48,#58,#68")
+                .replace("PLANE('',#105)", "CYLINDRICAL_SURFACE('',#105,1.)")
+        };
+
+        let (pts, expected, stats) = edge_loop_points(&half_cylinder(0.0));
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert!(
+            stats.pcurve_repairs.is_empty(),
+            "{:?}",
+            stats.pcurve_repairs
+        );
+        assert!(!expected.triangles.is_empty());
+        for p in &pts {
+            assert!(
+                p.y >= -1e-9 && (p.xy().norm() - 1.0).abs() < 1e-9,
+                "{:?}",
+                p
+            );
+        }
+
+        let (_, mesh, stats) = edge_loop_points(&half_cylinder(2.0 * std::f64::consts::PI));
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(stats.pcurve_repairs, [(55, PcurveRepair::Shifted)]);
+        assert_eq!(mesh.triangles.len(), expected.triangles.len());
+        assert_eq!(mesh.verts.len(), expected.verts.len());
+    }
+
+    /// Triangulates a generated STEP file
+    fn triangulate_text(text: &str) -> (Mesh, Stats) {
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        triangulate(&step)
+    }
+
+    /// Returns the bounding box of a part's vertices
+    fn part_bounds(mesh: &Mesh, part: &Part) -> (DVec3, DVec3) {
+        mesh.triangles[part.triangles.clone()]
+            .iter()
+            .flat_map(|t| t.verts.iter())
+            .map(|i| mesh.verts[*i as usize].pos)
+            .fold(
+                (DVec3::repeat(f64::INFINITY), DVec3::repeat(-f64::INFINITY)),
+                |(lo, hi), p| (lo.inf(&p), hi.sup(&p)),
+            )
+    }
+
+    #[test]
+    fn test_fixtures() {
+        // Without a tolerance, a full circle is sampled with 64 points, so
+        // half and quarter circles have 32 and 16.  Each face is bounded by
+        // a single loop, so a face with n points on its boundary has n - 2
+        // triangles.
+        for (part, triangles) in [
+            (step::fixture::cube(10.0), 6 * 2),
+            // Two caps with 62 points, and two half-cylinders with 64
+            (step::fixture::cylinder(2.0, 5.0), 2 * 60 + 2 * 62),
+            // Two caps with 64 points, four flat sides with 4, and four
+            // fillets with 32
+            (
+                step::fixture::filleted_block(10.0, 2.0),
+                2 * 62 + 4 * 2 + 4 * 30,
+            ),
+        ] {
+            let (mesh, stats) = triangulate_text(&part.to_step());
+            assert_eq!(stats.num_faces, part.num_faces());
+            assert_eq!(stats.num_errors + stats.num_panics, 0);
+            assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+            assert_eq!(mesh.triangles.len(), triangles, "{:?}", part);
+            assert_eq!(mesh.parts.len(), 1);
+            assert_eq!(mesh.open_edges(), 0);
+        }
+
+        // A finer tolerance means more triangles on curved faces
+        let text = step::fixture::cylinder(2.0, 5.0).to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let (coarse, _) = triangulate_with_tolerance(&step, Some(1e-2), &|_, _| ());
+        let (fine, _) = triangulate_with_tolerance(&step, Some(1e-4), &|_, _| ());
+        assert!(fine.triangles.len() > coarse.triangles.len());
+    }
+
+    #[test]
+    fn test_fixture_assembly() {
+        use step::fixture;
+        let asm = fixture::assembly(vec![
+            fixture::cube(1.0).color([1.0, 0.0, 0.0]),
+            fixture::cylinder(1.0, 2.0).translate([5.0, 0.0, 0.0]),
+        ]);
+        let (mesh, stats) = triangulate_text(&asm.to_step());
+        assert_eq!(stats.num_faces, asm.num_faces());
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(mesh.parts.len(), 2);
+        assert_eq!(mesh.open_edges(), 0);
+
+        // Parts are placed by their transforms, and keep their own colors
+        let mut parts = mesh.parts.clone();
+        parts.sort_by_key(|p| p.triangles.len());
+        let (cube, cylinder) = (&parts[0], &parts[1]);
+        assert_eq!(cube.triangles.len(), 12);
+        let (lo, hi) = part_bounds(&mesh, cube);
+        assert!((lo - DVec3::zeros()).norm() < 1e-9 && (hi - DVec3::repeat(1.0)).norm() < 1e-9);
+        let (lo, hi) = part_bounds(&mesh, cylinder);
+        assert!((lo.x - 4.0).abs() < 1e-9 && (hi.x - 6.0).abs() < 1e-9);
+        assert!(lo.z.abs() < 1e-9 && (hi.z - 2.0).abs() < 1e-9);
+
+        let color =
+            |p: &Part| mesh.verts[mesh.triangles[p.triangles.start].verts[0] as usize].color;
+        assert_eq!(color(cube), DVec3::new(1.0, 0.0, 0.0));
+        assert_eq!(color(cylinder), DVec3::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_retriangulate_faces() {
+        use step::fixture;
+        let asm = fixture::assembly(vec![
+            fixture::cube(1.0),
+            fixture::cylinder(1.0, 2.0)
+                .translate([5.0, 0.0, 0.0])
+                .color([0.0, 0.0, 1.0]),
+        ]);
+        let flat = StepFile::strip_flatten(asm.to_step().as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let (mut mesh, stats) = triangulate_with_tolerance(&step, Some(1e-2), &|_, _| ());
+        let (fine, _) = triangulate_with_tolerance(&step, Some(1e-4), &|_, _| ());
+        let target = stats
+            .surface_types
+            .iter()
+            .find(|(_, t)| **t == SurfaceType::Cylinder)
+            .map(|(id, _)| *id)
+            .unwrap();
+
+        // Returns the corners of each of a face's triangles
+        let face_tris = |mesh: &Mesh, id: usize| -> Vec<[DVec3; 3]> {
+            let f = mesh.faces.iter().find(|f| f.id == id).unwrap();
+            mesh.triangles[f.triangles.clone()]
+                .iter()
+                .map(|t| [0, 1, 2].map(|i| mesh.verts[t.verts[i] as usize].pos))
+                .collect()
+        };
+        let before: HashMap<usize, _> = mesh
+            .faces
+            .iter()
+            .map(|f| (f.id, face_tris(&mesh, f.id)))
+            .collect();
+        let num_triangles = mesh.triangles.len();
+
+        let opts = TriangulateOptions {
+            tolerance: Some(1e-4),
+            ..Default::default()
+        };
+        let stats = retriangulate_faces(&step, &mut mesh, &[target], &opts);
+        assert_eq!(stats.num_faces, 1);
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+
+        // Other faces keep exactly the same triangles
+        assert_eq!(mesh.faces.len(), before.len());
+        for (id, tris) in &before {
+            if *id != target {
+                assert_eq!(face_tris(&mesh, *id), *tris, "face #{}", id);
+            }
+        }
+
+        // The target face matches a finer triangulation of the whole model,
+        // placed and colored like the rest of its part
+        let new = face_tris(&mesh, target);
+        assert_eq!(new.len(), face_tris(&fine, target).len());
+        assert!(new.len() > before[&target].len());
+        assert_eq!(
+            mesh.triangles.len(),
+            num_triangles - before[&target].len() + new.len()
+        );
+        for p in new.iter().flatten() {
+            assert!((p.xy() - DVec2::new(5.0, 0.0)).norm() < 1.0 + 1e-9);
+        }
+        let f = mesh.faces.iter().find(|f| f.id == target).unwrap();
+        let v = mesh.triangles[f.triangles.start].verts[0] as usize;
+        assert_eq!(mesh.verts[v].color, DVec3::new(0.0, 0.0, 1.0));
+
+        // Parts still cover every triangle, in order
+        let mut end = 0;
+        let mut parts = mesh.parts.clone();
+        parts.sort_by_key(|p| p.triangles.start);
+        for p in &parts {
+            assert_eq!(p.triangles.start, end);
+            end = p.triangles.end;
+        }
+        assert_eq!(end, mesh.triangles.len());
+
+        // Entities which aren't faces are reported, and change nothing
+        let num_triangles = mesh.triangles.len();
+        let shape = mesh.parts[0].shape;
+        let stats = retriangulate_faces(&step, &mut mesh, &[shape], &opts);
+        assert_eq!(stats.diagnostics.len(), 1);
+        assert_eq!(mesh.triangles.len(), num_triangles);
+    }
+
+    #[test]
+    fn test_crop() {
+        use step::fixture;
+        let asm = fixture::assembly(vec![
+            fixture::cube(1.0),
+            fixture::cylinder(1.0, 2.0).translate([5.0, 0.0, 0.0]),
+        ]);
+        let flat = StepFile::strip_flatten(asm.to_step().as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let crop = |b: &str| {
+            let b: Aabb = b.parse().unwrap();
+            triangulate_streaming(
+                &step,
+                None,
+                Some(&b),
+                MeshTopology::Triangles,
+                &|_, _| (),
+                &|_| (),
+            )
+        };
+
+        // A box around the cylinder drops the whole cube
+        let (mesh, stats) = crop("3,-2,-1, 7,2,3");
+        assert_eq!(stats.num_cropped, 6);
+        assert_eq!(mesh.parts.len(), 1);
+        let (lo, hi) = part_bounds(&mesh, &mesh.parts[0]);
+        assert!((lo.x - 4.0).abs() < 1e-9 && (hi.x - 6.0).abs() < 1e-9);
+        assert_eq!(mesh.open_edges(), 0);
+
+        // A box around one corner of the cube keeps the three faces which
+        // touch that corner, and nothing else
+        let (mesh, stats) = crop("-0.5,-0.5,-0.5, 0.5,0.5,0.5");
+        assert_eq!(stats.num_cropped, 7);
+        assert_eq!(mesh.parts.len(), 1);
+        assert_eq!(mesh.triangles.len(), 6);
+        assert_eq!(mesh.faces.len(), 3);
+        assert_eq!(mesh.parts[0].triangles, 0..6);
+
+        // A box which misses everything leaves nothing at all
+        let (mesh, stats) = crop("10,10,10, 11,11,11");
+        assert_eq!(stats.num_cropped, 10);
+        assert!(mesh.parts.is_empty() && mesh.triangles.is_empty() && mesh.verts.is_empty());
+    }
+
+    #[test]
+    fn test_layers() {
+        // Faces of a 10mm cube are #100, #112, #124, #136, #148, and #160,
+        // and its solid is #162
+        let text = step::fixture::cube(10.0).to_step().replace(
+            "ENDSEC;\nEND-ISO",
+            "#1000=PRESENTATION_LAYER_ASSIGNMENT('body','',(#162));
+#1001=PRESENTATION_LAYER_ASSIGNMENT('sides','',(#100,#112));
+#1002=PRESENTATION_LAYER_ASSIGNMENT('top','',(#160));
+#1003=PRESENTATION_LAYER_ASSIGNMENT('sides','',(#124));
+#1004=PRESENTATION_LAYER_ASSIGNMENT('axes','',(#166));
+ENDSEC;\nEND-ISO",
+        );
+        let (mesh, _stats) = triangulate_text(&text);
+        let names: Vec<_> = mesh.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["body", "sides", "top"]);
+        assert_eq!(mesh.layers[1].faces, [100, 112, 124]);
+        assert_eq!(mesh.face_layers(112), ["body", "sides"]);
+        assert_eq!(mesh.face_layers(160), ["body", "top"]);
+        assert_eq!(mesh.face_layers(148), ["body"]);
+        assert_eq!(mesh.layer_parts(2), [0]);
+        assert!(mesh.layer_parts(3).is_empty());
+
+        // Layers survive being combined with another mesh
+        let both = Mesh::combine(mesh.clone(), mesh);
+        assert_eq!(both.layers.len(), 3);
+        assert_eq!(both.layer_parts(1), [0, 1]);
+    }
+
+    #[test]
+    fn test_reference_cycle() {
+        let text = step::fixture::cube_with_cycle();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        assert_eq!(
+            edge_curve(&step, Id::new(33), true, None, &mut Stats::default()).unwrap_err(),
+            Error::Graph {
+                id: 1000,
+                source: step::walk::WalkError::CycleDetected(1000)
+            }
+        );
+
+        // The two faces on the broken edge fail, but the rest of the cube
+        // is still triangulated
+        let (mesh, stats) = triangulate(&step);
+        assert!(stats
+            .diagnostics
+            .iter()
+            .all(|d| d.message.ends_with("Reference cycle through #1000")));
+        let mut failed: Vec<_> = stats
+            .diagnostics
+            .iter()
+            .filter(|d| d.level == Level::Error)
+            .map(|d| d.entity)
+            .collect();
+        failed.sort();
+        assert_eq!(failed, [Some(100), Some(148)]);
+        assert_eq!(mesh.faces.len(), 4);
+        assert_eq!(mesh.triangles.len(), 8);
+    }
+
+    /// Triangulates a 5mm x 10mm cylinder from [`step::fixture::cylinder`]
+    fn cylinder(topology: MeshTopology) -> (Mesh, Stats) {
+        let text = step::fixture::cylinder(5.0, 10.0).to_step();
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        triangulate_streaming(&step, None, None, topology, &|_, _| (), &|_| ())
+    }
+
+    #[test]
+    fn test_grid() {
+        let (mesh, stats) = cylinder(MeshTopology::Grid);
+        let (plain, _) = cylinder(MeshTopology::Triangles);
+        assert_eq!(mesh.triangles.len(), plain.triangles.len());
+        assert!(plain.grids.is_empty());
+        assert_eq!(mesh.grids.len(), 4);
+
+        for g in &mesh.grids {
+            assert_eq!(g.points.len(), g.rows * g.cols);
+            assert_eq!(g.mask.len(), g.rows * g.cols);
+            let radius = |p: &DVec3| p.xy().norm();
+            match stats.surface_types[&g.id] {
+                // Each half of the side fills its whole grid, which runs
+                // around the half-circle and up the side
+                SurfaceType::Cylinder => {
+                    assert!(g.is_full(), "{:?}", g.mask);
+                    assert!((g.hi.x - g.lo.x - std::f64::consts::PI).abs() < 1e-9);
+                    assert!((g.hi.y - g.lo.y - 10.0).abs() < 1e-9);
+                    assert_eq!(g.cols, 32);
+                    assert!(g.rows > 2);
+                    for p in &g.points {
+                        assert!((radius(p) - 5.0).abs() < 1e-9, "{:?}", p);
+                    }
+                    let z: Vec<_> = [0, g.points.len() - 1].map(|i| g.points[i].z).into();
+                    assert!(z.contains(&0.0) || z.contains(&10.0), "{:?}", z);
+                }
+                // The caps are disks trimmed out of their grid's square
+                SurfaceType::Plane => {
+                    assert!(!g.is_full());
+                    for (p, m) in g.points.iter().zip(&g.mask) {
+                        if radius(p) < 5.0 * (std::f64::consts::PI / 32.0).cos() - 1e-9 {
+                            assert!(m, "{:?} should be on the face", p);
+                        } else if radius(p) > 5.0 + 1e-9 {
+                            assert!(!m, "{:?} should be off the face", p);
+                        }
+                    }
+                    let corners = [0, g.cols - 1, g.points.len() - g.cols, g.points.len() - 1];
+                    assert!(corners.iter().all(|i| !g.mask[*i]));
+                }
+                t => panic!("Unexpected surface type {:?}", t),
+            }
+        }
+    }
+
+    #[test]
+    fn test_quads() {
+        let (mesh, _stats) = cylinder(MeshTopology::QuadDominant);
+        assert!(mesh.grids.is_empty());
+        assert_eq!(mesh.open_edges(), 0);
+        assert!(!mesh.quads.is_empty());
+
+        // Quads are pairs of triangles on the same face, with four distinct
+        // corners which are on the cylinder's side
+        let mut on_side = 0;
+        for &q in &mesh.quads {
+            let face = mesh
+                .faces
+                .iter()
+                .find(|f| f.triangles.contains(&q))
+                .unwrap();
+            assert!(face.triangles.contains(&(q + 1)));
+            let v = mesh.quad(q);
+            assert!((0..4).all(|i| !v[i + 1..].contains(&v[i])), "{:?}", v);
+            let r = v.map(|i| mesh.verts[i as usize].pos.xy().norm());
+            if r.iter().all(|r| (r - 5.0).abs() < 1e-9) {
+                on_side += 1;
+            }
+        }
+        assert!(on_side > 0);
+
+        // The caps have interior quads as well, but those next to their rim
+        // stay as triangles
+        assert!(mesh.quads.len() * 2 < mesh.triangles.len());
+    }
+
+    #[test]
+    fn test_surface_model() {
+        // An open box, with the four edges around its top left open
+        let part = step::fixture::cube(1.0).open();
+        let (mesh, stats) = triangulate_text(&part.to_step());
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(stats.num_faces, 5);
+        assert_eq!((stats.num_shells, stats.num_open_shells), (1, 1));
+        assert!(stats.is_open());
+        assert_eq!(mesh.triangles.len(), 10);
+        assert_eq!(mesh.open_edges(), 4);
+    }
+
+    #[test]
+    fn test_wireframe() {
+        use step::fixture;
+        let part = fixture::cylinder(1.0, 2.0).wireframe();
+        let (mesh, stats) = triangulate_text(&part.to_step());
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(stats.num_faces, 0);
+        assert_eq!(stats.num_wires, 6);
+        assert!(mesh.triangles.is_empty() && mesh.parts.is_empty());
+
+        // Four half circles and two vertical lines
+        assert_eq!(mesh.wires.len(), 6);
+        let lines: Vec<_> = mesh.wires.iter().filter(|w| w.points.len() == 2).collect();
+        assert_eq!(lines.len(), 2);
+        for w in &mesh.wires {
+            assert!(w.points.iter().all(|p| (p.xy().norm() - 1.0).abs() < 1e-9));
+            if w.points.len() > 2 {
+                assert_eq!(w.points.len(), 32);
+                assert!(w.points.iter().all(|p| p.z == w.points[0].z));
+            }
+        }
+
+        // In an assembly, each placement gets its own copy of the wires
+        let asm = fixture::assembly(vec![part.clone(), part.translate([5.0, 0.0, 0.0])]);
+        let (mesh, stats) = triangulate_text(&asm.to_step());
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!(mesh.wires.len(), 12);
+        let moved = mesh.wires.iter().filter(|w| w.points[0].x > 3.0).count();
+        assert_eq!(moved, 6);
+    }
+
+    #[test]
+    fn test_builder() {
+        // A unit square, built from scratch as a single-face solid
+        let mut b = step::builder::StepBuilder::new("square");
+        let corners = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ];
+        let v: Vec<_> = corners.iter().map(|p| b.vertex(*p)).collect();
+        let edges: Vec<_> = (0..4)
+            .map(|i| {
+                let line = b.line(corners[i], corners[(i + 1) % 4]);
+                (b.edge_curve(v[i], v[(i + 1) % 4], line), true)
+            })
+            .collect();
+        let lp = b.edge_loop(&edges);
+        let axis = b.axis2_placement([0.0; 3], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+        let plane = b.plane(axis);
+        let face = b.advanced_face(plane, &[lp]);
+        let shell = b.closed_shell(&[face]);
+        b.manifold_solid(shell);
+
+        let (mesh, stats) = triangulate_text(&b.finish().unwrap());
+        assert!(stats.diagnostics.is_empty(), "{:?}", stats.diagnostics);
+        assert_eq!((stats.num_faces, stats.num_errors), (1, 0));
+        assert_eq!(mesh.parts.len(), 1);
+        assert_eq!(mesh.triangles.len(), 2);
+        assert!(mesh.verts.iter().all(|v| v.norm == DVec3::z()));
+    }
 }
@@ -0,0 +1,229 @@
+//! Length units declared by STEP files.
+//!
+//! Meshes are built in the file's own units, which are declared by an
+//! `SI_UNIT` (e.g. millimetres) or a `CONVERSION_BASED_UNIT` (e.g. inches)
+//! that's combined with a `LENGTH_UNIT` in a complex entity.
+use step::{
+    ap214::{Entity, SiPrefix, SiUnitName},
+    step_file::StepFile,
+    units::{Dimension, Quantity},
+};
+
+/// A unit of length, which coordinates in the mesh are measured in
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "cache", derive(serde::Serialize, serde::Deserialize))]
+pub enum LengthUnit {
+    Micrometre,
+    Millimetre,
+    Centimetre,
+    Decimetre,
+    Metre,
+    Kilometre,
+    Inch,
+    Foot,
+    Yard,
+    Mile,
+}
+
+impl LengthUnit {
+    /// Returns the unit's usual abbreviation, e.g. "mm"
+    pub fn symbol(&self) -> &'static str {
+        use LengthUnit::*;
+        match self {
+            Micrometre => "µm",
+            Millimetre => "mm",
+            Centimetre => "cm",
+            Decimetre => "dm",
+            Metre => "m",
+            Kilometre => "km",
+            Inch => "in",
+            Foot => "ft",
+            Yard => "yd",
+            Mile => "mi",
+        }
+    }
+
+    /// Returns the length of one unit in metres
+    pub fn metres(&self) -> f64 {
+        use LengthUnit::*;
+        match self {
+            Micrometre => 1e-6,
+            Millimetre => 1e-3,
+            Centimetre => 1e-2,
+            Decimetre => 1e-1,
+            Metre => 1.0,
+            Kilometre => 1e3,
+            Inch => 0.0254,
+            Foot => 0.3048,
+            Yard => 0.9144,
+            Mile => 1609.344,
+        }
+    }
+
+    /// Converts a length, area, or volume measured in this unit (e.g. from
+    /// [`crate::mesh::Mesh::part_properties`]) into SI units for display
+    pub fn quantity(&self, value: f64, dimension: Dimension) -> Quantity {
+        Quantity::from_model(value, dimension, self.metres())
+    }
+
+    /// Converts a quantity back into this unit
+    pub fn value(&self, q: &Quantity) -> f64 {
+        q.to_model(self.metres())
+    }
+
+    /// Looks up a conversion-based unit by its name, e.g. `INCH`
+    fn from_name(name: &str) -> Option<Self> {
+        use LengthUnit::*;
+        match name.to_ascii_uppercase().as_str() {
+            "INCH" | "INCHES" => Some(Inch),
+            "FOOT" | "FEET" => Some(Foot),
+            "YARD" | "YARDS" => Some(Yard),
+            "MILE" | "MILES" => Some(Mile),
+            _ => None,
+        }
+    }
+
+    /// Converts a single entity, if it's a length unit
+    fn from_entity(e: &Entity) -> Option<Self> {
+        use LengthUnit::*;
+        match e {
+            Entity::SiUnit(u) => match (&u.name, &u.prefix) {
+                (SiUnitName::Metre, None) => Some(Metre),
+                (SiUnitName::Metre, Some(p)) => match p {
+                    SiPrefix::Micro => Some(Micrometre),
+                    SiPrefix::Milli => Some(Millimetre),
+                    SiPrefix::Centi => Some(Centimetre),
+                    SiPrefix::Deci => Some(Decimetre),
+                    SiPrefix::Kilo => Some(Kilometre),
+                    _ => None,
+                },
+                _ => None,
+            },
+            Entity::ConversionBasedUnit(u) => Self::from_name(u.name.0),
+            Entity::ComplexEntity(v) => v.iter().find_map(Self::from_entity),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the length unit of a file's geometry, or `None` if it doesn't
+/// declare one that we recognize.
+///
+/// Units are looked up through each geometric representation context,
+/// since files may also declare units for other purposes (e.g. mass
+/// properties in metres for a model that's drawn in inches).  Files
+/// without such a context fall back to their first length unit.
+pub fn length_unit(step: &StepFile) -> Option<LengthUnit> {
+    let lookup = |id: usize| step.0.get(id).and_then(LengthUnit::from_entity);
+    step.0
+        .iter()
+        .filter_map(|e| match e {
+            Entity::ComplexEntity(v)
+                if v.iter()
+                    .any(|e| matches!(e, Entity::GeometricRepresentationContext(_))) =>
+            {
+                v.iter().find_map(|e| match e {
+                    Entity::GlobalUnitAssignedContext(c) => Some(c),
+                    _ => None,
+                })
+            }
+            _ => None,
+        })
+        .find_map(|c| c.units.iter().find_map(|u| lookup(u.0)))
+        .or_else(|| step.0.iter().find_map(LengthUnit::from_entity))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(data: &str) -> Option<LengthUnit> {
+        let flat = StepFile::strip_flatten(data.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        length_unit(&step)
+    }
+
+    #[test]
+    fn test_length_unit() {
+        assert_eq!(
+            unit(&step::fixture::cube(1.0).to_step()),
+            Some(LengthUnit::Millimetre)
+        );
+        assert_eq!(
+            unit(
+                "DATA;
+            #1=(NAMED_UNIT(*) PLANE_ANGLE_UNIT() SI_UNIT($,.RADIAN.));
+            #2=(LENGTH_UNIT() NAMED_UNIT(*) SI_UNIT($,.METRE.));
+            ENDSEC;"
+            ),
+            Some(LengthUnit::Metre)
+        );
+        assert_eq!(
+            unit(
+                "DATA;
+            #1=(LENGTH_UNIT() NAMED_UNIT(*) SI_UNIT(.MILLI.,.METRE.));
+            #2=LENGTH_MEASURE_WITH_UNIT(LENGTH_MEASURE(25.4),#1);
+            #3=DIMENSIONAL_EXPONENTS(1.,0.,0.,0.,0.,0.,0.);
+            #4=(CONVERSION_BASED_UNIT('INCH',#2) LENGTH_UNIT() NAMED_UNIT(#3));
+            ENDSEC;"
+            )
+            .map(|u| u.metres()),
+            Some(0.001)
+        );
+        assert_eq!(
+            unit(
+                "DATA;
+            #3=DIMENSIONAL_EXPONENTS(1.,0.,0.,0.,0.,0.,0.);
+            #4=(CONVERSION_BASED_UNIT('INCH',#2) LENGTH_UNIT() NAMED_UNIT(#3));
+            ENDSEC;"
+            ),
+            Some(LengthUnit::Inch)
+        );
+        assert_eq!(
+            unit(
+                "DATA;
+            #1=(NAMED_UNIT(*) PLANE_ANGLE_UNIT() SI_UNIT($,.RADIAN.));
+            ENDSEC;"
+            ),
+            None
+        );
+
+        // The geometric context's unit wins over other length units
+        assert_eq!(
+            unit(
+                "DATA;
+            #1=(LENGTH_UNIT() NAMED_UNIT(*) SI_UNIT($,.METRE.));
+            #2=(LENGTH_UNIT() NAMED_UNIT(*) SI_UNIT(.MILLI.,.METRE.));
+            #3=DIMENSIONAL_EXPONENTS(1.,0.,0.,0.,0.,0.,0.);
+            #4=LENGTH_MEASURE_WITH_UNIT(LENGTH_MEASURE(25.4),#2);
+            #5=(CONVERSION_BASED_UNIT('INCH',#4) LENGTH_UNIT() NAMED_UNIT(#3));
+            #6=(GEOMETRIC_REPRESENTATION_CONTEXT(3) \
+               GLOBAL_UNIT_ASSIGNED_CONTEXT((#5)) \
+               REPRESENTATION_CONTEXT('',''));
+            ENDSEC;"
+            ),
+            Some(LengthUnit::Inch)
+        );
+    }
+
+    #[test]
+    fn test_quantity() {
+        use LengthUnit::*;
+        let q = Inch.quantity(2.0, Dimension::Length);
+        assert_eq!(q.to_string(), "50.8 mm");
+        assert_eq!(Inch.value(&q), 2.0);
+
+        // A 1 ft cube is 144 in² per side and 1728 in³ in all
+        let area = Foot.quantity(6.0, Dimension::Area);
+        let volume = Foot.quantity(1.0, Dimension::Volume);
+        assert!((Inch.value(&area) - 6.0 * 144.0).abs() < 1e-9);
+        assert!((Inch.value(&volume) - 1728.0).abs() < 1e-9);
+        assert_eq!(area.to_string(), "557418 mm²");
+        assert_eq!(volume.to_string(), "28316847 mm³");
+
+        assert_eq!(Metre.quantity(1.0, Dimension::Volume).to_string(), "1 m³");
+        assert_eq!(Millimetre.quantity(1e6, Dimension::Area).to_string(), "1 m²");
+    }
+}
@@ -0,0 +1,201 @@
+//! Snapshot tests, which triangulate each generated fixture and compare a
+//! handful of metrics against the JSON files in `tests/snapshots`.
+//!
+//! Counts must match exactly, and measurements must match to within a small
+//! relative tolerance.  After an intended change to triangulation, run
+//! `UPDATE_SNAPSHOTS=1 cargo test -p triangulate --test snapshots` to
+//! rewrite the snapshots, then review the diff.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use nalgebra_glm::DVec3;
+use serde_json::{json, Value};
+use step::{fixture, step_file::StepFile};
+use triangulate::{mesh::Mesh, stats::Stats, triangulate::triangulate_with_tolerance};
+
+/// Relative tolerance for measurements (area, volume, and bounds)
+const TOLERANCE: f64 = 1e-6;
+
+/// Returns every fixture in the corpus, by name
+fn corpus() -> Vec<(&'static str, String)> {
+    vec![
+        ("cube", fixture::cube(10.0).to_step()),
+        ("cylinder", fixture::cylinder(2.0, 5.0).to_step()),
+        (
+            "filleted_block",
+            fixture::filleted_block(10.0, 2.0).to_step(),
+        ),
+        ("slanted_cube", fixture::cube(10.0).slant(0.5).to_step()),
+        (
+            "slanted_cylinder",
+            fixture::cylinder(2.0, 5.0).slant(0.5).to_step(),
+        ),
+        (
+            "open_cylinder",
+            fixture::cylinder(2.0, 5.0).open().to_step(),
+        ),
+        (
+            "wireframe_block",
+            fixture::filleted_block(10.0, 2.0).wireframe().to_step(),
+        ),
+        (
+            "assembly",
+            fixture::assembly(vec![
+                fixture::filleted_block(10.0, 2.0),
+                fixture::cylinder(2.0, 5.0).translate([20.0, 0.0, 0.0]),
+                fixture::cube(3.0)
+                    .color([1.0, 0.0, 0.0])
+                    .translate([0.0, 20.0, 0.0]),
+            ])
+            .to_step(),
+        ),
+    ]
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{}.json", name))
+}
+
+/// Measures a triangulated fixture
+fn metrics(mesh: &Mesh, stats: &Stats) -> Value {
+    let (mut area, mut volume) = (0.0, 0.0);
+    for t in &mesh.triangles {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.verts[t.verts[i] as usize].pos);
+        area += (b - a).cross(&(c - a)).norm() / 2.0;
+        volume += a.dot(&b.cross(&c)) / 6.0;
+    }
+    let (mut lo, mut hi) = (DVec3::repeat(f64::INFINITY), DVec3::repeat(-f64::INFINITY));
+    let points = mesh.verts.iter().map(|v| v.pos);
+    for p in points.chain(mesh.wires.iter().flat_map(|w| w.points.iter().copied())) {
+        lo = lo.inf(&p);
+        hi = hi.sup(&p);
+    }
+    let bounds = if lo.x <= hi.x {
+        json!({ "min": [lo.x, lo.y, lo.z], "max": [hi.x, hi.y, hi.z] })
+    } else {
+        Value::Null
+    };
+
+    // Surface types are counted by name, so that a face which falls back
+    // to a plane shows up as a changed count
+    let mut surfaces: BTreeMap<String, usize> = BTreeMap::new();
+    for t in stats.surface_types.values() {
+        *surfaces.entry(format!("{:?}", t)).or_default() += 1;
+    }
+
+    json!({
+        "triangles": mesh.triangles.len(),
+        "parts": mesh.parts.len(),
+        "wires": mesh.wires.len(),
+        "boundary_edges": mesh.boundary_edges().len(),
+        "area": area,
+        "signed_volume": volume,
+        "bounds": bounds,
+        "faces": stats.num_faces,
+        "face_errors": stats.num_errors + stats.num_panics,
+        "fallbacks": stats.num_fallbacks,
+        "surface_types": surfaces,
+    })
+}
+
+/// Compares two snapshots, pushing a description of each difference.
+/// Integers must match exactly, while floats may differ by `tol` times their
+/// magnitude (or by `tol`, for values smaller than one).
+fn compare(path: &str, expected: &Value, actual: &Value, tol: f64, out: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            for k in e.keys().chain(a.keys().filter(|k| !e.contains_key(*k))) {
+                let (e, a) = (&e.get(k), &a.get(k));
+                let path = format!("{}.{}", path, k);
+                match (e, a) {
+                    (Some(e), Some(a)) => compare(&path, e, a, tol, out),
+                    _ => {
+                        let show =
+                            |v: &Option<&Value>| v.map_or("nothing".to_owned(), Value::to_string);
+                        out.push(format!("{}: expected {}, got {}", path, show(e), show(a)))
+                    }
+                }
+            }
+        }
+        (Value::Array(e), Value::Array(a)) if e.len() == a.len() => {
+            for (i, (e, a)) in e.iter().zip(a).enumerate() {
+                compare(&format!("{}[{}]", path, i), e, a, tol, out);
+            }
+        }
+        (Value::Number(e), Value::Number(a)) if e.is_f64() || a.is_f64() => {
+            let (e, a) = (e.as_f64().unwrap(), a.as_f64().unwrap());
+            if (e - a).abs() > tol * e.abs().max(1.0) {
+                out.push(format!("{}: expected {}, got {}", path, e, a));
+            }
+        }
+        _ if expected == actual => (),
+        _ => out.push(format!("{}: expected {}, got {}", path, expected, actual)),
+    }
+}
+
+#[test]
+fn test_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some_and(|v| v == "1");
+    let mut failures = vec![];
+    for (name, text) in corpus() {
+        let flat = StepFile::strip_flatten(text.as_bytes());
+        let step = StepFile::parse(&flat).unwrap();
+        let (mesh, stats) = triangulate_with_tolerance(&step, None, &|_, _| ());
+        let actual = metrics(&mesh, &stats);
+
+        let path = snapshot_path(name);
+        if update {
+            let text = serde_json::to_string_pretty(&actual).unwrap();
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, text + "\n").unwrap();
+            continue;
+        }
+        let expected: Value = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap(),
+            Err(e) => {
+                failures.push(format!(
+                    "{}: could not read {}: {}",
+                    name,
+                    path.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+        compare(name, &expected, &actual, TOLERANCE, &mut failures);
+    }
+    assert!(
+        failures.is_empty(),
+        "Triangulation snapshots changed (rerun with UPDATE_SNAPSHOTS=1 if \
+         this is intended):\n  {}",
+        failures.join("\n  ")
+    );
+}
+
+#[test]
+fn test_compare() {
+    let expected = json!({ "triangles": 12, "area": 600.0, "bounds": { "min": [0.0, 0.0] } });
+    let mut out = vec![];
+    compare("a", &expected, &expected, 1e-6, &mut out);
+    assert!(out.is_empty());
+
+    // Floats may drift within the tolerance, but counts can't
+    let close = json!({ "triangles": 12, "area": 600.0000001, "bounds": { "min": [0.0, 0.0] } });
+    compare("a", &expected, &close, 1e-6, &mut out);
+    assert!(out.is_empty(), "{:?}", out);
+
+    let far = json!({ "triangles": 14, "area": 601.0, "bounds": { "min": [0.0] }, "new": 1 });
+    compare("a", &expected, &far, 1e-6, &mut out);
+    assert_eq!(
+        out,
+        vec![
+            "a.area: expected 600, got 601",
+            "a.bounds.min: expected [0.0,0.0], got [0.0]",
+            "a.triangles: expected 12, got 14",
+            "a.new: expected nothing, got 1",
+        ]
+    );
+}
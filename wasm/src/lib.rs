@@ -15,13 +15,92 @@ pub fn init_log() {
     console_log::init_with_level(Level::Info).expect("Failed to initialize log");
 }
 
+/// Flat vertex and index buffers for a triangulated STEP file, ready to be
+/// uploaded to WebGL or WebGPU
+#[wasm_bindgen]
+pub struct MeshBuffers {
+    positions: Vec<f32>,
+    normals: Vec<f32>,
+    colors: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+// Getters return a fresh typed array each time, rather than a view into
+// WebAssembly memory, because views are invalidated whenever that memory
+// grows.
+#[wasm_bindgen]
+impl MeshBuffers {
+    /// Vertex positions, as packed XYZ triples
+    #[wasm_bindgen(getter)]
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions.clone()
+    }
+
+    /// Vertex normals, as packed XYZ triples
+    #[wasm_bindgen(getter)]
+    pub fn normals(&self) -> Vec<f32> {
+        self.normals.clone()
+    }
+
+    /// Vertex colors, as packed RGB triples in the range 0-1
+    #[wasm_bindgen(getter)]
+    pub fn colors(&self) -> Vec<f32> {
+        self.colors.clone()
+    }
+
+    /// Triangles, as packed triples of vertex indices
+    #[wasm_bindgen(getter)]
+    pub fn indices(&self) -> Vec<u32> {
+        self.indices.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = vertexCount)]
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    #[wasm_bindgen(getter, js_name = triangleCount)]
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+/// Parses and triangulates a STEP file, returning flat mesh buffers in the
+/// file's own units.
+///
+/// `tolerance` is the largest allowed distance between curved edges and
+/// the mesh; pass 0 to use the default sampling.
+#[wasm_bindgen]
+pub fn triangulate_step(bytes: &[u8], tolerance: f64) -> Result<MeshBuffers, JsError> {
+    use triangulate::load::{load_mesh_from_bytes, TriangulateOptions};
+
+    let opts = TriangulateOptions {
+        tolerance: (tolerance > 0.0).then_some(tolerance),
+        ..Default::default()
+    };
+    let mesh = load_mesh_from_bytes(bytes, &opts)?.0.mesh;
+    let flat = |f: fn(&triangulate::mesh::Vertex) -> [f64; 3]| -> Vec<f32> {
+        mesh.verts.iter().flat_map(f).map(|f| f as f32).collect()
+    };
+    Ok(MeshBuffers {
+        positions: flat(|v| v.pos.into()),
+        normals: flat(|v| v.norm.into()),
+        colors: flat(|v| v.color.into()),
+        indices: mesh
+            .triangles
+            .iter()
+            .flat_map(|t| t.verts.iter().copied())
+            .collect(),
+    })
+}
+
 #[wasm_bindgen]
 pub fn step_to_triangle_buf(data: String) -> Vec<f32> {
     use step::step_file::StepFile;
     use triangulate::triangulate::triangulate; // lol
 
     let flat = StepFile::strip_flatten(data.as_bytes());
-    let step = StepFile::parse(&flat);
+    let step = StepFile::parse_unchecked(&flat);
     let (mut mesh, _stats) = triangulate(&step);
 
     let (mut xmin, mut xmax) = (f64::INFINITY, -f64::INFINITY);
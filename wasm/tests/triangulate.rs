@@ -0,0 +1,29 @@
+#![cfg(target_arch = "wasm32")]
+
+use step::fixture;
+use wasm::triangulate_step;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn test_triangulate_step() {
+    let text = fixture::filleted_block(10.0, 2.0).to_step();
+    let mesh = triangulate_step(text.as_bytes(), 0.0).unwrap();
+    assert!(mesh.triangle_count() > 0);
+    assert_eq!(mesh.positions().len(), mesh.vertex_count() * 3);
+    assert_eq!(mesh.normals().len(), mesh.positions().len());
+    assert_eq!(mesh.colors().len(), mesh.positions().len());
+    assert!(mesh
+        .indices()
+        .iter()
+        .all(|i| (*i as usize) < mesh.vertex_count()));
+
+    // A finer tolerance produces a denser mesh
+    let coarse = triangulate_step(text.as_bytes(), 1e-2).unwrap();
+    let fine = triangulate_step(text.as_bytes(), 1e-4).unwrap();
+    assert!(fine.triangle_count() > coarse.triangle_count());
+}
+
+#[wasm_bindgen_test]
+fn test_triangulate_invalid() {
+    assert!(triangulate_step(b"not a STEP file;", 0.0).is_err());
+}